@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use alloy::{
     eips::eip2718::Encodable2718,
     hex,
@@ -10,7 +12,7 @@ use beacon_api_client::mainnet::Client as BeaconApiClient;
 use clap::Parser;
 use eyre::{bail, Result};
 use serde_json::{json, Value};
-use tracing::info;
+use tracing::{info, warn};
 use url::Url;
 
 mod registry;
@@ -45,6 +47,17 @@ struct Opts {
     /// Flag for sending all "count" transactions in a single bundle
     #[clap(long, default_value_t = false)]
     bundle: bool,
+    /// Flag for automatically retargeting a request to the earliest slot offered
+    /// by the sidecar, if the original target slot is not served by it
+    #[clap(long, default_value_t = false)]
+    retarget: bool,
+    /// Maximum number of times to resubmit a request rejected with a retryable error
+    #[clap(long, default_value_t = 3)]
+    max_retries: u32,
+    /// Delay before resubmitting a request rejected with a retryable error, in milliseconds.
+    /// The delay grows linearly with the number of attempts.
+    #[clap(long, default_value_t = 500)]
+    retry_backoff_ms: u64,
 
     /// Flag for using the registry to fetch the lookahead
     #[clap(long, default_value_t = false, requires_ifs([("true", "registry_address"), ("true", "beacon_client_url")]))]
@@ -72,6 +85,12 @@ async fn main() -> Result<()> {
     let provider = ProviderBuilder::new().on_http(opts.rpc_url.clone());
     let sender = wallet.address();
 
+    let retry = RetryConfig {
+        retarget: opts.retarget,
+        max_retries: opts.max_retries,
+        backoff: Duration::from_millis(opts.retry_backoff_ms),
+    };
+
     let (target_sidecar_url, target_slot) = if opts.use_registry {
         // Fetch the next preconfer slot from the registry and use it
         let beacon_api_client = BeaconApiClient::new(opts.beacon_client_url.unwrap());
@@ -120,48 +139,97 @@ async fn main() -> Result<()> {
                 target_slot,
                 target_sidecar_url.clone(),
                 &wallet,
+                &retry,
             )
             .await?;
         }
     }
 
     if opts.bundle {
-        send_rpc_request(txs_rlp, tx_hashes, target_slot, target_sidecar_url, &wallet).await?;
+        send_rpc_request(
+            txs_rlp,
+            tx_hashes,
+            target_slot,
+            target_sidecar_url,
+            &wallet,
+            &retry,
+        )
+        .await?;
     }
 
     Ok(())
 }
 
+/// How to react to rejected requests.
+struct RetryConfig {
+    /// Whether to retarget a request to the earliest slot offered by the sidecar, at most once.
+    retarget: bool,
+    /// Maximum number of times to resubmit a request rejected with a retryable error.
+    max_retries: u32,
+    /// Base delay before resubmitting a request.
+    backoff: Duration,
+}
+
 async fn send_rpc_request(
     txs_rlp: Vec<String>,
     tx_hashes: Vec<B256>,
     target_slot: u64,
     target_sidecar_url: Url,
     wallet: &PrivateKeySigner,
+    retry: &RetryConfig,
 ) -> Result<()> {
-    let request = prepare_rpc_request(
-        "bolt_requestInclusion",
-        json!({
-            "slot": target_slot,
-            "txs": txs_rlp,
-        }),
-    );
-
-    info!(?tx_hashes, target_slot, %target_sidecar_url);
-    let signature = sign_request(tx_hashes, target_slot, wallet).await?;
-
-    let response = reqwest::Client::new()
-        .post(target_sidecar_url)
-        .header("content-type", "application/json")
-        .header("x-bolt-signature", signature)
-        .body(serde_json::to_string(&request)?)
-        .send()
-        .await?;
+    let mut target_slot = target_slot;
+    let mut retargeted = false;
+    let mut attempts = 0;
+
+    loop {
+        let request = prepare_rpc_request(
+            "bolt_requestInclusion",
+            json!({
+                "slot": target_slot,
+                "txs": txs_rlp,
+            }),
+        );
+
+        info!(?tx_hashes, target_slot, %target_sidecar_url);
+        let signature = sign_request(tx_hashes.clone(), target_slot, wallet).await?;
+
+        let response = reqwest::Client::new()
+            .post(target_sidecar_url.clone())
+            .header("content-type", "application/json")
+            .header("x-bolt-signature", signature)
+            .body(serde_json::to_string(&request)?)
+            .send()
+            .await?;
 
-    let response = response.text().await?;
+        let response = response.text().await?;
+
+        let json = serde_json::from_str::<Value>(&response).ok();
+
+        if let Some(error) = json.as_ref().and_then(RpcError::from_response) {
+            // Resubmit the identical request if the sidecar says it may succeed later
+            if error.is_retryable() && attempts < retry.max_retries {
+                attempts += 1;
+                warn!(code = error.code, err = %error.message, attempts, "Retrying request");
+                tokio::time::sleep(retry.backoff * attempts).await;
+                continue;
+            }
+
+            // Retarget at most once to the earliest slot offered by the sidecar
+            if retry.retarget && !retargeted {
+                let hint = json.as_ref().and_then(NotOurSlotHint::from_response);
+                if let Some(next_slot) = hint.as_ref().and_then(NotOurSlotHint::earliest_slot) {
+                    info!(target_slot, next_slot, "Target slot not served by sidecar, retargeting");
+                    target_slot = next_slot;
+                    retargeted = true;
+                    continue;
+                }
+            }
+        }
 
-    // strip out long series of zeros in the response (to avoid spamming blob contents)
-    let response = response.replace(&"0".repeat(32), ".").replace(&".".repeat(4), "");
-    info!("Response: {:?}", response);
-    Ok(())
+        // strip out long series of zeros in the response (to avoid spamming blob contents)
+        let response = response.replace(&"0".repeat(32), ".").replace(&".".repeat(4), "");
+        info!("Response: {:?}", response);
+        return Ok(());
+    }
 }
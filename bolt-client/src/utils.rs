@@ -10,7 +10,9 @@ use alloy::{
 };
 use beacon_api_client::{mainnet::Client as BeaconApiClient, BlockId, ProposerDuty};
 use rand::{thread_rng, Rng};
+use serde::Deserialize;
 use serde_json::Value;
+use url::Url;
 
 pub const NOICE_GAS_PRICE: u128 = 69_420_000u128;
 pub const DEAD_ADDRESS: &str = "0xdeaDDeADDEaDdeaDdEAddEADDEAdDeadDEADDEaD";
@@ -48,6 +50,141 @@ pub fn prepare_rpc_request(method: &str, params: Value) -> Value {
     })
 }
 
+/// Chain and timing parameters returned by the `bolt_getChainInfo` method of a sidecar.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[allow(unused)]
+pub struct ChainInfo {
+    pub chain_id: u64,
+    pub chain_name: String,
+    pub genesis_time: u64,
+    pub seconds_per_slot: u64,
+    pub slots_per_epoch: u64,
+    pub fork_version: String,
+    pub head_slot: u64,
+}
+
+impl ChainInfo {
+    /// Returns the slot that contains the given UNIX timestamp (in seconds),
+    /// or `None` if the timestamp is before genesis.
+    pub fn slot_for_timestamp(&self, timestamp: u64) -> Option<u64> {
+        timestamp.checked_sub(self.genesis_time)?.checked_div(self.seconds_per_slot)
+    }
+}
+
+/// Fetches the chain and timing parameters from the sidecar at the given URL.
+#[allow(unused)]
+pub async fn get_chain_info(sidecar_url: Url) -> eyre::Result<ChainInfo> {
+    let request = serde_json::json!({
+        "id": "1",
+        "jsonrpc": "2.0",
+        "method": "bolt_getChainInfo",
+        "params": [],
+    });
+
+    let mut response = reqwest::Client::new()
+        .post(sidecar_url)
+        .json(&request)
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+
+    if let Some(error) = response.get("error") {
+        eyre::bail!("bolt_getChainInfo failed: {error}");
+    }
+
+    Ok(serde_json::from_value(response["result"].take())?)
+}
+
+/// JSON-RPC error code returned by the sidecar when the target slot
+/// is not assigned to any of its validators.
+pub const NOT_OUR_SLOT_ERROR_CODE: i64 = -32008;
+
+/// Routing hint attached to a `NotOurSlot` rejection from a sidecar.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct NotOurSlotHint {
+    /// The slot targeted by the rejected request.
+    pub slot: u64,
+    /// The next upcoming slots that the sidecar can serve.
+    pub our_next_slots: Vec<u64>,
+    /// Optional registry URL to discover the sidecar responsible for `slot`.
+    pub registry_hint_url: Option<String>,
+}
+
+impl NotOurSlotHint {
+    /// Extracts the hint from a JSON-RPC response, if it is a `NotOurSlot` rejection.
+    pub fn from_response(response: &Value) -> Option<Self> {
+        let error = response.get("error")?;
+        if error.get("code")?.as_i64()? != NOT_OUR_SLOT_ERROR_CODE {
+            return None;
+        }
+
+        serde_json::from_value(error.get("data")?.clone()).ok()
+    }
+
+    /// Returns the earliest slot offered by the sidecar, if any.
+    pub fn earliest_slot(&self) -> Option<u64> {
+        self.our_next_slots.iter().min().copied()
+    }
+}
+
+/// Hint attached by the sidecar to an error response on how to retry the request.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RetryHint {
+    /// The same request may succeed later.
+    SameRequestLater,
+    /// The request may succeed with the given field modified.
+    ModifyAndResubmit { field: String },
+}
+
+/// A JSON-RPC error returned by the sidecar, with its retry classification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcError {
+    /// The JSON-RPC error code.
+    pub code: i64,
+    /// The error message.
+    pub message: String,
+    /// Whether resubmitting the identical request may succeed.
+    pub retryable: bool,
+    /// Optional hint on how to retry the request.
+    pub retry_hint: Option<RetryHint>,
+}
+
+impl RpcError {
+    /// Extracts the error from a JSON-RPC response, if it is an error response.
+    /// Errors without a retry classification are treated as not retryable.
+    pub fn from_response(response: &Value) -> Option<Self> {
+        let error = response.get("error")?;
+        let data = error.get("data");
+
+        Some(Self {
+            code: error.get("code")?.as_i64()?,
+            message: error.get("message").and_then(Value::as_str).unwrap_or_default().to_string(),
+            retryable: data
+                .and_then(|data| data.get("retryable"))
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+            retry_hint: data
+                .and_then(|data| data.get("retry_hint"))
+                .and_then(|hint| serde_json::from_value(hint.clone()).ok()),
+        })
+    }
+
+    /// Returns true if resubmitting the identical request may succeed.
+    pub fn is_retryable(&self) -> bool {
+        self.retryable
+    }
+
+    /// Returns the request field to modify before resubmitting, if hinted by the sidecar.
+    pub fn field_to_modify(&self) -> Option<&str> {
+        match self.retry_hint {
+            Some(RetryHint::ModifyAndResubmit { ref field }) => Some(field),
+            _ => None,
+        }
+    }
+}
+
 /// Returns the current slot from the beacon client
 pub async fn get_current_slot(beacon_api_client: &BeaconApiClient) -> eyre::Result<u64> {
     Ok(beacon_api_client.get_beacon_header(BlockId::Head).await?.header.message.slot)
@@ -94,7 +231,9 @@ mod tests {
         signers::local::PrivateKeySigner,
     };
 
-    use crate::sign_request;
+    use serde_json::json;
+
+    use crate::{sign_request, ChainInfo, NotOurSlotHint, RetryHint, RpcError};
 
     #[tokio::test]
     async fn test_sign_request() -> eyre::Result<()> {
@@ -145,4 +284,95 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_slot_for_timestamp() {
+        let info = ChainInfo {
+            chain_id: 17000,
+            chain_name: "holesky".to_string(),
+            genesis_time: 1_695_902_400,
+            seconds_per_slot: 12,
+            slots_per_epoch: 32,
+            fork_version: "0x01017000".to_string(),
+            head_slot: 0,
+        };
+
+        assert_eq!(info.slot_for_timestamp(info.genesis_time - 1), None);
+        assert_eq!(info.slot_for_timestamp(info.genesis_time), Some(0));
+        assert_eq!(info.slot_for_timestamp(info.genesis_time + 11), Some(0));
+        assert_eq!(info.slot_for_timestamp(info.genesis_time + 12), Some(1));
+        assert_eq!(info.slot_for_timestamp(info.genesis_time + 12 * 254464 + 5), Some(254464));
+    }
+
+    #[test]
+    fn test_not_our_slot_hint() {
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": "1",
+            "error": {
+                "code": -32008,
+                "message": "Consensus validation error: Slot 10 is not assigned to this sidecar's validators",
+                "data": {
+                    "slot": 10,
+                    "our_next_slots": [15, 12],
+                    "registry_hint_url": "https://registry.example.com/"
+                }
+            }
+        });
+
+        let hint = NotOurSlotHint::from_response(&response).unwrap();
+        assert_eq!(hint.slot, 10);
+        assert_eq!(hint.registry_hint_url.as_deref(), Some("https://registry.example.com/"));
+        assert_eq!(hint.earliest_slot(), Some(12));
+
+        // Other errors don't carry a retargeting hint
+        let response = json!({ "error": { "code": -32006, "message": "Validation failed" } });
+        assert!(NotOurSlotHint::from_response(&response).is_none());
+
+        // A sidecar without upcoming slots can't be retargeted to
+        let response = json!({
+            "error": { "code": -32008, "message": "", "data": { "slot": 10, "our_next_slots": [] } }
+        });
+        assert_eq!(NotOurSlotHint::from_response(&response).unwrap().earliest_slot(), None);
+    }
+
+    #[test]
+    fn test_rpc_error_retry_info() {
+        let response = json!({
+            "error": {
+                "code": -32009,
+                "message": "Sidecar is not ready yet, try again later",
+                "data": { "retryable": true, "retry_hint": { "action": "same_request_later" } }
+            }
+        });
+
+        let error = RpcError::from_response(&response).unwrap();
+        assert!(error.is_retryable());
+        assert_eq!(error.retry_hint, Some(RetryHint::SameRequestLater));
+        assert_eq!(error.field_to_modify(), None);
+
+        let response = json!({
+            "error": {
+                "code": -32008,
+                "message": "Slot 10 is not assigned to this sidecar's validators",
+                "data": {
+                    "slot": 10,
+                    "our_next_slots": [12],
+                    "retryable": false,
+                    "retry_hint": { "action": "modify_and_resubmit", "field": "slot" }
+                }
+            }
+        });
+
+        let error = RpcError::from_response(&response).unwrap();
+        assert!(!error.is_retryable());
+        assert_eq!(error.field_to_modify(), Some("slot"));
+
+        // Errors from older sidecars without classification are not retried
+        let response = json!({ "error": { "code": -32002, "message": "Internal server error" } });
+        assert!(!RpcError::from_response(&response).unwrap().is_retryable());
+
+        // Successful responses are not errors
+        assert!(RpcError::from_response(&json!({ "result": {} })).is_none());
+    }
 }
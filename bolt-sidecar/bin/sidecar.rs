@@ -1,22 +1,158 @@
-use bolt_sidecar::{Config, SidecarDriver};
+use std::time::Duration;
+
+#[cfg(feature = "persistence")]
+use bolt_sidecar::db::Store;
+use bolt_sidecar::{
+    commitments::{link::LinkClient, server::CommitmentsApiServer},
+    earnings::EarningsReport,
+    logging,
+    primitives::{delegation::sign_delegations, ChainHead},
+    replay::Replayer,
+    signer_backend,
+    simulate::{Simulation, SimulationOutcome},
+    ApiOpts, Command, Config, DbCommand, DbOpts, DelegateOpts, EarningsOpts, MigrateOpts,
+    ReplayOpts, SidecarDriver, SimulateOpts,
+};
 use eyre::{bail, Result};
-use tracing::info;
+use tokio::sync::mpsc;
+use tracing::{info, level_filters::LevelFilter};
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // TODO: improve telemetry setup (#116)
-    tracing_subscriber::fmt::init();
+    logging::init(LevelFilter::INFO);
+
+    if let Some(command) = Command::parse_from_cli()? {
+        return match command {
+            Command::Earnings(opts) => print_earnings(opts),
+            Command::Replay(opts) => replay(opts).await,
+            Command::Db(DbOpts { command: DbCommand::Migrate(opts) }) => migrate_store(opts),
+            Command::Simulate(opts) => simulate(opts).await,
+            Command::Api(opts) => run_api(opts).await,
+            Command::Driver(opts) => match Config::try_from(*opts) {
+                Ok(config) => run_sidecar(config).await,
+                Err(err) => bail!("Failed to parse CLI arguments: {:?}", err),
+            },
+            Command::Delegate(opts) => delegate(opts).await,
+        };
+    }
 
     let config = match Config::parse_from_cli() {
         Ok(config) => config,
         Err(err) => bail!("Failed to parse CLI arguments: {:?}", err),
     };
 
+    run_sidecar(config).await
+}
+
+/// Run the sidecar with the given configuration, until it's interrupted.
+async fn run_sidecar(config: Config) -> Result<()> {
+    logging::set_level(config.log_level);
     info!(chain = config.chain.name(), "Starting Bolt sidecar");
     match SidecarDriver::new(config).await {
-        Ok(driver) => driver.run_forever().await,
+        Ok(driver) => {
+            driver.run_forever().await;
+            info!("Bolt sidecar stopped");
+            Ok(())
+        }
         Err(err) => bail!("Failed to initialize the sidecar driver: {:?}", err),
-    };
+    }
+}
+
+/// Run the commitments API alone, forwarding the requests to a driver over the link.
+async fn run_api(opts: ApiOpts) -> Result<()> {
+    info!(driver = %opts.link_addr, "Starting Bolt commitments API");
+    let (events_tx, events_rx) = mpsc::channel(1024);
+    let mut server = CommitmentsApiServer::new(("0.0.0.0", opts.port))?
+        .with_chain(opts.chain, opts.genesis_time, ChainHead::default())
+        .with_response_timeout(Duration::from_millis(opts.response_timeout_ms))
+        .with_max_inflight_requests(opts.max_inflight_requests);
+    server.run(events_tx).await?;
 
+    tokio::spawn(LinkClient::new(opts.link_addr, opts.link_secret).run(events_rx));
+    tokio::signal::ctrl_c().await?;
     Ok(())
 }
+
+/// Print the earnings report for the given (inclusive) range of days.
+#[cfg(feature = "persistence")]
+fn print_earnings(opts: EarningsOpts) -> Result<()> {
+    if opts.to < opts.from {
+        bail!("The end of the range must not be before its start");
+    }
+
+    let store = Store::open(&opts.db_path)?;
+    // The end day is inclusive, so the range must extend to the start of the next day
+    let report = EarningsReport::from_store(&store, opts.from, opts.to + 86_400)?;
+
+    if opts.csv {
+        print!("{}", report.to_csv());
+    } else {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "persistence"))]
+fn print_earnings(_: EarningsOpts) -> Result<()> {
+    bail!("The earnings command requires the `persistence` feature")
+}
+
+/// Replay a recorded log, failing on the first decision that doesn't match.
+async fn replay(opts: ReplayOpts) -> Result<()> {
+    let report = Replayer::from_file(&opts.log)?.run().await?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+
+    if let Some(divergence) = report.divergence {
+        bail!("Replay diverged at entry #{} of the log", divergence.index + 1);
+    }
+
+    Ok(())
+}
+
+/// Validate a request against a snapshot of the chain state, failing if it's rejected.
+async fn simulate(opts: SimulateOpts) -> Result<()> {
+    let outcome = Simulation::run_opts(opts).await?;
+    println!("{}", serde_json::to_string_pretty(&outcome)?);
+
+    if let SimulationOutcome::Rejected { error } = outcome {
+        bail!("The request would be rejected: {}", error.message);
+    }
+
+    Ok(())
+}
+
+/// Sign the delegations, or revocations, of the validator keys of the signer to the delegatee.
+async fn delegate(opts: DelegateOpts) -> Result<()> {
+    let action = opts.action();
+    let config = Config::try_from(opts.signing)?;
+    let signer = signer_backend(&config).await?;
+
+    let delegations =
+        sign_delegations(signer.as_ref(), action, opts.delegatee_pubkey, opts.slasher).await?;
+    std::fs::write(&opts.out, serde_json::to_string_pretty(&delegations)?)?;
+    info!(count = delegations.len(), ?action, path = ?opts.out, "Wrote the signed delegations");
+
+    Ok(())
+}
+
+/// Report the pending migrations of the store, and apply them unless it's a dry run.
+#[cfg(feature = "persistence")]
+fn migrate_store(opts: MigrateOpts) -> Result<()> {
+    let status = Store::migration_status(&opts.db_path)?;
+    println!("{}", serde_json::to_string_pretty(&status)?);
+
+    if !opts.dry_run && !status.pending.is_empty() {
+        // Opening the store applies the pending migrations
+        Store::open(&opts.db_path)?;
+        info!(version = status.target, "Migrated the store");
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "persistence"))]
+fn migrate_store(_: MigrateOpts) -> Result<()> {
+    bail!("The db commands require the `persistence` feature")
+}
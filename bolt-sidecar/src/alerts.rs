@@ -0,0 +1,554 @@
+//! Alerts on the critical conditions of the sidecar, for operators to be paged without a full
+//! observability stack.
+//!
+//! Subsystems raise an [`AlertCondition`] when they enter a dangerous state and clear it once
+//! they recover. Each raised condition is delivered to the configured sinks, an HTTPS webhook
+//! and/or an external command reading the [`Alert`] JSON on its stdin, and a resolution is
+//! delivered once the condition has been cleared.
+//!
+//! To avoid flapping, a condition is only resolved once it hasn't been raised again for the
+//! hold time, and a condition is raised at most once per minimum interval.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+    process::Stdio,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use parking_lot::Mutex;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use tokio::{io::AsyncWriteExt, process::Command, sync::mpsc, task::JoinHandle};
+use tracing::{error, info, warn};
+
+/// Default time a condition must go without being raised before it is resolved.
+pub const DEFAULT_ALERT_HOLD_TIME: Duration = Duration::from_secs(60);
+
+/// Default minimum time between two raised alerts of the same condition.
+pub const DEFAULT_ALERT_MIN_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Default share of committed transactions missed from their slot above which the miss-rate
+/// condition is raised.
+pub const DEFAULT_MAX_MISS_RATE: f64 = 0.1;
+
+/// Default number of commitment requests awaiting the response of the driver above which
+/// the pending-responses condition is raised.
+pub const DEFAULT_MAX_PENDING_RESPONSES: usize = 256;
+
+/// Interval at which cleared conditions are checked for resolution.
+const RESOLVE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Maximum time to deliver an alert to a single sink.
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The critical conditions the sidecar raises alerts for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// The commitment signer fails to sign commitments, constraints or the identity document.
+    SignerUnavailable,
+    /// The share of committed transactions missed from their slot exceeds the configured SLO.
+    MissRateBreached,
+    /// Commitment records can't be written to the store.
+    StoreWriteFailure,
+    /// Non-critical records are buffered in memory, as the store doesn't accept writes.
+    StoreDegraded,
+    /// Constraints can't be submitted to MEV-Boost, even after retrying, or it is unreachable.
+    RelaySubmissionFailure,
+    /// Too many commitment requests are awaiting the response of the driver.
+    PendingResponsesBacklog,
+}
+
+impl AlertCondition {
+    /// All alert conditions.
+    pub const ALL: [Self; 6] = [
+        Self::SignerUnavailable,
+        Self::MissRateBreached,
+        Self::StoreWriteFailure,
+        Self::StoreDegraded,
+        Self::RelaySubmissionFailure,
+        Self::PendingResponsesBacklog,
+    ];
+}
+
+impl fmt::Display for AlertCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SignerUnavailable => write!(f, "signer_unavailable"),
+            Self::MissRateBreached => write!(f, "miss_rate_breached"),
+            Self::StoreWriteFailure => write!(f, "store_write_failure"),
+            Self::StoreDegraded => write!(f, "store_degraded"),
+            Self::RelaySubmissionFailure => write!(f, "relay_submission_failure"),
+            Self::PendingResponsesBacklog => write!(f, "pending_responses_backlog"),
+        }
+    }
+}
+
+impl FromStr for AlertCondition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|condition| condition.to_string() == s).ok_or_else(|| {
+            let known = Self::ALL.map(|condition| condition.to_string()).join(", ");
+            format!("Unknown alert condition '{s}', expected one of: {known}")
+        })
+    }
+}
+
+/// Whether an alert reports a raised or a resolved condition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertStatus {
+    /// The condition was raised.
+    Raised,
+    /// The condition was resolved.
+    Resolved,
+}
+
+/// An alert, as delivered to the sinks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Alert {
+    /// The condition of the alert.
+    pub condition: AlertCondition,
+    /// Whether the condition was raised or resolved.
+    pub status: AlertStatus,
+    /// A human-readable description of the alert.
+    pub message: String,
+    /// The identifier of the sidecar instance that raised the alert.
+    pub instance_id: String,
+    /// The UNIX timestamp (in seconds) of the alert.
+    pub timestamp: u64,
+}
+
+/// Configuration of the alerts: the sinks they are delivered to and the conditions they are
+/// raised for.
+#[derive(Debug, Clone)]
+pub struct AlertConfig {
+    /// URL of the webhook alerts are posted to as JSON, if any.
+    pub webhook_url: Option<Url>,
+    /// Command executed with each alert as JSON on its stdin, if any.
+    pub command: Option<PathBuf>,
+    /// The conditions alerts are raised for.
+    pub conditions: HashSet<AlertCondition>,
+    /// Time a condition must go without being raised before it is resolved.
+    pub hold_time: Duration,
+    /// Minimum time between two raised alerts of the same condition.
+    pub min_interval: Duration,
+    /// Share of committed transactions missed from their slot above which the miss-rate
+    /// condition is raised.
+    pub max_miss_rate: f64,
+    /// Number of commitment requests awaiting the response of the driver above which the
+    /// pending-responses condition is raised.
+    pub max_pending_responses: usize,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            webhook_url: None,
+            command: None,
+            conditions: AlertCondition::ALL.into_iter().collect(),
+            hold_time: DEFAULT_ALERT_HOLD_TIME,
+            min_interval: DEFAULT_ALERT_MIN_INTERVAL,
+            max_miss_rate: DEFAULT_MAX_MISS_RATE,
+            max_pending_responses: DEFAULT_MAX_PENDING_RESPONSES,
+        }
+    }
+}
+
+impl AlertConfig {
+    /// Returns true if alerts are delivered to at least one sink. The webhook is only a sink
+    /// with the `webhooks` feature.
+    pub fn has_sinks(&self) -> bool {
+        (cfg!(feature = "webhooks") && self.webhook_url.is_some()) || self.command.is_some()
+    }
+}
+
+/// The state of a raised condition.
+#[derive(Debug)]
+struct ActiveCondition {
+    /// The last time the condition was raised.
+    last_raised: Instant,
+    /// Whether the raised alert was delivered, or suppressed by the rate limit.
+    delivered: bool,
+    /// Whether the subsystem recovered since the condition was last raised.
+    cleared: bool,
+}
+
+/// The raise and resolve state machine of the conditions.
+#[derive(Debug)]
+struct AlertBook {
+    hold_time: Duration,
+    min_interval: Duration,
+    /// The raised conditions that are not resolved yet.
+    active: HashMap<AlertCondition, ActiveCondition>,
+    /// The last time an alert was delivered for each condition.
+    last_delivered: HashMap<AlertCondition, Instant>,
+}
+
+impl AlertBook {
+    fn new(hold_time: Duration, min_interval: Duration) -> Self {
+        Self { hold_time, min_interval, active: HashMap::new(), last_delivered: HashMap::new() }
+    }
+
+    /// Raises the condition, returning true if an alert must be delivered for it. Conditions
+    /// that are already raised, or were delivered less than the minimum interval ago, aren't.
+    fn raise(&mut self, condition: AlertCondition, now: Instant) -> bool {
+        if let Some(active) = self.active.get_mut(&condition) {
+            active.last_raised = now;
+            active.cleared = false;
+            return false;
+        }
+
+        let delivered = self
+            .last_delivered
+            .get(&condition)
+            .map_or(true, |last| now.saturating_duration_since(*last) >= self.min_interval);
+        if delivered {
+            self.last_delivered.insert(condition, now);
+        }
+
+        let active = ActiveCondition { last_raised: now, delivered, cleared: false };
+        self.active.insert(condition, active);
+        delivered
+    }
+
+    /// Marks the condition as recovered, so that it is resolved once the hold time elapses.
+    fn clear(&mut self, condition: AlertCondition) {
+        if let Some(active) = self.active.get_mut(&condition) {
+            active.cleared = true;
+        }
+    }
+
+    /// Returns true if the condition is raised and wasn't cleared since.
+    fn is_raised(&self, condition: AlertCondition) -> bool {
+        self.active.get(&condition).is_some_and(|active| !active.cleared)
+    }
+
+    /// Resolves the cleared conditions that weren't raised for the hold time, returning the
+    /// ones whose raised alert was delivered.
+    fn resolve(&mut self, now: Instant) -> Vec<AlertCondition> {
+        let mut resolved = Vec::new();
+        self.active.retain(|condition, active| {
+            let held = now.saturating_duration_since(active.last_raised) >= self.hold_time;
+            if !(active.cleared && held) {
+                return true;
+            }
+
+            if active.delivered {
+                resolved.push(*condition);
+            }
+            false
+        });
+
+        resolved
+    }
+}
+
+/// The messages of the delivery task.
+#[derive(Debug)]
+enum Delivery {
+    /// An alert to deliver to the sinks.
+    Alert(Alert),
+    /// Stops the delivery once the alerts sent before are delivered.
+    Close,
+}
+
+#[derive(Debug)]
+struct AlertsInner {
+    book: Mutex<AlertBook>,
+    conditions: HashSet<AlertCondition>,
+    instance_id: String,
+    sender: mpsc::UnboundedSender<Delivery>,
+    /// The delivery task, until it is awaited on close.
+    delivery: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl AlertsInner {
+    fn send(&self, condition: AlertCondition, status: AlertStatus, message: String) {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let alert =
+            Alert { condition, status, message, instance_id: self.instance_id.clone(), timestamp };
+
+        // The delivery task only stops once closed or all the handles are dropped
+        let _ = self.sender.send(Delivery::Alert(alert));
+    }
+
+    fn resolve(&self, now: Instant) {
+        for condition in self.book.lock().resolve(now) {
+            info!(%condition, "Resolved alert");
+            self.send(condition, AlertStatus::Resolved, format!("{condition} resolved"));
+        }
+    }
+}
+
+/// A handle to raise and clear alert conditions, shared by the subsystems. Cheap to clone.
+///
+/// Alerts are delivered in the background, so raising a condition never blocks the caller.
+/// The default handle is disabled and ignores all conditions.
+#[derive(Debug, Clone, Default)]
+pub struct Alerts {
+    inner: Option<Arc<AlertsInner>>,
+}
+
+impl Alerts {
+    /// Starts delivering the alerts of this instance to the configured sinks. The returned
+    /// handle is disabled if there are no sinks.
+    pub fn spawn(config: &AlertConfig, instance_id: impl Into<String>) -> Self {
+        if !config.has_sinks() {
+            return Self::default();
+        }
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let sinks = AlertSinks::new(config.webhook_url.clone(), config.command.clone());
+        let delivery = tokio::spawn(sinks.deliver(receiver));
+        let inner = Arc::new(AlertsInner {
+            book: Mutex::new(AlertBook::new(config.hold_time, config.min_interval)),
+            conditions: config.conditions.clone(),
+            instance_id: instance_id.into(),
+            sender,
+            delivery: Mutex::new(Some(delivery)),
+        });
+
+        // Periodically resolve the cleared conditions, until all the handles are dropped
+        let weak = Arc::downgrade(&inner);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(RESOLVE_CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                let Some(inner) = weak.upgrade() else { return };
+                inner.resolve(Instant::now());
+            }
+        });
+
+        Self { inner: Some(inner) }
+    }
+
+    /// Raises the condition. An alert is delivered unless the condition is already raised,
+    /// or was delivered less than the minimum interval ago.
+    pub fn raise(&self, condition: AlertCondition, message: impl Into<String>) {
+        let Some(inner) = &self.inner else { return };
+        if !inner.conditions.contains(&condition) {
+            return;
+        }
+
+        if inner.book.lock().raise(condition, Instant::now()) {
+            let message = message.into();
+            warn!(%condition, message, "Raised alert");
+            inner.send(condition, AlertStatus::Raised, message);
+        }
+    }
+
+    /// Clears the condition after the subsystem recovered. It is resolved once it hasn't been
+    /// raised again for the hold time.
+    pub fn clear(&self, condition: AlertCondition) {
+        if let Some(inner) = &self.inner {
+            inner.book.lock().clear(condition);
+        }
+    }
+
+    /// Returns true if the condition is raised and its subsystem didn't recover since.
+    /// Always false on a disabled handle.
+    pub fn is_raised(&self, condition: AlertCondition) -> bool {
+        self.inner.as_ref().is_some_and(|inner| inner.book.lock().is_raised(condition))
+    }
+
+    /// Stops delivering alerts once the ones sent so far are delivered, and waits for their
+    /// delivery. The alerts raised or resolved after are dropped.
+    pub async fn close(&self) {
+        let Some(inner) = &self.inner else { return };
+        let _ = inner.sender.send(Delivery::Close);
+        let delivery = inner.delivery.lock().take();
+        if let Some(delivery) = delivery {
+            if let Err(err) = delivery.await {
+                error!(?err, "Alert delivery task failed");
+            }
+        }
+    }
+}
+
+/// The sinks alerts are delivered to.
+#[derive(Debug)]
+struct AlertSinks {
+    #[cfg(feature = "webhooks")]
+    webhook: Option<Webhook>,
+    command: Option<PathBuf>,
+}
+
+impl AlertSinks {
+    fn new(webhook_url: Option<Url>, command: Option<PathBuf>) -> Self {
+        #[cfg(not(feature = "webhooks"))]
+        let _ = webhook_url;
+        Self {
+            #[cfg(feature = "webhooks")]
+            webhook: webhook_url.map(Webhook::new),
+            command,
+        }
+    }
+
+    /// Delivers the alerts to all the sinks, in order, until the delivery is closed or all
+    /// the handles are dropped.
+    async fn deliver(self, mut receiver: mpsc::UnboundedReceiver<Delivery>) {
+        while let Some(Delivery::Alert(alert)) = receiver.recv().await {
+            let payload = match serde_json::to_vec(&alert) {
+                Ok(payload) => payload,
+                Err(err) => {
+                    error!(?err, condition = %alert.condition, "Failed to encode alert");
+                    continue;
+                }
+            };
+
+            #[cfg(feature = "webhooks")]
+            if let Some(webhook) = &self.webhook {
+                if let Err(err) = webhook.post(&payload).await {
+                    error!(?err, condition = %alert.condition, "Failed to post alert to webhook");
+                }
+            }
+
+            if let Some(command) = &self.command {
+                if let Err(err) = run_command(command, &payload).await {
+                    error!(?err, condition = %alert.condition, "Failed to run alert command");
+                }
+            }
+        }
+    }
+}
+
+/// The webhook alerts are posted to as JSON.
+#[cfg(feature = "webhooks")]
+#[derive(Debug)]
+struct Webhook {
+    client: reqwest::Client,
+    url: Url,
+}
+
+#[cfg(feature = "webhooks")]
+impl Webhook {
+    fn new(url: Url) -> Self {
+        let client = reqwest::ClientBuilder::new()
+            .user_agent("bolt-sidecar")
+            .timeout(DELIVERY_TIMEOUT)
+            .build()
+            .expect("Valid HTTP client");
+
+        Self { client, url }
+    }
+
+    async fn post(&self, payload: &[u8]) -> eyre::Result<()> {
+        let response = self
+            .client
+            .post(self.url.clone())
+            .header("content-type", "application/json")
+            .body(payload.to_vec())
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            eyre::bail!("Webhook responded with status {}", response.status());
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs the alert command with the payload on its stdin, waiting for it to exit.
+async fn run_command(command: &Path, payload: &[u8]) -> eyre::Result<()> {
+    let mut child = Command::new(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(payload).await?;
+        // Dropping stdin closes it, so that the command sees the end of the payload
+    }
+
+    let status = tokio::time::timeout(DELIVERY_TIMEOUT, child.wait()).await??;
+    if !status.success() {
+        eyre::bail!("Alert command exited with {status}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_alert_conditions() {
+        for condition in AlertCondition::ALL {
+            assert_eq!(condition.to_string().parse::<AlertCondition>(), Ok(condition));
+        }
+
+        let err = "signer_down".parse::<AlertCondition>().unwrap_err();
+        assert!(err.contains("signer_unavailable"), "{err}");
+    }
+
+    #[test]
+    fn test_hold_time_and_rate_limit() {
+        let hold = Duration::from_secs(60);
+        let mut book = AlertBook::new(hold, Duration::from_secs(300));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let condition = AlertCondition::StoreWriteFailure;
+
+        // Raising an already raised condition is not delivered again
+        assert!(book.raise(condition, at(0)));
+        assert!(!book.raise(condition, at(10)));
+
+        // The condition isn't resolved before it is cleared, nor within the hold time
+        assert_eq!(book.resolve(at(100)), vec![]);
+        assert!(book.is_raised(condition));
+        book.clear(condition);
+        assert!(!book.is_raised(condition));
+        assert!(!book.raise(condition, at(110)));
+        book.clear(condition);
+        assert_eq!(book.resolve(at(150)), vec![]);
+        assert_eq!(book.resolve(at(170)), vec![condition]);
+
+        // Raised again within the minimum interval, it is neither delivered nor resolved
+        assert!(!book.raise(condition, at(200)));
+        book.clear(condition);
+        assert_eq!(book.resolve(at(260)), vec![]);
+
+        // After the minimum interval, it is delivered again
+        assert!(book.raise(condition, at(300)));
+        assert!(book.raise(AlertCondition::SignerUnavailable, at(300)));
+    }
+
+    #[tokio::test]
+    async fn test_command_sink() {
+        let suffix: u64 = rand::random();
+        let dir = std::env::temp_dir().join(format!("bolt-alerts-{suffix}"));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // The command copies the alert JSON from its stdin to a file
+        let output = dir.join("alert.json");
+        let script = dir.join("alert.sh");
+        std::fs::write(&script, format!("#!/bin/sh\ncat > {}\n", output.display())).unwrap();
+        let mut permissions = std::fs::metadata(&script).unwrap().permissions();
+        std::os::unix::fs::PermissionsExt::set_mode(&mut permissions, 0o755);
+        std::fs::set_permissions(&script, permissions).unwrap();
+
+        let config = AlertConfig { command: Some(script), ..Default::default() };
+        let alerts = Alerts::spawn(&config, "test");
+        alerts.raise(AlertCondition::SignerUnavailable, "Failed to sign commitment");
+
+        // Closing the alerts waits for the delivery of the raised ones
+        alerts.close().await;
+        let json = std::fs::read(&output).expect("alert delivered to the command");
+        let alert = serde_json::from_slice::<Alert>(&json).unwrap();
+        assert_eq!(alert.condition, AlertCondition::SignerUnavailable);
+        assert_eq!(alert.status, AlertStatus::Raised);
+        assert_eq!(alert.message, "Failed to sign commitment");
+        assert_eq!(alert.instance_id, "test");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
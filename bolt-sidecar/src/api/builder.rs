@@ -152,6 +152,7 @@ where
             *local_payload = Some(payload_and_bid.payload);
         }
 
+        // The local payloads are only built before the Electra fork, in the Deneb containers
         let versioned_bid = VersionedValue::<SignedBuilderBid> {
             version: Fork::Deneb,
             data: payload_and_bid.bid,
@@ -0,0 +1,109 @@
+//! REST endpoints of the admin server, to manage the whitelist and the limits at runtime.
+//!
+//! The admin server only listens on localhost. Next to its JSON-RPC methods, it serves:
+//!
+//! | Endpoint                           | Body                  | Response                  |
+//! |------------------------------------|-----------------------|---------------------------|
+//! | `GET /admin/whitelist`             |                       | The whitelist             |
+//! | `POST /admin/whitelist`            | [`WhitelistAddition`] | The added entry           |
+//! | `DELETE /admin/whitelist/:address` |                       | The removed entry, or 404 |
+//! | `PUT /admin/limits`                | [`LimitsUpdate`]      | The updated [`Limits`]    |
+//!
+//! Changes apply from the next request, and are lost on restart. When an admin token is
+//! configured, every request to the admin server must carry it in the [`ADMIN_TOKEN_HEADER`].
+
+use std::num::NonZero;
+
+use alloy::primitives::{keccak256, Address};
+use axum::http::HeaderMap;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Limits;
+
+use super::whitelist::WhitelistEntry;
+
+/// The header carrying the static token of the admin server.
+pub const ADMIN_TOKEN_HEADER: &str = "x-bolt-admin-token";
+
+/// The path of the whitelist endpoints.
+pub(super) const WHITELIST_PATH: &str = "/admin/whitelist";
+
+/// The path of the removal of a signer from the whitelist.
+pub(super) const WHITELIST_ENTRY_PATH: &str = "/admin/whitelist/:address";
+
+/// The path of the limits endpoint.
+pub(super) const LIMITS_PATH: &str = "/admin/limits";
+
+/// A signer to add to the whitelist, with the metadata of its entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WhitelistAddition {
+    /// The address of the signer.
+    pub address: Address,
+    /// The metadata of the entry, which replaces the existing one if any.
+    #[serde(flatten)]
+    pub entry: WhitelistEntry,
+}
+
+/// The limits to adjust. The fields that aren't set keep their value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LimitsUpdate {
+    /// The maximum gas that can be committed to in a slot.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_committed_gas_per_slot: Option<NonZero<u64>>,
+    /// The minimum priority fee per gas of the transactions, in wei.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_priority_fee: Option<u128>,
+}
+
+impl LimitsUpdate {
+    /// Applies the update to the limits.
+    pub fn apply(&self, limits: &mut Limits) {
+        if let Some(max_committed_gas) = self.max_committed_gas_per_slot {
+            limits.max_committed_gas_per_slot = max_committed_gas;
+        }
+        if let Some(min_priority_fee) = self.min_priority_fee {
+            limits.min_priority_fee = min_priority_fee;
+        }
+    }
+}
+
+/// Returns true if the headers carry the admin token. The hashes of the tokens are compared,
+/// so that the time of the comparison doesn't reveal how much of the token matches.
+pub fn is_authorized(token: &str, headers: &HeaderMap) -> bool {
+    headers
+        .get(ADMIN_TOKEN_HEADER)
+        .is_some_and(|value| keccak256(value.as_bytes()) == keccak256(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_update() {
+        let mut limits = Limits::default();
+        let update: LimitsUpdate = serde_json::from_str(r#"{ "min_priority_fee": 2 }"#).unwrap();
+        update.apply(&mut limits);
+        assert_eq!(limits, Limits { min_priority_fee: 2, ..Default::default() });
+
+        let max_gas = NonZero::new(1_000_000).unwrap();
+        let update = LimitsUpdate { max_committed_gas_per_slot: Some(max_gas), ..update };
+        update.apply(&mut limits);
+        assert_eq!(limits.max_committed_gas_per_slot, max_gas);
+        assert_eq!(limits.min_priority_fee, 2);
+
+        // A committed gas of zero is rejected
+        let zero = r#"{ "max_committed_gas_per_slot": 0 }"#;
+        assert!(serde_json::from_str::<LimitsUpdate>(zero).is_err());
+    }
+
+    #[test]
+    fn test_is_authorized() {
+        let mut headers = HeaderMap::new();
+        assert!(!is_authorized("secret", &headers));
+
+        headers.insert(ADMIN_TOKEN_HEADER, "secret".parse().unwrap());
+        assert!(is_authorized("secret", &headers));
+        assert!(!is_authorized("secret2", &headers));
+    }
+}
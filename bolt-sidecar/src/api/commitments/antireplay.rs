@@ -0,0 +1,184 @@
+//! Replay protection of the signed requests.
+//!
+//! Without it, a relay or anyone on the path of a request can send its payload and
+//! [`SIGNATURE_HEADER`] again at a later slot, to burn the inclusion budget of the signer.
+//! When enabled, the header must carry the UNIX timestamp in milliseconds at which the request
+//! was signed, as `<signer>:<signature>:<timestamp>`, and the signature must be over the
+//! [`timestamped_digest`] of the request. Requests signed outside of the window around the
+//! current time are rejected, and so are the ones whose signature was already seen within it.
+//!
+//! A signature is remembered until its timestamp leaves the window, after which it would be
+//! rejected anyway, so the seen set is bounded by the number of requests per window.
+//!
+//! [`SIGNATURE_HEADER`]: super::spec::SIGNATURE_HEADER
+
+use std::{collections::HashMap, sync::Weak, time::Duration};
+
+use alloy::primitives::{keccak256, Address, B256};
+use parking_lot::Mutex;
+use tracing::debug;
+
+use crate::state::clock::{Clock, TimeSource};
+
+/// The capability advertised by the sidecars that require timestamped signatures.
+pub const REPLAY_PROTECTION_CAPABILITY: &str = "replay_protection";
+
+/// The default window around the current time in which a signature timestamp is accepted.
+pub const DEFAULT_REPLAY_WINDOW: Duration = Duration::from_secs(30);
+
+/// Interval at which the signatures that left the window are evicted.
+pub(super) const REPLAY_SWEEP_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Returns the digest signed in a timestamped [`SIGNATURE_HEADER`]: the keccak256 hash of the
+/// digest of the request followed by the big-endian timestamp in milliseconds.
+///
+/// [`SIGNATURE_HEADER`]: super::spec::SIGNATURE_HEADER
+pub fn timestamped_digest(digest: B256, timestamp_ms: u64) -> B256 {
+    keccak256([digest.as_slice(), &timestamp_ms.to_be_bytes()].concat())
+}
+
+/// The reasons a signed request is rejected by the replay protection.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ReplayError {
+    /// The signature header has no timestamp.
+    #[error("Missing the timestamp of the signature, required by the replay protection")]
+    MissingTimestamp,
+    /// The request was signed too long ago, or too far in the future.
+    #[error("Signature timestamp {timestamp_ms} is outside of the {window_ms}ms replay window")]
+    TimestampExpired {
+        /// The timestamp of the signature, in milliseconds.
+        timestamp_ms: u64,
+        /// The window around the current time in which timestamps are accepted, in
+        /// milliseconds.
+        window_ms: u64,
+    },
+    /// The same signer already sent a request with the same digest within the window.
+    #[error("Request already received within the replay window")]
+    Replayed,
+}
+
+/// The signatures seen within the replay window, by signer and signed digest.
+#[derive(Debug)]
+pub struct ReplayGuard {
+    window: Duration,
+    /// The time after which each signature can be forgotten.
+    seen: Mutex<HashMap<(Address, B256), Duration>>,
+}
+
+impl ReplayGuard {
+    /// Create a guard accepting the timestamps within `window` of the current time, with no
+    /// signature seen yet.
+    pub fn new(window: Duration) -> Self {
+        Self { window, seen: Mutex::default() }
+    }
+
+    /// Returns the window around the current time in which timestamps are accepted.
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Checks that the signature timestamp is within the window of the time `now`. Clients
+    /// with a clock ahead of the sidecar are accepted within the same window.
+    pub fn check_timestamp(
+        &self,
+        timestamp_ms: Option<u64>,
+        now: Duration,
+    ) -> Result<(), ReplayError> {
+        let timestamp_ms = timestamp_ms.ok_or(ReplayError::MissingTimestamp)?;
+        let timestamp = Duration::from_millis(timestamp_ms);
+
+        let skew = if timestamp > now { timestamp - now } else { now - timestamp };
+        if skew > self.window {
+            let window_ms = self.window.as_millis() as u64;
+            return Err(ReplayError::TimestampExpired { timestamp_ms, window_ms });
+        }
+        Ok(())
+    }
+
+    /// Records the signature of `signer` over the timestamped digest, signed at `timestamp_ms`,
+    /// or returns [`ReplayError::Replayed`] if it was already seen within the window.
+    pub fn record(
+        &self,
+        signer: Address,
+        digest: B256,
+        timestamp_ms: u64,
+    ) -> Result<(), ReplayError> {
+        let expiry = Duration::from_millis(timestamp_ms) + self.window;
+        let mut seen = self.seen.lock();
+        if seen.insert((signer, digest), expiry).is_some() {
+            return Err(ReplayError::Replayed);
+        }
+        Ok(())
+    }
+
+    /// Removes the signatures whose timestamp left the window at the time `now`, returning
+    /// how many were removed.
+    pub fn evict_expired(&self, now: Duration) -> usize {
+        let mut seen = self.seen.lock();
+        let before = seen.len();
+        seen.retain(|_, expiry| *expiry >= now);
+        before - seen.len()
+    }
+
+    /// Returns the number of signatures seen within the window.
+    pub fn len(&self) -> usize {
+        self.seen.lock().len()
+    }
+
+    /// Returns true if no signature was seen within the window.
+    pub fn is_empty(&self) -> bool {
+        self.seen.lock().is_empty()
+    }
+}
+
+/// Evicts the signatures that left the window periodically, until the guard is dropped.
+pub(super) async fn sweep_expired(guard: Weak<ReplayGuard>, clock: Clock) {
+    let mut interval = tokio::time::interval(REPLAY_SWEEP_INTERVAL);
+    loop {
+        interval.tick().await;
+        let Some(guard) = guard.upgrade() else { return };
+
+        let evicted = guard.evict_expired(clock.now());
+        if evicted > 0 {
+            debug!(evicted, remaining = guard.len(), "Evicted expired request signatures");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_guard() {
+        let guard = ReplayGuard::new(DEFAULT_REPLAY_WINDOW);
+        let now = Duration::from_secs(1_700_000_000);
+        let now_ms = now.as_millis() as u64;
+        let window_ms = DEFAULT_REPLAY_WINDOW.as_millis() as u64;
+
+        // Clock skew just inside the window passes, in both directions
+        for timestamp_ms in [now_ms, now_ms - window_ms, now_ms + window_ms] {
+            assert_eq!(guard.check_timestamp(Some(timestamp_ms), now), Ok(()));
+        }
+        for timestamp_ms in [now_ms - window_ms - 1, now_ms + window_ms + 1] {
+            let err = guard.check_timestamp(Some(timestamp_ms), now).unwrap_err();
+            assert_eq!(err, ReplayError::TimestampExpired { timestamp_ms, window_ms });
+        }
+        assert_eq!(guard.check_timestamp(None, now), Err(ReplayError::MissingTimestamp));
+
+        // Only the exact replays are rejected
+        let (alice, bob) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let digest = timestamped_digest(B256::repeat_byte(1), now_ms);
+        assert_ne!(digest, timestamped_digest(B256::repeat_byte(1), now_ms + 1));
+        guard.record(alice, digest, now_ms).unwrap();
+        assert_eq!(guard.record(alice, digest, now_ms), Err(ReplayError::Replayed));
+        guard.record(bob, digest, now_ms).unwrap();
+        guard.record(alice, B256::repeat_byte(2), now_ms - window_ms).unwrap();
+
+        // Signatures are forgotten once their timestamp leaves the window
+        assert_eq!(guard.evict_expired(now), 0);
+        assert_eq!(guard.evict_expired(now + Duration::from_millis(1)), 1);
+        assert_eq!(guard.evict_expired(now + DEFAULT_REPLAY_WINDOW + Duration::from_millis(1)), 2);
+        assert!(guard.is_empty());
+    }
+}
@@ -0,0 +1,444 @@
+//! Append-only audit log of the commitment requests and the decisions on them.
+//!
+//! Every commitment request whose params are parsed is logged as one JSON line, with its
+//! signer, digest, target slot and transactions, and the decision on it: accepted, with the
+//! signature of the commitment, or rejected, with the code, reason and message of its error.
+//! Each line carries the [`AUDIT_LOG_VERSION`] of its format.
+//!
+//! The lines are written by a dedicated blocking task fed by a channel, and flushed after
+//! each batch, so that a slow disk never delays the responses. The file is rotated once it
+//! would exceed its size cap or the UTC day changes, by renaming it to
+//! `<file>.<YYYY-MM-DD>.<n>`. If it is moved or removed from under the sidecar, e.g. by
+//! `logrotate`, it is created again on the next write.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use alloy::primitives::{Address, TxHash, B256};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::{
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+};
+use tracing::{error, warn};
+
+use super::{
+    request_id::RequestId,
+    spec::{Error, ErrorCode},
+};
+use crate::earnings::format_day;
+
+/// The version of the format of the audit log entries.
+pub const AUDIT_LOG_VERSION: u32 = 1;
+
+/// Default size of the audit log file after which it is rotated, in bytes (256 MiB).
+pub const DEFAULT_AUDIT_LOG_MAX_BYTES: u64 = 256 * 1024 * 1024;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// The commitment request an audit log entry is about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditSubject {
+    /// The digest of the request.
+    pub digest: B256,
+    /// The target slot of the request.
+    pub slot: u64,
+    /// The hashes of the transactions of the request.
+    pub tx_hashes: Vec<TxHash>,
+}
+
+/// The decision of the sidecar on a commitment request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum AuditDecision {
+    /// A commitment was issued.
+    Accepted {
+        /// The signature of the commitment, as returned.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        signature: Option<String>,
+    },
+    /// The request was rejected with an error.
+    Rejected {
+        /// The JSON-RPC code of the error.
+        code: i32,
+        /// The reason sub-code of the error, if it has one.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+        /// The message of the error.
+        message: String,
+    },
+}
+
+/// An entry of the audit log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// The version of the format of the entry, see [`AUDIT_LOG_VERSION`].
+    pub version: u32,
+    /// The time of the decision, in milliseconds since the UNIX epoch.
+    pub timestamp_ms: u64,
+    /// The id of the call the request was received in.
+    pub request_id: RequestId,
+    /// The JSON-RPC method of the request.
+    pub method: String,
+    /// The signer of the request, if it was authenticated.
+    pub signer: Option<Address>,
+    /// The digest of the request.
+    pub digest: B256,
+    /// The target slot of the request.
+    pub slot: u64,
+    /// The hashes of the transactions of the request.
+    pub tx_hashes: Vec<TxHash>,
+    /// The decision on the request.
+    #[serde(flatten)]
+    pub decision: AuditDecision,
+}
+
+impl AuditEntry {
+    /// Creates the entry of a request whose call returned the given result at `now`.
+    pub fn new(
+        now: Duration,
+        request_id: RequestId,
+        method: &str,
+        signer: Option<Address>,
+        subject: AuditSubject,
+        result: &Result<Value, Error>,
+    ) -> Self {
+        let decision = match result {
+            Ok(commitment) => {
+                let signature = commitment.get("signature").and_then(Value::as_str);
+                AuditDecision::Accepted { signature: signature.map(String::from) }
+            }
+            Err(err) => {
+                let ErrorCode { code, reason } = err.error_code();
+                let (reason, message) = (reason.map(String::from), err.to_string());
+                AuditDecision::Rejected { code, reason, message }
+            }
+        };
+
+        let AuditSubject { digest, slot, tx_hashes } = subject;
+        Self {
+            version: AUDIT_LOG_VERSION,
+            timestamp_ms: now.as_millis() as u64,
+            request_id,
+            method: method.to_string(),
+            signer,
+            digest,
+            slot,
+            tx_hashes,
+            decision,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Command {
+    Write(Box<AuditEntry>),
+    Flush(oneshot::Sender<()>),
+    /// Stops the writer once the entries recorded before are written and flushed.
+    Close,
+}
+
+/// A handle to the audit log. Cheap to clone, the entries of all the handles are written in
+/// the order they are recorded.
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    commands: mpsc::UnboundedSender<Command>,
+    /// The writer task, until it is awaited on close.
+    writer: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+impl AuditLog {
+    /// Starts writing the audit log to the file at `path`, which is appended to if it exists,
+    /// and rotated once it would exceed `max_bytes`.
+    pub fn spawn(path: PathBuf, max_bytes: u64) -> Self {
+        let (commands, receiver) = mpsc::unbounded_channel();
+        let writer = AuditWriter { path, max_bytes, file: None };
+        let writer = tokio::task::spawn_blocking(move || writer.run(receiver));
+        Self { commands, writer: Arc::new(Mutex::new(Some(writer))) }
+    }
+
+    /// Appends an entry to the log, in the background. Write errors are logged, as auditing
+    /// must not affect the responses.
+    pub fn record(&self, entry: AuditEntry) {
+        let _ = self.commands.send(Command::Write(Box::new(entry)));
+    }
+
+    /// Waits until the entries recorded so far are written and flushed.
+    pub async fn flush(&self) {
+        let (done, flushed) = oneshot::channel();
+        if self.commands.send(Command::Flush(done)).is_ok() {
+            let _ = flushed.await;
+        }
+    }
+
+    /// Stops writing the log once the entries recorded so far are written and flushed, and
+    /// waits for the writer to finish. The entries recorded after are dropped.
+    pub async fn close(&self) {
+        let _ = self.commands.send(Command::Close);
+        let writer = self.writer.lock().take();
+        if let Some(writer) = writer {
+            if let Err(err) = writer.await {
+                error!(?err, "Audit log writer failed");
+            }
+        }
+    }
+}
+
+/// The audit log file being written to.
+#[derive(Debug)]
+struct OpenFile {
+    writer: BufWriter<File>,
+    /// The device and inode of the file, to notice that it was moved or removed.
+    id: (u64, u64),
+    /// The size of the file, in bytes.
+    len: u64,
+    /// The UTC day of the entries of the file, in days since the UNIX epoch.
+    day: u64,
+}
+
+/// Writes the entries of the audit log, on a blocking task.
+#[derive(Debug)]
+struct AuditWriter {
+    path: PathBuf,
+    max_bytes: u64,
+    file: Option<OpenFile>,
+}
+
+impl AuditWriter {
+    /// Writes the entries until the log is closed or all its handles are dropped. The entries
+    /// queued by the time one is received are written along with it, and flushed together.
+    fn run(mut self, mut commands: mpsc::UnboundedReceiver<Command>) {
+        while let Some(command) = commands.blocking_recv() {
+            self.close_if_moved();
+
+            let (mut flushed, mut closed) = (Vec::new(), false);
+            let mut next = Some(command);
+            while let Some(command) = next.take().or_else(|| commands.try_recv().ok()) {
+                match command {
+                    Command::Write(entry) => {
+                        if let Err(err) = self.write(&entry) {
+                            error!(?err, path = %self.path.display(), "Failed to write audit log");
+                        }
+                    }
+                    Command::Flush(done) => flushed.push(done),
+                    Command::Close => {
+                        closed = true;
+                        break;
+                    }
+                }
+            }
+
+            if let Some(ref mut open) = self.file {
+                if let Err(err) = open.writer.flush() {
+                    error!(?err, path = %self.path.display(), "Failed to flush audit log");
+                }
+            }
+            for done in flushed {
+                let _ = done.send(());
+            }
+            if closed {
+                return;
+            }
+        }
+    }
+
+    /// Closes the file if it was moved or removed since it was opened, so that the next
+    /// write creates it again. The entries written until then stay in the moved file.
+    fn close_if_moved(&mut self) {
+        let Some(ref open) = self.file else { return };
+        if file_id(&self.path).ok() != Some(open.id) {
+            warn!(path = %self.path.display(), "Audit log moved or removed, creating it again");
+            self.file = None;
+        }
+    }
+
+    fn write(&mut self, entry: &AuditEntry) -> io::Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+
+        let day = entry.timestamp_ms / 1000 / SECONDS_PER_DAY;
+        let open = self.file_for(day, line.len() as u64)?;
+        open.writer.write_all(&line)?;
+        open.len += line.len() as u64;
+        Ok(())
+    }
+
+    /// Returns the file to append `len` bytes of the given day to, rotating it first if it
+    /// would exceed the size cap or holds the entries of an earlier day.
+    fn file_for(&mut self, day: u64, len: u64) -> io::Result<&mut OpenFile> {
+        let mut open = match self.file.take() {
+            Some(open) => open,
+            None => self.open(day)?,
+        };
+
+        let full = open.len > 0 && open.len.saturating_add(len) > self.max_bytes;
+        if full || open.day < day {
+            open.writer.flush()?;
+            fs::rename(&self.path, self.rotated_path(open.day))?;
+            open = self.open(day)?;
+        }
+        Ok(self.file.insert(open))
+    }
+
+    /// Opens the file for appending, creating it if needed. The entries of an existing file
+    /// are of the day it was last modified.
+    fn open(&self, day: u64) -> io::Result<OpenFile> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+
+        let metadata = file.metadata()?;
+        let modified = metadata.modified()?.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let day = if metadata.len() > 0 { modified.as_secs() / SECONDS_PER_DAY } else { day };
+        let id = (metadata.dev(), metadata.ino());
+        Ok(OpenFile { writer: BufWriter::new(file), id, len: metadata.len(), day })
+    }
+
+    /// Returns the first free name of a rotated file with the entries of the given day.
+    fn rotated_path(&self, day: u64) -> PathBuf {
+        let name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        let date = format_day(day * SECONDS_PER_DAY);
+        (1..)
+            .map(|n| self.path.with_file_name(format!("{name}.{date}.{n}")))
+            .find(|path| !path.exists())
+            .expect("There is a free rotated name")
+    }
+}
+
+/// Returns the device and inode of the file at `path`.
+fn file_id(path: &Path) -> io::Result<(u64, u64)> {
+    let metadata = fs::metadata(path)?;
+    Ok((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bolt-audit-{}", rand::random::<u64>()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn entry(timestamp: Duration, result: &Result<Value, Error>) -> AuditEntry {
+        let subject = AuditSubject { digest: B256::random(), slot: 12, tx_hashes: vec![] };
+        let request_id = RequestId::generate();
+        AuditEntry::new(timestamp, request_id, "bolt_requestInclusion", None, subject, result)
+    }
+
+    fn json_commitment() -> Value {
+        serde_json::json!({ "slot": 12, "signature": "0x1234" })
+    }
+
+    fn read(path: &Path) -> Vec<AuditEntry> {
+        let lines = fs::read_to_string(path).unwrap();
+        lines.lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+    }
+
+    /// Returns the names of the files in the directory, sorted.
+    fn names(dir: &Path) -> Vec<String> {
+        let entries = fs::read_dir(dir).unwrap();
+        let mut names = entries
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_entry_format() {
+        let accepted = entry(Duration::from_millis(1_500), &Ok(json_commitment()));
+        let json = serde_json::to_value(&accepted).unwrap();
+        assert_eq!(json["version"], AUDIT_LOG_VERSION);
+        assert_eq!(json["timestamp_ms"], 1_500);
+        assert_eq!(json["decision"], "accepted");
+        assert_eq!(json["signature"], "0x1234");
+
+        let rejected = entry(Duration::ZERO, &Err(Error::Duplicate));
+        let json = serde_json::to_value(&rejected).unwrap();
+        assert_eq!(json["decision"], "rejected");
+        assert_eq!(json["message"], Error::Duplicate.to_string());
+        assert_eq!(serde_json::from_value::<AuditEntry>(json).unwrap(), rejected);
+    }
+
+    #[tokio::test]
+    async fn test_rotation() {
+        let dir = temp_dir();
+        let path = dir.join("audit.jsonl");
+        let day = Duration::from_secs(20_000 * SECONDS_PER_DAY);
+
+        // The file is rotated once it would exceed its size cap
+        let first = entry(day, &Err(Error::Duplicate));
+        let len = serde_json::to_vec(&first).unwrap().len() as u64 + 1;
+        let log = AuditLog::spawn(path.clone(), 2 * len);
+        log.record(first.clone());
+        log.record(first.clone());
+        log.record(first.clone());
+        log.flush().await;
+        let date = format_day(day.as_secs());
+        assert_eq!(names(&dir), vec!["audit.jsonl".to_string(), format!("audit.jsonl.{date}.1")]);
+        assert_eq!(read(&dir.join(format!("audit.jsonl.{date}.1"))).len(), 2);
+        assert_eq!(read(&path), vec![first]);
+
+        // And on the first entry of the next day
+        let next = entry(day + Duration::from_secs(SECONDS_PER_DAY), &Err(Error::Duplicate));
+        log.record(next.clone());
+        log.flush().await;
+        assert_eq!(read(&dir.join(format!("audit.jsonl.{date}.2"))).len(), 1);
+        assert_eq!(read(&path), vec![next]);
+    }
+
+    #[tokio::test]
+    async fn test_file_rotated_from_under_the_log() {
+        let dir = temp_dir();
+        let path = dir.join("audit.jsonl");
+        let log = AuditLog::spawn(path.clone(), DEFAULT_AUDIT_LOG_MAX_BYTES);
+        let now = Duration::from_secs(20_000 * SECONDS_PER_DAY);
+
+        let first = entry(now, &Err(Error::Duplicate));
+        log.record(first.clone());
+        log.flush().await;
+
+        // Moved away by an external tool, the file is created again
+        let moved = dir.join("audit.jsonl.old");
+        fs::rename(&path, &moved).unwrap();
+        let second = entry(now, &Ok(json_commitment()));
+        log.record(second.clone());
+        log.flush().await;
+        assert_eq!(read(&moved), vec![first]);
+        assert_eq!(read(&path), vec![second.clone()]);
+
+        // As when removed
+        fs::remove_file(&path).unwrap();
+        let third = entry(now, &Err(Error::Duplicate));
+        log.record(third.clone());
+        log.flush().await;
+        assert_eq!(read(&path), vec![third]);
+    }
+
+    #[tokio::test]
+    async fn test_close() {
+        let dir = temp_dir();
+        let path = dir.join("audit.jsonl");
+        let log = AuditLog::spawn(path.clone(), DEFAULT_AUDIT_LOG_MAX_BYTES);
+        let now = Duration::from_secs(20_000 * SECONDS_PER_DAY);
+
+        // The entries recorded before the log is closed are written, not the later ones
+        let first = entry(now, &Err(Error::Duplicate));
+        log.record(first.clone());
+        log.close().await;
+        log.record(entry(now, &Ok(json_commitment())));
+        log.flush().await;
+        assert_eq!(read(&path), vec![first]);
+    }
+}
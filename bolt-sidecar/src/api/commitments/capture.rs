@@ -0,0 +1,425 @@
+use std::{
+    fmt::Write as _,
+    fs, io,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use alloy::primitives::Address;
+use axum::{
+    async_trait,
+    body::{to_bytes, Body, Bytes},
+    extract::{FromRef, FromRequest, Request},
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::{
+    primitives::{address::deserialize_addresses, parse_address},
+    state::clock::{Clock, TimeSource},
+};
+
+use super::{
+    jsonrpc::{JsonRequest, JsonResponse},
+    relay::RELAY_ATTESTATION_HEADER,
+    spec::{Error, SignatureScheme, EIP712_SCHEME, SIGNATURE_HEADER},
+};
+
+/// Default global size cap for all captured exchanges, in bytes (64 MiB).
+pub const DEFAULT_CAPTURE_MAX_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Default number of captures after which capture mode is automatically disabled.
+pub const DEFAULT_MAX_CAPTURES: usize = 100;
+
+/// Headers whose values are never written to disk.
+const REDACTED_HEADERS: &[&str] = &["authorization", "proxy-authorization", "cookie"];
+
+/// Placeholder for redacted header values.
+const REDACTED: &str = "<redacted>";
+
+/// Placeholder for captured header values with control characters, which could forge
+/// lines of the captured headers file.
+const INVALID: &str = "<invalid>";
+
+/// Filter selecting which exchanges are captured. An exchange is captured if
+/// its signer is in `signers` or its response error code is in `error_codes`.
+/// If both lists are empty, every exchange is captured.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureFilter {
+    /// Signer addresses (from the `x-bolt-signature` header) to capture.
+    #[serde(default, deserialize_with = "deserialize_addresses")]
+    pub signers: Vec<Address>,
+    /// JSON-RPC error codes to capture.
+    #[serde(default)]
+    pub error_codes: Vec<i32>,
+    /// Number of captures after which capture mode is automatically disabled.
+    #[serde(default = "default_max_captures")]
+    pub max_captures: usize,
+}
+
+fn default_max_captures() -> usize {
+    DEFAULT_MAX_CAPTURES
+}
+
+impl CaptureFilter {
+    fn matches(&self, signer: Option<Address>, error_code: Option<i32>) -> bool {
+        if self.signers.is_empty() && self.error_codes.is_empty() {
+            return true;
+        }
+
+        signer.is_some_and(|s| self.signers.contains(&s)) ||
+            error_code.is_some_and(|c| self.error_codes.contains(&c))
+    }
+}
+
+#[derive(Debug, Default)]
+struct CaptureState {
+    /// The active filter. Capture mode is disabled if `None`.
+    filter: Option<CaptureFilter>,
+    /// Number of captures written since capture mode was last enabled.
+    captured: usize,
+    /// Total bytes written since startup, counted against the global cap.
+    total_bytes: u64,
+    /// Monotonic sequence number to disambiguate captures in the same millisecond.
+    seq: u64,
+}
+
+/// Debug capture of raw JSON-RPC exchanges. Off by default.
+///
+/// When enabled, the exact bytes of matching requests and responses are written
+/// under the configured directory, one sub-directory per exchange containing
+/// `headers.txt` (with secrets redacted), `request.bin` and `response.bin`.
+#[derive(Debug, Clone)]
+pub struct RequestCapture {
+    /// The directory to write captures to. Capture can't be enabled without it.
+    dir: Option<PathBuf>,
+    /// The global size cap for all captures.
+    max_total_bytes: u64,
+    /// The clock that timestamps the captured exchanges.
+    clock: Clock,
+    state: Arc<Mutex<CaptureState>>,
+}
+
+impl Default for RequestCapture {
+    fn default() -> Self {
+        Self::new(None, DEFAULT_CAPTURE_MAX_BYTES)
+    }
+}
+
+impl RequestCapture {
+    /// Create a new, disabled capture writing to `dir` up to `max_total_bytes` in total.
+    pub fn new(dir: Option<PathBuf>, max_total_bytes: u64) -> Self {
+        let state = Arc::new(Mutex::new(CaptureState::default()));
+        Self { dir, max_total_bytes, clock: Clock::System, state }
+    }
+
+    /// Timestamps the captured exchanges with the given clock, sharing the capture state.
+    pub fn with_clock(self, clock: Clock) -> Self {
+        Self { clock, ..self }
+    }
+
+    /// Enable capture mode with the given filter, resetting the capture count.
+    pub fn enable(&self, filter: CaptureFilter) -> Result<(), CaptureError> {
+        let Some(ref dir) = self.dir else {
+            return Err(CaptureError::NoDirectory);
+        };
+
+        fs::create_dir_all(dir)?;
+
+        let mut state = self.state.lock();
+        if state.total_bytes >= self.max_total_bytes {
+            return Err(CaptureError::SizeCapReached(self.max_total_bytes));
+        }
+
+        info!(?filter, dir = %dir.display(), "Request capture enabled");
+        state.filter = Some(filter);
+        state.captured = 0;
+        Ok(())
+    }
+
+    /// Disable capture mode.
+    pub fn disable(&self) {
+        if self.state.lock().filter.take().is_some() {
+            info!("Request capture disabled");
+        }
+    }
+
+    /// Returns true if capture mode is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.state.lock().filter.is_some()
+    }
+
+    /// Write the given exchange to disk if it matches the active filter.
+    /// Returns true if the exchange was captured.
+    pub fn record(
+        &self,
+        headers: &HeaderMap,
+        request: &[u8],
+        response: &[u8],
+        error_code: Option<i32>,
+    ) -> Result<bool, CaptureError> {
+        let Some(ref dir) = self.dir else { return Ok(false) };
+
+        let mut state = self.state.lock();
+        let Some(ref filter) = state.filter else { return Ok(false) };
+
+        if !filter.matches(signer_from_headers(headers), error_code) {
+            return Ok(false);
+        }
+
+        let max_captures = filter.max_captures;
+        let headers = redacted_headers(headers);
+        let size = (headers.len() + request.len() + response.len()) as u64;
+
+        if state.total_bytes + size > self.max_total_bytes {
+            warn!(max_bytes = self.max_total_bytes, "Request capture size cap reached, disabling");
+            state.filter = None;
+            return Ok(false);
+        }
+
+        let now = self.clock.now().as_millis();
+        let path = dir.join(format!("{now}-{:06}", state.seq));
+        state.seq += 1;
+
+        fs::create_dir_all(&path)?;
+        fs::write(path.join("headers.txt"), headers)?;
+        fs::write(path.join("request.bin"), request)?;
+        fs::write(path.join("response.bin"), response)?;
+
+        state.total_bytes += size;
+        state.captured += 1;
+
+        if state.captured >= max_captures {
+            info!(captured = state.captured, "Request capture limit reached, disabling");
+            state.filter = None;
+        }
+
+        Ok(true)
+    }
+}
+
+/// Errors that can occur while capturing requests.
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum CaptureError {
+    #[error("No capture directory configured")]
+    NoDirectory,
+    #[error("Capture size cap of {0} bytes reached")]
+    SizeCapReached(u64),
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Extracts the signer address from the [`SIGNATURE_HEADER`], if present
+/// and well-formed. The signature itself is not verified.
+fn signer_from_headers(headers: &HeaderMap) -> Option<Address> {
+    let auth = headers.get(SIGNATURE_HEADER)?.to_str().ok()?;
+    let (_, auth) = SignatureScheme::split(auth).ok()?;
+    parse_address(auth.split(':').next()?).ok()
+}
+
+/// Returns the part of a header value up to the signature: the signer, after the
+/// [`EIP712_SCHEME`] prefix if there is one.
+fn signer_part(value: &str) -> &str {
+    let (start, rest) = match value.split_once(':') {
+        Some((EIP712_SCHEME, rest)) => (EIP712_SCHEME.len() + 1, rest),
+        _ => (0, value),
+    };
+    &value[..start + rest.find(':').unwrap_or(rest.len())]
+}
+
+/// Formats the headers one per line, redacting secrets.
+/// Only the address part of the [`SIGNATURE_HEADER`] and [`RELAY_ATTESTATION_HEADER`] is kept.
+fn redacted_headers(headers: &HeaderMap) -> String {
+    let mut out = String::new();
+
+    for (name, value) in headers {
+        let value = if REDACTED_HEADERS.contains(&name.as_str()) {
+            REDACTED.to_string()
+        } else if value.as_bytes().iter().any(u8::is_ascii_control) {
+            INVALID.to_string()
+        } else if name == SIGNATURE_HEADER || name == RELAY_ATTESTATION_HEADER {
+            let value = String::from_utf8_lossy(value.as_bytes());
+            format!("{}:{REDACTED}", signer_part(&value))
+        } else {
+            String::from_utf8_lossy(value.as_bytes()).into_owned()
+        };
+
+        let _ = writeln!(out, "{name}: {value}");
+    }
+
+    out
+}
+
+/// The raw bytes of a request, kept around to be captured with its response.
+#[derive(Debug)]
+pub(super) struct RawRequest {
+    capture: RequestCapture,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl RawRequest {
+    /// Captures the exchange if it matches the active filter, returning the
+    /// response unchanged.
+    pub(super) async fn capture(self, response: Response) -> Response {
+        let (parts, body) = response.into_parts();
+
+        let bytes = match to_bytes(body, usize::MAX).await {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                error!(?err, "Failed to buffer response body for capture");
+                return Response::from_parts(parts, Body::empty());
+            }
+        };
+
+        let error_code = serde_json::from_slice::<JsonResponse>(&bytes)
+            .ok()
+            .and_then(|res| res.error)
+            .map(|err| err.code);
+
+        if let Err(err) = self.capture.record(&self.headers, &self.body, &bytes, error_code) {
+            error!(?err, "Failed to write request capture");
+        }
+
+        Response::from_parts(parts, Body::from(bytes))
+    }
+}
+
+/// JSON-RPC body extractor that sees the exact bytes that arrived,
+/// so they can be captured when capture mode is enabled.
+#[derive(Debug)]
+pub(super) struct CapturedPayload {
+    /// The request headers.
+    pub headers: HeaderMap,
+    /// The parsed JSON-RPC request, single or batched.
+    pub payload: JsonRequest,
+    /// The raw request, only kept if capture mode is enabled.
+    pub raw: Option<RawRequest>,
+}
+
+#[async_trait]
+impl<S> FromRequest<S> for CapturedPayload
+where
+    S: Send + Sync,
+    RequestCapture: FromRef<S>,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let capture = RequestCapture::from_ref(state);
+        let headers = req.headers().clone();
+
+        let body = Bytes::from_request(req, state)
+            .await
+            .map_err(|err| Error::InvalidBody(err).into_response())?;
+
+        let raw = capture.is_enabled().then(|| RawRequest {
+            capture,
+            headers: headers.clone(),
+            body: body.clone(),
+        });
+
+        match JsonRequest::from_slice(&body) {
+            Ok(payload) => Ok(Self { headers, payload, raw }),
+            Err(err) => {
+                let response = Error::InvalidPayload(err).into_response();
+                match raw {
+                    Some(raw) => Err(raw.capture(response).await),
+                    None => Err(response),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_capture_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("bolt-capture-{}", rand::random::<u64>()))
+    }
+
+    fn headers_for(signer: Address) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(SIGNATURE_HEADER, format!("{signer}:0xdeadbeef").parse().unwrap());
+        headers.insert("authorization", "Bearer secret".parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_capture_filter_and_limit() {
+        let dir = temp_capture_dir();
+        let capture = RequestCapture::new(Some(dir.clone()), DEFAULT_CAPTURE_MAX_BYTES);
+
+        let ours = Address::random();
+        let other = Address::random();
+
+        // Disabled by default
+        assert!(!capture.record(&headers_for(ours), b"req", b"res", None).unwrap());
+
+        let filter = CaptureFilter { signers: vec![ours], error_codes: vec![], max_captures: 2 };
+        capture.enable(filter).unwrap();
+
+        assert!(capture.record(&headers_for(ours), b"req1", b"res1", None).unwrap());
+        assert!(!capture.record(&headers_for(other), b"req2", b"res2", None).unwrap());
+        assert!(capture.record(&headers_for(ours), b"req3", b"res3", Some(-32000)).unwrap());
+
+        // Automatically disabled after 2 captures
+        assert!(!capture.is_enabled());
+        assert!(!capture.record(&headers_for(ours), b"req4", b"res4", None).unwrap());
+
+        let entries = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().path()).collect::<Vec<_>>();
+        assert_eq!(entries.len(), 2);
+
+        for entry in entries {
+            let headers = fs::read_to_string(entry.join("headers.txt")).unwrap();
+            assert!(headers.contains(&format!("{SIGNATURE_HEADER}: {ours}:{REDACTED}")));
+            assert!(headers.contains(&format!("authorization: {REDACTED}")));
+            assert!(!headers.contains("secret") && !headers.contains("deadbeef"));
+        }
+
+        // The scheme prefix is kept along with the signer
+        let mut headers = HeaderMap::new();
+        let value = format!("{EIP712_SCHEME}:{ours}:0xdeadbeef");
+        headers.insert(SIGNATURE_HEADER, value.parse().unwrap());
+        assert_eq!(signer_from_headers(&headers), Some(ours));
+        let redacted = redacted_headers(&headers);
+        assert!(
+            redacted.contains(&format!("{SIGNATURE_HEADER}: {EIP712_SCHEME}:{ours}:{REDACTED}"))
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_capture_size_cap() {
+        let dir = temp_capture_dir();
+        let capture = RequestCapture::new(Some(dir.clone()), 1024);
+
+        let filter = CaptureFilter { signers: vec![], error_codes: vec![-32006], max_captures: 10 };
+        capture.enable(filter).unwrap();
+
+        let headers = HeaderMap::new();
+        assert!(!capture.record(&headers, b"req", b"res", Some(-32000)).unwrap());
+        assert!(capture.record(&headers, &[0; 500], &[0; 500], Some(-32006)).unwrap());
+
+        // This one would exceed the global cap
+        assert!(!capture.record(&headers, &[0; 500], &[0; 500], Some(-32006)).unwrap());
+        assert!(!capture.is_enabled());
+
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_enable_without_directory() {
+        let capture = RequestCapture::default();
+        let filter = CaptureFilter { signers: vec![], error_codes: vec![], max_captures: 1 };
+        assert!(matches!(capture.enable(filter), Err(CaptureError::NoDirectory)));
+    }
+}
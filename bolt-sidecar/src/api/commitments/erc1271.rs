@@ -0,0 +1,226 @@
+//! EIP-1271 signatures of the smart-contract wallets, such as Safes.
+//!
+//! A contract can't sign the digest of a request, so the signer recovered from the signature
+//! never matches the address of the contract in the [`SIGNATURE_HEADER`]. When enabled, the
+//! sidecar then asks the contract itself whether the signature is valid, with an
+//! `isValidSignature(bytes32,bytes)` call through its execution client. The signature must
+//! still be a 65-byte signature, such as the one of a single-owner Safe.
+//!
+//! The outcome of a check is only kept for the HTTP request it was made for, as the owners of
+//! a wallet can change at any time: the calls of a batch signed by the same wallet over the
+//! same digest share a single check.
+//!
+//! [`SIGNATURE_HEADER`]: super::spec::SIGNATURE_HEADER
+
+use std::{collections::HashMap, sync::Arc};
+
+use alloy::{
+    network::TransactionBuilder,
+    primitives::{Address, Bytes, FixedBytes, Signature, B256},
+    rpc::types::TransactionRequest,
+    sol,
+    sol_types::SolCall,
+    transports::{RpcError, TransportError},
+};
+use parking_lot::Mutex;
+use tokio::sync::OnceCell;
+use tracing::{debug, warn};
+
+use crate::client::rpc::RpcClient;
+
+/// The value returned by `isValidSignature` for a valid signature: its function selector.
+pub const ERC1271_MAGIC_VALUE: FixedBytes<4> = FixedBytes([0x16, 0x26, 0xba, 0x7e]);
+
+/// The JSON-RPC error code of a reverted `eth_call`, as returned by geth and reth.
+const EXECUTION_REVERTED_CODE: i64 = 3;
+
+sol! {
+    /// Returns the magic value if the signature of the hash is valid for the contract.
+    function isValidSignature(bytes32 hash, bytes signature) external view returns (bytes4);
+}
+
+/// The reasons a contract signature check fails.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Erc1271Error {
+    /// The contract reverted, e.g. because the signature isn't made by its owners.
+    #[error("Signer contract reverted: {0}")]
+    Reverted(String),
+    /// The contract returned something else than the magic value, e.g. because the signer
+    /// isn't a contract at all.
+    #[error("Signer contract returned {0} instead of the EIP-1271 magic value")]
+    InvalidMagicValue(Bytes),
+    /// The execution client couldn't be reached, or failed to run the call.
+    #[error("Failed to check the contract signature: {0}")]
+    Rpc(String),
+}
+
+impl From<TransportError> for Erc1271Error {
+    fn from(err: TransportError) -> Self {
+        match err {
+            RpcError::ErrorResp(ref payload) if is_revert(payload.code, &payload.message) => {
+                Self::Reverted(payload.message.clone())
+            }
+            err => Self::Rpc(err.to_string()),
+        }
+    }
+}
+
+/// Returns whether an error response of `eth_call` is a revert of the call, rather than a
+/// failure of the execution client.
+fn is_revert(code: i64, message: &str) -> bool {
+    code == EXECUTION_REVERTED_CODE || message.contains("revert")
+}
+
+/// Checks the signatures of the smart-contract wallets with their `isValidSignature` method.
+#[derive(Debug, Clone)]
+pub struct Erc1271Verifier {
+    client: RpcClient,
+}
+
+impl Erc1271Verifier {
+    /// Create a verifier calling the contracts through the given execution client.
+    pub fn new(client: RpcClient) -> Self {
+        Self { client }
+    }
+
+    /// Checks that the contract at `signer` considers the signature of the digest valid, at
+    /// the latest block.
+    pub async fn verify(
+        &self,
+        signer: Address,
+        digest: B256,
+        signature: &Signature,
+    ) -> Result<(), Erc1271Error> {
+        let signature = Bytes::copy_from_slice(&signature.as_bytes());
+        let call = isValidSignatureCall { hash: digest, signature };
+        let tx = TransactionRequest::default().with_to(signer).with_input(call.abi_encode());
+        let output = self.client.call(&tx, None).await?;
+
+        match isValidSignatureCall::abi_decode_returns(&output, true) {
+            Ok(returns) if returns._0 == ERC1271_MAGIC_VALUE => Ok(()),
+            _ => Err(Erc1271Error::InvalidMagicValue(output)),
+        }
+    }
+}
+
+/// The outcomes of the contract signature checks made for an HTTP request, by signer and
+/// digest. Concurrent checks of the same signature wait for the first one.
+#[derive(Debug, Clone, Default)]
+pub struct ContractSignatureCache {
+    checks: Arc<Mutex<HashMap<(Address, B256), Arc<OnceCell<Result<(), Erc1271Error>>>>>>,
+}
+
+impl ContractSignatureCache {
+    /// Checks the contract signature of the digest with the verifier, unless it was already
+    /// checked for this request.
+    pub async fn verify(
+        &self,
+        verifier: &Erc1271Verifier,
+        signer: Address,
+        digest: B256,
+        signature: &Signature,
+    ) -> Result<(), Erc1271Error> {
+        let check = Arc::clone(self.checks.lock().entry((signer, digest)).or_default());
+        let outcome = check
+            .get_or_init(|| async {
+                let outcome = verifier.verify(signer, digest, signature).await;
+                match outcome {
+                    Ok(()) => debug!(%signer, %digest, "Contract signature is valid"),
+                    Err(ref err) => {
+                        warn!(%signer, %digest, %err, "Contract signature check failed")
+                    }
+                }
+                outcome
+            })
+            .await;
+        outcome.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use alloy::signers::{local::PrivateKeySigner, Signer};
+    use axum::{extract::State, routing::post, Json, Router};
+    use reqwest::Url;
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Serves an execution client answering every `eth_call` with the given response, and
+    /// counting the calls.
+    async fn mock_rpc(response: Value) -> (Erc1271Verifier, Arc<AtomicUsize>) {
+        async fn handle(
+            State((response, calls)): State<(Value, Arc<AtomicUsize>)>,
+            Json(request): Json<Value>,
+        ) -> Json<Value> {
+            assert_eq!(request["method"], "eth_call");
+            calls.fetch_add(1, Ordering::SeqCst);
+
+            let mut response = response;
+            response["jsonrpc"] = json!("2.0");
+            response["id"] = request["id"].clone();
+            Json(response)
+        }
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let router = Router::new().route("/", post(handle)).with_state((response, calls.clone()));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        (Erc1271Verifier::new(RpcClient::new(url)), calls)
+    }
+
+    /// Returns the ABI encoding of a `bytes4` return value.
+    fn encoded_return(value: [u8; 4]) -> String {
+        let mut word = [0; 32];
+        word[..4].copy_from_slice(&value);
+        format!("0x{}", hex::encode(word))
+    }
+
+    #[tokio::test]
+    async fn test_contract_signatures() {
+        let wallet = Address::repeat_byte(0x5a);
+        let digest = B256::repeat_byte(1);
+        let signature = PrivateKeySigner::random().sign_hash(&digest).await.unwrap();
+
+        let magic = json!({ "result": encoded_return(ERC1271_MAGIC_VALUE.0) });
+        let (verifier, calls) = mock_rpc(magic).await;
+        assert_eq!(verifier.verify(wallet, digest, &signature).await, Ok(()));
+
+        // The checks of the same request are cached
+        let cache = ContractSignatureCache::default();
+        for _ in 0..3 {
+            assert_eq!(cache.verify(&verifier, wallet, digest, &signature).await, Ok(()));
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        let other = B256::repeat_byte(2);
+        assert_eq!(cache.verify(&verifier, wallet, other, &signature).await, Ok(()));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // Anything but the magic value is a rejection, including the empty output of an EOA
+        for garbage in [encoded_return([0xde, 0xad, 0xbe, 0xef]), "0x".to_string()] {
+            let (verifier, _) = mock_rpc(json!({ "result": garbage })).await;
+            let err = verifier.verify(wallet, digest, &signature).await.unwrap_err();
+            assert!(matches!(err, Erc1271Error::InvalidMagicValue(_)), "{err}");
+        }
+
+        let revert = json!({ "error": { "code": 3, "message": "execution reverted: GS026" } });
+        let (verifier, _) = mock_rpc(revert).await;
+        let err = verifier.verify(wallet, digest, &signature).await.unwrap_err();
+        assert_eq!(err, Erc1271Error::Reverted("execution reverted: GS026".to_string()));
+
+        // The failures of the execution client are told apart from the rejections
+        let unavailable = json!({ "error": { "code": -32000, "message": "header not found" } });
+        let (verifier, _) = mock_rpc(unavailable).await;
+        let err = verifier.verify(wallet, digest, &signature).await.unwrap_err();
+        assert!(matches!(err, Erc1271Error::Rpc(_)), "{err}");
+
+        let unreachable = RpcClient::new(Url::parse("http://127.0.0.1:1").unwrap());
+        let err = Erc1271Verifier::new(unreachable).verify(wallet, digest, &signature).await;
+        assert!(matches!(err, Err(Erc1271Error::Rpc(_))));
+    }
+}
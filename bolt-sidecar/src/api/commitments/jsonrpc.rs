@@ -1,5 +1,41 @@
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use std::fmt;
+
+use serde::{
+    de::{self, IgnoredAny, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+use serde_json::{Number, Value};
+use thiserror::Error;
+
+/// Maximum length of a string request id, in bytes.
+pub const MAX_ID_LENGTH: usize = 128;
+
+/// Maximum length of a method name, in bytes.
+pub const MAX_METHOD_LENGTH: usize = 64;
+
+/// Maximum nesting depth of the arrays and objects of a request, counting the request itself.
+pub const MAX_NESTING_DEPTH: usize = 32;
+
+/// Maximum number of requests in a batch.
+pub const MAX_BATCH_SIZE: usize = 32;
+
+/// Errors parsing a JSON-RPC request payload.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum PayloadError {
+    #[error("Request is nested deeper than {MAX_NESTING_DEPTH} levels")]
+    TooDeep,
+    #[error("Request id is longer than {MAX_ID_LENGTH} bytes")]
+    IdTooLong,
+    #[error("Request id must be a number, null, or a string without control characters")]
+    InvalidId,
+    #[error("Method is longer than {MAX_METHOD_LENGTH} bytes or contains control characters")]
+    InvalidMethod,
+    #[error("Batch must contain between 1 and {MAX_BATCH_SIZE} requests")]
+    InvalidBatchSize,
+    #[error("Failed to parse the request body: {0}")]
+    Json(#[from] serde_json::Error),
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsonPayload {
@@ -11,6 +47,190 @@ pub struct JsonPayload {
     pub id: Option<Value>,
     /// The parameters object.
     pub params: Vec<Value>,
+    /// Optional signature of the request signer, formatted like the [`SIGNATURE_HEADER`].
+    /// It authenticates the request in place of the header, so that each request of a batch
+    /// can be signed by its own signer.
+    ///
+    /// [`SIGNATURE_HEADER`]: super::spec::SIGNATURE_HEADER
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl JsonPayload {
+    /// Parses a payload from the exact request body bytes, rejecting pathological payloads
+    /// before they are allocated or echoed: deeply nested params, and ids or methods that
+    /// are too long or contain control characters, which would end up in logs and headers.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, PayloadError> {
+        check_nesting_depth(bytes)?;
+        Self::from_raw(serde_json::from_slice(bytes)?)
+    }
+
+    fn from_raw(raw: RawPayload) -> Result<Self, PayloadError> {
+        let RawPayload { jsonrpc, method, id, params, signature } = raw;
+        let id = match id {
+            RawId::Valid(id) => id,
+            RawId::TooLong => return Err(PayloadError::IdTooLong),
+            RawId::Invalid => return Err(PayloadError::InvalidId),
+        };
+
+        if method.len() > MAX_METHOD_LENGTH || method.chars().any(char::is_control) {
+            return Err(PayloadError::InvalidMethod);
+        }
+
+        Ok(Self { jsonrpc, method, id, params, signature })
+    }
+}
+
+/// The body of a JSON-RPC request: a single payload, or a batch of them.
+#[derive(Debug)]
+pub enum JsonRequest {
+    /// A single request.
+    Single(JsonPayload),
+    /// The requests of a batch, parsed independently so that an invalid one only fails
+    /// itself.
+    Batch(Vec<Result<JsonPayload, PayloadError>>),
+}
+
+impl JsonRequest {
+    /// Parses a request from the exact request body bytes, as a batch if it is an array.
+    /// See [`JsonPayload::from_slice`] for the checks of each payload.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, PayloadError> {
+        if bytes.iter().find(|byte| !byte.is_ascii_whitespace()) != Some(&b'[') {
+            return JsonPayload::from_slice(bytes).map(Self::Single);
+        }
+
+        check_nesting_depth(bytes)?;
+        let entries: Vec<Value> = serde_json::from_slice(bytes)?;
+        if entries.is_empty() || entries.len() > MAX_BATCH_SIZE {
+            return Err(PayloadError::InvalidBatchSize);
+        }
+
+        let parse = |entry| JsonPayload::from_raw(serde_json::from_value(entry)?);
+        Ok(Self::Batch(entries.into_iter().map(parse).collect()))
+    }
+
+    /// Returns the method of a single request, or `batch`.
+    pub fn method(&self) -> &str {
+        match self {
+            Self::Single(payload) => &payload.method,
+            Self::Batch(_) => "batch",
+        }
+    }
+}
+
+/// A payload as deserialized, before its fields are checked.
+#[derive(Deserialize)]
+struct RawPayload {
+    jsonrpc: String,
+    method: String,
+    #[serde(default)]
+    id: RawId,
+    params: Vec<Value>,
+    #[serde(default)]
+    signature: Option<String>,
+}
+
+/// A request id, classified while deserializing so that invalid ids are never allocated.
+#[derive(Debug)]
+enum RawId {
+    Valid(Option<Value>),
+    TooLong,
+    Invalid,
+}
+
+impl Default for RawId {
+    fn default() -> Self {
+        Self::Valid(None)
+    }
+}
+
+impl<'de> Deserialize<'de> for RawId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(RawIdVisitor)
+    }
+}
+
+struct RawIdVisitor;
+
+impl<'de> Visitor<'de> for RawIdVisitor {
+    type Value = RawId;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a request id")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<RawId, E> {
+        Ok(RawId::Valid(None))
+    }
+
+    fn visit_u64<E: de::Error>(self, id: u64) -> Result<RawId, E> {
+        Ok(RawId::Valid(Some(id.into())))
+    }
+
+    fn visit_i64<E: de::Error>(self, id: i64) -> Result<RawId, E> {
+        Ok(RawId::Valid(Some(id.into())))
+    }
+
+    fn visit_f64<E: de::Error>(self, id: f64) -> Result<RawId, E> {
+        Ok(Number::from_f64(id).map_or(RawId::Invalid, |id| RawId::Valid(Some(id.into()))))
+    }
+
+    fn visit_str<E: de::Error>(self, id: &str) -> Result<RawId, E> {
+        if id.len() > MAX_ID_LENGTH {
+            Ok(RawId::TooLong)
+        } else if id.chars().any(char::is_control) {
+            Ok(RawId::Invalid)
+        } else {
+            Ok(RawId::Valid(Some(id.into())))
+        }
+    }
+
+    fn visit_bool<E: de::Error>(self, _: bool) -> Result<RawId, E> {
+        Ok(RawId::Invalid)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<RawId, A::Error> {
+        while seq.next_element::<IgnoredAny>()?.is_some() {}
+        Ok(RawId::Invalid)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<RawId, A::Error> {
+        while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
+        Ok(RawId::Invalid)
+    }
+}
+
+/// Rejects documents whose arrays and objects are nested deeper than [`MAX_NESTING_DEPTH`],
+/// in a single pass over the bytes before they are parsed. Malformed documents are left to
+/// the parser.
+fn check_nesting_depth(bytes: &[u8]) -> Result<(), PayloadError> {
+    let (mut depth, mut in_string, mut escaped) = (0usize, false, false);
+
+    for &byte in bytes {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' => {
+                depth += 1;
+                if depth > MAX_NESTING_DEPTH {
+                    return Err(PayloadError::TooDeep);
+                }
+            }
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    Ok(())
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,13 +256,120 @@ impl JsonResponse {
             jsonrpc: "2.0".to_string(),
             id: None,
             result: Value::Null,
-            error: Some(JsonError { code, message }),
+            error: Some(JsonError { code, message, data: None }),
         }
     }
 }
 
+/// A JSON-RPC error object.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsonError {
+    /// The JSON-RPC error code.
     pub code: i32,
+    /// A human-readable description of the error.
     pub message: String,
+    /// Optional structured data with additional information about the error.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub data: Option<Value>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn request_with_id(id: Value) -> Vec<u8> {
+        serde_json::to_vec(&json!({
+            "jsonrpc": "2.0",
+            "method": "bolt_requestInclusion",
+            "id": id,
+            "params": [],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_valid_ids() {
+        for id in [json!(1), json!(-1), json!(1.5), json!("abc-é"), json!(null)] {
+            let payload = JsonPayload::from_slice(&request_with_id(id.clone())).unwrap();
+            assert_eq!(payload.id.unwrap_or_default(), id);
+        }
+
+        let body = br#"{"jsonrpc":"2.0","method":"bolt_getVersion","params":[]}"#;
+        assert_eq!(JsonPayload::from_slice(body).unwrap().id, None);
+    }
+
+    #[test]
+    fn test_huge_id_rejected_promptly() {
+        let body = request_with_id(Value::String("a".repeat(1024 * 1024)));
+
+        let start = Instant::now();
+        let err = JsonPayload::from_slice(&body).unwrap_err();
+        assert!(matches!(err, PayloadError::IdTooLong), "{err}");
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_invalid_ids() {
+        for id in [json!("line\nbreak"), json!("nul\u{0}"), json!(true), json!({"a": 1})] {
+            let err = JsonPayload::from_slice(&request_with_id(id)).unwrap_err();
+            assert!(matches!(err, PayloadError::InvalidId), "{err}");
+        }
+    }
+
+    #[test]
+    fn test_deep_nesting_rejected() {
+        let nested = format!("{}{}", "[".repeat(200), "]".repeat(200));
+        let body = format!(r#"{{"jsonrpc":"2.0","method":"m","id":1,"params":{nested}}}"#);
+        let err = JsonPayload::from_slice(body.as_bytes()).unwrap_err();
+        assert!(matches!(err, PayloadError::TooDeep), "{err}");
+
+        // Brackets in strings don't count
+        let brackets = "[".repeat(200);
+        let body = format!(r#"{{"jsonrpc":"2.0","method":"m","id":1,"params":["{brackets}"]}}"#);
+        assert!(JsonPayload::from_slice(body.as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn test_batch_parsing() {
+        let single = JsonRequest::from_slice(&request_with_id(json!(1))).unwrap();
+        assert!(matches!(single, JsonRequest::Single(ref payload) if payload.id == Some(json!(1))));
+
+        // Invalid requests of a batch fail independently
+        let batch = format!(
+            " [{}, {}, 42]",
+            String::from_utf8(request_with_id(json!(1))).unwrap(),
+            String::from_utf8(request_with_id(json!("a".repeat(MAX_ID_LENGTH + 1)))).unwrap(),
+        );
+        let JsonRequest::Batch(entries) = JsonRequest::from_slice(batch.as_bytes()).unwrap() else {
+            panic!("Expected a batch");
+        };
+        assert!(matches!(entries[0], Ok(ref payload) if payload.id == Some(json!(1))));
+        assert!(matches!(entries[1], Err(PayloadError::IdTooLong)));
+        assert!(matches!(entries[2], Err(PayloadError::Json(_))));
+
+        let too_large = format!("[{}]", vec!["{}"; MAX_BATCH_SIZE + 1].join(","));
+        for body in ["[]", too_large.as_str()] {
+            let err = JsonRequest::from_slice(body.as_bytes()).unwrap_err();
+            assert!(matches!(err, PayloadError::InvalidBatchSize), "{err}");
+        }
+    }
+
+    #[test]
+    fn test_invalid_methods() {
+        for method in ["bolt_\nrequestInclusion".to_string(), "m".repeat(MAX_METHOD_LENGTH + 1)] {
+            let body = serde_json::to_vec(&json!({
+                "jsonrpc": "2.0",
+                "method": method,
+                "id": 1,
+                "params": [],
+            }))
+            .unwrap();
+            let err = JsonPayload::from_slice(&body).unwrap_err();
+            assert!(matches!(err, PayloadError::InvalidMethod), "{err}");
+        }
+    }
 }
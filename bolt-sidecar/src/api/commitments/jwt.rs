@@ -0,0 +1,94 @@
+//! JWT bearer authentication of the JSON-RPC listeners.
+//!
+//! Operators sharing a sidecar between several internal apps can restrict who reaches its
+//! methods at all, independently of the signature of each request. When a secret is set, every
+//! request must carry an `Authorization: Bearer <token>` header with an HS256 token signed
+//! with it, like the engine API of the execution clients: its `iat` claim must be within a
+//! minute of the current time, and its `exp` claim, if any, must not have passed by more than
+//! a minute. The [`SIGNATURE_HEADER`] is only looked at once the token is valid.
+//!
+//! [`SIGNATURE_HEADER`]: super::spec::SIGNATURE_HEADER
+
+use axum::http::{header::AUTHORIZATION, HeaderMap};
+use reth_rpc_layer::{JwtError, JwtSecret};
+
+/// The reasons a request is rejected by the JWT authentication.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum JwtAuthError {
+    /// The request has no bearer token.
+    #[error("Missing JWT bearer token")]
+    MissingToken,
+    /// The token was issued too long ago, or expired.
+    #[error("JWT bearer token expired")]
+    ExpiredToken,
+    /// The token isn't signed with the secret, or can't be decoded.
+    #[error("Invalid JWT bearer token: {0}")]
+    InvalidToken(String),
+}
+
+impl From<JwtError> for JwtAuthError {
+    fn from(err: JwtError) -> Self {
+        match err {
+            JwtError::InvalidIssuanceTimestamp => Self::ExpiredToken,
+            // The `exp` claim is checked by the decoder, which only reports it by name
+            JwtError::JwtDecodingError(ref detail) if detail.contains("ExpiredSignature") => {
+                Self::ExpiredToken
+            }
+            err => Self::InvalidToken(err.to_string()),
+        }
+    }
+}
+
+/// Checks that the `Authorization` header carries a valid bearer token signed with `secret`.
+pub fn authorize(secret: &JwtSecret, headers: &HeaderMap) -> Result<(), JwtAuthError> {
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(JwtAuthError::MissingToken)?;
+
+    Ok(secret.validate(token.trim())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use reth_rpc_layer::Claims;
+
+    use super::*;
+
+    fn headers_with(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, format!("Bearer {token}").parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_authorize() {
+        let secret = JwtSecret::random();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+        let valid = secret.encode(&Claims { iat: now, exp: Some(now + 600) }).unwrap();
+        assert_eq!(authorize(&secret, &headers_with(&valid)), Ok(()));
+        // Within the clock tolerance
+        let skewed = secret.encode(&Claims { iat: now + 30, exp: None }).unwrap();
+        assert_eq!(authorize(&secret, &headers_with(&skewed)), Ok(()));
+
+        let stale = secret.encode(&Claims { iat: now - 600, exp: None }).unwrap();
+        assert_eq!(authorize(&secret, &headers_with(&stale)), Err(JwtAuthError::ExpiredToken));
+        let expired = secret.encode(&Claims { iat: now, exp: Some(now - 600) }).unwrap();
+        assert_eq!(authorize(&secret, &headers_with(&expired)), Err(JwtAuthError::ExpiredToken));
+
+        let forged = JwtSecret::random().encode(&Claims { iat: now, exp: None }).unwrap();
+        let err = authorize(&secret, &headers_with(&forged)).unwrap_err();
+        assert!(matches!(err, JwtAuthError::InvalidToken(_)), "{err}");
+        let err = authorize(&secret, &headers_with("not-a-token")).unwrap_err();
+        assert!(matches!(err, JwtAuthError::InvalidToken(_)), "{err}");
+
+        assert_eq!(authorize(&secret, &HeaderMap::new()), Err(JwtAuthError::MissingToken));
+        let mut basic = HeaderMap::new();
+        basic.insert(AUTHORIZATION, "Basic dXNlcjpwYXNz".parse().unwrap());
+        assert_eq!(authorize(&secret, &basic), Err(JwtAuthError::MissingToken));
+    }
+}
@@ -0,0 +1,692 @@
+//! The link between the commitments API and the driver, when they run in separate processes.
+//!
+//! In a single process, the API server hands each request to the driver as an [`Event`] over
+//! a channel, and the driver answers on the response channel of the event. The link carries
+//! the same contract over TCP, so that the API server can run on a host exposed to the
+//! users while the driver, with the keys and the chain connections, runs on an internal one:
+//! [`LinkClient`] forwards the events of an API server to a driver, and [`serve`] hands them
+//! to the driver's channel and sends the responses back. Neither half can tell it isn't
+//! talking to the in-process channel:
+//!
+//! - Requests can be answered in any order, and are correlated by an identifier.
+//! - A request whose response channel is closed before it's forwarded is dropped. If it's
+//!   closed after, the response channel on the driver side is dropped as well.
+//! - Backpressure is the flow control of the connection: the driver side stops reading
+//!   while the channel of the driver is full, which in turn fills the channel of the API
+//!   server.
+//! - While the link is down, new requests are rejected with [`Error::ServiceUnavailable`],
+//!   as are the requests that were in flight when it dropped, as if the driver had crashed.
+//!   The client reconnects with exponential backoff.
+//!
+//! Both sides prove to each other that they know a shared secret: each sends a random nonce,
+//! and answers with the hash of the secret, its role and both nonces. Every frame is then
+//! authenticated with a MAC keyed by the session key, over the direction and the sequence
+//! number of the frame, so that frames can't be injected, replayed or reordered. Keccak
+//! isn't subject to length extension, so prefixing the key is enough to make it a MAC.
+//! The link isn't encrypted: it should run on a private network, or through a tunnel.
+//!
+//! A frame is the length of its body as a big-endian `u32`, its MAC, and its body, which
+//! is a JSON message.
+
+use std::{collections::HashMap, fmt, str::FromStr, time::Duration};
+
+use alloy::primitives::{keccak256, Address, Bytes, Signature, B256};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::{mpsc, oneshot},
+};
+use tracing::{debug, info, warn};
+
+use crate::primitives::{commitment::SignedCommitment, CommitmentRequest};
+
+use super::{
+    request_id::RequestId, server::Event, spec::Error, whitelist::QuotaOverrides, JsonError,
+};
+
+/// The greeting of the API side, which also versions the protocol.
+const GREETING: &[u8; 8] = b"BOLTLNK1";
+
+/// The maximum size of the body of a frame. Requests are bounded by the body limit of the
+/// API server, which is smaller.
+const MAX_FRAME_SIZE: usize = 4 * 1024 * 1024;
+
+/// The time allowed to connect and complete the handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The delay before the first reconnection attempt, doubled after each failed one.
+const MIN_RECONNECT_DELAY: Duration = Duration::from_millis(100);
+
+/// The maximum delay between two reconnection attempts.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+/// The secret shared by the two sides of the link. It isn't printed in the logs.
+#[derive(Clone, PartialEq, Eq)]
+pub struct LinkSecret(B256);
+
+impl LinkSecret {
+    /// Creates a secret from its bytes.
+    pub fn new(secret: B256) -> Self {
+        Self(secret)
+    }
+}
+
+impl fmt::Debug for LinkSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LinkSecret(..)")
+    }
+}
+
+impl FromStr for LinkSecret {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        B256::from_str(s)
+            .map(Self)
+            .map_err(|_| "Invalid link secret, expected 32 bytes in hex".to_string())
+    }
+}
+
+/// Configuration of the link listener of the driver.
+#[derive(Debug, Clone)]
+pub struct LinkConfig {
+    /// The port to accept the links of the commitments API processes on.
+    pub port: u16,
+    /// The secret shared with the commitments API processes.
+    pub secret: LinkSecret,
+}
+
+/// Errors that close the link.
+#[derive(Debug, Error)]
+pub enum LinkError {
+    /// I/O error on the connection.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    /// The peer didn't complete the handshake in time.
+    #[error("Handshake timed out")]
+    HandshakeTimeout,
+    /// The peer doesn't speak this version of the protocol.
+    #[error("Unexpected greeting")]
+    UnexpectedGreeting,
+    /// The peer doesn't know the shared secret.
+    #[error("Authentication failed")]
+    AuthenticationFailed,
+    /// The frame is larger than the maximum frame size.
+    #[error("Frame of {0} bytes is too large")]
+    FrameTooLarge(usize),
+    /// The MAC of the frame is invalid.
+    #[error("Invalid frame MAC")]
+    InvalidMac,
+    /// The body of the frame isn't a valid message.
+    #[error("Invalid message: {0}")]
+    InvalidMessage(#[from] serde_json::Error),
+    /// The message isn't expected from this side of the link.
+    #[error("Unexpected message")]
+    UnexpectedMessage,
+}
+
+/// The side of the link.
+#[derive(Debug, Clone, Copy)]
+enum Role {
+    Api,
+    Driver,
+}
+
+impl Role {
+    fn label(self) -> &'static [u8] {
+        match self {
+            Role::Api => b"api",
+            Role::Driver => b"driver",
+        }
+    }
+}
+
+/// The messages exchanged over the link.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Message {
+    /// A request to process, from the API side. The signature and signer of the request
+    /// aren't part of its serialization, so they are sent along with it.
+    Request {
+        id: u64,
+        request: CommitmentRequest,
+        signature: Option<Bytes>,
+        signer: Option<Address>,
+        /// Missing if the API side predates the request ids.
+        #[serde(default)]
+        request_id: Option<RequestId>,
+        /// The quota overrides of the signer, omitted if there are none.
+        #[serde(default, skip_serializing_if = "QuotaOverrides::is_empty")]
+        quotas: QuotaOverrides,
+    },
+    /// The response channel of a request was closed on the API side.
+    Cancel { id: u64 },
+    /// The response to a request, from the driver side.
+    Response { id: u64, outcome: Outcome },
+}
+
+/// The result of a request, with the error as served by the API.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Outcome {
+    Commitment(SignedCommitment),
+    Error(JsonError),
+}
+
+/// Hashes the secret with the given parts, prefixed by the secret.
+fn keyed_hash(key: &B256, parts: &[&[u8]]) -> B256 {
+    let mut buf = key.to_vec();
+    for part in parts {
+        buf.extend_from_slice(part);
+    }
+    keccak256(buf)
+}
+
+/// Connects to the driver side, and returns the session key.
+async fn connect(stream: &mut TcpStream, secret: &LinkSecret) -> Result<B256, LinkError> {
+    let nonce = B256::from(rand::random::<[u8; 32]>());
+    stream.write_all(GREETING).await?;
+    stream.write_all(nonce.as_slice()).await?;
+
+    let mut challenge = [0; 64];
+    stream.read_exact(&mut challenge).await?;
+    let (peer_nonce, proof) = (B256::from_slice(&challenge[..32]), &challenge[32..]);
+    let (api, driver) = (nonce.as_slice(), peer_nonce.as_slice());
+    if proof != keyed_hash(&secret.0, &[Role::Driver.label(), api, driver]).as_slice() {
+        return Err(LinkError::AuthenticationFailed);
+    }
+
+    let proof = keyed_hash(&secret.0, &[Role::Api.label(), api, driver]);
+    stream.write_all(proof.as_slice()).await?;
+    Ok(keyed_hash(&secret.0, &[b"session", api, driver]))
+}
+
+/// Accepts a connection from the API side, and returns the session key.
+async fn accept(stream: &mut TcpStream, secret: &LinkSecret) -> Result<B256, LinkError> {
+    let mut hello = [0; 40];
+    stream.read_exact(&mut hello).await?;
+    if &hello[..8] != GREETING {
+        return Err(LinkError::UnexpectedGreeting);
+    }
+
+    let nonce = B256::from(rand::random::<[u8; 32]>());
+    let (api, driver) = (&hello[8..], nonce.as_slice());
+    let proof = keyed_hash(&secret.0, &[Role::Driver.label(), api, driver]);
+    stream.write_all(driver).await?;
+    stream.write_all(proof.as_slice()).await?;
+
+    let mut proof = [0; 32];
+    stream.read_exact(&mut proof).await?;
+    if B256::from(proof) != keyed_hash(&secret.0, &[Role::Api.label(), api, driver]) {
+        return Err(LinkError::AuthenticationFailed);
+    }
+
+    Ok(keyed_hash(&secret.0, &[b"session", api, driver]))
+}
+
+/// Writes the authenticated frames sent by one side of the link.
+struct FrameWriter<W> {
+    inner: W,
+    key: B256,
+    role: Role,
+    seq: u64,
+}
+
+impl<W: AsyncWrite + Unpin> FrameWriter<W> {
+    async fn send(&mut self, message: &Message) -> Result<(), LinkError> {
+        let body = serde_json::to_vec(message)?;
+        let mac = keyed_hash(&self.key, &[self.role.label(), &self.seq.to_be_bytes(), &body]);
+        self.seq += 1;
+
+        let mut frame = Vec::with_capacity(4 + 32 + body.len());
+        frame.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        frame.extend_from_slice(mac.as_slice());
+        frame.extend_from_slice(&body);
+        self.inner.write_all(&frame).await?;
+        Ok(())
+    }
+}
+
+/// Reads the authenticated frames sent by the other side of the link.
+struct FrameReader<R> {
+    inner: R,
+    key: B256,
+    role: Role,
+    seq: u64,
+}
+
+impl<R: AsyncRead + Unpin + Send + 'static> FrameReader<R> {
+    async fn recv(&mut self) -> Result<Message, LinkError> {
+        let len = self.inner.read_u32().await? as usize;
+        if len > MAX_FRAME_SIZE {
+            return Err(LinkError::FrameTooLarge(len));
+        }
+
+        let mut mac = [0; 32];
+        self.inner.read_exact(&mut mac).await?;
+        let mut body = vec![0; len];
+        self.inner.read_exact(&mut body).await?;
+
+        let expected = keyed_hash(&self.key, &[self.role.label(), &self.seq.to_be_bytes(), &body]);
+        if B256::from(mac) != expected {
+            return Err(LinkError::InvalidMac);
+        }
+        self.seq += 1;
+
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    /// Reads the frames in a task until the connection fails, as reading a frame can't be
+    /// interrupted without losing its start.
+    fn spawn(mut self) -> mpsc::Receiver<Result<Message, LinkError>> {
+        let (frames_tx, frames_rx) = mpsc::channel(16);
+        tokio::spawn(async move {
+            loop {
+                let frame = self.recv().await;
+                let failed = frame.is_err();
+                if frames_tx.send(frame).await.is_err() || failed {
+                    return;
+                }
+            }
+        });
+        frames_rx
+    }
+}
+
+/// Forwards the events of a commitments API server to a driver in another process.
+#[derive(Debug, Clone)]
+pub struct LinkClient {
+    addr: String,
+    secret: LinkSecret,
+}
+
+impl LinkClient {
+    /// Creates a client of the driver link listening on the given address.
+    pub fn new(addr: impl Into<String>, secret: LinkSecret) -> Self {
+        Self { addr: addr.into(), secret }
+    }
+
+    /// Forwards the events until the API server is gone. The requests received while the
+    /// link is down are rejected, until it's reconnected.
+    pub async fn run(self, mut events: mpsc::Receiver<Event>) {
+        let mut delay = MIN_RECONNECT_DELAY;
+
+        loop {
+            let connection = tokio::time::timeout(HANDSHAKE_TIMEOUT, self.connect()).await;
+            match connection.unwrap_or(Err(LinkError::HandshakeTimeout)) {
+                Ok((stream, key)) => {
+                    info!(addr = %self.addr, "Connected to the driver");
+                    delay = MIN_RECONNECT_DELAY;
+                    match forward(stream, key, &mut events).await {
+                        Ok(()) => return,
+                        Err(err) => warn!(addr = %self.addr, ?err, "Link to the driver dropped"),
+                    }
+                }
+                Err(err) => warn!(addr = %self.addr, ?err, "Failed to connect to the driver"),
+            }
+
+            let reconnect = tokio::time::sleep(delay);
+            tokio::pin!(reconnect);
+            loop {
+                tokio::select! {
+                    _ = &mut reconnect => break,
+                    event = events.recv() => match event {
+                        Some(event) => {
+                            let _ = event.response.send(Err(Error::ServiceUnavailable));
+                        }
+                        None => return,
+                    },
+                }
+            }
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    }
+
+    async fn connect(&self) -> Result<(TcpStream, B256), LinkError> {
+        let mut stream = TcpStream::connect(&self.addr).await?;
+        stream.set_nodelay(true)?;
+        let key = connect(&mut stream, &self.secret).await?;
+        Ok((stream, key))
+    }
+}
+
+/// Forwards the events over an established link, until the API server is gone or the link
+/// fails.
+async fn forward(
+    stream: TcpStream,
+    key: B256,
+    events: &mut mpsc::Receiver<Event>,
+) -> Result<(), LinkError> {
+    let (reader, writer) = stream.into_split();
+    let mut frames = FrameReader { inner: reader, key, role: Role::Driver, seq: 0 }.spawn();
+    let mut writer = FrameWriter { inner: writer, key, role: Role::Api, seq: 0 };
+
+    // Dropping the link drops the senders of the requests in flight, which rejects them
+    let mut in_flight = HashMap::new();
+    let (cancels_tx, mut cancels) = mpsc::unbounded_channel();
+    let mut next_id = 0;
+
+    loop {
+        tokio::select! {
+            event = events.recv() => {
+                let Some(Event { request, response, request_id, quotas }) = event else {
+                    return Ok(());
+                };
+                // The request was abandoned before being forwarded
+                if response.is_closed() {
+                    continue;
+                }
+
+                let id = next_id;
+                next_id += 1;
+                let message = Message::Request {
+                    id,
+                    signature: request.signature().map(|sig| sig.as_bytes().to_vec().into()),
+                    signer: request.signer(),
+                    request_id: Some(request_id),
+                    quotas,
+                    request,
+                };
+
+                let (remote_tx, remote_rx) = oneshot::channel();
+                in_flight.insert(id, remote_tx);
+                tokio::spawn(correlate(id, remote_rx, response, cancels_tx.clone()));
+                writer.send(&message).await?;
+            }
+            frame = frames.recv() => match frame {
+                Some(Ok(Message::Response { id, outcome })) => {
+                    // The request may have been cancelled in the meantime
+                    let Some(remote) = in_flight.remove(&id) else { continue };
+                    let _ = remote.send(match outcome {
+                        Outcome::Commitment(commitment) => Ok(commitment),
+                        Outcome::Error(err) => Err(Error::Remote(err)),
+                    });
+                }
+                Some(Ok(_)) => return Err(LinkError::UnexpectedMessage),
+                Some(Err(err)) => return Err(err),
+                None => return Err(LinkError::Io(std::io::ErrorKind::UnexpectedEof.into())),
+            },
+            Some(id) = cancels.recv() => {
+                if in_flight.remove(&id).is_some() {
+                    writer.send(&Message::Cancel { id }).await?;
+                }
+            }
+        }
+    }
+}
+
+/// Answers a request forwarded over the link with the response of the driver, or cancels
+/// it if its response channel is closed first.
+async fn correlate(
+    id: u64,
+    remote: oneshot::Receiver<Result<SignedCommitment, Error>>,
+    mut response: oneshot::Sender<Result<SignedCommitment, Error>>,
+    cancels: mpsc::UnboundedSender<u64>,
+) {
+    tokio::select! {
+        result = remote => {
+            let _ = response.send(result.unwrap_or(Err(Error::ServiceUnavailable)));
+        }
+        _ = response.closed() => {
+            let _ = cancels.send(id);
+        }
+    }
+}
+
+/// Accepts the links of commitments API processes, and hands their requests to the driver
+/// over the given channel.
+pub async fn serve(listener: TcpListener, secret: LinkSecret, events: mpsc::Sender<Event>) {
+    loop {
+        let (mut stream, peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(err) => {
+                warn!(?err, "Failed to accept a link connection");
+                continue;
+            }
+        };
+
+        let (secret, events) = (secret.clone(), events.clone());
+        tokio::spawn(async move {
+            let handshake = tokio::time::timeout(HANDSHAKE_TIMEOUT, accept(&mut stream, &secret));
+            let key = match handshake.await.unwrap_or(Err(LinkError::HandshakeTimeout)) {
+                Ok(key) => key,
+                Err(err) => {
+                    warn!(%peer, ?err, "Rejected a link connection");
+                    return;
+                }
+            };
+
+            info!(%peer, "Commitments API connected over the link");
+            if let Err(err) = dispatch(stream, key, events).await {
+                warn!(%peer, ?err, "Link to the commitments API dropped");
+            }
+        });
+    }
+}
+
+/// Hands the requests received over an established link to the driver, until the driver is
+/// gone or the link fails.
+async fn dispatch(
+    stream: TcpStream,
+    key: B256,
+    events: mpsc::Sender<Event>,
+) -> Result<(), LinkError> {
+    stream.set_nodelay(true)?;
+    let (reader, writer) = stream.into_split();
+    let mut frames = FrameReader { inner: reader, key, role: Role::Api, seq: 0 }.spawn();
+    let mut writer = FrameWriter { inner: writer, key, role: Role::Driver, seq: 0 };
+
+    // Dropping the cancellation sender of a request drops its response channel
+    let mut cancels = HashMap::new();
+    let (responses_tx, mut responses) = mpsc::unbounded_channel();
+
+    loop {
+        tokio::select! {
+            frame = frames.recv() => match frame {
+                Some(Ok(Message::Request {
+                    id,
+                    mut request,
+                    signature,
+                    signer,
+                    request_id,
+                    quotas,
+                })) => {
+                    if let Some(signature) =
+                        signature.and_then(|sig| Signature::try_from(sig.as_ref()).ok())
+                    {
+                        request.set_signature(signature);
+                    }
+                    if let Some(signer) = signer {
+                        request.set_signer(signer);
+                    }
+
+                    // Waiting for room in the channel of the driver stops reading the link
+                    let (response, result) = oneshot::channel();
+                    let request_id = request_id.unwrap_or_else(RequestId::generate);
+                    let event = Event { request, response, request_id, quotas };
+                    if events.send(event).await.is_err() {
+                        debug!("Driver is gone, closing the link");
+                        return Ok(());
+                    }
+
+                    let (cancel_tx, cancel_rx) = oneshot::channel::<()>();
+                    cancels.insert(id, cancel_tx);
+                    tokio::spawn(relay(id, result, cancel_rx, responses_tx.clone()));
+                }
+                Some(Ok(Message::Cancel { id })) => {
+                    cancels.remove(&id);
+                }
+                Some(Ok(_)) => return Err(LinkError::UnexpectedMessage),
+                Some(Err(err)) => return Err(err),
+                None => return Err(LinkError::Io(std::io::ErrorKind::UnexpectedEof.into())),
+            },
+            Some((id, outcome)) = responses.recv() => {
+                cancels.remove(&id);
+                writer.send(&Message::Response { id, outcome }).await?;
+            }
+        }
+    }
+}
+
+/// Sends the response of the driver to a request back over the link, unless the request is
+/// cancelled first.
+async fn relay(
+    id: u64,
+    result: oneshot::Receiver<Result<SignedCommitment, Error>>,
+    cancel: oneshot::Receiver<()>,
+    responses: mpsc::UnboundedSender<(u64, Outcome)>,
+) {
+    tokio::select! {
+        result = result => {
+            let outcome = match result {
+                Ok(Ok(commitment)) => Outcome::Commitment(commitment),
+                Ok(Err(err)) => Outcome::Error(err.into_json_error()),
+                Err(_) => Outcome::Error(Error::ServiceUnavailable.into_json_error()),
+            };
+            let _ = responses.send((id, outcome));
+        }
+        // Dropping the receiver closes the response channel, as on the API side
+        _ = cancel => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::signers::{local::PrivateKeySigner, Signer};
+    use serde_json::json;
+
+    use crate::{
+        commitments::{jsonrpc::JsonResponse, spec::SIGNATURE_HEADER},
+        primitives::commitment::ECDSASignatureExt,
+        state::ValidationError,
+        test_util::{create_signed_commitment_request, default_test_transaction, DeterministicEnv},
+    };
+
+    use super::*;
+
+    /// Sends a signed inclusion request to the commitments API at the given address.
+    async fn request_inclusion(env: &DeterministicEnv, addr: &str, nonce: u64) -> JsonResponse {
+        let sk = env.secret_key();
+        let signer = PrivateKeySigner::from(sk.clone());
+        let tx = default_test_transaction(signer.address(), Some(nonce));
+        let req = create_signed_commitment_request(&[tx], &sk, 12).await.unwrap();
+        let sig = req.signature().unwrap().to_hex();
+
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "bolt_requestInclusion",
+            "params": [req]
+        });
+        reqwest::Client::new()
+            .post(format!("http://{addr}"))
+            .header(SIGNATURE_HEADER, format!("{}:{}", signer.address(), sig))
+            .json(&payload)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_split_deployment() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+        let secret = LinkSecret::new(B256::repeat_byte(7));
+
+        // The driver half, listening for the link
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let link_addr = listener.local_addr().unwrap().to_string();
+        let (driver_tx, mut driver_rx) = mpsc::channel(1);
+        tokio::spawn(serve(listener, secret.clone(), driver_tx));
+
+        // The API half, forwarding its events over the link
+        let mut server = env.server();
+        let (events_tx, events_rx) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let addr = server.local_addr().to_string();
+        tokio::spawn(LinkClient::new(link_addr.clone(), secret).run(events_rx));
+
+        let commitment_signer = env.signer();
+        let driver = tokio::spawn(async move {
+            // The first request is committed to, with its signer authenticated by the API
+            let Event { request, response, .. } = driver_rx.recv().await.unwrap();
+            assert!(request.signer().is_some() && request.signature().is_some());
+            let commitment = request.commit_and_sign(&commitment_signer).await.unwrap();
+            response.send(Ok(commitment.clone())).unwrap();
+
+            // The second one is rejected
+            let event = driver_rx.recv().await.unwrap();
+            event.response.send(Err(ValidationError::NonceTooLow(2, 1).into())).unwrap();
+            commitment
+        });
+
+        let committed = request_inclusion(&env, &addr, 0).await;
+        let rejected = request_inclusion(&env, &addr, 1).await;
+        let commitment = driver.await.unwrap();
+
+        assert!(committed.error.is_none(), "{:?}", committed.error);
+        assert_eq!(committed.result, serde_json::to_value(commitment).unwrap());
+
+        // The error of the driver is served as if it was in the same process
+        let error = rejected.error.unwrap();
+        assert_eq!(error.code, -32006);
+        assert_eq!(error.data.unwrap()["reason"], "nonce_too_low");
+
+        // A client with the wrong secret can't connect, and its requests are rejected
+        let mut server = env.server();
+        let (events_tx, events_rx) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let wrong = LinkSecret::new(B256::repeat_byte(8));
+        tokio::spawn(LinkClient::new(link_addr, wrong).run(events_rx));
+
+        let response = request_inclusion(&env, &server.local_addr().to_string(), 2).await;
+        assert_eq!(response.error.unwrap().code, -32014);
+    }
+
+    #[tokio::test]
+    async fn test_reconnect() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+        let secret = LinkSecret::new(B256::repeat_byte(7));
+
+        // Pick a free port for the driver, which isn't listening yet
+        let link_addr = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+
+        let mut server = env.server();
+        let (events_tx, events_rx) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let addr = server.local_addr().to_string();
+        tokio::spawn(LinkClient::new(link_addr.to_string(), secret.clone()).run(events_rx));
+
+        // Requests are rejected while the link is down
+        let response = request_inclusion(&env, &addr, 0).await;
+        assert_eq!(response.error.unwrap().code, -32014);
+
+        let listener = TcpListener::bind(link_addr).await.unwrap();
+        let (driver_tx, mut driver_rx) = mpsc::channel(1);
+        tokio::spawn(serve(listener, secret, driver_tx));
+        let commitment_signer = env.signer();
+        tokio::spawn(async move {
+            while let Some(Event { request, response, .. }) = driver_rx.recv().await {
+                let commitment = request.commit_and_sign(&commitment_signer).await.unwrap();
+                let _ = response.send(Ok(commitment));
+            }
+        });
+
+        // And served once the client has reconnected
+        let mut response = request_inclusion(&env, &addr, 0).await;
+        for _ in 0..50 {
+            if response.error.is_none() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            response = request_inclusion(&env, &addr, 0).await;
+        }
+        assert!(response.error.is_none(), "{:?}", response.error);
+    }
+}
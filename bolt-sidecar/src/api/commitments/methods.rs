@@ -0,0 +1,1592 @@
+use std::{collections::HashMap, fmt, net::IpAddr, sync::Arc};
+
+use alloy::{
+    primitives::{Address, Signature, B256},
+    sol_types::Eip712Domain,
+};
+use axum::{http::HeaderMap, Json};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+#[cfg(feature = "admin")]
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+use tracing::{error, info, warn, Span};
+
+use crate::{
+    common::CARGO_PKG_VERSION,
+    inclusion::CommitmentStatus,
+    primitives::{
+        commitment::{ExclusionCommitment, InclusionCommitment},
+        ExclusionRequest, InclusionRequest, Slot,
+    },
+    state::{clock::TimeSource, SlotManifest},
+};
+#[cfg(feature = "admin")]
+use crate::{
+    crypto::external::ImportError,
+    drops::DroppedCommitment,
+    earnings::{AnnotatedFee, EarningsReport},
+    state::maintenance::{DeclaredWindow, MaintenanceDeclaration, MaintenanceWindow},
+};
+
+use super::{
+    audit::{AuditEntry, AuditSubject},
+    erc1271::ContractSignatureCache,
+    jsonrpc::{JsonPayload, JsonResponse},
+    openrpc::{self, ContentDescriptor, MethodObject, OpenRpcDocument, Schema},
+    profile::MethodProfile,
+    relay::RelayAttestation,
+    request_id::RequestId,
+    server::{auth_from_headers, CancelEvent, CommitmentsApiInner},
+    spec::{
+        CancelError, CancelOutcome, ChainInfo, CommitmentDigest, CommitmentQuery, CommitmentsApi,
+        Error, InclusionCancellation, RejectionError, SidecarMetadata, SidecarStatus,
+        SignatureHeader, SignatureScheme, VersionInfo, CANCEL_INCLUSION_METHOD, DISCOVER_METHOD,
+        EIP712_SCHEME, GET_CHAIN_INFO_METHOD, GET_COMMITMENT_METHOD, GET_COMMITMENT_STATUS_METHOD,
+        GET_SLOT_MANIFEST_METHOD, GET_STATUS_METHOD, GET_VERSION_METHOD, METADATA_METHOD,
+        REQUEST_EXCLUSION_METHOD, REQUEST_INCLUSION_METHOD,
+    },
+};
+#[cfg(feature = "admin")]
+use super::{
+    capture::CaptureFilter,
+    server::{DropAction, DropEvent, ImportEvent, MaintenanceEvent, RebindEvent},
+    spec::{
+        CertificateReload, ContextQuery, EarningsRange, ImportSignedCommitment, ListenerAddrs,
+        ListenerRebind, MaintenanceId, CANCEL_MAINTENANCE_METHOD, CONFIRM_DROP_METHOD,
+        DECLARE_MAINTENANCE_METHOD, DISABLE_CAPTURE_METHOD, DROP_COMMITMENT_METHOD,
+        ENABLE_CAPTURE_METHOD, FIND_COMMITMENTS_BY_CONTEXT_METHOD, GET_EARNINGS_METHOD,
+        GET_WHITELIST_METHOD, IMPORT_SIGNED_COMMITMENT_METHOD, REBIND_LISTENER_METHOD,
+        RELOAD_CERTIFICATES_METHOD, RESTORE_COMMITMENT_METHOD,
+    },
+    whitelist::Whitelist,
+};
+
+/// The authenticated signer of a request, from its signature header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestSigner {
+    /// The address the request is attributed to: the one recovered from the signature, which
+    /// matches the one in the header, unless the request was relayed by a gateway that
+    /// isn't trusted to attest it.
+    pub address: Address,
+    /// The signature over the digest of the params, or over their EIP-712 digest for
+    /// typed-data signatures.
+    pub signature: Signature,
+}
+
+/// The context of a JSON-RPC request, passed to the method handlers along with the params.
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    /// The id of the request.
+    pub id: Option<Value>,
+    /// The id correlating the logs of the call, echoed to the client.
+    pub request_id: RequestId,
+    /// The authenticated signer, set for methods that require authentication.
+    pub signer: Option<RequestSigner>,
+    /// The IP address of the client, if known.
+    pub client_ip: Option<IpAddr>,
+    /// The tracing span of the request, to instrument work spawned by the handler.
+    pub span: Span,
+    /// The methods exposed by the listener that received the request.
+    pub profile: Arc<MethodProfile>,
+    /// The contract signature checks made for the HTTP request or message of the call.
+    pub contract_signatures: ContractSignatureCache,
+}
+
+impl RequestContext {
+    /// Creates the context of an unauthenticated request received on a listener with the
+    /// given profile, in the current span, with a new request id.
+    pub fn new(id: Option<Value>, profile: Arc<MethodProfile>) -> Self {
+        let request_id = RequestId::generate();
+        Self {
+            id,
+            request_id,
+            signer: None,
+            client_ip: None,
+            span: Span::current(),
+            profile,
+            contract_signatures: ContractSignatureCache::default(),
+        }
+    }
+}
+
+/// The typed params of a method, parsed from the positional JSON-RPC params.
+pub trait FromParams: Sized {
+    /// Parses the params, rejecting them as a validation failure if they are invalid.
+    fn from_params(params: Vec<Value>) -> Result<Self, Error>;
+}
+
+/// Methods without params ignore any params they are called with.
+impl FromParams for () {
+    fn from_params(_: Vec<Value>) -> Result<Self, Error> {
+        Ok(())
+    }
+}
+
+/// Parses the first positional param, the only one of the methods that take params.
+fn first_param<T: DeserializeOwned>(params: Vec<Value>) -> Result<T, Error> {
+    let Some(param) = params.into_iter().next() else {
+        return Err(RejectionError::MissingParams { expected: 1, got: 0 }.into());
+    };
+
+    serde_json::from_value(param)
+        .map_err(|e| RejectionError::InvalidParams { index: 0, message: e.to_string() }.into())
+}
+
+impl FromParams for InclusionCancellation {
+    fn from_params(params: Vec<Value>) -> Result<Self, Error> {
+        first_param(params)
+    }
+}
+
+impl FromParams for CommitmentQuery {
+    fn from_params(params: Vec<Value>) -> Result<Self, Error> {
+        first_param(params)
+    }
+}
+
+impl FromParams for InclusionRequest {
+    fn from_params(params: Vec<Value>) -> Result<Self, Error> {
+        first_param(params)
+    }
+}
+
+#[cfg(feature = "admin")]
+impl FromParams for CaptureFilter {
+    fn from_params(params: Vec<Value>) -> Result<Self, Error> {
+        first_param(params)
+    }
+}
+
+#[cfg(feature = "admin")]
+impl FromParams for EarningsRange {
+    fn from_params(params: Vec<Value>) -> Result<Self, Error> {
+        first_param(params)
+    }
+}
+
+#[cfg(feature = "admin")]
+impl FromParams for ContextQuery {
+    fn from_params(params: Vec<Value>) -> Result<Self, Error> {
+        first_param(params)
+    }
+}
+
+#[cfg(feature = "admin")]
+impl FromParams for ImportSignedCommitment {
+    fn from_params(params: Vec<Value>) -> Result<Self, Error> {
+        first_param(params)
+    }
+}
+
+#[cfg(feature = "admin")]
+impl FromParams for ListenerRebind {
+    fn from_params(params: Vec<Value>) -> Result<Self, Error> {
+        first_param(params)
+    }
+}
+
+impl FromParams for CommitmentDigest {
+    fn from_params(params: Vec<Value>) -> Result<Self, Error> {
+        first_param(params)
+    }
+}
+
+#[cfg(feature = "admin")]
+impl FromParams for MaintenanceWindow {
+    fn from_params(params: Vec<Value>) -> Result<Self, Error> {
+        first_param(params)
+    }
+}
+
+#[cfg(feature = "admin")]
+impl FromParams for MaintenanceId {
+    fn from_params(params: Vec<Value>) -> Result<Self, Error> {
+        first_param(params)
+    }
+}
+
+/// The slot is the only param of the methods that take one.
+impl FromParams for Slot {
+    fn from_params(params: Vec<Value>) -> Result<Self, Error> {
+        first_param(params)
+    }
+}
+
+/// Without params, all the certificates are reloaded.
+#[cfg(feature = "admin")]
+impl FromParams for CertificateReload {
+    fn from_params(params: Vec<Value>) -> Result<Self, Error> {
+        if params.is_empty() {
+            return Ok(Self::default());
+        }
+        first_param(params)
+    }
+}
+
+/// A JSON-RPC method of the commitments API.
+///
+/// The [`MethodRegistry`] takes care of everything before the handler runs, in the same
+/// order for every method: the readiness checks, the authentication of the request
+/// signer and the parsing of the params. Handlers only receive typed, validated inputs.
+#[async_trait::async_trait]
+pub trait Method: Send + Sync + 'static {
+    /// The name of the method.
+    const NAME: &'static str;
+    /// Whether the request must be signed, see [`Method::signed_digest`].
+    const REQUIRES_AUTH: bool = false;
+    /// Whether the sidecar must be warmed up and synced to serve the method.
+    const REQUIRES_READY: bool = false;
+    /// A short summary of the method, for the [OpenRPC document](super::openrpc).
+    const SUMMARY: &'static str = "";
+
+    /// The params of the method.
+    type Params: FromParams + Send;
+    /// The result of the method.
+    type Output: Serialize + Send;
+
+    /// Returns the digest of the params signed by the request signer on the chain with the
+    /// given id, for methods that require authentication.
+    fn signed_digest(_params: &Self::Params, _chain_id: u64) -> Option<B256> {
+        None
+    }
+
+    /// Returns the digest the request signer signed before [`Method::signed_digest`] was
+    /// scoped to the chain, for the methods whose digest changed. It is only accepted during
+    /// the deprecation window of the legacy digests.
+    fn legacy_digest(_params: &Self::Params) -> Option<B256> {
+        None
+    }
+
+    /// Returns the EIP-712 digest of the params in the given domain, for the methods that
+    /// accept typed-data signatures. The other ones reject them.
+    fn typed_digest(_params: &Self::Params, _domain: &Eip712Domain) -> Option<B256> {
+        None
+    }
+
+    /// Returns the commitment request in the params, for the methods whose calls are logged
+    /// in the audit log.
+    fn audit_subject(_params: &Self::Params) -> Option<AuditSubject> {
+        None
+    }
+
+    /// Describes the params of the method in the OpenRPC document.
+    fn params_schema() -> Vec<ContentDescriptor> {
+        Vec::new()
+    }
+
+    /// Describes the result of the method in the OpenRPC document, which is any value
+    /// unless specified.
+    fn result_schema() -> ContentDescriptor {
+        ContentDescriptor::optional("result", json!({}))
+    }
+
+    /// Handles a request.
+    async fn handle(
+        &self,
+        api: &CommitmentsApiInner,
+        ctx: &RequestContext,
+        params: Self::Params,
+    ) -> Result<Self::Output, Error>;
+}
+
+/// A type-erased [`Method`], as stored in the [`MethodRegistry`].
+#[async_trait::async_trait]
+trait Handler: Send + Sync {
+    async fn call(
+        &self,
+        api: &CommitmentsApiInner,
+        ctx: RequestContext,
+        headers: &HeaderMap,
+        params: Vec<Value>,
+    ) -> Result<Value, Error>;
+
+    fn describe(&self) -> MethodObject;
+}
+
+#[async_trait::async_trait]
+impl<M: Method> Handler for M {
+    async fn call(
+        &self,
+        api: &CommitmentsApiInner,
+        mut ctx: RequestContext,
+        headers: &HeaderMap,
+        params: Vec<Value>,
+    ) -> Result<Value, Error> {
+        if M::REQUIRES_READY {
+            if api.readiness.is_shutting_down() {
+                return Err(Error::ShuttingDown);
+            }
+            if !api.readiness.is_warmed_up() {
+                return Err(Error::NotReady);
+            }
+            if !api.readiness.is_synced() {
+                return Err(Error::NotSynced);
+            }
+        }
+
+        let auth = if M::REQUIRES_AUTH {
+            let auth = auth_from_headers(headers).inspect_err(|e| {
+                error!("Failed to extract signature from headers: {:?}", e);
+            })?;
+            let attestation = RelayAttestation::from_headers(headers).inspect_err(|e| {
+                error!("Failed to extract relay attestation from headers: {:?}", e);
+            })?;
+            Some((auth, attestation))
+        } else {
+            None
+        };
+
+        let params = M::Params::from_params(params)?;
+        let audited = api.audit.as_ref().and_then(|log| Some((log, M::audit_subject(&params)?)));
+
+        let result = async {
+            if let Some((header, attestation)) = auth {
+                let (digest, legacy) = match header.scheme {
+                    SignatureScheme::Raw => {
+                        let digest =
+                            M::signed_digest(&params, api.chain_id()).ok_or(Error::Internal)?;
+                        (digest, M::legacy_digest(&params).filter(|_| api.accept_legacy_digests))
+                    }
+                    SignatureScheme::Eip712 => {
+                        let digest =
+                            M::typed_digest(&params, &api.eip712_domain()).ok_or_else(|| {
+                                Error::UnsupportedSignatureScheme(EIP712_SCHEME.to_string())
+                            })?;
+                        (digest, None)
+                    }
+                };
+                let signatures = &ctx.contract_signatures;
+                let signer = authenticate(api, signatures, header, digest, legacy, attestation);
+                ctx.signer = Some(signer.await?);
+            }
+
+            let output = self.handle(api, &ctx, params).await?;
+            serde_json::to_value(output).map_err(|err| {
+                error!(?err, method = M::NAME, "Failed to serialize result");
+                Error::Internal
+            })
+        }
+        .await;
+
+        // Recorded once the request is decided on, whether it was authenticated or not
+        if let Some((log, subject)) = audited {
+            let (request_id, signer) = (ctx.request_id.clone(), ctx.signer.map(|s| s.address));
+            let now = api.clock.now();
+            log.record(AuditEntry::new(now, request_id, M::NAME, signer, subject, &result));
+        }
+        result
+    }
+
+    fn describe(&self) -> MethodObject {
+        MethodObject {
+            name: M::NAME.to_string(),
+            summary: M::SUMMARY.to_string(),
+            params: M::params_schema(),
+            result: M::result_schema(),
+        }
+    }
+}
+
+/// Checks that the signature over the digest was made by the signer in the header, and
+/// that the signer the request is attributed to is whitelisted by an entry that hasn't
+/// expired.
+///
+/// If EIP-1271 signatures are accepted, a signature that wasn't made by the signer is checked
+/// by the contract at its address instead, once per HTTP request.
+///
+/// If the replay protection is enabled, the signature must be over the timestamped digest,
+/// with a timestamp within the window, and is rejected if it was already seen. The timestamp
+/// is checked before the signature, and the signature is only recorded once it's valid.
+///
+/// Requests relayed by a gateway are attributed according to its attestation, see
+/// [`TrustedGateways::attribute`](super::relay::TrustedGateways::attribute).
+///
+/// If a legacy digest is given, a signature over it is accepted in place of one over the
+/// digest, with a warning.
+async fn authenticate(
+    api: &CommitmentsApiInner,
+    signatures: &ContractSignatureCache,
+    header: SignatureHeader,
+    digest: B256,
+    legacy_digest: Option<B256>,
+    attestation: Option<RelayAttestation>,
+) -> Result<RequestSigner, Error> {
+    let SignatureHeader { signer, signature, timestamp_ms, .. } = header;
+    if let Some(ref guard) = api.replay_guard {
+        guard.check_timestamp(timestamp_ms, api.clock.now())?;
+    }
+
+    let mut digest = header.signed_digest(digest);
+    let signed_by = |digest: &B256| signature.recover_address_from_prehash(digest).ok();
+    if let Some(legacy) = legacy_digest.map(|legacy| header.signed_digest(legacy)) {
+        if signed_by(&digest) != Some(signer) && signed_by(&legacy) == Some(signer) {
+            warn!(?signer, "Request signed over its legacy digest, not scoped to the chain");
+            digest = legacy;
+        }
+    }
+    let recovered = signature.recover_address_from_prehash(&digest);
+
+    let recovered_signer = match (recovered, &api.erc1271) {
+        (Ok(recovered_signer), _) if recovered_signer == signer => recovered_signer,
+        (_, Some(verifier)) => {
+            signatures.verify(verifier, signer, digest, &signature).await?;
+            signer
+        }
+        (Ok(recovered_signer), None) => {
+            error!(
+                ?recovered_signer,
+                ?signer,
+                "Recovered signer does not match the provided signer"
+            );
+            return Err(Error::InvalidSignature(crate::primitives::SignatureError));
+        }
+        (Err(err), None) => return Err(err.into()),
+    };
+
+    if let (Some(guard), Some(timestamp_ms)) = (&api.replay_guard, timestamp_ms) {
+        guard.record(signer, digest, timestamp_ms)?;
+    }
+
+    let now = api.clock.unix_secs();
+    let address = match attestation {
+        Some(ref attestation) => {
+            api.trusted_gateways.attribute(attestation, recovered_signer, digest, now)?
+        }
+        None => recovered_signer,
+    };
+
+    if let Some(ref whitelist) = api.whitelist {
+        whitelist.read().authorize(address, now)?;
+    }
+
+    Ok(RequestSigner { address, signature })
+}
+
+/// The methods served by a listener, by name.
+#[derive(Default)]
+pub struct MethodRegistry {
+    methods: HashMap<&'static str, Box<dyn Handler>>,
+}
+
+impl fmt::Debug for MethodRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MethodRegistry").field("methods", &self.names()).finish()
+    }
+}
+
+impl MethodRegistry {
+    /// The methods of the public and internal listeners.
+    pub fn rpc() -> Self {
+        Self::default()
+            .register(GetVersion)
+            .register(GetChainInfo)
+            .register(GetStatus)
+            .register(RequestInclusion)
+            .register(RequestExclusion)
+            .register(GetSlotManifest)
+            .register(GetCommitment)
+            .register(GetCommitmentStatus)
+            .register(CancelInclusion)
+            .register(Metadata)
+            .register(Discover)
+    }
+
+    /// The methods of the admin listener.
+    #[cfg(feature = "admin")]
+    pub fn admin() -> Self {
+        Self::default()
+            .register(EnableCapture)
+            .register(DisableCapture)
+            .register(GetEarnings)
+            .register(FindCommitmentsByContext)
+            .register(AdminImportSignedCommitment)
+            .register(ReloadCertificates)
+            .register(RebindListener)
+            .register(GetWhitelist)
+            .register(DropCommitment)
+            .register(ConfirmDrop)
+            .register(RestoreCommitment)
+            .register(GetSlotManifest)
+            .register(DeclareMaintenance)
+            .register(CancelMaintenance)
+    }
+
+    /// Registers a method, replacing any method with the same name.
+    pub fn register<M: Method>(mut self, method: M) -> Self {
+        self.methods.insert(M::NAME, Box::new(method));
+        self
+    }
+
+    /// Returns the names of the registered methods, in alphabetical order.
+    pub fn names(&self) -> Vec<&'static str> {
+        let mut names: Vec<_> = self.methods.keys().copied().collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Returns the OpenRPC descriptions of the registered methods, in alphabetical order.
+    pub fn describe(&self) -> Vec<MethodObject> {
+        self.names().into_iter().map(|name| self.methods[name].describe()).collect()
+    }
+
+    /// Dispatches a JSON-RPC request to the matching method.
+    ///
+    /// Methods that are not exposed by the listener's profile are rejected before their
+    /// params are parsed, the same way as nonexistent methods. The contract signature
+    /// checks are shared with the other calls of the same HTTP request.
+    pub async fn dispatch(
+        &self,
+        api: &CommitmentsApiInner,
+        profile: Arc<MethodProfile>,
+        headers: &HeaderMap,
+        payload: JsonPayload,
+        client_ip: Option<IpAddr>,
+        contract_signatures: ContractSignatureCache,
+    ) -> Result<Json<JsonResponse>, Error> {
+        if !profile.allows(&payload.method) {
+            error!("Method not exposed: {}", payload.method);
+            return Err(Error::UnknownMethod);
+        }
+
+        let Some(handler) = self.methods.get(payload.method.as_str()) else {
+            error!("Unknown method: {}", payload.method);
+            return Err(Error::UnknownMethod);
+        };
+
+        let request_id = RequestId::from_headers(headers);
+        let ctx = RequestContext {
+            request_id,
+            client_ip,
+            contract_signatures,
+            ..RequestContext::new(payload.id, profile)
+        };
+        let id = ctx.id.clone();
+        let result = handler.call(api, ctx, headers, payload.params).await?;
+
+        Ok(Json(JsonResponse { id, result, ..Default::default() }))
+    }
+}
+
+/// `bolt_getVersion`: the version of the sidecar and the methods of the listener.
+#[derive(Debug)]
+pub struct GetVersion;
+
+#[async_trait::async_trait]
+impl Method for GetVersion {
+    const NAME: &'static str = GET_VERSION_METHOD;
+    const SUMMARY: &'static str = "Returns the version of the sidecar and the methods served";
+    type Params = ();
+    type Output = VersionInfo;
+
+    async fn handle(
+        &self,
+        api: &CommitmentsApiInner,
+        ctx: &RequestContext,
+        _: (),
+    ) -> Result<VersionInfo, Error> {
+        Ok(VersionInfo {
+            version: format!("bolt-sidecar-v{CARGO_PKG_VERSION}"),
+            methods: ctx.profile.methods(),
+            capabilities: api.capabilities(),
+        })
+    }
+}
+
+/// `rpc.discover`: the OpenRPC document of the methods of the listener.
+#[derive(Debug)]
+pub struct Discover;
+
+#[async_trait::async_trait]
+impl Method for Discover {
+    const NAME: &'static str = DISCOVER_METHOD;
+    const SUMMARY: &'static str = "Returns the OpenRPC document of the methods of the listener";
+    type Params = ();
+    type Output = OpenRpcDocument;
+
+    async fn handle(
+        &self,
+        _: &CommitmentsApiInner,
+        ctx: &RequestContext,
+        _: (),
+    ) -> Result<OpenRpcDocument, Error> {
+        Ok(openrpc::document_for(&ctx.profile))
+    }
+}
+
+/// `bolt_getChainInfo`: the chain and timing parameters of the sidecar.
+#[derive(Debug)]
+pub struct GetChainInfo;
+
+#[async_trait::async_trait]
+impl Method for GetChainInfo {
+    const NAME: &'static str = GET_CHAIN_INFO_METHOD;
+    const SUMMARY: &'static str = "Returns the chain and timing parameters of the sidecar";
+    type Params = ();
+    type Output = ChainInfo;
+
+    async fn handle(
+        &self,
+        api: &CommitmentsApiInner,
+        _: &RequestContext,
+        _: (),
+    ) -> Result<ChainInfo, Error> {
+        Ok(api.chain_info())
+    }
+}
+
+/// `bolt_metadata`: the limits of the sidecar and the budget of the next slot requests can
+/// target.
+#[derive(Debug)]
+pub struct Metadata;
+
+#[async_trait::async_trait]
+impl Method for Metadata {
+    const NAME: &'static str = METADATA_METHOD;
+    const SUMMARY: &'static str = "Returns the limits of the sidecar and the next slot budget";
+    type Params = ();
+    type Output = SidecarMetadata;
+
+    async fn handle(
+        &self,
+        api: &CommitmentsApiInner,
+        _: &RequestContext,
+        _: (),
+    ) -> Result<SidecarMetadata, Error> {
+        Ok(api.metadata())
+    }
+}
+
+/// `bolt_getStatus`: the runtime status of the sidecar.
+#[derive(Debug)]
+pub struct GetStatus;
+
+#[async_trait::async_trait]
+impl Method for GetStatus {
+    const NAME: &'static str = GET_STATUS_METHOD;
+    const SUMMARY: &'static str = "Returns the runtime status of the sidecar";
+    type Params = ();
+    type Output = SidecarStatus;
+
+    async fn handle(
+        &self,
+        api: &CommitmentsApiInner,
+        _: &RequestContext,
+        _: (),
+    ) -> Result<SidecarStatus, Error> {
+        Ok(api.status())
+    }
+}
+
+/// `bolt_getSlotManifest`: the obligations of our proposer in a slot, for the block builder.
+/// Returns null if we have no commitment for the slot.
+#[derive(Debug)]
+pub struct GetSlotManifest;
+
+#[async_trait::async_trait]
+impl Method for GetSlotManifest {
+    const NAME: &'static str = GET_SLOT_MANIFEST_METHOD;
+    const SUMMARY: &'static str = "Returns the obligations of our proposer in a slot";
+    type Params = Slot;
+    type Output = Option<SlotManifest>;
+
+    fn params_schema() -> Vec<ContentDescriptor> {
+        vec![ContentDescriptor::required("slot", json!({ "type": "integer", "minimum": 0 }))]
+    }
+
+    async fn handle(
+        &self,
+        api: &CommitmentsApiInner,
+        _: &RequestContext,
+        slot: Slot,
+    ) -> Result<Option<SlotManifest>, Error> {
+        Ok(api.manifests.get(slot).map(|manifest| SlotManifest::clone(&manifest)))
+    }
+}
+
+/// `bolt_getCommitment`: a commitment signed for a client that disconnected or whose
+/// response expired. Returns null if no such commitment is kept for the request.
+#[derive(Debug)]
+pub struct GetCommitment;
+
+#[async_trait::async_trait]
+impl Method for GetCommitment {
+    const NAME: &'static str = GET_COMMITMENT_METHOD;
+    const SUMMARY: &'static str = "Returns a signed commitment whose response wasn't delivered";
+    type Params = CommitmentDigest;
+    type Output = Option<InclusionCommitment>;
+
+    fn params_schema() -> Vec<ContentDescriptor> {
+        vec![ContentDescriptor::required("query", openrpc::digest_schema())]
+    }
+
+    fn result_schema() -> ContentDescriptor {
+        let schema = json!({ "oneOf": [InclusionCommitment::reference(), { "type": "null" }] });
+        ContentDescriptor::optional("commitment", schema)
+    }
+
+    async fn handle(
+        &self,
+        api: &CommitmentsApiInner,
+        _: &RequestContext,
+        CommitmentDigest { digest }: CommitmentDigest,
+    ) -> Result<Option<InclusionCommitment>, Error> {
+        Ok(api.pending.orphaned(&digest))
+    }
+}
+
+/// `bolt_getCommitmentStatus`: whether the transactions of a commitment were included in its
+/// target slot, as seen by the driver.
+#[derive(Debug)]
+pub struct GetCommitmentStatus;
+
+#[async_trait::async_trait]
+impl Method for GetCommitmentStatus {
+    const NAME: &'static str = GET_COMMITMENT_STATUS_METHOD;
+    const SUMMARY: &'static str = "Returns whether the transactions of a commitment were included";
+    type Params = CommitmentQuery;
+    type Output = CommitmentStatus;
+
+    async fn handle(
+        &self,
+        api: &CommitmentsApiInner,
+        _: &RequestContext,
+        query: CommitmentQuery,
+    ) -> Result<CommitmentStatus, Error> {
+        Ok(match query {
+            CommitmentQuery::Digest { digest } => api.inclusions.status(&digest),
+            CommitmentQuery::CommitmentId { commitment_id } => {
+                api.inclusions.status_of_commitment(&commitment_id)
+            }
+            CommitmentQuery::Transaction { tx_hash, slot } => {
+                api.inclusions.status_of_transaction(tx_hash, slot)
+            }
+        })
+    }
+}
+
+/// `bolt_cancelInclusion`: cancels a commitment whose constraints weren't submitted yet,
+/// on behalf of the signer of its request.
+#[derive(Debug)]
+pub struct CancelInclusion;
+
+#[async_trait::async_trait]
+impl Method for CancelInclusion {
+    const NAME: &'static str = CANCEL_INCLUSION_METHOD;
+    const REQUIRES_AUTH: bool = true;
+    const SUMMARY: &'static str = "Cancels a commitment whose constraints weren't submitted yet";
+    type Params = InclusionCancellation;
+    type Output = CancelOutcome;
+
+    fn params_schema() -> Vec<ContentDescriptor> {
+        vec![ContentDescriptor::required("cancellation", openrpc::commitment_key_schema())]
+    }
+
+    fn signed_digest(params: &InclusionCancellation, _: u64) -> Option<B256> {
+        Some(params.signed_digest())
+    }
+
+    async fn handle(
+        &self,
+        api: &CommitmentsApiInner,
+        ctx: &RequestContext,
+        cancellation: InclusionCancellation,
+    ) -> Result<CancelOutcome, Error> {
+        let signer = ctx.signer.ok_or(Error::NoSignature)?;
+        let Some(ref cancellations) = api.cancellations else {
+            return Err(CancelError::NotEnabled.into());
+        };
+        let digest = match cancellation {
+            InclusionCancellation::Digest { digest } => digest,
+            InclusionCancellation::CommitmentId { commitment_id } => api
+                .inclusions
+                .digest_of(&commitment_id)
+                .ok_or(CancelError::UnknownCommitment(commitment_id))?,
+        };
+
+        let (response_tx, response_rx) = oneshot::channel();
+        let event = CancelEvent { digest, signer: signer.address, response: response_tx };
+        cancellations.send(event).await.map_err(|_| Error::Internal)?;
+        let outcome = response_rx.await.map_err(|_| Error::Internal)??;
+
+        info!(signer = ?signer.address, %digest, ?outcome, "Processed inclusion cancellation");
+        Ok(outcome)
+    }
+}
+
+/// `bolt_requestInclusion`: requests a commitment to include transactions at a slot.
+#[derive(Debug)]
+pub struct RequestInclusion;
+
+#[async_trait::async_trait]
+impl Method for RequestInclusion {
+    const NAME: &'static str = REQUEST_INCLUSION_METHOD;
+    const REQUIRES_AUTH: bool = true;
+    const REQUIRES_READY: bool = true;
+    const SUMMARY: &'static str = "Requests a commitment to include transactions at a slot";
+    type Params = InclusionRequest;
+    type Output = InclusionCommitment;
+
+    fn params_schema() -> Vec<ContentDescriptor> {
+        vec![ContentDescriptor::required("request", InclusionRequest::reference())]
+    }
+
+    fn result_schema() -> ContentDescriptor {
+        ContentDescriptor::optional("commitment", InclusionCommitment::reference())
+    }
+
+    fn signed_digest(request: &InclusionRequest, chain_id: u64) -> Option<B256> {
+        Some(request.chain_digest(chain_id))
+    }
+
+    fn legacy_digest(request: &InclusionRequest) -> Option<B256> {
+        Some(request.digest())
+    }
+
+    fn typed_digest(request: &InclusionRequest, domain: &Eip712Domain) -> Option<B256> {
+        Some(request.typed_digest_in(domain))
+    }
+
+    fn audit_subject(request: &InclusionRequest) -> Option<AuditSubject> {
+        let tx_hashes = request.txs.iter().map(|tx| *tx.hash()).collect();
+        Some(AuditSubject { digest: request.digest(), slot: request.slot, tx_hashes })
+    }
+
+    async fn handle(
+        &self,
+        api: &CommitmentsApiInner,
+        ctx: &RequestContext,
+        mut request: InclusionRequest,
+    ) -> Result<InclusionCommitment, Error> {
+        let signer = ctx.signer.ok_or(Error::NoSignature)?;
+        if let Some(ref limiter) = api.rate_limiter {
+            limiter.check(signer.address, api.clock.now()).inspect_err(|err| {
+                warn!(signer = ?signer.address, "Inclusion request rate limited: {err}");
+            })?;
+        }
+        request.set_signature(signer.signature);
+        request.set_signer(signer.address);
+
+        let (digest, context) = (request.digest(), request.context_hash());
+        let commitment_id = request.commitment_id();
+        info!(
+            signer = ?signer.address,
+            %digest,
+            %commitment_id,
+            ?context,
+            "New valid inclusion request received"
+        );
+        api.request_inclusion(request, ctx.request_id.clone()).await
+    }
+}
+
+/// `bolt_requestExclusion`: requests a commitment to exclude senders and transactions from
+/// the block of a slot.
+#[derive(Debug)]
+pub struct RequestExclusion;
+
+#[async_trait::async_trait]
+impl Method for RequestExclusion {
+    const NAME: &'static str = REQUEST_EXCLUSION_METHOD;
+    const REQUIRES_AUTH: bool = true;
+    const REQUIRES_READY: bool = true;
+    const SUMMARY: &'static str = "Requests a commitment to exclude transactions from a slot";
+    type Params = ExclusionRequest;
+    type Output = ExclusionCommitment;
+
+    fn params_schema() -> Vec<ContentDescriptor> {
+        vec![ContentDescriptor::required("request", ExclusionRequest::reference())]
+    }
+
+    fn result_schema() -> ContentDescriptor {
+        ContentDescriptor::optional("commitment", ExclusionCommitment::reference())
+    }
+
+    fn signed_digest(request: &ExclusionRequest, _: u64) -> Option<B256> {
+        Some(request.digest())
+    }
+
+    fn typed_digest(request: &ExclusionRequest, domain: &Eip712Domain) -> Option<B256> {
+        Some(request.typed_digest_in(domain))
+    }
+
+    fn audit_subject(request: &ExclusionRequest) -> Option<AuditSubject> {
+        let tx_hashes = request.tx_hashes.clone();
+        Some(AuditSubject { digest: request.digest(), slot: request.slot, tx_hashes })
+    }
+
+    async fn handle(
+        &self,
+        api: &CommitmentsApiInner,
+        ctx: &RequestContext,
+        mut request: ExclusionRequest,
+    ) -> Result<ExclusionCommitment, Error> {
+        let signer = ctx.signer.ok_or(Error::NoSignature)?;
+        if let Some(ref limiter) = api.rate_limiter {
+            limiter.check(signer.address, api.clock.now()).inspect_err(|err| {
+                warn!(signer = ?signer.address, "Exclusion request rate limited: {err}");
+            })?;
+        }
+        request.signature = Some(signer.signature);
+        request.signer = Some(signer.address);
+
+        let (digest, commitment_id) = (request.digest(), request.commitment_id());
+        info!(signer = ?signer.address, %digest, %commitment_id, "New exclusion request received");
+        api.request_exclusion(request, ctx.request_id.clone()).await
+    }
+}
+
+/// `bolt_enableCapture`: enables the debug capture of the exchanges matching a filter.
+#[cfg(feature = "admin")]
+#[derive(Debug)]
+pub struct EnableCapture;
+
+#[cfg(feature = "admin")]
+#[async_trait::async_trait]
+impl Method for EnableCapture {
+    const NAME: &'static str = ENABLE_CAPTURE_METHOD;
+    type Params = CaptureFilter;
+    type Output = bool;
+
+    async fn handle(
+        &self,
+        api: &CommitmentsApiInner,
+        _: &RequestContext,
+        filter: CaptureFilter,
+    ) -> Result<bool, Error> {
+        api.capture.enable(filter).map_err(RejectionError::Capture)?;
+        Ok(true)
+    }
+}
+
+/// `bolt_disableCapture`: disables the debug capture.
+#[cfg(feature = "admin")]
+#[derive(Debug)]
+pub struct DisableCapture;
+
+#[cfg(feature = "admin")]
+#[async_trait::async_trait]
+impl Method for DisableCapture {
+    const NAME: &'static str = DISABLE_CAPTURE_METHOD;
+    type Params = ();
+    type Output = bool;
+
+    async fn handle(
+        &self,
+        api: &CommitmentsApiInner,
+        _: &RequestContext,
+        _: (),
+    ) -> Result<bool, Error> {
+        api.capture.disable();
+        Ok(true)
+    }
+}
+
+/// `bolt_getEarnings`: the report of the fees earned by commitments over a time range.
+#[cfg(feature = "admin")]
+#[derive(Debug)]
+pub struct GetEarnings;
+
+#[cfg(feature = "admin")]
+#[async_trait::async_trait]
+impl Method for GetEarnings {
+    const NAME: &'static str = GET_EARNINGS_METHOD;
+    type Params = EarningsRange;
+    type Output = EarningsReport;
+
+    async fn handle(
+        &self,
+        api: &CommitmentsApiInner,
+        _: &RequestContext,
+        range: EarningsRange,
+    ) -> Result<EarningsReport, Error> {
+        let Some(ref store) = api.store else {
+            error!("Earnings requested but no store is configured");
+            return Err(Error::Internal);
+        };
+
+        EarningsReport::from_store(store, range.from, range.to)
+            .inspect_err(|err| error!(?err, "Failed to load earnings report"))
+            .map_err(|_| Error::Internal)
+    }
+}
+
+/// `bolt_findCommitmentsByContext`: the fee records of the commitments whose requests had
+/// the given context, with the finality of their reconciliation.
+#[cfg(feature = "admin")]
+#[derive(Debug)]
+pub struct FindCommitmentsByContext;
+
+#[cfg(feature = "admin")]
+#[async_trait::async_trait]
+impl Method for FindCommitmentsByContext {
+    const NAME: &'static str = FIND_COMMITMENTS_BY_CONTEXT_METHOD;
+    type Params = ContextQuery;
+    type Output = Vec<AnnotatedFee>;
+
+    async fn handle(
+        &self,
+        api: &CommitmentsApiInner,
+        _: &RequestContext,
+        query: ContextQuery,
+    ) -> Result<Vec<AnnotatedFee>, Error> {
+        let Some(ref store) = api.store else {
+            error!("Commitments requested but no store is configured");
+            return Err(Error::Internal);
+        };
+
+        store
+            .fees_by_context(&query.context)
+            .inspect_err(|err| error!(?err, "Failed to look up commitments by context"))
+            .map_err(|_| Error::Internal)
+    }
+}
+
+/// `admin_getWhitelist`: the entries of the whitelist of request signers, including the
+/// expired ones that weren't swept yet, or null if there is no whitelist.
+#[cfg(feature = "admin")]
+#[derive(Debug)]
+pub struct GetWhitelist;
+
+#[cfg(feature = "admin")]
+#[async_trait::async_trait]
+impl Method for GetWhitelist {
+    const NAME: &'static str = GET_WHITELIST_METHOD;
+    type Params = ();
+    type Output = Option<Whitelist>;
+
+    async fn handle(
+        &self,
+        api: &CommitmentsApiInner,
+        _: &RequestContext,
+        _: (),
+    ) -> Result<Option<Whitelist>, Error> {
+        Ok(api.whitelist.as_ref().map(|whitelist| whitelist.read().clone()))
+    }
+}
+
+/// `admin_importSignedCommitment`: imports the signature of a commitment made by the
+/// external signer.
+#[cfg(feature = "admin")]
+#[derive(Debug)]
+pub struct AdminImportSignedCommitment;
+
+#[cfg(feature = "admin")]
+#[async_trait::async_trait]
+impl Method for AdminImportSignedCommitment {
+    const NAME: &'static str = IMPORT_SIGNED_COMMITMENT_METHOD;
+    type Params = ImportSignedCommitment;
+    type Output = InclusionCommitment;
+
+    async fn handle(
+        &self,
+        api: &CommitmentsApiInner,
+        _: &RequestContext,
+        params: ImportSignedCommitment,
+    ) -> Result<InclusionCommitment, Error> {
+        let Some(ref imports) = api.imports else {
+            return Err(ImportError::NotEnabled.into());
+        };
+
+        let (response_tx, response_rx) = oneshot::channel();
+        let event = ImportEvent {
+            digest: params.digest,
+            signature: params.signature,
+            response: response_tx,
+        };
+
+        imports.send(event).await.map_err(|_| Error::Internal)?;
+        Ok(response_rx.await.map_err(|_| Error::Internal)??.into())
+    }
+}
+
+/// `admin_reloadCertificates`: reloads the TLS certificate of a hostname from disk, or all
+/// of them, without restarting the listener.
+#[cfg(feature = "admin")]
+#[derive(Debug)]
+pub struct ReloadCertificates;
+
+#[cfg(feature = "admin")]
+#[async_trait::async_trait]
+impl Method for ReloadCertificates {
+    const NAME: &'static str = RELOAD_CERTIFICATES_METHOD;
+    type Params = CertificateReload;
+    type Output = bool;
+
+    async fn handle(
+        &self,
+        api: &CommitmentsApiInner,
+        _: &RequestContext,
+        params: CertificateReload,
+    ) -> Result<bool, Error> {
+        #[cfg(feature = "tls")]
+        if let Some(ref tls) = api.tls {
+            tls.reload(params.hostname.as_deref()).map_err(RejectionError::Tls)?;
+            return Ok(true);
+        }
+
+        #[cfg(not(feature = "tls"))]
+        let _ = (api, params);
+        Err(RejectionError::NotEnabled("tls").into())
+    }
+}
+
+/// `admin_rebindListener`: rebinds the public or internal listener to another address,
+/// draining the previous one without dropping the requests in flight.
+#[cfg(feature = "admin")]
+#[derive(Debug)]
+pub struct RebindListener;
+
+#[cfg(feature = "admin")]
+#[async_trait::async_trait]
+impl Method for RebindListener {
+    const NAME: &'static str = REBIND_LISTENER_METHOD;
+    type Params = ListenerRebind;
+    type Output = ListenerAddrs;
+
+    async fn handle(
+        &self,
+        api: &CommitmentsApiInner,
+        _: &RequestContext,
+        rebind: ListenerRebind,
+    ) -> Result<ListenerAddrs, Error> {
+        let Some(ref rebinds) = api.rebinds else {
+            error!("Rebind requested but the listeners are not running");
+            return Err(Error::Internal);
+        };
+
+        let (response_tx, response_rx) = oneshot::channel();
+        let event = RebindEvent { rebind, response: response_tx };
+        rebinds.send(event).await.map_err(|_| Error::Internal)?;
+        response_rx.await.map_err(|_| Error::Internal)?
+    }
+}
+
+/// Sends a drop operation on the commitment to the driver, on behalf of the operator who
+/// sent the request. The admin listener doesn't authenticate its clients, so the operators
+/// are identified by their IP address in the audit log.
+#[cfg(feature = "admin")]
+async fn drop_action(
+    api: &CommitmentsApiInner,
+    ctx: &RequestContext,
+    action: DropAction,
+    digest: B256,
+) -> Result<DroppedCommitment, Error> {
+    let Some(ref drops) = api.drops else {
+        return Err(RejectionError::NotEnabled("commitment_drops").into());
+    };
+
+    let admin = ctx.client_ip.map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+    let (response_tx, response_rx) = oneshot::channel();
+    let event = DropEvent { action, digest, admin, response: response_tx };
+    drops.send(event).await.map_err(|_| Error::Internal)?;
+    response_rx.await.map_err(|_| Error::Internal)?
+}
+
+/// `admin_dropCommitment`: drops a commitment, pending the confirmation of the drop. Until
+/// then, its constraints are still submitted with its slot.
+#[cfg(feature = "admin")]
+#[derive(Debug)]
+pub struct DropCommitment;
+
+#[cfg(feature = "admin")]
+#[async_trait::async_trait]
+impl Method for DropCommitment {
+    const NAME: &'static str = DROP_COMMITMENT_METHOD;
+    type Params = CommitmentDigest;
+    type Output = DroppedCommitment;
+
+    async fn handle(
+        &self,
+        api: &CommitmentsApiInner,
+        ctx: &RequestContext,
+        params: CommitmentDigest,
+    ) -> Result<DroppedCommitment, Error> {
+        drop_action(api, ctx, DropAction::Drop, params.digest).await
+    }
+}
+
+/// `admin_confirmDrop`: confirms the drop of a commitment before the confirmation delay,
+/// excluding its constraints from its slot.
+#[cfg(feature = "admin")]
+#[derive(Debug)]
+pub struct ConfirmDrop;
+
+#[cfg(feature = "admin")]
+#[async_trait::async_trait]
+impl Method for ConfirmDrop {
+    const NAME: &'static str = CONFIRM_DROP_METHOD;
+    type Params = CommitmentDigest;
+    type Output = DroppedCommitment;
+
+    async fn handle(
+        &self,
+        api: &CommitmentsApiInner,
+        ctx: &RequestContext,
+        params: CommitmentDigest,
+    ) -> Result<DroppedCommitment, Error> {
+        drop_action(api, ctx, DropAction::Confirm, params.digest).await
+    }
+}
+
+/// `admin_restoreCommitment`: restores a dropped commitment, confirmed or not, until its
+/// slot has passed.
+#[cfg(feature = "admin")]
+#[derive(Debug)]
+pub struct RestoreCommitment;
+
+#[cfg(feature = "admin")]
+#[async_trait::async_trait]
+impl Method for RestoreCommitment {
+    const NAME: &'static str = RESTORE_COMMITMENT_METHOD;
+    type Params = CommitmentDigest;
+    type Output = DroppedCommitment;
+
+    async fn handle(
+        &self,
+        api: &CommitmentsApiInner,
+        ctx: &RequestContext,
+        params: CommitmentDigest,
+    ) -> Result<DroppedCommitment, Error> {
+        drop_action(api, ctx, DropAction::Restore, params.digest).await
+    }
+}
+
+/// Returns the channel of the maintenance windows to the driver, and the operator who sent
+/// the request, identified by their IP address like in [`drop_action`].
+#[cfg(feature = "admin")]
+fn maintenance_channel<'a>(
+    api: &'a CommitmentsApiInner,
+    ctx: &RequestContext,
+) -> Result<(&'a mpsc::Sender<MaintenanceEvent>, String), Error> {
+    let Some(ref maintenance) = api.maintenance else {
+        return Err(RejectionError::NotEnabled("maintenance_windows").into());
+    };
+
+    let admin = ctx.client_ip.map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+    Ok((maintenance, admin))
+}
+
+/// `admin_declareMaintenance`: declares a window during which a validator won't propose.
+/// The requests targeting its slots are rejected, and the commitments already signed for
+/// them are returned as conflicts, to be honored or dropped with `admin_dropCommitment`.
+#[cfg(feature = "admin")]
+#[derive(Debug)]
+pub struct DeclareMaintenance;
+
+#[cfg(feature = "admin")]
+#[async_trait::async_trait]
+impl Method for DeclareMaintenance {
+    const NAME: &'static str = DECLARE_MAINTENANCE_METHOD;
+    type Params = MaintenanceWindow;
+    type Output = MaintenanceDeclaration;
+
+    async fn handle(
+        &self,
+        api: &CommitmentsApiInner,
+        ctx: &RequestContext,
+        window: MaintenanceWindow,
+    ) -> Result<MaintenanceDeclaration, Error> {
+        let (maintenance, admin) = maintenance_channel(api, ctx)?;
+        let (response_tx, response_rx) = oneshot::channel();
+        let event = MaintenanceEvent::Declare { window, admin, response: response_tx };
+        maintenance.send(event).await.map_err(|_| Error::Internal)?;
+        response_rx.await.map_err(|_| Error::Internal)?
+    }
+}
+
+/// `admin_cancelMaintenance`: cancels a maintenance window, so that the slots of its
+/// validator can be served again.
+#[cfg(feature = "admin")]
+#[derive(Debug)]
+pub struct CancelMaintenance;
+
+#[cfg(feature = "admin")]
+#[async_trait::async_trait]
+impl Method for CancelMaintenance {
+    const NAME: &'static str = CANCEL_MAINTENANCE_METHOD;
+    type Params = MaintenanceId;
+    type Output = DeclaredWindow;
+
+    async fn handle(
+        &self,
+        api: &CommitmentsApiInner,
+        ctx: &RequestContext,
+        params: MaintenanceId,
+    ) -> Result<DeclaredWindow, Error> {
+        let (maintenance, admin) = maintenance_channel(api, ctx)?;
+        let (response_tx, response_rx) = oneshot::channel();
+        let event = MaintenanceEvent::Cancel { id: params.id, admin, response: response_tx };
+        maintenance.send(event).await.map_err(|_| Error::Internal)?;
+        response_rx.await.map_err(|_| Error::Internal)?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use alloy::signers::Signer;
+    use serde_json::json;
+    use tokio::sync::mpsc;
+
+    #[cfg(feature = "admin")]
+    use crate::{commitments::profile::ADMIN_METHODS, db::Store, earnings::CommitmentFee};
+    use crate::{
+        commitments::{
+            profile::{INTERNAL_METHODS, RPC_METHODS},
+            server::CommitmentsApiServer,
+            spec::GET_EARNINGS_METHOD,
+            whitelist::{Whitelist, WhitelistEntry},
+        },
+        state::Readiness,
+        test_util::DeterministicEnv,
+    };
+
+    use super::*;
+
+    fn test_api(server: &CommitmentsApiServer) -> CommitmentsApiInner {
+        let (events, _) = mpsc::channel(1);
+        CommitmentsApiInner::new(events, server)
+    }
+
+    /// Dispatches an unsigned call of the method to the registry, on a listener exposing
+    /// all the RPC methods.
+    async fn call(
+        registry: &MethodRegistry,
+        api: &CommitmentsApiInner,
+        method: &str,
+    ) -> Result<Json<JsonResponse>, Error> {
+        let profile = Arc::new(MethodProfile::all(RPC_METHODS));
+        let payload = JsonPayload {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            id: Some(json!(1)),
+            params: vec![],
+            signature: None,
+        };
+
+        registry.dispatch(api, profile, &HeaderMap::new(), payload, None, Default::default()).await
+    }
+
+    #[test]
+    fn test_registries_match_profiles() {
+        // The public methods are a subset of the internal ones
+        let mut rpc = INTERNAL_METHODS.to_vec();
+        rpc.sort_unstable();
+        assert_eq!(MethodRegistry::rpc().names(), rpc);
+        assert!(RPC_METHODS.iter().all(|method| INTERNAL_METHODS.contains(method)));
+
+        #[cfg(feature = "admin")]
+        {
+            let mut admin = ADMIN_METHODS.to_vec();
+            admin.sort_unstable();
+            assert_eq!(MethodRegistry::admin().names(), admin);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handlers_without_http() {
+        let server = CommitmentsApiServer::new("127.0.0.1:0").unwrap();
+        let api = test_api(&server);
+        let profile = Arc::new(MethodProfile::all(&[GET_VERSION_METHOD]));
+        let ctx = RequestContext::new(None, profile);
+
+        let info = GetChainInfo.handle(&api, &ctx, ()).await.unwrap();
+        assert_eq!(info, api.chain_info());
+
+        let version = GetVersion.handle(&api, &ctx, ()).await.unwrap();
+        assert_eq!(version.methods, vec![GET_VERSION_METHOD.to_string()]);
+    }
+
+    #[cfg(feature = "admin")]
+    #[tokio::test]
+    async fn test_find_commitments_by_context() -> eyre::Result<()> {
+        let store = Store::in_memory()?;
+        let request = |slot| InclusionRequest {
+            slot,
+            txs: vec![],
+            signature: None,
+            signer: None,
+            context: Some("user-42".to_string()),
+            atomic: false,
+            max_slot: None,
+        };
+        let (first, second) = (request(1), request(2));
+        for request in [&first, &second] {
+            let fee = CommitmentFee::new(request, request.slot, 0);
+            store.insert_commitment_fee(&fee, request.context.as_deref())?;
+        }
+
+        let api = test_api(&CommitmentsApiServer::new("127.0.0.1:0").unwrap().with_store(store));
+        let ctx = RequestContext::new(None, Arc::new(MethodProfile::all(ADMIN_METHODS)));
+        let query = |context: &str| ContextQuery { context: context.to_string() };
+
+        let found = FindCommitmentsByContext.handle(&api, &ctx, query("user-42")).await.unwrap();
+        let digests = found.iter().map(|found| found.fee.digest).collect::<Vec<_>>();
+        assert_eq!(digests, vec![first.digest(), second.digest()]);
+
+        let found = FindCommitmentsByContext.handle(&api, &ctx, query("user-7")).await.unwrap();
+        assert!(found.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_checks() {
+        let registry = MethodRegistry::rpc();
+        let api = test_api(&CommitmentsApiServer::new("127.0.0.1:0").unwrap());
+
+        // Unsigned inclusion requests are rejected before their params are parsed
+        let err = call(&registry, &api, REQUEST_INCLUSION_METHOD).await.unwrap_err();
+        assert!(matches!(err, Error::NoSignature), "{err}");
+
+        // Methods of another listener are unknown
+        let err = call(&registry, &api, GET_EARNINGS_METHOD).await.unwrap_err();
+        assert!(matches!(err, Error::UnknownMethod), "{err}");
+
+        let Json(response) = call(&registry, &api, GET_CHAIN_INFO_METHOD).await.unwrap();
+        assert_eq!(response.id, Some(json!(1)));
+        assert_eq!(response.result, serde_json::to_value(api.chain_info()).unwrap());
+
+        // Readiness is checked before authentication
+        let server =
+            CommitmentsApiServer::new("127.0.0.1:0").unwrap().with_readiness(Readiness::new(false));
+        let api = test_api(&server);
+        let err = call(&registry, &api, REQUEST_INCLUSION_METHOD).await.unwrap_err();
+        assert!(matches!(err, Error::NotReady), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_register_method() {
+        /// Returns the slot after the one in the params, for signed requests if `AUTH` is set.
+        struct NextSlot<const AUTH: bool>;
+
+        #[async_trait::async_trait]
+        impl<const AUTH: bool> Method for NextSlot<AUTH> {
+            const NAME: &'static str = if AUTH { "test_signedNextSlot" } else { "test_nextSlot" };
+            const REQUIRES_AUTH: bool = AUTH;
+            type Params = Slot;
+            type Output = Slot;
+
+            fn signed_digest(slot: &Slot, _: u64) -> Option<B256> {
+                Some(B256::with_last_byte(*slot as u8))
+            }
+
+            async fn handle(
+                &self,
+                _: &CommitmentsApiInner,
+                _: &RequestContext,
+                slot: Slot,
+            ) -> Result<Slot, Error> {
+                Ok(slot + 1)
+            }
+        }
+
+        let registry =
+            MethodRegistry::default().register(NextSlot::<false>).register(NextSlot::<true>);
+        assert_eq!(registry.names(), vec!["test_nextSlot", "test_signedNextSlot"]);
+        assert_eq!(registry.describe()[0].name, "test_nextSlot");
+
+        let api = test_api(&CommitmentsApiServer::new("127.0.0.1:0").unwrap());
+        let profile = Arc::new(MethodProfile::all(&["test_nextSlot", "test_signedNextSlot"]));
+        let headers = HeaderMap::new();
+        let dispatch = |method: &str, params: Vec<Value>| {
+            let payload = JsonPayload {
+                jsonrpc: "2.0".to_string(),
+                method: method.to_string(),
+                id: Some(json!(7)),
+                params,
+                signature: None,
+            };
+            registry.dispatch(&api, profile.clone(), &headers, payload, None, Default::default())
+        };
+
+        let Json(response) = dispatch("test_nextSlot", vec![json!(41)]).await.unwrap();
+        assert_eq!((response.id, response.result), (Some(json!(7)), json!(42)));
+
+        let err = dispatch("test_nextSlot", vec![]).await.unwrap_err();
+        assert_eq!(err.error_code().reason, Some("missing_params"));
+
+        // The authentication is only required by the methods that opt into it
+        let err = dispatch("test_signedNextSlot", vec![json!(41)]).await.unwrap_err();
+        assert!(matches!(err, Error::NoSignature), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_whitelist_entry_expiry() -> eyre::Result<()> {
+        let env = DeterministicEnv::new(1);
+        let signer = env.signer();
+        let address = signer.address();
+        let entry_expiry = env.now() + 1;
+        let entry = WhitelistEntry {
+            expires_at: Some(entry_expiry),
+            label: Some("trial".to_string()),
+            ..Default::default()
+        };
+        let mut whitelist = Whitelist::default();
+        whitelist.insert(address, entry);
+
+        let server = env.server().with_whitelist(Some(whitelist));
+        let api = test_api(&server);
+        let digest = B256::random();
+        let header = raw_header(address, signer.sign_hash(&digest).await?);
+        let signatures = ContractSignatureCache::default();
+        let auth = || authenticate(&api, &signatures, header, digest, None, None);
+        assert_eq!(auth().await?.address, address);
+
+        // The entry expires between the two requests
+        env.advance(Duration::from_secs(1));
+        let err = auth().await.unwrap_err();
+        let expired = RejectionError::AuthorizationExpired(address, entry_expiry);
+        assert!(matches!(err, Error::Rejected(RejectionError::AuthorizationExpired(..))), "{err}");
+        assert_eq!(err.to_string(), Error::Rejected(expired).to_string());
+
+        // Expired entries are still listed until they are swept
+        #[cfg(feature = "admin")]
+        {
+            let ctx = RequestContext::new(None, Arc::new(MethodProfile::all(ADMIN_METHODS)));
+            let listed = GetWhitelist.handle(&api, &ctx, ()).await?.unwrap();
+            let label = listed.get(&address).and_then(|entry| entry.label.as_deref());
+            assert_eq!(label, Some("trial"));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_legacy_digest_window() -> eyre::Result<()> {
+        let env = DeterministicEnv::new(1);
+        let signer = env.signer();
+        let address = signer.address();
+        let (digest, legacy) = (B256::random(), B256::random());
+        let header = raw_header(address, signer.sign_hash(&legacy).await?);
+        let signatures = ContractSignatureCache::default();
+
+        // Outside of the deprecation window, only the signatures over the digest are accepted
+        let server = env.server();
+        let api = test_api(&server);
+        let err = authenticate(&api, &signatures, header, digest, None, None).await.unwrap_err();
+        assert!(matches!(err, Error::InvalidSignature(_)), "{err}");
+
+        let auth = authenticate(&api, &signatures, header, digest, Some(legacy), None).await?;
+        assert_eq!(auth.address, address);
+        let header = raw_header(address, signer.sign_hash(&digest).await?);
+        let auth = authenticate(&api, &signatures, header, digest, Some(legacy), None).await?;
+        assert_eq!(auth.address, address);
+
+        // The legacy digest of a request is only given during the deprecation window
+        let params = vec![json!({ "slot": 10, "txs": [] })];
+        let request = InclusionRequest::from_params(params)?;
+        assert_eq!(RequestInclusion::legacy_digest(&request), Some(request.digest()));
+        assert_ne!(RequestInclusion::signed_digest(&request, 1), Some(request.digest()));
+        assert!(!test_api(&server).accept_legacy_digests);
+        assert!(test_api(&server.with_legacy_digests(true)).accept_legacy_digests);
+
+        Ok(())
+    }
+
+    fn raw_header(signer: Address, signature: Signature) -> SignatureHeader {
+        SignatureHeader { scheme: SignatureScheme::Raw, signer, signature, timestamp_ms: None }
+    }
+}
@@ -1,6 +1,47 @@
+/// REST endpoints of the admin server, to manage the whitelist and the limits.
+#[cfg(feature = "admin")]
+pub mod admin;
+/// Replay protection of the signed requests, with timestamped signatures.
+pub mod antireplay;
+/// Append-only audit log of the commitment requests and the decisions on them.
+pub mod audit;
+/// Debug capture of raw JSON-RPC exchanges.
+pub mod capture;
+/// EIP-1271 signature checks of the smart-contract wallets.
+pub mod erc1271;
 /// JSON-RPC helper types and functions.
 mod jsonrpc;
+/// JWT bearer authentication of the JSON-RPC listeners.
+pub mod jwt;
+pub use jsonrpc::JsonError;
+/// The link between the commitments API and a driver in another process.
+pub mod link;
+/// Registry of the JSON-RPC methods and their handlers.
+pub mod methods;
+/// The OpenRPC document of the commitments API.
+pub mod openrpc;
+/// The registry of the commitment requests awaiting the response of the driver.
+pub mod pending;
+/// Method exposure profiles of the listeners.
+pub mod profile;
+/// Per-signer rate limiting of the inclusion requests.
+pub mod ratelimit;
+/// Attestations of the requests relayed by a trusted gateway.
+pub mod relay;
+/// Correlation ids of the JSON-RPC calls, propagated to the driver and echoed to the clients.
+pub mod request_id;
 /// The commitments-API JSON-RPC server implementation.
 pub mod server;
+/// Signing of the commitments-API responses with the commitment key.
+pub mod signing;
 /// The commitments-API specification and errors.
 pub mod spec;
+/// TLS certificates of the commitments-API listener, selected by SNI.
+pub mod tls;
+/// Unix domain socket listener of the commitments API.
+pub mod unix;
+/// The whitelist of request signers, with the expiry and metadata of each entry.
+pub mod whitelist;
+/// WebSocket transport of the JSON-RPC methods.
+#[cfg(feature = "ws")]
+pub mod ws;
@@ -0,0 +1,410 @@
+//! OpenRPC document of the commitments API, served by `rpc.discover`.
+//!
+//! The document describes the methods of the public and internal listeners, the schemas of
+//! the commitment types they exchange and the error codes listed in the
+//! [`ERROR_CATALOGUE`]. It is built once from the [`MethodRegistry`], and filtered by the
+//! profile of the listener that serves it.
+
+use std::{collections::BTreeMap, sync::OnceLock};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    common::CARGO_PKG_VERSION,
+    primitives::{
+        commitment::{
+            ExclusionCommitment, InclusionCommitment, SignedCommitment, MAX_CONTEXT_LEN,
+            MAX_EXCLUSION_TARGETS,
+        },
+        ExclusionRequest, InclusionRequest,
+    },
+};
+
+use super::{
+    methods::MethodRegistry,
+    profile::MethodProfile,
+    spec::{ErrorCode, ERROR_CATALOGUE},
+};
+
+/// The version of the OpenRPC specification the document follows.
+pub const OPENRPC_VERSION: &str = "1.3.2";
+
+/// An OpenRPC document.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpenRpcDocument {
+    /// The version of the OpenRPC specification.
+    pub openrpc: String,
+    /// The title and version of the API.
+    pub info: Info,
+    /// The methods of the API, in alphabetical order.
+    pub methods: Vec<MethodObject>,
+    /// The schemas and errors referenced by the methods.
+    pub components: Components,
+}
+
+/// The metadata of the API.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Info {
+    /// The title of the API.
+    pub title: String,
+    /// The version of the sidecar serving the API.
+    pub version: String,
+}
+
+/// The description of a method.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MethodObject {
+    /// The name of the method.
+    pub name: String,
+    /// A short summary of what the method does.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub summary: String,
+    /// The positional params of the method.
+    pub params: Vec<ContentDescriptor>,
+    /// The result of the method.
+    pub result: ContentDescriptor,
+}
+
+/// A named param or result, with its JSON schema.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentDescriptor {
+    /// The name of the param or result.
+    pub name: String,
+    /// Whether the param must be set.
+    #[serde(default)]
+    pub required: bool,
+    /// The JSON schema of the value.
+    pub schema: Value,
+}
+
+impl ContentDescriptor {
+    /// Describes a required param.
+    pub fn required(name: &str, schema: Value) -> Self {
+        Self { name: name.to_string(), required: true, schema }
+    }
+
+    /// Describes a result, or an optional param.
+    pub fn optional(name: &str, schema: Value) -> Self {
+        Self { name: name.to_string(), required: false, schema }
+    }
+}
+
+/// The schemas and errors referenced by the methods.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Components {
+    /// The JSON schemas of the commitment types, by name.
+    pub schemas: BTreeMap<String, Value>,
+    /// The errors of the API, by code and reason.
+    pub errors: BTreeMap<String, ErrorObject>,
+}
+
+/// An error returned by the API.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorObject {
+    /// The JSON-RPC error code.
+    pub code: i32,
+    /// A description of the error.
+    pub message: String,
+    /// The data included with the error, if it has a reason sub-code.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+/// A type with a JSON schema in the components of the document.
+pub trait Schema {
+    /// The name of the schema in the components.
+    const NAME: &'static str;
+
+    /// Returns the JSON schema of the serialized type.
+    fn schema() -> Value;
+
+    /// Returns a reference to the schema in the components.
+    fn reference() -> Value {
+        json!({ "$ref": format!("#/components/schemas/{}", Self::NAME) })
+    }
+}
+
+impl Schema for InclusionRequest {
+    const NAME: &'static str = "InclusionRequest";
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "required": ["slot", "txs"],
+            "properties": {
+                "slot": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "description": "The slot at which the transactions should be included",
+                },
+                "txs": {
+                    "type": "array",
+                    "minItems": 1,
+                    "items": {
+                        "type": "string",
+                        "pattern": "^0x[0-9a-fA-F]*$",
+                        "description": "An EIP-2718 encoded signed transaction",
+                    },
+                },
+                "context": {
+                    "type": "string",
+                    "maxLength": MAX_CONTEXT_LEN,
+                    "description": "An opaque tag echoed in the commitment",
+                },
+                "atomic": {
+                    "type": "boolean",
+                    "default": false,
+                    "description": "Whether the transactions are included together in their \
+                        order, or not at all",
+                },
+                "max_slot": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "description": "The last slot of the range at which the transactions may \
+                        be included, from `slot`. The commitment names the earliest slot of \
+                        the range proposed by the sidecar",
+                },
+            },
+        })
+    }
+}
+
+impl Schema for InclusionCommitment {
+    const NAME: &'static str = "InclusionCommitment";
+
+    fn schema() -> Value {
+        json!({
+            "allOf": [
+                InclusionRequest::reference(),
+                {
+                    "type": "object",
+                    "required": ["commitment_id", "signature"],
+                    "properties": {
+                        "commitment_id": {
+                            "type": "string",
+                            "pattern": "^0x[0-9a-fA-F]{64}$",
+                            "description": "The id derived from the request digest and slot",
+                        },
+                        "signature": {
+                            "type": "string",
+                            "pattern": "^0x[0-9a-fA-F]{130}$",
+                            "description": "The signature of the sidecar over the request digest",
+                        },
+                        "proof": {
+                            "type": "object",
+                            "required": ["block_hash", "transactions_root", "transactions"],
+                            "description": "The SSZ Merkle proofs of the transactions against \
+                                the transactions root of the block of the slot, once it is seen",
+                        },
+                    },
+                },
+            ],
+        })
+    }
+}
+
+impl Schema for ExclusionRequest {
+    const NAME: &'static str = "ExclusionRequest";
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "required": ["slot"],
+            "description": format!(
+                "Between 1 and {MAX_EXCLUSION_TARGETS} senders and transactions to exclude"
+            ),
+            "properties": {
+                "slot": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "description": "The slot from whose block the transactions are excluded",
+                },
+                "senders": {
+                    "type": "array",
+                    "items": { "type": "string", "pattern": "^0x[0-9a-fA-F]{40}$" },
+                    "description": "The senders whose transactions are excluded",
+                },
+                "tx_hashes": {
+                    "type": "array",
+                    "items": { "type": "string", "pattern": "^0x[0-9a-fA-F]{64}$" },
+                    "description": "The hashes of the excluded transactions",
+                },
+            },
+        })
+    }
+}
+
+impl Schema for ExclusionCommitment {
+    const NAME: &'static str = "ExclusionCommitment";
+
+    fn schema() -> Value {
+        json!({
+            "allOf": [
+                ExclusionRequest::reference(),
+                {
+                    "type": "object",
+                    "required": ["commitment_id", "signature"],
+                    "properties": {
+                        "commitment_id": {
+                            "type": "string",
+                            "pattern": "^0x[0-9a-fA-F]{64}$",
+                            "description": "The id derived from the request digest and slot",
+                        },
+                        "signature": {
+                            "type": "string",
+                            "pattern": "^0x[0-9a-fA-F]{130}$",
+                            "description": "The signature of the sidecar over the request digest",
+                        },
+                    },
+                },
+            ],
+        })
+    }
+}
+
+impl Schema for SignedCommitment {
+    const NAME: &'static str = "SignedCommitment";
+
+    fn schema() -> Value {
+        json!({ "oneOf": [InclusionCommitment::reference(), ExclusionCommitment::reference()] })
+    }
+}
+
+/// Returns the schema of the params identifying a commitment by the digest of its request.
+pub(super) fn digest_schema() -> Value {
+    json!({
+        "type": "object",
+        "required": ["digest"],
+        "properties": { "digest": { "type": "string", "pattern": "^0x[0-9a-fA-F]{64}$" } },
+    })
+}
+
+/// Returns the schema of the params identifying a commitment by the digest of its request or
+/// by its commitment id.
+pub(super) fn commitment_key_schema() -> Value {
+    let hash = json!({ "type": "string", "pattern": "^0x[0-9a-fA-F]{64}$" });
+    json!({
+        "oneOf": [
+            digest_schema(),
+            {
+                "type": "object",
+                "required": ["commitment_id"],
+                "properties": { "commitment_id": hash },
+            },
+        ],
+    })
+}
+
+/// Returns the document of the methods of the public and internal listeners, built once.
+pub fn document() -> &'static OpenRpcDocument {
+    static DOCUMENT: OnceLock<OpenRpcDocument> = OnceLock::new();
+    DOCUMENT.get_or_init(|| build(&MethodRegistry::rpc()))
+}
+
+/// Returns the document restricted to the methods exposed by the profile.
+pub fn document_for(profile: &MethodProfile) -> OpenRpcDocument {
+    let mut document = document().clone();
+    document.methods.retain(|method| profile.allows(&method.name));
+    document
+}
+
+/// Builds the document of the methods of the registry.
+fn build(registry: &MethodRegistry) -> OpenRpcDocument {
+    let schemas = [
+        (InclusionRequest::NAME, InclusionRequest::schema()),
+        (InclusionCommitment::NAME, InclusionCommitment::schema()),
+        (ExclusionRequest::NAME, ExclusionRequest::schema()),
+        (ExclusionCommitment::NAME, ExclusionCommitment::schema()),
+        (SignedCommitment::NAME, SignedCommitment::schema()),
+    ];
+
+    let errors = ERROR_CATALOGUE.iter().map(|&code| (error_key(code), error_object(code)));
+
+    OpenRpcDocument {
+        openrpc: OPENRPC_VERSION.to_string(),
+        info: Info {
+            title: "Bolt commitments API".to_string(),
+            version: CARGO_PKG_VERSION.to_string(),
+        },
+        methods: registry.describe(),
+        components: Components {
+            schemas: schemas.into_iter().map(|(name, schema)| (name.to_string(), schema)).collect(),
+            errors: errors.collect(),
+        },
+    }
+}
+
+/// The name of an error in the components, e.g. `-32006:nonce_too_low`.
+fn error_key(ErrorCode { code, reason }: ErrorCode) -> String {
+    match reason {
+        Some(reason) => format!("{code}:{reason}"),
+        None => code.to_string(),
+    }
+}
+
+/// The error object of an error code, described as in the table of the spec module.
+fn error_object(ErrorCode { code, reason }: ErrorCode) -> ErrorObject {
+    let message = match code {
+        -32000 => "Request rejected",
+        -32001 => "Duplicate request",
+        -32002 => "Internal server error",
+        -32003 => "Missing signature header",
+        -32004 => "Invalid signature",
+        -32005 => "Signature error",
+        -32006 => "Consensus or state validation failed",
+        -32007 => "Malformed signature header",
+        -32008 => "Slot not assigned to the sidecar",
+        -32009 => "Sidecar not ready",
+        -32010 => "Commitment import failed",
+        -32011 => "Sidecar not synced",
+        -32012 => "Persistence unavailable",
+        -32013 => "Timed out",
+        -32014 => "Commitment service unavailable",
+        -32015 => "Cancellation failed",
+        -32016 => "Rate limited",
+        -32017 => "Overloaded",
+        -32018 => "Shutting down",
+        -32019 => "Unsupported signature scheme",
+        -32020 => "Contract signature check failed",
+        -32021 => "Replay protection rejected",
+        -32022 => "Unauthorized",
+        -32023 => "Signer quota exceeded",
+        -32024 => "Remote signer unavailable",
+        -32600 => "Invalid request",
+        -32601 => "Unknown method",
+        -32602 => "Invalid params",
+        _ => "Error",
+    };
+
+    let data = reason.map(|reason| json!({ "reason": reason }));
+    ErrorObject { code, message: message.to_string(), data }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::commitments::profile::{INTERNAL_METHODS, RPC_METHODS};
+
+    #[test]
+    fn test_document() {
+        let document = document();
+        assert_eq!(document.info.version, CARGO_PKG_VERSION);
+
+        let names: Vec<_> = document.methods.iter().map(|method| method.name.as_str()).collect();
+        let mut expected = INTERNAL_METHODS.to_vec();
+        expected.sort_unstable();
+        assert_eq!(names, expected);
+        assert_eq!(document.components.errors.len(), ERROR_CATALOGUE.len());
+
+        // Every reference resolves to a schema of the components
+        let serialized = serde_json::to_string(document).unwrap();
+        for reference in serialized.split("\"$ref\":\"#/components/schemas/").skip(1) {
+            let name = reference.split('"').next().unwrap();
+            assert!(document.components.schemas.contains_key(name), "{name}");
+        }
+
+        let public = document_for(&MethodProfile::all(RPC_METHODS));
+        assert_eq!(public.methods.len(), RPC_METHODS.len());
+    }
+}
@@ -0,0 +1,280 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Weak,
+    },
+    time::Duration,
+};
+
+use alloy::primitives::{Address, B256};
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::sync::oneshot;
+use tracing::warn;
+
+use crate::{
+    alerts::{AlertCondition, Alerts, DEFAULT_MAX_PENDING_RESPONSES},
+    primitives::commitment::InclusionCommitment,
+    state::clock::{Clock, TimeSource},
+    telemetry,
+};
+
+use super::spec::Error;
+
+/// Default time the driver has to respond to a dispatched commitment request, after which
+/// the client gets a timeout error.
+pub const DEFAULT_RESPONSE_TIMEOUT: Duration = Duration::from_secs(12);
+
+/// Default number of commitment requests dispatched to the driver, or waiting to be, above
+/// which new requests are rejected as overloaded.
+pub const DEFAULT_MAX_INFLIGHT_REQUESTS: usize = 256;
+
+/// Delay after which the clients of overloaded requests are told to retry.
+pub const OVERLOADED_RETRY_AFTER: Duration = Duration::from_millis(250);
+
+/// Interval at which the pending responses are checked for expiry.
+pub(super) const PENDING_SWEEP_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Number of orphaned commitments kept for retrieval, after which the oldest are forgotten.
+pub const MAX_ORPHANED_COMMITMENTS: usize = 1_024;
+
+/// A commitment request dispatched to the driver, awaiting its response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PendingResponse {
+    /// The digest of the request.
+    pub digest: B256,
+    /// The signer of the request, if authenticated.
+    pub signer: Option<Address>,
+    /// The time the request was dispatched, since the UNIX epoch.
+    pub created_at: Duration,
+    /// The time after which the response expires, since the UNIX epoch.
+    pub deadline: Duration,
+}
+
+#[derive(Debug)]
+struct PendingEntry {
+    response: PendingResponse,
+    /// Notifies the waiting handler once the response expired.
+    expire: oneshot::Sender<Error>,
+}
+
+/// The registry of the commitment requests dispatched to the driver, until their response
+/// is received or expires.
+///
+/// Each dispatched request is registered with a [`PendingGuard`], which deregisters it when
+/// dropped: once the response is received, or when the handler is cancelled. Requests the
+/// driver doesn't respond to before their deadline are expired by [`sweep_expired`], which
+/// sends the timeout error to their handler.
+///
+/// The driver processes a dispatched request to completion even if its handler is gone,
+/// because the client disconnected or the response expired. The commitments it signs for
+/// those requests are orphaned: they are kept in the registry, so that the clients can
+/// retrieve them by digest with `bolt_getCommitment`.
+#[derive(Debug)]
+pub struct PendingResponses {
+    entries: Mutex<HashMap<u64, PendingEntry>>,
+    orphans: Mutex<VecDeque<(B256, InclusionCommitment)>>,
+    next_id: AtomicU64,
+    timeout: Duration,
+    clock: Clock,
+    alerts: Alerts,
+    max_pending: usize,
+}
+
+impl PendingResponses {
+    /// Create an empty registry, where responses expire after `timeout`.
+    pub fn new(timeout: Duration, clock: Clock) -> Self {
+        Self {
+            entries: Mutex::default(),
+            orphans: Mutex::default(),
+            next_id: AtomicU64::new(0),
+            timeout,
+            clock,
+            alerts: Alerts::default(),
+            max_pending: DEFAULT_MAX_PENDING_RESPONSES,
+        }
+    }
+
+    /// Raise an alert while more than `max_pending` responses are pending.
+    pub fn with_alerts(self, alerts: Alerts, max_pending: usize) -> Self {
+        Self { alerts, max_pending, ..self }
+    }
+
+    /// Returns the time the driver has to respond to a request.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Returns the number of pending responses.
+    pub fn len(&self) -> usize {
+        self.entries.lock().len()
+    }
+
+    /// Returns true if no response is pending.
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().is_empty()
+    }
+
+    /// Returns the pending responses, in the order they were dispatched.
+    pub fn entries(&self) -> Vec<PendingResponse> {
+        let entries = self.entries.lock();
+        let mut entries = entries.iter().collect::<Vec<_>>();
+        entries.sort_by_key(|(id, _)| **id);
+        entries.into_iter().map(|(_, entry)| entry.response.clone()).collect()
+    }
+
+    /// Registers a request dispatched to the driver, until the returned guard is dropped.
+    pub fn register(&self, digest: B256, signer: Option<Address>) -> PendingGuard<'_> {
+        let created_at = self.clock.now();
+        let deadline = created_at + self.timeout;
+        let response = PendingResponse { digest, signer, created_at, deadline };
+
+        let (expire, expired) = oneshot::channel();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut entries = self.entries.lock();
+        entries.insert(id, PendingEntry { response, expire });
+        self.update(entries.len());
+
+        PendingGuard { registry: self, id, expired }
+    }
+
+    /// Expires the responses past their deadline, sending the timeout error to their
+    /// handlers. Returns the number of expired responses.
+    pub fn expire(&self) -> usize {
+        let now = self.clock.now();
+        let mut entries = self.entries.lock();
+        let expired = entries.iter().filter(|(_, entry)| entry.response.deadline <= now);
+        let expired = expired.map(|(id, _)| *id).collect::<Vec<_>>();
+
+        for id in &expired {
+            let Some(PendingEntry { response, expire }) = entries.remove(id) else { continue };
+
+            let elapsed_ms = now.saturating_sub(response.created_at).as_millis() as u64;
+            let PendingResponse { digest, signer, .. } = response;
+            warn!(%digest, ?signer, elapsed_ms, "Driver didn't respond in time, expiring request");
+            let _ = expire.send(Error::Timeout { elapsed_ms });
+        }
+
+        metrics::counter!(telemetry::EXPIRED_RESPONSES).increment(expired.len() as u64);
+        self.update(entries.len());
+        expired.len()
+    }
+
+    /// Keeps a commitment signed for a request whose handler is gone, until it's among the
+    /// [`MAX_ORPHANED_COMMITMENTS`] oldest ones.
+    pub fn orphan(&self, digest: B256, commitment: InclusionCommitment) {
+        warn!(%digest, "Commitment signed for a client that is gone, keeping it for retrieval");
+        metrics::counter!(telemetry::ORPHANED_COMMITMENTS).increment(1);
+
+        let mut orphans = self.orphans.lock();
+        if orphans.len() >= MAX_ORPHANED_COMMITMENTS {
+            orphans.pop_front();
+        }
+        orphans.push_back((digest, commitment));
+    }
+
+    /// Returns the orphaned commitment of the request with the given digest, if any.
+    pub fn orphaned(&self, digest: &B256) -> Option<InclusionCommitment> {
+        let orphans = self.orphans.lock();
+        orphans
+            .iter()
+            .find(|(orphan, _)| orphan == digest)
+            .map(|(_, commitment)| commitment.clone())
+    }
+
+    fn deregister(&self, id: u64) {
+        let mut entries = self.entries.lock();
+        if entries.remove(&id).is_some() {
+            self.update(entries.len());
+        }
+    }
+
+    /// Updates the gauge and the alert with the number of pending responses.
+    fn update(&self, len: usize) {
+        metrics::gauge!(telemetry::PENDING_RESPONSES).set(len as f64);
+
+        if len > self.max_pending {
+            let message = format!("{len} commitment requests are awaiting the driver response");
+            self.alerts.raise(AlertCondition::PendingResponsesBacklog, message);
+        } else {
+            self.alerts.clear(AlertCondition::PendingResponsesBacklog);
+        }
+    }
+}
+
+/// The registration of a pending response, removed from the registry when dropped.
+#[derive(Debug)]
+pub struct PendingGuard<'a> {
+    registry: &'a PendingResponses,
+    id: u64,
+    expired: oneshot::Receiver<Error>,
+}
+
+impl PendingGuard<'_> {
+    /// Resolves with the timeout error once the response expired. It never resolves
+    /// otherwise.
+    pub async fn expired(&mut self) -> Error {
+        match (&mut self.expired).await {
+            Ok(err) => err,
+            Err(_) => std::future::pending().await,
+        }
+    }
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        self.registry.deregister(self.id);
+    }
+}
+
+/// Expires the pending responses past their deadline periodically, until the registry
+/// is dropped.
+pub(super) async fn sweep_expired(pending: Weak<PendingResponses>) {
+    let mut interval = tokio::time::interval(PENDING_SWEEP_INTERVAL);
+    loop {
+        interval.tick().await;
+        let Some(pending) = pending.upgrade() else { return };
+        pending.expire();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{alerts::AlertConfig, state::clock::VirtualTimeSource};
+
+    #[tokio::test]
+    async fn test_expire_and_deregister() {
+        let time = VirtualTimeSource::new(Duration::from_secs(1_000));
+        let config = AlertConfig { command: Some(PathBuf::from("true")), ..Default::default() };
+        let alerts = Alerts::spawn(&config, "test");
+        let pending = PendingResponses::new(Duration::from_secs(2), Clock::Virtual(time.clone()))
+            .with_alerts(alerts.clone(), 1);
+
+        let (first, second) = (B256::repeat_byte(1), B256::repeat_byte(2));
+        let mut expiring = pending.register(first, Some(Address::ZERO));
+        time.set(Duration::from_secs(1_001));
+        let completed = pending.register(second, None);
+        assert_eq!(pending.entries().iter().map(|e| e.digest).collect::<Vec<_>>(), [first, second]);
+        assert!(alerts.is_raised(AlertCondition::PendingResponsesBacklog));
+
+        // Completed responses are deregistered
+        drop(completed);
+        assert_eq!(pending.len(), 1);
+        assert!(!alerts.is_raised(AlertCondition::PendingResponsesBacklog));
+
+        time.set(Duration::from_millis(1_001_999));
+        assert_eq!(pending.expire(), 0);
+        time.set(Duration::from_secs(1_002));
+        assert_eq!(pending.expire(), 1);
+        assert!(matches!(expiring.expired().await, Error::Timeout { elapsed_ms: 2_000 }));
+        assert!(pending.is_empty());
+
+        // The expired guard is already deregistered
+        drop(expiring);
+        assert!(pending.is_empty());
+    }
+}
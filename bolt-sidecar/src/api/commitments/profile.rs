@@ -0,0 +1,150 @@
+use std::collections::BTreeSet;
+
+use super::spec::{
+    CANCEL_INCLUSION_METHOD, CANCEL_MAINTENANCE_METHOD, CONFIRM_DROP_METHOD,
+    DECLARE_MAINTENANCE_METHOD, DISABLE_CAPTURE_METHOD, DISCOVER_METHOD, DROP_COMMITMENT_METHOD,
+    ENABLE_CAPTURE_METHOD, FIND_COMMITMENTS_BY_CONTEXT_METHOD, GET_CHAIN_INFO_METHOD,
+    GET_COMMITMENT_METHOD, GET_COMMITMENT_STATUS_METHOD, GET_EARNINGS_METHOD,
+    GET_SLOT_MANIFEST_METHOD, GET_STATUS_METHOD, GET_VERSION_METHOD, GET_WHITELIST_METHOD,
+    IMPORT_SIGNED_COMMITMENT_METHOD, METADATA_METHOD, REBIND_LISTENER_METHOD,
+    RELOAD_CERTIFICATES_METHOD, REQUEST_EXCLUSION_METHOD, REQUEST_INCLUSION_METHOD,
+    RESTORE_COMMITMENT_METHOD,
+};
+
+/// The methods served by the public and internal listeners.
+pub const RPC_METHODS: &[&str] = &[
+    GET_VERSION_METHOD,
+    GET_CHAIN_INFO_METHOD,
+    GET_STATUS_METHOD,
+    REQUEST_INCLUSION_METHOD,
+    REQUEST_EXCLUSION_METHOD,
+    GET_COMMITMENT_METHOD,
+    GET_COMMITMENT_STATUS_METHOD,
+    CANCEL_INCLUSION_METHOD,
+    METADATA_METHOD,
+    DISCOVER_METHOD,
+];
+
+/// The methods served by the internal listener, which also exposes the obligations of our
+/// proposers to the block builder.
+pub const INTERNAL_METHODS: &[&str] = &[
+    GET_VERSION_METHOD,
+    GET_CHAIN_INFO_METHOD,
+    GET_STATUS_METHOD,
+    REQUEST_INCLUSION_METHOD,
+    REQUEST_EXCLUSION_METHOD,
+    GET_COMMITMENT_METHOD,
+    GET_COMMITMENT_STATUS_METHOD,
+    CANCEL_INCLUSION_METHOD,
+    METADATA_METHOD,
+    GET_SLOT_MANIFEST_METHOD,
+    DISCOVER_METHOD,
+];
+
+/// The methods served by the admin listener.
+pub const ADMIN_METHODS: &[&str] = &[
+    ENABLE_CAPTURE_METHOD,
+    DISABLE_CAPTURE_METHOD,
+    GET_EARNINGS_METHOD,
+    IMPORT_SIGNED_COMMITMENT_METHOD,
+    RELOAD_CERTIFICATES_METHOD,
+    FIND_COMMITMENTS_BY_CONTEXT_METHOD,
+    REBIND_LISTENER_METHOD,
+    GET_WHITELIST_METHOD,
+    DROP_COMMITMENT_METHOD,
+    CONFIRM_DROP_METHOD,
+    RESTORE_COMMITMENT_METHOD,
+    GET_SLOT_MANIFEST_METHOD,
+    DECLARE_MAINTENANCE_METHOD,
+    CANCEL_MAINTENANCE_METHOD,
+];
+
+/// Errors that can occur while parsing a method profile.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ProfileError {
+    /// The method is not served by the listener.
+    #[error("Unknown method {0}, expected one of: {1}")]
+    UnknownMethod(String, String),
+}
+
+/// The allowlist of JSON-RPC methods exposed by a listener.
+///
+/// Methods outside of the profile are rejected before their params are parsed, with the
+/// same error as nonexistent methods.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodProfile {
+    allowed: BTreeSet<&'static str>,
+}
+
+impl MethodProfile {
+    /// A profile that exposes all the given methods.
+    pub fn all(methods: &[&'static str]) -> Self {
+        Self { allowed: methods.iter().copied().collect() }
+    }
+
+    /// Parses a comma-separated allowlist of methods, which must all be part of `available`.
+    pub fn parse(list: &str, available: &[&'static str]) -> Result<Self, ProfileError> {
+        let mut allowed = BTreeSet::new();
+
+        for method in list.split(',').map(str::trim).filter(|m| !m.is_empty()) {
+            let Some(method) = available.iter().find(|m| **m == method) else {
+                return Err(ProfileError::UnknownMethod(method.to_string(), available.join(",")));
+            };
+            allowed.insert(*method);
+        }
+
+        Ok(Self { allowed })
+    }
+
+    /// Returns true if the method is exposed.
+    pub fn allows(&self, method: &str) -> bool {
+        self.allowed.contains(method)
+    }
+
+    /// Returns the exposed methods, in alphabetical order.
+    pub fn methods(&self) -> Vec<String> {
+        self.allowed.iter().map(|m| m.to_string()).collect()
+    }
+}
+
+/// The method profiles of the listeners of the commitments API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodProfiles {
+    /// The profile of the public listener.
+    pub public: MethodProfile,
+    /// The profile of the internal listener.
+    pub internal: MethodProfile,
+    /// The profile of the admin listener.
+    pub admin: MethodProfile,
+}
+
+impl Default for MethodProfiles {
+    fn default() -> Self {
+        Self {
+            public: MethodProfile::all(RPC_METHODS),
+            internal: MethodProfile::all(INTERNAL_METHODS),
+            admin: MethodProfile::all(ADMIN_METHODS),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_profile() {
+        let profile =
+            MethodProfile::parse("bolt_requestInclusion, bolt_getVersion,", RPC_METHODS).unwrap();
+        assert!(profile.allows("bolt_requestInclusion"));
+        assert!(profile.allows("bolt_getVersion"));
+        assert!(!profile.allows("bolt_getStatus"));
+        assert_eq!(profile.methods(), vec!["bolt_getVersion", "bolt_requestInclusion"]);
+
+        // Admin methods can't be exposed on the public listener
+        assert!(matches!(
+            MethodProfile::parse("bolt_getEarnings", RPC_METHODS),
+            Err(ProfileError::UnknownMethod(method, _)) if method == "bolt_getEarnings"
+        ));
+    }
+}
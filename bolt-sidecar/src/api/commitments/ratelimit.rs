@@ -0,0 +1,167 @@
+use std::{collections::HashMap, sync::Weak, time::Duration};
+
+use alloy::primitives::Address;
+use parking_lot::{Mutex, RwLock};
+use tracing::debug;
+
+use crate::state::clock::{Clock, TimeSource};
+
+use super::spec::Error;
+
+/// Interval at which the buckets of the idle signers are evicted.
+pub(super) const RATE_LIMIT_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Time after which the bucket of a signer that sent no request is evicted, unless it takes
+/// longer to refill.
+pub const IDLE_BUCKET_TTL: Duration = Duration::from_secs(300);
+
+/// The rate at which each signer can send inclusion requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimits {
+    /// The sustained number of requests per second.
+    pub requests_per_sec: u32,
+    /// The number of requests that can be sent at once, after being idle.
+    pub burst: u32,
+}
+
+/// The token bucket of a signer.
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Duration,
+}
+
+/// Token-bucket rate limiter of the inclusion requests, keyed by request signer.
+///
+/// Each signer starts with a full bucket of `burst` tokens, refilled at `requests_per_sec`,
+/// and every request takes a token. Buckets are kept in memory until their signer is idle
+/// for [`IDLE_BUCKET_TTL`], at which point they are full again and can be dropped. The limits
+/// can be changed at runtime, when the config is reloaded.
+#[derive(Debug)]
+pub struct SignerRateLimiter {
+    limits: RwLock<RateLimits>,
+    buckets: Mutex<HashMap<Address, Bucket>>,
+}
+
+impl SignerRateLimiter {
+    /// Create a rate limiter with the given limits, and no signer seen yet.
+    pub fn new(limits: RateLimits) -> Self {
+        Self { limits: RwLock::new(limits), buckets: Mutex::default() }
+    }
+
+    /// Returns the current limits.
+    pub fn limits(&self) -> RateLimits {
+        *self.limits.read()
+    }
+
+    /// Replaces the limits. The buckets of the signers already seen gain or lose the
+    /// difference of the bursts, so that a higher burst is available right away.
+    pub fn set_limits(&self, limits: RateLimits) {
+        let mut current = self.limits.write();
+        let (old, new) = (f64::from(current.burst), f64::from(limits.burst));
+        for bucket in self.buckets.lock().values_mut() {
+            bucket.tokens = (bucket.tokens + new - old).clamp(0.0, new);
+        }
+        *current = limits;
+    }
+
+    /// Takes a token from the bucket of the signer at the time `now`, or returns
+    /// [`Error::RateLimited`] with the time until the next token if it's empty.
+    pub fn check(&self, signer: Address, now: Duration) -> Result<(), Error> {
+        let RateLimits { requests_per_sec, burst } = self.limits();
+        let (rate, capacity) = (f64::from(requests_per_sec), f64::from(burst));
+
+        let mut buckets = self.buckets.lock();
+        let bucket = buckets.entry(signer).or_insert(Bucket { tokens: capacity, updated: now });
+        let elapsed = now.saturating_sub(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity);
+        bucket.updated = bucket.updated.max(now);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        let retry_after_ms = if rate > 0.0 {
+            ((1.0 - bucket.tokens) / rate * 1_000.0).ceil() as u64
+        } else {
+            u64::MAX
+        };
+        Err(Error::RateLimited { retry_after_ms })
+    }
+
+    /// Removes the buckets of the signers idle since the TTL, returning how many were removed.
+    pub fn evict_idle(&self, now: Duration) -> usize {
+        let ttl = self.idle_ttl();
+        let mut buckets = self.buckets.lock();
+        let before = buckets.len();
+        buckets.retain(|_, bucket| now.saturating_sub(bucket.updated) < ttl);
+        before - buckets.len()
+    }
+
+    /// Returns the number of signers with a bucket.
+    pub fn len(&self) -> usize {
+        self.buckets.lock().len()
+    }
+
+    /// Returns true if no signer has a bucket.
+    pub fn is_empty(&self) -> bool {
+        self.buckets.lock().is_empty()
+    }
+
+    /// The idle time after which a bucket is full, and can be evicted without letting its
+    /// signer exceed the limits.
+    fn idle_ttl(&self) -> Duration {
+        let RateLimits { requests_per_sec, burst } = self.limits();
+        if requests_per_sec == 0 {
+            return Duration::MAX;
+        }
+        let refill = Duration::from_secs_f64(f64::from(burst) / f64::from(requests_per_sec));
+        IDLE_BUCKET_TTL.max(refill)
+    }
+}
+
+/// Evicts the buckets of the idle signers periodically, until the rate limiter is dropped.
+pub(super) async fn sweep_idle(limiter: Weak<SignerRateLimiter>, clock: Clock) {
+    let mut interval = tokio::time::interval(RATE_LIMIT_SWEEP_INTERVAL);
+    loop {
+        interval.tick().await;
+        let Some(limiter) = limiter.upgrade() else { return };
+
+        let evicted = limiter.evict_idle(clock.now());
+        if evicted > 0 {
+            debug!(evicted, remaining = limiter.len(), "Evicted idle rate limit buckets");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let limiter = SignerRateLimiter::new(RateLimits { requests_per_sec: 2, burst: 3 });
+        let (alice, bob) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let start = Duration::from_secs(1_000);
+
+        for _ in 0..3 {
+            limiter.check(alice, start).unwrap();
+        }
+        let Err(Error::RateLimited { retry_after_ms }) = limiter.check(alice, start) else {
+            panic!("The burst is exhausted");
+        };
+        assert_eq!(retry_after_ms, 500);
+        limiter.check(bob, start).unwrap();
+
+        // A token is refilled every 500ms
+        assert!(limiter.check(alice, start + Duration::from_millis(400)).is_err());
+        limiter.check(alice, start + Duration::from_millis(500)).unwrap();
+        assert!(limiter.check(alice, start + Duration::from_millis(500)).is_err());
+
+        // Idle buckets are evicted after the TTL
+        assert_eq!(limiter.evict_idle(start + IDLE_BUCKET_TTL), 1);
+        assert_eq!(limiter.evict_idle(start + Duration::from_millis(500) + IDLE_BUCKET_TTL), 1);
+        assert!(limiter.is_empty());
+    }
+}
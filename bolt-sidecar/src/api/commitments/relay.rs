@@ -0,0 +1,242 @@
+//! Attestations of the commitment requests relayed by a gateway, such as a sidecar in
+//! forwarding mode in front of the one holding the commitment key.
+//!
+//! The downstream sidecar sees the gateway as the HTTP peer. The gateway forwards the
+//! [`SIGNATURE_HEADER`] of the original signer as is, and adds a [`RELAY_ATTESTATION_HEADER`]
+//! with its own signature over the original signer, the request digest and a timestamp.
+//! The original signer is only honored if the attestation is valid, fresh and made by one
+//! of the [`TrustedGateways`]: otherwise, the request is attributed to the gateway itself.
+
+use std::{collections::HashSet, fmt, str::FromStr, time::Duration};
+
+use alloy::{
+    primitives::{keccak256, Address, Signature, B256},
+    signers::Signer,
+};
+use axum::http::{HeaderMap, HeaderValue};
+use serde_json::Value;
+use tracing::warn;
+
+use crate::{
+    primitives::{
+        commitment::ECDSASignatureExt, parse_address, ExclusionRequest, InclusionRequest,
+    },
+    telemetry,
+};
+
+use super::{
+    server::auth_from_headers,
+    spec::{
+        Error, SignatureScheme, REQUEST_EXCLUSION_METHOD, REQUEST_INCLUSION_METHOD,
+        SIGNATURE_HEADER,
+    },
+};
+
+/// The header carrying the attestation of the gateway that relayed a request, formatted as
+/// `<gateway>:<timestamp>:<signature>`.
+pub const RELAY_ATTESTATION_HEADER: &str = "x-bolt-relay-attestation";
+
+/// Maximum difference between the timestamp of an attestation and the clock of the
+/// downstream sidecar, in either direction to tolerate clock skew.
+pub const MAX_ATTESTATION_AGE: Duration = Duration::from_secs(30);
+
+/// Returns the digest signed by the gateway in a [`RelayAttestation`]: the keccak256 hash of
+/// the original signer, the request digest and the big-endian timestamp.
+pub fn attestation_digest(signer: Address, digest: B256, timestamp: u64) -> B256 {
+    keccak256([signer.as_slice(), digest.as_slice(), &timestamp.to_be_bytes()].concat())
+}
+
+/// Returns the signer in the [`SIGNATURE_HEADER`], its signature and the digests it may sign
+/// for a request on the chain with the given id, one of which a gateway attests when relaying
+/// it. Only commitment requests are signed. The digest of an inclusion request is scoped to
+/// the chain, and its legacy digest follows, as downstream sidecars may still accept it.
+///
+/// Typed-data signatures aren't attested, as their digest depends on the EIP-712 domain of the
+/// downstream sidecar: they're attributed to their original signer anyway.
+pub fn signed_request(
+    headers: &HeaderMap,
+    method: &str,
+    params: &[Value],
+    chain_id: u64,
+) -> Option<(Address, Signature, Vec<B256>)> {
+    if method != REQUEST_INCLUSION_METHOD && method != REQUEST_EXCLUSION_METHOD {
+        return None;
+    }
+
+    let auth = auth_from_headers(headers).ok()?;
+    if auth.scheme != SignatureScheme::Raw {
+        return None;
+    }
+    let params = params.first()?.clone();
+    let digests = if method == REQUEST_INCLUSION_METHOD {
+        let request = serde_json::from_value::<InclusionRequest>(params).ok()?;
+        vec![request.chain_digest(chain_id), request.digest()]
+    } else {
+        vec![serde_json::from_value::<ExclusionRequest>(params).ok()?.digest()]
+    };
+    let digests = digests.into_iter().map(|digest| auth.signed_digest(digest)).collect();
+    Some((auth.signer, auth.signature, digests))
+}
+
+/// The attestation of a gateway that it relayed a request of the original signer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RelayAttestation {
+    /// The address of the gateway.
+    pub gateway: Address,
+    /// The UNIX timestamp at which the request was relayed, in seconds.
+    pub timestamp: u64,
+    /// The signature of the gateway over the [`attestation_digest`].
+    pub signature: Signature,
+}
+
+impl RelayAttestation {
+    /// Signs the attestation of a request by `signer` with the given digest, relayed at
+    /// `timestamp`.
+    pub async fn sign<S: Signer + ?Sized>(
+        gateway: &S,
+        signer: Address,
+        digest: B256,
+        timestamp: u64,
+    ) -> Result<Self, alloy::signers::Error> {
+        let signature = gateway.sign_hash(&attestation_digest(signer, digest, timestamp)).await?;
+        Ok(Self { gateway: gateway.address(), timestamp, signature })
+    }
+
+    /// Returns the attestation in the [`RELAY_ATTESTATION_HEADER`], if the request was relayed.
+    pub fn from_headers(headers: &HeaderMap) -> Result<Option<Self>, Error> {
+        let Some(value) = headers.get(RELAY_ATTESTATION_HEADER) else { return Ok(None) };
+        value.to_str().map_err(|_| Error::MalformedHeader)?.parse().map(Some)
+    }
+
+    /// Returns the value of the [`RELAY_ATTESTATION_HEADER`] carrying the attestation.
+    pub fn to_header_value(&self) -> HeaderValue {
+        HeaderValue::from_str(&self.to_string()).expect("Valid header")
+    }
+
+    /// Recovers the address that signed the attestation of a request by `signer` with the
+    /// given digest.
+    pub fn recover(&self, signer: Address, digest: B256) -> Result<Address, Error> {
+        let digest = attestation_digest(signer, digest, self.timestamp);
+        Ok(self.signature.recover_address_from_prehash(&digest)?)
+    }
+}
+
+impl fmt::Display for RelayAttestation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.gateway, self.timestamp, self.signature.to_hex())
+    }
+}
+
+impl FromStr for RelayAttestation {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut split = s.split(':');
+        let mut next = || split.next().ok_or(Error::MalformedHeader);
+
+        let gateway = parse_address(next()?).map_err(|_| Error::MalformedHeader)?;
+        let timestamp = next()?.parse().map_err(|_| Error::MalformedHeader)?;
+        let signature = Signature::from_str(next()?)
+            .map_err(|_| Error::InvalidSignature(crate::primitives::SignatureError))?;
+
+        Ok(Self { gateway, timestamp, signature })
+    }
+}
+
+/// The gateways trusted to attest the original signer of the requests they relay.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrustedGateways {
+    gateways: HashSet<Address>,
+}
+
+impl TrustedGateways {
+    /// Create the set of trusted gateways from their addresses.
+    pub fn new(gateways: impl IntoIterator<Item = Address>) -> Self {
+        Self { gateways: gateways.into_iter().collect() }
+    }
+
+    /// Returns the number of trusted gateways.
+    pub fn len(&self) -> usize {
+        self.gateways.len()
+    }
+
+    /// Returns true if no gateway is trusted.
+    pub fn is_empty(&self) -> bool {
+        self.gateways.is_empty()
+    }
+
+    /// Returns true if the gateway is trusted.
+    pub fn contains(&self, gateway: &Address) -> bool {
+        self.gateways.contains(gateway)
+    }
+
+    /// Returns the address a request by `signer` with the given digest is attributed to,
+    /// given the attestation of the gateway that relayed it, at the UNIX timestamp `now`.
+    ///
+    /// A valid and fresh attestation of a trusted gateway honors the original signer.
+    /// Otherwise, the request is attributed to the address that signed the attestation: the
+    /// gateway itself, unless the attestation was made for another request. The claimed
+    /// gateway is never trusted without its signature, so that a client can't borrow the
+    /// identity of a gateway by attaching a forged attestation.
+    pub fn attribute(
+        &self,
+        attestation: &RelayAttestation,
+        signer: Address,
+        digest: B256,
+        now: u64,
+    ) -> Result<Address, Error> {
+        let gateway = attestation.recover(signer, digest)?;
+
+        let outcome = if gateway != attestation.gateway {
+            "mismatch"
+        } else if !self.contains(&gateway) {
+            "untrusted"
+        } else if now.abs_diff(attestation.timestamp) > MAX_ATTESTATION_AGE.as_secs() {
+            "stale"
+        } else {
+            "honored"
+        };
+        metrics::counter!(telemetry::RELAYED_REQUESTS, "attestation" => outcome).increment(1);
+
+        if outcome == "honored" {
+            return Ok(signer);
+        }
+
+        let (claimed, timestamp) = (attestation.gateway, attestation.timestamp);
+        warn!(%signer, %claimed, %gateway, timestamp, outcome, "Attributing request to gateway");
+        Ok(gateway)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::signers::local::PrivateKeySigner;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_attestation_attribution() {
+        let gateway = PrivateKeySigner::random();
+        let (signer, digest, now) = (Address::random(), B256::random(), 1_700_000_000);
+        let attestation = RelayAttestation::sign(&gateway, signer, digest, now).await.unwrap();
+        assert_eq!(attestation.to_string().parse::<RelayAttestation>().unwrap(), attestation);
+
+        let trusted = TrustedGateways::new([gateway.address()]);
+        assert_eq!(trusted.attribute(&attestation, signer, digest, now + 30).unwrap(), signer);
+
+        // Stale attestations and untrusted gateways fall back to the gateway
+        let stale = trusted.attribute(&attestation, signer, digest, now + 31).unwrap();
+        assert_eq!(stale, gateway.address());
+        let untrusted = TrustedGateways::default().attribute(&attestation, signer, digest, now);
+        assert_eq!(untrusted.unwrap(), gateway.address());
+
+        // An attestation of another request is attributed to whoever signed it
+        let other = trusted.attribute(&attestation, signer, B256::random(), now).unwrap();
+        assert_ne!(other, signer);
+        assert_ne!(other, gateway.address());
+
+        for malformed in ["", "0xdead:1:0x00", &format!("{}:now:0x00", gateway.address())] {
+            assert!(malformed.parse::<RelayAttestation>().is_err());
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Correlation ids of the JSON-RPC calls.
+//!
+//! Every call is given an id, carried by its tracing span, by the [`Event`](super::server::Event)
+//! of its commitment request and so by the logs of the driver, and echoed to the client in the
+//! [`REQUEST_ID_HEADER`] of the response and in the data of its errors. Clients can set their
+//! own id in the header of the request, which is reused if it's a valid one.
+
+use std::fmt;
+
+use axum::http::{HeaderMap, HeaderValue};
+use serde::{Deserialize, Serialize};
+
+/// The header carrying the id of a call, in both the request and the response.
+pub const REQUEST_ID_HEADER: &str = "x-bolt-request-id";
+
+/// The maximum length of the ids set by the clients.
+pub const MAX_REQUEST_ID_LEN: usize = 64;
+
+/// The id of a JSON-RPC call: a random UUID, unless set by the client.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RequestId(String);
+
+impl RequestId {
+    /// Generates a random (version 4) UUID.
+    pub fn generate() -> Self {
+        let mut bytes: [u8; 16] = rand::random();
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+        let hex = hex::encode(bytes);
+        let (a, b, c, d, e) = (&hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..]);
+        Self(format!("{a}-{b}-{c}-{d}-{e}"))
+    }
+
+    /// Returns the id set by the client, if it's at most [`MAX_REQUEST_ID_LEN`] characters
+    /// among ASCII alphanumerics, `-`, `_`, `.` and `:`.
+    pub fn parse(id: &str) -> Option<Self> {
+        let valid = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':');
+        let valid = !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN && id.chars().all(valid);
+        valid.then(|| Self(id.to_string()))
+    }
+
+    /// Returns the valid id in the [`REQUEST_ID_HEADER`] of the request, or a generated one.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        headers
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(Self::parse)
+            .unwrap_or_else(Self::generate)
+    }
+
+    /// Returns the id as a string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns the id as the value of a header.
+    pub fn header_value(&self) -> HeaderValue {
+        HeaderValue::from_str(&self.0).expect("Request ids are valid header values")
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_ids() {
+        let id = RequestId::generate();
+        assert_eq!(id.as_str().len(), 36);
+        assert_eq!(id.as_str().as_bytes()[14], b'4');
+        assert_ne!(id, RequestId::generate());
+
+        assert_eq!(RequestId::parse("client-42:retry.1").unwrap().as_str(), "client-42:retry.1");
+        for invalid in ["", "with space", "quote\"", "é", &"a".repeat(MAX_REQUEST_ID_LEN + 1)] {
+            assert!(RequestId::parse(invalid).is_none(), "{invalid}");
+        }
+
+        // Invalid ids set by the client are replaced
+        let mut headers = HeaderMap::new();
+        headers.insert(REQUEST_ID_HEADER, HeaderValue::from_static("bad id"));
+        assert_ne!(RequestId::from_headers(&headers).as_str(), "bad id");
+        headers.insert(REQUEST_ID_HEADER, HeaderValue::from_static("good-id"));
+        assert_eq!(RequestId::from_headers(&headers).as_str(), "good-id");
+    }
+}
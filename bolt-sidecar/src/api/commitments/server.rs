@@ -1,46 +1,219 @@
 use std::{
-    collections::HashSet,
+    borrow::Cow,
+    collections::HashMap,
     fmt,
     future::Future,
+    io,
     net::{SocketAddr, ToSocketAddrs},
+    path::PathBuf,
     pin::Pin,
     str::FromStr,
     sync::Arc,
+    time::Duration,
 };
 
-use alloy::primitives::{Address, Signature};
-use axum::{extract::State, http::HeaderMap, routing::post, Json, Router};
+use alloy::{
+    primitives::{Address, Signature, B256},
+    sol_types::Eip712Domain,
+};
+#[cfg(feature = "ws")]
+use axum::extract::WebSocketUpgrade;
+#[cfg(feature = "admin")]
+use axum::routing::{delete, put};
+#[cfg(feature = "tls")]
+use axum::Extension;
+use axum::{
+    extract::{ConnectInfo, FromRef, Path, Request, State},
+    http::{HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{
+        sse::{Event as SseEvent, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post},
+    Json, Router,
+};
 use axum_extra::extract::WithRejection;
+use futures::StreamExt;
+#[cfg(feature = "tls")]
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto,
+    service::TowerToHyperService,
+};
+use parking_lot::RwLock;
+use reth_rpc_layer::JwtSecret;
 use serde_json::Value;
 use tokio::{
-    net::TcpListener,
-    sync::{mpsc, oneshot},
+    net::{TcpListener, UnixListener},
+    sync::{
+        mpsc::{self, error::TrySendError},
+        oneshot, watch, Semaphore,
+    },
+    task::JoinHandle,
 };
-use tracing::{debug, error, info, instrument};
+#[cfg(feature = "tls")]
+use tokio_rustls::TlsAcceptor;
+use tracing::{debug, error, info, instrument, warn};
 
 use crate::{
-    common::CARGO_PKG_VERSION,
+    alerts::{Alerts, DEFAULT_MAX_PENDING_RESPONSES},
+    config::SharedLimits,
+    drops::DroppedCommitment,
+    inclusion::InclusionReader,
     primitives::{
-        commitment::{InclusionCommitment, SignedCommitment},
-        CommitmentRequest, InclusionRequest,
+        commitment::{ExclusionCommitment, InclusionCommitment, SignedCommitment},
+        parse_address, ChainHead, CommitmentRequest, ExclusionRequest, InclusionRequest,
+        ServedIdentity, Slot,
+    },
+    state::{
+        clock::Clock,
+        maintenance::{DeclaredWindow, MaintenanceDeclaration, MaintenanceWindow},
+        scheduler::ScheduleEntry,
+        HealthReader, ManifestReader, Readiness, SnapshotReader, StateSnapshot,
     },
+    ChainConfig,
 };
+#[cfg(feature = "admin")]
+use crate::{config::Limits, db::Store};
 
+#[cfg(feature = "admin")]
+use super::admin::{
+    self, LimitsUpdate, WhitelistAddition, LIMITS_PATH, WHITELIST_ENTRY_PATH, WHITELIST_PATH,
+};
+#[cfg(feature = "tls")]
+use super::tls::SniResolver;
+#[cfg(feature = "admin")]
+use super::whitelist::WhitelistEntry;
+#[cfg(feature = "ws")]
+use super::ws;
 use super::{
-    jsonrpc::{JsonPayload, JsonResponse},
+    antireplay::{self, ReplayGuard, REPLAY_PROTECTION_CAPABILITY},
+    audit::AuditLog,
+    capture::{CapturedPayload, RequestCapture},
+    erc1271::{ContractSignatureCache, Erc1271Verifier},
+    jsonrpc::{JsonError, JsonPayload, JsonRequest, JsonResponse},
+    jwt,
+    methods::MethodRegistry,
+    pending::{
+        self, PendingResponses, DEFAULT_MAX_INFLIGHT_REQUESTS, DEFAULT_RESPONSE_TIMEOUT,
+        OVERLOADED_RETRY_AFTER,
+    },
+    profile::{MethodProfile, MethodProfiles},
+    ratelimit::{self, RateLimits, SignerRateLimiter},
+    relay::TrustedGateways,
+    request_id::{RequestId, REQUEST_ID_HEADER},
+    signing::{ResponseSigner, RESPONSE_SIGNING_CAPABILITY},
     spec::{
-        CommitmentsApi, Error, RejectionError, GET_VERSION_METHOD, REQUEST_INCLUSION_METHOD,
-        SIGNATURE_HEADER,
+        CancelOutcome, ChainInfo, CommitmentsApi, Error, ListenerAddrs, ListenerRebind,
+        ReadinessReport, RebindableListener, RejectionError, SidecarMetadata, SidecarStatus,
+        SignatureHeader, SignatureScheme, GET_SLOT_MANIFEST_METHOD, SIGNATURE_HEADER,
     },
+    unix::{self, UnixSocketConfig},
+    whitelist::{self, QuotaOverrides, SharedWhitelist, Whitelist},
 };
 
+/// The TLS acceptor of a listener, which can't exist without the `tls` feature.
+#[cfg(not(feature = "tls"))]
+type TlsAcceptor = std::convert::Infallible;
+
 /// Event type emitted by the commitments API.
+///
+/// This is the whole contract between the API server and the driver: each event is answered
+/// once on its response channel, in any order. An event whose response channel is closed
+/// may be skipped, and dropping the channel unanswered rejects the request as unavailable.
+/// The events travel over an in-process channel, or over a [`link`](super::link) when the
+/// two run in separate processes.
 #[derive(Debug)]
 pub struct Event {
     /// The request to process.
     pub request: CommitmentRequest,
     /// The response channel.
     pub response: oneshot::Sender<Result<SignedCommitment, Error>>,
+    /// The id of the call that made the request, to correlate the logs of the driver.
+    pub request_id: RequestId,
+    /// The quotas of the whitelist entry of the signer, which apply instead of the
+    /// configured ones.
+    pub quotas: QuotaOverrides,
+}
+
+/// Event type emitted by the admin API to import the signature of a commitment
+/// that was signed by an external signer.
+#[derive(Debug)]
+pub struct ImportEvent {
+    /// The digest of the commitment request awaiting its signature.
+    pub digest: B256,
+    /// The signature over the digest.
+    pub signature: Signature,
+    /// The response channel.
+    pub response: oneshot::Sender<Result<SignedCommitment, Error>>,
+}
+
+/// The operations on the commitments dropped from the admin API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropAction {
+    /// Drop a commitment, pending the confirmation of the drop.
+    Drop,
+    /// Confirm the drop of a commitment, excluding it from the constraints.
+    Confirm,
+    /// Restore a dropped commitment.
+    Restore,
+}
+
+/// Event type emitted by the admin API to drop, confirm the drop of, or restore a commitment.
+#[derive(Debug)]
+pub struct DropEvent {
+    /// The operation on the commitment.
+    pub action: DropAction,
+    /// The digest of the commitment request.
+    pub digest: B256,
+    /// The operator requesting the operation, recorded in the audit log.
+    pub admin: String,
+    /// The response channel, with the dropped commitment after the operation.
+    pub response: oneshot::Sender<Result<DroppedCommitment, Error>>,
+}
+
+/// Event type emitted by the API to cancel a commitment on behalf of the signer of its request.
+#[derive(Debug)]
+pub struct CancelEvent {
+    /// The digest of the commitment request.
+    pub digest: B256,
+    /// The signer of the cancellation, which must be the signer of the request.
+    pub signer: Address,
+    /// The response channel, with whether the commitment was cancelled in time.
+    pub response: oneshot::Sender<Result<CancelOutcome, Error>>,
+}
+
+/// Event type emitted by the admin API to declare or cancel a maintenance window.
+#[derive(Debug)]
+pub enum MaintenanceEvent {
+    /// Declare a maintenance window, responding with the conflicting signed commitments.
+    Declare {
+        /// The validator and the slots it won't propose.
+        window: MaintenanceWindow,
+        /// The operator declaring the window, recorded in the audit log.
+        admin: String,
+        /// The response channel.
+        response: oneshot::Sender<Result<MaintenanceDeclaration, Error>>,
+    },
+    /// Cancel the maintenance window with the given identifier.
+    Cancel {
+        /// The identifier of the window.
+        id: u64,
+        /// The operator cancelling the window, recorded in the audit log.
+        admin: String,
+        /// The response channel, with the cancelled window.
+        response: oneshot::Sender<Result<DeclaredWindow, Error>>,
+    },
+}
+
+/// Event type emitted by the admin API to rebind a listener to another address.
+#[derive(Debug)]
+pub struct RebindEvent {
+    /// The listener to rebind, and its new address.
+    pub rebind: ListenerRebind,
+    /// The response channel, with the addresses of the listeners after the rebind.
+    pub response: oneshot::Sender<Result<ListenerAddrs, Error>>,
 }
 
 /// The inner commitments-API handler that implements the [CommitmentsApi] spec.
@@ -49,15 +222,210 @@ pub struct Event {
 pub struct CommitmentsApiInner {
     /// Event notification channel
     events: mpsc::Sender<Event>,
-    /// Optional whitelist of ECDSA public keys
-    #[allow(unused)]
-    whitelist: Option<HashSet<Address>>,
+    /// Optional whitelist of request signers, swept of its expired entries.
+    pub(super) whitelist: Option<SharedWhitelist>,
+    /// The gateways trusted to attest the original signer of the requests they relay.
+    pub(super) trusted_gateways: TrustedGateways,
+    /// Optional verifier of the EIP-1271 signatures of the smart-contract wallets.
+    pub(super) erc1271: Option<Erc1271Verifier>,
+    /// Optional secret of the JWT bearer tokens required by the JSON-RPC listeners.
+    pub(super) jwt_secret: Option<JwtSecret>,
+    /// Optional rate limiter of the inclusion requests of each signer.
+    pub(super) rate_limiter: Option<Arc<SignerRateLimiter>>,
+    /// Optional replay protection of the signed requests.
+    pub(super) replay_guard: Option<Arc<ReplayGuard>>,
+    /// Whether the signatures over the legacy digests of the requests, which aren't scoped to
+    /// the chain, are still accepted.
+    pub(super) accept_legacy_digests: bool,
+    /// The chain the sidecar is running on.
+    chain: ChainConfig,
+    /// The beacon chain genesis time in seconds.
+    genesis_time: u64,
+    /// The latest chain head, kept up to date by the driver.
+    head: ChainHead,
+    /// The commitment limits of the driver, served by `bolt_metadata` and adjusted from the
+    /// admin server.
+    limits: SharedLimits,
+    /// Debug capture of raw requests and responses.
+    pub(super) capture: RequestCapture,
+    /// Optional audit log of the commitment requests and the decisions on them.
+    pub(super) audit: Option<AuditLog>,
+    /// The persistent store, used to serve earnings reports and commitment lookups.
+    #[cfg(feature = "admin")]
+    pub(super) store: Option<Store>,
+    /// Whether the sidecar has completed its warmup and accepts requests.
+    pub(super) readiness: Readiness,
+    /// The connectivity of the driver to its upstream services, published by the driver.
+    health: Option<HealthReader>,
+    /// The slot-relative schedule of the driver tasks.
+    schedule: Vec<ScheduleEntry>,
+    /// Snapshots of the driver state, published by the driver.
+    snapshots: SnapshotReader,
+    /// The manifests of the commitments of each slot, published by the driver.
+    pub(super) manifests: ManifestReader,
+    /// The inclusion statuses of the issued commitments, tracked by the driver.
+    pub(super) inclusions: InclusionReader,
+    /// Import notification channel for externally signed commitments, if enabled.
+    #[cfg(feature = "admin")]
+    pub(super) imports: Option<mpsc::Sender<ImportEvent>>,
+    /// Drop notification channel for the commitments dropped by the operators, if enabled.
+    #[cfg(feature = "admin")]
+    pub(super) drops: Option<mpsc::Sender<DropEvent>>,
+    /// Cancellation notification channel for the commitments cancelled by their requesters.
+    pub(super) cancellations: Option<mpsc::Sender<CancelEvent>>,
+    /// Maintenance notification channel for the windows declared by the operators, if enabled.
+    #[cfg(feature = "admin")]
+    pub(super) maintenance: Option<mpsc::Sender<MaintenanceEvent>>,
+    /// The addresses the listeners are bound to, updated when they're rebound.
+    listeners: RwLock<ListenerAddrs>,
+    /// The previous addresses of the rebound listeners, until their connections are drained.
+    draining: RwLock<Vec<SocketAddr>>,
+    /// Rebind notification channel of the public and internal listeners, once they run.
+    #[cfg(feature = "admin")]
+    pub(super) rebinds: Option<mpsc::Sender<RebindEvent>>,
+    /// Shutdown signal of the listeners, once they run, closing the open WebSocket connections.
+    pub(super) shutdown: Option<watch::Receiver<()>>,
+    /// Signer of the responses, if response signing is enabled.
+    response_signer: Option<ResponseSigner>,
+    /// The identity document served at `GET /identity`, kept up to date by the driver.
+    identity: ServedIdentity,
+    /// The TLS certificates of the public listener, reloaded from the admin server.
+    #[cfg(all(feature = "admin", feature = "tls"))]
+    pub(super) tls: Option<Arc<SniResolver>>,
+    /// The wall clock, against which the whitelist entries and the identity are checked.
+    pub(super) clock: Clock,
+    /// The requests dispatched to the driver, awaiting its response.
+    pub(super) pending: Arc<PendingResponses>,
+    /// The permits of the commitment requests in flight, taken until they are responded to.
+    inflight: Arc<Semaphore>,
+}
+
+/// The state of a listener of the commitments API: the shared API handler,
+/// the methods served by the listener and the ones it exposes.
+#[derive(Debug, Clone)]
+struct Listener {
+    api: Arc<CommitmentsApiInner>,
+    profile: Arc<MethodProfile>,
+    methods: Arc<MethodRegistry>,
+}
+
+impl Listener {
+    fn new(
+        api: Arc<CommitmentsApiInner>,
+        profile: &MethodProfile,
+        methods: Arc<MethodRegistry>,
+    ) -> Self {
+        Self { api, profile: Arc::new(profile.clone()), methods }
+    }
+}
+
+impl FromRef<Listener> for RequestCapture {
+    fn from_ref(listener: &Listener) -> Self {
+        listener.api.capture.clone()
+    }
 }
 
 impl CommitmentsApiInner {
-    /// Create a new API server with an optional whitelist of ECDSA public keys.
-    pub fn new(events: mpsc::Sender<Event>) -> Self {
-        Self { events, whitelist: None }
+    /// Create a new API handler with the default state of the given server.
+    pub(super) fn new(events: mpsc::Sender<Event>, server: &CommitmentsApiServer) -> Self {
+        Self {
+            events,
+            whitelist: server.whitelist.clone(),
+            trusted_gateways: server.trusted_gateways.clone(),
+            erc1271: server.erc1271.clone(),
+            jwt_secret: server.jwt_secret,
+            rate_limiter: server.rate_limiter.clone(),
+            replay_guard: server.replay_window.map(|window| Arc::new(ReplayGuard::new(window))),
+            accept_legacy_digests: server.accept_legacy_digests,
+            chain: server.chain.clone(),
+            genesis_time: server.genesis_time,
+            head: server.head.clone(),
+            limits: server.limits.clone(),
+            capture: server.capture.clone().with_clock(server.clock.clone()),
+            audit: server.audit.clone(),
+            #[cfg(feature = "admin")]
+            store: server.store.clone(),
+            readiness: server.readiness.clone(),
+            health: server.health.clone(),
+            schedule: server.schedule.clone(),
+            snapshots: server.snapshots.clone(),
+            manifests: server.manifests.clone(),
+            inclusions: server.inclusions.clone(),
+            #[cfg(feature = "admin")]
+            imports: server.imports.clone(),
+            #[cfg(feature = "admin")]
+            drops: server.drops.clone(),
+            cancellations: server.cancellations.clone(),
+            #[cfg(feature = "admin")]
+            maintenance: server.maintenance.clone(),
+            listeners: RwLock::new(server.listener_addrs()),
+            draining: RwLock::default(),
+            #[cfg(feature = "admin")]
+            rebinds: None,
+            shutdown: None,
+            response_signer: server.response_signer.clone(),
+            identity: server.identity.clone(),
+            #[cfg(all(feature = "admin", feature = "tls"))]
+            tls: server.tls.clone(),
+            clock: server.clock.clone(),
+            pending: Arc::new(
+                PendingResponses::new(server.response_timeout, server.clock.clone())
+                    .with_alerts(server.alerts.clone(), server.max_pending_responses),
+            ),
+            inflight: Arc::new(Semaphore::new(server.max_inflight_requests)),
+        }
+    }
+
+    /// Returns the chain and timing parameters of the sidecar.
+    pub fn chain_info(&self) -> ChainInfo {
+        ChainInfo {
+            chain_id: self.chain.chain_id(),
+            chain_name: self.chain.name().to_string(),
+            genesis_time: self.genesis_time,
+            seconds_per_slot: self.chain.slot_time(),
+            slots_per_epoch: self.chain.slots_per_epoch(),
+            fork_version: format!("0x{}", hex::encode(self.chain.fork_version())),
+            head_slot: self.head.slot(),
+        }
+    }
+
+    /// Returns the id of the chain the request digests are scoped to.
+    pub fn chain_id(&self) -> u64 {
+        self.chain.chain_id()
+    }
+
+    /// Returns the EIP-712 domain of the typed-data request signatures.
+    pub fn eip712_domain(&self) -> Eip712Domain {
+        self.chain.eip712_domain()
+    }
+
+    /// Returns the limits of the sidecar and the next slot requests can target.
+    pub fn metadata(&self) -> SidecarMetadata {
+        SidecarMetadata::new(self.chain.chain_id(), &self.limits.read(), &self.snapshots.load())
+    }
+
+    /// Returns the optional features enabled on the sidecar, advertised in `bolt_getVersion`.
+    pub fn capabilities(&self) -> Vec<String> {
+        let mut capabilities = Vec::new();
+        if self.response_signer.is_some() {
+            capabilities.push(RESPONSE_SIGNING_CAPABILITY.to_string());
+        }
+        if self.replay_guard.is_some() {
+            capabilities.push(REPLAY_PROTECTION_CAPABILITY.to_string());
+        }
+        capabilities
+    }
+
+    /// Returns the runtime status of the sidecar.
+    pub fn status(&self) -> SidecarStatus {
+        SidecarStatus {
+            ready: self.readiness.is_ready(),
+            schedule: self.schedule.clone(),
+            listeners: *self.listeners.read(),
+            draining: self.draining.read().clone(),
+            failing_probe_stages: self.readiness.failing_probe_stages(),
+            state: StateSnapshot::clone(&self.snapshots.load()),
+        }
     }
 }
 
@@ -66,312 +434,3955 @@ impl CommitmentsApi for CommitmentsApiInner {
     async fn request_inclusion(
         &self,
         inclusion_request: InclusionRequest,
+        request_id: RequestId,
     ) -> Result<InclusionCommitment, Error> {
+        let commitment = self.dispatch(inclusion_request.into(), request_id).await?;
+        commitment.into_inclusion().ok_or(Error::Internal)
+    }
+
+    async fn request_exclusion(
+        &self,
+        exclusion_request: ExclusionRequest,
+        request_id: RequestId,
+    ) -> Result<ExclusionCommitment, Error> {
+        let commitment = self.dispatch(exclusion_request.into(), request_id).await?;
+        commitment.into_exclusion().ok_or(Error::Internal)
+    }
+}
+
+impl CommitmentsApiInner {
+    /// Dispatches a commitment request to the driver, and waits for its commitment.
+    async fn dispatch(
+        &self,
+        request: CommitmentRequest,
+        request_id: RequestId,
+    ) -> Result<SignedCommitment, Error> {
         let (response_tx, response_rx) = oneshot::channel();
+        let (digest, signer) = (request.digest(), request.signer());
 
+        // The permit is held until the request is responded to, so that the requests waiting
+        // for room in the event channel or for the driver are bounded
+        let Ok(_permit) = self.inflight.try_acquire() else {
+            warn!(%digest, "Too many commitment requests in flight, request rejected");
+            let retry_after_ms = OVERLOADED_RETRY_AFTER.as_millis() as u64;
+            return Err(Error::Overloaded { retry_after_ms });
+        };
+
+        let quotas = match (&self.whitelist, signer) {
+            (Some(list), Some(signer)) => list.read().get(&signer).map(|entry| entry.quotas),
+            _ => None,
+        };
         let event = Event {
-            request: CommitmentRequest::Inclusion(inclusion_request),
+            request,
             response: response_tx,
+            request_id,
+            quotas: quotas.unwrap_or_default(),
+        };
+
+        // The driver is gone if it crashed or is shutting down. If the client disconnects
+        // while the request is dispatched, the event is never received by the driver.
+        let dispatched = match self.events.try_send(event) {
+            Ok(()) => true,
+            Err(TrySendError::Full(event)) => self.events.send(event).await.is_ok(),
+            Err(TrySendError::Closed(_)) => false,
         };
+        if !dispatched {
+            warn!(%digest, "The driver is unavailable, request not processed");
+            return Err(Error::ServiceUnavailable);
+        }
 
-        self.events.send(event).await.unwrap();
+        // Once dispatched, the request is processed to completion even if this handler is
+        // cancelled: the response is awaited in a separate task, which orphans it if the
+        // handler is gone by the time it's signed.
+        let (forward_tx, forward_rx) = oneshot::channel();
+        let orphans = Arc::clone(&self.pending);
+        tokio::spawn(async move {
+            // Only inclusion commitments are kept for retrieval
+            if let Err(Ok(Ok(SignedCommitment::Inclusion(commitment)))) =
+                forward_tx.send(response_rx.await)
+            {
+                orphans.orphan(digest, commitment);
+            }
+        });
 
-        response_rx.await.map_err(|_| Error::Internal)?.map(|c| c.into())
+        // The driver has until the deadline of the pending response to respond
+        let mut pending = self.pending.register(digest, signer);
+        tokio::select! {
+            response = forward_rx => match response {
+                Ok(Ok(response)) => response,
+                Ok(Err(_)) | Err(_) => {
+                    warn!(%digest, "The driver dropped the request without responding");
+                    Err(Error::ServiceUnavailable)
+                }
+            },
+            err = pending.expired() => Err(err),
+        }
     }
 }
 
+/// Errors that prevent the commitments API server from starting.
+#[derive(Debug, thiserror::Error)]
+pub enum ServerError {
+    /// The address of the server couldn't be resolved.
+    #[error("Failed to resolve the server address: {0}")]
+    Resolve(#[source] io::Error),
+    /// The address of the server resolved to no socket address.
+    #[error("The server address doesn't resolve to any socket address")]
+    NoAddress,
+    /// A listener couldn't be bound to its address.
+    #[error("Failed to bind the {name} to {addr}: {source}")]
+    Bind {
+        /// The name of the listener.
+        name: &'static str,
+        /// The address it was bound to.
+        addr: SocketAddr,
+        /// The underlying error.
+        #[source]
+        source: io::Error,
+    },
+    /// A listener couldn't be bound to its Unix socket.
+    #[error("Failed to bind the {name} to {}: {source}", path.display())]
+    BindSocket {
+        /// The name of the listener.
+        name: &'static str,
+        /// The path of the socket file.
+        path: PathBuf,
+        /// The underlying error.
+        #[source]
+        source: io::Error,
+    },
+}
+
 /// The outer commitments-API JSON-RPC server that wraps the [CommitmentsApiInner] handler.
 pub struct CommitmentsApiServer {
     /// The address to bind the server to. This will be updated
     /// with the actual address after the server is started.
     addr: SocketAddr,
+    /// The address of the other IP family the host of the server resolves to, if any, which
+    /// the public listener is bound to as well.
+    dual_stack_addr: Option<SocketAddr>,
+    /// The chain the sidecar is running on.
+    chain: ChainConfig,
+    /// The beacon chain genesis time in seconds.
+    genesis_time: u64,
+    /// The latest chain head, shared with the driver.
+    head: ChainHead,
+    /// The commitment limits of the driver, served by `bolt_metadata`.
+    limits: SharedLimits,
+    /// The optional address to bind the admin server to. This will be updated
+    /// with the actual address after the server is started.
+    admin_addr: Option<SocketAddr>,
+    /// Optional static token required in the headers of the requests to the admin server.
+    #[cfg(feature = "admin")]
+    admin_token: Option<String>,
+    /// Debug capture of raw requests and responses, toggled via the admin server.
+    capture: RequestCapture,
+    /// Optional audit log of the commitment requests and the decisions on them.
+    audit: Option<AuditLog>,
+    /// The persistent store, used to serve earnings reports on the admin server.
+    #[cfg(feature = "admin")]
+    store: Option<Store>,
+    /// Optional whitelist of request signers. If set, requests from other signers are rejected.
+    whitelist: Option<SharedWhitelist>,
+    /// The gateways trusted to attest the original signer of the requests they relay.
+    trusted_gateways: TrustedGateways,
+    /// Optional verifier of the signatures of the smart-contract wallets, through the
+    /// execution client.
+    erc1271: Option<Erc1271Verifier>,
+    /// Optional secret of the JWT bearer tokens required by the JSON-RPC listeners.
+    jwt_secret: Option<JwtSecret>,
+    /// Optional rate limiter of the inclusion requests of each signer.
+    rate_limiter: Option<Arc<SignerRateLimiter>>,
+    /// Optional window of the timestamps of the signatures, enabling the replay protection.
+    replay_window: Option<Duration>,
+    /// Whether the signatures over the legacy request digests are still accepted.
+    accept_legacy_digests: bool,
+    /// Whether the sidecar has completed its warmup and accepts requests.
+    readiness: Readiness,
+    /// The connectivity of the driver to its upstream services, served at `GET /readyz`.
+    health: Option<HealthReader>,
+    /// The internal listening address, exposing the internal method profile.
+    internal_addr: Option<SocketAddr>,
+    /// The address the Prometheus metrics are served on, reported in the status.
+    metrics_addr: Option<SocketAddr>,
+    /// The methods exposed by each listener.
+    profiles: MethodProfiles,
+    /// The slot-relative schedule of the driver tasks, reported in the status.
+    schedule: Vec<ScheduleEntry>,
+    /// Snapshots of the driver state, reported in the status.
+    snapshots: SnapshotReader,
+    /// The manifests of the commitments of each slot, served to the block builder.
+    manifests: ManifestReader,
+    /// The inclusion statuses of the issued commitments, served to the clients.
+    inclusions: InclusionReader,
+    /// Optional channel to import externally signed commitments on the admin server.
+    #[cfg(feature = "admin")]
+    imports: Option<mpsc::Sender<ImportEvent>>,
+    /// Optional channel to drop and restore commitments on the admin server.
+    #[cfg(feature = "admin")]
+    drops: Option<mpsc::Sender<DropEvent>>,
+    /// Optional channel to cancel commitments on behalf of the signers of their requests.
+    cancellations: Option<mpsc::Sender<CancelEvent>>,
+    /// Optional channel to declare and cancel maintenance windows on the admin server.
+    #[cfg(feature = "admin")]
+    maintenance: Option<mpsc::Sender<MaintenanceEvent>>,
+    /// Optional signer of the responses of the public and internal listeners.
+    response_signer: Option<ResponseSigner>,
+    /// The identity document served at `GET /identity`, shared with the driver.
+    identity: ServedIdentity,
+    /// The TLS certificates of the public listener, if it serves TLS.
+    #[cfg(feature = "tls")]
+    tls: Option<Arc<SniResolver>>,
+    /// The socket file the public listener is served on instead of its TCP address, if any.
+    unix_socket: Option<UnixSocketConfig>,
+    /// The wall clock of the API handlers.
+    clock: Clock,
+    /// The time the driver has to respond to a commitment request.
+    response_timeout: Duration,
+    /// The number of commitment requests in flight above which new ones are rejected.
+    max_inflight_requests: usize,
+    /// The handle to raise alerts on.
+    alerts: Alerts,
+    /// The number of commitment requests awaiting the driver response above which an alert
+    /// is raised.
+    max_pending_responses: usize,
+    /// The requests awaiting the driver response, once the server is started.
+    pending: Option<Arc<PendingResponses>>,
     /// The shutdown signal.
     signal: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
 }
 
 impl fmt::Debug for CommitmentsApiServer {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("CommitmentsApiServer").field("addr", &self.addr).finish()
+        let mut debug = f.debug_struct("CommitmentsApiServer");
+        debug
+            .field("addr", &self.addr)
+            .field("dual_stack_addr", &self.dual_stack_addr)
+            .field("internal_addr", &self.internal_addr)
+            .field("admin_addr", &self.admin_addr)
+            .field("metrics_addr", &self.metrics_addr)
+            .field("profiles", &self.profiles)
+            .field("response_signer", &self.response_signer);
+        #[cfg(feature = "tls")]
+        debug.field("tls", &self.tls.is_some());
+        debug.field("unix_socket", &self.unix_socket).finish()
     }
 }
 
 impl CommitmentsApiServer {
     /// Creates the server with the given address and default shutdown signal (CTRL+C).
-    pub fn new<A: ToSocketAddrs>(addr: A) -> Self {
-        Self {
-            addr: addr.to_socket_addrs().unwrap().next().unwrap(),
+    ///
+    /// If the host resolves to both an IPv4 and an IPv6 address, such as `localhost`, the
+    /// public listener is bound to the first address of each family.
+    pub fn new<A: ToSocketAddrs>(addr: A) -> Result<Self, ServerError> {
+        let mut addrs = addr.to_socket_addrs().map_err(ServerError::Resolve)?;
+        let addr = addrs.next().ok_or(ServerError::NoAddress)?;
+        let dual_stack_addr = addrs.find(|other| other.is_ipv4() != addr.is_ipv4());
+
+        Ok(Self {
+            addr,
+            dual_stack_addr,
+            chain: ChainConfig::default(),
+            genesis_time: 0,
+            head: ChainHead::default(),
+            limits: SharedLimits::default(),
+            admin_addr: None,
+            #[cfg(feature = "admin")]
+            admin_token: None,
+            capture: RequestCapture::default(),
+            audit: None,
+            #[cfg(feature = "admin")]
+            store: None,
+            whitelist: None,
+            trusted_gateways: TrustedGateways::default(),
+            erc1271: None,
+            jwt_secret: None,
+            rate_limiter: None,
+            replay_window: None,
+            accept_legacy_digests: false,
+            readiness: Readiness::new(true),
+            health: None,
+            internal_addr: None,
+            metrics_addr: None,
+            profiles: MethodProfiles::default(),
+            schedule: Vec::new(),
+            snapshots: SnapshotReader::default(),
+            manifests: ManifestReader::default(),
+            inclusions: InclusionReader::default(),
+            #[cfg(feature = "admin")]
+            imports: None,
+            #[cfg(feature = "admin")]
+            drops: None,
+            cancellations: None,
+            #[cfg(feature = "admin")]
+            maintenance: None,
+            response_signer: None,
+            identity: ServedIdentity::default(),
+            #[cfg(feature = "tls")]
+            tls: None,
+            unix_socket: None,
+            clock: Clock::System,
+            response_timeout: DEFAULT_RESPONSE_TIMEOUT,
+            max_inflight_requests: DEFAULT_MAX_INFLIGHT_REQUESTS,
+            alerts: Alerts::default(),
+            max_pending_responses: DEFAULT_MAX_PENDING_RESPONSES,
+            pending: None,
             signal: Some(Box::pin(async {
                 let _ = tokio::signal::ctrl_c().await;
             })),
-        }
+        })
     }
 
-    /// Creates the server with the given address and shutdown signal.
-    pub fn with_shutdown<A, S>(self, addr: A, signal: S) -> Self
-    where
-        A: ToSocketAddrs,
-        S: Future<Output = ()> + Send + 'static,
-    {
-        Self {
-            addr: addr.to_socket_addrs().unwrap().next().unwrap(),
-            signal: Some(Box::pin(signal)),
-        }
+    /// Sets the chain parameters served by `bolt_getChainInfo`, along with
+    /// the chain head handle that the driver keeps up to date.
+    pub fn with_chain(self, chain: ChainConfig, genesis_time: u64, head: ChainHead) -> Self {
+        Self { chain, genesis_time, head, ..self }
     }
 
-    /// Runs the JSON-RPC server, sending events to the provided channel.
-    pub async fn run(&mut self, events_tx: mpsc::Sender<Event>) {
-        let api = Arc::new(CommitmentsApiInner::new(events_tx));
+    /// Sets the commitment limits shared with the driver, served by `bolt_metadata` and
+    /// adjusted from the admin server.
+    pub fn with_limits(self, limits: SharedLimits) -> Self {
+        Self { limits, ..self }
+    }
 
-        let router = Router::new().route("/", post(Self::handle_rpc)).with_state(api);
+    /// Enables the admin JSON-RPC server on the given address. Admin methods
+    /// are never served on the public address. Fails if the address doesn't resolve.
+    pub fn with_admin_addr<A: ToSocketAddrs>(self, addr: A) -> Result<Self, ServerError> {
+        Ok(Self { admin_addr: Some(resolve(addr)?), ..self })
+    }
 
-        let listener = match TcpListener::bind(self.addr).await {
-            Ok(listener) => listener,
-            Err(err) => {
-                error!(?err, "Failed to bind Commitments API server");
-                panic!("Failed to bind Commitments API server");
-            }
-        };
+    /// Sets the static token required in the [`admin::ADMIN_TOKEN_HEADER`] of the requests to
+    /// the admin server. No token is required if `None`.
+    #[cfg(feature = "admin")]
+    pub fn with_admin_token(self, admin_token: Option<String>) -> Self {
+        Self { admin_token, ..self }
+    }
 
-        let addr = listener.local_addr().expect("Failed to get local address");
-        self.addr = addr;
+    /// Enables the internal JSON-RPC server on the given address. It serves the same
+    /// methods as the public server, subject to its own method profile. Fails if the address
+    /// doesn't resolve.
+    pub fn with_internal_addr<A: ToSocketAddrs>(self, addr: A) -> Result<Self, ServerError> {
+        Ok(Self { internal_addr: Some(resolve(addr)?), ..self })
+    }
 
-        info!("Commitments RPC server bound to {addr}");
+    /// Sets the address the Prometheus metrics are served on, reported in the status.
+    pub fn with_metrics_addr(self, addr: SocketAddr) -> Self {
+        Self { metrics_addr: Some(addr), ..self }
+    }
 
-        let signal = self.signal.take().expect("Signal not set");
+    /// Sets the methods exposed by each listener.
+    pub fn with_profiles(self, profiles: MethodProfiles) -> Self {
+        Self { profiles, ..self }
+    }
 
-        tokio::spawn(async move {
-            if let Err(err) = axum::serve(listener, router).with_graceful_shutdown(signal).await {
-                error!(?err, "Commitments API Server error");
-            }
-        });
+    /// Sets the request capture handle, which can be toggled from the admin server.
+    pub fn with_capture(self, capture: RequestCapture) -> Self {
+        Self { capture, ..self }
     }
 
-    /// Returns the local addr the server is listening on (or configured with).
-    pub fn local_addr(&self) -> SocketAddr {
-        self.addr
+    /// Sets the audit log the commitment requests and the decisions on them are logged to.
+    pub fn with_audit_log(self, audit: Option<AuditLog>) -> Self {
+        Self { audit, ..self }
     }
 
-    /// Handler function for the root JSON-RPC path.
-    #[instrument(skip_all, name = "RPC", fields(method = %payload.method))]
-    async fn handle_rpc(
-        headers: HeaderMap,
-        State(api): State<Arc<CommitmentsApiInner>>,
-        WithRejection(Json(payload), _): WithRejection<Json<JsonPayload>, Error>,
-    ) -> Result<Json<JsonResponse>, Error> {
-        debug!("Received new request");
+    /// Sets the whitelist of request signers. Requests from other signers are rejected.
+    pub fn with_whitelist(self, whitelist: Option<Whitelist>) -> Self {
+        Self { whitelist: whitelist.map(|list| Arc::new(RwLock::new(list))), ..self }
+    }
 
-        let (signer, signature) = auth_from_headers(&headers).inspect_err(|e| {
-            error!("Failed to extract signature from headers: {:?}", e);
-        })?;
+    /// Sets the gateways trusted to attest the original signer of the requests they relay.
+    /// Relayed requests without a valid attestation of one of them are attributed to the
+    /// gateway that relayed them.
+    pub fn with_trusted_gateways(self, trusted_gateways: TrustedGateways) -> Self {
+        Self { trusted_gateways, ..self }
+    }
 
-        match payload.method.as_str() {
-            GET_VERSION_METHOD => {
-                let version_string = format!("bolt-sidecar-v{CARGO_PKG_VERSION}");
-                Ok(Json(JsonResponse {
-                    id: payload.id,
-                    result: Value::String(version_string),
-                    ..Default::default()
-                }))
-            }
+    /// Accepts the EIP-1271 signatures of the smart-contract wallets: when the signer recovered
+    /// from a signature doesn't match the one in the header, the contract at its address is
+    /// asked whether the signature is valid.
+    pub fn with_erc1271(self, verifier: Erc1271Verifier) -> Self {
+        Self { erc1271: Some(verifier), ..self }
+    }
 
-            REQUEST_INCLUSION_METHOD => {
-                let Some(request_json) = payload.params.first().cloned() else {
-                    return Err(RejectionError::ValidationFailed("Bad params".to_string()).into());
-                };
+    /// Requires a valid JWT bearer token signed with the secret on the JSON-RPC listeners, in
+    /// front of the signatures of the requests.
+    pub fn with_jwt_secret(self, jwt_secret: Option<JwtSecret>) -> Self {
+        Self { jwt_secret, ..self }
+    }
 
-                // Parse the inclusion request from the parameters
-                let mut inclusion_request: InclusionRequest = serde_json::from_value(request_json)
-                    .map_err(|e| RejectionError::ValidationFailed(e.to_string()))?;
+    /// Sets the rate of the inclusion requests of each signer. Requests above it are rejected
+    /// until the bucket of their signer is refilled.
+    pub fn with_rate_limits(self, rate_limits: Option<RateLimits>) -> Self {
+        let rate_limiter = rate_limits.map(|limits| Arc::new(SignerRateLimiter::new(limits)));
+        Self { rate_limiter, ..self }
+    }
 
-                // Set the signature here for later processing
-                inclusion_request.set_signature(signature);
+    /// Enables the replay protection of the signed requests: their signature must cover a
+    /// timestamp within the window of the current time, and can only be used once.
+    pub fn with_replay_window(self, replay_window: Option<Duration>) -> Self {
+        Self { replay_window, ..self }
+    }
 
-                let digest = inclusion_request.digest();
-                let recovered_signer = signature.recover_address_from_prehash(&digest)?;
+    /// Keeps accepting the signatures over the legacy digests of the inclusion requests,
+    /// which aren't scoped to the chain, during their deprecation window. A warning is logged
+    /// for each request signed that way.
+    pub fn with_legacy_digests(self, accept_legacy_digests: bool) -> Self {
+        Self { accept_legacy_digests, ..self }
+    }
 
-                if recovered_signer != signer {
-                    error!(
-                        ?recovered_signer,
-                        ?signer,
-                        "Recovered signer does not match the provided signer"
-                    );
+    /// Sets the readiness flag. Commitment requests are rejected until it is set.
+    pub fn with_readiness(self, readiness: Readiness) -> Self {
+        Self { readiness, ..self }
+    }
 
-                    return Err(Error::InvalidSignature(crate::primitives::SignatureError));
-                }
+    /// Sets the reader of the connectivity of the driver to its upstream services, served at
+    /// `GET /readyz`. Without it, `/readyz` only reflects the readiness flags.
+    pub fn with_health(self, health: HealthReader) -> Self {
+        Self { health: Some(health), ..self }
+    }
 
-                // Set the request signer
-                inclusion_request.set_signer(recovered_signer);
+    /// Sets the slot-relative schedule of the driver tasks, reported in the status.
+    pub fn with_schedule(self, schedule: Vec<ScheduleEntry>) -> Self {
+        Self { schedule, ..self }
+    }
 
-                info!(signer = ?recovered_signer, %digest, "New valid inclusion request received");
-                let inclusion_commitment = api.request_inclusion(inclusion_request).await?;
+    /// Sets the reader of the snapshots of the driver state, reported in the status.
+    pub fn with_snapshots(self, snapshots: SnapshotReader) -> Self {
+        Self { snapshots, ..self }
+    }
 
-                // Create the JSON-RPC response
-                let response = JsonResponse {
-                    id: payload.id,
-                    result: serde_json::to_value(inclusion_commitment).unwrap(),
-                    ..Default::default()
-                };
+    /// Sets the reader of the manifests of the commitments of each slot.
+    pub fn with_manifests(self, manifests: ManifestReader) -> Self {
+        Self { manifests, ..self }
+    }
 
-                Ok(Json(response))
-            }
-            other => {
-                error!("Unknown method: {}", other);
-                Err(Error::UnknownMethod)
-            }
-        }
+    /// Sets the reader of the inclusion statuses of the issued commitments.
+    pub fn with_inclusions(self, inclusions: InclusionReader) -> Self {
+        Self { inclusions, ..self }
     }
-}
 
-/// Extracts the signature ([SIGNATURE_HEADER]) from the HTTP headers.
-#[inline]
-fn auth_from_headers(headers: &HeaderMap) -> Result<(Address, Signature), Error> {
-    let auth = headers.get(SIGNATURE_HEADER).ok_or(Error::NoSignature)?;
+    /// Sets the channel to import externally signed commitments on the admin server.
+    #[cfg(feature = "admin")]
+    pub fn with_imports(self, imports: mpsc::Sender<ImportEvent>) -> Self {
+        Self { imports: Some(imports), ..self }
+    }
 
-    // Remove the "0x" prefix
-    let auth = auth.to_str().map_err(|_| Error::MalformedHeader)?;
+    /// Sets the channel to drop and restore commitments on the admin server.
+    #[cfg(feature = "admin")]
+    pub fn with_drops(self, drops: mpsc::Sender<DropEvent>) -> Self {
+        Self { drops: Some(drops), ..self }
+    }
 
-    let mut split = auth.split(':');
+    /// Sets the channel to cancel commitments on behalf of the signers of their requests.
+    pub fn with_cancellations(self, cancellations: mpsc::Sender<CancelEvent>) -> Self {
+        Self { cancellations: Some(cancellations), ..self }
+    }
 
-    let address = split.next().ok_or(Error::MalformedHeader)?;
-    let address = Address::from_str(address).map_err(|_| Error::MalformedHeader)?;
+    /// Sets the channel to declare and cancel maintenance windows on the admin server.
+    #[cfg(feature = "admin")]
+    pub fn with_maintenance(self, maintenance: mpsc::Sender<MaintenanceEvent>) -> Self {
+        Self { maintenance: Some(maintenance), ..self }
+    }
 
-    let sig = split.next().ok_or(Error::MalformedHeader)?;
-    let sig = Signature::from_str(sig)
-        .map_err(|_| Error::InvalidSignature(crate::primitives::SignatureError))?;
+    /// Enables response signing: every response of the public and internal listeners carries
+    /// a signature of the commitment key over its body and request id.
+    pub fn with_response_signer(self, signer: ResponseSigner) -> Self {
+        Self { response_signer: Some(signer), ..self }
+    }
 
-    Ok((address, sig))
-}
+    /// Sets the identity document handle served at `GET /identity`, kept up to date by
+    /// the driver.
+    pub fn with_identity(self, identity: ServedIdentity) -> Self {
+        Self { identity, ..self }
+    }
 
-#[cfg(test)]
-mod test {
-    use alloy::{
-        primitives::TxHash,
-        signers::{k256::SecretKey, local::PrivateKeySigner, Signer},
-    };
-    use serde_json::json;
+    /// Serves the public listener over TLS, presenting the certificates selected by the
+    /// resolver from the SNI of each connection.
+    #[cfg(feature = "tls")]
+    pub fn with_tls(self, resolver: Arc<SniResolver>) -> Self {
+        Self { tls: Some(resolver), ..self }
+    }
 
-    use crate::{
-        primitives::commitment::ECDSASignatureExt,
-        test_util::{create_signed_commitment_request, default_test_transaction},
-    };
+    /// Serves the public listener on a Unix domain socket instead of its TCP address, over
+    /// plain HTTP. The listener can't be rebound from the admin server.
+    pub fn with_unix_socket(self, socket: UnixSocketConfig) -> Self {
+        Self { unix_socket: Some(socket), ..self }
+    }
 
-    use super::*;
+    /// Sets the persistent store used to serve earnings reports on the admin server.
+    #[cfg(feature = "admin")]
+    pub fn with_store(self, store: Store) -> Self {
+        Self { store: Some(store), ..self }
+    }
 
-    #[tokio::test]
-    async fn test_signature_from_headers() {
-        let mut headers = HeaderMap::new();
-        let hash = TxHash::random();
-        let signer = PrivateKeySigner::random();
-        let addr = signer.address();
+    /// Sets the wall clock of the API handlers, which is the system clock by default.
+    pub fn with_clock(self, clock: Clock) -> Self {
+        Self { clock, ..self }
+    }
 
-        let expected_sig = signer.sign_hash(&hash).await.unwrap();
-        headers
-            .insert(SIGNATURE_HEADER, format!("{addr}:{}", expected_sig.to_hex()).parse().unwrap());
+    /// Sets the time the driver has to respond to a commitment request, after which the
+    /// client gets a timeout error.
+    pub fn with_response_timeout(self, response_timeout: Duration) -> Self {
+        Self { response_timeout, ..self }
+    }
 
-        let (address, signature) = auth_from_headers(&headers).unwrap();
-        assert_eq!(signature, expected_sig);
-        assert_eq!(address, addr);
+    /// Sets the number of commitment requests dispatched to the driver, or waiting to be,
+    /// above which new requests are rejected as overloaded instead of waiting.
+    pub fn with_max_inflight_requests(self, max_inflight_requests: usize) -> Self {
+        Self { max_inflight_requests, ..self }
     }
 
-    #[tokio::test]
-    async fn test_request_unauthorized() {
-        let _ = tracing_subscriber::fmt::try_init();
+    /// Raise an alert while more than `max_pending_responses` commitment requests are
+    /// awaiting the response of the driver.
+    pub fn with_alerts(self, alerts: Alerts, max_pending_responses: usize) -> Self {
+        Self { alerts, max_pending_responses, ..self }
+    }
 
-        let mut server = CommitmentsApiServer::new("0.0.0.0:0");
+    /// Sets the shutdown signal, instead of CTRL+C.
+    pub fn with_shutdown<S>(self, signal: S) -> Self
+    where
+        S: Future<Output = ()> + Send + 'static,
+    {
+        Self { signal: Some(Box::pin(signal)), ..self }
+    }
 
-        let (events_tx, _) = mpsc::channel(1);
+    /// Runs the JSON-RPC server, sending events to the provided channel.
+    ///
+    /// All listeners are bound before any of them serves requests, so that the status
+    /// always reports the bound addresses. Fails without serving anything if one of them
+    /// can't be bound.
+    pub async fn run(&mut self, events_tx: mpsc::Sender<Event>) -> Result<(), ServerError> {
+        let name = "Commitments RPC server";
+        let public = match self.unix_socket {
+            Some(ref socket) => PublicListener::Unix(bind_unix(name, socket)?),
+            None => {
+                let mut listeners = vec![bind(name, self.addr).await?];
+                self.addr = listeners[0].local_addr().expect("Failed to get local address");
+                if let Some(mut addr) = self.dual_stack_addr {
+                    // Both families are served on the same port, even if it was chosen
+                    if addr.port() == 0 {
+                        addr.set_port(self.addr.port());
+                    }
+                    let listener = bind(name, addr).await?;
+                    self.dual_stack_addr = listener.local_addr().ok();
+                    listeners.push(listener);
+                }
+                PublicListener::Tcp(listeners)
+            }
+        };
 
-        server.run(events_tx).await;
-        let addr = server.local_addr();
+        let internal = match self.internal_addr {
+            Some(addr) => Some(bind("Commitments RPC internal server", addr).await?),
+            None => None,
+        };
+        self.internal_addr = internal.as_ref().and_then(|listener| listener.local_addr().ok());
 
-        let sk = SecretKey::random(&mut rand::thread_rng());
-        let signer = PrivateKeySigner::from(sk.clone());
-        let tx = default_test_transaction(signer.address(), None);
-        let req = create_signed_commitment_request(&[tx], &sk, 12).await.unwrap();
+        // The admin address is only accepted with the admin feature
+        #[cfg(feature = "admin")]
+        let admin = match self.admin_addr {
+            Some(addr) => {
+                let listener = bind("Commitments RPC admin server", addr).await?;
+                self.admin_addr = listener.local_addr().ok();
+                Some(listener)
+            }
+            None => None,
+        };
 
-        let payload = json!({
-            "jsonrpc": "2.0",
-            "id": 1,
-            "method": "bolt_requestInclusion",
-            "params": [req]
+        // The shutdown signal is shared between all the listeners
+        let (shutdown_tx, shutdown_rx) = watch::channel(());
+        let signal = self.signal.take().expect("Signal not set");
+        tokio::spawn(async move {
+            signal.await;
+            let _ = shutdown_tx.send(());
         });
 
-        let url = format!("http://{addr}");
+        // The listeners are only rebound from the admin server
+        let (rebinds_tx, rebinds_rx) = mpsc::channel(4);
+        #[cfg(not(feature = "admin"))]
+        drop(rebinds_tx);
+        let api = Arc::new(CommitmentsApiInner {
+            #[cfg(feature = "admin")]
+            rebinds: Some(rebinds_tx),
+            shutdown: Some(shutdown_rx.clone()),
+            ..CommitmentsApiInner::new(events_tx, self)
+        });
+        if let Some(ref list) = api.whitelist {
+            tokio::spawn(whitelist::sweep_expired(Arc::downgrade(list), api.clock.clone()));
+        }
+        if let Some(ref limiter) = api.rate_limiter {
+            tokio::spawn(ratelimit::sweep_idle(Arc::downgrade(limiter), api.clock.clone()));
+        }
+        if let Some(ref guard) = api.replay_guard {
+            tokio::spawn(antireplay::sweep_expired(Arc::downgrade(guard), api.clock.clone()));
+        }
+        tokio::spawn(pending::sweep_expired(Arc::downgrade(&api.pending)));
+        self.pending = Some(api.pending.clone());
 
-        let client = reqwest::Client::new();
-        // client.post(url).header("content-type", "application/json").body(payload)
+        let rpc_methods = Arc::new(MethodRegistry::rpc());
+        // The streams of the manifests are behind the JWT like the JSON-RPC methods, unlike
+        // the probes
+        let jwt = middleware::from_fn_with_state(api.jwt_secret.clone(), Self::require_jwt);
+        let manifest_updates = get(Self::handle_manifest_updates).route_layer(jwt);
+        let rpc_router = |profile: &MethodProfile| {
+            let router = Router::new()
+                .route("/", post(Self::handle_rpc))
+                .route("/ready", get(Self::handle_ready))
+                .route("/health", get(Self::handle_health))
+                .route("/readyz", get(Self::handle_readyz))
+                .route("/identity", get(Self::handle_identity))
+                .route("/manifests/:slot", manifest_updates.clone());
+            #[cfg(feature = "ws")]
+            let router = router.route("/ws", get(Self::handle_ws));
+            router.with_state(Listener::new(api.clone(), profile, rpc_methods.clone()))
+        };
 
-        let response = client
-            .post(url)
-            .json(&payload)
-            .send()
-            .await
-            .unwrap()
-            .json::<JsonResponse>()
-            .await
-            .unwrap();
+        // The public and internal listeners can be rebound from the admin server
+        let mut rebindable = RebindableListeners::new(api.clone(), shutdown_rx.clone());
+        let router = rpc_router(&self.profiles.public);
+        match public {
+            PublicListener::Tcp(listeners) => {
+                #[cfg(feature = "tls")]
+                let acceptor = self.tls.as_ref().map(|resolver| resolver.acceptor());
+                #[cfg(not(feature = "tls"))]
+                let acceptor = None;
+                let kind = RebindableListener::Commitments;
+                rebindable.start(kind, name, listeners, router, acceptor);
+            }
+            PublicListener::Unix(listener) => {
+                let path = self.unix_socket.as_ref().expect("Socket is set").path.clone();
+                let shutdown = shutdown_signal(shutdown_rx.clone());
+                unix::serve(name, listener, path, router, shutdown);
+            }
+        }
 
-        // Assert unauthorized because of missing signature
-        assert_eq!(response.error.unwrap().code, -32003);
+        if let Some(listener) = internal {
+            let router = rpc_router(&self.profiles.internal);
+            let (kind, name) = (RebindableListener::Internal, "Commitments RPC internal server");
+            rebindable.start(kind, name, vec![listener], router, None);
+        }
+        tokio::spawn(rebindable.run(rebinds_rx));
+
+        #[cfg(feature = "admin")]
+        if let Some(listener) = admin {
+            let methods = Arc::new(MethodRegistry::admin());
+            let token = self.admin_token.clone();
+            let router = Router::new()
+                .route("/", post(Self::handle_admin_rpc))
+                .route("/manifests/:slot", get(Self::handle_manifest_updates))
+                .route(
+                    WHITELIST_PATH,
+                    get(Self::handle_get_whitelist).post(Self::handle_add_signer),
+                )
+                .route(WHITELIST_ENTRY_PATH, delete(Self::handle_remove_signer))
+                .route(LIMITS_PATH, put(Self::handle_update_limits))
+                .route_layer(middleware::from_fn_with_state(token, Self::require_admin_token))
+                .with_state(Listener::new(api, &self.profiles.admin, methods));
+            let shutdown = shutdown_signal(shutdown_rx);
+            serve("Commitments RPC admin server", listener, router, shutdown);
+        }
+
+        Ok(())
     }
 
-    #[tokio::test]
-    async fn test_request_success() {
-        let _ = tracing_subscriber::fmt::try_init();
+    /// Returns the addresses of the listeners, as reported in the status. The address
+    /// of a listener is only known once the server is running if it was configured
+    /// with port 0. The public listener has no address when served on a Unix socket.
+    pub fn listener_addrs(&self) -> ListenerAddrs {
+        let commitments = match self.unix_socket {
+            Some(_) => None,
+            None => Some(ListenerAddrs::reachable(self.addr)),
+        };
+        ListenerAddrs {
+            commitments,
+            internal: self.internal_addr.map(ListenerAddrs::reachable),
+            admin: self.admin_addr.map(ListenerAddrs::reachable),
+            metrics: self.metrics_addr.map(ListenerAddrs::reachable),
+        }
+    }
 
-        let mut server = CommitmentsApiServer::new("0.0.0.0:0");
+    /// Returns the whitelist of request signers, if enabled, to replace its entries when the
+    /// config is reloaded.
+    pub fn whitelist(&self) -> Option<SharedWhitelist> {
+        self.whitelist.clone()
+    }
 
-        let (events_tx, mut events) = mpsc::channel(1);
+    /// Returns the rate limiter of the inclusion requests, if enabled, to change its limits
+    /// when the config is reloaded.
+    pub fn rate_limiter(&self) -> Option<Arc<SignerRateLimiter>> {
+        self.rate_limiter.clone()
+    }
 
-        server.run(events_tx).await;
-        let addr = server.local_addr();
+    /// Returns the requests awaiting the driver response, once the server is started.
+    pub fn pending_responses(&self) -> Option<&PendingResponses> {
+        self.pending.as_deref()
+    }
 
-        let sk = SecretKey::random(&mut rand::thread_rng());
-        let signer = PrivateKeySigner::from(sk.clone());
-        let tx = default_test_transaction(signer.address(), None);
-        let req = create_signed_commitment_request(&[tx], &sk, 12).await.unwrap();
+    /// Returns the local addr the server is listening on (or configured with). When served on
+    /// a Unix socket, this is the unused TCP address: see [Self::bind_target].
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
 
-        let sig = req.signature().unwrap().to_hex();
+    /// Returns the local addrs the server is listening on (or configured with): the one of
+    /// [Self::local_addr], followed by the one of the other IP family if dual-stack.
+    pub fn local_addrs(&self) -> Vec<SocketAddr> {
+        std::iter::once(self.addr).chain(self.dual_stack_addr).collect()
+    }
 
-        let payload = json!({
-            "jsonrpc": "2.0",
-            "id": 1,
-            "method": "bolt_requestInclusion",
-            "params": [req]
-        });
+    /// Returns where the public listener is bound (or configured to be).
+    pub fn bind_target(&self) -> BindTarget {
+        match self.unix_socket {
+            Some(ref socket) => BindTarget::Unix(socket.path.clone()),
+            None => BindTarget::Tcp(self.addr),
+        }
+    }
 
-        let url = format!("http://{addr}");
+    /// Returns the local addr the internal server is listening on (or configured with), if enabled.
+    pub fn internal_local_addr(&self) -> Option<SocketAddr> {
+        self.internal_addr
+    }
 
-        let client = reqwest::Client::new();
-        // client.post(url).header("content-type", "application/json").body(payload)
+    /// Returns the local addr the admin server is listening on (or configured with), if enabled.
+    pub fn admin_local_addr(&self) -> Option<SocketAddr> {
+        self.admin_addr
+    }
 
-        let (tx, rx) = oneshot::channel();
+    #[cfg(feature = "admin")]
+    /// Handler function for the root JSON-RPC path of the admin server.
+    #[instrument(skip_all, name = "ADMIN", fields(method = %payload.method))]
+    async fn handle_admin_rpc(
+        State(Listener { api, profile, methods }): State<Listener>,
+        connect_info: Option<ConnectInfo<SocketAddr>>,
+        headers: HeaderMap,
+        WithRejection(Json(payload), _): WithRejection<Json<JsonPayload>, Error>,
+    ) -> Result<Json<JsonResponse>, Error> {
+        debug!("Received new admin request");
 
-        tokio::spawn(async move {
+        let client_ip = connect_info.map(|ConnectInfo(addr)| addr.ip());
+        let signatures = ContractSignatureCache::default();
+        methods.dispatch(&api, profile, &headers, payload, client_ip, signatures).await
+    }
+
+    #[cfg(feature = "admin")]
+    /// Rejects the requests to the admin server that don't carry the admin token, if one is
+    /// set.
+    async fn require_admin_token(
+        State(token): State<Option<String>>,
+        request: Request,
+        next: Next,
+    ) -> Response {
+        match token {
+            Some(token) if !admin::is_authorized(&token, request.headers()) => {
+                warn!(path = %request.uri().path(), "Rejected admin request without a valid token");
+                StatusCode::UNAUTHORIZED.into_response()
+            }
+            _ => next.run(request).await,
+        }
+    }
+
+    /// Rejects the requests without a valid JWT bearer token, if a secret is set.
+    async fn require_jwt(
+        State(secret): State<Option<JwtSecret>>,
+        request: Request,
+        next: Next,
+    ) -> Response {
+        if let Some(ref secret) = secret {
+            if let Err(err) = jwt::authorize(secret, request.headers()) {
+                warn!(%err, path = %request.uri().path(), "Rejected request");
+                return Error::from(err).into_response();
+            }
+        }
+        next.run(request).await
+    }
+
+    #[cfg(feature = "admin")]
+    /// Handler function for `GET /admin/whitelist`. Returns the entries of the whitelist,
+    /// including the expired ones that weren't swept yet.
+    async fn handle_get_whitelist(
+        State(Listener { api, .. }): State<Listener>,
+    ) -> Result<Json<Whitelist>, Error> {
+        let whitelist = api.whitelist.as_ref().ok_or(RejectionError::NotEnabled("whitelist"))?;
+        Ok(Json(whitelist.read().clone()))
+    }
+
+    #[cfg(feature = "admin")]
+    /// Handler function for `POST /admin/whitelist`. Adds the signer to the whitelist, or
+    /// replaces its entry, from its next request.
+    async fn handle_add_signer(
+        State(Listener { api, .. }): State<Listener>,
+        WithRejection(Json(addition), _): WithRejection<Json<WhitelistAddition>, Error>,
+    ) -> Result<Json<WhitelistEntry>, Error> {
+        let whitelist = api.whitelist.as_ref().ok_or(RejectionError::NotEnabled("whitelist"))?;
+
+        let WhitelistAddition { address, entry } = addition;
+        let replaced = whitelist.write().insert(address, entry.clone()).is_some();
+        info!(%address, ?entry, replaced, "Added signer to the whitelist from the admin API");
+        Ok(Json(entry))
+    }
+
+    #[cfg(feature = "admin")]
+    /// Handler function for `DELETE /admin/whitelist/:address`. Removes the signer from the
+    /// whitelist, returning its entry, or 404 if it wasn't whitelisted.
+    async fn handle_remove_signer(
+        State(Listener { api, .. }): State<Listener>,
+        Path(address): Path<Address>,
+    ) -> Result<Response, Error> {
+        let whitelist = api.whitelist.as_ref().ok_or(RejectionError::NotEnabled("whitelist"))?;
+
+        let Some(entry) = whitelist.write().remove(&address) else {
+            return Ok(StatusCode::NOT_FOUND.into_response());
+        };
+        info!(%address, ?entry, "Removed signer from the whitelist from the admin API");
+        Ok(Json(entry).into_response())
+    }
+
+    #[cfg(feature = "admin")]
+    /// Handler function for `PUT /admin/limits`. Adjusts the limits shared with the driver,
+    /// from its next validation, and returns them.
+    async fn handle_update_limits(
+        State(Listener { api, .. }): State<Listener>,
+        WithRejection(Json(update), _): WithRejection<Json<LimitsUpdate>, Error>,
+    ) -> Json<Limits> {
+        let mut limits = api.limits.write();
+        let previous = *limits;
+        update.apply(&mut limits);
+        info!(?previous, current = ?*limits, "Updated the limits from the admin API");
+        Json(*limits)
+    }
+
+    /// Handler function for the readiness endpoint. Returns 200 once the sidecar completed
+    /// its warmup, 503 before, while the chain heads are stale, while a probe stage fails or
+    /// once it's shutting down.
+    async fn handle_ready(State(Listener { api, .. }): State<Listener>) -> StatusCode {
+        if api.readiness.is_ready() {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+    }
+
+    /// Handler function for the liveness endpoint. Always returns 200 while the server runs.
+    async fn handle_health() -> StatusCode {
+        StatusCode::OK
+    }
+
+    /// Handler function for the dependency readiness endpoint. Returns 200 once the latest
+    /// calls of the driver to the execution client, the beacon node and the relay succeeded,
+    /// and 503 otherwise, with the status of each of them and the health of each constraint
+    /// submission target.
+    async fn handle_readyz(State(Listener { api, .. }): State<Listener>) -> Response {
+        let dependencies = api.health.as_ref().map(HealthReader::load);
+        let ready = match dependencies {
+            Some(ref dependencies) => dependencies.is_ready(),
+            None => api.readiness.is_ready(),
+        };
+
+        let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+        let relays = api.snapshots.load().relays.relays.clone();
+        (status, Json(ReadinessReport { ready, dependencies, relays })).into_response()
+    }
+
+    /// Handler function for the identity endpoint. Returns the identity document signed by
+    /// the validator key, or 404 if there is none or it has lapsed.
+    async fn handle_identity(State(Listener { api, .. }): State<Listener>) -> Response {
+        let now = api.clock.unix_secs();
+        match api.identity.get().filter(|identity| identity.message.is_valid_at(now)) {
+            Some(identity) => Json(identity).into_response(),
+            None => StatusCode::NOT_FOUND.into_response(),
+        }
+    }
+
+    /// Handler function for the manifest updates endpoint. Streams the revisions of the
+    /// manifest of a slot as server-sent events, until it is frozen. Served on the listeners
+    /// that expose `bolt_getSlotManifest`, and 404 on the other ones or without the `ws`
+    /// feature. Like the JSON-RPC methods, the streams require a valid JWT bearer token if a
+    /// secret is set.
+    async fn handle_manifest_updates(
+        State(Listener { api, profile, .. }): State<Listener>,
+        Path(slot): Path<Slot>,
+    ) -> Response {
+        if !cfg!(feature = "ws") || !profile.allows(GET_SLOT_MANIFEST_METHOD) {
+            return StatusCode::NOT_FOUND.into_response();
+        }
+
+        let events = api.manifests.subscribe(slot).map(|update| {
+            let event = SseEvent::default().event("manifest");
+            event.json_data(&*update)
+        });
+        Sse::new(events).keep_alive(KeepAlive::default()).into_response()
+    }
+
+    /// Handler function for the WebSocket endpoint. Upgrades the connection, then serves the
+    /// JSON-RPC requests of its messages with the methods exposed by the listener.
+    #[cfg(feature = "ws")]
+    async fn handle_ws(
+        State(Listener { api, profile, methods }): State<Listener>,
+        connect_info: Option<ConnectInfo<SocketAddr>>,
+        headers: HeaderMap,
+        upgrade: WebSocketUpgrade,
+    ) -> Response {
+        debug!("Received new WebSocket connection");
+        if let Some(ref secret) = api.jwt_secret {
+            if let Err(err) = jwt::authorize(secret, &headers) {
+                warn!(%err, "Rejected WebSocket connection");
+                return Error::from(err).into_response();
+            }
+        }
+
+        let client_ip = connect_info.map(|ConnectInfo(addr)| addr.ip());
+        let shutdown = api.shutdown.clone();
+        let session = ws::Session::new(api, profile, methods, headers, client_ip);
+        upgrade
+            .max_message_size(ws::MAX_MESSAGE_SIZE)
+            .on_upgrade(move |socket| session.run(socket, shutdown))
+    }
+
+    /// Handler function for the root JSON-RPC path. The requests of a batch are processed
+    /// concurrently, and their responses are returned in the same order.
+    ///
+    /// The call is identified by the [REQUEST_ID_HEADER] of the request, or by a new id,
+    /// which is echoed in the same header of the response and in the data of its errors.
+    #[instrument(
+        skip_all,
+        name = "RPC",
+        fields(method = %request.payload.method(), request_id = tracing::field::Empty)
+    )]
+    async fn handle_rpc(
+        State(Listener { api, profile, methods }): State<Listener>,
+        connect_info: Option<ConnectInfo<SocketAddr>>,
+        request: CapturedPayload,
+    ) -> Response {
+        let CapturedPayload { mut headers, payload, raw } = request;
+        let request_id = RequestId::from_headers(&headers);
+        tracing::Span::current().record("request_id", request_id.as_str());
+        debug!("Received new request");
+
+        // The JWT is checked before anything else, including the signature of the request
+        if let Some(ref secret) = api.jwt_secret {
+            if let Err(err) = jwt::authorize(secret, &headers) {
+                warn!(%err, "Rejected request");
+                let err = Error::from(err);
+                let status = err.http_status();
+                let error = tag_request_id(err.into_json_error(), &request_id);
+                let error = JsonResponse { error: Some(error), ..Default::default() };
+                let mut response = (status, Json(error)).into_response();
+                response.headers_mut().insert(REQUEST_ID_HEADER, request_id.header_value());
+                return response;
+            }
+        }
+
+        // The method handlers read the id from the headers
+        headers.insert(REQUEST_ID_HEADER, request_id.header_value());
+        let client_ip = connect_info.map(|ConnectInfo(addr)| addr.ip());
+        // The contract signatures are checked once for all the calls of a batch
+        let signatures = ContractSignatureCache::default();
+        let (api, methods, headers) = (&api, &methods, &headers);
+        let call = move |payload: JsonPayload| {
+            let (profile, signatures) = (Arc::clone(&profile), signatures.clone());
+            async move {
+                let headers = payload_headers(headers, &payload)?;
+                methods.dispatch(api, profile, &headers, payload, client_ip, signatures).await
+            }
+        };
+
+        let request_id = &request_id;
+        let (id, mut response) = match payload {
+            JsonRequest::Single(payload) => {
+                let id = payload.id.clone();
+                let response = match call(payload).await {
+                    Ok(response) => response.into_response(),
+                    Err(err) => {
+                        let status = err.http_status();
+                        let error = tag_request_id(err.into_json_error(), request_id);
+                        let error = JsonResponse { error: Some(error), ..Default::default() };
+                        (status, Json(error)).into_response()
+                    }
+                };
+                (id, response)
+            }
+            JsonRequest::Batch(payloads) => {
+                let ids = payloads
+                    .iter()
+                    .map(|payload| payload.as_ref().ok().and_then(|p| p.id.clone()))
+                    .collect::<Vec<_>>();
+
+                // A failed request only fails its own response
+                let (call, entries) = (&call, payloads.into_iter().zip(ids.clone()));
+                let responses = entries.map(|(payload, id)| async move {
+                    let result = match payload {
+                        Ok(payload) => call(payload).await,
+                        Err(err) => Err(Error::InvalidPayload(err)),
+                    };
+                    let mut response = batch_response(id, result);
+                    response.error = response.error.map(|err| tag_request_id(err, request_id));
+                    response
+                });
+                let responses = futures::future::join_all(responses).await;
+
+                // The response to a batch is bound to the array of its ids
+                let ids = ids.into_iter().map(Option::unwrap_or_default).collect();
+                (Some(Value::Array(ids)), Json(responses).into_response())
+            }
+        };
+
+        response.headers_mut().insert(REQUEST_ID_HEADER, request_id.header_value());
+
+        // Signed after serialization, so that the signature covers the exact body bytes
+        if let Some(ref signer) = api.response_signer {
+            response = signer.sign(id.as_ref(), response).await;
+        }
+
+        match raw {
+            Some(raw) => raw.capture(response).await,
+            None => response,
+        }
+    }
+}
+
+/// Resolves `addr` to its first socket address.
+fn resolve<A: ToSocketAddrs>(addr: A) -> Result<SocketAddr, ServerError> {
+    addr.to_socket_addrs().map_err(ServerError::Resolve)?.next().ok_or(ServerError::NoAddress)
+}
+
+/// Binds a listener on `addr`.
+async fn bind(name: &'static str, addr: SocketAddr) -> Result<TcpListener, ServerError> {
+    let listener =
+        TcpListener::bind(addr).await.map_err(|source| ServerError::Bind { name, addr, source })?;
+
+    if let Ok(addr) = listener.local_addr() {
+        info!("{name} bound to {addr}");
+    }
+
+    Ok(listener)
+}
+
+/// Binds a listener on the socket file.
+fn bind_unix(name: &'static str, socket: &UnixSocketConfig) -> Result<UnixListener, ServerError> {
+    unix::bind(socket).map_err(|source| {
+        let path = socket.path.clone();
+        ServerError::BindSocket { name, path, source }
+    })
+}
+
+/// Where the public listener of the commitments API is bound.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BindTarget {
+    /// A TCP address.
+    Tcp(SocketAddr),
+    /// The path of a Unix domain socket.
+    Unix(PathBuf),
+}
+
+impl fmt::Display for BindTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(addr) => write!(f, "{addr}"),
+            Self::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// The bound public listeners, before they serve requests.
+enum PublicListener {
+    Tcp(Vec<TcpListener>),
+    Unix(UnixListener),
+}
+
+/// A running listener of the commitments API, which can be replaced by one bound to
+/// another address.
+struct ListenerTask {
+    name: &'static str,
+    router: Router,
+    acceptor: Option<TlsAcceptor>,
+    /// The sockets the listener serves, one per address it is bound to.
+    sockets: Vec<ListenerSocket>,
+}
+
+/// A socket served by a listener.
+struct ListenerSocket {
+    /// The address the socket is bound to.
+    addr: SocketAddr,
+    /// Stops accepting connections and drains the open ones when sent.
+    drain: oneshot::Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+/// The public and internal listeners, owned by the task that rebinds them on request of
+/// the admin server.
+struct RebindableListeners {
+    api: Arc<CommitmentsApiInner>,
+    shutdown: watch::Receiver<()>,
+    tasks: HashMap<RebindableListener, ListenerTask>,
+}
+
+impl RebindableListeners {
+    fn new(api: Arc<CommitmentsApiInner>, shutdown: watch::Receiver<()>) -> Self {
+        Self { api, shutdown, tasks: HashMap::new() }
+    }
+
+    /// Starts serving the router on the sockets, over TLS if an acceptor is given.
+    fn start(
+        &mut self,
+        kind: RebindableListener,
+        name: &'static str,
+        listeners: Vec<TcpListener>,
+        router: Router,
+        acceptor: Option<TlsAcceptor>,
+    ) {
+        let sockets = listeners
+            .into_iter()
+            .map(|listener| {
+                let addr = listener.local_addr().expect("Failed to get local address");
+                let shutdown = self.shutdown.clone();
+                let (drain, handle) =
+                    spawn_listener(name, listener, router.clone(), acceptor.clone(), shutdown);
+                ListenerSocket { addr, drain, handle }
+            })
+            .collect();
+        self.tasks.insert(kind, ListenerTask { name, router, acceptor, sockets });
+    }
+
+    /// Serves the rebind requests until the shutdown signal.
+    async fn run(mut self, mut rebinds: mpsc::Receiver<RebindEvent>) {
+        let mut shutdown = self.shutdown.clone();
+        loop {
+            tokio::select! {
+                Some(RebindEvent { rebind, response }) = rebinds.recv() => {
+                    let _ = response.send(self.rebind(rebind).await);
+                }
+                _ = shutdown.changed() => break,
+            }
+        }
+    }
+
+    /// Binds the listener to its new address and starts serving there, then drains the
+    /// previous sockets of the listener in the background. If the new address can't be
+    /// bound, the previous listener keeps serving as if nothing happened.
+    ///
+    /// Returns the addresses of the listeners after the rebind.
+    async fn rebind(&mut self, rebind: ListenerRebind) -> Result<ListenerAddrs, Error> {
+        let ListenerRebind { listener: kind, addr } = rebind;
+        let Some(task) = self.tasks.get_mut(&kind) else {
+            return Err(RejectionError::ListenerNotEnabled(kind).into());
+        };
+
+        let listener = TcpListener::bind(addr).await.map_err(|err| {
+            error!(?err, %addr, "Failed to rebind {}", task.name);
+            RejectionError::BindFailed { addr, message: err.to_string() }
+        })?;
+        let new_addr = listener.local_addr().map_err(|_| Error::Internal)?;
+
+        let shutdown = self.shutdown.clone();
+        let (router, acceptor) = (task.router.clone(), task.acceptor.clone());
+        let (drain, handle) = spawn_listener(task.name, listener, router, acceptor, shutdown);
+        let socket = ListenerSocket { addr: new_addr, drain, handle };
+        let old_sockets = std::mem::replace(&mut task.sockets, vec![socket]);
+
+        let reachable = Some(ListenerAddrs::reachable(new_addr));
+        let listeners = {
+            let mut listeners = self.api.listeners.write();
+            match kind {
+                RebindableListener::Commitments => listeners.commitments = reachable,
+                RebindableListener::Internal => listeners.internal = reachable,
+            }
+            *listeners
+        };
+
+        // The previous sockets are only reported as closed once their connections are drained
+        for ListenerSocket { addr, drain, handle } in old_sockets {
+            let old_addr = ListenerAddrs::reachable(addr);
+            info!(%old_addr, %new_addr, "{} rebound, draining the previous listener", task.name);
+            self.api.draining.write().push(old_addr);
+            let _ = drain.send(());
+            let (api, name) = (self.api.clone(), task.name);
+            tokio::spawn(async move {
+                let _ = handle.await;
+                api.draining.write().retain(|addr| *addr != old_addr);
+                info!(%old_addr, "{name} previous listener drained and closed");
+            });
+        }
+
+        Ok(listeners)
+    }
+}
+
+/// Serves the router on the listener, over TLS if an acceptor is given, until the shutdown
+/// signal or the returned drain signal.
+fn spawn_listener(
+    name: &'static str,
+    listener: TcpListener,
+    router: Router,
+    acceptor: Option<TlsAcceptor>,
+    mut shutdown: watch::Receiver<()>,
+) -> (oneshot::Sender<()>, JoinHandle<()>) {
+    let (drain, drained) = oneshot::channel();
+    let signal = async move {
+        tokio::select! {
+            _ = shutdown.changed() => {}
+            _ = drained => {}
+        }
+    };
+
+    let handle = match acceptor {
+        #[cfg(feature = "tls")]
+        Some(acceptor) => serve_tls(name, listener, router, acceptor, signal),
+        _ => serve(name, listener, router, signal),
+    };
+    (drain, handle)
+}
+
+/// Resolves when the shutdown signal is received.
+pub(super) async fn shutdown_signal(mut shutdown: watch::Receiver<()>) {
+    let _ = shutdown.changed().await;
+}
+
+/// Serves the router on the listener until the shutdown signal, then waits for the open
+/// connections to complete their requests.
+fn serve<S>(
+    name: &'static str,
+    listener: TcpListener,
+    router: Router,
+    shutdown: S,
+) -> JoinHandle<()>
+where
+    S: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(async move {
+        // The client addresses are passed to the method handlers
+        let service = router.into_make_service_with_connect_info::<SocketAddr>();
+        if let Err(err) = axum::serve(listener, service).with_graceful_shutdown(shutdown).await {
+            error!(?err, "{name} error");
+        }
+    })
+}
+
+/// Serves the router over TLS on the listener until the shutdown signal. Unlike [serve],
+/// the connections that are open when the signal is received are not waited for.
+#[cfg(feature = "tls")]
+fn serve_tls<S>(
+    name: &'static str,
+    listener: TcpListener,
+    router: Router,
+    acceptor: TlsAcceptor,
+    shutdown: S,
+) -> JoinHandle<()>
+where
+    S: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(async move {
+        tokio::pin!(shutdown);
+        loop {
+            let (stream, remote_addr) = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(err) => {
+                        error!(?err, "{name} failed to accept a connection");
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        continue;
+                    }
+                },
+                _ = &mut shutdown => break,
+            };
+
+            let (acceptor, router) = (acceptor.clone(), router.clone());
+            tokio::spawn(async move {
+                let stream = match acceptor.accept(stream).await {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        debug!(?err, %remote_addr, "{name} TLS handshake failed");
+                        return;
+                    }
+                };
+
+                // The client addresses are passed to the method handlers
+                let service = TowerToHyperService::new(
+                    router.layer(Extension(ConnectInfo(remote_addr))),
+                );
+                let builder = auto::Builder::new(TokioExecutor::new());
+                let connection =
+                    builder.serve_connection_with_upgrades(TokioIo::new(stream), service);
+                if let Err(err) = connection.await {
+                    debug!(?err, %remote_addr, "{name} connection error");
+                }
+            });
+        }
+    })
+}
+
+/// Returns the response to a request of a batch, which carries its id even if it failed.
+pub(super) fn batch_response(
+    id: Option<Value>,
+    result: Result<Json<JsonResponse>, Error>,
+) -> JsonResponse {
+    match result {
+        Ok(Json(response)) => response,
+        Err(err) => JsonResponse { id, error: Some(err.into_json_error()), ..Default::default() },
+    }
+}
+
+/// Adds the request id to the data of a JSON-RPC error.
+fn tag_request_id(mut error: JsonError, request_id: &RequestId) -> JsonError {
+    let data = error.data.get_or_insert_with(|| Value::Object(Default::default()));
+    if let Value::Object(map) = data {
+        map.insert("request_id".to_string(), Value::String(request_id.to_string()));
+    }
+    error
+}
+
+/// Returns the headers a payload is authenticated with: its own signature, if it has one,
+/// replaces the [SIGNATURE_HEADER] of the HTTP request.
+pub(super) fn payload_headers<'a>(
+    headers: &'a HeaderMap,
+    payload: &JsonPayload,
+) -> Result<Cow<'a, HeaderMap>, Error> {
+    let Some(ref signature) = payload.signature else {
+        return Ok(Cow::Borrowed(headers));
+    };
+
+    let mut headers = headers.clone();
+    headers.insert(SIGNATURE_HEADER, signature.parse().map_err(|_| Error::MalformedHeader)?);
+    Ok(Cow::Owned(headers))
+}
+
+/// Extracts the signature ([SIGNATURE_HEADER]) from the HTTP headers, along with its
+/// [SignatureScheme] and timestamp.
+#[inline]
+pub(super) fn auth_from_headers(headers: &HeaderMap) -> Result<SignatureHeader, Error> {
+    let auth = headers.get(SIGNATURE_HEADER).ok_or(Error::NoSignature)?;
+
+    // Remove the "0x" prefix
+    let auth = auth.to_str().map_err(|_| Error::MalformedHeader)?;
+
+    let (scheme, auth) = SignatureScheme::split(auth)?;
+    let mut split = auth.split(':');
+
+    let address = split.next().ok_or(Error::MalformedHeader)?;
+    let address = parse_address(address).map_err(|_| Error::MalformedHeader)?;
+
+    let sig = split.next().ok_or(Error::MalformedHeader)?;
+    let sig = Signature::from_str(sig)
+        .map_err(|_| Error::InvalidSignature(crate::primitives::SignatureError))?;
+
+    let timestamp_ms = split.next().map(str::parse).transpose();
+    let timestamp_ms = timestamp_ms.map_err(|_| Error::MalformedHeader)?;
+
+    Ok(SignatureHeader { scheme, signer: address, signature: sig, timestamp_ms })
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        ffi::OsString,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use alloy::{
+        primitives::{FixedBytes, TxHash},
+        signers::{k256::SecretKey as K256SecretKey, local::PrivateKeySigner, Signer, SignerSync},
+    };
+    use reth_rpc_layer::Claims;
+    use serde_json::{json, Value};
+
+    use crate::{
+        builder::template::BlockTemplate,
+        client::{
+            commitments::{ClientError, ResponseVerificationError, ResponseVerifier},
+            relay::RelayForwarder,
+            rpc::RpcClient,
+        },
+        commitments::signing::RESPONSE_SIGNATURE_HEADER,
+        common::CARGO_PKG_VERSION,
+        config::{Limits, ReloadTargets, DEFAULT_RPC_PORT},
+        crypto::{bls::Signer as BlsSigner, external::ExternalSigner, SignerBLS},
+        primitives::{
+            commitment::ECDSASignatureExt, identity::DEFAULT_IDENTITY_VALIDITY, BlsPublicKey,
+            ConstraintsMessage, IdentityDocument, SignedConstraints, SignedIdentity,
+        },
+        commitments::{
+            antireplay::timestamped_digest,
+            audit::{AuditDecision, AuditEntry},
+            erc1271::ERC1271_MAGIC_VALUE,
+            methods::{GetCommitment, Method, RequestContext},
+            openrpc::{OpenRpcDocument, Schema},
+            profile::RPC_METHODS,
+            relay::RELAY_ATTESTATION_HEADER,
+            spec::{
+                CancelError, CommitmentDigest, InclusionCancellation, VersionInfo, EIP712_SCHEME,
+            },
+        },
+        state::{
+            basefee::BaseFeeStatus,
+            health::{Dependency, DependencyStatus},
+            relays::{RelayPerformance, RelayStatus},
+            snapshot::SlotBudget,
+            HealthPublisher, ManifestPublisher, SlotManifest, SnapshotPublisher, ValidationError,
+        },
+        test_util::{
+            create_signed_commitment_request, default_test_transaction, rewrite_config_file,
+            write_config_file, DeterministicEnv, LOOPBACK_EPHEMERAL,
+        },
+        Config,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_signature_from_headers() {
+        let mut headers = HeaderMap::new();
+        let hash = TxHash::random();
+        let signer = PrivateKeySigner::random();
+        let addr = signer.address();
+
+        let expected_sig = signer.sign_hash(&hash).await.unwrap();
+        headers
+            .insert(SIGNATURE_HEADER, format!("{addr}:{}", expected_sig.to_hex()).parse().unwrap());
+
+        let auth = auth_from_headers(&headers).unwrap();
+        assert_eq!(auth.scheme, SignatureScheme::Raw);
+        assert_eq!(auth.signature, expected_sig);
+        assert_eq!(auth.signer, addr);
+        assert_eq!(auth.timestamp_ms, None);
+
+        // The part after the signature is its timestamp
+        let value = format!("{addr}:{}:1700000000000", expected_sig.to_hex());
+        headers.insert(SIGNATURE_HEADER, value.parse().unwrap());
+        assert_eq!(auth_from_headers(&headers).unwrap().timestamp_ms, Some(1_700_000_000_000));
+        let value = format!("{addr}:{}:extra", expected_sig.to_hex());
+        headers.insert(SIGNATURE_HEADER, value.parse().unwrap());
+        assert!(matches!(auth_from_headers(&headers), Err(Error::MalformedHeader)));
+
+        let expected = SignatureHeader {
+            scheme: SignatureScheme::Eip712,
+            signer: addr,
+            signature: expected_sig,
+            timestamp_ms: Some(1_700_000_000_000),
+        };
+        headers.insert(SIGNATURE_HEADER, expected.header_value().parse().unwrap());
+        assert_eq!(auth_from_headers(&headers).unwrap(), expected);
+
+        headers.insert(SIGNATURE_HEADER, format!("eip1271:{addr}:0x00").parse().unwrap());
+        let err = auth_from_headers(&headers).unwrap_err();
+        assert!(
+            matches!(err, Error::UnsupportedSignatureScheme(ref scheme) if scheme == "eip1271")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_signature_header_checksum() {
+        let sig = PrivateKeySigner::random().sign_hash(&TxHash::random()).await.unwrap().to_hex();
+        let checksummed = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        let expected = Address::from_str(checksummed).unwrap();
+
+        let headers_with = |addr: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(SIGNATURE_HEADER, format!("{addr}:{sig}").parse().unwrap());
+            headers
+        };
+
+        // Checksummed and all-lowercase addresses are accepted and normalized
+        for addr in [checksummed.to_string(), checksummed.to_lowercase()] {
+            let auth = auth_from_headers(&headers_with(&addr)).unwrap();
+            assert_eq!(auth.signer, expected);
+        }
+
+        // Wrong checksum (last character flipped to uppercase)
+        let wrong = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD";
+        assert!(matches!(auth_from_headers(&headers_with(wrong)), Err(Error::MalformedHeader)));
+    }
+
+    #[tokio::test]
+    async fn test_request_unauthorized() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let mut server = env.server();
+
+        let (events_tx, _) = mpsc::channel(1);
+
+        server.run(events_tx).await.unwrap();
+        let addr = server.local_addr();
+
+        let sk = env.secret_key();
+        let signer = PrivateKeySigner::from(sk.clone());
+        let tx = default_test_transaction(signer.address(), None);
+        let req = create_signed_commitment_request(&[tx], &sk, 12).await.unwrap();
+
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "bolt_requestInclusion",
+            "params": [req]
+        });
+
+        let url = format!("http://{addr}");
+
+        let client = reqwest::Client::new();
+        // client.post(url).header("content-type", "application/json").body(payload)
+
+        let response = client
+            .post(url)
+            .json(&payload)
+            .send()
+            .await
+            .unwrap()
+            .json::<JsonResponse>()
+            .await
+            .unwrap();
+
+        // Assert unauthorized because of missing signature
+        assert_eq!(response.error.unwrap().code, -32003);
+    }
+
+    #[tokio::test]
+    async fn test_request_success() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let mut server = env.server();
+
+        let (events_tx, mut events) = mpsc::channel(1);
+
+        server.run(events_tx).await.unwrap();
+        let addr = server.local_addr();
+
+        let sk = env.secret_key();
+        let signer = PrivateKeySigner::from(sk.clone());
+        let tx = default_test_transaction(signer.address(), None);
+        let req = create_signed_commitment_request(&[tx], &sk, 12).await.unwrap();
+
+        let sig = req.signature().unwrap().to_hex();
+
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "bolt_requestInclusion",
+            "params": [req]
+        });
+
+        let url = format!("http://{addr}");
+
+        let client = reqwest::Client::new();
+        // client.post(url).header("content-type", "application/json").body(payload)
+
+        let (tx, rx) = oneshot::channel();
+
+        tokio::spawn(async move {
+            let response = client
+                .post(url)
+                .header(SIGNATURE_HEADER, format!("{}:{}", signer.address(), sig))
+                .json(&payload)
+                .send()
+                .await
+                .unwrap();
+
+            let json = response.json::<JsonResponse>().await.unwrap();
+
+            // Assert unauthorized because of missing signature
+            assert!(json.error.is_none());
+
+            let _ = tx.send(());
+        });
+
+        let Event { request, response, .. } = events.recv().await.unwrap();
+
+        let commitment_signer = env.signer();
+
+        let commitment = request.commit_and_sign(&commitment_signer).await.unwrap();
+
+        response.send(Ok(commitment)).unwrap();
+
+        rx.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_request_typed_data_signature() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let mut server = env.server();
+        let (events_tx, mut events) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let url = format!("http://{}", server.local_addr());
+
+        let sk = env.secret_key();
+        let signer = PrivateKeySigner::from(sk.clone());
+        let tx = default_test_transaction(signer.address(), None);
+        let req = create_signed_commitment_request(&[tx], &sk, 12).await.unwrap();
+
+        // Signed over the typed data instead of the raw digest
+        let inclusion = req.as_inclusion_request().unwrap();
+        let digest = inclusion.typed_digest(ChainConfig::default().chain_id());
+        let signature = signer.sign_hash(&digest).await.unwrap();
+        let header = SignatureScheme::Eip712.header_value(signer.address(), &signature);
+
+        let client = reqwest::Client::new();
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "bolt_requestInclusion",
+            "params": [req]
+        });
+        let request = client.post(&url).header(SIGNATURE_HEADER, &header).json(&payload);
+        let response = tokio::spawn(request.send());
+
+        let Event { request, response: responder, .. } = events.recv().await.unwrap();
+        let commitment = request.commit_and_sign(&env.signer()).await.unwrap();
+        responder.send(Ok(commitment)).unwrap();
+
+        let json = response.await.unwrap().unwrap().json::<JsonResponse>().await.unwrap();
+        assert!(json.error.is_none(), "{:?}", json.error);
+
+        // The raw signature doesn't match the typed-data digest, and vice versa
+        let raw = SignatureScheme::Eip712.header_value(signer.address(), req.signature().unwrap());
+        let response = client.post(&url).header(SIGNATURE_HEADER, raw).json(&payload);
+        let json = response.send().await.unwrap().json::<JsonResponse>().await.unwrap();
+        assert_eq!(json.error.unwrap().code, -32004);
+
+        // Methods signing a digest without a typed-data definition reject the scheme
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "bolt_cancelInclusion",
+            "params": [{ "digest": inclusion.digest() }]
+        });
+        let response = client.post(&url).header(SIGNATURE_HEADER, &header).json(&payload);
+        let json = response.send().await.unwrap().json::<JsonResponse>().await.unwrap();
+        let error = json.error.unwrap();
+        assert_eq!(error.code, -32019);
+        assert_eq!(error.data.unwrap()["scheme"], EIP712_SCHEME);
+    }
+
+    #[tokio::test]
+    async fn test_request_contract_signature() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        // An execution client answering every `eth_call` with the returned value
+        async fn handle(
+            State(returns): State<Arc<RwLock<FixedBytes<4>>>>,
+            Json(call): Json<Value>,
+        ) -> Json<Value> {
+            let mut word = [0; 32];
+            word[..4].copy_from_slice(returns.read().as_slice());
+            let result = format!("0x{}", hex::encode(word));
+            Json(json!({ "jsonrpc": "2.0", "id": call["id"], "result": result }))
+        }
+        let returns = Arc::new(RwLock::new(ERC1271_MAGIC_VALUE));
+        let rpc = Router::new().route("/", post(handle)).with_state(returns.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let rpc_url = format!("http://{}", listener.local_addr().unwrap()).parse().unwrap();
+        tokio::spawn(async move { axum::serve(listener, rpc).await.unwrap() });
+
+        let verifier = Erc1271Verifier::new(RpcClient::new(rpc_url));
+        let mut server = env.server().with_erc1271(verifier);
+        let (events_tx, mut events) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let url = format!("http://{}", server.local_addr());
+
+        // Signed by an owner of the wallet, on behalf of the wallet
+        let sk = env.secret_key();
+        let wallet = Address::repeat_byte(0x5a);
+        let tx = default_test_transaction(wallet, None);
+        let req = create_signed_commitment_request(&[tx], &sk, 12).await.unwrap();
+        let header = SignatureScheme::Raw.header_value(wallet, req.signature().unwrap());
+
+        let client = reqwest::Client::new();
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "bolt_requestInclusion",
+            "params": [req]
+        });
+        let request = client.post(&url).header(SIGNATURE_HEADER, &header).json(&payload);
+        let response = tokio::spawn(request.send());
+
+        let Event { request, response: responder, .. } = events.recv().await.unwrap();
+        assert_eq!(request.as_inclusion_request().unwrap().signer(), Some(wallet));
+        let commitment = request.commit_and_sign(&env.signer()).await.unwrap();
+        responder.send(Ok(commitment)).unwrap();
+
+        let json = response.await.unwrap().unwrap().json::<JsonResponse>().await.unwrap();
+        assert!(json.error.is_none(), "{:?}", json.error);
+
+        // The wallet no longer considers the signature valid
+        *returns.write() = FixedBytes([0xde, 0xad, 0xbe, 0xef]);
+        let response = client.post(&url).header(SIGNATURE_HEADER, &header).json(&payload);
+        let json = response.send().await.unwrap().json::<JsonResponse>().await.unwrap();
+        let error = json.error.unwrap();
+        assert_eq!(error.code, -32020);
+        assert_eq!(error.data.unwrap()["reason"], "invalid_magic_value");
+
+        // Without EIP-1271 support, the signature simply doesn't match the signer
+        let mut server = env.server();
+        let (events_tx, _events) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let url = format!("http://{}", server.local_addr());
+        let response = client.post(&url).header(SIGNATURE_HEADER, &header).json(&payload);
+        let json = response.send().await.unwrap().json::<JsonResponse>().await.unwrap();
+        assert_eq!(json.error.unwrap().code, -32004);
+    }
+
+    #[tokio::test]
+    async fn test_request_jwt() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let secret = JwtSecret::random();
+        let mut server = env.server().with_jwt_secret(Some(secret));
+        let (events_tx, mut events) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let url = format!("http://{}", server.local_addr());
+
+        let sk = env.secret_key();
+        let signer = PrivateKeySigner::from(sk.clone());
+        let tx = default_test_transaction(signer.address(), None);
+        let req = create_signed_commitment_request(&[tx], &sk, 12).await.unwrap();
+        let header = SignatureScheme::Raw.header_value(signer.address(), req.signature().unwrap());
+
+        let client = reqwest::Client::new();
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "bolt_requestInclusion",
+            "params": [req]
+        });
+        let request = || client.post(&url).header(SIGNATURE_HEADER, &header).json(&payload);
+
+        let iat = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let token = secret.encode(&Claims { iat, exp: Some(iat + 60) }).unwrap();
+        let response = tokio::spawn(request().bearer_auth(&token).send());
+        let Event { request: req, response: responder, .. } = events.recv().await.unwrap();
+        responder.send(Ok(req.commit_and_sign(&env.signer()).await.unwrap())).unwrap();
+        let json = response.await.unwrap().unwrap().json::<JsonResponse>().await.unwrap();
+        assert!(json.error.is_none(), "{:?}", json.error);
+
+        // The signature is still required underneath
+        let no_signature = client.post(&url).json(&payload).bearer_auth(&token);
+        let json = no_signature.send().await.unwrap().json::<JsonResponse>().await.unwrap();
+        assert_eq!(json.error.unwrap().code, -32003);
+
+        let expired = secret.encode(&Claims { iat: iat - 600, exp: Some(iat - 300) }).unwrap();
+        let forged = JwtSecret::random().encode(&Claims { iat, exp: None }).unwrap();
+        let cases = [
+            (None, "missing_token"),
+            (Some(expired), "expired_token"),
+            (Some(forged), "invalid_token"),
+        ];
+        for (token, reason) in cases {
+            let request = match token {
+                Some(token) => request().bearer_auth(token),
+                None => request(),
+            };
+            let response = request.send().await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert!(response.headers().contains_key(REQUEST_ID_HEADER));
+            let error = response.json::<JsonResponse>().await.unwrap().error.unwrap();
+            assert_eq!(error.code, -32022);
+            assert_eq!(error.data.unwrap()["reason"], reason);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_replay_protection() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let mut server = env.server().with_replay_window(Some(Duration::from_secs(30)));
+        let (events_tx, mut events) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let url = format!("http://{}", server.local_addr());
+
+        let sk = env.secret_key();
+        let signer = PrivateKeySigner::from(sk.clone());
+        let tx = default_test_transaction(signer.address(), None);
+        let req = create_signed_commitment_request(&[tx], &sk, 12).await.unwrap();
+        let digest =
+            req.as_inclusion_request().unwrap().chain_digest(ChainConfig::default().chain_id());
+        let header_at = |timestamp_ms: u64| {
+            let signature = signer.sign_hash_sync(&timestamped_digest(digest, timestamp_ms));
+            let header = SignatureHeader {
+                scheme: SignatureScheme::Raw,
+                signer: signer.address(),
+                signature: signature.unwrap(),
+                timestamp_ms: Some(timestamp_ms),
+            };
+            header.header_value()
+        };
+
+        let client = reqwest::Client::new();
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "bolt_requestInclusion",
+            "params": [req]
+        });
+        let send = |header: String| {
+            let request = client.post(&url).header(SIGNATURE_HEADER, header).json(&payload);
+            async move {
+                let response = request.send().await.unwrap();
+                response.json::<JsonResponse>().await.unwrap().error.map(|error| {
+                    assert_eq!(error.code, -32021);
+                    error.data.unwrap()["reason"].as_str().unwrap().to_string()
+                })
+            }
+        };
+
+        // Clock skew just inside the window passes
+        let now_ms = env.now() * 1_000;
+        let header = header_at(now_ms + 29_999);
+        let response = tokio::spawn(send(header.clone()));
+        let Event { request, response: responder, .. } = events.recv().await.unwrap();
+        responder.send(Ok(request.commit_and_sign(&env.signer()).await.unwrap())).unwrap();
+        assert_eq!(response.await.unwrap(), None);
+
+        // The exact replay is rejected, but not a new signature of the same request
+        assert_eq!(send(header).await.as_deref(), Some("replayed"));
+        let response = tokio::spawn(send(header_at(now_ms - 29_999)));
+        let Event { request, response: responder, .. } = events.recv().await.unwrap();
+        responder.send(Ok(request.commit_and_sign(&env.signer()).await.unwrap())).unwrap();
+        assert_eq!(response.await.unwrap(), None);
+
+        assert_eq!(send(header_at(now_ms - 30_001)).await.as_deref(), Some("timestamp_expired"));
+        let legacy = SignatureScheme::Raw.header_value(signer.address(), req.signature().unwrap());
+        assert_eq!(send(legacy).await.as_deref(), Some("missing_timestamp"));
+    }
+
+    #[tokio::test]
+    async fn test_request_id() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let mut server = env.server();
+        let (events_tx, mut events) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let url = format!("http://{}", server.local_addr());
+
+        let signer = env.signer();
+        let tx = default_test_transaction(signer.address(), None);
+        let req = create_signed_commitment_request(&[tx], &env.secret_key(), 12).await.unwrap();
+        let sig = req.signature().unwrap().to_hex();
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "bolt_requestInclusion",
+            "params": [req]
+        });
+
+        // The id set by the client is passed to the driver, and echoed in the response
+        let client = reqwest::Client::new();
+        let request = client
+            .post(&url)
+            .header(SIGNATURE_HEADER, format!("{}:{}", signer.address(), sig))
+            .header(REQUEST_ID_HEADER, "client-id-1")
+            .json(&payload)
+            .send();
+        let response = tokio::spawn(request);
+
+        let Event { request, response: commitment, request_id, .. } = events.recv().await.unwrap();
+        assert_eq!(request_id.as_str(), "client-id-1");
+        commitment.send(Ok(request.commit_and_sign(&signer).await.unwrap())).unwrap();
+
+        let response = response.await.unwrap().unwrap();
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "client-id-1");
+
+        // Otherwise an id is generated, and added to the data of the errors
+        let response = client.post(&url).json(&payload).send().await.unwrap();
+        let request_id = response.headers()[REQUEST_ID_HEADER].to_str().unwrap().to_string();
+        assert!(RequestId::parse(&request_id).is_some());
+
+        let error = response.json::<JsonResponse>().await.unwrap().error.unwrap();
+        assert_eq!(error.code, -32003);
+        assert_eq!(error.data.unwrap()["request_id"], request_id);
+    }
+
+    #[tokio::test]
+    async fn test_nonce_rejection_data() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let mut server = env.server();
+        let (events_tx, mut events) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+
+        let signer = env.signer();
+        let tx = default_test_transaction(signer.address(), Some(10));
+        let req = create_signed_commitment_request(&[tx], &env.secret_key(), 12).await.unwrap();
+        let sig = req.signature().unwrap().to_hex();
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "bolt_requestInclusion",
+            "params": [req]
+        });
+
+        let request = reqwest::Client::new()
+            .post(format!("http://{}", server.local_addr()))
+            .header(SIGNATURE_HEADER, format!("{}:{}", signer.address(), sig))
+            .json(&payload)
+            .send();
+        let response = tokio::spawn(request);
+
+        // The driver rejects the request, because the account nonce is higher
+        let Event { response: commitment, .. } = events.recv().await.unwrap();
+        commitment.send(Err(ValidationError::NonceTooLow(12, 10).into())).unwrap();
+
+        let response = response.await.unwrap().unwrap().json::<JsonResponse>().await.unwrap();
+        let error = response.error.unwrap();
+        let data = error.data.clone().unwrap();
+        assert_eq!(error.code, -32006);
+        assert_eq!(data["reason"], "nonce_too_low");
+        assert_eq!((data["expected"].clone(), data["got"].clone()), (json!(12), json!(10)));
+        assert_eq!(data["retryable"], false);
+
+        let err = ClientError::from_rpc(error.code, error.message, error.data);
+        assert_eq!(err, ClientError::NonceTooLow { expected: 12, got: 10 });
+    }
+
+    #[tokio::test]
+    async fn test_audit_log() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let path = std::env::temp_dir().join(format!("bolt-audit-{}.jsonl", rand::random::<u64>()));
+        let audit = AuditLog::spawn(path.clone(), 1024 * 1024);
+        let mut server = env.server().with_audit_log(Some(audit.clone()));
+        let (events_tx, mut events) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let url = format!("http://{}", server.local_addr());
+
+        let signer = env.signer();
+        let tx = default_test_transaction(signer.address(), None);
+        let req = create_signed_commitment_request(&[tx], &env.secret_key(), 12).await.unwrap();
+        let sig = req.signature().unwrap().to_hex();
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "bolt_requestInclusion",
+            "params": [req]
+        });
+        let client = reqwest::Client::new();
+        let request = || {
+            let request = client.post(&url).json(&payload);
+            let request = request.header(SIGNATURE_HEADER, format!("{}:{}", signer.address(), sig));
+            tokio::spawn(request.send())
+        };
+
+        // The driver issues a commitment for the first call, and rejects the second one
+        let response = request();
+        let Event { request: first, response: commitment, .. } = events.recv().await.unwrap();
+        let signed = first.commit_and_sign(&signer).await.unwrap();
+        commitment.send(Ok(signed.clone())).unwrap();
+        let accepted = response.await.unwrap().unwrap();
+        let accepted_id = accepted.headers()[REQUEST_ID_HEADER].to_str().unwrap().to_string();
+
+        let response = request();
+        let Event { response: commitment, .. } = events.recv().await.unwrap();
+        commitment.send(Err(ValidationError::NonceTooLow(12, 10).into())).unwrap();
+        let rejected = response.await.unwrap().unwrap();
+        let rejected_id = rejected.headers()[REQUEST_ID_HEADER].to_str().unwrap().to_string();
+
+        // Unauthenticated calls are rejected before their params are read, and not logged
+        let response = client.post(&url).json(&payload).send().await.unwrap();
+        assert_eq!(response.json::<JsonResponse>().await.unwrap().error.unwrap().code, -32003);
+
+        audit.flush().await;
+        let lines = std::fs::read_to_string(&path).unwrap();
+        let entries = lines.lines().map(|line| serde_json::from_str(line).unwrap());
+        let entries: Vec<AuditEntry> = entries.collect();
+        assert_eq!(entries.len(), 2);
+
+        let signature = serde_json::to_value(signed).unwrap()["signature"].clone();
+        let txs = &req.as_inclusion_request().unwrap().txs;
+        let tx_hashes = txs.iter().map(|tx| *tx.hash()).collect::<Vec<_>>();
+        for entry in &entries {
+            assert_eq!(entry.method, "bolt_requestInclusion");
+            assert_eq!(entry.signer, Some(signer.address()));
+            assert_eq!((entry.digest, entry.slot), (req.digest(), 12));
+            assert_eq!(entry.tx_hashes, tx_hashes);
+        }
+
+        assert_eq!(entries[0].request_id.as_str(), accepted_id);
+        let signature = signature.as_str().map(String::from);
+        assert_eq!(entries[0].decision, AuditDecision::Accepted { signature });
+
+        assert_eq!(entries[1].request_id.as_str(), rejected_id);
+        let AuditDecision::Rejected { code, ref reason, .. } = entries[1].decision else {
+            panic!("expected a rejection, got {:?}", entries[1].decision);
+        };
+        assert_eq!((code, reason.as_deref()), (-32006, Some("nonce_too_low")));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_signer() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let limits = RateLimits { requests_per_sec: 1, burst: 2 };
+        let mut server = env.server().with_rate_limits(Some(limits));
+        let (events_tx, mut events) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let addr = server.local_addr();
+
+        // The mock driver commits to every request
+        let commitment_signer = env.signer();
+        tokio::spawn(async move {
+            while let Some(Event { request, response, .. }) = events.recv().await {
+                let commitment = request.commit_and_sign(&commitment_signer).await.unwrap();
+                let _ = response.send(Ok(commitment));
+            }
+        });
+
+        let client = reqwest::Client::new();
+        let request_inclusion = |sk: K256SecretKey, slot: u64| {
+            let client = client.clone();
+            async move {
+                let signer = PrivateKeySigner::from(sk.clone());
+                let tx = default_test_transaction(signer.address(), None);
+                let req = create_signed_commitment_request(&[tx], &sk, slot).await.unwrap();
+                let header = format!("{}:{}", signer.address(), req.signature().unwrap().to_hex());
+                let payload = json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "bolt_requestInclusion",
+                    "params": [req]
+                });
+                let request = client.post(format!("http://{addr}")).json(&payload);
+                let response = request.header(SIGNATURE_HEADER, header).send().await.unwrap();
+                (response.status(), response.json::<JsonResponse>().await.unwrap())
+            }
+        };
+
+        // The burst of the signer is committed to, and the next request is rejected
+        let (alice, bob) = (env.secret_key(), env.secret_key());
+        for slot in [12, 13] {
+            let (_, response) = request_inclusion(alice.clone(), slot).await;
+            assert!(response.error.is_none(), "{:?}", response.error);
+        }
+        let (status, response) = request_inclusion(alice.clone(), 14).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        let error = response.error.unwrap();
+        assert_eq!(error.code, -32016);
+        assert_eq!(error.data.unwrap()["retry_after_ms"], 1_000);
+
+        // Other signers are unaffected
+        let (_, response) = request_inclusion(bob, 14).await;
+        assert!(response.error.is_none(), "{:?}", response.error);
+
+        // The signer can send a request again once a token is refilled
+        env.advance(Duration::from_secs(1));
+        let (_, response) = request_inclusion(alice, 14).await;
+        assert!(response.error.is_none(), "{:?}", response.error);
+    }
+
+    #[tokio::test]
+    async fn test_reload_rate_limits() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let path = write_config_file("rate_limit_per_sec = 1\nrate_limit_burst = 1\n");
+        let args = ["bolt-sidecar", "--config", path.to_str().unwrap()].map(OsString::from);
+        let mut config = Config::try_parse_from(args.to_vec()).unwrap();
+
+        let mut server = env.server().with_rate_limits(config.rate_limits);
+        let (events_tx, mut events) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let addr = server.local_addr();
+        let targets = ReloadTargets {
+            limits: Arc::new(RwLock::new(config.limits)),
+            whitelist: server.whitelist(),
+            rate_limiter: server.rate_limiter(),
+        };
+
+        // The mock driver commits to every request
+        let commitment_signer = env.signer();
+        tokio::spawn(async move {
+            while let Some(Event { request, response, .. }) = events.recv().await {
+                let commitment = request.commit_and_sign(&commitment_signer).await.unwrap();
+                let _ = response.send(Ok(commitment));
+            }
+        });
+
+        let sk = env.secret_key();
+        let request_inclusion = |slot: u64| {
+            let sk = sk.clone();
+            async move {
+                let signer = PrivateKeySigner::from(sk.clone());
+                let tx = default_test_transaction(signer.address(), None);
+                let req = create_signed_commitment_request(&[tx], &sk, slot).await.unwrap();
+                let header = format!("{}:{}", signer.address(), req.signature().unwrap().to_hex());
+                let payload = json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "bolt_requestInclusion",
+                    "params": [req]
+                });
+                let request = reqwest::Client::new().post(format!("http://{addr}")).json(&payload);
+                let response = request.header(SIGNATURE_HEADER, header).send().await.unwrap();
+                response.json::<JsonResponse>().await.unwrap()
+            }
+        };
+
+        let response = request_inclusion(12).await;
+        assert!(response.error.is_none(), "{:?}", response.error);
+        assert_eq!(request_inclusion(13).await.error.unwrap().code, -32016);
+
+        // The higher burst of the reloaded config applies right away, unlike the new port
+        rewrite_config_file(&path, "rate_limit_per_sec = 1\nrate_limit_burst = 3\nport = 9000\n");
+        let reload = config.reload_from(args.to_vec()).unwrap();
+        assert_eq!(reload.applied, vec!["rate_limit_burst".to_string()]);
+        assert!(reload.ignored.contains(&"port".to_string()), "{:?}", reload.ignored);
+        targets.apply(&mut config, reload);
+        assert_eq!(config.rate_limits, Some(RateLimits { requests_per_sec: 1, burst: 3 }));
+
+        let response = request_inclusion(13).await;
+        assert!(response.error.is_none(), "{:?}", response.error);
+
+        // The ignored option is reported again, as the running port didn't change
+        let reload = config.reload_from(args.to_vec()).unwrap();
+        assert!(reload.applied.is_empty(), "{:?}", reload.applied);
+        assert!(reload.ignored.contains(&"port".to_string()), "{:?}", reload.ignored);
+        assert_eq!(config.rpc_port, DEFAULT_RPC_PORT);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_overloaded_requests_rejected() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let mut server = env.server().with_max_inflight_requests(2);
+        let (events_tx, mut events) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let addr = server.local_addr();
+
+        let client = reqwest::Client::new();
+        let sk = env.secret_key();
+        let signer = PrivateKeySigner::from(sk.clone()).address();
+        let request_inclusion = |slot: u64| {
+            let (client, sk) = (client.clone(), sk.clone());
+            async move {
+                let tx = default_test_transaction(signer, None);
+                let req = create_signed_commitment_request(&[tx], &sk, slot).await.unwrap();
+                let header = format!("{signer}:{}", req.signature().unwrap().to_hex());
+                let payload = json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "bolt_requestInclusion",
+                    "params": [req]
+                });
+                let request = client.post(format!("http://{addr}")).json(&payload);
+                let response = request.header(SIGNATURE_HEADER, header).send().await.unwrap();
+                response.json::<JsonResponse>().await.unwrap()
+            }
+        };
+
+        // The mock driver holds on to the responses of the first requests, saturating the limit
+        let inflight = [tokio::spawn(request_inclusion(10)), tokio::spawn(request_inclusion(11))];
+        let held = [events.recv().await.unwrap(), events.recv().await.unwrap()];
+
+        // The excess requests are rejected right away instead of waiting
+        let excess = futures::future::join_all((12..44).map(request_inclusion));
+        let responses = tokio::time::timeout(Duration::from_secs(5), excess).await;
+        for response in responses.expect("Excess requests must not wait") {
+            let error = response.error.unwrap();
+            assert_eq!(error.code, -32017);
+            assert_eq!(error.data.unwrap()["retry_after_ms"], 250);
+        }
+
+        // The requests in flight complete, and free their permits
+        let commitment_signer = env.signer();
+        for Event { request, response, .. } in held {
+            let commitment = request.commit_and_sign(&commitment_signer).await.unwrap();
+            response.send(Ok(commitment)).unwrap();
+        }
+        for request in inflight {
+            assert!(request.await.unwrap().error.is_none());
+        }
+        let next = tokio::spawn(request_inclusion(44));
+        let Event { request, response, .. } = events.recv().await.unwrap();
+        let commitment = request.commit_and_sign(&commitment_signer).await.unwrap();
+        response.send(Ok(commitment)).unwrap();
+        assert!(next.await.unwrap().error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_requests() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let readiness = Readiness::new(true);
+        let mut server = env.server().with_readiness(readiness.clone());
+        let (events_tx, mut events) = mpsc::channel(4);
+        server.run(events_tx).await.unwrap();
+        let addr = server.local_addr();
+
+        let client = reqwest::Client::new();
+        let sk = env.secret_key();
+        let signer = PrivateKeySigner::from(sk.clone()).address();
+        let request_inclusion = |slot: u64| {
+            let (client, sk) = (client.clone(), sk.clone());
+            async move {
+                let tx = default_test_transaction(signer, None);
+                let req = create_signed_commitment_request(&[tx], &sk, slot).await.unwrap();
+                let header = format!("{signer}:{}", req.signature().unwrap().to_hex());
+                let payload = json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "bolt_requestInclusion",
+                    "params": [req]
+                });
+                let request = client.post(format!("http://{addr}")).json(&payload);
+                let response = request.header(SIGNATURE_HEADER, header).send().await.unwrap();
+                (response.status(), response.json::<JsonResponse>().await.unwrap())
+            }
+        };
+
+        // The request is in the channel when the shutdown starts
+        let inflight = tokio::spawn(request_inclusion(10));
+        while server.pending_responses().unwrap().is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // Like the driver: new requests are rejected, then the channel is drained
+        readiness.set_shutting_down();
+        events.close();
+        let ready = client.get(format!("http://{addr}/ready")).send().await.unwrap().status();
+        assert_eq!(ready, StatusCode::SERVICE_UNAVAILABLE);
+        let (status, response) = request_inclusion(11).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.error.unwrap().code, -32018);
+
+        let commitment_signer = env.signer();
+        let mut drained = 0;
+        while let Some(Event { request, response, .. }) = events.recv().await {
+            let commitment = request.commit_and_sign(&commitment_signer).await.unwrap();
+            response.send(Ok(commitment)).unwrap();
+            drained += 1;
+        }
+        assert_eq!(drained, 1);
+
+        // The request in flight still gets its signed commitment
+        let (status, response) = inflight.await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert!(response.error.is_none(), "{:?}", response.error);
+        serde_json::from_value::<InclusionCommitment>(response.result).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_request_expires_without_response() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let mut server = env.server().with_response_timeout(Duration::from_secs(2));
+        let (events_tx, mut events) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let addr = server.local_addr();
+
+        let signer = env.signer();
+        let tx = default_test_transaction(signer.address(), None);
+        let req = create_signed_commitment_request(&[tx], &env.secret_key(), 12).await.unwrap();
+        let sig = req.signature().unwrap().to_hex();
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "bolt_requestInclusion",
+            "params": [req]
+        });
+
+        let request = reqwest::Client::new()
+            .post(format!("http://{addr}"))
+            .header(SIGNATURE_HEADER, format!("{}:{}", signer.address(), sig))
+            .json(&payload)
+            .send();
+        let request = tokio::spawn(request);
+
+        // The mock driver holds on to the response sender, and never responds
+        let leaked = events.recv().await.unwrap();
+        let pending = server.pending_responses().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending.entries()[0].signer, Some(signer.address()));
+
+        env.advance(Duration::from_secs(2));
+        let response = request.await.unwrap().unwrap().json::<JsonResponse>().await.unwrap();
+        let error = response.error.unwrap();
+        assert_eq!(error.code, -32013);
+        assert_eq!(error.data.unwrap()["elapsed_ms"], 2_000);
+
+        // The registry is empty again, and a late response of the driver is dropped
+        assert!(pending.is_empty());
+        assert!(leaked.response.is_closed());
+    }
+
+    #[tokio::test]
+    async fn test_response_just_before_timeout() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let mut server = env.server().with_response_timeout(Duration::from_secs(2));
+        let (events_tx, mut events) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let addr = server.local_addr();
+
+        let signer = env.signer();
+        let tx = default_test_transaction(signer.address(), None);
+        let req = create_signed_commitment_request(&[tx], &env.secret_key(), 12).await.unwrap();
+        let sig = req.signature().unwrap().to_hex();
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "bolt_requestInclusion",
+            "params": [req]
+        });
+
+        let request = reqwest::Client::new()
+            .post(format!("http://{addr}"))
+            .header(SIGNATURE_HEADER, format!("{}:{}", signer.address(), sig))
+            .json(&payload)
+            .send();
+        let request = tokio::spawn(request);
+
+        // The driver responds a millisecond before the deadline, after several sweeps
+        let Event { request: commitment_request, response, .. } = events.recv().await.unwrap();
+        env.advance(Duration::from_millis(1_999));
+        tokio::time::sleep(pending::PENDING_SWEEP_INTERVAL * 3).await;
+        let commitment = commitment_request.commit_and_sign(&signer).await.unwrap();
+        response.send(Ok(commitment)).unwrap();
+
+        let response = request.await.unwrap().unwrap().json::<JsonResponse>().await.unwrap();
+        assert!(response.error.is_none());
+        assert!(server.pending_responses().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_request_without_driver() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let mut server = env.server();
+        let (events_tx, events) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let addr = server.local_addr();
+
+        let signer = env.signer();
+        let tx = default_test_transaction(signer.address(), None);
+        let req = create_signed_commitment_request(&[tx], &env.secret_key(), 12).await.unwrap();
+        let sig = req.signature().unwrap().to_hex();
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "bolt_requestInclusion",
+            "params": [req]
+        });
+
+        // The driver is gone before the request is sent
+        drop(events);
+        let response = reqwest::Client::new()
+            .post(format!("http://{addr}"))
+            .header(SIGNATURE_HEADER, format!("{}:{}", signer.address(), sig))
+            .json(&payload)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let error = response.json::<JsonResponse>().await.unwrap().error.unwrap();
+        assert_eq!(error.code, -32014);
+        assert_eq!(error.data.unwrap()["retryable"], true);
+    }
+
+    #[tokio::test]
+    async fn test_batch_requests() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let mut server = env.server();
+        let (events_tx, mut events) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let addr = server.local_addr();
+
+        let signer = env.signer();
+        let tx = default_test_transaction(signer.address(), None);
+        let valid = create_signed_commitment_request(&[tx], &env.secret_key(), 12).await.unwrap();
+        let tx = default_test_transaction(signer.address(), Some(1));
+        let forged = create_signed_commitment_request(&[tx], &env.secret_key(), 12).await.unwrap();
+        let forged_sig = PrivateKeySigner::random().sign_hash(&forged.digest()).await.unwrap();
+
+        // Each request is authenticated by its own signature
+        let entry = |id: u64, method: &str, params: Value, sig: String| {
+            let signature = format!("{}:{sig}", signer.address());
+            json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": method,
+                "params": params,
+                "signature": signature,
+            })
+        };
+        let batch = json!([
+            entry(1, "bolt_requestInclusion", json!([valid]), valid.signature().unwrap().to_hex()),
+            entry(2, "bolt_requestInclusion", json!([forged]), forged_sig.to_hex()),
+            entry(3, "bolt_doesNotExist", json!([]), forged_sig.to_hex()),
+        ]);
+        let request = reqwest::Client::new().post(format!("http://{addr}")).json(&batch).send();
+        let request = tokio::spawn(request);
+
+        // Only the valid request reaches the driver
+        let Event { request: commitment_request, response, .. } = events.recv().await.unwrap();
+        assert_eq!(commitment_request.as_inclusion_request().unwrap().digest(), valid.digest());
+        let commitment = commitment_request.commit_and_sign(&signer).await.unwrap();
+        response.send(Ok(commitment)).unwrap();
+
+        let response = request.await.unwrap().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let responses = response.json::<Vec<JsonResponse>>().await.unwrap();
+        let ids = responses.iter().map(|response| response.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids, [Some(json!(1)), Some(json!(2)), Some(json!(3))]);
+        assert!(responses[0].error.is_none());
+        assert_eq!(responses[1].error.as_ref().unwrap().code, -32004);
+        assert_eq!(responses[2].error.as_ref().unwrap().code, -32601);
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_client_disconnect() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let server = env.server();
+        let (events_tx, mut events) = mpsc::channel(1);
+        let api = CommitmentsApiInner::new(events_tx.clone(), &server);
+        let ctx = RequestContext::new(None, Arc::new(MethodProfile::all(RPC_METHODS)));
+
+        let signer = env.signer();
+        let tx = default_test_transaction(signer.address(), None);
+        let req = create_signed_commitment_request(&[tx], &env.secret_key(), 12).await.unwrap();
+        let req = req.into_inclusion_request().unwrap();
+        let digest = CommitmentDigest { digest: req.digest() };
+
+        // A client disconnecting before the request is dispatched aborts it: the driver is
+        // busy, so the request is still waiting to be sent
+        let request = CommitmentRequest::Inclusion(req.clone());
+        let (response, request_id) = (oneshot::channel().0, RequestId::generate());
+        let quotas = QuotaOverrides::default();
+        events_tx.send(Event { request, response, request_id, quotas }).await.unwrap();
+        let cancelled = api.request_inclusion(req.clone(), RequestId::generate());
+        assert!(tokio::time::timeout(Duration::from_millis(50), cancelled).await.is_err());
+        events.recv().await.unwrap();
+        assert!(events.try_recv().is_err());
+        assert!(api.pending.is_empty());
+
+        // A client disconnecting once the request is dispatched doesn't cancel it
+        let Event { request, response, .. } = tokio::select! {
+            _ = api.request_inclusion(req, RequestId::generate()) => {
+                unreachable!("The driver didn't respond")
+            }
+            event = events.recv() => event.unwrap(),
+        };
+        assert!(GetCommitment.handle(&api, &ctx, digest).await.unwrap().is_none());
+        let commitment = request.commit_and_sign(&signer).await.unwrap();
+        response.send(Ok(commitment.clone())).unwrap();
+
+        // The commitment signed for the gone client can be retrieved by digest
+        let orphaned = tokio::time::timeout(Duration::from_secs(1), async {
+            loop {
+                match GetCommitment.handle(&api, &ctx, digest).await.unwrap() {
+                    Some(orphaned) => break orphaned,
+                    None => tokio::task::yield_now().await,
+                }
+            }
+        });
+        assert_eq!(orphaned.await.unwrap(), commitment.into_inclusion().unwrap());
+        assert!(api.pending.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_relayed_request_attribution() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+        let (gateway, untrusted) = (env.signer(), env.signer());
+        let sk = env.secret_key();
+        let signer = PrivateKeySigner::from(sk.clone()).address();
+
+        let whitelist = Whitelist::from_addresses([signer, untrusted.address()]);
+        let mut server = env
+            .server()
+            .with_whitelist(Some(whitelist))
+            .with_trusted_gateways(TrustedGateways::new([gateway.address()]));
+        let (events_tx, mut events) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let downstream: reqwest::Url = format!("http://{}", server.local_addr()).parse().unwrap();
+        let forwarder = |gateway: &PrivateKeySigner| {
+            RelayForwarder::new(downstream.clone(), gateway.clone()).with_clock(env.clock())
+        };
+
+        let mut requests = Vec::new();
+        for nonce in 0..2 {
+            let tx = default_test_transaction(signer, Some(nonce));
+            let req = create_signed_commitment_request(&[tx], &sk, 12).await.unwrap();
+            let mut headers = HeaderMap::new();
+            let auth = format!("{signer}:{}", req.signature().unwrap().to_hex());
+            headers.insert(SIGNATURE_HEADER, auth.parse().unwrap());
+            let payload = json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "bolt_requestInclusion",
+                "params": [req]
+            });
+            requests.push((headers, payload));
+        }
+
+        // Requests are attributed to their original signer only if the gateway is trusted
+        for (gateway, expected) in [(&gateway, signer), (&untrusted, untrusted.address())] {
+            let relay = forwarder(gateway);
+            let (headers, payload) = requests[0].clone();
+            let relayed = tokio::spawn(async move { relay.forward(&headers, &payload).await });
+
+            let Event { request, response, .. } = events.recv().await.unwrap();
+            assert_eq!(request.as_inclusion_request().unwrap().signer(), Some(expected));
+            response.send(Err(Error::Internal)).unwrap();
+            relayed.await.unwrap().unwrap();
+        }
+
+        // An attestation of another request is attributed to its signer, which isn't whitelisted
+        let (headers, payload) = &requests[0];
+        let attestation = forwarder(&gateway).attest(headers, payload).await.unwrap().unwrap();
+        let (mut headers, payload) = requests[1].clone();
+        headers.insert(RELAY_ATTESTATION_HEADER, attestation.to_header_value());
+
+        let request = reqwest::Client::new().post(downstream).headers(headers).json(&payload);
+        let response = request.send().await.unwrap().json::<JsonResponse>().await.unwrap();
+        let error = response.error.unwrap();
+        assert_eq!(error.code, -32000);
+        let attributed: Address =
+            serde_json::from_value(error.data.unwrap()["signer"].clone()).unwrap();
+        assert_ne!(attributed, signer);
+        assert_ne!(attributed, gateway.address());
+    }
+
+    #[tokio::test]
+    async fn test_get_chain_info() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        for chain in [ChainConfig::mainnet(), ChainConfig::kurtosis(2, 1000)] {
+            let genesis_time = env.now() - 100 * chain.slot_time();
+
+            let head = ChainHead::default();
+            head.update_slot(42);
+
+            let mut server = env.server().with_chain(chain.clone(), genesis_time, head);
+
+            let (events_tx, _) = mpsc::channel(1);
+            server.run(events_tx).await.unwrap();
+            let addr = server.local_addr();
+
+            let payload = json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "bolt_getChainInfo",
+                "params": []
+            });
+
+            // No signature header is needed for read-only methods
+            let response = reqwest::Client::new()
+                .post(format!("http://{addr}"))
+                .json(&payload)
+                .send()
+                .await
+                .unwrap()
+                .json::<JsonResponse>()
+                .await
+                .unwrap();
+
+            assert!(response.error.is_none());
+            let info: ChainInfo = serde_json::from_value(response.result).unwrap();
+
+            assert_eq!(info.chain_id, chain.chain_id());
+            assert_eq!(info.seconds_per_slot, chain.slot_time());
+            assert_eq!(info.genesis_time, genesis_time);
+            assert_eq!(info.head_slot, 42);
+
+            assert_eq!(info.slot_at(env.now()), Some(100));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rpc_discover() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let mut server = env.server();
+        let (events_tx, _) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+
+        let payload = json!({ "jsonrpc": "2.0", "id": 1, "method": "rpc.discover", "params": [] });
+        let response = reqwest::Client::new()
+            .post(format!("http://{}", server.local_addr()))
+            .json(&payload)
+            .send()
+            .await
+            .unwrap()
+            .json::<JsonResponse>()
+            .await
+            .unwrap();
+
+        let document: OpenRpcDocument = serde_json::from_value(response.result).unwrap();
+        assert_eq!(document.info.version, CARGO_PKG_VERSION);
+        let method = |name: &str| document.methods.iter().find(|method| method.name == name);
+        assert!(method("bolt_getVersion").is_some());
+        assert!(method("rpc.discover").is_some());
+        // Only the methods of the public listener are described
+        assert!(method("bolt_getSlotManifest").is_none());
+
+        let inclusion = method("bolt_requestInclusion").unwrap();
+        assert_eq!(inclusion.params[0].schema, InclusionRequest::reference());
+        assert!(document.components.errors.contains_key("-32006:nonce_too_low"));
+
+        // The schema describes the fields of the serialized requests
+        let signer = env.signer();
+        let tx = default_test_transaction(signer.address(), None);
+        let mut req = create_signed_commitment_request(&[tx], &env.secret_key(), 12).await.unwrap();
+        req.context = Some("order-1".to_string());
+        let serialized = serde_json::to_value(&req).unwrap();
+        let schema = &document.components.schemas["InclusionRequest"];
+        for field in schema["required"].as_array().unwrap() {
+            assert!(serialized.get(field.as_str().unwrap()).is_some(), "{field}");
+        }
+        for field in serialized.as_object().unwrap().keys() {
+            assert!(schema["properties"].get(field).is_some(), "{field}");
+        }
+    }
+
+    #[cfg(feature = "admin")]
+    #[tokio::test]
+    async fn test_capture_matching_signer() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let dir = std::env::temp_dir().join(format!("bolt-capture-{}", rand::random::<u64>()));
+        let capture = RequestCapture::new(Some(dir.clone()), 1024 * 1024);
+
+        let mut server =
+            env.server().with_admin_addr(LOOPBACK_EPHEMERAL).unwrap().with_capture(capture);
+
+        let (events_tx, _) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let addr = server.local_addr();
+        let admin_addr = server.admin_local_addr().unwrap();
+
+        let ours = env.signer().address();
+        let other = env.signer().address();
+        let client = reqwest::Client::new();
+
+        // Capture methods are not exposed on the public server
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "bolt_enableCapture",
+            "params": [{ "signers": [ours], "max_captures": 2 }]
+        });
+        let response = client.post(format!("http://{addr}")).json(&payload).send().await.unwrap();
+        let response = response.json::<JsonResponse>().await.unwrap();
+        assert_eq!(response.error.unwrap().code, -32601);
+
+        let response =
+            client.post(format!("http://{admin_addr}")).json(&payload).send().await.unwrap();
+        assert!(response.json::<JsonResponse>().await.unwrap().error.is_none());
+
+        // Odd formatting to make sure the exact bytes are captured
+        let body = |id: u64| {
+            format!(r#"{{ "jsonrpc":"2.0",  "id":{id}, "method":"bolt_getVersion","params":[] }}"#)
+        };
+
+        for (id, signer) in [(1, other), (2, ours), (3, other), (4, ours), (5, ours)] {
+            client
+                .post(format!("http://{addr}"))
+                .header("content-type", "application/json")
+                .header(SIGNATURE_HEADER, format!("{signer}:0x1234"))
+                .body(body(id))
+                .send()
+                .await
+                .unwrap();
+        }
+
+        let mut captured = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| std::fs::read_to_string(e.unwrap().path().join("request.bin")).unwrap())
+            .collect::<Vec<_>>();
+        captured.sort();
+
+        // Only the first 2 requests of our signer are captured
+        assert_eq!(captured, vec![body(2), body(4)]);
+        assert!(!server.capture.is_enabled());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_bind_failure() {
+        let env = DeterministicEnv::new(1);
+        let taken = TcpListener::bind(LOOPBACK_EPHEMERAL).await.unwrap();
+        let addr = taken.local_addr().unwrap();
+
+        // A taken port fails the server instead of panicking, naming the address
+        let (events_tx, _) = mpsc::channel(1);
+        let mut server = CommitmentsApiServer::new(addr).unwrap();
+        let err = server.run(events_tx.clone()).await.unwrap_err();
+        assert!(matches!(err, ServerError::Bind { addr: bound, .. } if bound == addr), "{err}");
+        assert!(err.to_string().contains(&addr.to_string()), "{err}");
+
+        // Likewise for the other listeners, even though the public one could be bound
+        let mut server = env.server().with_internal_addr(addr).unwrap();
+        let err = server.run(events_tx.clone()).await.unwrap_err();
+        assert!(err.to_string().contains("internal"), "{err}");
+        #[cfg(feature = "admin")]
+        {
+            let mut server = env.server().with_admin_addr(addr).unwrap();
+            let err = server.run(events_tx).await.unwrap_err();
+            assert!(err.to_string().contains("admin"), "{err}");
+        }
+
+        // Addresses without a port are invalid, for the other listeners too
+        assert!(matches!(CommitmentsApiServer::new("localhost"), Err(ServerError::Resolve(_))));
+        let server = env.server().with_internal_addr("localhost");
+        assert!(matches!(server, Err(ServerError::Resolve(_))));
+    }
+
+    #[tokio::test]
+    async fn test_dual_stack_listeners() {
+        let env = DeterministicEnv::new(1);
+        let mut server = CommitmentsApiServer::new("localhost:0").unwrap().with_clock(env.clock());
+        let (events_tx, _) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+
+        // One listener per IP family localhost resolves to, on the same port
+        let mut families = ("localhost", 0)
+            .to_socket_addrs()
+            .unwrap()
+            .map(|addr| addr.is_ipv4())
+            .collect::<Vec<_>>();
+        families.dedup();
+        let addrs = server.local_addrs();
+        assert_eq!(addrs.iter().map(SocketAddr::is_ipv4).collect::<Vec<_>>(), families);
+        assert!(addrs.iter().all(|addr| addr.port() == server.local_addr().port()));
+
+        let client = reqwest::Client::new();
+        for addr in addrs {
+            let status = client.get(format!("http://{addr}/health")).send().await.unwrap().status();
+            assert_eq!(status, StatusCode::OK, "{addr}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unix_socket_listener() {
+        use std::os::unix::fs::PermissionsExt;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        /// Sends the JSON-RPC payload over a new connection to the socket, with a hand-rolled
+        /// HTTP/1.1 client, and returns the status line and the body of the response.
+        async fn post(path: &std::path::Path, payload: Value) -> (String, Value) {
+            let mut stream = tokio::net::UnixStream::connect(path).await.unwrap();
+            let body = payload.to_string();
+            let request = format!(
+                "POST / HTTP/1.1\r\nhost: localhost\r\ncontent-type: application/json\r\n\
+                 content-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(request.as_bytes()).await.unwrap();
+
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            let (head, body) = response.split_once("\r\n\r\n").unwrap();
+            let status = head.lines().next().unwrap().to_string();
+            (status, serde_json::from_str(body).unwrap())
+        }
+
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let path = std::env::temp_dir().join(format!("bolt-rpc-{}.sock", std::process::id()));
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let shutdown = async move {
+            let _ = shutdown_rx.await;
+        };
+        let mut server =
+            env.server().with_unix_socket(UnixSocketConfig::new(&path)).with_shutdown(shutdown);
+        let (events_tx, _) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        assert_eq!(server.bind_target(), BindTarget::Unix(path.clone()));
+        assert_eq!(server.listener_addrs().commitments, None);
+
+        // Only the owner can connect by default
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        let payload =
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "bolt_getVersion", "params": [] });
+        let (status, response) = post(&path, payload).await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        let response = serde_json::from_value::<JsonResponse>(response).unwrap();
+        assert!(response.error.is_none(), "{response:?}");
+        serde_json::from_value::<VersionInfo>(response.result).unwrap();
+
+        // The socket file is removed on shutdown
+        shutdown_tx.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while path.exists() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("The socket file must be removed on shutdown");
+    }
+
+    #[tokio::test]
+    async fn test_health_endpoints() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let (health, reader) = HealthPublisher::new();
+        let (publisher, snapshots) = SnapshotPublisher::new();
+        let mut server = env.server().with_health(reader).with_snapshots(snapshots);
+        let (events_tx, _) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let addr = server.local_addr();
+
+        let client = reqwest::Client::new();
+        let readyz = || {
+            let request = client.get(format!("http://{addr}/readyz")).send();
+            async move {
+                let response = request.await.unwrap();
+                (response.status(), response.json::<ReadinessReport>().await.unwrap())
+            }
+        };
+
+        // The server is live even though the driver isn't connected to anything yet
+        let status = client.get(format!("http://{addr}/health")).send().await.unwrap().status();
+        assert_eq!(status, StatusCode::OK);
+        let (status, report) = readyz().await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            report,
+            ReadinessReport {
+                ready: false,
+                dependencies: Some(Default::default()),
+                relays: Vec::new()
+            }
+        );
+
+        // Ready once the driver reached all of its dependencies
+        health.record(Dependency::Execution, true, 100);
+        health.record(Dependency::Consensus, true, 101);
+        assert_eq!(readyz().await.0, StatusCode::SERVICE_UNAVAILABLE);
+        health.record(Dependency::Relay, true, 102);
+        let (status, report) = readyz().await;
+        assert_eq!(status, StatusCode::OK);
+        let relay = report.dependencies.unwrap().relay;
+        assert_eq!(relay, DependencyStatus { connected: true, last_success: Some(102) });
+
+        // And not ready again while one of them is unreachable
+        health.record(Dependency::Relay, false, 114);
+        let (status, report) = readyz().await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(report.dependencies.unwrap().relay.last_success, Some(102));
+
+        // Along with the health of each relay target published by the driver
+        let backing_off = RelayPerformance {
+            url: "http://relay-b/".to_string(),
+            rank: 1,
+            samples: 12,
+            healthy: false,
+            failed_slots: 3,
+            backoff_until_slot: Some(40),
+            ..Default::default()
+        };
+        let relays = RelayStatus { relays: vec![backing_off.clone()], ..Default::default() };
+        publisher.publish(StateSnapshot { relays, ..Default::default() });
+        assert_eq!(readyz().await.1.relays, vec![backing_off]);
+    }
+
+    #[tokio::test]
+    async fn test_readiness() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let readiness = Readiness::new(false);
+        let mut server = env.server().with_readiness(readiness.clone());
+
+        let (events_tx, _) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let addr = server.local_addr();
+        let client = reqwest::Client::new();
+
+        let status = client.get(format!("http://{addr}/ready")).send().await.unwrap().status();
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+        let sk = env.secret_key();
+        let signer = PrivateKeySigner::from(sk.clone());
+        let tx = default_test_transaction(signer.address(), None);
+        let req = create_signed_commitment_request(&[tx], &sk, 12).await.unwrap();
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "bolt_requestInclusion",
+            "params": [req]
+        });
+
+        let response = client.post(format!("http://{addr}")).json(&payload).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.json::<JsonResponse>().await.unwrap().error.unwrap().code, -32009);
+
+        readiness.set_ready();
+
+        let status = client.get(format!("http://{addr}/ready")).send().await.unwrap().status();
+        assert_eq!(status, StatusCode::OK);
+
+        // Stale chain heads flip the sidecar back to not ready, until fresh heads resume
+        readiness.set_stale_heads(true);
+        let status = client.get(format!("http://{addr}/ready")).send().await.unwrap().status();
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+        let response = client.post(format!("http://{addr}")).json(&payload).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.json::<JsonResponse>().await.unwrap().error.unwrap().code, -32011);
+
+        readiness.set_stale_heads(false);
+        let status = client.get(format!("http://{addr}/ready")).send().await.unwrap().status();
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_get_status() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let schedule = vec![
+            ScheduleEntry { task: "commitment_deadline".to_string(), offset_ms: -4_000 },
+            ScheduleEntry { task: "slot_start".to_string(), offset_ms: 0 },
+        ];
+
+        let (publisher, snapshots) = SnapshotPublisher::new();
+        let mut server = env.server().with_schedule(schedule.clone()).with_snapshots(snapshots);
+
+        // Snapshots published by the driver are visible in the status
+        let basefee = BaseFeeStatus { margin: 1.25, adaptive: true, ..Default::default() };
+        publisher.publish(StateSnapshot { head_slot: 7, basefee, ..Default::default() });
+        let generation =
+            publisher.publish(StateSnapshot { head_slot: 8, basefee, ..Default::default() });
+
+        // The driver loop isn't running: status reads don't go through it
+        let (events_tx, _) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let addr = server.local_addr();
+
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "bolt_getStatus",
+            "params": []
+        });
+
+        let response = reqwest::Client::new()
+            .post(format!("http://{addr}"))
+            .json(&payload)
+            .send()
+            .await
+            .unwrap()
+            .json::<JsonResponse>()
+            .await
+            .unwrap();
+
+        let status: SidecarStatus = serde_json::from_value(response.result).unwrap();
+        assert_eq!(
+            status,
+            SidecarStatus {
+                ready: true,
+                schedule,
+                listeners: server.listener_addrs(),
+                draining: vec![],
+                failing_probe_stages: vec![],
+                state: StateSnapshot { generation, head_slot: 8, basefee, ..Default::default() },
+            }
+        );
+        assert_eq!(generation, 2);
+    }
+
+    #[tokio::test]
+    async fn test_metadata() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let limits = Limits::default();
+        let (publisher, snapshots) = SnapshotPublisher::new();
+        let shared = Arc::new(RwLock::new(limits));
+        let mut server = env.server().with_limits(shared).with_snapshots(snapshots);
+        let (events_tx, _) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let addr = server.local_addr();
+
+        let client = reqwest::Client::new();
+        let payload = json!({ "jsonrpc": "2.0", "id": 1, "method": "bolt_metadata", "params": [] });
+        let metadata = || {
+            let request = client.post(format!("http://{addr}")).json(&payload);
+            async move {
+                let response = request.send().await.unwrap().json::<JsonResponse>().await.unwrap();
+                serde_json::from_value::<SidecarMetadata>(response.result).unwrap()
+            }
+        };
+
+        // The slot fields are null until the driver has seen a head
+        let max_gas = limits.max_committed_gas_per_slot.get();
+        let initial = metadata().await;
+        assert_eq!(initial.max_committed_gas_per_slot, max_gas);
+        assert_eq!(initial.head_slot, None);
+        assert_eq!((initial.next_commitment_slot, initial.remaining_gas), (None, None));
+        assert_eq!(initial.min_priority_fee, limits.min_priority_fee);
+
+        let snapshot = StateSnapshot {
+            head_slot: 8,
+            lookahead: vec![10, 12],
+            lookahead_end_slot: 31,
+            ..Default::default()
+        };
+        publisher.publish(snapshot.clone());
+        let fresh = metadata().await;
+        assert_eq!((fresh.head_slot, fresh.lookahead_end_slot), (Some(8), Some(31)));
+        assert_eq!((fresh.next_commitment_slot, fresh.remaining_gas), (Some(10), Some(max_gas)));
+
+        // The remaining gas decreases once a commitment is accepted for the slot
+        let budget = SlotBudget {
+            slot: 10,
+            commitments: 1,
+            committed_gas: 21_000,
+            max_committed_gas: max_gas,
+            ..Default::default()
+        };
+        publisher.publish(StateSnapshot { slots: vec![budget], ..snapshot.clone() });
+        assert_eq!(metadata().await.remaining_gas, Some(max_gas - 21_000));
+
+        // The priority fee floor and the projected base fee of the next commitment slot are
+        // the ones computed by the driver
+        assert_eq!(fresh.projected_basefee, None);
+        let (priority_fee_floor, projected_basefee) = (Some(2_000_000_000), Some(11_250_000_000));
+        publisher.publish(StateSnapshot { priority_fee_floor, projected_basefee, ..snapshot });
+        let latest = metadata().await;
+        assert_eq!(latest.min_priority_fee, 2_000_000_000);
+        assert_eq!(latest.projected_basefee, Some(11_250_000_000));
+    }
+
+    #[cfg(feature = "admin")]
+    #[tokio::test]
+    async fn test_admin_whitelist_and_limits() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let sk = env.secret_key();
+        let signer = PrivateKeySigner::from(sk.clone()).address();
+        let limits = Arc::new(RwLock::new(Limits::default()));
+        let commitment_key = env.signer();
+        let mut server = env
+            .server()
+            .with_admin_addr(LOOPBACK_EPHEMERAL)
+            .unwrap()
+            .with_admin_token(Some("secret".to_string()))
+            .with_whitelist(Some(Whitelist::default()))
+            .with_limits(limits.clone());
+        let (events_tx, mut events) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let (addr, admin_addr) = (server.local_addr(), server.admin_local_addr().unwrap());
+        let client = reqwest::Client::new();
+        let admin = |method: reqwest::Method, path: &str| {
+            let request = client.request(method, format!("http://{admin_addr}{path}"));
+            request.header(admin::ADMIN_TOKEN_HEADER, "secret")
+        };
+
+        let tx = default_test_transaction(signer, None);
+        let req = create_signed_commitment_request(&[tx], &sk, 12).await.unwrap();
+        let header = format!("{signer}:{}", req.signature().unwrap().to_hex());
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "bolt_requestInclusion",
+            "params": [req]
+        });
+        let send = || {
+            let request = client.post(format!("http://{addr}")).json(&payload);
+            let request = request.header(SIGNATURE_HEADER, header.clone());
+            async move { request.send().await.unwrap().json::<JsonResponse>().await.unwrap() }
+        };
+        assert_eq!(send().await.error.unwrap().code, -32000);
+
+        // The admin requests must carry the token
+        let unauthorized = client.get(format!("http://{admin_addr}{WHITELIST_PATH}"));
+        let forged = unauthorized.try_clone().unwrap().header(admin::ADMIN_TOKEN_HEADER, "guess");
+        assert_eq!(unauthorized.send().await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(forged.send().await.unwrap().status(), StatusCode::UNAUTHORIZED);
+
+        // Once added from the admin API, the signer is authorized from its next request
+        let addition = json!({ "address": signer, "label": "team" });
+        let response = admin(reqwest::Method::POST, WHITELIST_PATH).json(&addition).send().await;
+        let entry = response.unwrap().json::<WhitelistEntry>().await.unwrap();
+        assert_eq!(entry.label.as_deref(), Some("team"));
+
+        let response = tokio::spawn(send());
+        let Event { request, response: commitment, .. } = events.recv().await.unwrap();
+        commitment.send(Ok(request.commit_and_sign(&commitment_key).await.unwrap())).unwrap();
+        assert!(response.await.unwrap().error.is_none());
+
+        let response = admin(reqwest::Method::GET, WHITELIST_PATH).send().await.unwrap();
+        let listed = response.json::<Whitelist>().await.unwrap();
+        assert_eq!(listed.get(&signer), Some(&entry));
+
+        // The limits are shared with the driver, and served by `bolt_metadata`
+        let update = json!({ "max_committed_gas_per_slot": 1_000_000, "min_priority_fee": 5 });
+        let response = admin(reqwest::Method::PUT, LIMITS_PATH).json(&update).send().await;
+        let updated = response.unwrap().json::<Limits>().await.unwrap();
+        assert_eq!(*limits.read(), updated);
+        assert_eq!(updated.max_committed_gas_per_slot.get(), 1_000_000);
+        assert_eq!(updated.min_priority_fee, 5);
+        let payload = json!({ "jsonrpc": "2.0", "id": 1, "method": "bolt_metadata", "params": [] });
+        let response = client.post(format!("http://{addr}")).json(&payload).send().await.unwrap();
+        let metadata = response.json::<JsonResponse>().await.unwrap().result;
+        assert_eq!(metadata["max_committed_gas_per_slot"], 1_000_000);
+
+        // Once removed, the signer is rejected again
+        let path = format!("{WHITELIST_PATH}/{signer}");
+        let response = admin(reqwest::Method::DELETE, &path).send().await.unwrap();
+        assert_eq!(response.json::<WhitelistEntry>().await.unwrap(), entry);
+        let response = admin(reqwest::Method::DELETE, &path).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(send().await.error.unwrap().code, -32000);
+    }
+
+    #[cfg(feature = "admin")]
+    #[tokio::test]
+    async fn test_ports_file() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let mut server = env
+            .server()
+            .with_internal_addr(LOOPBACK_EPHEMERAL)
+            .unwrap()
+            .with_admin_addr(LOOPBACK_EPHEMERAL)
+            .unwrap();
+
+        let (events_tx, _) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+
+        let path = std::env::temp_dir().join(format!("bolt-ports-{}.json", rand::random::<u64>()));
+        server.listener_addrs().write_ports_file(&path).unwrap();
+        let listeners: ListenerAddrs =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let addr = listeners.commitments.unwrap();
+        assert_eq!(addr.ip().to_string(), "127.0.0.1");
+        assert_ne!(addr.port(), 0);
+        assert_ne!(listeners.internal.unwrap().port(), 0);
+        assert_ne!(listeners.admin.unwrap().port(), 0);
+        assert_eq!(listeners.env_lines()[0], format!("COMMITMENTS_ADDR={addr}"));
+
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "bolt_getStatus",
+            "params": []
+        });
+
+        let response = reqwest::Client::new()
+            .post(format!("http://{addr}"))
+            .json(&payload)
+            .send()
+            .await
+            .unwrap()
+            .json::<JsonResponse>()
+            .await
+            .unwrap();
+
+        let status: SidecarStatus = serde_json::from_value(response.result).unwrap();
+        assert_eq!(status.listeners, listeners);
+    }
+
+    #[tokio::test]
+    async fn test_method_profiles() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let public = "bolt_requestInclusion,bolt_getVersion,bolt_getChainInfo";
+        let profiles = MethodProfiles {
+            public: MethodProfile::parse(public, RPC_METHODS).unwrap(),
+            ..Default::default()
+        };
+
+        let mut server =
+            env.server().with_internal_addr(LOOPBACK_EPHEMERAL).unwrap().with_profiles(profiles);
+
+        let (events_tx, _) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let public_addr = server.local_addr();
+        let internal_addr = server.internal_local_addr().unwrap();
+        let client = reqwest::Client::new();
+
+        let call = |addr: SocketAddr, method: &str, params: Value| {
+            let payload = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+            let request = client.post(format!("http://{addr}")).json(&payload);
+            async move { request.send().await.unwrap().json::<JsonResponse>().await.unwrap() }
+        };
+
+        // The status is only exposed on the internal listener
+        let response = call(internal_addr, "bolt_getStatus", json!([])).await;
+        assert!(response.error.is_none());
+        let response = call(public_addr, "bolt_getStatus", json!([])).await;
+        assert_eq!(response.error.unwrap().code, -32601);
+
+        // A hidden method is indistinguishable from a nonexistent one, even with bad params
+        let hidden = call(public_addr, "bolt_getStatus", json!([{ "bad": "params" }])).await;
+        let missing = call(public_addr, "bolt_doesNotExist", json!([])).await;
+        let (hidden, missing) = (hidden.error.unwrap(), missing.error.unwrap());
+        assert_eq!((hidden.code, hidden.message), (missing.code, missing.message));
+        assert_eq!(hidden.data, missing.data);
+
+        // The advertised methods follow the profile of each listener
+        let version = |response: JsonResponse| -> VersionInfo {
+            serde_json::from_value(response.result).unwrap()
+        };
+        let public = version(call(public_addr, "bolt_getVersion", json!([])).await);
+        let internal = version(call(internal_addr, "bolt_getVersion", json!([])).await);
+        assert_eq!(
+            public.methods,
+            vec!["bolt_getChainInfo", "bolt_getVersion", "bolt_requestInclusion"]
+        );
+        assert_eq!(
+            internal.methods,
+            vec![
+                "bolt_cancelInclusion",
+                "bolt_getChainInfo",
+                "bolt_getCommitment",
+                "bolt_getCommitmentStatus",
+                "bolt_getSlotManifest",
+                "bolt_getStatus",
+                "bolt_getVersion",
+                "bolt_metadata",
+                "bolt_requestInclusion"
+            ]
+        );
+        assert_eq!(public.version, internal.version);
+    }
+
+    #[tokio::test]
+    async fn test_identity_endpoint() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let identity = ServedIdentity::default();
+        let mut server = env.server().with_identity(identity.clone());
+        let (events_tx, _) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let url = format!("http://{}/identity", server.local_addr());
+
+        let get = || async { reqwest::get(&url).await.unwrap() };
+        assert_eq!(get().await.status(), StatusCode::NOT_FOUND);
+
+        let validator = BlsSigner::new(crate::test_util::test_bls_secret_key());
+        let commitment_signer = env.signer();
+        let now = env.now();
+        let document = |valid_from| {
+            let document = IdentityDocument::new(
+                commitment_signer.address(),
+                1337,
+                valid_from,
+                DEFAULT_IDENTITY_VALIDITY,
+            );
+            SignedIdentity::sign(document, &validator).unwrap()
+        };
+
+        // A commitment verifies against the served identity
+        identity.set(Some(document(now)));
+        let served: SignedIdentity = get().await.json().await.unwrap();
+        assert_eq!(Some(&served), identity.get().as_ref());
+
+        let sk = env.secret_key();
+        let tx = default_test_transaction(PrivateKeySigner::from(sk.clone()).address(), None);
+        let request = create_signed_commitment_request(&[tx], &sk, 10).await.unwrap();
+        let commitment = request.commit_and_sign(&commitment_signer).await.unwrap();
+        let commitment = commitment.into_inclusion().unwrap();
+        let pubkey = BlsPublicKey::try_from(validator.pubkey().to_bytes().as_slice()).unwrap();
+        crate::verify_commitment_with_identity(&commitment, &served, &pubkey).unwrap();
+
+        // A lapsed identity is not served
+        identity.set(Some(document(now - DEFAULT_IDENTITY_VALIDITY.as_secs() - 1)));
+        assert_eq!(get().await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[cfg(feature = "ws")]
+    #[tokio::test]
+    async fn test_slot_manifest() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let (publisher, manifests) = ManifestPublisher::new();
+        let mut server =
+            env.server().with_internal_addr(LOOPBACK_EPHEMERAL).unwrap().with_manifests(manifests);
+        let (events_tx, _) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let internal_addr = server.internal_local_addr().unwrap();
+
+        let sk = env.secret_key();
+        let sender = PrivateKeySigner::from(sk.clone()).address();
+        let mut constraints = Vec::new();
+        for nonce in 0..3 {
+            let tx = default_test_transaction(sender, Some(nonce));
+            let request = create_signed_commitment_request(&[tx], &sk, 10).await.unwrap();
+            let request = request.into_inclusion_request().unwrap();
+            let message = ConstraintsMessage::build(0, request);
+            constraints.push(SignedConstraints { message, ..Default::default() });
+        }
+
+        let mut templates = HashMap::from([(10, BlockTemplate::default())]);
+        templates.get_mut(&10).unwrap().add_constraints(constraints[0].clone());
+        publisher.update(&templates);
+
+        // The manifests are not exposed on the public listener
+        let public = format!("http://{}/manifests/10", server.local_addr());
+        assert_eq!(reqwest::get(&public).await.unwrap().status(), StatusCode::NOT_FOUND);
+
+        let url = format!("http://{internal_addr}/manifests/10");
+        let mut updates = reqwest::get(url).await.unwrap();
+        let first = updates.chunk().await.unwrap().unwrap();
+        let mut events = String::from_utf8(first.to_vec()).unwrap();
+
+        // A commitment is added, then replaced by another one before the deadline
+        templates.get_mut(&10).unwrap().add_constraints(constraints[1].clone());
+        publisher.update(&templates);
+        let template = templates.get_mut(&10).unwrap();
+        template.remove_commitment(&constraints[1].message.request_digest());
+        template.add_constraints(constraints[2].clone());
+        publisher.update(&templates);
+        publisher.freeze(10);
+
+        // The stream ends with the frozen revision
+        events.push_str(&updates.text().await.unwrap());
+        let updates = events
+            .lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .map(|data| serde_json::from_str::<SlotManifest>(data.trim()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(updates[0].revision, 0);
+        assert!(updates.windows(2).all(|pair| pair[0].revision < pair[1].revision));
+        let last = updates.last().unwrap();
+        assert!(last.frozen);
+
+        // The queried manifest agrees with the last update
+        let payload =
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "bolt_getSlotManifest", "params": [10] });
+        let request = reqwest::Client::new().post(format!("http://{internal_addr}")).json(&payload);
+        let response = request.send().await.unwrap().json::<JsonResponse>().await.unwrap();
+        let queried: SlotManifest = serde_json::from_value(response.result).unwrap();
+        assert_eq!(&queried, last);
+
+        let digests = queried.groups.iter().map(|group| group.digest).collect::<Vec<_>>();
+        let expected = [&constraints[0], &constraints[2]].map(|c| c.message.request_digest());
+        assert_eq!(digests, expected);
+    }
+
+    #[cfg(feature = "ws")]
+    #[tokio::test]
+    async fn test_slot_manifest_jwt() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let secret = JwtSecret::random();
+        let server = env.server().with_internal_addr(LOOPBACK_EPHEMERAL).unwrap();
+        let mut server = server.with_jwt_secret(Some(secret));
+        let (events_tx, _) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let url = format!("http://{}/manifests/10", server.internal_local_addr().unwrap());
+        let client = reqwest::Client::new();
+
+        // The stream is rejected without a token, like the JSON-RPC methods
+        let response = client.get(&url).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let iat = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let token = secret.encode(&Claims { iat, exp: Some(iat + 60) }).unwrap();
+        let response = client.get(&url).bearer_auth(&token).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[cfg(feature = "ws")]
+    #[tokio::test]
+    async fn test_websocket_pipelining() {
+        use futures::{SinkExt, Stream};
+        use tokio_tungstenite::tungstenite::{Error as SocketError, Message};
+
+        async fn receive<S>(socket: &mut S) -> JsonResponse
+        where
+            S: Stream<Item = Result<Message, SocketError>> + Unpin,
+        {
+            let Some(Ok(Message::Text(text))) = socket.next().await else {
+                panic!("Expected a text message");
+            };
+            serde_json::from_str(&text).unwrap()
+        }
+
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let shutdown = async move {
+            let _ = shutdown_rx.await;
+        };
+        let mut server = env.server().with_shutdown(shutdown);
+        let (events_tx, mut events) = mpsc::channel(2);
+        server.run(events_tx).await.unwrap();
+        let addr = server.local_addr();
+
+        let url = format!("ws://{addr}/ws");
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+
+        // Each message is authenticated by the signature of its payload
+        let signer = env.signer();
+        let mut digests = HashMap::new();
+        for (id, nonce) in [(1, 0), (2, 1)] {
+            let tx = default_test_transaction(signer.address(), Some(nonce));
+            let request =
+                create_signed_commitment_request(&[tx], &env.secret_key(), 12).await.unwrap();
+            digests.insert(request.digest(), id);
+            let signature = request.signature().unwrap().to_hex();
+            let signature = format!("{}:{signature}", signer.address());
+            let payload = json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "bolt_requestInclusion",
+                "params": [request],
+                "signature": signature,
+            });
+            socket.send(Message::Text(payload.to_string())).await.unwrap();
+        }
+
+        let mut pending = Vec::new();
+        for _ in 0..2 {
+            let Event { request, response, .. } = events.recv().await.unwrap();
+            let id = digests[&request.as_inclusion_request().unwrap().digest()];
+            pending.push((id, request, response));
+        }
+        pending.sort_by_key(|(id, ..)| *id);
+
+        // The second request is answered first, and its response carries its id
+        for (id, request, response) in pending.into_iter().rev() {
+            let commitment = request.commit_and_sign(&signer).await.unwrap();
+            response.send(Ok(commitment)).unwrap();
+            let response = receive(&mut socket).await;
+            assert_eq!(response.id, Some(json!(id)));
+            assert!(response.error.is_none());
+        }
+
+        // The other methods are served too, and the socket is closed on shutdown
+        let payload = json!({ "jsonrpc": "2.0", "id": 3, "method": "bolt_getVersion" });
+        socket.send(Message::Text(payload.to_string())).await.unwrap();
+        assert_eq!(receive(&mut socket).await.id, Some(json!(3)));
+        shutdown_tx.send(()).unwrap();
+        let Some(Ok(Message::Close(Some(frame)))) = socket.next().await else {
+            panic!("Expected a close frame");
+        };
+        assert_eq!(u16::from(frame.code), 1001);
+    }
+
+    #[tokio::test]
+    async fn test_adversarial_payloads() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let mut server = env.server();
+        let (events_tx, _) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let url = format!("http://{}", server.local_addr());
+        let client = reqwest::Client::new();
+
+        let huge_id = json!({
+            "jsonrpc": "2.0",
+            "id": "a".repeat(1024 * 1024),
+            "method": "bolt_getVersion",
+            "params": [],
+        });
+        let nested = format!("{}{}", "[".repeat(200), "]".repeat(200));
+        let deep = format!(r#"{{"jsonrpc":"2.0","id":1,"method":"m","params":{nested}}}"#);
+        let newline_id = json!({
+            "jsonrpc": "2.0",
+            "id": "a\nb",
+            "method": "bolt_getVersion",
+            "params": [],
+        });
+
+        for body in [huge_id.to_string(), deep, newline_id.to_string()] {
             let response = client
-                .post(url)
-                .header(SIGNATURE_HEADER, format!("{}:{}", signer.address(), sig))
-                .json(&payload)
+                .post(&url)
+                .header("content-type", "application/json")
+                .body(body)
                 .send()
                 .await
                 .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
 
-            let json = response.json::<JsonResponse>().await.unwrap();
+            // The invalid id is never echoed
+            let response = response.json::<JsonResponse>().await.unwrap();
+            assert_eq!(response.id, None);
+            assert_eq!(response.error.unwrap().code, -32600);
+        }
+    }
 
-            // Assert unauthorized because of missing signature
-            assert!(json.error.is_none());
+    #[cfg(feature = "admin")]
+    #[tokio::test]
+    async fn test_import_signed_commitment() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
 
-            let _ = tx.send(());
+        let (imports_tx, mut imports) = mpsc::channel(1);
+        let mut server =
+            env.server().with_admin_addr(LOOPBACK_EPHEMERAL).unwrap().with_imports(imports_tx);
+
+        let (events_tx, mut events) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let addr = server.local_addr();
+        let admin_addr = server.admin_local_addr().unwrap();
+
+        let sk = env.secret_key();
+        let signer = PrivateKeySigner::from(sk.clone());
+        let tx = default_test_transaction(signer.address(), None);
+        let req = create_signed_commitment_request(&[tx], &sk, 12).await.unwrap();
+        let sig = req.signature().unwrap().to_hex();
+
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "bolt_requestInclusion",
+            "params": [req]
         });
 
-        let Event { request, response } = events.recv().await.unwrap();
+        let client = reqwest::Client::new();
+        let request = client
+            .post(format!("http://{addr}"))
+            .header(SIGNATURE_HEADER, format!("{}:{}", signer.address(), sig))
+            .json(&payload)
+            .send();
+        let client_response = tokio::spawn(request);
 
-        let commitment_signer = PrivateKeySigner::random();
+        // The driver parks the request instead of signing it
+        let cold_key = env.signer();
+        let mut external = ExternalSigner::new(cold_key.address());
+        let Event { request, response, .. } = events.recv().await.unwrap();
+        let request = request.into_inclusion_request().unwrap();
+        let digest = external.park(request, response);
 
-        let commitment = request.commit_and_sign(&commitment_signer).await.unwrap();
+        let import = |signature: Signature| {
+            let payload = json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "admin_importSignedCommitment",
+                "params": [{ "digest": digest, "signature": signature.to_hex() }]
+            });
+            tokio::spawn(client.post(format!("http://{admin_addr}")).json(&payload).send())
+        };
+
+        // Process the next import like the driver does
+        let mut process_import = |event: ImportEvent| {
+            match external.import(event.digest, event.signature) {
+                Ok((_, client, commitment)) => {
+                    client.send(Ok(commitment.clone())).unwrap();
+                    event.response.send(Ok(commitment)).unwrap();
+                }
+                Err(err) => event.response.send(Err(err.into())).unwrap(),
+            }
+        };
+
+        // A signature from another key is rejected
+        let wrong = env.signer().sign_hash(&digest).await.unwrap();
+        let response = import(wrong);
+        process_import(imports.recv().await.unwrap());
+        let response = response.await.unwrap().unwrap().json::<JsonResponse>().await.unwrap();
+        assert_eq!(response.error.unwrap().code, -32010);
+
+        // Sign the exported digest offline and import the signature
+        let signature = cold_key.sign_hash(&digest).await.unwrap();
+        let response = import(signature);
+        process_import(imports.recv().await.unwrap());
+        let response = response.await.unwrap().unwrap().json::<JsonResponse>().await.unwrap();
+        assert!(response.error.is_none());
+        assert_eq!(response.result["signature"], json!(signature.to_hex()));
+
+        // The original client request completes with the imported commitment
+        let client_response = client_response.await.unwrap().unwrap();
+        let client_response = client_response.json::<JsonResponse>().await.unwrap();
+        assert_eq!(client_response.result, response.result);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_inclusion() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let (cancellations_tx, mut cancellations) = mpsc::channel(1);
+        let mut server = env.server().with_cancellations(cancellations_tx);
+        let (events_tx, _) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let addr = server.local_addr();
+
+        let signer = env.signer();
+        let digest = B256::random();
+        let params = InclusionCancellation::Digest { digest };
+        let client = reqwest::Client::new();
+        let cancel = |params: InclusionCancellation, signature: Signature| {
+            let payload = json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "bolt_cancelInclusion",
+                "params": [params]
+            });
+            let header = format!("{}:{}", signer.address(), signature.to_hex());
+            let request = client.post(format!("http://{addr}")).header(SIGNATURE_HEADER, header);
+            tokio::spawn(request.json(&payload).send())
+        };
+
+        // The signature of the request itself can't be replayed to cancel it
+        let replayed = signer.sign_hash(&digest).await.unwrap();
+        let response = cancel(params, replayed).await.unwrap().unwrap();
+        let response = response.json::<JsonResponse>().await.unwrap();
+        assert_eq!(response.error.unwrap().code, -32004);
+
+        // Unknown commitments are reported as such
+        let signature = signer.sign_hash(&params.signed_digest()).await.unwrap();
+        let response = cancel(params, signature);
+        let event = cancellations.recv().await.unwrap();
+        assert_eq!((event.digest, event.signer), (digest, signer.address()));
+        event.response.send(Err(CancelError::UnknownCommitment(event.digest).into())).unwrap();
+        let response = response.await.unwrap().unwrap().json::<JsonResponse>().await.unwrap();
+        let error = response.error.unwrap();
+        assert_eq!(error.code, -32015);
+        assert_eq!(error.data.unwrap()["reason"], "unknown_commitment");
+
+        // The outcome of the driver is returned to the signer
+        let response = cancel(params, signature);
+        let event = cancellations.recv().await.unwrap();
+        event.response.send(Ok(CancelOutcome::TooLate)).unwrap();
+        let response = response.await.unwrap().unwrap().json::<JsonResponse>().await.unwrap();
+        assert_eq!(response.result, json!({ "status": "too_late" }));
+
+        // The ids of the commitments that aren't tracked are unknown, without asking the driver
+        let params = InclusionCancellation::CommitmentId { commitment_id: B256::random() };
+        let signature = signer.sign_hash(&params.signed_digest()).await.unwrap();
+        let response = cancel(params, signature).await.unwrap().unwrap();
+        let error = response.json::<JsonResponse>().await.unwrap().error.unwrap();
+        assert_eq!(error.data.unwrap()["reason"], "unknown_commitment");
+        assert!(cancellations.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_response_signing() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let commitment_key = env.signer();
+        let mut signed =
+            env.server().with_response_signer(ResponseSigner::new(commitment_key.clone()));
+        let mut unsigned = env.server();
+
+        let (events_tx, _) = mpsc::channel(1);
+        signed.run(events_tx.clone()).await.unwrap();
+        unsigned.run(events_tx).await.unwrap();
+        let client = reqwest::Client::new();
+
+        let call = |addr: SocketAddr, method: &str| {
+            let payload = json!({ "jsonrpc": "2.0", "id": 7, "method": method, "params": [] });
+            let request = client.post(format!("http://{addr}")).json(&payload);
+            async move {
+                let response = request.send().await.unwrap();
+                let headers = response.headers().clone();
+                (headers, response.bytes().await.unwrap())
+            }
+        };
+        let version = |body: &[u8]| -> VersionInfo {
+            let response: JsonResponse = serde_json::from_slice(body).unwrap();
+            serde_json::from_value(response.result).unwrap()
+        };
+        let id = json!(7);
+
+        // The signing mode is advertised, and both results and errors are signed
+        let (headers, body) = call(signed.local_addr(), "bolt_getVersion").await;
+        let verifier = ResponseVerifier::new(commitment_key.address(), &version(&body));
+        assert!(verifier.is_enabled());
+        assert_eq!(verifier.verify(&headers, &body, Some(&id)), Ok(()));
+
+        let (headers, body) = call(signed.local_addr(), "bolt_doesNotExist").await;
+        assert_eq!(verifier.verify(&headers, &body, Some(&id)), Ok(()));
+
+        // A tampered body, or a response replayed to another request, is detected
+        let tampered = String::from_utf8(body.to_vec()).unwrap().replace("-32601", "-32600");
+        let err = verifier.verify(&headers, tampered.as_bytes(), Some(&id)).unwrap_err();
+        assert!(matches!(err, ResponseVerificationError::SignerMismatch { .. }));
+        let err = verifier.verify(&headers, &body, Some(&json!(8))).unwrap_err();
+        assert!(matches!(err, ResponseVerificationError::SignerMismatch { .. }));
+        let err = verifier.verify(&HeaderMap::new(), &body, Some(&id)).unwrap_err();
+        assert_eq!(err, ResponseVerificationError::MissingSignature);
+
+        // Without the signing mode, the header is absent and nothing is verified
+        let (headers, body) = call(unsigned.local_addr(), "bolt_getVersion").await;
+        assert!(headers.get(RESPONSE_SIGNATURE_HEADER).is_none());
+        let verifier = ResponseVerifier::new(commitment_key.address(), &version(&body));
+        assert!(!verifier.is_enabled());
+        assert_eq!(verifier.verify(&headers, &body, Some(&id)), Ok(()));
+    }
+
+    #[cfg(all(feature = "tls", feature = "admin"))]
+    #[tokio::test]
+    async fn test_public_listener_over_tls() {
+        use crate::{
+            commitments::tls::{TlsConfig, UnknownSni},
+            test_util::{test_tls_connector, write_test_cert},
+        };
+
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let dir = std::env::temp_dir().join(format!("bolt-tls-{}", rand::random::<u64>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = TlsConfig {
+            default: Some(write_test_cert(&dir, "default", "alpha.test")),
+            hosts: [("beta.test".to_string(), write_test_cert(&dir, "beta", "beta.test"))].into(),
+            unknown_sni: UnknownSni::Default,
+        };
+        let resolver = Arc::new(SniResolver::new(config).unwrap());
+
+        let mut server =
+            env.server().with_admin_addr(LOOPBACK_EPHEMERAL).unwrap().with_tls(resolver);
+        let (events_tx, _) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let addr = server.local_addr();
+        let admin_addr = server.admin_local_addr().unwrap();
+
+        // A JSON-RPC call over HTTP/1.1 on a TLS connection with the given SNI
+        let call = |sni: &'static str| async move {
+            use tokio::io::{AsyncReadExt, AsyncWriteExt};
+            use tokio_rustls::rustls::pki_types::ServerName;
+
+            let tcp = tokio::net::TcpStream::connect(addr).await.unwrap();
+            let name = ServerName::try_from(sni).unwrap().to_owned();
+            let mut stream = test_tls_connector().connect(name, tcp).await.unwrap();
+
+            let body = r#"{"jsonrpc":"2.0","id":1,"method":"bolt_getVersion","params":[]}"#;
+            let request = format!(
+                "POST / HTTP/1.1\r\nhost: {sni}\r\ncontent-type: application/json\r\n\
+                 content-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+
+            let (_, body) = response.split_once("\r\n\r\n").unwrap();
+            serde_json::from_str::<JsonResponse>(body).unwrap()
+        };
+
+        for sni in ["beta.test", "unknown.test"] {
+            let version: VersionInfo = serde_json::from_value(call(sni).await.result).unwrap();
+            assert!(version.methods.contains(&"bolt_getVersion".to_string()));
+        }
 
+        // Certificates are reloaded per hostname from the admin server
+        let reload = |params: Value| {
+            let payload = json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "admin_reloadCertificates",
+                "params": params
+            });
+            let request =
+                reqwest::Client::new().post(format!("http://{admin_addr}")).json(&payload);
+            async move { request.send().await.unwrap().json::<JsonResponse>().await.unwrap() }
+        };
+        assert_eq!(reload(json!([{ "hostname": "beta.test" }])).await.result, json!(true));
+        assert_eq!(reload(json!([])).await.result, json!(true));
+        let response = reload(json!([{ "hostname": "gamma.test" }])).await;
+        assert_eq!(response.error.unwrap().code, -32000);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "admin")]
+    #[tokio::test]
+    async fn test_rebind_listener() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let env = DeterministicEnv::new(1);
+
+        let mut server = env.server().with_admin_addr(LOOPBACK_EPHEMERAL).unwrap();
+        let (events_tx, mut events) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let (old_addr, admin_addr) = (server.local_addr(), server.admin_local_addr().unwrap());
+
+        let rebind = |listener: &'static str, addr: String| {
+            let payload = json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "admin_rebindListener",
+                "params": [{ "listener": listener, "addr": addr }]
+            });
+            let request =
+                reqwest::Client::new().post(format!("http://{admin_addr}")).json(&payload);
+            async move { request.send().await.unwrap().json::<JsonResponse>().await.unwrap() }
+        };
+        let status = |addr: SocketAddr| async move {
+            let payload =
+                json!({ "jsonrpc": "2.0", "id": 1, "method": "bolt_getStatus", "params": [] });
+            let request = reqwest::Client::new().post(format!("http://{addr}")).json(&payload);
+            let response = request.send().await.unwrap().json::<JsonResponse>().await.unwrap();
+            serde_json::from_value::<SidecarStatus>(response.result).unwrap()
+        };
+
+        // Leave an inclusion request in flight on the current listener
+        let sk = env.secret_key();
+        let signer = PrivateKeySigner::from(sk.clone());
+        let tx = default_test_transaction(signer.address(), None);
+        let req = create_signed_commitment_request(&[tx], &sk, 12).await.unwrap();
+        let sig = req.signature().unwrap().to_hex();
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "bolt_requestInclusion",
+            "params": [req]
+        });
+        let in_flight = reqwest::Client::new()
+            .post(format!("http://{old_addr}"))
+            .header(SIGNATURE_HEADER, format!("{}:{}", signer.address(), sig))
+            .json(&payload)
+            .send();
+        let in_flight = tokio::spawn(in_flight);
+        let Event { request, response, .. } = events.recv().await.unwrap();
+
+        // Failing to bind the new address leaves the listener untouched
+        let error = rebind("commitments", admin_addr.to_string()).await.error.unwrap();
+        assert_eq!(error.code, -32000);
+        let error = rebind("internal", "127.0.0.1:0".to_string()).await.error.unwrap();
+        assert_eq!(error.code, -32000);
+        assert_eq!(status(old_addr).await.listeners.commitments, Some(old_addr));
+
+        // Rebind to another ephemeral port while the request is in flight
+        let rebound = rebind("commitments", "127.0.0.1:0".to_string()).await;
+        let listeners: ListenerAddrs = serde_json::from_value(rebound.result).unwrap();
+        let new_addr = listeners.commitments.unwrap();
+        assert_ne!(new_addr, old_addr);
+
+        let current = status(new_addr).await;
+        assert_eq!(current.listeners, listeners);
+        assert_eq!(current.draining, vec![old_addr]);
+
+        // The request in flight completes on the previous listener
+        let commitment = request.commit_and_sign(&env.signer()).await.unwrap();
         response.send(Ok(commitment)).unwrap();
+        let in_flight = in_flight.await.unwrap().unwrap().json::<JsonResponse>().await.unwrap();
+        assert!(in_flight.error.is_none());
 
-        rx.await.unwrap();
+        // Only then is the previous listener closed
+        let drained = async {
+            while !status(new_addr).await.draining.is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), drained).await.unwrap();
+        assert!(tokio::net::TcpStream::connect(old_addr).await.is_err());
+    }
+
+    #[cfg(feature = "admin")]
+    /// Runs one scenario against a server in the deterministic environment of the seed,
+    /// and returns what it observed in order: the request forwarded to the driver, the
+    /// signed responses, and the captured exchanges with their timestamped names.
+    async fn run_deterministic_scenario(seed: u64) -> Vec<Vec<u8>> {
+        let env = DeterministicEnv::new(seed);
+        let dir = std::env::temp_dir().join(format!("bolt-capture-{}", rand::random::<u64>()));
+        let capture = RequestCapture::new(Some(dir.clone()), 1024 * 1024);
+
+        let sk = env.secret_key();
+        let signer = PrivateKeySigner::from(sk.clone());
+        let mut whitelist = Whitelist::default();
+        let entry = WhitelistEntry { expires_at: Some(env.now() + 60), ..Default::default() };
+        whitelist.insert(signer.address(), entry);
+
+        let commitment_key = env.signer();
+        let mut server = env
+            .server()
+            .with_admin_addr(LOOPBACK_EPHEMERAL)
+            .unwrap()
+            .with_capture(capture)
+            .with_whitelist(Some(whitelist))
+            .with_response_signer(ResponseSigner::new(commitment_key.clone()));
+        let (events_tx, mut events) = mpsc::channel(1);
+        server.run(events_tx).await.unwrap();
+        let (addr, admin_addr) = (server.local_addr(), server.admin_local_addr().unwrap());
+        let client = reqwest::Client::new();
+
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "bolt_enableCapture",
+            "params": [{ "signers": [signer.address()] }]
+        });
+        client.post(format!("http://{admin_addr}")).json(&payload).send().await.unwrap();
+
+        let tx = default_test_transaction(signer.address(), None);
+        let req = create_signed_commitment_request(&[tx], &sk, 12).await.unwrap();
+        let header = format!("{}:{}", signer.address(), req.signature().unwrap().to_hex());
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "bolt_requestInclusion",
+            "params": [req]
+        });
+        let send = || {
+            let request = client.post(format!("http://{addr}")).json(&payload);
+            request.header(SIGNATURE_HEADER, header.clone()).send()
+        };
+        let mut observed = Vec::new();
+
+        // The driver commits to the forwarded request
+        let response = tokio::spawn(send());
+        let Event { request, response: commitment, .. } = events.recv().await.unwrap();
+        observed.push(serde_json::to_vec(&request).unwrap());
+        commitment.send(Ok(request.commit_and_sign(&commitment_key).await.unwrap())).unwrap();
+        let response = response.await.unwrap().unwrap();
+        observed.push(response.headers()[RESPONSE_SIGNATURE_HEADER].as_bytes().to_vec());
+        observed.push(response.bytes().await.unwrap().to_vec());
+
+        // Once the whitelist entry has expired, the same request is rejected
+        env.advance(Duration::from_secs(60));
+        let response = send().await.unwrap();
+        observed.push(response.headers()[RESPONSE_SIGNATURE_HEADER].as_bytes().to_vec());
+        observed.push(response.bytes().await.unwrap().to_vec());
+
+        // The headers are left out, as the host header contains the ephemeral port
+        let mut captured =
+            std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().path()).collect::<Vec<_>>();
+        captured.sort();
+        for path in captured {
+            observed.push(path.file_name().unwrap().to_string_lossy().as_bytes().to_vec());
+            observed.push(std::fs::read(path.join("request.bin")).unwrap());
+            observed.push(std::fs::read(path.join("response.bin")).unwrap());
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+        observed
+    }
+
+    #[cfg(feature = "admin")]
+    #[tokio::test]
+    async fn test_deterministic_env_is_reproducible() {
+        let _ = tracing_subscriber::fmt::try_init();
+
+        let first = run_deterministic_scenario(7).await;
+        assert_eq!(first.len(), 5 + 2 * 3, "both requests are captured");
+        assert_eq!(first, run_deterministic_scenario(7).await);
+
+        // Another seed draws other keys, so every signature differs
+        assert_ne!(first, run_deterministic_scenario(8).await);
     }
 }
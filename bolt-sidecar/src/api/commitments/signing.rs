@@ -0,0 +1,87 @@
+use std::{fmt, sync::Arc};
+
+use alloy::{
+    primitives::{keccak256, Address, B256},
+    signers::Signer,
+};
+use axum::{
+    body::{to_bytes, Body},
+    http::HeaderValue,
+    response::Response,
+};
+use serde_json::Value;
+use tracing::error;
+
+use crate::{common::canonical, primitives::commitment::ECDSASignatureExt};
+
+/// The header carrying the signature of the commitment key over a response.
+pub const RESPONSE_SIGNATURE_HEADER: &str = "x-bolt-response-signature";
+
+/// The capability advertised in `bolt_getVersion` when responses are signed.
+pub const RESPONSE_SIGNING_CAPABILITY: &str = "response_signing";
+
+/// Returns the digest signed in the [`RESPONSE_SIGNATURE_HEADER`]: the keccak256 hash of the
+/// exact response body bytes, followed by the tagged canonical JSON encoding of the request id
+/// (`null` if none).
+///
+/// Binding the request id prevents a middlebox from replaying a signed response to another
+/// request.
+pub fn response_digest(body: &[u8], id: Option<&Value>) -> B256 {
+    let id = canonical::hash_input(&id).expect("JSON values serialize");
+    keccak256([body, &id].concat())
+}
+
+/// Signs the responses of the commitments API with the commitment key, so that clients can
+/// verify them even when TLS terminates before the sidecar.
+///
+/// Every response costs a signature, which can be expensive with remote signers, so this is
+/// only enabled with `--sign-responses`.
+#[derive(Clone)]
+pub struct ResponseSigner {
+    signer: Arc<dyn Signer + Send + Sync>,
+}
+
+impl fmt::Debug for ResponseSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseSigner").field("address", &self.signer.address()).finish()
+    }
+}
+
+impl ResponseSigner {
+    /// Creates a response signer from the commitment signer.
+    pub fn new<S: Signer + Send + Sync + 'static>(signer: S) -> Self {
+        Self { signer: Arc::new(signer) }
+    }
+
+    /// Returns the address of the key the responses are signed with.
+    pub fn address(&self) -> Address {
+        self.signer.address()
+    }
+
+    /// Signs the serialized response to the request with the given id, attaching the
+    /// signature in the [`RESPONSE_SIGNATURE_HEADER`].
+    ///
+    /// If signing fails, the response is returned unsigned: clients expecting a signature
+    /// reject it the same way as a tampered one.
+    pub(super) async fn sign(&self, id: Option<&Value>, response: Response) -> Response {
+        let (mut parts, body) = response.into_parts();
+
+        let bytes = match to_bytes(body, usize::MAX).await {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                error!(?err, "Failed to buffer response body for signing");
+                return Response::from_parts(parts, Body::empty());
+            }
+        };
+
+        match self.signer.sign_hash(&response_digest(&bytes, id)).await {
+            Ok(signature) => {
+                let value = HeaderValue::from_str(&signature.to_hex()).expect("Valid header");
+                parts.headers.insert(RESPONSE_SIGNATURE_HEADER, value);
+            }
+            Err(err) => error!(?err, "Failed to sign response"),
+        }
+
+        Response::from_parts(parts, Body::from(bytes))
+    }
+}
@@ -1,20 +1,221 @@
-use alloy::primitives::SignatureError;
-use axum::{extract::rejection::JsonRejection, http::StatusCode, response::IntoResponse, Json};
+//! Types and errors of the commitments API.
+//!
+//! # Error codes
+//!
+//! Every error is returned as a JSON-RPC error with a stable code. Codes that cover several
+//! errors include a `reason` sub-code in the error data, and all of them include the
+//! `retryable` flag and `retry_hint` of their [`RetryPolicy`]. The fields of the errors are
+//! added to the data next to them. Every code and reason is listed in [`ERROR_CATALOGUE`].
+//!
+//! | Code     | Error                            | Data                                          |
+//! |----------|----------------------------------|-----------------------------------------------|
+//! | `-32000` | Request rejected                 | `reason`, the fields of the [`RejectionError`] |
+//! | `-32001` | Duplicate request                |                                               |
+//! | `-32002` | Internal server error            |                                               |
+//! | `-32003` | Missing signature header         |                                               |
+//! | `-32004` | Invalid signature                |                                               |
+//! | `-32005` | Signature error                  |                                               |
+//! | `-32006` | Consensus or state validation    | `reason`, e.g. `expected` and `got` nonces    |
+//! | `-32007` | Malformed signature header       |                                               |
+//! | `-32008` | Slot not assigned to the sidecar | `slot`, `our_next_slots`, `registry_hint_url` |
+//! | `-32009` | Sidecar not ready                |                                               |
+//! | `-32010` | Commitment import failed         | `reason`                                      |
+//! | `-32011` | Sidecar not synced               |                                               |
+//! | `-32012` | Persistence unavailable          |                                               |
+//! | `-32013` | Timed out                        | `elapsed_ms`                                  |
+//! | `-32014` | Commitment service unavailable   |                                               |
+//! | `-32015` | Cancellation failed              | `reason`                                      |
+//! | `-32016` | Rate limited                     | `retry_after_ms`                              |
+//! | `-32017` | Overloaded                       | `retry_after_ms`                              |
+//! | `-32018` | Shutting down                    |                                               |
+//! | `-32019` | Unsupported signature scheme     | `scheme`                                      |
+//! | `-32020` | Contract signature check failed  | `reason`                                      |
+//! | `-32021` | Replay protection rejected       | `reason`, `timestamp_ms` and `window_ms`      |
+//! | `-32022` | Unauthorized                     | `reason`                                      |
+//! | `-32023` | Signer quota exceeded            | `reason`, `limit`, `used`, `resets_at_slot`   |
+//! | `-32024` | Remote signer unavailable        |                                               |
+//! | `-32600` | Invalid JSON-RPC request         |                                               |
+//! | `-32601` | Unknown method                   |                                               |
+//! | `-32602` | Invalid params                   | `reason`, the fields of the [`RejectionError`] |
+//!
+//! The codes are never reused: new errors get a new code or reason, so that clients can match
+//! on them across versions of the sidecar.
+
+use std::{
+    io,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::Path,
+};
+
+use alloy::primitives::{keccak256, Address, Signature, SignatureError, B256};
+use axum::{
+    extract::rejection::{BytesRejection, JsonRejection},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use thiserror::Error;
 
 use crate::{
-    primitives::{commitment::InclusionCommitment, InclusionRequest},
-    state::{consensus::ConsensusError, ValidationError},
+    config::Limits,
+    crypto::external::ImportError,
+    drops::DropError,
+    primitives::{
+        commitment::{
+            deserialize_sig, ECDSASignatureExt, ExclusionCommitment, InclusionCommitment,
+        },
+        parse_address, ExclusionRequest, InclusionRequest, Slot,
+    },
+    probe::ProbeStage,
+    state::{
+        consensus::ConsensusError,
+        health::DependencyHealth,
+        quotas::{Quota, QuotaError},
+        relays::RelayPerformance,
+        scheduler::ScheduleEntry,
+        StateSnapshot, ValidationError,
+    },
 };
 
-use super::jsonrpc::JsonResponse;
+use super::{
+    antireplay::{timestamped_digest, ReplayError},
+    capture::CaptureError,
+    erc1271::Erc1271Error,
+    jsonrpc::{JsonError, JsonResponse, PayloadError},
+    jwt::JwtAuthError,
+    request_id::RequestId,
+    tls::TlsError,
+};
+
+/// The header carrying the signature of the request signer, formatted as `<signer>:<signature>`,
+/// optionally prefixed by its [`SignatureScheme`], e.g. `eip712:<signer>:<signature>`, and
+/// optionally followed by the timestamp of the signature for the
+/// [replay protection](super::antireplay), e.g. `<signer>:<signature>:<timestamp>`.
+pub const SIGNATURE_HEADER: &str = "x-bolt-signature";
+
+/// The prefix of the [`SIGNATURE_HEADER`] carrying an EIP-712 typed-data signature.
+pub const EIP712_SCHEME: &str = "eip712";
+
+/// The scheme of the signature in the [`SIGNATURE_HEADER`], given by its prefix.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SignatureScheme {
+    /// A signature over the raw digest of the request, without a prefix.
+    #[default]
+    Raw,
+    /// A signature over the EIP-712 digest of the request, as made by wallets with
+    /// `eth_signTypedData_v4`, with the [`EIP712_SCHEME`] prefix.
+    Eip712,
+}
 
-pub(super) const SIGNATURE_HEADER: &str = "x-bolt-signature";
+impl SignatureScheme {
+    /// Splits the value of the [`SIGNATURE_HEADER`] into its scheme and the rest of the
+    /// value, without the prefix.
+    ///
+    /// Values starting with an address have no prefix. Any other first part followed by a
+    /// signer and a signature is the prefix of an unsupported scheme.
+    pub fn split(auth: &str) -> Result<(Self, &str), Error> {
+        match auth.split_once(':') {
+            Some((EIP712_SCHEME, rest)) => Ok((Self::Eip712, rest)),
+            Some((prefix, rest)) if rest.contains(':') && parse_address(prefix).is_err() => {
+                Err(Error::UnsupportedSignatureScheme(prefix.to_string()))
+            }
+            _ => Ok((Self::Raw, auth)),
+        }
+    }
+
+    /// Formats the value of the [`SIGNATURE_HEADER`] for a signature of this scheme.
+    pub fn header_value(&self, signer: Address, signature: &Signature) -> String {
+        match self {
+            Self::Raw => format!("{signer}:{}", signature.to_hex()),
+            Self::Eip712 => format!("{EIP712_SCHEME}:{signer}:{}", signature.to_hex()),
+        }
+    }
+}
+
+/// The parsed value of the [`SIGNATURE_HEADER`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignatureHeader {
+    /// The scheme of the signature.
+    pub scheme: SignatureScheme,
+    /// The signer claimed by the header.
+    pub signer: Address,
+    /// The signature of the signer.
+    pub signature: Signature,
+    /// The UNIX timestamp at which the request was signed, in milliseconds, if the signature
+    /// covers one.
+    pub timestamp_ms: Option<u64>,
+}
+
+impl SignatureHeader {
+    /// Returns the digest signed by the signer for a request with the given digest: its
+    /// [`timestamped_digest`] if the header has a timestamp, or the digest itself.
+    pub fn signed_digest(&self, digest: B256) -> B256 {
+        match self.timestamp_ms {
+            Some(timestamp_ms) => timestamped_digest(digest, timestamp_ms),
+            None => digest,
+        }
+    }
+
+    /// Formats the value of the header.
+    pub fn header_value(&self) -> String {
+        let value = self.scheme.header_value(self.signer, &self.signature);
+        match self.timestamp_ms {
+            Some(timestamp_ms) => format!("{value}:{timestamp_ms}"),
+            None => value,
+        }
+    }
+}
 
 pub(super) const GET_VERSION_METHOD: &str = "bolt_getVersion";
 
 pub(super) const REQUEST_INCLUSION_METHOD: &str = "bolt_requestInclusion";
 
+pub(super) const REQUEST_EXCLUSION_METHOD: &str = "bolt_requestExclusion";
+
+pub(super) const GET_CHAIN_INFO_METHOD: &str = "bolt_getChainInfo";
+
+pub(super) const GET_STATUS_METHOD: &str = "bolt_getStatus";
+
+pub(super) const ENABLE_CAPTURE_METHOD: &str = "bolt_enableCapture";
+
+pub(super) const DISABLE_CAPTURE_METHOD: &str = "bolt_disableCapture";
+
+pub(super) const GET_EARNINGS_METHOD: &str = "bolt_getEarnings";
+
+pub(super) const IMPORT_SIGNED_COMMITMENT_METHOD: &str = "admin_importSignedCommitment";
+
+pub(super) const RELOAD_CERTIFICATES_METHOD: &str = "admin_reloadCertificates";
+
+pub(super) const FIND_COMMITMENTS_BY_CONTEXT_METHOD: &str = "bolt_findCommitmentsByContext";
+
+pub(super) const REBIND_LISTENER_METHOD: &str = "admin_rebindListener";
+
+pub(super) const GET_WHITELIST_METHOD: &str = "admin_getWhitelist";
+
+pub(super) const DROP_COMMITMENT_METHOD: &str = "admin_dropCommitment";
+
+pub(super) const CONFIRM_DROP_METHOD: &str = "admin_confirmDrop";
+
+pub(super) const RESTORE_COMMITMENT_METHOD: &str = "admin_restoreCommitment";
+
+pub(super) const GET_SLOT_MANIFEST_METHOD: &str = "bolt_getSlotManifest";
+
+pub(super) const DECLARE_MAINTENANCE_METHOD: &str = "admin_declareMaintenance";
+
+pub(super) const CANCEL_MAINTENANCE_METHOD: &str = "admin_cancelMaintenance";
+
+pub(super) const GET_COMMITMENT_METHOD: &str = "bolt_getCommitment";
+
+pub(super) const GET_COMMITMENT_STATUS_METHOD: &str = "bolt_getCommitmentStatus";
+
+pub(super) const CANCEL_INCLUSION_METHOD: &str = "bolt_cancelInclusion";
+
+pub(super) const METADATA_METHOD: &str = "bolt_metadata";
+
+pub(super) const DISCOVER_METHOD: &str = "rpc.discover";
+
 /// Error type for the commitments API.
 #[derive(Debug, Error)]
 pub enum Error {
@@ -42,85 +243,1937 @@ pub enum Error {
     /// Malformed authentication header.
     #[error("Malformed authentication header")]
     MalformedHeader,
+    /// The scheme of the signature header isn't supported, or not by the method.
+    #[error("Unsupported signature scheme '{0}'")]
+    UnsupportedSignatureScheme(String),
     /// Signature error.
     #[error(transparent)]
     Signature(#[from] SignatureError),
+    /// The sidecar is still warming up and not accepting requests yet.
+    #[error("Sidecar is not ready yet, try again later")]
+    NotReady,
+    /// The chain heads observed by the sidecar are stale.
+    #[error("Sidecar is not synced with the chain head, try again later")]
+    NotSynced,
+    /// The commitment couldn't be recorded in the store, so it wasn't signed.
+    #[error("Persistence unavailable, try again later")]
+    PersistenceUnavailable,
+    /// The driver is unavailable, because it crashed or is shutting down.
+    #[error("Commitment service unavailable, try again later")]
+    ServiceUnavailable,
+    /// The driver didn't respond to the request before its deadline. The request may still
+    /// be committed to.
+    #[error("Timed out waiting for the commitment after {elapsed_ms}ms")]
+    Timeout {
+        /// The time elapsed since the request was dispatched, in milliseconds.
+        elapsed_ms: u64,
+    },
+    /// The request signer exceeded its rate of inclusion requests.
+    #[error("Rate limit exceeded, retry after {retry_after_ms}ms")]
+    RateLimited {
+        /// The time until the signer can send a request again, in milliseconds.
+        retry_after_ms: u64,
+    },
+    /// Too many commitment requests are in flight to accept another one.
+    #[error("Sidecar is overloaded, retry after {retry_after_ms}ms")]
+    Overloaded {
+        /// The time after which the request can be retried, in milliseconds.
+        retry_after_ms: u64,
+    },
+    /// The sidecar is shutting down, and only responds to the requests it already received.
+    #[error("Sidecar is shutting down, try another instance or again later")]
+    ShuttingDown,
+    /// Importing an externally signed commitment failed.
+    #[error(transparent)]
+    Import(#[from] ImportError),
+    /// Cancelling a commitment failed.
+    #[error(transparent)]
+    Cancel(#[from] CancelError),
+    /// The EIP-1271 check of the signature of a smart-contract wallet failed.
+    #[error(transparent)]
+    ContractSignature(#[from] Erc1271Error),
+    /// The request was rejected by the replay protection.
+    #[error(transparent)]
+    Replay(#[from] ReplayError),
+    /// The request has no valid JWT bearer token.
+    #[error(transparent)]
+    Unauthorized(#[from] JwtAuthError),
+    /// The request would exceed a quota of its signer.
+    #[error(transparent)]
+    Quota(#[from] QuotaError),
+    /// The remote signer of the commitments and constraints failed to sign, after retries.
+    #[error("Remote signer unavailable, try again later")]
+    SignerUnavailable,
     /// Unknown method.
     #[error("Unknown method")]
     UnknownMethod,
     /// Invalid JSON.
     #[error(transparent)]
     InvalidJson(#[from] JsonRejection),
+    /// The request body could not be read, e.g. because it is too large.
+    #[error(transparent)]
+    InvalidBody(#[from] BytesRejection),
+    /// The request is valid JSON, but too deeply nested or with an invalid id or method.
+    #[error(transparent)]
+    InvalidPayload(#[from] PayloadError),
+    /// An error returned by a driver in another process, over the link.
+    #[error("{}", .0.message)]
+    Remote(JsonError),
+}
+
+impl Error {
+    /// Returns whether and how a client can retry a request that failed with this error.
+    ///
+    /// Every variant is classified explicitly, so that new variants can't be
+    /// added without deciding how clients should react to them.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        match self {
+            Error::Rejected(err) => match err {
+                RejectionError::MissingParams { .. } => RetryPolicy::modify("params"),
+                RejectionError::InvalidParams { .. } => RetryPolicy::modify("params"),
+                RejectionError::SignerNotWhitelisted(_) => RetryPolicy::permanent(),
+                RejectionError::AuthorizationExpired(..) => RetryPolicy::permanent(),
+                RejectionError::NotEnabled(_) => RetryPolicy::permanent(),
+                RejectionError::ListenerNotEnabled(_) => RetryPolicy::permanent(),
+                RejectionError::BindFailed { .. } => RetryPolicy::permanent(),
+                RejectionError::Capture(_) => RetryPolicy::permanent(),
+                RejectionError::Tls(_) => RetryPolicy::permanent(),
+                RejectionError::Drop(_) => RetryPolicy::permanent(),
+                RejectionError::UnknownMaintenanceWindow(_) => RetryPolicy::permanent(),
+            },
+            Error::Consensus(err) => match err {
+                ConsensusError::BeaconApiError(_) => RetryPolicy::later(),
+                ConsensusError::InvalidSlot(_) => RetryPolicy::modify("slot"),
+                ConsensusError::DeadlineExceeded { .. } => RetryPolicy::modify("slot"),
+                ConsensusError::DeadlinePassedDuringProcessing { .. } => {
+                    RetryPolicy::modify("slot")
+                }
+                ConsensusError::ValidatorNotFound => RetryPolicy::modify("slot"),
+                // Another instance holds the lease, but it may be handed over
+                ConsensusError::LeaseNotHeld(_) => RetryPolicy::later(),
+                ConsensusError::NotOurSlot { .. } => RetryPolicy::modify("slot"),
+                ConsensusError::ProposerUnavailable { .. } => RetryPolicy::modify("slot"),
+                ConsensusError::InvalidSlotRange { .. } => RetryPolicy::modify("max_slot"),
+                ConsensusError::SlotRangePassed(_) => RetryPolicy::modify("max_slot"),
+                ConsensusError::NoProposerInRange { .. } => RetryPolicy::modify("max_slot"),
+                ConsensusError::Recorded(_) => RetryPolicy::later(),
+            },
+            Error::Validation(err) => validation_retry_policy(err),
+            Error::Duplicate => RetryPolicy::permanent(),
+            Error::Internal => RetryPolicy::later(),
+            Error::NoSignature => RetryPolicy::modify(SIGNATURE_HEADER),
+            Error::InvalidSignature(_) => RetryPolicy::modify(SIGNATURE_HEADER),
+            Error::MalformedHeader => RetryPolicy::modify(SIGNATURE_HEADER),
+            Error::UnsupportedSignatureScheme(_) => RetryPolicy::modify(SIGNATURE_HEADER),
+            Error::Signature(_) => RetryPolicy::modify(SIGNATURE_HEADER),
+            Error::NotReady => RetryPolicy::later(),
+            Error::NotSynced => RetryPolicy::later(),
+            Error::PersistenceUnavailable => RetryPolicy::later(),
+            Error::ServiceUnavailable => RetryPolicy::later(),
+            Error::Timeout { .. } => RetryPolicy::later(),
+            Error::RateLimited { .. } => RetryPolicy::later(),
+            Error::Overloaded { .. } => RetryPolicy::later(),
+            Error::ShuttingDown => RetryPolicy::later(),
+            Error::Import(err) => match err {
+                ImportError::NotEnabled => RetryPolicy::permanent(),
+                ImportError::UnknownDigest(_) => RetryPolicy::permanent(),
+                ImportError::SignerMismatch { .. } => RetryPolicy::modify("signature"),
+                ImportError::InvalidSignature(_) => RetryPolicy::modify("signature"),
+            },
+            Error::Cancel(err) => match err {
+                CancelError::NotEnabled => RetryPolicy::permanent(),
+                CancelError::UnknownCommitment(_) => RetryPolicy::permanent(),
+                CancelError::SignerMismatch(_) => RetryPolicy::modify(SIGNATURE_HEADER),
+            },
+            Error::ContractSignature(err) => match err {
+                Erc1271Error::Reverted(_) => RetryPolicy::modify(SIGNATURE_HEADER),
+                Erc1271Error::InvalidMagicValue(_) => RetryPolicy::modify(SIGNATURE_HEADER),
+                Erc1271Error::Rpc(_) => RetryPolicy::later(),
+            },
+            Error::Replay(err) => match err {
+                ReplayError::MissingTimestamp => RetryPolicy::modify(SIGNATURE_HEADER),
+                // The request can be signed again with a current timestamp
+                ReplayError::TimestampExpired { .. } => RetryPolicy::modify(SIGNATURE_HEADER),
+                ReplayError::Replayed => RetryPolicy::permanent(),
+            },
+            Error::Unauthorized(_) => RetryPolicy::modify("authorization"),
+            // The quota of the signer is available again from `resets_at_slot`
+            Error::Quota(_) => RetryPolicy::modify("slot"),
+            Error::SignerUnavailable => RetryPolicy::later(),
+            Error::UnknownMethod => RetryPolicy::modify("method"),
+            Error::InvalidJson(_) => RetryPolicy::permanent(),
+            Error::InvalidBody(_) => RetryPolicy::permanent(),
+            Error::InvalidPayload(_) => RetryPolicy::permanent(),
+            // The policy of the original error is included in its data
+            Error::Remote(err) => err
+                .data
+                .clone()
+                .and_then(|data| serde_json::from_value(data).ok())
+                .unwrap_or_else(RetryPolicy::permanent),
+        }
+    }
+
+    /// Returns the JSON-RPC error code of the error, with its reason sub-code if it has one.
+    /// Every returned code is listed in [`ERROR_CATALOGUE`].
+    pub fn error_code(&self) -> ErrorCode {
+        let reason = ErrorCode::with_reason;
+
+        match self {
+            Error::Rejected(err) => match err {
+                RejectionError::MissingParams { .. } | RejectionError::InvalidParams { .. } => {
+                    reason(-32602, rejection_reason(err))
+                }
+                _ => reason(-32000, rejection_reason(err)),
+            },
+            Error::Duplicate => ErrorCode::plain(-32001),
+            Error::Internal => ErrorCode::plain(-32002),
+            Error::NoSignature => ErrorCode::plain(-32003),
+            Error::InvalidSignature(_) => ErrorCode::plain(-32004),
+            Error::Signature(_) => ErrorCode::plain(-32005),
+            Error::Consensus(err) => match err {
+                ConsensusError::BeaconApiError(_) => reason(-32006, "beacon_api_error"),
+                ConsensusError::Recorded(_) => reason(-32006, "beacon_api_error"),
+                ConsensusError::InvalidSlot(_) => reason(-32006, "invalid_slot"),
+                ConsensusError::DeadlineExceeded { .. } => reason(-32006, "deadline_exceeded"),
+                ConsensusError::DeadlinePassedDuringProcessing { .. } => {
+                    reason(-32006, "deadline_passed_during_processing")
+                }
+                ConsensusError::ValidatorNotFound => reason(-32006, "validator_not_found"),
+                ConsensusError::LeaseNotHeld(_) => reason(-32006, "lease_not_held"),
+                ConsensusError::NotOurSlot { .. } => ErrorCode::plain(-32008),
+                ConsensusError::ProposerUnavailable { .. } => {
+                    reason(-32006, "proposer_unavailable")
+                }
+                ConsensusError::InvalidSlotRange { .. } => reason(-32006, "invalid_slot_range"),
+                ConsensusError::SlotRangePassed(_) => reason(-32006, "slot_range_passed"),
+                ConsensusError::NoProposerInRange { .. } => reason(-32006, "no_proposer_in_range"),
+            },
+            Error::Validation(err) => reason(-32006, validation_reason(err)),
+            Error::MalformedHeader => ErrorCode::plain(-32007),
+            Error::NotReady => ErrorCode::plain(-32009),
+            Error::NotSynced => ErrorCode::plain(-32011),
+            Error::PersistenceUnavailable => ErrorCode::plain(-32012),
+            Error::Timeout { .. } => ErrorCode::plain(-32013),
+            Error::ServiceUnavailable => ErrorCode::plain(-32014),
+            Error::RateLimited { .. } => ErrorCode::plain(-32016),
+            Error::Overloaded { .. } => ErrorCode::plain(-32017),
+            Error::ShuttingDown => ErrorCode::plain(-32018),
+            Error::UnsupportedSignatureScheme(_) => ErrorCode::plain(-32019),
+            Error::Import(err) => match err {
+                ImportError::NotEnabled => reason(-32010, "not_enabled"),
+                ImportError::UnknownDigest(_) => reason(-32010, "unknown_digest"),
+                ImportError::SignerMismatch { .. } => reason(-32010, "signer_mismatch"),
+                ImportError::InvalidSignature(_) => reason(-32010, "invalid_signature"),
+            },
+            Error::Cancel(err) => match err {
+                CancelError::NotEnabled => reason(-32015, "not_enabled"),
+                CancelError::UnknownCommitment(_) => reason(-32015, "unknown_commitment"),
+                CancelError::SignerMismatch(_) => reason(-32015, "signer_mismatch"),
+            },
+            Error::ContractSignature(err) => match err {
+                Erc1271Error::Reverted(_) => reason(-32020, "contract_reverted"),
+                Erc1271Error::InvalidMagicValue(_) => reason(-32020, "invalid_magic_value"),
+                Erc1271Error::Rpc(_) => reason(-32020, "rpc_unavailable"),
+            },
+            Error::Replay(err) => match err {
+                ReplayError::MissingTimestamp => reason(-32021, "missing_timestamp"),
+                ReplayError::TimestampExpired { .. } => reason(-32021, "timestamp_expired"),
+                ReplayError::Replayed => reason(-32021, "replayed"),
+            },
+            Error::Unauthorized(err) => match err {
+                JwtAuthError::MissingToken => reason(-32022, "missing_token"),
+                JwtAuthError::ExpiredToken => reason(-32022, "expired_token"),
+                JwtAuthError::InvalidToken(_) => reason(-32022, "invalid_token"),
+            },
+            Error::Quota(err) => reason(-32023, err.quota.as_str()),
+            Error::SignerUnavailable => ErrorCode::plain(-32024),
+            Error::UnknownMethod => ErrorCode::plain(-32601),
+            Error::InvalidJson(_) => ErrorCode::plain(-32600),
+            Error::InvalidBody(_) => ErrorCode::plain(-32600),
+            Error::InvalidPayload(_) => ErrorCode::plain(-32600),
+            Error::Remote(err) => {
+                let reason = err.data.as_ref().and_then(|data| data["reason"].as_str());
+                ERROR_CATALOGUE
+                    .iter()
+                    .find(|entry| entry.code == err.code && entry.reason == reason)
+                    .copied()
+                    .unwrap_or(ErrorCode::plain(err.code))
+            }
+        }
+    }
+
+    /// Returns the HTTP status of the response carrying the error.
+    ///
+    /// Errors of the JSON-RPC layer ride a `200 OK` response with the error object, so that
+    /// load balancers and HTTP retry layers don't treat them as failures of the server. Only
+    /// problems of the transport itself use a 4xx or 5xx status, and their response still
+    /// carries a JSON-RPC error object.
+    pub fn http_status(&self) -> StatusCode {
+        match self {
+            Error::Rejected(_) => StatusCode::OK,
+            Error::Consensus(_) => StatusCode::OK,
+            Error::Validation(_) => StatusCode::OK,
+            Error::Duplicate => StatusCode::OK,
+            Error::Internal => StatusCode::OK,
+            Error::NoSignature => StatusCode::OK,
+            Error::InvalidSignature(_) => StatusCode::OK,
+            Error::MalformedHeader => StatusCode::OK,
+            Error::UnsupportedSignatureScheme(_) => StatusCode::OK,
+            Error::Signature(_) => StatusCode::OK,
+            Error::Import(_) => StatusCode::OK,
+            Error::Cancel(_) => StatusCode::OK,
+            Error::ContractSignature(_) => StatusCode::OK,
+            Error::Replay(_) => StatusCode::OK,
+            Error::Quota(_) => StatusCode::OK,
+            Error::SignerUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            Error::UnknownMethod => StatusCode::OK,
+            // Like the engine API, so that the token is refreshed by HTTP auth layers
+            Error::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Error::NotReady => StatusCode::SERVICE_UNAVAILABLE,
+            Error::NotSynced => StatusCode::SERVICE_UNAVAILABLE,
+            Error::PersistenceUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            Error::ServiceUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            Error::Timeout { .. } => StatusCode::OK,
+            // Like the unavailability errors, so that HTTP clients and proxies back off
+            Error::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Error::Overloaded { .. } => StatusCode::OK,
+            Error::ShuttingDown => StatusCode::SERVICE_UNAVAILABLE,
+            Error::InvalidJson(err) => match err {
+                // The body was received, but isn't a valid JSON-RPC request
+                JsonRejection::JsonDataError(_) | JsonRejection::JsonSyntaxError(_) => {
+                    StatusCode::OK
+                }
+                // Missing or wrong content type, or the body couldn't be read
+                err => err.status(),
+            },
+            Error::InvalidBody(err) => err.status(),
+            Error::InvalidPayload(_) => StatusCode::OK,
+            // The status of the original error, which can't be a transport error
+            Error::Remote(err) => match err.code {
+                -32009 | -32011 | -32012 | -32014 | -32018 => StatusCode::SERVICE_UNAVAILABLE,
+                -32016 => StatusCode::TOO_MANY_REQUESTS,
+                _ => StatusCode::OK,
+            },
+        }
+    }
+}
+
+/// Returns the reason sub-code of a rejection error.
+fn rejection_reason(err: &RejectionError) -> &'static str {
+    match err {
+        RejectionError::MissingParams { .. } => "missing_params",
+        RejectionError::InvalidParams { .. } => "invalid_params",
+        RejectionError::SignerNotWhitelisted(_) => "signer_not_whitelisted",
+        RejectionError::AuthorizationExpired(..) => "authorization_expired",
+        RejectionError::NotEnabled(_) => "not_enabled",
+        RejectionError::ListenerNotEnabled(_) => "listener_not_enabled",
+        RejectionError::BindFailed { .. } => "bind_failed",
+        RejectionError::Capture(CaptureError::NoDirectory) => "no_capture_directory",
+        RejectionError::Capture(CaptureError::SizeCapReached(_)) => "capture_size_cap_reached",
+        RejectionError::Capture(CaptureError::Io(_)) => "capture_failed",
+        RejectionError::Tls(_) => "certificate_reload_failed",
+        RejectionError::Drop(err) => match err {
+            DropError::NotFound(_) => "commitment_not_found",
+            DropError::AlreadyDropped(_) => "already_dropped",
+            DropError::NotDropped(_) => "not_dropped",
+            DropError::AlreadyConfirmed(_) => "drop_already_confirmed",
+            DropError::SlotPassed(..) => "slot_passed",
+        },
+        RejectionError::UnknownMaintenanceWindow(_) => "unknown_maintenance_window",
+    }
 }
 
+/// Returns the retry policy of a validation error.
+fn validation_retry_policy(err: &ValidationError) -> RetryPolicy {
+    match err {
+        ValidationError::BaseFeeTooLow { .. } => RetryPolicy::modify("max_fee_per_gas"),
+        ValidationError::BlobBaseFeeTooLow(_) => RetryPolicy::modify("max_fee_per_blob_gas"),
+        ValidationError::BlobValidation(_) => RetryPolicy::modify("txs"),
+        ValidationError::MaxBaseFeeCalcOverflow => RetryPolicy::modify("slot"),
+        ValidationError::NonceTooLow(_, _) => RetryPolicy::permanent(),
+        // The missing nonces may be committed to in the meantime
+        ValidationError::NonceTooHigh(_, _) => RetryPolicy::later(),
+        // The committed transaction must be cancelled before it's replaced
+        ValidationError::NonceAlreadyCommitted(_) => RetryPolicy::permanent(),
+        ValidationError::AccountHasCode => RetryPolicy::permanent(),
+        ValidationError::GasLimitTooHigh => RetryPolicy::modify("gas_limit"),
+        ValidationError::TransactionSizeTooHigh => RetryPolicy::modify("input"),
+        ValidationError::MaxPriorityFeePerGasTooHigh => {
+            RetryPolicy::modify("max_priority_fee_per_gas")
+        }
+        ValidationError::PriorityFeeTooLow(_) => RetryPolicy::modify("max_priority_fee_per_gas"),
+        // The account may be funded in the meantime
+        ValidationError::InsufficientBalance { .. } => RetryPolicy::later(),
+        ValidationError::Eip4844Limit => RetryPolicy::modify("slot"),
+        ValidationError::SlotTooLow(_) => RetryPolicy::modify("slot"),
+        ValidationError::MaxCommitmentsReachedForSlot(_, _) => RetryPolicy::modify("slot"),
+        ValidationError::MaxCommittedGasReachedForSlot(_, _) => RetryPolicy::modify("slot"),
+        ValidationError::ByteBudgetExceeded { .. } => RetryPolicy::modify("slot"),
+        ValidationError::BlobBudgetExceeded { .. } => RetryPolicy::modify("slot"),
+        // Memory is released as the earlier slots expire
+        ValidationError::InsufficientQueueMemory { .. } => RetryPolicy::later(),
+        ValidationError::Signature(_) => RetryPolicy::permanent(),
+        ValidationError::RecoverSigner => RetryPolicy::permanent(),
+        ValidationError::ChainIdMismatch => RetryPolicy::permanent(),
+        // The transaction type activates at a later fork
+        ValidationError::TransactionTypeNotActive(_) => RetryPolicy::modify("slot"),
+        ValidationError::InvalidContext(_) => RetryPolicy::modify("context"),
+        // The offending transaction decides whether the bundle can be retried
+        ValidationError::InvalidBundleTransaction { source, .. } => validation_retry_policy(source),
+        ValidationError::InvalidExclusion(_) => RetryPolicy::modify("senders"),
+        // The conflicting commitment holds until its slot
+        ValidationError::ExclusionConflict { .. } => RetryPolicy::modify("slot"),
+        ValidationError::Internal(_) => RetryPolicy::later(),
+    }
+}
+
+/// Returns the reason sub-code of a validation error.
+fn validation_reason(err: &ValidationError) -> &'static str {
+    match err {
+        ValidationError::BaseFeeTooLow { .. } => "base_fee_too_low",
+        ValidationError::BlobBaseFeeTooLow(_) => "blob_base_fee_too_low",
+        ValidationError::BlobValidation(_) => "blob_validation",
+        ValidationError::MaxBaseFeeCalcOverflow => "max_base_fee_calc_overflow",
+        ValidationError::NonceTooLow(_, _) => "nonce_too_low",
+        ValidationError::NonceTooHigh(_, _) => "nonce_too_high",
+        ValidationError::NonceAlreadyCommitted(_) => "nonce_already_committed",
+        ValidationError::AccountHasCode => "account_has_code",
+        ValidationError::GasLimitTooHigh => "gas_limit_too_high",
+        ValidationError::TransactionSizeTooHigh => "transaction_size_too_high",
+        ValidationError::MaxPriorityFeePerGasTooHigh => "max_priority_fee_per_gas_too_high",
+        ValidationError::PriorityFeeTooLow(_) => "priority_fee_too_low",
+        ValidationError::InsufficientBalance { .. } => "insufficient_balance",
+        ValidationError::Eip4844Limit => "eip4844_limit",
+        ValidationError::SlotTooLow(_) => "slot_too_low",
+        ValidationError::MaxCommitmentsReachedForSlot(_, _) => "max_commitments_reached",
+        ValidationError::MaxCommittedGasReachedForSlot(_, _) => "max_committed_gas_reached",
+        ValidationError::ByteBudgetExceeded { .. } => "byte_budget_exceeded",
+        ValidationError::BlobBudgetExceeded { .. } => "blob_budget_exceeded",
+        ValidationError::InsufficientQueueMemory { .. } => "insufficient_queue_memory",
+        ValidationError::Signature(_) => "invalid_transaction_signature",
+        ValidationError::RecoverSigner => "recover_signer",
+        ValidationError::ChainIdMismatch => "chain_id_mismatch",
+        ValidationError::TransactionTypeNotActive(_) => "transaction_type_not_active",
+        ValidationError::InvalidContext(_) => "invalid_context",
+        ValidationError::InvalidBundleTransaction { .. } => "invalid_bundle_transaction",
+        ValidationError::InvalidExclusion(_) => "invalid_exclusion",
+        ValidationError::ExclusionConflict { .. } => "exclusion_conflict",
+        ValidationError::Internal(_) => "internal",
+    }
+}
+
+/// A JSON-RPC error code of the commitments API, with the reason sub-code that is
+/// included in the error data as `reason` for codes that cover several errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorCode {
+    /// The JSON-RPC error code.
+    pub code: i32,
+    /// The reason sub-code, if any.
+    pub reason: Option<&'static str>,
+}
+
+impl ErrorCode {
+    const fn plain(code: i32) -> Self {
+        Self { code, reason: None }
+    }
+
+    const fn with_reason(code: i32, reason: &'static str) -> Self {
+        Self { code, reason: Some(reason) }
+    }
+}
+
+/// Every error code and reason returned by the commitments API.
+pub const ERROR_CATALOGUE: &[ErrorCode] = &[
+    ErrorCode::with_reason(-32000, "signer_not_whitelisted"),
+    ErrorCode::with_reason(-32000, "authorization_expired"),
+    ErrorCode::with_reason(-32000, "not_enabled"),
+    ErrorCode::with_reason(-32000, "listener_not_enabled"),
+    ErrorCode::with_reason(-32000, "bind_failed"),
+    ErrorCode::with_reason(-32000, "no_capture_directory"),
+    ErrorCode::with_reason(-32000, "capture_size_cap_reached"),
+    ErrorCode::with_reason(-32000, "capture_failed"),
+    ErrorCode::with_reason(-32000, "certificate_reload_failed"),
+    ErrorCode::with_reason(-32000, "commitment_not_found"),
+    ErrorCode::with_reason(-32000, "already_dropped"),
+    ErrorCode::with_reason(-32000, "not_dropped"),
+    ErrorCode::with_reason(-32000, "drop_already_confirmed"),
+    ErrorCode::with_reason(-32000, "slot_passed"),
+    ErrorCode::with_reason(-32000, "unknown_maintenance_window"),
+    ErrorCode::plain(-32001),
+    ErrorCode::plain(-32002),
+    ErrorCode::plain(-32003),
+    ErrorCode::plain(-32004),
+    ErrorCode::plain(-32005),
+    ErrorCode::with_reason(-32006, "beacon_api_error"),
+    ErrorCode::with_reason(-32006, "invalid_slot"),
+    ErrorCode::with_reason(-32006, "deadline_exceeded"),
+    ErrorCode::with_reason(-32006, "deadline_passed_during_processing"),
+    ErrorCode::with_reason(-32006, "validator_not_found"),
+    ErrorCode::with_reason(-32006, "lease_not_held"),
+    ErrorCode::with_reason(-32006, "proposer_unavailable"),
+    ErrorCode::with_reason(-32006, "invalid_slot_range"),
+    ErrorCode::with_reason(-32006, "slot_range_passed"),
+    ErrorCode::with_reason(-32006, "no_proposer_in_range"),
+    ErrorCode::with_reason(-32006, "base_fee_too_low"),
+    ErrorCode::with_reason(-32006, "blob_base_fee_too_low"),
+    ErrorCode::with_reason(-32006, "blob_validation"),
+    ErrorCode::with_reason(-32006, "max_base_fee_calc_overflow"),
+    ErrorCode::with_reason(-32006, "nonce_too_low"),
+    ErrorCode::with_reason(-32006, "nonce_too_high"),
+    ErrorCode::with_reason(-32006, "nonce_already_committed"),
+    ErrorCode::with_reason(-32006, "account_has_code"),
+    ErrorCode::with_reason(-32006, "gas_limit_too_high"),
+    ErrorCode::with_reason(-32006, "transaction_size_too_high"),
+    ErrorCode::with_reason(-32006, "max_priority_fee_per_gas_too_high"),
+    ErrorCode::with_reason(-32006, "priority_fee_too_low"),
+    ErrorCode::with_reason(-32006, "insufficient_balance"),
+    ErrorCode::with_reason(-32006, "eip4844_limit"),
+    ErrorCode::with_reason(-32006, "slot_too_low"),
+    ErrorCode::with_reason(-32006, "max_commitments_reached"),
+    ErrorCode::with_reason(-32006, "max_committed_gas_reached"),
+    ErrorCode::with_reason(-32006, "byte_budget_exceeded"),
+    ErrorCode::with_reason(-32006, "blob_budget_exceeded"),
+    ErrorCode::with_reason(-32006, "insufficient_queue_memory"),
+    ErrorCode::with_reason(-32006, "invalid_transaction_signature"),
+    ErrorCode::with_reason(-32006, "recover_signer"),
+    ErrorCode::with_reason(-32006, "chain_id_mismatch"),
+    ErrorCode::with_reason(-32006, "transaction_type_not_active"),
+    ErrorCode::with_reason(-32006, "invalid_context"),
+    ErrorCode::with_reason(-32006, "invalid_bundle_transaction"),
+    ErrorCode::with_reason(-32006, "invalid_exclusion"),
+    ErrorCode::with_reason(-32006, "exclusion_conflict"),
+    ErrorCode::with_reason(-32006, "internal"),
+    ErrorCode::plain(-32007),
+    ErrorCode::plain(-32008),
+    ErrorCode::plain(-32009),
+    ErrorCode::with_reason(-32010, "not_enabled"),
+    ErrorCode::with_reason(-32010, "unknown_digest"),
+    ErrorCode::with_reason(-32010, "signer_mismatch"),
+    ErrorCode::with_reason(-32010, "invalid_signature"),
+    ErrorCode::plain(-32011),
+    ErrorCode::plain(-32012),
+    ErrorCode::plain(-32013),
+    ErrorCode::plain(-32014),
+    ErrorCode::with_reason(-32015, "not_enabled"),
+    ErrorCode::with_reason(-32015, "unknown_commitment"),
+    ErrorCode::with_reason(-32015, "signer_mismatch"),
+    ErrorCode::plain(-32016),
+    ErrorCode::plain(-32017),
+    ErrorCode::plain(-32018),
+    ErrorCode::plain(-32019),
+    ErrorCode::with_reason(-32020, "contract_reverted"),
+    ErrorCode::with_reason(-32020, "invalid_magic_value"),
+    ErrorCode::with_reason(-32020, "rpc_unavailable"),
+    ErrorCode::with_reason(-32021, "missing_timestamp"),
+    ErrorCode::with_reason(-32021, "timestamp_expired"),
+    ErrorCode::with_reason(-32021, "replayed"),
+    ErrorCode::with_reason(-32022, "missing_token"),
+    ErrorCode::with_reason(-32022, "expired_token"),
+    ErrorCode::with_reason(-32022, "invalid_token"),
+    ErrorCode::with_reason(-32023, "gas_per_slot"),
+    ErrorCode::with_reason(-32023, "txs_per_slot"),
+    ErrorCode::with_reason(-32023, "commitments_per_epoch"),
+    ErrorCode::plain(-32024),
+    ErrorCode::plain(-32600),
+    ErrorCode::plain(-32601),
+    ErrorCode::with_reason(-32602, "missing_params"),
+    ErrorCode::with_reason(-32602, "invalid_params"),
+];
+
+/// The HTTP status of the response is given by [`Error::http_status`], and its body by
+/// [`Error::into_json_error`].
 impl IntoResponse for Error {
     fn into_response(self) -> axum::http::Response<axum::body::Body> {
-        match self {
-            Error::Rejected(err) => {
-                (StatusCode::BAD_REQUEST, Json(JsonResponse::from_error(-32000, err.to_string())))
-                    .into_response()
+        let status = self.http_status();
+        let response = JsonResponse { error: Some(self.into_json_error()), ..Default::default() };
+        (status, Json(response)).into_response()
+    }
+}
+
+impl Error {
+    /// This is the single conversion point of errors into JSON-RPC errors. The error data
+    /// includes the [`RetryPolicy`], and the reason sub-code if there is one.
+    pub fn into_json_error(self) -> JsonError {
+        let ErrorCode { code, reason } = self.error_code();
+        let retry = self.retry_policy();
+
+        let (message, data) = match self {
+            Error::Rejected(ref err) => (err.to_string(), err.data()),
+            Error::Duplicate => (self.to_string(), None),
+            Error::Internal => (self.to_string(), None),
+            Error::NoSignature => (self.to_string(), None),
+            Error::InvalidSignature(err) => (err.to_string(), None),
+            Error::Signature(err) => (err.to_string(), None),
+            Error::Consensus(ConsensusError::NotOurSlot {
+                slot,
+                ref our_next_slots,
+                ref registry_hint_url,
+            }) => {
+                let data = serde_json::json!({
+                    "slot": slot,
+                    "our_next_slots": our_next_slots,
+                    "registry_hint_url": registry_hint_url.as_ref().map(|url| url.as_str()),
+                });
+                (self.to_string(), Some(data))
+            }
+            Error::Consensus(ConsensusError::ProposerUnavailable { slot, ref our_next_slots }) => {
+                let data = serde_json::json!({ "slot": slot, "our_next_slots": our_next_slots });
+                (self.to_string(), Some(data))
+            }
+            Error::Consensus(ConsensusError::DeadlinePassedDuringProcessing {
+                slot,
+                stage,
+                retarget_slot,
+            }) => {
+                let data = serde_json::json!({
+                    "slot": slot,
+                    "stage": stage,
+                    "retarget_slot": retarget_slot,
+                });
+                (self.to_string(), Some(data))
             }
-            Error::Duplicate => {
-                (StatusCode::BAD_REQUEST, Json(JsonResponse::from_error(-32001, self.to_string())))
-                    .into_response()
+            Error::Consensus(ref err) => {
+                let data = match *err {
+                    ConsensusError::InvalidSlot(slot) | ConsensusError::LeaseNotHeld(slot) => {
+                        Some(serde_json::json!({ "slot": slot }))
+                    }
+                    ConsensusError::DeadlineExceeded { slot, deadline_ms } => {
+                        Some(serde_json::json!({ "slot": slot, "deadline_ms": deadline_ms }))
+                    }
+                    ConsensusError::InvalidSlotRange { slot, max_slot } => {
+                        Some(serde_json::json!({ "slot": slot, "max_slot": max_slot }))
+                    }
+                    ConsensusError::SlotRangePassed(max_slot) => {
+                        Some(serde_json::json!({ "max_slot": max_slot }))
+                    }
+                    ConsensusError::NoProposerInRange { slot, max_slot, ref our_next_slots } => {
+                        Some(serde_json::json!({
+                            "slot": slot,
+                            "max_slot": max_slot,
+                            "our_next_slots": our_next_slots,
+                        }))
+                    }
+                    _ => None,
+                };
+                (err.to_string(), data)
             }
-            Error::Internal => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(JsonResponse::from_error(-32002, self.to_string())),
-            )
-                .into_response(),
-            Error::NoSignature => {
-                (StatusCode::BAD_REQUEST, Json(JsonResponse::from_error(-32003, self.to_string())))
-                    .into_response()
+            Error::Validation(ref err) => {
+                let data = match *err {
+                    ValidationError::BaseFeeTooLow { required, projected } => {
+                        Some(serde_json::json!({ "required": required, "projected": projected }))
+                    }
+                    ValidationError::BlobBaseFeeTooLow(required) |
+                    ValidationError::PriorityFeeTooLow(required) => {
+                        Some(serde_json::json!({ "required": required }))
+                    }
+                    ValidationError::NonceTooLow(expected, got) |
+                    ValidationError::NonceTooHigh(expected, got) => {
+                        Some(serde_json::json!({ "expected": expected, "got": got }))
+                    }
+                    ValidationError::NonceAlreadyCommitted(nonce) => {
+                        Some(serde_json::json!({ "nonce": nonce }))
+                    }
+                    ValidationError::InsufficientBalance { required, available, reserved } => {
+                        Some(serde_json::json!({
+                            "required": required,
+                            "available": available,
+                            "reserved": reserved,
+                        }))
+                    }
+                    ValidationError::SlotTooLow(min_slot) => {
+                        Some(serde_json::json!({ "min_slot": min_slot }))
+                    }
+                    ValidationError::MaxCommitmentsReachedForSlot(slot, max) => {
+                        Some(serde_json::json!({ "slot": slot, "max": max }))
+                    }
+                    ValidationError::MaxCommittedGasReachedForSlot(slot, max) => {
+                        Some(serde_json::json!({ "slot": slot, "max": max }))
+                    }
+                    ValidationError::ByteBudgetExceeded { slot, requested, remaining } |
+                    ValidationError::BlobBudgetExceeded { slot, requested, remaining } => {
+                        Some(serde_json::json!({
+                            "slot": slot,
+                            "requested": requested,
+                            "remaining": remaining,
+                        }))
+                    }
+                    ValidationError::InsufficientQueueMemory { requested, available } => {
+                        Some(serde_json::json!({ "requested": requested, "available": available }))
+                    }
+                    ValidationError::TransactionTypeNotActive(tx_type) => {
+                        Some(serde_json::json!({ "tx_type": tx_type }))
+                    }
+                    ValidationError::InvalidContext(max_len) => {
+                        Some(serde_json::json!({ "max_len": max_len }))
+                    }
+                    ValidationError::InvalidBundleTransaction { index, ref source } => Some(
+                        serde_json::json!({ "index": index, "cause": validation_reason(source) }),
+                    ),
+                    ValidationError::InvalidExclusion(max) => {
+                        Some(serde_json::json!({ "max": max }))
+                    }
+                    ValidationError::ExclusionConflict { slot, tx_hash } => {
+                        Some(serde_json::json!({ "slot": slot, "tx_hash": tx_hash }))
+                    }
+                    _ => None,
+                };
+                (err.to_string(), data)
             }
-            Error::InvalidSignature(err) => {
-                (StatusCode::BAD_REQUEST, Json(JsonResponse::from_error(-32004, err.to_string())))
-                    .into_response()
+            Error::MalformedHeader => (self.to_string(), None),
+            Error::UnsupportedSignatureScheme(ref scheme) => {
+                (self.to_string(), Some(serde_json::json!({ "scheme": scheme })))
             }
-            Error::Signature(err) => {
-                (StatusCode::BAD_REQUEST, Json(JsonResponse::from_error(-32005, err.to_string())))
-                    .into_response()
+            Error::NotReady => (self.to_string(), None),
+            Error::NotSynced => (self.to_string(), None),
+            Error::PersistenceUnavailable => (self.to_string(), None),
+            Error::ServiceUnavailable => (self.to_string(), None),
+            Error::ShuttingDown => (self.to_string(), None),
+            Error::Timeout { elapsed_ms } => {
+                (self.to_string(), Some(serde_json::json!({ "elapsed_ms": elapsed_ms })))
             }
-            Error::Consensus(err) => {
-                (StatusCode::BAD_REQUEST, Json(JsonResponse::from_error(-32006, err.to_string())))
-                    .into_response()
+            Error::RateLimited { retry_after_ms } | Error::Overloaded { retry_after_ms } => {
+                (self.to_string(), Some(serde_json::json!({ "retry_after_ms": retry_after_ms })))
             }
-            Error::Validation(err) => {
-                (StatusCode::BAD_REQUEST, Json(JsonResponse::from_error(-32006, err.to_string())))
-                    .into_response()
+            Error::Import(ref err) => {
+                let data = match *err {
+                    ImportError::UnknownDigest(digest) => {
+                        Some(serde_json::json!({ "digest": digest }))
+                    }
+                    ImportError::SignerMismatch { expected, recovered } => {
+                        Some(serde_json::json!({ "expected": expected, "recovered": recovered }))
+                    }
+                    _ => None,
+                };
+                (err.to_string(), data)
             }
-            Error::MalformedHeader => {
-                (StatusCode::BAD_REQUEST, Json(JsonResponse::from_error(-32007, self.to_string())))
-                    .into_response()
+            Error::Cancel(ref err) => {
+                let data = match *err {
+                    CancelError::UnknownCommitment(digest) => {
+                        Some(serde_json::json!({ "digest": digest }))
+                    }
+                    CancelError::SignerMismatch(signer) => {
+                        Some(serde_json::json!({ "signer": signer }))
+                    }
+                    CancelError::NotEnabled => None,
+                };
+                (err.to_string(), data)
             }
-            Error::UnknownMethod => {
-                (StatusCode::BAD_REQUEST, Json(JsonResponse::from_error(-32601, self.to_string())))
-                    .into_response()
+            Error::ContractSignature(ref err) => (err.to_string(), None),
+            Error::Replay(ref err) => {
+                let data = match *err {
+                    ReplayError::TimestampExpired { timestamp_ms, window_ms } => Some(
+                        serde_json::json!({ "timestamp_ms": timestamp_ms, "window_ms": window_ms }),
+                    ),
+                    _ => None,
+                };
+                (err.to_string(), data)
             }
-            Error::InvalidJson(err) => (
-                StatusCode::BAD_REQUEST,
-                Json(JsonResponse::from_error(-32600, format!("Invalid request: {err}"))),
-            )
-                .into_response(),
+            Error::Unauthorized(ref err) => (err.to_string(), None),
+            Error::Quota(ref err) => {
+                let data = serde_json::json!({
+                    "limit": err.limit,
+                    "used": err.used,
+                    "requested": err.requested,
+                    "resets_at_slot": err.resets_at_slot,
+                });
+                (err.to_string(), Some(data))
+            }
+            Error::SignerUnavailable => (self.to_string(), None),
+            Error::UnknownMethod => (self.to_string(), None),
+            Error::InvalidJson(err) => (format!("Invalid request: {err}"), None),
+            Error::InvalidBody(err) => (format!("Invalid request: {err}"), None),
+            Error::InvalidPayload(err) => (format!("Invalid request: {err}"), None),
+            Error::Remote(err) => (err.message, err.data),
+        };
+
+        let mut data = retry.into_data(data);
+        if let (Some(reason), Value::Object(map)) = (reason, &mut data) {
+            map.insert("reason".to_string(), Value::String(reason.to_string()));
+        }
+
+        JsonError { code, message, data: Some(data) }
+    }
+}
+
+/// Hint on how to change a request so that it may succeed when resubmitted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RetryHint {
+    /// Resubmit the same request later.
+    SameRequestLater,
+    /// Modify the given field of the request and resubmit it.
+    ModifyAndResubmit {
+        /// The name of the request field or header to modify.
+        field: String,
+    },
+}
+
+/// Whether and how a client can retry a failed request. This is included
+/// in the data of every error response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// Whether resubmitting the identical request may succeed.
+    pub retryable: bool,
+    /// Optional hint on how to retry the request.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub retry_hint: Option<RetryHint>,
+}
+
+impl RetryPolicy {
+    /// The error is transient: the same request may succeed later.
+    fn later() -> Self {
+        Self { retryable: true, retry_hint: Some(RetryHint::SameRequestLater) }
+    }
+
+    /// The request can't succeed as is, but may succeed with the given field modified.
+    fn modify(field: &str) -> Self {
+        Self {
+            retryable: false,
+            retry_hint: Some(RetryHint::ModifyAndResubmit { field: field.to_string() }),
         }
     }
+
+    /// The request can't succeed, retrying is pointless.
+    fn permanent() -> Self {
+        Self { retryable: false, retry_hint: None }
+    }
+
+    /// Merges the policy into the given error data object.
+    fn into_data(self, data: Option<Value>) -> Value {
+        let mut data = match data {
+            Some(Value::Object(map)) => map,
+            _ => Map::new(),
+        };
+
+        data.insert("retryable".to_string(), Value::Bool(self.retryable));
+        if let Some(hint) = self.retry_hint {
+            data.insert("retry_hint".to_string(), serde_json::to_value(hint).unwrap());
+        }
+
+        Value::Object(data)
+    }
 }
 
-/// Error indicating the rejection of a commitment request. This should
-/// be returned to the user.
+/// Error indicating the rejection of a request, before it reaches the validation of the
+/// commitments. This should be returned to the user.
+///
+/// Every variant has its own reason sub-code, and its fields are included in the error data.
 #[derive(Debug, Error)]
 pub enum RejectionError {
-    /// State validation failed for this request.
-    #[error("Validation failed: {0}")]
-    ValidationFailed(String),
+    /// The method takes params, but fewer were sent.
+    #[error("Missing params: expected {expected}, got {got}")]
+    MissingParams {
+        /// The number of params the method takes.
+        expected: usize,
+        /// The number of params of the request.
+        got: usize,
+    },
+    /// A param couldn't be deserialized.
+    #[error("Invalid param {index}: {message}")]
+    InvalidParams {
+        /// The position of the param.
+        index: usize,
+        /// The deserialization error.
+        message: String,
+    },
+    /// The request signer is not in the whitelist.
+    #[error("Signer {0} is not whitelisted")]
+    SignerNotWhitelisted(Address),
+    /// The whitelist entry of the request signer expired, at the given UNIX timestamp.
+    #[error("Authorization of signer {0} expired at {1}")]
+    AuthorizationExpired(Address, u64),
+    /// The feature the method relies on is not enabled on this sidecar.
+    #[error("The {0} feature is not enabled")]
+    NotEnabled(&'static str),
+    /// The listener to rebind is not enabled.
+    #[error("The {0} listener is not enabled")]
+    ListenerNotEnabled(RebindableListener),
+    /// The listener couldn't be bound to its new address.
+    #[error("Failed to bind {addr}: {message}")]
+    BindFailed {
+        /// The address to bind the listener to.
+        addr: SocketAddr,
+        /// The bind error.
+        message: String,
+    },
+    /// The capture of the requests couldn't be enabled.
+    #[error("Failed to enable the capture: {0}")]
+    Capture(#[from] CaptureError),
+    /// The certificates couldn't be reloaded.
+    #[error("Failed to reload the certificates: {0}")]
+    Tls(#[from] TlsError),
+    /// The commitment couldn't be dropped, confirmed or restored.
+    #[error(transparent)]
+    Drop(#[from] DropError),
+    /// No maintenance window has the id.
+    #[error("No maintenance window with id {0}")]
+    UnknownMaintenanceWindow(u64),
+}
+
+impl RejectionError {
+    /// Returns the structured data of the error, listed in the table of the module docs.
+    fn data(&self) -> Option<Value> {
+        let data = match *self {
+            Self::MissingParams { expected, got } => {
+                serde_json::json!({ "expected": expected, "got": got })
+            }
+            Self::InvalidParams { index, .. } => serde_json::json!({ "index": index }),
+            Self::SignerNotWhitelisted(signer) => serde_json::json!({ "signer": signer }),
+            Self::AuthorizationExpired(signer, expired_at) => {
+                serde_json::json!({ "signer": signer, "expired_at": expired_at })
+            }
+            Self::NotEnabled(feature) => serde_json::json!({ "feature": feature }),
+            Self::ListenerNotEnabled(listener) => serde_json::json!({ "listener": listener }),
+            Self::BindFailed { addr, .. } => serde_json::json!({ "addr": addr }),
+            Self::Capture(CaptureError::SizeCapReached(max_bytes)) => {
+                serde_json::json!({ "max_bytes": max_bytes })
+            }
+            Self::Capture(_) => return None,
+            Self::Tls(TlsError::UnknownHostname(ref hostname)) => {
+                serde_json::json!({ "hostname": hostname })
+            }
+            Self::Tls(
+                TlsError::Io { ref path, .. } |
+                TlsError::NoCertificate(ref path) |
+                TlsError::NoPrivateKey(ref path),
+            ) => serde_json::json!({ "path": path }),
+            #[cfg(feature = "tls")]
+            Self::Tls(
+                TlsError::UnsupportedKey { ref path, .. } |
+                TlsError::KeyMismatch { key: ref path, .. },
+            ) => serde_json::json!({ "path": path }),
+            Self::Drop(
+                DropError::NotFound(digest) |
+                DropError::AlreadyDropped(digest) |
+                DropError::NotDropped(digest) |
+                DropError::AlreadyConfirmed(digest),
+            ) => serde_json::json!({ "digest": digest }),
+            Self::Drop(DropError::SlotPassed(digest, slot)) => {
+                serde_json::json!({ "digest": digest, "slot": slot })
+            }
+            Self::UnknownMaintenanceWindow(id) => serde_json::json!({ "id": id }),
+        };
+        Some(data)
+    }
+}
+
+/// Errors that can occur while cancelling a commitment with `bolt_cancelInclusion`.
+#[derive(Debug, Error)]
+pub enum CancelError {
+    /// Cancellations are not forwarded to the driver by this process.
+    #[error("Commitment cancellations are not enabled")]
+    NotEnabled,
+    /// No commitment was issued for the digest, or it was forgotten since.
+    #[error("No commitment with digest {0}")]
+    UnknownCommitment(B256),
+    /// The cancellation isn't signed by the signer of the commitment request.
+    #[error("Commitment wasn't requested by {0}")]
+    SignerMismatch(Address),
+}
+
+/// Chain and timing parameters of the sidecar, returned by `bolt_getChainInfo`.
+///
+/// Clients can use these values to compute target slots from wall-clock deadlines
+/// without hard-coding the parameters of a specific network.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainInfo {
+    /// The chain ID of the execution layer.
+    pub chain_id: u64,
+    /// The name of the chain the sidecar is running on.
+    pub chain_name: String,
+    /// The beacon chain genesis time, as a UNIX timestamp in seconds.
+    pub genesis_time: u64,
+    /// The slot duration in seconds.
+    pub seconds_per_slot: u64,
+    /// The number of slots in an epoch.
+    pub slots_per_epoch: u64,
+    /// The fork version used for signing builder messages, hex-encoded.
+    pub fork_version: String,
+    /// The latest head slot observed by the sidecar.
+    pub head_slot: u64,
+}
+
+impl ChainInfo {
+    /// Returns the slot at the given UNIX timestamp (in seconds).
+    /// Returns `None` if the timestamp is before genesis.
+    pub fn slot_at(&self, timestamp: u64) -> Option<u64> {
+        timestamp.checked_sub(self.genesis_time)?.checked_div(self.seconds_per_slot)
+    }
+}
+
+/// Version of the sidecar and the methods exposed by the listener, returned by `bolt_getVersion`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionInfo {
+    /// The version string of the sidecar.
+    pub version: String,
+    /// The JSON-RPC methods exposed by the listener that served the call.
+    pub methods: Vec<String>,
+    /// The optional features enabled on the sidecar, e.g. response signing.
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+}
+
+/// The limits of the sidecar and the next slot requests can target, returned by
+/// `bolt_metadata`.
+///
+/// The slot fields are `null` until the driver has seen a chain head, and the budget of the
+/// next slot is `null` if none of our validators proposes before the end of the lookahead.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SidecarMetadata {
+    /// The chain ID of the execution layer.
+    pub chain_id: u64,
+    /// The maximum number of commitments per slot.
+    pub max_commitments_per_slot: usize,
+    /// The maximum gas committed per slot.
+    pub max_committed_gas_per_slot: u64,
+    /// The latest head slot observed by the driver.
+    pub head_slot: Option<Slot>,
+    /// The last slot requests can target, at the end of the epoch of the known proposer duties.
+    pub lookahead_end_slot: Option<Slot>,
+    /// The next slot of our validators that requests can target.
+    pub next_commitment_slot: Option<Slot>,
+    /// The gas left to commit in the next commitment slot.
+    pub remaining_gas: Option<u64>,
+    /// The minimum priority fee per gas (in wei) the transactions must tip in the next
+    /// commitment slot, which rises with the projected base fee if the floor is dynamic.
+    pub min_priority_fee: u128,
+    /// The base fee (in wei) projected under EIP-1559 for the next commitment slot, which
+    /// the maximum fee per gas of the transactions must cover once the margin is applied.
+    pub projected_basefee: Option<u128>,
+}
+
+impl SidecarMetadata {
+    /// Returns the metadata of a sidecar with the given limits, in the state of the snapshot.
+    pub fn new(chain_id: u64, limits: &Limits, snapshot: &StateSnapshot) -> Self {
+        let max_committed_gas_per_slot = limits.max_committed_gas_per_slot.get();
+        let head_slot = (snapshot.head_slot > 0).then_some(snapshot.head_slot);
+        let next_commitment_slot =
+            head_slot.and_then(|head| snapshot.lookahead.iter().copied().find(|&s| s > head));
+        let remaining_gas = next_commitment_slot.map(|slot| {
+            let budget = snapshot.slots.iter().find(|budget| budget.slot == slot);
+            budget.map_or(max_committed_gas_per_slot, |budget| {
+                budget.max_committed_gas.saturating_sub(budget.committed_gas)
+            })
+        });
+
+        Self {
+            chain_id,
+            max_commitments_per_slot: limits.max_commitments_per_slot.get(),
+            max_committed_gas_per_slot,
+            head_slot,
+            lookahead_end_slot: head_slot.map(|_| snapshot.lookahead_end_slot),
+            next_commitment_slot,
+            remaining_gas,
+            min_priority_fee: snapshot.priority_fee_floor.unwrap_or(limits.min_priority_fee),
+            projected_basefee: snapshot.projected_basefee,
+        }
+    }
+}
+
+/// The readiness of the sidecar, returned by `GET /readyz`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReadinessReport {
+    /// Whether the driver is connected to all its upstream services.
+    pub ready: bool,
+    /// The connectivity to each upstream service, if the driver publishes it.
+    pub dependencies: Option<DependencyHealth>,
+    /// The health of each constraint submission target, in submission order.
+    #[serde(default)]
+    pub relays: Vec<RelayPerformance>,
+}
+
+/// Runtime status of the sidecar, returned by `bolt_getStatus` for debugging.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SidecarStatus {
+    /// Whether the sidecar completed its warmup and accepts commitment requests.
+    pub ready: bool,
+    /// The tasks that run at fixed offsets within each slot.
+    pub schedule: Vec<ScheduleEntry>,
+    /// The addresses the listeners of the sidecar are bound to.
+    pub listeners: ListenerAddrs,
+    /// The previous addresses of the rebound listeners, while their open connections drain.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub draining: Vec<SocketAddr>,
+    /// The stages of the pipeline that failed the latest synthetic probe, if any.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failing_probe_stages: Vec<ProbeStage>,
+    /// The latest snapshot of the driver state, including its generation.
+    #[serde(flatten)]
+    pub state: StateSnapshot,
+}
+
+/// The addresses the listeners of the sidecar are bound to, reported in the status and
+/// written to the `--ports-file`. Listeners bound to all interfaces are reported with the
+/// loopback address, where local clients can reach them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListenerAddrs {
+    /// The public commitments API.
+    pub commitments: Option<SocketAddr>,
+    /// The internal commitments API, if enabled.
+    pub internal: Option<SocketAddr>,
+    /// The admin API, if enabled.
+    pub admin: Option<SocketAddr>,
+    /// The Prometheus metrics, if enabled.
+    pub metrics: Option<SocketAddr>,
+}
+
+impl ListenerAddrs {
+    /// Returns the address at which local clients can reach a listener bound to `addr`.
+    pub fn reachable(addr: SocketAddr) -> SocketAddr {
+        match addr {
+            SocketAddr::V4(v4) if v4.ip().is_unspecified() => {
+                (Ipv4Addr::LOCALHOST, v4.port()).into()
+            }
+            SocketAddr::V6(v6) if v6.ip().is_unspecified() => {
+                (Ipv6Addr::LOCALHOST, v6.port()).into()
+            }
+            addr => addr,
+        }
+    }
+
+    /// Returns the machine-readable `NAME=address` lines announcing the bound addresses.
+    pub fn env_lines(&self) -> Vec<String> {
+        [
+            ("COMMITMENTS_ADDR", self.commitments),
+            ("INTERNAL_ADDR", self.internal),
+            ("ADMIN_ADDR", self.admin),
+            ("METRICS_ADDR", self.metrics),
+        ]
+        .into_iter()
+        .filter_map(|(name, addr)| Some(format!("{name}={}", addr?)))
+        .collect()
+    }
+
+    /// Writes the addresses as JSON to `path`. The file is replaced atomically, so that
+    /// readers never see a partially written file.
+    pub fn write_ports_file(&self, path: &Path) -> io::Result<()> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+
+        std::fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(tmp, path)
+    }
+}
+
+/// Parameters of `admin_importSignedCommitment`: the digest of a commitment
+/// request that is awaiting its signature, and the signature made by the commitment key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportSignedCommitment {
+    /// The digest of the commitment request.
+    pub digest: B256,
+    /// The signature over the digest, hex-encoded.
+    #[serde(deserialize_with = "deserialize_sig")]
+    pub signature: Signature,
+}
+
+/// Parameters of `admin_reloadCertificates`: the hostname whose TLS certificate to reload
+/// from disk, or none to reload all of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CertificateReload {
+    /// The hostname of the certificate to reload.
+    #[serde(default)]
+    pub hostname: Option<String>,
+}
+
+/// The listeners that can be rebound to another address with `admin_rebindListener`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RebindableListener {
+    /// The public commitments API.
+    Commitments,
+    /// The internal commitments API.
+    Internal,
+}
+
+impl std::fmt::Display for RebindableListener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Commitments => write!(f, "commitments"),
+            Self::Internal => write!(f, "internal"),
+        }
+    }
+}
+
+/// Parameters of `admin_rebindListener`: the listener to rebind, and its new address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListenerRebind {
+    /// The listener to rebind.
+    pub listener: RebindableListener,
+    /// The address to bind the listener to.
+    pub addr: SocketAddr,
+}
+
+/// Parameters of `admin_dropCommitment`, `admin_confirmDrop`, `admin_restoreCommitment` and
+/// `bolt_getCommitment`: the digest of the commitment request to drop, confirm the drop of,
+/// restore or retrieve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitmentDigest {
+    /// The digest of the commitment request.
+    pub digest: B256,
+}
+
+/// Parameters of `bolt_getCommitmentStatus`: the commitment to query, by the digest of its
+/// request, by its commitment id or by one of its transactions and its target slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CommitmentQuery {
+    /// The digest of the commitment request.
+    Digest {
+        /// The digest of the commitment request.
+        digest: B256,
+    },
+    /// The id of the commitment.
+    CommitmentId {
+        /// The id of the commitment, see [`InclusionRequest::commitment_id`].
+        commitment_id: B256,
+    },
+    /// A committed transaction and the target slot of its commitment.
+    Transaction {
+        /// The hash of the committed transaction.
+        tx_hash: B256,
+        /// The target slot of the commitment.
+        slot: u64,
+    },
+}
+
+/// Parameters of `bolt_cancelInclusion`: the commitment to cancel, by the digest of its
+/// request or by its commitment id.
+///
+/// The cancellation is signed by the signer of the request, over its own
+/// [`InclusionCancellation::signed_digest`], so that the signature of the request can't be replayed
+/// to cancel it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum InclusionCancellation {
+    /// The digest of the commitment request.
+    Digest {
+        /// The digest of the commitment request.
+        digest: B256,
+    },
+    /// The id of the commitment.
+    CommitmentId {
+        /// The id of the commitment, see [`InclusionRequest::commitment_id`].
+        commitment_id: B256,
+    },
+}
+
+impl InclusionCancellation {
+    /// The prefix of the signed digest of the cancellations.
+    pub const DOMAIN: &'static [u8] = b"bolt_cancelInclusion";
+
+    /// Returns the digest signed by the request signer: the keccak256 hash of the
+    /// [`DOMAIN`](Self::DOMAIN) followed by the digest of the request or the commitment id.
+    pub fn signed_digest(&self) -> B256 {
+        let (Self::Digest { digest: key } | Self::CommitmentId { commitment_id: key }) = self;
+        keccak256([Self::DOMAIN, key.as_slice()].concat())
+    }
+}
+
+/// The outcome of `bolt_cancelInclusion`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum CancelOutcome {
+    /// The constraints of the commitment were removed before they were submitted.
+    Cancelled,
+    /// The commitment deadline of its slot has passed, so its constraints were submitted.
+    TooLate,
+}
+
+/// Parameters of `admin_cancelMaintenance`: the identifier of the window to cancel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceId {
+    /// The identifier of the maintenance window.
+    pub id: u64,
+}
+
+/// Parameters of `bolt_findCommitmentsByContext`: the context set by the client on the
+/// requests to look up.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContextQuery {
+    /// The context of the requests.
+    pub context: String,
+}
+
+/// Time range parameters of `bolt_getEarnings`, as UNIX timestamps in seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EarningsRange {
+    /// The start of the range (inclusive).
+    pub from: u64,
+    /// The end of the range (exclusive).
+    pub to: u64,
 }
 
 /// Implements the commitments-API: <https://chainbound.github.io/bolt-docs/api/rpc>
 #[async_trait::async_trait]
 pub trait CommitmentsApi {
     /// Implements: <https://chainbound.github.io/bolt-docs/api/rpc#bolt_requestinclusion>
+    ///
+    /// The request id correlates the logs of the request across the API and the driver.
     async fn request_inclusion(
         &self,
         inclusion_request: InclusionRequest,
+        request_id: RequestId,
     ) -> Result<InclusionCommitment, Error>;
+
+    /// Requests a commitment to exclude senders and transactions from the block of a slot.
+    async fn request_exclusion(
+        &self,
+        exclusion_request: ExclusionRequest,
+        request_id: RequestId,
+    ) -> Result<ExclusionCommitment, Error>;
+}
+
+/// Sample errors covering the [`ERROR_CATALOGUE`], for tests of clients decoding them.
+/// Errors that can't be constructed in tests and don't carry structured data are missing.
+#[cfg(test)]
+pub(crate) fn error_samples() -> Vec<Error> {
+    use alloy::primitives::U256;
+
+    use crate::state::consensus::ProcessingStage;
+
+    vec![
+        Error::Rejected(RejectionError::MissingParams { expected: 1, got: 0 }),
+        Error::Rejected(RejectionError::InvalidParams { index: 0, message: "invalid".to_string() }),
+        Error::Rejected(RejectionError::NotEnabled("tls")),
+        Error::Rejected(RejectionError::ListenerNotEnabled(RebindableListener::Internal)),
+        Error::Rejected(RejectionError::BindFailed {
+            addr: SocketAddr::from((Ipv4Addr::LOCALHOST, 8017)),
+            message: "in use".to_string(),
+        }),
+        Error::Rejected(RejectionError::Capture(CaptureError::NoDirectory)),
+        Error::Rejected(RejectionError::Capture(CaptureError::SizeCapReached(1_024))),
+        Error::Rejected(RejectionError::Capture(CaptureError::Io(io::Error::other("full")))),
+        Error::Rejected(RejectionError::Tls(TlsError::UnknownHostname("a.test".to_string()))),
+        Error::Rejected(RejectionError::Drop(DropError::NotFound(B256::ZERO))),
+        Error::Rejected(RejectionError::Drop(DropError::AlreadyDropped(B256::ZERO))),
+        Error::Rejected(RejectionError::Drop(DropError::NotDropped(B256::ZERO))),
+        Error::Rejected(RejectionError::Drop(DropError::AlreadyConfirmed(B256::ZERO))),
+        Error::Rejected(RejectionError::Drop(DropError::SlotPassed(B256::ZERO, 7))),
+        Error::Rejected(RejectionError::UnknownMaintenanceWindow(3)),
+        Error::Rejected(RejectionError::SignerNotWhitelisted(Address::ZERO)),
+        Error::Rejected(RejectionError::AuthorizationExpired(Address::ZERO, 1_700_000_000)),
+        Error::Duplicate,
+        Error::Internal,
+        Error::NoSignature,
+        Error::InvalidSignature(crate::primitives::SignatureError),
+        Error::Consensus(ConsensusError::Recorded("unavailable".to_string())),
+        Error::Consensus(ConsensusError::InvalidSlot(1)),
+        Error::Consensus(ConsensusError::DeadlineExceeded { slot: 2, deadline_ms: 8_000 }),
+        Error::Consensus(ConsensusError::DeadlinePassedDuringProcessing {
+            slot: 2,
+            stage: ProcessingStage::CommitmentSigning,
+            retarget_slot: None,
+        }),
+        Error::Consensus(ConsensusError::ValidatorNotFound),
+        Error::Consensus(ConsensusError::LeaseNotHeld(3)),
+        Error::Consensus(ConsensusError::NotOurSlot {
+            slot: 4,
+            our_next_slots: vec![6],
+            registry_hint_url: None,
+        }),
+        Error::Consensus(ConsensusError::ProposerUnavailable { slot: 5, our_next_slots: vec![6] }),
+        Error::Consensus(ConsensusError::InvalidSlotRange { slot: 6, max_slot: 5 }),
+        Error::Consensus(ConsensusError::SlotRangePassed(5)),
+        Error::Consensus(ConsensusError::NoProposerInRange {
+            slot: 4,
+            max_slot: 5,
+            our_next_slots: vec![6],
+        }),
+        Error::Validation(ValidationError::BaseFeeTooLow { required: 10, projected: 8 }),
+        Error::Validation(ValidationError::BlobBaseFeeTooLow(1)),
+        Error::Validation(ValidationError::MaxBaseFeeCalcOverflow),
+        Error::Validation(ValidationError::NonceTooLow(2, 1)),
+        Error::Validation(ValidationError::NonceTooHigh(1, 2)),
+        Error::Validation(ValidationError::NonceAlreadyCommitted(1)),
+        Error::Validation(ValidationError::AccountHasCode),
+        Error::Validation(ValidationError::GasLimitTooHigh),
+        Error::Validation(ValidationError::TransactionSizeTooHigh),
+        Error::Validation(ValidationError::MaxPriorityFeePerGasTooHigh),
+        Error::Validation(ValidationError::PriorityFeeTooLow(1_000_000_000)),
+        Error::Validation(ValidationError::InsufficientBalance {
+            required: U256::from(2_000),
+            available: U256::from(1_000),
+            reserved: U256::from(3_000),
+        }),
+        Error::Validation(ValidationError::Eip4844Limit),
+        Error::Validation(ValidationError::SlotTooLow(5)),
+        Error::Validation(ValidationError::MaxCommitmentsReachedForSlot(5, 128)),
+        Error::Validation(ValidationError::MaxCommittedGasReachedForSlot(5, 30_000_000)),
+        Error::Validation(ValidationError::ByteBudgetExceeded {
+            slot: 5,
+            requested: 60_000,
+            remaining: 40_000,
+        }),
+        Error::Validation(ValidationError::BlobBudgetExceeded {
+            slot: 5,
+            requested: 2,
+            remaining: 1,
+        }),
+        Error::Validation(ValidationError::InsufficientQueueMemory {
+            requested: 4_096,
+            available: 1_024,
+        }),
+        Error::Validation(ValidationError::Signature(crate::primitives::SignatureError)),
+        Error::Validation(ValidationError::RecoverSigner),
+        Error::Validation(ValidationError::ChainIdMismatch),
+        Error::Validation(ValidationError::TransactionTypeNotActive(4)),
+        Error::Validation(ValidationError::InvalidContext(256)),
+        Error::Validation(ValidationError::InvalidBundleTransaction {
+            index: 2,
+            source: Box::new(ValidationError::NonceTooHigh(1, 2)),
+        }),
+        Error::Validation(ValidationError::InvalidExclusion(128)),
+        Error::Validation(ValidationError::ExclusionConflict {
+            slot: 5,
+            tx_hash: B256::repeat_byte(1),
+        }),
+        Error::Validation(ValidationError::Internal("failed".to_string())),
+        Error::MalformedHeader,
+        Error::NotReady,
+        Error::NotSynced,
+        Error::PersistenceUnavailable,
+        Error::Timeout { elapsed_ms: 12_000 },
+        Error::ServiceUnavailable,
+        Error::RateLimited { retry_after_ms: 250 },
+        Error::Overloaded { retry_after_ms: 250 },
+        Error::ShuttingDown,
+        Error::UnsupportedSignatureScheme("eip1271".to_string()),
+        Error::Import(ImportError::NotEnabled),
+        Error::Import(ImportError::UnknownDigest(B256::ZERO)),
+        Error::Import(ImportError::SignerMismatch {
+            expected: Address::ZERO,
+            recovered: Address::repeat_byte(1),
+        }),
+        Error::Cancel(CancelError::NotEnabled),
+        Error::Cancel(CancelError::UnknownCommitment(B256::ZERO)),
+        Error::Cancel(CancelError::SignerMismatch(Address::ZERO)),
+        Error::ContractSignature(Erc1271Error::Reverted("execution reverted".to_string())),
+        Error::ContractSignature(Erc1271Error::InvalidMagicValue(Default::default())),
+        Error::ContractSignature(Erc1271Error::Rpc("connection refused".to_string())),
+        Error::Replay(ReplayError::MissingTimestamp),
+        Error::Replay(ReplayError::TimestampExpired { timestamp_ms: 1_000, window_ms: 30_000 }),
+        Error::Replay(ReplayError::Replayed),
+        Error::Unauthorized(JwtAuthError::MissingToken),
+        Error::Unauthorized(JwtAuthError::ExpiredToken),
+        Error::Unauthorized(JwtAuthError::InvalidToken("InvalidSignature".to_string())),
+        Error::Quota(QuotaError {
+            quota: Quota::GasPerSlot,
+            limit: 100_000,
+            used: 84_000,
+            requested: 21_000,
+            resets_at_slot: 6,
+        }),
+        Error::Quota(QuotaError {
+            quota: Quota::TxsPerSlot,
+            limit: 4,
+            used: 4,
+            requested: 1,
+            resets_at_slot: 6,
+        }),
+        Error::Quota(QuotaError {
+            quota: Quota::CommitmentsPerEpoch,
+            limit: 16,
+            used: 16,
+            requested: 1,
+            resets_at_slot: 32,
+        }),
+        Error::SignerUnavailable,
+        Error::UnknownMethod,
+        Error::InvalidPayload(PayloadError::IdTooLong),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::{to_bytes, Body, Bytes},
+        extract::{FromRequest, Path as AxumPath},
+        http::Request,
+        routing::post,
+        Router,
+    };
+    use reqwest::Url;
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::{api::commitments::jsonrpc::JsonPayload, state::consensus::ProcessingStage};
+
+    #[tokio::test]
+    async fn test_not_our_slot_response() {
+        let err = Error::Consensus(ConsensusError::NotOurSlot {
+            slot: 10,
+            our_next_slots: vec![12, 15],
+            registry_hint_url: Some(Url::parse("https://registry.example.com/").unwrap()),
+        });
+
+        let response = err.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let response: JsonResponse = serde_json::from_slice(&body).unwrap();
+        let error = response.error.unwrap();
+
+        assert_eq!(error.code, -32008);
+        assert_eq!(
+            error.data.unwrap(),
+            serde_json::json!({
+                "slot": 10,
+                "our_next_slots": [12, 15],
+                "registry_hint_url": "https://registry.example.com/",
+                "retryable": false,
+                "retry_hint": { "action": "modify_and_resubmit", "field": "slot" },
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_error_snapshots() {
+        let later = serde_json::json!({
+            "retryable": true,
+            "retry_hint": { "action": "same_request_later" },
+        });
+        let modify = |field: &str| {
+            serde_json::json!({
+                "retryable": false,
+                "retry_hint": { "action": "modify_and_resubmit", "field": field },
+            })
+        };
+        let permanent = serde_json::json!({ "retryable": false });
+        let with_reason = |mut data: Value, reason: &str| {
+            data["reason"] = Value::String(reason.to_string());
+            data
+        };
+
+        let cases = [
+            (Error::NotReady, StatusCode::SERVICE_UNAVAILABLE, -32009, later.clone()),
+            (Error::PersistenceUnavailable, StatusCode::SERVICE_UNAVAILABLE, -32012, later.clone()),
+            (Error::ServiceUnavailable, StatusCode::SERVICE_UNAVAILABLE, -32014, later.clone()),
+            (Error::ShuttingDown, StatusCode::SERVICE_UNAVAILABLE, -32018, later.clone()),
+            (Error::SignerUnavailable, StatusCode::SERVICE_UNAVAILABLE, -32024, later.clone()),
+            (Error::Internal, StatusCode::OK, -32002, later.clone()),
+            (Error::Duplicate, StatusCode::OK, -32001, permanent.clone()),
+            (Error::NoSignature, StatusCode::OK, -32003, modify(SIGNATURE_HEADER)),
+            (Error::MalformedHeader, StatusCode::OK, -32007, modify(SIGNATURE_HEADER)),
+            (
+                Error::UnsupportedSignatureScheme("eip1271".to_string()),
+                StatusCode::OK,
+                -32019,
+                serde_json::json!({
+                    "scheme": "eip1271",
+                    "retryable": false,
+                    "retry_hint": { "action": "modify_and_resubmit", "field": SIGNATURE_HEADER },
+                }),
+            ),
+            (Error::UnknownMethod, StatusCode::OK, -32601, modify("method")),
+            (
+                Error::Replay(ReplayError::TimestampExpired {
+                    timestamp_ms: 1_000,
+                    window_ms: 30_000,
+                }),
+                StatusCode::OK,
+                -32021,
+                serde_json::json!({
+                    "timestamp_ms": 1_000,
+                    "window_ms": 30_000,
+                    "reason": "timestamp_expired",
+                    "retryable": false,
+                    "retry_hint": { "action": "modify_and_resubmit", "field": SIGNATURE_HEADER },
+                }),
+            ),
+            (
+                Error::Replay(ReplayError::Replayed),
+                StatusCode::OK,
+                -32021,
+                with_reason(permanent.clone(), "replayed"),
+            ),
+            (
+                Error::Unauthorized(JwtAuthError::ExpiredToken),
+                StatusCode::UNAUTHORIZED,
+                -32022,
+                with_reason(modify("authorization"), "expired_token"),
+            ),
+            (
+                Error::Timeout { elapsed_ms: 12_000 },
+                StatusCode::OK,
+                -32013,
+                serde_json::json!({
+                    "elapsed_ms": 12_000,
+                    "retryable": true,
+                    "retry_hint": { "action": "same_request_later" },
+                }),
+            ),
+            (
+                Error::Overloaded { retry_after_ms: 250 },
+                StatusCode::OK,
+                -32017,
+                serde_json::json!({
+                    "retry_after_ms": 250,
+                    "retryable": true,
+                    "retry_hint": { "action": "same_request_later" },
+                }),
+            ),
+            (
+                Error::RateLimited { retry_after_ms: 250 },
+                StatusCode::TOO_MANY_REQUESTS,
+                -32016,
+                serde_json::json!({
+                    "retry_after_ms": 250,
+                    "retryable": true,
+                    "retry_hint": { "action": "same_request_later" },
+                }),
+            ),
+            (
+                Error::Rejected(RejectionError::SignerNotWhitelisted(Address::ZERO)),
+                StatusCode::OK,
+                -32000,
+                serde_json::json!({
+                    "signer": Address::ZERO,
+                    "reason": "signer_not_whitelisted",
+                    "retryable": false,
+                }),
+            ),
+            (
+                Error::Consensus(ConsensusError::DeadlineExceeded { slot: 2, deadline_ms: 8_000 }),
+                StatusCode::OK,
+                -32006,
+                serde_json::json!({
+                    "slot": 2,
+                    "deadline_ms": 8_000,
+                    "reason": "deadline_exceeded",
+                    "retryable": false,
+                    "retry_hint": { "action": "modify_and_resubmit", "field": "slot" },
+                }),
+            ),
+            (
+                Error::Consensus(ConsensusError::DeadlinePassedDuringProcessing {
+                    slot: 2,
+                    stage: ProcessingStage::ExecutionValidation,
+                    retarget_slot: Some(5),
+                }),
+                StatusCode::OK,
+                -32006,
+                serde_json::json!({
+                    "slot": 2,
+                    "stage": "execution_validation",
+                    "retarget_slot": 5,
+                    "reason": "deadline_passed_during_processing",
+                    "retryable": false,
+                    "retry_hint": { "action": "modify_and_resubmit", "field": "slot" },
+                }),
+            ),
+            (
+                Error::Validation(ValidationError::ChainIdMismatch),
+                StatusCode::OK,
+                -32006,
+                with_reason(permanent.clone(), "chain_id_mismatch"),
+            ),
+            (
+                Error::Validation(ValidationError::NonceTooLow(2, 1)),
+                StatusCode::OK,
+                -32006,
+                serde_json::json!({
+                    "expected": 2,
+                    "got": 1,
+                    "reason": "nonce_too_low",
+                    "retryable": false,
+                }),
+            ),
+            (
+                Error::Validation(ValidationError::NonceTooHigh(1, 2)),
+                StatusCode::OK,
+                -32006,
+                serde_json::json!({
+                    "expected": 1,
+                    "got": 2,
+                    "reason": "nonce_too_high",
+                    "retryable": true,
+                    "retry_hint": { "action": "same_request_later" },
+                }),
+            ),
+            (
+                Error::Validation(ValidationError::BaseFeeTooLow { required: 10, projected: 8 }),
+                StatusCode::OK,
+                -32006,
+                serde_json::json!({
+                    "required": 10,
+                    "projected": 8,
+                    "reason": "base_fee_too_low",
+                    "retryable": false,
+                    "retry_hint": { "action": "modify_and_resubmit", "field": "max_fee_per_gas" },
+                }),
+            ),
+            (
+                Error::Validation(ValidationError::MaxCommitmentsReachedForSlot(10, 128)),
+                StatusCode::OK,
+                -32006,
+                serde_json::json!({
+                    "slot": 10,
+                    "max": 128,
+                    "reason": "max_commitments_reached",
+                    "retryable": false,
+                    "retry_hint": { "action": "modify_and_resubmit", "field": "slot" },
+                }),
+            ),
+            (
+                Error::Import(ImportError::UnknownDigest(B256::ZERO)),
+                StatusCode::OK,
+                -32010,
+                serde_json::json!({
+                    "digest": B256::ZERO,
+                    "reason": "unknown_digest",
+                    "retryable": false,
+                }),
+            ),
+            (
+                Error::Cancel(CancelError::UnknownCommitment(B256::ZERO)),
+                StatusCode::OK,
+                -32015,
+                serde_json::json!({
+                    "digest": B256::ZERO,
+                    "reason": "unknown_commitment",
+                    "retryable": false,
+                }),
+            ),
+        ];
+
+        for (err, status, code, data) in cases {
+            let message = err.to_string();
+            let response = err.into_response();
+            assert_eq!(response.status(), status, "{message}");
+
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let error = serde_json::from_slice::<JsonResponse>(&body).unwrap().error.unwrap();
+            assert_eq!(error.code, code, "{message}");
+            assert_eq!(error.data.unwrap(), data, "{message}");
+        }
+    }
+
+    #[test]
+    fn test_rejection_data() {
+        let params = serde_json::json!({
+            "retryable": false,
+            "retry_hint": { "action": "modify_and_resubmit", "field": "params" },
+        });
+        let data = |reason: &str, mut data: Value| {
+            data["reason"] = Value::String(reason.to_string());
+            data["retryable"] = Value::Bool(false);
+            data
+        };
+        let (addr, digest) = (SocketAddr::from((Ipv4Addr::LOCALHOST, 8017)), B256::ZERO);
+
+        let cases = [
+            (
+                RejectionError::MissingParams { expected: 1, got: 0 },
+                -32602,
+                serde_json::json!({ "expected": 1, "got": 0, "reason": "missing_params" }),
+            ),
+            (
+                RejectionError::InvalidParams { index: 0, message: "invalid".to_string() },
+                -32602,
+                serde_json::json!({ "index": 0, "reason": "invalid_params" }),
+            ),
+            (
+                RejectionError::SignerNotWhitelisted(Address::ZERO),
+                -32000,
+                data("signer_not_whitelisted", serde_json::json!({ "signer": Address::ZERO })),
+            ),
+            (
+                RejectionError::AuthorizationExpired(Address::ZERO, 10),
+                -32000,
+                data(
+                    "authorization_expired",
+                    serde_json::json!({ "signer": Address::ZERO, "expired_at": 10 }),
+                ),
+            ),
+            (
+                RejectionError::NotEnabled("tls"),
+                -32000,
+                data("not_enabled", serde_json::json!({ "feature": "tls" })),
+            ),
+            (
+                RejectionError::ListenerNotEnabled(RebindableListener::Internal),
+                -32000,
+                data("listener_not_enabled", serde_json::json!({ "listener": "internal" })),
+            ),
+            (
+                RejectionError::BindFailed { addr, message: "in use".to_string() },
+                -32000,
+                data("bind_failed", serde_json::json!({ "addr": "127.0.0.1:8017" })),
+            ),
+            (
+                RejectionError::Capture(CaptureError::NoDirectory),
+                -32000,
+                data("no_capture_directory", serde_json::json!({})),
+            ),
+            (
+                RejectionError::Capture(CaptureError::SizeCapReached(1_024)),
+                -32000,
+                data("capture_size_cap_reached", serde_json::json!({ "max_bytes": 1_024 })),
+            ),
+            (
+                RejectionError::Capture(CaptureError::Io(io::Error::other("full"))),
+                -32000,
+                data("capture_failed", serde_json::json!({})),
+            ),
+            (
+                RejectionError::Tls(TlsError::UnknownHostname("a.test".to_string())),
+                -32000,
+                data("certificate_reload_failed", serde_json::json!({ "hostname": "a.test" })),
+            ),
+            (
+                RejectionError::Tls(TlsError::NoPrivateKey("key.pem".into())),
+                -32000,
+                data("certificate_reload_failed", serde_json::json!({ "path": "key.pem" })),
+            ),
+            (
+                RejectionError::Drop(DropError::NotFound(digest)),
+                -32000,
+                data("commitment_not_found", serde_json::json!({ "digest": digest })),
+            ),
+            (
+                RejectionError::Drop(DropError::AlreadyDropped(digest)),
+                -32000,
+                data("already_dropped", serde_json::json!({ "digest": digest })),
+            ),
+            (
+                RejectionError::Drop(DropError::NotDropped(digest)),
+                -32000,
+                data("not_dropped", serde_json::json!({ "digest": digest })),
+            ),
+            (
+                RejectionError::Drop(DropError::AlreadyConfirmed(digest)),
+                -32000,
+                data("drop_already_confirmed", serde_json::json!({ "digest": digest })),
+            ),
+            (
+                RejectionError::Drop(DropError::SlotPassed(digest, 7)),
+                -32000,
+                data("slot_passed", serde_json::json!({ "digest": digest, "slot": 7 })),
+            ),
+            (
+                RejectionError::UnknownMaintenanceWindow(3),
+                -32000,
+                data("unknown_maintenance_window", serde_json::json!({ "id": 3 })),
+            ),
+        ];
+
+        for (err, code, mut data) in cases {
+            if code == -32602 {
+                data.as_object_mut().unwrap().extend(params.as_object().unwrap().clone());
+            }
+
+            let err = Error::Rejected(err);
+            let message = err.to_string();
+            let error = err.into_json_error();
+            assert_eq!(error.code, code, "{message}");
+            assert_eq!(error.data.unwrap(), data, "{message}");
+        }
+    }
+
+    #[test]
+    fn test_error_catalogue() {
+        for (i, code) in ERROR_CATALOGUE.iter().enumerate() {
+            assert!(!ERROR_CATALOGUE[..i].contains(code), "duplicate entry {code:?}");
+        }
+
+        for err in error_samples() {
+            assert!(ERROR_CATALOGUE.contains(&err.error_code()), "{err} is not catalogued");
+        }
+    }
+
+    #[test]
+    fn test_remote_errors() {
+        // An error relayed by a driver in another process is served as the original one
+        for (err, original) in error_samples().into_iter().zip(error_samples()) {
+            let remote = Error::Remote(original.into_json_error());
+            assert_eq!(remote.error_code(), err.error_code(), "{err}");
+            assert_eq!(remote.retry_policy(), err.retry_policy(), "{err}");
+            assert_eq!(remote.http_status(), err.http_status(), "{err}");
+
+            let message = err.to_string();
+            let (remote, err) = (remote.into_json_error(), err.into_json_error());
+            assert_eq!(remote.message, err.message, "{message}");
+            assert_eq!(remote.data, err.data, "{message}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_http_status_policy() {
+        // Serve every sample error through axum, at the path of its index
+        async fn sample(AxumPath(index): AxumPath<usize>) -> Error {
+            error_samples().swap_remove(index)
+        }
+
+        let router = Router::new().route("/:index", post(sample));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        let client = reqwest::Client::new();
+        for (index, err) in error_samples().into_iter().enumerate() {
+            let ErrorCode { code, reason } = err.error_code();
+            let response = client.post(format!("http://{addr}/{index}")).send().await.unwrap();
+
+            // Only the errors that aren't about the request itself use a non-200 status
+            let expected = if matches!(
+                err,
+                Error::NotReady |
+                    Error::NotSynced |
+                    Error::PersistenceUnavailable |
+                    Error::ServiceUnavailable |
+                    Error::ShuttingDown |
+                    Error::SignerUnavailable
+            ) {
+                StatusCode::SERVICE_UNAVAILABLE
+            } else {
+                StatusCode::OK
+            };
+            assert_eq!(response.status(), expected, "{err}");
+            assert_eq!(response.status(), err.http_status(), "{err}");
+
+            let body = response.json::<Value>().await.unwrap();
+            assert_eq!(body["jsonrpc"], "2.0", "{err}");
+            assert!(body.get("result").is_none(), "{err}");
+            assert_eq!(body["error"]["code"], code, "{err}");
+            assert!(body["error"]["message"].is_string(), "{err}");
+            assert_eq!(body["error"]["data"]["reason"].as_str(), reason, "{err}");
+            assert!(body["error"]["data"]["retryable"].is_boolean(), "{err}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transport_errors() {
+        let request = |content_type: Option<&str>, body: Vec<u8>| {
+            let mut request = Request::post("/");
+            if let Some(content_type) = content_type {
+                request = request.header("content-type", content_type);
+            }
+            request.body(Body::from(body)).unwrap()
+        };
+
+        let too_large = request(None, vec![b' '; 3 * 1024 * 1024]);
+        let too_large = Bytes::from_request(too_large, &()).await.unwrap_err();
+        let no_content_type = request(None, b"{}".to_vec());
+        let no_content_type =
+            Json::<JsonPayload>::from_request(no_content_type, &()).await.unwrap_err();
+        let malformed = request(Some("application/json"), b"{ not json".to_vec());
+        let malformed = Json::<JsonPayload>::from_request(malformed, &()).await.unwrap_err();
+
+        let cases = [
+            (Error::InvalidBody(too_large), StatusCode::PAYLOAD_TOO_LARGE),
+            (Error::InvalidJson(no_content_type), StatusCode::UNSUPPORTED_MEDIA_TYPE),
+            // A body that isn't a JSON-RPC request is an error of the JSON-RPC layer
+            (Error::InvalidJson(malformed), StatusCode::OK),
+        ];
+
+        for (err, status) in cases {
+            let message = err.to_string();
+            let response = err.into_response();
+            assert_eq!(response.status(), status, "{message}");
+
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let error = serde_json::from_slice::<JsonResponse>(&body).unwrap().error.unwrap();
+            assert_eq!(error.code, -32600, "{message}");
+        }
+    }
 }
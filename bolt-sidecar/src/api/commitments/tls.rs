@@ -0,0 +1,488 @@
+//! The certificates of the TLS listener. The configuration and its errors are always
+//! available, so that the options can be parsed, while the resolver and the acceptor need the
+//! `tls` feature.
+
+use std::{collections::BTreeMap, fmt, path::PathBuf, str::FromStr};
+#[cfg(feature = "tls")]
+use std::{collections::HashMap, fs::File, io::BufReader, path::Path, sync::Arc};
+
+#[cfg(feature = "tls")]
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "tls")]
+use tokio_rustls::{
+    rustls::{
+        self,
+        client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        crypto::{ring, verify_tls12_signature, verify_tls13_signature, CryptoProvider},
+        pki_types::{CertificateDer, ServerName, UnixTime},
+        server::{ClientHello, ResolvesServerCert},
+        sign::CertifiedKey,
+        ClientConfig, ClientConnection, Connection, DigitallySignedStruct, ServerConfig,
+        ServerConnection, SignatureScheme,
+    },
+    TlsAcceptor,
+};
+#[cfg(feature = "tls")]
+use tracing::info;
+
+/// Errors that can occur while loading the TLS certificates.
+#[derive(Debug, thiserror::Error)]
+pub enum TlsError {
+    /// A certificate or key file couldn't be read.
+    #[error("Failed to read {}: {source}", path.display())]
+    Io {
+        /// The path of the file.
+        path: PathBuf,
+        /// The underlying error.
+        source: std::io::Error,
+    },
+    /// The certificate file doesn't contain any certificate.
+    #[error("No certificate found in {}", .0.display())]
+    NoCertificate(PathBuf),
+    /// The key file doesn't contain any private key.
+    #[error("No private key found in {}", .0.display())]
+    NoPrivateKey(PathBuf),
+    /// The private key is not of a supported type.
+    #[cfg(feature = "tls")]
+    #[error("Unsupported private key in {}: {source}", path.display())]
+    UnsupportedKey {
+        /// The path of the key file.
+        path: PathBuf,
+        /// The underlying error.
+        source: rustls::Error,
+    },
+    /// The private key doesn't match the leaf certificate.
+    #[cfg(feature = "tls")]
+    #[error("Key {} doesn't match certificate {}: {source}", key.display(), cert.display())]
+    KeyMismatch {
+        /// The path of the certificate chain.
+        cert: PathBuf,
+        /// The path of the key file.
+        key: PathBuf,
+        /// The handshake error.
+        source: rustls::Error,
+    },
+    /// No certificate is configured for the hostname.
+    #[error("No certificate is configured for {0}")]
+    UnknownHostname(String),
+}
+
+/// The paths of a PEM-encoded certificate chain and its private key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CertPair {
+    /// The path of the certificate chain, leaf first.
+    pub cert: PathBuf,
+    /// The path of the private key.
+    pub key: PathBuf,
+}
+
+/// What to do with TLS handshakes for a server name without a certificate of its own,
+/// including handshakes without any server name.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnknownSni {
+    /// Present the default certificate.
+    #[default]
+    Default,
+    /// Abort the handshake.
+    Reject,
+}
+
+impl FromStr for UnknownSni {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Self::Default),
+            "reject" => Ok(Self::Reject),
+            other => Err(format!("Unknown SNI policy '{other}', expected 'default' or 'reject'")),
+        }
+    }
+}
+
+impl fmt::Display for UnknownSni {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::Reject => write!(f, "reject"),
+        }
+    }
+}
+
+/// The TLS configuration of a listener: the certificates it presents by server name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TlsConfig {
+    /// The certificate presented for server names without a certificate of their own.
+    pub default: Option<CertPair>,
+    /// The certificates presented for each server name, by lowercase hostname.
+    pub hosts: BTreeMap<String, CertPair>,
+    /// What to do with handshakes for unknown server names.
+    pub unknown_sni: UnknownSni,
+}
+
+#[cfg(feature = "tls")]
+/// Selects the certificate a listener presents from the server name the client asks for
+/// with SNI, so that a single listener can serve several hostnames.
+///
+/// The certificates are loaded from disk when the resolver is created, and can be reloaded
+/// one hostname at a time, for instance after a renewal, without restarting the listener.
+#[derive(Debug)]
+pub struct SniResolver {
+    config: TlsConfig,
+    default: RwLock<Option<Arc<CertifiedKey>>>,
+    hosts: RwLock<HashMap<String, Arc<CertifiedKey>>>,
+}
+
+#[cfg(feature = "tls")]
+impl SniResolver {
+    /// Creates a resolver, loading all the configured certificates.
+    pub fn new(config: TlsConfig) -> Result<Self, TlsError> {
+        let default = config.default.as_ref().map(load_pair).transpose()?.map(Arc::new);
+
+        let mut hosts = HashMap::new();
+        for (hostname, pair) in &config.hosts {
+            hosts.insert(hostname.to_ascii_lowercase(), Arc::new(load_pair(pair)?));
+        }
+
+        Ok(Self { config, default: RwLock::new(default), hosts: RwLock::new(hosts) })
+    }
+
+    /// Reloads the certificate of the given hostname from disk, or all the certificates
+    /// if no hostname is given. The previous certificate is kept if loading fails.
+    pub fn reload(&self, hostname: Option<&str>) -> Result<(), TlsError> {
+        let Some(hostname) = hostname else {
+            let reloaded = Self::new(self.config.clone())?;
+            *self.default.write() = reloaded.default.into_inner();
+            *self.hosts.write() = reloaded.hosts.into_inner();
+            info!("Reloaded all the TLS certificates");
+            return Ok(());
+        };
+
+        let hostname = hostname.to_ascii_lowercase();
+        let pair = self
+            .config
+            .hosts
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&hostname))
+            .map(|(_, pair)| pair)
+            .ok_or_else(|| TlsError::UnknownHostname(hostname.clone()))?;
+
+        let key = Arc::new(load_pair(pair)?);
+        self.hosts.write().insert(hostname.clone(), key);
+        info!(hostname, "Reloaded the TLS certificate");
+        Ok(())
+    }
+
+    /// Returns a TLS acceptor presenting the certificates selected by this resolver.
+    pub fn acceptor(self: &Arc<Self>) -> TlsAcceptor {
+        let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .expect("The default provider supports the default protocol versions")
+            .with_no_client_auth()
+            .with_cert_resolver(self.clone());
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+        TlsAcceptor::from(Arc::new(config))
+    }
+}
+
+#[cfg(feature = "tls")]
+impl ResolvesServerCert for SniResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        if let Some(name) = client_hello.server_name() {
+            if let Some(key) = self.hosts.read().get(&name.to_ascii_lowercase()) {
+                return Some(key.clone());
+            }
+        }
+
+        match self.config.unknown_sni {
+            UnknownSni::Default => self.default.read().clone(),
+            UnknownSni::Reject => None,
+        }
+    }
+}
+
+#[cfg(feature = "tls")]
+/// Loads a PEM-encoded certificate chain and its private key.
+fn load_pair(pair: &CertPair) -> Result<CertifiedKey, TlsError> {
+    let open = |path: &Path| {
+        File::open(path)
+            .map(BufReader::new)
+            .map_err(|source| TlsError::Io { path: path.to_path_buf(), source })
+    };
+    let io_err = |path: &Path| {
+        let path = path.to_path_buf();
+        move |source| TlsError::Io { path, source }
+    };
+
+    let certs = rustls_pemfile::certs(&mut open(&pair.cert)?)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io_err(&pair.cert))?;
+    if certs.is_empty() {
+        return Err(TlsError::NoCertificate(pair.cert.clone()));
+    }
+
+    let key = rustls_pemfile::private_key(&mut open(&pair.key)?)
+        .map_err(io_err(&pair.key))?
+        .ok_or_else(|| TlsError::NoPrivateKey(pair.key.clone()))?;
+    let key = ring::sign::any_supported_type(&key)
+        .map_err(|source| TlsError::UnsupportedKey { path: pair.key.clone(), source })?;
+
+    let key = CertifiedKey::new(certs, key);
+    check_key_matches(key.clone()).map_err(|source| TlsError::KeyMismatch {
+        cert: pair.cert.clone(),
+        key: pair.key.clone(),
+        source,
+    })?;
+    Ok(key)
+}
+
+#[cfg(feature = "tls")]
+/// Checks that the private key matches the leaf certificate, by running a handshake in
+/// memory with a client that only verifies the signature of the server. Otherwise a
+/// mismatched pair is only noticed once the clients fail to connect.
+fn check_key_matches(key: CertifiedKey) -> Result<(), rustls::Error> {
+    let provider = Arc::new(ring::default_provider());
+    let server = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_cert_resolver(Arc::new(SingleKey(Arc::new(key))));
+    let client = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(SignatureOnly(provider)))
+        .with_no_client_auth();
+
+    let name = ServerName::try_from("localhost").expect("Valid server name");
+    let mut client = Connection::from(ClientConnection::new(Arc::new(client), name)?);
+    let mut server = Connection::from(ServerConnection::new(Arc::new(server))?);
+
+    // A full handshake takes two round trips
+    for _ in 0..4 {
+        if !client.is_handshaking() && !server.is_handshaking() {
+            return Ok(());
+        }
+        transfer(&mut client, &mut server)?;
+        transfer(&mut server, &mut client)?;
+    }
+    Err(rustls::Error::General("The handshake didn't complete".to_string()))
+}
+
+#[cfg(feature = "tls")]
+/// Sends the pending TLS records of a connection to the other.
+fn transfer(from: &mut Connection, to: &mut Connection) -> Result<(), rustls::Error> {
+    let io_err = |err: std::io::Error| rustls::Error::General(err.to_string());
+
+    let mut records = Vec::new();
+    from.write_tls(&mut records).map_err(io_err)?;
+    let mut records = records.as_slice();
+    while !records.is_empty() {
+        to.read_tls(&mut records).map_err(io_err)?;
+        to.process_new_packets()?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "tls")]
+/// Presents the same certificate to every client.
+#[derive(Debug)]
+struct SingleKey(Arc<CertifiedKey>);
+
+#[cfg(feature = "tls")]
+impl ResolvesServerCert for SingleKey {
+    fn resolve(&self, _: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.0.clone())
+    }
+}
+
+#[cfg(feature = "tls")]
+/// Accepts any certificate, but checks that the server signed the handshake with its key.
+#[derive(Debug)]
+struct SignatureOnly(Arc<CryptoProvider>);
+
+#[cfg(feature = "tls")]
+impl ServerCertVerifier for SignatureOnly {
+    fn verify_server_cert(
+        &self,
+        _: &CertificateDer<'_>,
+        _: &[CertificateDer<'_>],
+        _: &ServerName<'_>,
+        _: &[u8],
+        _: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+#[cfg(all(test, feature = "tls"))]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use crate::test_util::{test_tls_connector, write_test_cert, TEST_CERTS};
+
+    use super::*;
+
+    fn test_dir() -> PathBuf {
+        let suffix: u64 = rand::random();
+        let dir = std::env::temp_dir().join(format!("bolt-tls-{suffix}"));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn der_of(hostname: &str) -> CertificateDer<'static> {
+        let (_, cert, _) = TEST_CERTS.iter().find(|(name, ..)| *name == hostname).unwrap();
+        rustls_pemfile::certs(&mut cert.as_bytes()).next().unwrap().unwrap()
+    }
+
+    /// Serves TLS handshakes with the resolver, echoing a byte on each connection.
+    async fn serve(resolver: Arc<SniResolver>) -> std::net::SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let acceptor = resolver.acceptor();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    if let Ok(mut stream) = acceptor.accept(stream).await {
+                        let mut byte = [0; 1];
+                        if stream.read_exact(&mut byte).await.is_ok() {
+                            let _ = stream.write_all(&byte).await;
+                        }
+                    }
+                });
+            }
+        });
+        addr
+    }
+
+    /// Connects with the given SNI, returning the leaf certificate presented by the server.
+    async fn presented_cert(
+        addr: std::net::SocketAddr,
+        sni: &str,
+    ) -> std::io::Result<CertificateDer<'static>> {
+        let tcp = TcpStream::connect(addr).await?;
+        let name = ServerName::try_from(sni).unwrap().to_owned();
+        let mut stream = test_tls_connector().connect(name, tcp).await?;
+
+        // Round-trip a byte, so that a rejected handshake surfaces as an error
+        stream.write_all(&[1]).await?;
+        let mut byte = [0; 1];
+        stream.read_exact(&mut byte).await?;
+
+        let (_, connection) = stream.get_ref();
+        Ok(connection.peer_certificates().unwrap()[0].clone().into_owned())
+    }
+
+    #[tokio::test]
+    async fn test_certificate_selection_by_sni() {
+        let dir = test_dir();
+        let config = TlsConfig {
+            default: Some(write_test_cert(&dir, "default", "alpha.test")),
+            hosts: BTreeMap::from([
+                ("alpha.test".to_string(), write_test_cert(&dir, "alpha", "alpha.test")),
+                ("beta.test".to_string(), write_test_cert(&dir, "beta", "beta.test")),
+            ]),
+            unknown_sni: UnknownSni::Default,
+        };
+        let addr = serve(Arc::new(SniResolver::new(config.clone()).unwrap())).await;
+
+        assert_eq!(presented_cert(addr, "alpha.test").await.unwrap(), der_of("alpha.test"));
+        assert_eq!(presented_cert(addr, "beta.test").await.unwrap(), der_of("beta.test"));
+        // Server names are case-insensitive
+        assert_eq!(presented_cert(addr, "BETA.test").await.unwrap(), der_of("beta.test"));
+        // Unknown names fall back to the default certificate
+        assert_eq!(presented_cert(addr, "gamma.test").await.unwrap(), der_of("alpha.test"));
+
+        // Or are rejected
+        let config = TlsConfig { unknown_sni: UnknownSni::Reject, ..config };
+        let addr = serve(Arc::new(SniResolver::new(config).unwrap())).await;
+        assert_eq!(presented_cert(addr, "beta.test").await.unwrap(), der_of("beta.test"));
+        assert!(presented_cert(addr, "gamma.test").await.is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reload_certificate_per_hostname() {
+        let dir = test_dir();
+        let alpha = write_test_cert(&dir, "alpha", "alpha.test");
+        let beta = write_test_cert(&dir, "beta", "beta.test");
+        let config = TlsConfig {
+            default: None,
+            hosts: BTreeMap::from([
+                ("alpha.test".to_string(), alpha.clone()),
+                ("beta.test".to_string(), beta.clone()),
+            ]),
+            unknown_sni: UnknownSni::Reject,
+        };
+        let resolver = Arc::new(SniResolver::new(config).unwrap());
+        let addr = serve(resolver.clone()).await;
+
+        // Renew the certificates of both hostnames on disk by swapping them
+        write_test_cert(&dir, "alpha", "beta.test");
+        write_test_cert(&dir, "beta", "alpha.test");
+        assert_eq!(presented_cert(addr, "beta.test").await.unwrap(), der_of("beta.test"));
+
+        // Only the reloaded hostname presents its new certificate
+        resolver.reload(Some("beta.test")).unwrap();
+        assert_eq!(presented_cert(addr, "beta.test").await.unwrap(), der_of("alpha.test"));
+        assert_eq!(presented_cert(addr, "alpha.test").await.unwrap(), der_of("alpha.test"));
+
+        resolver.reload(None).unwrap();
+        assert_eq!(presented_cert(addr, "alpha.test").await.unwrap(), der_of("beta.test"));
+
+        // Unknown hostnames can't be reloaded, and failed reloads keep the certificate
+        let err = resolver.reload(Some("gamma.test")).unwrap_err();
+        assert!(matches!(err, TlsError::UnknownHostname(_)), "{err}");
+        std::fs::write(&beta.key, "not a key").unwrap();
+        let err = resolver.reload(Some("beta.test")).unwrap_err();
+        assert!(matches!(err, TlsError::NoPrivateKey(_)), "{err}");
+        assert_eq!(presented_cert(addr, "beta.test").await.unwrap(), der_of("alpha.test"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_unusable_certificate_pairs() {
+        let dir = test_dir();
+        let alpha = write_test_cert(&dir, "alpha", "alpha.test");
+        let beta = write_test_cert(&dir, "beta", "beta.test");
+        let config = |pair: CertPair| TlsConfig { default: Some(pair), ..Default::default() };
+
+        let missing = CertPair { cert: dir.join("missing.crt"), ..alpha.clone() };
+        let err = SniResolver::new(config(missing)).unwrap_err();
+        assert!(matches!(err, TlsError::Io { .. }), "{err}");
+
+        let mismatched = CertPair { cert: alpha.cert, key: beta.key };
+        let err = SniResolver::new(config(mismatched)).unwrap_err();
+        assert!(matches!(err, TlsError::KeyMismatch { .. }), "{err}");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
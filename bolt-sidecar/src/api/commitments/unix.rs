@@ -0,0 +1,166 @@
+//! Unix domain socket listener of the commitments API.
+//!
+//! Operators running the client on the same host can serve the public listener on a socket
+//! file instead of a TCP port, whose access is controlled by its file permissions. The socket
+//! serves plain HTTP, with the same routes and methods as the TCP listener, and without the
+//! client addresses: the methods that rely on them see requests as coming from nowhere.
+//!
+//! A socket file left over by a sidecar that didn't shut down gracefully is removed on
+//! startup, and the socket file is removed again on shutdown.
+
+use std::{
+    fs,
+    future::Future,
+    io,
+    os::unix::fs::{FileTypeExt, PermissionsExt},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use axum::Router;
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto,
+    service::TowerToHyperService,
+};
+use tokio::{net::UnixListener, task::JoinHandle};
+use tracing::{debug, error, info, warn};
+
+/// The default permissions of the socket file: read and write for the owner only.
+pub const DEFAULT_SOCKET_MODE: u32 = 0o600;
+
+/// The socket file the public listener is served on, instead of a TCP address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnixSocketConfig {
+    /// The path of the socket file.
+    pub path: PathBuf,
+    /// The permissions of the socket file.
+    pub mode: u32,
+}
+
+impl UnixSocketConfig {
+    /// Create the config of a socket file at the given path, with the default permissions.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), mode: DEFAULT_SOCKET_MODE }
+    }
+
+    /// Sets the permissions of the socket file.
+    pub fn with_mode(self, mode: u32) -> Self {
+        Self { mode, ..self }
+    }
+}
+
+/// Binds a listener on the socket file, after removing a stale socket left at its path, then
+/// sets its permissions.
+///
+/// Fails if another process is listening on the socket, or if the path is another kind of
+/// file, which is never removed.
+pub(super) fn bind(socket: &UnixSocketConfig) -> io::Result<UnixListener> {
+    let path = &socket.path;
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            if std::os::unix::net::UnixStream::connect(path).is_ok() {
+                let message = format!("{} is in use by another process", path.display());
+                return Err(io::Error::new(io::ErrorKind::AddrInUse, message));
+            }
+            warn!(path = %path.display(), "Removing stale socket file");
+            fs::remove_file(path)?;
+        }
+        Ok(_) => {
+            let message = format!("{} exists and is not a socket", path.display());
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, message));
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err),
+    }
+
+    let listener = UnixListener::bind(path)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(socket.mode))?;
+    Ok(listener)
+}
+
+/// Serves the router on the socket until the shutdown signal, then removes the socket file.
+/// Like the TLS listener, the connections that are open when the signal is received are not
+/// waited for.
+pub(super) fn serve<S>(
+    name: &'static str,
+    listener: UnixListener,
+    path: PathBuf,
+    router: Router,
+    shutdown: S,
+) -> JoinHandle<()>
+where
+    S: Future<Output = ()> + Send + 'static,
+{
+    info!("{name} bound to {}", path.display());
+    tokio::spawn(async move {
+        tokio::pin!(shutdown);
+        loop {
+            let stream = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(err) => {
+                        error!(?err, "{name} failed to accept a connection");
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        continue;
+                    }
+                },
+                _ = &mut shutdown => break,
+            };
+
+            let service = TowerToHyperService::new(router.clone());
+            tokio::spawn(async move {
+                let builder = auto::Builder::new(TokioExecutor::new());
+                let connection =
+                    builder.serve_connection_with_upgrades(TokioIo::new(stream), service);
+                if let Err(err) = connection.await {
+                    debug!(?err, "{name} connection error");
+                }
+            });
+        }
+
+        drop(listener);
+        remove_socket(&path);
+    })
+}
+
+/// Removes the socket file, if it's still there.
+fn remove_socket(path: &Path) {
+    match fs::remove_file(path) {
+        Ok(()) => debug!(path = %path.display(), "Removed socket file"),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => warn!(?err, path = %path.display(), "Failed to remove socket file"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_bind_socket_file() {
+        let dir = std::env::temp_dir().join(format!("bolt-unix-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let socket = UnixSocketConfig::new(dir.join("bind.sock")).with_mode(0o640);
+
+        // A live socket is never taken over
+        let listener = bind(&socket).unwrap();
+        let mode = fs::metadata(&socket.path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+        assert_eq!(bind(&socket).unwrap_err().kind(), io::ErrorKind::AddrInUse);
+
+        // The socket file left by a dead listener is stale
+        drop(listener);
+        assert!(socket.path.exists());
+        let listener = bind(&socket).unwrap();
+        drop(listener);
+
+        // Regular files are never removed
+        let file = UnixSocketConfig::new(dir.join("file"));
+        fs::write(&file.path, "data").unwrap();
+        assert_eq!(bind(&file).unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+        assert!(file.path.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,300 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    path::Path,
+    sync::{Arc, Weak},
+    time::Duration,
+};
+
+use alloy::primitives::Address;
+use parking_lot::RwLock;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use tracing::info;
+
+use crate::{
+    primitives::address::{parse_address, parse_address_list, AddressListError},
+    state::clock::Clock,
+};
+
+use super::spec::RejectionError;
+
+/// Interval at which the expired entries are removed from the whitelist.
+pub(super) const WHITELIST_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Errors that can occur while loading a whitelist.
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum WhitelistError {
+    #[error("Failed to read the whitelist: {0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid whitelist: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Invalid whitelist {0}")]
+    AddressList(#[from] AddressListError),
+}
+
+/// Overrides of the per-signer quotas for a whitelisted signer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotaOverrides {
+    /// Maximum gas the signer may hold committed in a single slot.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_gas_per_slot: Option<u64>,
+    /// Maximum number of transactions the signer may hold committed in a single slot.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_txs_per_slot: Option<u64>,
+    /// Maximum number of commitments the signer may obtain in a single epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_commitments_per_epoch: Option<u64>,
+}
+
+impl QuotaOverrides {
+    /// Returns true if no quota is overridden.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// The metadata of a whitelisted signer. Entries of the plain address-list form have the
+/// default metadata: they never expire.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WhitelistEntry {
+    /// The UNIX timestamp (in seconds) from which the entry is expired, if it expires.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    /// A human-readable label of the signer, such as the name of a team or customer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Who added the entry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added_by: Option<String>,
+    /// The quotas that apply to the signer instead of the configured ones.
+    #[serde(default, skip_serializing_if = "QuotaOverrides::is_empty")]
+    pub quotas: QuotaOverrides,
+}
+
+impl WhitelistEntry {
+    /// Returns true if the entry is expired at the given UNIX timestamp (in seconds).
+    pub fn is_expired_at(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+}
+
+/// The whitelist of request signers, with the metadata of each entry.
+///
+/// It is stored as a JSON object of the entries by signer address, or in the plain
+/// address-list form: addresses separated by commas or newlines.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Whitelist {
+    entries: BTreeMap<Address, WhitelistEntry>,
+}
+
+/// The whitelist of request signers, shared between the commitments API and the driver so
+/// that it can be replaced when the config is reloaded.
+pub type SharedWhitelist = Arc<RwLock<Whitelist>>;
+
+impl Whitelist {
+    /// Create a whitelist of the given addresses, with the default metadata.
+    pub fn from_addresses(addresses: impl IntoIterator<Item = Address>) -> Self {
+        let entries = addresses.into_iter().map(|address| (address, WhitelistEntry::default()));
+        Self { entries: entries.collect() }
+    }
+
+    /// Parses a whitelist in either of its forms.
+    pub fn parse(content: &str) -> Result<Self, WhitelistError> {
+        if content.trim_start().starts_with('{') {
+            return Ok(serde_json::from_str(content)?);
+        }
+
+        Ok(Self::from_addresses(parse_address_list(&content.replace('\n', ","))?))
+    }
+
+    /// Loads the whitelist from the file at the given path, in either of its forms.
+    pub fn load(path: &Path) -> Result<Self, WhitelistError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Saves the whitelist to the file at the given path, as a JSON object of the entries.
+    /// The file is replaced atomically.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+
+        std::fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(tmp, path)
+    }
+
+    /// Adds or replaces the entry of the signer, and returns the replaced one.
+    pub fn insert(&mut self, address: Address, entry: WhitelistEntry) -> Option<WhitelistEntry> {
+        self.entries.insert(address, entry)
+    }
+
+    /// Removes the entry of the signer, and returns it.
+    pub fn remove(&mut self, address: &Address) -> Option<WhitelistEntry> {
+        self.entries.remove(address)
+    }
+
+    /// Returns the entry of the signer, expired or not.
+    pub fn get(&self, address: &Address) -> Option<&WhitelistEntry> {
+        self.entries.get(address)
+    }
+
+    /// Returns true if the whitelist has an entry for the signer, expired or not.
+    pub fn contains(&self, address: &Address) -> bool {
+        self.entries.contains_key(address)
+    }
+
+    /// Returns the number of entries, expired or not.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the whitelist has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the entries by signer address, in the order of the addresses.
+    pub fn iter(&self) -> impl Iterator<Item = (&Address, &WhitelistEntry)> {
+        self.entries.iter()
+    }
+
+    /// Checks that the signer is authorized at the given UNIX timestamp (in seconds).
+    /// Expired entries are rejected as such, and otherwise behave as absent.
+    pub fn authorize(&self, signer: Address, now: u64) -> Result<&WhitelistEntry, RejectionError> {
+        match self.entries.get(&signer) {
+            None => Err(RejectionError::SignerNotWhitelisted(signer)),
+            Some(entry) => match entry.expires_at {
+                Some(expires_at) if now >= expires_at => {
+                    Err(RejectionError::AuthorizationExpired(signer, expires_at))
+                }
+                _ => Ok(entry),
+            },
+        }
+    }
+
+    /// Removes the entries expired at the given UNIX timestamp (in seconds), and returns them.
+    pub fn remove_expired(&mut self, now: u64) -> Vec<(Address, WhitelistEntry)> {
+        let expired = self.entries.iter().filter(|(_, entry)| entry.is_expired_at(now));
+        let expired = expired.map(|(address, _)| *address).collect::<Vec<_>>();
+
+        let removed = expired.into_iter().filter_map(|address| {
+            self.entries.remove(&address).map(|entry| (address, entry))
+        });
+        removed.collect()
+    }
+}
+
+impl Serialize for Whitelist {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Addresses are written with their EIP-55 checksum
+        let entries = self.entries.iter().map(|(address, entry)| (address.to_string(), entry));
+        serializer.collect_map(entries)
+    }
+}
+
+impl<'de> Deserialize<'de> for Whitelist {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let entries = BTreeMap::<String, WhitelistEntry>::deserialize(deserializer)?;
+        let entries = entries.into_iter().map(|(address, entry)| {
+            let address = parse_address(&address).map_err(|err| {
+                serde::de::Error::custom(format!("invalid address {address}: {err}"))
+            })?;
+            Ok((address, entry))
+        });
+
+        Ok(Self { entries: entries.collect::<Result<_, D::Error>>()? })
+    }
+}
+
+/// Removes the expired entries from the whitelist periodically, until it is dropped.
+pub(super) async fn sweep_expired(whitelist: Weak<RwLock<Whitelist>>, clock: Clock) {
+    let mut interval = tokio::time::interval(WHITELIST_SWEEP_INTERVAL);
+    loop {
+        interval.tick().await;
+        let Some(whitelist) = whitelist.upgrade() else { return };
+
+        for (address, entry) in whitelist.write().remove_expired(clock.unix_secs()) {
+            info!(%address, label = ?entry.label, "Removed expired whitelist entry");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    const ALICE: &str = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+    const BOB: &str = "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359";
+
+    #[test]
+    fn test_legacy_address_list() {
+        let suffix: u64 = rand::random();
+        let path = std::env::temp_dir().join(format!("bolt-whitelist-{suffix}.txt"));
+        std::fs::write(&path, format!("{ALICE},\n{BOB}\n")).unwrap();
+
+        let whitelist = Whitelist::load(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(whitelist.len(), 2);
+        for address in [ALICE, BOB] {
+            let entry = whitelist.get(&Address::from_str(address).unwrap()).unwrap();
+            assert_eq!(*entry, WhitelistEntry::default());
+        }
+
+        let err = Whitelist::parse(&format!("{ALICE},0x1234")).unwrap_err();
+        assert!(matches!(err, WhitelistError::AddressList(AddressListError { position: 2, .. })));
+    }
+
+    #[test]
+    fn test_expiry_and_sweep() {
+        let (alice, bob) = (Address::from_str(ALICE).unwrap(), Address::from_str(BOB).unwrap());
+        let mut whitelist = Whitelist::from_addresses([alice]);
+        let entry = WhitelistEntry { expires_at: Some(1_000), ..Default::default() };
+        whitelist.insert(bob, entry.clone());
+
+        assert!(whitelist.authorize(bob, 999).is_ok());
+        assert!(matches!(
+            whitelist.authorize(bob, 1_000),
+            Err(RejectionError::AuthorizationExpired(signer, 1_000)) if signer == bob
+        ));
+        assert!(matches!(
+            whitelist.authorize(Address::ZERO, 999),
+            Err(RejectionError::SignerNotWhitelisted(_))
+        ));
+
+        assert_eq!(whitelist.remove_expired(999), vec![]);
+        assert_eq!(whitelist.remove_expired(1_000), vec![(bob, entry)]);
+        assert!(matches!(
+            whitelist.authorize(bob, 1_000),
+            Err(RejectionError::SignerNotWhitelisted(_))
+        ));
+        assert!(whitelist.authorize(alice, u64::MAX).is_ok());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let suffix: u64 = rand::random();
+        let path = std::env::temp_dir().join(format!("bolt-whitelist-{suffix}.json"));
+
+        let mut whitelist = Whitelist::from_addresses([Address::from_str(ALICE).unwrap()]);
+        let entry = WhitelistEntry {
+            expires_at: Some(1_700_000_000),
+            label: Some("hackathon".to_string()),
+            added_by: Some("ops".to_string()),
+            quotas: QuotaOverrides { max_txs_per_slot: Some(4), ..Default::default() },
+        };
+        whitelist.insert(Address::from_str(BOB).unwrap(), entry);
+
+        whitelist.save(&path).unwrap();
+        assert_eq!(Whitelist::load(&path).unwrap(), whitelist);
+
+        // Addresses are checksummed, and default metadata is omitted
+        let json: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(json[ALICE], serde_json::json!({}));
+        assert_eq!(json[BOB]["quotas"], serde_json::json!({ "max_txs_per_slot": 4 }));
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,167 @@
+//! WebSocket transport of the commitments API.
+//!
+//! Clients sending many requests can keep a socket open on `GET /ws`, instead of paying for
+//! a connection per request. Each text message is a JSON-RPC request or batch, served like
+//! a request body on `POST /` with the methods exposed by the listener, and answered by a
+//! text message carrying its id. Requests are processed concurrently, so the responses to
+//! pipelined requests may arrive in any order.
+//!
+//! Headers are only sent with the upgrade request, so each payload is authenticated by its
+//! own `signature` field, formatted like the [`SIGNATURE_HEADER`](super::spec::SIGNATURE_HEADER)
+//! it replaces. Responses aren't signed, as there is no header to carry their signature.
+//!
+//! At most [`MAX_QUEUED_RESPONSES`] requests can be in flight or waiting for their response
+//! to be sent: a client that doesn't read its responses fast enough is disconnected. The open
+//! sockets are closed when the server shuts down.
+
+use std::{net::IpAddr, sync::Arc};
+
+use axum::{
+    extract::ws::{close_code, CloseFrame, Message, WebSocket},
+    http::HeaderMap,
+    Json,
+};
+use futures::{SinkExt, StreamExt};
+use serde::Serialize;
+use tokio::sync::{mpsc, watch};
+use tracing::{debug, warn};
+
+use super::{
+    erc1271::ContractSignatureCache,
+    jsonrpc::{JsonPayload, JsonRequest, JsonResponse},
+    methods::MethodRegistry,
+    profile::MethodProfile,
+    server::{batch_response, payload_headers, shutdown_signal, CommitmentsApiInner},
+    spec::Error,
+};
+
+/// The maximum number of requests of a socket in flight or awaiting the sending of their
+/// response, after which the client is disconnected.
+pub const MAX_QUEUED_RESPONSES: usize = 64;
+
+/// The maximum size of a message, the same as the limit of the request bodies.
+pub const MAX_MESSAGE_SIZE: usize = 2 * 1024 * 1024;
+
+/// The response to a message: a single response, or the responses to a batch in order.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum SocketResponse {
+    Single(JsonResponse),
+    Batch(Vec<JsonResponse>),
+}
+
+/// A WebSocket connection to a listener of the commitments API.
+#[derive(Debug)]
+pub(super) struct Session {
+    api: Arc<CommitmentsApiInner>,
+    profile: Arc<MethodProfile>,
+    methods: Arc<MethodRegistry>,
+    /// The headers of the upgrade request, used by the payloads without a signature.
+    headers: HeaderMap,
+    client_ip: Option<IpAddr>,
+}
+
+impl Session {
+    pub(super) fn new(
+        api: Arc<CommitmentsApiInner>,
+        profile: Arc<MethodProfile>,
+        methods: Arc<MethodRegistry>,
+        headers: HeaderMap,
+        client_ip: Option<IpAddr>,
+    ) -> Self {
+        Self { api, profile, methods, headers, client_ip }
+    }
+
+    /// Serves the requests received on the socket, until the client closes it or falls
+    /// behind, or the server shuts down.
+    pub(super) async fn run(self, socket: WebSocket, shutdown: Option<watch::Receiver<()>>) {
+        let session = Arc::new(self);
+        let (mut sink, mut messages) = socket.split();
+        let (responses_tx, mut responses) = mpsc::channel(MAX_QUEUED_RESPONSES);
+
+        let shutdown = async move {
+            match shutdown {
+                Some(shutdown) => shutdown_signal(shutdown).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::pin!(shutdown);
+
+        let close = loop {
+            tokio::select! {
+                message = messages.next() => match message {
+                    Some(Ok(Message::Text(text))) => {
+                        // The slot of the response is taken before processing the request
+                        let Ok(permit) = responses_tx.clone().try_reserve_owned() else {
+                            warn!("WebSocket client is too slow, closing the connection");
+                            break (close_code::POLICY, "Too many pending responses");
+                        };
+                        let session = session.clone();
+                        tokio::spawn(async move {
+                            let _ = permit.send(session.respond(text.as_bytes()).await);
+                        });
+                    }
+                    Some(Ok(Message::Binary(_))) => {
+                        break (close_code::UNSUPPORTED, "Only text messages are supported");
+                    }
+                    Some(Ok(Message::Close(_))) | None => return,
+                    // Pings are answered by the socket itself
+                    Some(Ok(Message::Ping(_) | Message::Pong(_))) => {}
+                    Some(Err(err)) => {
+                        debug!(?err, "WebSocket connection failed");
+                        return;
+                    }
+                },
+                Some(response) = responses.recv() => {
+                    if sink.send(Message::Text(response)).await.is_err() {
+                        return;
+                    }
+                }
+                _ = &mut shutdown => break (close_code::AWAY, "Server is shutting down"),
+            }
+        };
+
+        let (code, reason) = close;
+        let frame = CloseFrame { code, reason: reason.into() };
+        let _ = sink.send(Message::Close(Some(frame))).await;
+    }
+
+    /// Processes the JSON-RPC request or batch of a message, and returns its response.
+    /// Like the calls of an HTTP request, the calls of a message share their contract
+    /// signature checks.
+    async fn respond(&self, bytes: &[u8]) -> String {
+        let signatures = &ContractSignatureCache::default();
+        let response = match JsonRequest::from_slice(bytes) {
+            Ok(JsonRequest::Single(payload)) => {
+                let id = payload.id.clone();
+                SocketResponse::Single(batch_response(id, self.call(payload, signatures).await))
+            }
+            Ok(JsonRequest::Batch(payloads)) => {
+                let responses = payloads.into_iter().map(|payload| async move {
+                    match payload {
+                        Ok(payload) => {
+                            let id = payload.id.clone();
+                            batch_response(id, self.call(payload, signatures).await)
+                        }
+                        Err(err) => batch_response(None, Err(Error::InvalidPayload(err))),
+                    }
+                });
+                SocketResponse::Batch(futures::future::join_all(responses).await)
+            }
+            Err(err) => SocketResponse::Single(batch_response(None, Err(err.into()))),
+        };
+
+        serde_json::to_string(&response).expect("JSON-RPC responses can be serialized")
+    }
+
+    async fn call(
+        &self,
+        payload: JsonPayload,
+        signatures: &ContractSignatureCache,
+    ) -> Result<Json<JsonResponse>, Error> {
+        let headers = payload_headers(&self.headers, &payload)?;
+        let (profile, signatures) = (self.profile.clone(), signatures.clone());
+        let client_ip = self.client_ip;
+        self.methods.dispatch(&self.api, profile, &headers, payload, client_ip, signatures).await
+    }
+}
@@ -23,6 +23,10 @@ pub const GET_HEADER_PATH: &str = "/eth/v1/builder/header/:slot/:parent_hash/:pu
 pub const GET_PAYLOAD_PATH: &str = "/eth/v1/builder/blinded_blocks";
 /// The path to the constraints API submit constraints endpoint.
 pub const CONSTRAINTS_PATH: &str = "/eth/v1/builder/constraints";
+/// The path to the constraints API delegate endpoint.
+pub const DELEGATE_PATH: &str = "/eth/v1/builder/delegate";
+/// The path to the constraints API revoke endpoint.
+pub const REVOKE_PATH: &str = "/eth/v1/builder/revoke";
 
 /// A response object for errors.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +36,13 @@ pub struct ErrorResponse {
     message: String,
 }
 
+impl ErrorResponse {
+    /// Creates the error of a response with the status code, when its body isn't one.
+    pub fn from_status(status: StatusCode) -> Self {
+        Self { code: status.as_u16(), message: status.to_string() }
+    }
+}
+
 /// Helper to serialize a status code as a string using the provided serializer.
 pub fn serialize_status_code<S>(value: &u16, serializer: S) -> Result<S::Ok, S::Error>
 where
@@ -52,6 +63,8 @@ pub enum BuilderApiError {
     FailedGettingPayload(ErrorResponse),
     #[error("Failed submitting constraints: {0:?}")]
     FailedSubmittingConstraints(ErrorResponse),
+    #[error("Failed submitting delegations: {0:?}")]
+    FailedSubmittingDelegations(ErrorResponse),
     #[error("Failed to fetch local payload for slot {0}")]
     FailedToFetchLocalPayload(u64),
     #[error("Axum error: {0:?}")]
@@ -68,6 +81,8 @@ pub enum BuilderApiError {
     LocalPayloadIntegrity(#[from] super::builder::LocalPayloadIntegrityError),
     #[error("Generic error: {0}")]
     Generic(String),
+    #[error("Circuit of the relay {0} is open after successive failures")]
+    CircuitOpen(String),
 }
 
 impl IntoResponse for BuilderApiError {
@@ -85,6 +100,9 @@ impl IntoResponse for BuilderApiError {
             BuilderApiError::FailedSubmittingConstraints(error) => {
                 (StatusCode::from_u16(error.code).unwrap(), Json(error)).into_response()
             }
+            BuilderApiError::FailedSubmittingDelegations(error) => {
+                (StatusCode::from_u16(error.code).unwrap(), Json(error)).into_response()
+            }
             BuilderApiError::AxumError(err) => {
                 (StatusCode::BAD_REQUEST, err.to_string()).into_response()
             }
@@ -113,6 +131,35 @@ impl IntoResponse for BuilderApiError {
             BuilderApiError::Generic(err) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, Json(err)).into_response()
             }
+            BuilderApiError::CircuitOpen(_) => {
+                (StatusCode::SERVICE_UNAVAILABLE, self.to_string()).into_response()
+            }
+        }
+    }
+}
+
+impl BuilderApiError {
+    /// Returns the HTTP status code of the upstream response that caused the error, if any.
+    pub fn status_code(&self) -> Option<u16> {
+        match self {
+            BuilderApiError::FailedRegisteringValidators(error) |
+            BuilderApiError::FailedGettingHeader(error) |
+            BuilderApiError::FailedGettingPayload(error) |
+            BuilderApiError::FailedSubmittingConstraints(error) |
+            BuilderApiError::FailedSubmittingDelegations(error) => Some(error.code),
+            BuilderApiError::ReqwestError(err) => err.status().map(|status| status.as_u16()),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the error is transient, such as a 5xx response, a timeout or a
+    /// connection reset, so that the request may succeed if it is retried.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            BuilderApiError::CircuitOpen(_) => false,
+            BuilderApiError::Timeout(_) => true,
+            BuilderApiError::ReqwestError(err) if err.status().is_none() => true,
+            _ => self.status_code().is_some_and(|code| code >= 500 || code == 429),
         }
     }
 }
@@ -10,8 +10,10 @@ use payload_builder::FallbackPayloadBuilder;
 use signature::sign_builder_message;
 
 use crate::{
+    config::Fork,
     primitives::{
-        BuilderBid, GetPayloadResponse, PayloadAndBid, PayloadAndBlobs, SignedBuilderBid,
+        BuilderBid, GetPayloadResponse, PayloadAndBid, PayloadAndBlobs, PayloadTransactions,
+        SignedBuilderBid,
     },
     ChainConfig, Config,
 };
@@ -33,6 +35,10 @@ mod compat;
 /// `engine_newPayloadV3` response error to produce a valid payload.
 pub mod payload_builder;
 
+/// Ordering of the committed transactions of the fallback payload, and
+/// its top-up with the transactions of the mempool.
+pub mod ordering;
+
 #[derive(Debug, thiserror::Error)]
 #[non_exhaustive]
 #[allow(missing_docs)]
@@ -57,6 +63,8 @@ pub enum BuilderError {
     InvalidEngineHint(String),
     #[error("Failed to build payload: {0}")]
     Custom(String),
+    #[error("Local payloads can't be built from the Electra fork on, at slot {0}")]
+    UnsupportedFork(u64),
 }
 
 /// Local builder instance that can ingest a sealed header and
@@ -88,19 +96,29 @@ impl LocalBuilder {
     }
 
     /// Build a new payload with the given transactions. This method will
-    /// cache the payload in the local builder instance, and make it available
+    /// cache the payload in the local builder instance, and make it available.
+    /// Returns the transactions of the payload, to prove their inclusion.
+    ///
+    /// Fails for the slots from the Electra fork on: the bid, the payload and the engine hints
+    /// are only built in their Deneb versions, which the proposer can't sign a valid block for.
     pub async fn build_new_local_payload(
         &mut self,
         slot: u64,
         template: &BlockTemplate,
-    ) -> Result<(), BuilderError> {
-        let transactions = template.as_signed_transactions();
-        let blobs_bundle = template.as_blobs_bundle();
-        let kzg_commitments = blobs_bundle.commitments.clone();
+    ) -> Result<PayloadTransactions, BuilderError> {
+        if self.chain.fork_at(slot) >= Fork::Electra {
+            return Err(BuilderError::UnsupportedFork(slot));
+        }
 
-        // 1. build a fallback payload with the given transactions, on top of
-        // the current head of the chain
-        let block = self.fallback_builder.build_fallback_payload(slot, &transactions).await?;
+        // 1. build a fallback payload with the committed transactions, topped up from the
+        // mempool, on top of the current head of the chain
+        let committed = template.committed_bundles();
+        let block = self.fallback_builder.build_fallback_payload(slot, committed).await?;
+        let transactions = block.body.clone();
+        let blobs_bundle = template.blobs_bundle_of(&transactions);
+        let kzg_commitments = blobs_bundle.commitments.clone();
+        let encoded = transactions.iter().map(|tx| (tx.hash(), tx.envelope_encoded()));
+        let payload_transactions = PayloadTransactions::new(block.hash(), encoded);
 
         // NOTE: we use a big value for the bid to ensure it gets chosen by mev-boost.
         // the client has no way to actually verify this, and we don't need to trust
@@ -125,7 +143,7 @@ impl LocalBuilder {
         self.payload_and_bid =
             Some(PayloadAndBid { bid: signed_bid, payload: get_payload_response });
 
-        Ok(())
+        Ok(payload_transactions)
     }
 
     /// Get the cached payload and bid from the local builder, consuming the value.
@@ -155,3 +173,25 @@ impl LocalBuilder {
         Ok(SignedBuilderBid { message, signature })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Url;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_no_local_payload_from_electra() {
+        let mut config = Config::default();
+        config.chain = ChainConfig::holesky().with_electra_fork_epoch(2);
+        let beacon_client = BeaconClient::new(Url::parse("http://127.0.0.1:1").unwrap());
+        let mut builder = LocalBuilder::new(&config, beacon_client, 0);
+
+        // The slots of the fork epoch are rejected before anything is fetched for them
+        let fork_slot = 2 * config.chain.slots_per_epoch();
+        let template = BlockTemplate::default();
+        let err = builder.build_new_local_payload(fork_slot, &template).await.unwrap_err();
+        assert!(matches!(err, BuilderError::UnsupportedFork(slot) if slot == fork_slot), "{err}");
+        assert!(builder.get_cached_payload().is_none());
+    }
+}
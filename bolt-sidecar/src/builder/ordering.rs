@@ -0,0 +1,350 @@
+//! Selection and ordering of the transactions of the fallback payload.
+//!
+//! The committed transactions come first, by effective priority fee at the base fee of the
+//! payload. The transactions of an atomic commitment stay together in their signed order,
+//! ranked by the lowest tip among them, and the transactions of a sender stay in nonce order.
+//!
+//! The gas left in the block is then filled with the pending transactions of the mempool of
+//! the execution client, by effective priority fee as well. The committed transactions are
+//! skipped, and the transactions of a sender are only included in nonce order, following its
+//! account nonce and its committed transactions, and while the balance left after its
+//! committed transactions pays for them: once one of them is left out, the later ones of its
+//! sender are too.
+
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet, VecDeque},
+};
+
+use alloy::primitives::{Address, U256};
+use reth_primitives::{TransactionSigned, TransactionSignedEcRecovered};
+use tracing::{debug, warn};
+
+use crate::primitives::AccountState;
+
+/// The maximum number of mempool transactions considered for a payload, which bounds the
+/// account lookups of their senders.
+pub const MAX_MEMPOOL_CANDIDATES: usize = 1_000;
+
+/// The limits of a payload, shared by its committed and mempool transactions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PayloadLimits {
+    /// The gas limit of the block.
+    pub gas_limit: u64,
+    /// The maximum number of blobs of the block.
+    pub max_blobs: usize,
+}
+
+/// The pending transactions of the mempool considered for a payload, with the account states
+/// of their senders at the head of the chain.
+#[derive(Debug, Clone, Default)]
+pub struct MempoolCandidates {
+    /// The pending transactions.
+    pub transactions: Vec<TransactionSignedEcRecovered>,
+    /// The account states of their senders. The transactions of the senders without one are
+    /// left out.
+    pub accounts: HashMap<Address, AccountState>,
+}
+
+/// Returns the best pending transactions by effective tip, at most [`MAX_MEMPOOL_CANDIDATES`]
+/// of them and as much gas as the block holds, to consider for a payload.
+pub fn mempool_candidates(
+    mut pending: Vec<TransactionSignedEcRecovered>,
+    base_fee: u64,
+    gas_limit: u64,
+) -> Vec<TransactionSignedEcRecovered> {
+    pending.sort_by_key(|tx| Reverse(effective_tip(tx, base_fee)));
+
+    let mut gas_left = gas_limit;
+    let fits = move |tx: &TransactionSignedEcRecovered| {
+        let left = gas_left.checked_sub(tx.gas_limit());
+        gas_left = left.unwrap_or(gas_left);
+        left.is_some()
+    };
+    pending.into_iter().filter(fits).take(MAX_MEMPOOL_CANDIDATES).collect()
+}
+
+/// Returns the transactions of the payload: the committed ones, grouped in the bundles they
+/// must be included as, followed by the mempool ones that fit in the limits.
+pub fn select_transactions(
+    committed: Vec<Vec<TransactionSignedEcRecovered>>,
+    mempool: MempoolCandidates,
+    base_fee: u64,
+    limits: PayloadLimits,
+) -> Vec<TransactionSigned> {
+    let mut payload = order_committed(committed, base_fee);
+
+    let gas = payload.iter().map(|tx| tx.gas_limit()).sum::<u64>();
+    let blobs = payload.iter().flat_map(|tx| tx.blob_versioned_hashes()).flatten().count();
+    if gas > limits.gas_limit || blobs > limits.max_blobs {
+        warn!(gas, blobs, ?limits, "The committed transactions exceed the limits of the payload");
+    }
+
+    let filled =
+        fill_from_mempool(&payload, mempool, base_fee, limits.gas_limit.saturating_sub(gas));
+    debug!(committed = payload.len(), mempool = filled.len(), "Selected the payload transactions");
+    payload.extend(filled);
+    payload.into_iter().map(TransactionSignedEcRecovered::into_signed).collect()
+}
+
+/// Orders the bundles of committed transactions by their lowest effective tip, the ones with
+/// equal tips keeping their order. A bundle is only placed once the transactions of lower
+/// nonces of its senders are.
+fn order_committed(
+    mut bundles: Vec<Vec<TransactionSignedEcRecovered>>,
+    base_fee: u64,
+) -> Vec<TransactionSignedEcRecovered> {
+    let tip = |bundle: &Vec<TransactionSignedEcRecovered>| {
+        bundle.iter().map(|tx| effective_tip(tx, base_fee).unwrap_or_default()).min()
+    };
+    bundles.sort_by_key(|bundle| Reverse(tip(bundle)));
+
+    let mut ordered = Vec::new();
+    while !bundles.is_empty() {
+        // The nonces only conflict if the committed transactions do, which their validation
+        // prevents: the best bundle goes first then
+        let ready = (0..bundles.len()).find(|&index| is_ready(&bundles, index));
+        ordered.extend(bundles.remove(ready.unwrap_or_default()));
+    }
+    ordered
+}
+
+/// Returns true if no other bundle holds a transaction of a lower nonce of a sender of the
+/// bundle at `index`.
+fn is_ready(bundles: &[Vec<TransactionSignedEcRecovered>], index: usize) -> bool {
+    let others = bundles.iter().enumerate().filter(|(other, _)| *other != index);
+    let mut others = others.flat_map(|(_, bundle)| bundle.iter());
+    !others.any(|other| {
+        bundles[index].iter().any(|tx| tx.signer() == other.signer() && other.nonce() < tx.nonce())
+    })
+}
+
+/// Returns the mempool transactions that fill the gas left after the committed ones, by
+/// effective tip, in the nonce order of each sender.
+fn fill_from_mempool(
+    committed: &[TransactionSignedEcRecovered],
+    mempool: MempoolCandidates,
+    base_fee: u64,
+    mut gas_left: u64,
+) -> Vec<TransactionSignedEcRecovered> {
+    // The next nonce and the balance left of each sender with an account state, once its
+    // committed transactions are applied
+    let mut next_nonces = HashMap::<Address, u64>::new();
+    let mut balances = HashMap::<Address, U256>::new();
+    for (sender, account) in &mempool.accounts {
+        next_nonces.insert(*sender, account.transaction_count);
+        balances.insert(*sender, account.balance);
+    }
+    for tx in committed {
+        let next = next_nonces.entry(tx.signer()).or_default();
+        *next = (*next).max(tx.nonce() + 1);
+        if let Some(balance) = balances.get_mut(&tx.signer()) {
+            *balance = balance.saturating_sub(max_cost(tx));
+        }
+    }
+
+    // The pending transactions of each sender with an account state, in nonce order, without
+    // the committed ones and the ones whose nonce is already taken
+    let hashes = committed.iter().map(|tx| tx.hash()).collect::<HashSet<_>>();
+    let mut senders = HashMap::<Address, VecDeque<TransactionSignedEcRecovered>>::new();
+    for tx in mempool.transactions.into_iter().filter(|tx| !hashes.contains(&tx.hash())) {
+        if balances.contains_key(&tx.signer()) && tx.nonce() >= next_nonces[&tx.signer()] {
+            senders.entry(tx.signer()).or_default().push_back(tx);
+        }
+    }
+    for queue in senders.values_mut() {
+        queue.make_contiguous().sort_by_key(|tx| tx.nonce());
+    }
+
+    let mut filled = Vec::new();
+    while let Some(sender) = best_sender(&senders, base_fee) {
+        let queue = senders.get_mut(&sender).expect("Sender with pending transactions");
+        let tx = queue.pop_front().expect("Pending transaction of the sender");
+
+        // A gap in the nonces, or a transaction that doesn't fit or that the balance left
+        // doesn't pay for, leaves out the later transactions of the sender. The blob
+        // transactions are left out as well, since the mempool doesn't serve their sidecars.
+        let balance = balances.get_mut(&sender).expect("Sender with an account state");
+        let cost = max_cost(&tx);
+        let in_order = tx.nonce() == next_nonces[&sender];
+        if !in_order || tx.gas_limit() > gas_left || cost > *balance || tx.is_eip4844() {
+            senders.remove(&sender);
+            continue;
+        }
+
+        gas_left -= tx.gas_limit();
+        *balance -= cost;
+        next_nonces.insert(sender, tx.nonce() + 1);
+        filled.push(tx);
+    }
+    filled
+}
+
+/// Returns the sender whose next pending transaction tips the most, the lowest address first
+/// among equal tips. The senders whose next transaction can't pay the base fee are skipped.
+fn best_sender(
+    senders: &HashMap<Address, VecDeque<TransactionSignedEcRecovered>>,
+    base_fee: u64,
+) -> Option<Address> {
+    let tips = senders.iter().filter_map(|(sender, queue)| {
+        let tip = effective_tip(queue.front()?, base_fee)?;
+        Some((tip, Reverse(*sender)))
+    });
+    tips.max().map(|(_, Reverse(sender))| sender)
+}
+
+/// Returns the maximum cost of the transaction: its gas and blob gas at their maximum fees,
+/// and its value. See [`crate::common::max_transaction_cost`].
+fn max_cost(tx: &TransactionSigned) -> U256 {
+    let gas = U256::from(tx.gas_limit()) * U256::from(tx.max_fee_per_gas());
+    let blob_fee = U256::from(tx.max_fee_per_blob_gas().unwrap_or_default());
+    let blob_gas = U256::from(tx.blob_gas_used().unwrap_or_default()) * blob_fee;
+    gas + blob_gas + tx.value()
+}
+
+/// Returns the priority fee per gas the transaction pays at the base fee, or `None` if its
+/// maximum fee doesn't cover the base fee.
+fn effective_tip(tx: &TransactionSigned, base_fee: u64) -> Option<u128> {
+    tx.effective_tip_per_gas(Some(base_fee))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        eips::eip2718::Encodable2718,
+        network::{EthereumWallet, TransactionBuilder},
+        primitives::B256,
+        signers::local::PrivateKeySigner,
+    };
+
+    use super::*;
+    use crate::test_util::default_test_transaction;
+
+    const GWEI: u128 = 1_000_000_000;
+
+    /// Signs a transfer of the given nonce, tip and gas limit, with a fee cap of 20 gwei.
+    async fn transfer(
+        signer: &PrivateKeySigner,
+        nonce: u64,
+        tip_gwei: u128,
+        gas: u64,
+    ) -> TransactionSignedEcRecovered {
+        let tx = default_test_transaction(signer.address(), Some(nonce))
+            .with_max_priority_fee_per_gas(tip_gwei * GWEI)
+            .with_gas_limit(gas);
+        let envelope = tx.build(&EthereumWallet::from(signer.clone())).await.unwrap();
+        let encoded = envelope.encoded_2718();
+        let tx = TransactionSigned::decode_enveloped(&mut encoded.as_slice()).unwrap();
+        TransactionSignedEcRecovered::from_signed_transaction(tx, signer.address())
+    }
+
+    fn hashes(txs: &[TransactionSigned]) -> Vec<B256> {
+        txs.iter().map(|tx| tx.hash()).collect()
+    }
+
+    /// The account states of the given signers, at nonce 0 and with 1 ETH each.
+    fn accounts(signers: &[&PrivateKeySigner]) -> HashMap<Address, AccountState> {
+        let account = AccountState {
+            transaction_count: 0,
+            balance: U256::from(10u128.pow(18)),
+            has_code: false,
+        };
+        signers.iter().map(|signer| (signer.address(), account)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_select_transactions() {
+        let (alice, bob, carol) =
+            (PrivateKeySigner::random(), PrivateKeySigner::random(), PrivateKeySigner::random());
+        let base_fee = 10 * GWEI as u64;
+
+        // Three committed transactions: two of alice, the later one tipping the most, and one
+        // of bob
+        let alice_0 = transfer(&alice, 0, 1, 21_000).await;
+        let alice_1 = transfer(&alice, 1, 5, 21_000).await;
+        let bob_0 = transfer(&bob, 0, 3, 21_000).await;
+        let committed = vec![vec![alice_0.clone()], vec![alice_1.clone()], vec![bob_0.clone()]];
+
+        // The mempool holds a committed transaction, a stale nonce of alice, her next one, and
+        // carol's transactions, the second of which doesn't fit in the gas limit
+        let mempool = vec![
+            bob_0.clone(),
+            transfer(&alice, 1, 9, 21_000).await,
+            transfer(&alice, 2, 2, 21_000).await,
+            transfer(&carol, 0, 4, 21_000).await,
+            transfer(&carol, 1, 8, 100_000).await,
+            transfer(&carol, 2, 8, 21_000).await,
+        ];
+        let limits = PayloadLimits { gas_limit: 150_000, max_blobs: 6 };
+        let candidates = |transactions: &[TransactionSignedEcRecovered]| MempoolCandidates {
+            transactions: transactions.to_vec(),
+            accounts: accounts(&[&alice, &bob, &carol]),
+        };
+
+        let payload = select_transactions(committed, candidates(&mempool), base_fee, limits);
+
+        // Bob's bundle tips more than alice's first transaction, which her second must follow
+        let expected = [&bob_0, &alice_0, &alice_1, &mempool[3], &mempool[2]].map(|tx| tx.hash());
+        assert_eq!(hashes(&payload), expected);
+        let gas = payload.iter().map(|tx| tx.gas_limit()).sum::<u64>();
+        assert!(gas <= limits.gas_limit);
+
+        // With more gas, carol's later transactions are included, by nonce then by tip
+        let limits = PayloadLimits { gas_limit: 300_000, ..limits };
+        let committed = vec![vec![alice_0.clone()], vec![alice_1.clone()], vec![bob_0.clone()]];
+        let payload = select_transactions(committed, candidates(&mempool), base_fee, limits);
+        let filled = [&mempool[3], &mempool[4], &mempool[5], &mempool[2]].map(|tx| tx.hash());
+        assert_eq!(hashes(&payload[3..]), filled);
+
+        // The mempool transactions follow the account nonces of their senders, and are paid
+        // for by the balance left after the committed transactions. Carol's transactions start
+        // from her account nonce, and alice can only pay for one more transfer.
+        let committed = vec![vec![alice_0.clone()], vec![alice_1.clone()], vec![bob_0.clone()]];
+        let mut mempool = candidates(&mempool);
+        mempool.accounts.get_mut(&carol.address()).unwrap().transaction_count = 1;
+        let alice_cost = max_cost(&alice_0) * U256::from(3);
+        mempool.accounts.get_mut(&alice.address()).unwrap().balance = alice_cost;
+        let alice_3 = transfer(&alice, 3, 2, 21_000).await;
+        mempool.transactions.push(alice_3);
+        let expected = mempool.transactions[4..6].iter().chain(&mempool.transactions[2..3]);
+        let expected = expected.map(|tx| tx.hash()).collect::<Vec<_>>();
+        let payload = select_transactions(committed, mempool, base_fee, limits);
+        assert_eq!(hashes(&payload[3..]), expected);
+
+        // And the senders without an account state are left out
+        let committed = vec![vec![alice_0.clone()]];
+        let mempool = MempoolCandidates { transactions: vec![bob_0.clone()], ..Default::default() };
+        let payload = select_transactions(committed, mempool, base_fee, limits);
+        assert_eq!(hashes(&payload), vec![alice_0.hash()]);
+
+        // The transactions of an atomic bundle stay together and in order, ranked by their
+        // lowest tip
+        let bundle = vec![alice_1.clone(), bob_0.clone()];
+        let carol_0 = mempool[3].clone();
+        let committed = vec![vec![alice_0.clone()], bundle, vec![carol_0.clone()]];
+        let payload = select_transactions(committed, Default::default(), base_fee, limits);
+        let expected = [&carol_0, &alice_0, &alice_1, &bob_0].map(|tx| tx.hash());
+        assert_eq!(hashes(&payload), expected);
+    }
+
+    #[tokio::test]
+    async fn test_mempool_candidates() {
+        let (alice, bob) = (PrivateKeySigner::random(), PrivateKeySigner::random());
+        let base_fee = 10 * GWEI as u64;
+
+        // The best transactions by tip are considered, as long as they fit in the gas limit
+        let pending = vec![
+            transfer(&alice, 0, 1, 21_000).await,
+            transfer(&bob, 0, 5, 100_000).await,
+            transfer(&alice, 1, 3, 21_000).await,
+        ];
+        let candidates = mempool_candidates(pending.clone(), base_fee, 50_000);
+        let expected = [&pending[2], &pending[0]].map(|tx| tx.hash());
+        assert_eq!(candidates.iter().map(|tx| tx.hash()).collect::<Vec<_>>(), expected);
+
+        // And up to a maximum number of them
+        let pending = vec![pending[0].clone(); MAX_MEMPOOL_CANDIDATES + 1];
+        let candidates = mempool_candidates(pending, base_fee, u64::MAX);
+        assert_eq!(candidates.len(), MAX_MEMPOOL_CANDIDATES);
+    }
+}
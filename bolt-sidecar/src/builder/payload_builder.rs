@@ -1,7 +1,11 @@
-use std::fmt;
+use std::{fmt, time::Duration};
 
 use alloy::{
-    eips::{calc_excess_blob_gas, calc_next_block_base_fee, eip1559::BaseFeeParams},
+    consensus::TxEnvelope,
+    eips::{
+        calc_excess_blob_gas, calc_next_block_base_fee, eip1559::BaseFeeParams,
+        eip2718::Encodable2718,
+    },
     primitives::{Address, Bytes, B256, U256},
     rpc::types::{engine::ExecutionPayload as AlloyExecutionPayload, Block},
 };
@@ -11,7 +15,7 @@ use regex::Regex;
 use reqwest::Url;
 use reth_primitives::{
     constants::BEACON_NONCE, proofs, BlockBody, Bloom, Header, SealedBlock, TransactionSigned,
-    Withdrawal, Withdrawals, EMPTY_OMMER_ROOT_HASH,
+    TransactionSignedEcRecovered, Withdrawal, Withdrawals, EMPTY_OMMER_ROOT_HASH,
 };
 use reth_rpc_layer::{secret_to_bearer_header, JwtSecret};
 use serde_json::Value;
@@ -19,9 +23,10 @@ use tracing::{debug, info, trace, warn};
 
 use super::{
     compat::{to_alloy_execution_payload, to_reth_withdrawal},
+    ordering::{mempool_candidates, select_transactions, MempoolCandidates, PayloadLimits},
     BuilderError,
 };
-use crate::{BeaconClient, Config, RpcClient};
+use crate::{BeaconClient, ChainConfig, Config, RpcClient};
 
 /// Extra-data payload field used for locally built blocks, decoded in UTF-8.
 ///
@@ -31,6 +36,11 @@ const DEFAULT_EXTRA_DATA: [u8; 20] = [
     0x42, 0x6f, 0x6c, 0x74,
 ];
 
+/// The time the mempool of the execution client and the account states of its senders have to
+/// be fetched in, as the fallback payload is built on the path of `get_payload`. Past it, the
+/// payload holds the committed transactions only.
+const MEMPOOL_FETCH_TIMEOUT: Duration = Duration::from_millis(500);
+
 /// The fallback payload builder is responsible for assembling a valid
 /// sealed block from a set of transactions. It (ab)uses the engine API
 /// to fetch "hints" for missing header values, such as the block hash,
@@ -48,7 +58,7 @@ pub struct FallbackPayloadBuilder {
     beacon_api_client: BeaconClient,
     execution_rpc_client: RpcClient,
     engine_hinter: EngineHinter,
-    slot_time: u64,
+    chain: ChainConfig,
     genesis_time: u64,
 }
 
@@ -66,7 +76,7 @@ impl FallbackPayloadBuilder {
             extra_data: DEFAULT_EXTRA_DATA.into(),
             fee_recipient: config.fee_recipient,
             execution_rpc_client: RpcClient::new(config.execution_api_url.clone()),
-            slot_time: config.chain.slot_time(),
+            chain: config.chain.clone(),
             genesis_time,
             beacon_api_client,
         }
@@ -103,10 +113,16 @@ struct Hints {
 impl FallbackPayloadBuilder {
     /// Build a minimal payload to be used as a fallback in case PBS relays fail
     /// to provide a valid payload that fulfills the commitments made by Bolt.
+    ///
+    /// The committed transactions, grouped in the bundles they must be included as, are
+    /// ordered by effective priority fee, and the remaining gas of the block is filled with
+    /// the pending transactions of the mempool that the senders can pay for once their
+    /// committed transactions are applied. The mempool is skipped if it can't be fetched
+    /// within [`MEMPOOL_FETCH_TIMEOUT`].
     pub async fn build_fallback_payload(
         &self,
         target_slot: u64,
-        transactions: &[TransactionSigned],
+        committed: Vec<Vec<TransactionSignedEcRecovered>>,
     ) -> Result<SealedBlock, BuilderError> {
         // We fetch the latest block to get the necessary parent values for the new block.
         // For the timestamp, we must use the one expected by the beacon chain instead, to
@@ -124,13 +140,6 @@ impl FallbackPayloadBuilder {
             self.beacon_api_client.get_beacon_block_root(BlockId::Head).await?;
         debug!(parent = ?parent_beacon_block_root, "got parent_beacon_block_root");
 
-        let versioned_hashes = transactions
-            .iter()
-            .flat_map(|tx| tx.blob_versioned_hashes())
-            .flatten()
-            .collect::<Vec<_>>();
-        info!(amount = ?versioned_hashes.len(), "got versioned_hashes");
-
         let base_fee = calc_next_block_base_fee(
             latest_block.header.gas_used,
             latest_block.header.gas_limit,
@@ -138,6 +147,30 @@ impl FallbackPayloadBuilder {
             BaseFeeParams::ethereum(),
         ) as u64;
 
+        let limits = PayloadLimits {
+            gas_limit: latest_block.header.gas_limit as u64,
+            max_blobs: self.chain.fork_at(target_slot).max_blobs_per_block(),
+        };
+        let fetch = self.get_mempool_candidates(base_fee, limits.gas_limit);
+        let mempool = match tokio::time::timeout(MEMPOOL_FETCH_TIMEOUT, fetch).await {
+            Ok(mempool) => mempool,
+            Err(_) => {
+                warn!(
+                    timeout = ?MEMPOOL_FETCH_TIMEOUT,
+                    "Timed out fetching the mempool, building with the committed transactions only"
+                );
+                MempoolCandidates::default()
+            }
+        };
+        let transactions = select_transactions(committed, mempool, base_fee, limits);
+
+        let versioned_hashes = transactions
+            .iter()
+            .flat_map(|tx| tx.blob_versioned_hashes())
+            .flatten()
+            .collect::<Vec<_>>();
+        info!(amount = ?versioned_hashes.len(), "got versioned_hashes");
+
         let excess_blob_gas = calc_excess_blob_gas(
             latest_block.header.excess_blob_gas.unwrap_or_default(),
             latest_block.header.blob_gas_used.unwrap_or_default(),
@@ -149,7 +182,7 @@ impl FallbackPayloadBuilder {
         // We must calculate the next block timestamp manually rather than rely on the
         // previous execution block, to cover the edge case where any previous slots have
         // been missed by the proposers immediately before us.
-        let block_timestamp = self.genesis_time + (target_slot * self.slot_time);
+        let block_timestamp = self.genesis_time + (target_slot * self.chain.slot_time());
 
         let ctx = Context {
             base_fee,
@@ -159,14 +192,14 @@ impl FallbackPayloadBuilder {
             prev_randao,
             extra_data: self.extra_data.clone(),
             fee_recipient: self.fee_recipient,
-            transactions_root: proofs::calculate_transaction_root(transactions),
+            transactions_root: proofs::calculate_transaction_root(&transactions),
             withdrawals_root: proofs::calculate_withdrawals_root(&withdrawals),
             block_timestamp,
         };
 
         let body = BlockBody {
             ommers: Vec::new(),
-            transactions: transactions.to_vec(),
+            transactions,
             withdrawals: Some(Withdrawals::new(withdrawals)),
             requests: None,
         };
@@ -228,6 +261,49 @@ impl FallbackPayloadBuilder {
         }
     }
 
+    /// Fetch the best pending transactions of the mempool of the execution client, with the
+    /// account states of their senders, see [`mempool_candidates`]. The payload only holds the
+    /// committed transactions if they can't be fetched.
+    async fn get_mempool_candidates(&self, base_fee: u64, gas_limit: u64) -> MempoolCandidates {
+        let pending = match self.execution_rpc_client.get_pending_transactions().await {
+            Ok(pending) => pending,
+            Err(err) => {
+                warn!(
+                    ?err,
+                    "Failed to fetch the mempool, building with the committed transactions only"
+                );
+                return MempoolCandidates::default();
+            }
+        };
+
+        let recovered = pending.into_iter().filter_map(|tx| {
+            let sender = tx.from;
+            let encoded = TxEnvelope::try_from(tx).ok()?.encoded_2718();
+            let tx = TransactionSigned::decode_enveloped(&mut encoded.as_slice()).ok()?;
+            Some(TransactionSignedEcRecovered::from_signed_transaction(tx, sender))
+        });
+        let transactions = mempool_candidates(recovered.collect(), base_fee, gas_limit);
+
+        // The transactions are checked against the nonces and balances of their senders once
+        // the committed transactions are applied
+        let mut senders = transactions.iter().map(|tx| tx.signer()).collect::<Vec<_>>();
+        senders.sort_unstable();
+        senders.dedup();
+        let accounts = match self.execution_rpc_client.get_account_states(&senders).await {
+            Ok(accounts) => accounts,
+            Err(err) => {
+                warn!(
+                    ?err,
+                    "Failed to fetch the mempool senders, building with the committed \
+                     transactions only"
+                );
+                return MempoolCandidates::default();
+            }
+        };
+
+        MempoolCandidates { transactions, accounts }
+    }
+
     /// Fetch the previous RANDAO value from the beacon chain.
     ///
     /// NOTE: for some reason, using the ApiResult from `beacon_api_client` doesn't work, so
@@ -417,15 +493,16 @@ mod tests {
     use alloy::{
         eips::eip2718::Encodable2718,
         network::{EthereumWallet, TransactionBuilder},
-        primitives::{hex, Address},
+        primitives::{hex, Address, B256},
         signers::{k256::ecdsa::SigningKey, local::PrivateKeySigner},
     };
     use beacon_api_client::mainnet::Client as BeaconClient;
-    use reth_primitives::TransactionSigned;
+    use reth_primitives::{TransactionSigned, TransactionSignedEcRecovered};
     use tracing::warn;
 
     use crate::{
-        builder::payload_builder::FallbackPayloadBuilder,
+        builder::{compat, payload_builder::FallbackPayloadBuilder},
+        primitives::PayloadTransactions,
         test_util::{default_test_transaction, get_test_config},
     };
 
@@ -458,8 +535,21 @@ mod tests {
             (SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() / cfg.chain.slot_time()) +
             1;
 
-        let block = builder.build_fallback_payload(slot, &[tx_signed_reth]).await?;
-        assert_eq!(block.body.len(), 1);
+        let committed =
+            TransactionSignedEcRecovered::from_signed_transaction(tx_signed_reth.clone(), addy);
+        let block = builder.build_fallback_payload(slot, vec![vec![committed]]).await?;
+
+        // The committed transaction comes before the ones of the mempool
+        assert_eq!(block.body[0].hash(), tx_signed_reth.hash());
+
+        // The transaction is proven against the transactions root of the payload header
+        let encoded = block.body.iter().map(|tx| (tx.hash(), tx.envelope_encoded()));
+        let transactions = PayloadTransactions::new(block.hash(), encoded);
+        let header = compat::to_execution_payload_header(&block, block.body.clone());
+        let root = B256::from(header.transactions_root.0);
+        assert_eq!(transactions.transactions_root(), root);
+        let proof = transactions.prove(&[tx_signed_reth.hash()]).expect("included transaction");
+        assert!(proof.transactions[0].verify(&raw_encoded, root));
 
         Ok(())
     }
@@ -93,7 +93,6 @@ pub fn compute_signing_root(object_root: [u8; 32], signing_domain: [u8; 32]) ->
 /// builder-api messages in the out-of-protocol specifications
 ///
 /// Docs: <https://github.com/ethereum/builder-specs/blob/982af908707113de373e62babee113782e6bb6cd/specs/bellatrix/builder.md#signing>
-#[allow(dead_code)]
 pub fn compute_builder_domain(
     fork_version: [u8; 4],
     genesis_validators_root: Option<[u8; 32]>,
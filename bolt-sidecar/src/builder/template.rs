@@ -6,18 +6,19 @@
 
 use std::collections::HashMap;
 
-use alloy::primitives::{Address, U256};
+use alloy::primitives::{Address, B256, U256};
 use ethereum_consensus::{
     crypto::{KzgCommitment, KzgProof},
     deneb::mainnet::{Blob, BlobsBundle},
 };
-use reth_primitives::TransactionSigned;
+use reth_primitives::{TransactionSigned, TransactionSignedEcRecovered};
 use tracing::warn;
 
 use crate::{
     common::max_transaction_cost,
     primitives::{
-        constraint::Constraint, AccountState, FullTransaction, SignedConstraints, TransactionExt,
+        constraint::Constraint, AccountState, Exclusion, FullTransaction, SignedConstraints,
+        TransactionExt,
     },
 };
 
@@ -68,36 +69,71 @@ impl BlockTemplate {
             .collect()
     }
 
+    /// Returns the committed transactions with their senders, grouped in the bundles they must
+    /// be included as: the transactions of an atomic commitment together in their order, and
+    /// each of the others on its own.
+    pub fn committed_bundles(&self) -> Vec<Vec<TransactionSignedEcRecovered>> {
+        let recovered = |constraint: &Constraint| {
+            let tx = constraint.transaction.clone().into_inner().into_transaction();
+            TransactionSignedEcRecovered::from_signed_transaction(tx, constraint.sender())
+        };
+
+        let mut bundles = Vec::new();
+        for sc in &self.signed_constraints_list {
+            let transactions = sc.message.constraints.iter().map(recovered);
+            if sc.message.atomic {
+                bundles.push(transactions.collect());
+            } else {
+                bundles.extend(transactions.map(|tx| vec![tx]));
+            }
+        }
+        bundles
+    }
+
     /// Converts the list of signed constraints into a list of all blobs in all transactions
     /// in the constraints. Use this when building a local execution payload.
     #[inline]
     pub fn as_blobs_bundle(&self) -> BlobsBundle {
-        let (commitments, proofs, blobs) =
-            self.signed_constraints_list
-                .iter()
-                .flat_map(|sc| sc.message.constraints.iter())
-                .filter_map(|c| c.transaction.blob_sidecar())
-                .fold(
-                    (Vec::new(), Vec::new(), Vec::new()),
-                    |(mut commitments, mut proofs, mut blobs), bs| {
-                        commitments.extend(bs.commitments.iter().map(|c| {
-                            KzgCommitment::try_from(c.as_slice()).expect("both are 48 bytes")
-                        }));
-                        proofs.extend(
-                            bs.proofs.iter().map(|p| {
-                                KzgProof::try_from(p.as_slice()).expect("both are 48 bytes")
-                            }),
-                        );
-                        blobs.extend(bs.blobs.iter().map(|b| {
-                            Blob::try_from(b.as_slice()).expect("both are 131_072 bytes")
-                        }));
-                        (commitments, proofs, blobs)
-                    },
-                );
+        self.blobs_bundle_of(&self.as_signed_transactions())
+    }
+
+    /// Returns the blobs of the committed transactions among the given transactions of a
+    /// payload, in their order.
+    pub fn blobs_bundle_of(&self, transactions: &[TransactionSigned]) -> BlobsBundle {
+        let sidecars = self
+            .signed_constraints_list
+            .iter()
+            .flat_map(|sc| sc.message.constraints.iter())
+            .filter_map(|c| Some((*c.transaction.hash(), c.transaction.blob_sidecar()?)))
+            .collect::<HashMap<_, _>>();
+
+        let (mut commitments, mut proofs, mut blobs) = (Vec::new(), Vec::new(), Vec::new());
+        for bs in transactions.iter().filter_map(|tx| sidecars.get(&tx.hash())) {
+            commitments.extend(
+                bs.commitments
+                    .iter()
+                    .map(|c| KzgCommitment::try_from(c.as_slice()).expect("both are 48 bytes")),
+            );
+            proofs.extend(
+                bs.proofs
+                    .iter()
+                    .map(|p| KzgProof::try_from(p.as_slice()).expect("both are 48 bytes")),
+            );
+            blobs.extend(
+                bs.blobs
+                    .iter()
+                    .map(|b| Blob::try_from(b.as_slice()).expect("both are 131_072 bytes")),
+            );
+        }
 
         BlobsBundle { commitments, proofs, blobs }
     }
 
+    /// Returns the exclusions committed to in the block template.
+    pub fn exclusions(&self) -> impl Iterator<Item = &Exclusion> {
+        self.signed_constraints_list.iter().flat_map(|sc| sc.message.exclusions.iter())
+    }
+
     /// Returns the length of the transactions in the block template.
     #[inline]
     pub fn transactions_len(&self) -> usize {
@@ -112,6 +148,15 @@ impl BlockTemplate {
         })
     }
 
+    /// Returns the bytes the committed transactions take in the block, without their blob
+    /// sidecars.
+    #[inline]
+    pub fn committed_bytes(&self) -> usize {
+        self.signed_constraints_list.iter().fold(0, |acc, sc| {
+            acc + sc.message.constraints.iter().fold(0, |acc, c| acc + c.transaction.size())
+        })
+    }
+
     /// Returns the blob count of the block template.
     #[inline]
     pub fn blob_count(&self) -> usize {
@@ -145,8 +190,22 @@ impl BlockTemplate {
         self.signed_constraints_list.push(constraints);
     }
 
-    /// Remove all signed constraints at the specified index and updates the state diff
-    fn remove_constraints_at_index(&mut self, index: usize) {
+    /// Returns the signed constraints of the commitment with the given request digest.
+    pub fn commitment(&self, digest: &B256) -> Option<&SignedConstraints> {
+        self.signed_constraints_list.iter().find(|sc| sc.message.request_digest() == *digest)
+    }
+
+    /// Removes the signed constraints of the commitment with the given request digest, and
+    /// updates the state diff. Returns the removed constraints.
+    pub fn remove_commitment(&mut self, digest: &B256) -> Option<SignedConstraints> {
+        let list = &self.signed_constraints_list;
+        let index = list.iter().position(|sc| sc.message.request_digest() == *digest)?;
+        Some(self.remove_constraints_at_index(index))
+    }
+
+    /// Remove all signed constraints at the specified index and updates the state diff.
+    /// Returns the removed constraints.
+    fn remove_constraints_at_index(&mut self, index: usize) -> SignedConstraints {
         let constraints = self.signed_constraints_list.remove(index);
 
         for constraint in constraints.message.constraints.iter() {
@@ -158,6 +217,8 @@ impl BlockTemplate {
                     *balance -= max_transaction_cost(&constraint.transaction);
                 });
         }
+
+        constraints
     }
 
     /// Retain removes any transactions that conflict with the given account state.
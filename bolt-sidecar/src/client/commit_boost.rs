@@ -1,132 +1,368 @@
-use std::sync::Arc;
+//! A signer backend calling the signer module API of Commit-Boost over HTTP, so that the keys
+//! the sidecar signs with are held by the Commit-Boost signer rather than by the sidecar.
+//!
+//! The sidecar authenticates as a Commit-Boost module with its JWT. The constraints and the
+//! identity documents are signed with the first consensus key of the signer, and the
+//! commitments with its first ECDSA proxy key. A request failing on the transport, with a
+//! server error or with a rate limit is retried with an exponential backoff, after which the
+//! signer is reported as unavailable.
 
-use alloy::rpc::types::beacon::{BlsPublicKey, BlsSignature};
-use cb_common::pbs::{COMMIT_BOOST_API, PUBKEYS_PATH, SIGN_REQUEST_PATH};
-use cb_crypto::types::SignRequest;
-use ethereum_consensus::ssz::prelude::ssz_rs;
-use parking_lot::RwLock;
-use thiserror::Error;
-use tracing::{debug, error, info};
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
 
-use crate::crypto::bls::SignerBLSAsync;
+use alloy::primitives::{Address, Bytes, FixedBytes, Signature, B256};
+use eyre::eyre;
+use reqwest::{StatusCode, Url};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tracing::info;
 
-const SIGN_REQUEST_ID: &str = "bolt";
+use crate::{
+    crypto::{
+        backend::retry_remote,
+        bls::{BLSSig, BlsPublicKey},
+        SignerBackend, SignerError,
+    },
+    telemetry,
+};
 
-#[derive(Debug, Clone)]
-pub struct CommitBoostClient {
-    base_url: String,
+/// The path of the public keys of the signer module API.
+const GET_PUBKEYS_PATH: &str = "/signer/v1/get_pubkeys";
+
+/// The path of the signature requests of the signer module API.
+const REQUEST_SIGNATURE_PATH: &str = "/signer/v1/request_signature";
+
+/// Delay before the first retry of a request to the signer, doubled on each retry.
+const SIGNER_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// Timeout of each attempt of a request to the signer.
+const SIGNER_REQUEST_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The config of the Commit-Boost signer backend.
+#[derive(Clone)]
+pub struct CommitBoostConfig {
+    /// The URL of the signer module API.
+    pub url: Url,
+    /// The JWT of the sidecar as a Commit-Boost module.
+    pub jwt: String,
+}
+
+impl fmt::Debug for CommitBoostConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CommitBoostConfig").field("url", &self.url).finish_non_exhaustive()
+    }
+}
+
+/// The keys of the signer, by consensus key.
+#[derive(Debug, Deserialize)]
+struct PubkeysResponse {
+    keys: Vec<ConsensusProxyMap>,
+}
+
+/// A consensus key of the signer, with the proxy keys delegated by it.
+#[derive(Debug, Deserialize)]
+struct ConsensusProxyMap {
+    consensus: FixedBytes<48>,
+    #[serde(default)]
+    proxy_ecdsa: Vec<Address>,
+}
+
+/// A signature request of the signer module API, of the root of an object.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SignRequest {
+    Consensus { pubkey: FixedBytes<48>, object_root: B256 },
+    ProxyEcdsa { proxy: Address, object_root: B256 },
+}
+
+/// The signer module API of Commit-Boost.
+#[derive(Clone)]
+struct SignerApi {
+    url: Url,
+    jwt: String,
     client: reqwest::Client,
-    pubkeys: Arc<RwLock<Vec<BlsPublicKey>>>,
+    retry_delay: Duration,
 }
 
-#[derive(Debug, Error)]
-pub enum CommitBoostError {
-    #[error(transparent)]
-    Reqwest(#[from] reqwest::Error),
-    #[error(transparent)]
-    Deserialize(#[from] serde_json::Error),
-    #[error("failed to compute hash tree root for constraint {0:?}")]
-    HashTreeRoot(#[from] ssz_rs::MerkleizationError),
-    #[error("failed to sign constraint: {0}")]
-    NoSignature(String),
+impl SignerApi {
+    /// Calls the API on the path, with a GET request or a POST request of the body, retrying
+    /// while the signer is unavailable.
+    async fn call<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: Option<&SignRequest>,
+    ) -> Result<T, SignerError> {
+        let start = Instant::now();
+        let result = retry_remote("commit-boost", self.retry_delay, || self.attempt(path, body));
+        let result = result.await;
+        if body.is_some() {
+            let elapsed = start.elapsed().as_secs_f64();
+            metrics::histogram!(telemetry::REMOTE_SIGNING_LATENCY, "signer" => "commit-boost")
+                .record(elapsed);
+        }
+        result
+    }
+
+    async fn attempt<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: Option<&SignRequest>,
+    ) -> Result<T, SignerError> {
+        let unavailable = |message: String| SignerError::Unavailable { attempts: 1, message };
+
+        let url = self.url.join(path).map_err(|err| SignerError::Local(err.to_string()))?;
+        let request = match body {
+            Some(body) => self.client.post(url).json(body),
+            None => self.client.get(url),
+        };
+        let response = request.bearer_auth(&self.jwt).send().await;
+        let response = response.map_err(|err| unavailable(err.to_string()))?;
+
+        let status = response.status();
+        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(unavailable(format!("responded with status {status}")));
+        }
+        let text = response.text().await.map_err(|err| unavailable(err.to_string()))?;
+        if !status.is_success() {
+            return Err(SignerError::Rejected(format!("status {status}: {text}")));
+        }
+
+        serde_json::from_str(&text)
+            .map_err(|err| SignerError::Rejected(format!("invalid response: {err}")))
+    }
+}
+
+/// A signer backend with the keys held by the Commit-Boost signer.
+#[derive(Clone)]
+pub struct CommitBoostSigner {
+    api: SignerApi,
+    /// The consensus key the constraints are signed with.
+    consensus_key: FixedBytes<48>,
+    constraint_pubkey: BlsPublicKey,
+    /// The address of the ECDSA proxy key the commitments are signed with.
+    commitment_address: Address,
 }
 
-#[allow(unused)]
-impl CommitBoostClient {
-    /// Create a new [CommitBoostClient] instance
-    pub async fn new(base_url: impl Into<String>) -> Result<Self, CommitBoostError> {
-        let client = Self {
-            base_url: base_url.into(),
-            client: reqwest::Client::new(),
-            pubkeys: Arc::new(RwLock::new(Vec::new())),
+impl fmt::Debug for CommitBoostSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CommitBoostSigner")
+            .field("url", &self.api.url)
+            .field("consensus_key", &self.consensus_key)
+            .field("commitment_address", &self.commitment_address)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CommitBoostSigner {
+    /// Connects to the signer module API, and loads the keys the signer signs with.
+    pub async fn connect(config: CommitBoostConfig) -> eyre::Result<Self> {
+        let client = reqwest::Client::builder()
+            .user_agent("bolt-sidecar")
+            .timeout(SIGNER_REQUEST_TIMEOUT)
+            .build()?;
+        let CommitBoostConfig { url, jwt } = config;
+        let api = SignerApi { url, jwt, client, retry_delay: SIGNER_RETRY_DELAY };
+
+        let PubkeysResponse { keys } = api.call(GET_PUBKEYS_PATH, None).await?;
+        let Some(consensus_key) = keys.first().map(|keys| keys.consensus) else {
+            return Err(eyre!("The Commit-Boost signer has no consensus key"));
         };
+        let constraint_pubkey = BlsPublicKey::from_bytes(consensus_key.as_slice())
+            .map_err(|err| eyre!("Invalid consensus key {consensus_key}: {err:?}"))?;
+        let proxy = keys.iter().find_map(|keys| keys.proxy_ecdsa.first().copied());
+        let Some(commitment_address) = proxy else {
+            return Err(eyre!("The Commit-Boost signer has no ECDSA proxy key for commitments"));
+        };
+
+        let url = &api.url;
+        info!(%url, %consensus_key, %commitment_address, "Using the Commit-Boost signer");
+        Ok(Self { api, consensus_key, constraint_pubkey, commitment_address })
+    }
+
+    /// Sets the delay before the first retry of a request to the signer.
+    pub fn with_retry_delay(self, retry_delay: Duration) -> Self {
+        Self { api: SignerApi { retry_delay, ..self.api }, ..self }
+    }
+}
+
+#[async_trait::async_trait]
+impl SignerBackend for CommitBoostSigner {
+    fn commitment_address(&self) -> Address {
+        self.commitment_address
+    }
+
+    fn constraint_pubkey(&self) -> BlsPublicKey {
+        self.constraint_pubkey
+    }
 
-        let mut this = client.clone();
-        tokio::spawn(async move {
-            this.load_pubkeys().await.expect("failed to load pubkeys");
-        });
+    async fn sign_commitment(&self, digest: &B256) -> Result<Signature, SignerError> {
+        let (proxy, object_root) = (self.commitment_address, *digest);
+        let request = SignRequest::ProxyEcdsa { proxy, object_root };
+        let signature: Bytes = self.api.call(REQUEST_SIGNATURE_PATH, Some(&request)).await?;
+        Signature::try_from(signature.as_ref())
+            .map_err(|err| SignerError::Rejected(format!("invalid signature: {err}")))
+    }
 
-        Ok(client)
+    async fn sign_bls(&self, data: &[u8]) -> Result<BLSSig, SignerError> {
+        let Ok(object_root) = B256::try_from(data) else {
+            let message = format!("expected a 32-byte root to sign, got {} bytes", data.len());
+            return Err(SignerError::Local(message));
+        };
+        let request = SignRequest::Consensus { pubkey: self.consensus_key, object_root };
+        self.api.call(REQUEST_SIGNATURE_PATH, Some(&request)).await
     }
+}
 
-    /// Load public keys from the remote commit-boost client and store them locally
-    async fn load_pubkeys(&mut self) -> Result<(), CommitBoostError> {
-        loop {
-            let url = self.url_from_path(PUBKEYS_PATH);
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
 
-            info!(url, "Loading public keys from commit-boost");
+    use alloy::signers::{k256::SecretKey as K256SecretKey, local::PrivateKeySigner, Signer};
+    use axum::{
+        extract::State,
+        http::HeaderMap,
+        routing::{get, post},
+        Json, Router,
+    };
+    use parking_lot::Mutex;
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
 
-            let response = match self.client.get(url).send().await {
-                Ok(res) => res,
-                Err(e) => {
-                    error!(err = ?e, "failed to get public keys from commit-boost, retrying...");
-                    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-                    continue;
-                }
-            };
+    use super::*;
+    use crate::{
+        crypto::{
+            backend::MAX_REMOTE_ATTEMPTS,
+            bls::{Signer as BlsSigner, SignerBLS},
+        },
+        test_util::{
+            create_signed_commitment_request, default_test_transaction, test_bls_secret_key,
+        },
+    };
 
-            let status = response.status();
-            let response_bytes = response.bytes().await?;
+    const TEST_JWT: &str = "bolt-module-jwt";
 
-            if !status.is_success() {
-                let err = String::from_utf8_lossy(&response_bytes).into_owned();
-                error!(err, ?status, "failed to get public keys, retrying...");
-                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-                continue;
-            }
+    /// A Commit-Boost signer, recording the signature requests with their authorization, and
+    /// failing the given number of them as unavailable first.
+    #[derive(Clone)]
+    struct MockSigner {
+        consensus: BlsSigner,
+        proxy: PrivateKeySigner,
+        requests: Arc<Mutex<Vec<(String, Value)>>>,
+        failures: Arc<AtomicU32>,
+    }
 
-            let pubkeys: Vec<BlsPublicKey> = serde_json::from_slice(&response_bytes)?;
+    async fn get_pubkeys(
+        State(mock): State<MockSigner>,
+        headers: HeaderMap,
+    ) -> (StatusCode, Json<Value>) {
+        if headers["authorization"] != format!("Bearer {TEST_JWT}") {
+            return (StatusCode::UNAUTHORIZED, Json(json!({ "message": "invalid jwt" })));
+        }
+        let consensus = format!("0x{}", hex::encode(mock.consensus.pubkey().to_bytes()));
+        let proxy_ecdsa = [mock.proxy.address()];
+        let keys = json!([{ "consensus": consensus, "proxy_bls": [], "proxy_ecdsa": proxy_ecdsa }]);
+        (StatusCode::OK, Json(json!({ "keys": keys })))
+    }
 
-            {
-                let mut pk = self.pubkeys.write();
-                *pk = pubkeys;
-                return Ok(());
-            } // drop write lock
+    async fn request_signature(
+        State(mock): State<MockSigner>,
+        headers: HeaderMap,
+        Json(request): Json<Value>,
+    ) -> (StatusCode, Json<Value>) {
+        let authorization = headers["authorization"].to_str().unwrap().to_string();
+        mock.requests.lock().push((authorization, request.clone()));
+        let failures = &mock.failures;
+        if failures.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1)).is_ok() {
+            return (StatusCode::SERVICE_UNAVAILABLE, Json(json!({ "message": "unavailable" })));
         }
+
+        let root: B256 = request["object_root"].as_str().unwrap().parse().unwrap();
+        let signature = match request["type"].as_str() {
+            Some("consensus") => mock.consensus.sign(root.as_slice()).unwrap().to_vec(),
+            Some("proxy_ecdsa") => mock.proxy.sign_hash(&root).await.unwrap().as_bytes().to_vec(),
+            _ => return (StatusCode::BAD_REQUEST, Json(json!({ "message": "unknown type" }))),
+        };
+        (StatusCode::OK, Json(json!(format!("0x{}", hex::encode(signature)))))
     }
 
-    #[inline]
-    fn url_from_path(&self, path: &str) -> String {
-        format!("{}{COMMIT_BOOST_API}{path}", self.base_url)
+    async fn mock_signer(failures: u32) -> (CommitBoostConfig, MockSigner) {
+        let mock = MockSigner {
+            consensus: BlsSigner::new(test_bls_secret_key()),
+            proxy: PrivateKeySigner::random(),
+            requests: Arc::default(),
+            failures: Arc::new(AtomicU32::new(failures)),
+        };
+        let router = Router::new()
+            .route(GET_PUBKEYS_PATH, get(get_pubkeys))
+            .route(REQUEST_SIGNATURE_PATH, post(request_signature))
+            .with_state(mock.clone());
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        (CommitBoostConfig { url, jwt: TEST_JWT.to_string() }, mock)
     }
-}
 
-#[async_trait::async_trait]
-impl SignerBLSAsync for CommitBoostClient {
-    async fn sign(&self, data: &[u8]) -> eyre::Result<BlsSignature> {
-        let root = if data.len() == 32 {
-            let mut root = [0u8; 32];
-            root.copy_from_slice(data);
-            Ok(root)
-        } else {
-            Err(CommitBoostError::NoSignature(format!(
-                "invalid data length. Expected 32 bytes, found {} bytes",
-                data.len()
-            )))
-        }?;
-
-        let request = SignRequest::builder(
-            SIGN_REQUEST_ID,
-            *self.pubkeys.read().first().expect("pubkeys loaded"),
-        )
-        .with_root(root);
-
-        let url = self.url_from_path(SIGN_REQUEST_PATH);
-
-        debug!(url, ?request, "Requesting signature from commit_boost");
-
-        let response = reqwest::Client::new().post(url).json(&request).send().await?;
+    #[tokio::test]
+    async fn test_commit_with_commit_boost_signer() -> eyre::Result<()> {
+        let (config, mock) = mock_signer(1).await;
+        let signer = CommitBoostSigner::connect(config).await?.with_retry_delay(Duration::ZERO);
+        assert_eq!(signer.commitment_address(), mock.proxy.address());
+        assert_eq!(signer.constraint_pubkey(), mock.consensus.pubkey());
 
-        let status = response.status();
-        let response_bytes = response.bytes().await?;
+        let sk = K256SecretKey::random(&mut rand::thread_rng());
+        let tx = default_test_transaction(PrivateKeySigner::from(sk.clone()).address(), None);
+        let request = create_signed_commitment_request(&[tx], &sk, 10).await?;
+        let digest = request.digest();
+        let commitment = request.commit_with_backend(&signer).await?;
 
-        if !status.is_success() {
-            let err = String::from_utf8_lossy(&response_bytes).into_owned();
-            error!(err, "failed to get signature");
-            return Err(eyre::eyre!(CommitBoostError::NoSignature(err)));
+        // The request was retried once, with the module JWT and the digest as object root
+        let requests = mock.requests.lock().clone();
+        assert_eq!(requests.len(), 2);
+        let proxy = mock.proxy.address();
+        let expected = json!({ "type": "proxy_ecdsa", "proxy": proxy, "object_root": digest });
+        for (authorization, body) in &requests {
+            assert_eq!(authorization, &format!("Bearer {TEST_JWT}"));
+            assert_eq!(body, &expected);
         }
 
-        let sig = serde_json::from_slice(&response_bytes)?;
-        Ok(sig)
+        // The commitment carries the signature of the proxy key returned by the signer
+        let signature = mock.proxy.sign_hash(&digest).await?;
+        assert_eq!(commitment.signature().as_bytes(), signature.as_bytes());
+        assert_eq!(signature.recover_address_from_prehash(&digest)?, signer.commitment_address());
+
+        // The constraints are signed with the consensus key
+        let root = B256::repeat_byte(7);
+        let constraints_signature = signer.sign_bls(root.as_slice()).await?;
+        assert_eq!(constraints_signature, mock.consensus.sign(root.as_slice())?);
+        let consensus = format!("0x{}", hex::encode(mock.consensus.pubkey().to_bytes()));
+        let body = &mock.requests.lock()[2].1;
+        assert_eq!(body, &json!({ "type": "consensus", "pubkey": consensus, "object_root": root }));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_commit_boost_signer_unavailable() -> eyre::Result<()> {
+        let (config, mock) = mock_signer(u32::MAX).await;
+        let signer = CommitBoostSigner::connect(config.clone()).await?;
+        let signer = signer.with_retry_delay(Duration::ZERO);
+
+        let err = signer.sign_commitment(&B256::ZERO).await.unwrap_err();
+        let attempts = MAX_REMOTE_ATTEMPTS;
+        assert!(matches!(err, SignerError::Unavailable { attempts: n, .. } if n == attempts));
+        assert!(err.is_remote());
+        assert_eq!(mock.requests.lock().len(), MAX_REMOTE_ATTEMPTS as usize);
+
+        // A wrong JWT is rejected without retries
+        let config = CommitBoostConfig { jwt: "wrong".to_string(), ..config };
+        let err = CommitBoostSigner::connect(config).await.unwrap_err();
+        assert!(err.to_string().contains("rejected"), "{err}");
+
+        Ok(())
     }
 }
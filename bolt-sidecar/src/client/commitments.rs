@@ -0,0 +1,637 @@
+use std::{
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy::primitives::{Address, Signature, B256, U256};
+use reqwest::header::HeaderMap;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{
+    api::commitments::{
+        signing::{response_digest, RESPONSE_SIGNATURE_HEADER, RESPONSE_SIGNING_CAPABILITY},
+        spec::VersionInfo,
+    },
+    primitives::{
+        commitment::InclusionCommitment,
+        identity::{IdentityError, SignedIdentity},
+        BlsPublicKey,
+    },
+    state::consensus::ProcessingStage,
+};
+
+/// A typed error returned by the commitments API, decoded from the JSON-RPC error code,
+/// the `reason` sub-code and the structured fields of the error data.
+///
+/// Errors that this version of the client doesn't know about are returned as
+/// [`ClientError::Unknown`], so that newer servers can add errors without breaking it.
+#[derive(Debug, Clone, PartialEq, Error)]
+#[allow(missing_docs)]
+pub enum ClientError {
+    /// The request failed validation, as reported by sidecars older than the typed
+    /// rejection reasons.
+    #[error("Request rejected: {message}")]
+    ValidationFailed { message: String },
+    /// The params of the request are missing or invalid.
+    #[error("Invalid params ({reason}): {message}")]
+    InvalidParams { reason: String, message: String },
+    /// The request was rejected by a method of the admin API.
+    #[error("Request rejected ({reason}): {message}")]
+    Rejected { reason: String, message: String },
+    /// The request signer is not in the whitelist of the sidecar.
+    #[error("Signer {signer} is not whitelisted")]
+    SignerNotWhitelisted { signer: Address },
+    /// The whitelist entry of the request signer expired, at the given UNIX timestamp.
+    #[error("Authorization of signer {signer} expired at {expired_at}")]
+    AuthorizationExpired { signer: Address, expired_at: u64 },
+    /// The same request was already received.
+    #[error("Duplicate request")]
+    Duplicate,
+    /// The sidecar failed to process the request.
+    #[error("Internal server error: {message}")]
+    Internal { message: String },
+    /// The authentication of the request failed.
+    #[error("Unauthorized: {reason}")]
+    Unauthorized { reason: String },
+    /// The sidecar failed to reach the beacon node.
+    #[error("Beacon API error: {message}")]
+    BeaconApi { message: String },
+    /// The target slot is invalid.
+    #[error("Invalid slot {slot}")]
+    InvalidSlot { slot: u64 },
+    /// The commitment deadline of the target slot has passed, `deadline_ms` into the slot
+    /// before it.
+    #[error("The deadline of slot {slot} passed, {deadline_ms} ms into the previous slot")]
+    DeadlineExceeded { slot: u64, deadline_ms: u64 },
+    /// The commitment deadline of the target slot passed while the request was processed.
+    #[error("The commitment deadline of slot {slot} passed during {stage}")]
+    DeadlinePassed { slot: u64, stage: ProcessingStage, retarget_slot: Option<u64> },
+    /// No validator of the sidecar proposes the target slot.
+    #[error("Validator not found in the slot")]
+    ValidatorNotFound,
+    /// The validator of the target slot is leased by another sidecar instance.
+    #[error("The validator of slot {slot} is leased by another sidecar instance")]
+    Leased { slot: u64 },
+    /// The target slot is not assigned to the validators of the sidecar.
+    #[error("Slot {slot} is not assigned to this sidecar's validators")]
+    NotOurSlot { slot: u64, our_next_slots: Vec<u64>, registry_hint_url: Option<String> },
+    /// The proposer of the target slot is in a maintenance window, and won't propose it.
+    #[error("The proposer of slot {slot} is unavailable")]
+    ProposerUnavailable { slot: u64, our_next_slots: Vec<u64> },
+    /// The slot range of the request ends before it starts.
+    #[error("Invalid slot range from {slot} to {max_slot}")]
+    InvalidSlotRange { slot: u64, max_slot: u64 },
+    /// The slot range of the request has already passed.
+    #[error("The slot range ending at slot {max_slot} has already passed")]
+    SlotRangePassed { max_slot: u64 },
+    /// No slot of the range of the request is proposed by the validators of the sidecar.
+    #[error("No slot from {slot} to {max_slot} is proposed by this sidecar's validators")]
+    NoProposerInRange { slot: u64, max_slot: u64, our_next_slots: Vec<u64> },
+    /// The fee of the request doesn't cover the base fee projected for the target slot, with
+    /// the projection before the safety margin of the sidecar, if it reports it.
+    #[error("Fee too low, at least {required} wei per gas is required")]
+    FeeTooLow { required: u128, projected: Option<u128> },
+    /// The blob fee of the request doesn't cover the maximum blob base fee of the target slot.
+    #[error("Blob fee too low, at least {required} wei per blob gas is required")]
+    BlobFeeTooLow { required: u128 },
+    /// A transaction of the request tips less than the minimum priority fee of the sidecar.
+    #[error("Priority fee too low, at least {required} wei per gas is required")]
+    PriorityFeeTooLow { required: u128 },
+    /// The transaction nonce is lower than the account nonce.
+    #[error("Nonce too low, expected {expected}, got {got}")]
+    NonceTooLow { expected: u64, got: u64 },
+    /// The transaction nonce is higher than the account nonce.
+    #[error("Nonce too high, expected {expected}, got {got}")]
+    NonceTooHigh { expected: u64, got: u64 },
+    /// The transaction nonce is taken by a commitment that must be cancelled to replace it.
+    #[error("Nonce {nonce} already committed to")]
+    NonceAlreadyCommitted { nonce: u64 },
+    /// The sender can't pay for the value and maximum fee of the transaction, with the balance
+    /// reserved by its earlier commitments and the earlier transactions of the request, if the
+    /// sidecar reports it.
+    #[error("Insufficient balance")]
+    InsufficientBalance { reserved: Option<U256> },
+    /// The sender already has a commitment for a later slot.
+    #[error("Slot too low, the target slot must be at least {min_slot}")]
+    SlotTooLow { min_slot: u64 },
+    /// The maximum number of commitments has been reached for the slot.
+    #[error("Max commitments reached for slot {slot}: {max}")]
+    MaxCommitmentsReached { slot: u64, max: u64 },
+    /// The maximum committed gas has been reached for the slot.
+    #[error("Max committed gas reached for slot {slot}: {max}")]
+    MaxCommittedGasReached { slot: u64, max: u64 },
+    /// The transactions of the request exceed the remaining byte budget of the slot.
+    #[error("Byte budget exceeded for slot {slot}: requested {requested}, remaining {remaining}")]
+    ByteBudgetExceeded { slot: u64, requested: usize, remaining: usize },
+    /// The blobs of the request exceed the remaining blob budget of the slot.
+    #[error("Blob budget exceeded for slot {slot}: requested {requested}, remaining {remaining}")]
+    BlobBudgetExceeded { slot: u64, requested: usize, remaining: usize },
+    /// The transactions of the request don't fit in the memory left for accepted requests.
+    #[error("Insufficient queue memory: requested {requested} bytes, {available} available")]
+    InsufficientQueueMemory { requested: usize, available: usize },
+    /// The context of the request is too long or has non-printable characters.
+    #[error("Invalid request context: at most {max_len} printable bytes are allowed")]
+    InvalidContext { max_len: usize },
+    /// A transaction of the request is invalid.
+    #[error("Invalid transaction ({reason}): {message}")]
+    InvalidTransaction { reason: String, message: String },
+    /// A transaction of an atomic request is invalid, with the reason of its error as cause.
+    #[error("Invalid transaction {index} of the bundle ({cause}): {message}")]
+    InvalidBundleTransaction { index: usize, cause: String, message: String, retryable: bool },
+    /// The exclusion request excludes no senders and transactions, or too many.
+    #[error("Invalid exclusion: between 1 and {max} senders and transactions can be excluded")]
+    InvalidExclusion { max: usize },
+    /// The request conflicts with a commitment of the sidecar for the slot.
+    #[error("Transaction {tx_hash} conflicts with a commitment for slot {slot}")]
+    ExclusionConflict { slot: u64, tx_hash: B256 },
+    /// Importing an externally signed commitment failed.
+    #[error("Import failed ({reason}): {message}")]
+    ImportFailed { reason: String, message: String },
+    /// Cancelling a commitment failed.
+    #[error("Cancellation failed ({reason}): {message}")]
+    CancelFailed { reason: String, message: String },
+    /// The sidecar is still warming up.
+    #[error("Sidecar is not ready yet")]
+    NotReady,
+    /// The chain heads observed by the sidecar are stale.
+    #[error("Sidecar is not synced with the chain head")]
+    NotSynced,
+    /// The sidecar can't record commitments in its store.
+    #[error("Persistence unavailable")]
+    PersistenceUnavailable,
+    /// The commitment service of the sidecar is unavailable, e.g. while it shuts down.
+    #[error("Service unavailable")]
+    ServiceUnavailable,
+    /// The sidecar didn't produce the commitment in time. It may still be committed to.
+    #[error("Timed out after {elapsed_ms}ms")]
+    Timeout { elapsed_ms: u64 },
+    /// The signer sent too many requests, and can send another one after the given delay.
+    #[error("Rate limited, retry after {retry_after_ms}ms")]
+    RateLimited { retry_after_ms: u64 },
+    /// The sidecar has too many requests in flight, and may accept one after the given delay.
+    #[error("Sidecar overloaded, retry after {retry_after_ms}ms")]
+    Overloaded { retry_after_ms: u64 },
+    /// The sidecar is shutting down, and doesn't accept new requests.
+    #[error("Sidecar shutting down")]
+    ShuttingDown,
+    /// The scheme of the signature header isn't supported by the sidecar or the method.
+    #[error("Unsupported signature scheme '{scheme}'")]
+    UnsupportedSignatureScheme { scheme: String },
+    /// The smart-contract wallet rejected the signature, or the sidecar couldn't ask it.
+    /// Only the `rpc_unavailable` failures are on the side of the sidecar.
+    #[error("Contract signature check failed ({reason}): {message}")]
+    ContractSignatureFailed { reason: String, message: String },
+    /// The replay protection of the sidecar rejected the signature: it has no timestamp, its
+    /// timestamp is outside of the window, or it was already used.
+    #[error("Replay protection rejected the request ({reason}): {message}")]
+    ReplayRejected { reason: String, message: String },
+    /// The sidecar requires a valid JWT bearer token, which the request didn't carry.
+    #[error("Unauthorized ({reason}): {message}")]
+    Unauthorized { reason: String, message: String },
+    /// The request would exceed the quota of its signer given by the reason, which is
+    /// available again from the `resets_at_slot` slot.
+    #[error("Signer quota {reason} of {limit} exceeded, resets at slot {resets_at_slot}")]
+    QuotaExceeded { reason: String, limit: u64, used: u64, requested: u64, resets_at_slot: u64 },
+    /// The remote signer of the sidecar failed to sign the commitment.
+    #[error("Remote signer unavailable")]
+    SignerUnavailable,
+    /// The request is not a valid JSON-RPC request.
+    #[error("Invalid request: {message}")]
+    InvalidRequest { message: String },
+    /// The method is unknown or not exposed by the listener.
+    #[error("Unknown method")]
+    UnknownMethod,
+    /// An error that this version of the client doesn't know about.
+    #[error("Error {code}: {message}")]
+    Unknown { code: i32, message: String, data: Option<Value> },
+}
+
+/// Reasons of the `-32006` validation errors that are reported as
+/// [`ClientError::InvalidTransaction`].
+const INVALID_TRANSACTION_REASONS: &[&str] = &[
+    "blob_validation",
+    "max_base_fee_calc_overflow",
+    "account_has_code",
+    "gas_limit_too_high",
+    "transaction_size_too_high",
+    "max_priority_fee_per_gas_too_high",
+    "eip4844_limit",
+    "invalid_transaction_signature",
+    "recover_signer",
+    "chain_id_mismatch",
+    "transaction_type_not_active",
+];
+
+/// Reasons of the `-32000` rejections of the admin methods, reported as
+/// [`ClientError::Rejected`].
+const REJECTION_REASONS: &[&str] = &[
+    "not_enabled",
+    "listener_not_enabled",
+    "bind_failed",
+    "no_capture_directory",
+    "capture_size_cap_reached",
+    "capture_failed",
+    "certificate_reload_failed",
+    "commitment_not_found",
+    "already_dropped",
+    "not_dropped",
+    "drop_already_confirmed",
+    "slot_passed",
+    "unknown_maintenance_window",
+];
+
+/// Reasons of the `-32010` import errors.
+const IMPORT_REASONS: &[&str] =
+    &["not_enabled", "unknown_digest", "signer_mismatch", "invalid_signature"];
+
+/// Reasons of the `-32015` cancellation errors.
+const CANCEL_REASONS: &[&str] = &["not_enabled", "unknown_commitment", "signer_mismatch"];
+
+/// Reasons of the `-32020` contract signature errors.
+const CONTRACT_SIGNATURE_REASONS: &[&str] =
+    &["contract_reverted", "invalid_magic_value", "rpc_unavailable"];
+
+/// Reasons of the `-32021` replay protection errors.
+const REPLAY_REASONS: &[&str] = &["missing_timestamp", "timestamp_expired", "replayed"];
+
+/// Reasons of the `-32022` JWT authentication errors.
+const UNAUTHORIZED_REASONS: &[&str] = &["missing_token", "expired_token", "invalid_token"];
+
+/// Reasons of the `-32023` signer quota errors.
+const QUOTA_REASONS: &[&str] = &["gas_per_slot", "txs_per_slot", "commitments_per_epoch"];
+
+impl ClientError {
+    /// Decodes a JSON-RPC error returned by the commitments API.
+    pub fn from_rpc(code: i32, message: String, data: Option<Value>) -> Self {
+        match Self::decode(code, &message, data.as_ref()) {
+            Some(err) => err,
+            None => Self::Unknown { code, message, data },
+        }
+    }
+
+    fn decode(code: i32, message: &str, data: Option<&Value>) -> Option<Self> {
+        let reason = data.and_then(|data| data.get("reason")).and_then(Value::as_str);
+        let message = message.to_string();
+
+        let err = match (code, reason) {
+            (-32000, Some("validation_failed")) => Self::ValidationFailed { message },
+            (-32000, Some("signer_not_whitelisted")) => {
+                Self::SignerNotWhitelisted { signer: field(data, "signer")? }
+            }
+            (-32000, Some("authorization_expired")) => Self::AuthorizationExpired {
+                signer: field(data, "signer")?,
+                expired_at: field(data, "expired_at")?,
+            },
+            (-32000, Some(reason)) if REJECTION_REASONS.contains(&reason) => {
+                Self::Rejected { reason: reason.to_string(), message }
+            }
+            (-32001, _) => Self::Duplicate,
+            (-32002, _) | (-32006, Some("internal")) => Self::Internal { message },
+            (-32003 | -32004 | -32005 | -32007, _) => Self::Unauthorized { reason: message },
+            (-32006, Some("beacon_api_error")) => Self::BeaconApi { message },
+            (-32006, Some("invalid_slot")) => Self::InvalidSlot { slot: field(data, "slot")? },
+            (-32006, Some("deadline_exceeded")) => Self::DeadlineExceeded {
+                slot: field(data, "slot")?,
+                deadline_ms: field(data, "deadline_ms")?,
+            },
+            (-32006, Some("deadline_passed_during_processing")) => Self::DeadlinePassed {
+                slot: field(data, "slot")?,
+                stage: field(data, "stage")?,
+                retarget_slot: field(data, "retarget_slot")?,
+            },
+            (-32006, Some("validator_not_found")) => Self::ValidatorNotFound,
+            (-32006, Some("lease_not_held")) => Self::Leased { slot: field(data, "slot")? },
+            (-32006, Some("proposer_unavailable")) => Self::ProposerUnavailable {
+                slot: field(data, "slot")?,
+                our_next_slots: field(data, "our_next_slots")?,
+            },
+            (-32006, Some("invalid_slot_range")) => Self::InvalidSlotRange {
+                slot: field(data, "slot")?,
+                max_slot: field(data, "max_slot")?,
+            },
+            (-32006, Some("slot_range_passed")) => {
+                Self::SlotRangePassed { max_slot: field(data, "max_slot")? }
+            }
+            (-32006, Some("no_proposer_in_range")) => Self::NoProposerInRange {
+                slot: field(data, "slot")?,
+                max_slot: field(data, "max_slot")?,
+                our_next_slots: field(data, "our_next_slots")?,
+            },
+            (-32006, Some("base_fee_too_low")) => Self::FeeTooLow {
+                required: field(data, "required")?,
+                projected: field(data, "projected"),
+            },
+            (-32006, Some("blob_base_fee_too_low")) => {
+                Self::BlobFeeTooLow { required: field(data, "required")? }
+            }
+            (-32006, Some("priority_fee_too_low")) => {
+                Self::PriorityFeeTooLow { required: field(data, "required")? }
+            }
+            (-32006, Some("nonce_too_low")) => {
+                Self::NonceTooLow { expected: field(data, "expected")?, got: field(data, "got")? }
+            }
+            (-32006, Some("nonce_too_high")) => {
+                Self::NonceTooHigh { expected: field(data, "expected")?, got: field(data, "got")? }
+            }
+            (-32006, Some("nonce_already_committed")) => {
+                Self::NonceAlreadyCommitted { nonce: field(data, "nonce")? }
+            }
+            (-32006, Some("insufficient_balance")) => {
+                Self::InsufficientBalance { reserved: field(data, "reserved") }
+            }
+            (-32006, Some("slot_too_low")) => {
+                Self::SlotTooLow { min_slot: field(data, "min_slot")? }
+            }
+            (-32006, Some("max_commitments_reached")) => {
+                Self::MaxCommitmentsReached { slot: field(data, "slot")?, max: field(data, "max")? }
+            }
+            (-32006, Some("max_committed_gas_reached")) => Self::MaxCommittedGasReached {
+                slot: field(data, "slot")?,
+                max: field(data, "max")?,
+            },
+            (-32006, Some("byte_budget_exceeded")) => Self::ByteBudgetExceeded {
+                slot: field(data, "slot")?,
+                requested: field(data, "requested")?,
+                remaining: field(data, "remaining")?,
+            },
+            (-32006, Some("blob_budget_exceeded")) => Self::BlobBudgetExceeded {
+                slot: field(data, "slot")?,
+                requested: field(data, "requested")?,
+                remaining: field(data, "remaining")?,
+            },
+            (-32006, Some("insufficient_queue_memory")) => Self::InsufficientQueueMemory {
+                requested: field(data, "requested")?,
+                available: field(data, "available")?,
+            },
+            (-32006, Some("invalid_context")) => {
+                Self::InvalidContext { max_len: field(data, "max_len")? }
+            }
+            (-32006, Some("invalid_bundle_transaction")) => Self::InvalidBundleTransaction {
+                index: field(data, "index")?,
+                cause: field(data, "cause")?,
+                message,
+                retryable: field(data, "retryable")?,
+            },
+            (-32006, Some("invalid_exclusion")) => {
+                Self::InvalidExclusion { max: field(data, "max")? }
+            }
+            (-32006, Some("exclusion_conflict")) => Self::ExclusionConflict {
+                slot: field(data, "slot")?,
+                tx_hash: field(data, "tx_hash")?,
+            },
+            (-32006, Some(reason)) if INVALID_TRANSACTION_REASONS.contains(&reason) => {
+                Self::InvalidTransaction { reason: reason.to_string(), message }
+            }
+            (-32008, _) => Self::NotOurSlot {
+                slot: field(data, "slot")?,
+                our_next_slots: field(data, "our_next_slots")?,
+                registry_hint_url: field(data, "registry_hint_url")?,
+            },
+            (-32009, _) => Self::NotReady,
+            (-32011, _) => Self::NotSynced,
+            (-32012, _) => Self::PersistenceUnavailable,
+            (-32013, _) => Self::Timeout { elapsed_ms: field(data, "elapsed_ms")? },
+            (-32014, _) => Self::ServiceUnavailable,
+            (-32016, _) => Self::RateLimited { retry_after_ms: field(data, "retry_after_ms")? },
+            (-32017, _) => Self::Overloaded { retry_after_ms: field(data, "retry_after_ms")? },
+            (-32018, _) => Self::ShuttingDown,
+            (-32019, _) => Self::UnsupportedSignatureScheme { scheme: field(data, "scheme")? },
+            (-32010, Some(reason)) if IMPORT_REASONS.contains(&reason) => {
+                Self::ImportFailed { reason: reason.to_string(), message }
+            }
+            (-32015, Some(reason)) if CANCEL_REASONS.contains(&reason) => {
+                Self::CancelFailed { reason: reason.to_string(), message }
+            }
+            (-32020, Some(reason)) if CONTRACT_SIGNATURE_REASONS.contains(&reason) => {
+                Self::ContractSignatureFailed { reason: reason.to_string(), message }
+            }
+            (-32021, Some(reason)) if REPLAY_REASONS.contains(&reason) => {
+                Self::ReplayRejected { reason: reason.to_string(), message }
+            }
+            (-32022, Some(reason)) if UNAUTHORIZED_REASONS.contains(&reason) => {
+                Self::Unauthorized { reason: reason.to_string(), message }
+            }
+            (-32023, Some(reason)) if QUOTA_REASONS.contains(&reason) => Self::QuotaExceeded {
+                reason: reason.to_string(),
+                limit: field(data, "limit")?,
+                used: field(data, "used")?,
+                requested: field(data, "requested")?,
+                resets_at_slot: field(data, "resets_at_slot")?,
+            },
+            (-32024, _) => Self::SignerUnavailable,
+            (-32600, _) => Self::InvalidRequest { message },
+            (-32601, _) => Self::UnknownMethod,
+            (-32602, Some(reason @ ("missing_params" | "invalid_params"))) => {
+                Self::InvalidParams { reason: reason.to_string(), message }
+            }
+            _ => return None,
+        };
+
+        Some(err)
+    }
+
+    /// Returns whether resubmitting the identical request later may succeed.
+    /// Mirrors the retry policy of the server.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Internal { .. } |
+            Self::BeaconApi { .. } |
+            Self::Leased { .. } |
+            Self::NonceTooHigh { .. } |
+            Self::InsufficientBalance { .. } |
+            Self::InsufficientQueueMemory { .. } |
+            Self::NotReady |
+            Self::NotSynced |
+            Self::PersistenceUnavailable |
+            Self::ServiceUnavailable |
+            Self::Timeout { .. } |
+            Self::RateLimited { .. } |
+            Self::Overloaded { .. } |
+            Self::ShuttingDown |
+            Self::SignerUnavailable => true,
+            Self::ContractSignatureFailed { reason, .. } => reason == "rpc_unavailable",
+            Self::InvalidBundleTransaction { retryable, .. } => *retryable,
+            Self::Unknown { data, .. } => data
+                .as_ref()
+                .and_then(|data| data.get("retryable"))
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
+
+    /// Returns the fee per gas (or per blob gas) the request needs to pay to be accepted,
+    /// if it was rejected because its fee was too low.
+    pub fn required_fee(&self) -> Option<u128> {
+        match self {
+            Self::FeeTooLow { required, .. } | Self::BlobFeeTooLow { required } => Some(*required),
+            _ => None,
+        }
+    }
+}
+
+/// Deserializes a field of the error data.
+fn field<T: DeserializeOwned>(data: Option<&Value>, name: &str) -> Option<T> {
+    serde_json::from_value(data?.get(name)?.clone()).ok()
+}
+
+/// An error verifying the signature of a response of the commitments API.
+#[derive(Debug, Clone, PartialEq, Error)]
+#[allow(missing_docs)]
+pub enum ResponseVerificationError {
+    #[error("Missing '{RESPONSE_SIGNATURE_HEADER}' header")]
+    MissingSignature,
+    #[error("Malformed '{RESPONSE_SIGNATURE_HEADER}' header")]
+    MalformedSignature,
+    #[error("Response signed by {recovered}, expected {expected}")]
+    SignerMismatch { expected: Address, recovered: Address },
+}
+
+/// Verifies the responses of a sidecar against its commitment key, if the sidecar
+/// advertises the response-signing capability in `bolt_getVersion`.
+#[derive(Debug, Clone)]
+pub struct ResponseVerifier {
+    /// The address of the commitment key of the sidecar.
+    signer: Address,
+    /// Whether the sidecar signs its responses.
+    enabled: bool,
+}
+
+impl ResponseVerifier {
+    /// Creates a verifier for the sidecar with the given commitment key and version info.
+    pub fn new(signer: Address, version: &VersionInfo) -> Self {
+        let enabled = version.capabilities.iter().any(|c| c == RESPONSE_SIGNING_CAPABILITY);
+        Self { signer, enabled }
+    }
+
+    /// Returns true if the sidecar signs its responses.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Verifies the signature of a response to the request with the given id, over the
+    /// exact body bytes. Always succeeds if the sidecar doesn't sign its responses.
+    pub fn verify(
+        &self,
+        headers: &HeaderMap,
+        body: &[u8],
+        id: Option<&Value>,
+    ) -> Result<(), ResponseVerificationError> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        let header = headers
+            .get(RESPONSE_SIGNATURE_HEADER)
+            .ok_or(ResponseVerificationError::MissingSignature)?;
+        let signature = header
+            .to_str()
+            .ok()
+            .and_then(|sig| Signature::from_str(sig).ok())
+            .ok_or(ResponseVerificationError::MalformedSignature)?;
+
+        let recovered = signature
+            .recover_address_from_prehash(&response_digest(body, id))
+            .map_err(|_| ResponseVerificationError::MalformedSignature)?;
+
+        if recovered != self.signer {
+            return Err(ResponseVerificationError::SignerMismatch {
+                expected: self.signer,
+                recovered,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Verifies that a commitment was signed by the sidecar of the given validator, according to
+/// the identity document served by the sidecar at `GET /identity`.
+///
+/// Unlike the signature in the commitment alone, this authenticates the commitment signer
+/// with the validator key: the identity must be signed by `validator_pubkey`, be valid now,
+/// and bind the address that signed the commitment.
+pub fn verify_commitment_with_identity(
+    commitment: &InclusionCommitment,
+    identity: &SignedIdentity,
+    validator_pubkey: &BlsPublicKey,
+) -> Result<(), IdentityError> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    identity.verify_commitment(commitment, validator_pubkey, now)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::to_bytes, response::IntoResponse};
+    use serde_json::json;
+
+    use super::*;
+    use crate::api::commitments::spec::{error_samples, ErrorCode, ERROR_CATALOGUE};
+
+    /// Decodes the JSON-RPC error of a server response.
+    async fn decode_response(response: axum::response::Response) -> ClientError {
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let mut response: Value = serde_json::from_slice(&body).unwrap();
+        let error = response["error"].take();
+
+        ClientError::from_rpc(
+            error["code"].as_i64().unwrap() as i32,
+            error["message"].as_str().unwrap().to_string(),
+            Some(error["data"].clone()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_every_server_error_is_mapped() {
+        let mut samples = error_samples();
+
+        for &ErrorCode { code, reason } in ERROR_CATALOGUE {
+            let sample = samples
+                .iter()
+                .position(|err| err.error_code() == ErrorCode { code, reason })
+                .map(|i| samples.swap_remove(i));
+
+            let (err, retryable) = match sample {
+                Some(sample) => {
+                    let retryable = sample.retry_policy().retryable;
+                    (decode_response(sample.into_response()).await, retryable)
+                }
+                // Errors without structured fields, that can't be constructed in tests
+                None => {
+                    let data = json!({ "reason": reason, "retryable": false });
+                    (ClientError::from_rpc(code, "error".to_string(), Some(data)), false)
+                }
+            };
+
+            assert!(!matches!(err, ClientError::Unknown { .. }), "{code} {reason:?} isn't mapped");
+            assert_eq!(err.is_retryable(), retryable, "{code} {reason:?}");
+        }
+    }
+
+    #[test]
+    fn test_unknown_errors() {
+        let data = json!({ "reason": "new_reason", "retryable": true });
+        let err = ClientError::from_rpc(-32006, "new error".to_string(), Some(data.clone()));
+
+        let message = "new error".to_string();
+        assert_eq!(err, ClientError::Unknown { code: -32006, message, data: Some(data) });
+        assert!(err.is_retryable());
+        assert_eq!(err.required_fee(), None);
+    }
+
+    #[test]
+    fn test_required_fee() {
+        let data = json!({
+            "reason": "base_fee_too_low",
+            "required": 12_000_000_000u64,
+            "projected": 11_250_000_000u64,
+        });
+        let err = ClientError::from_rpc(-32006, "fee too low".to_string(), Some(data));
+
+        let projected = Some(11_250_000_000);
+        assert_eq!(err, ClientError::FeeTooLow { required: 12_000_000_000, projected });
+        assert_eq!(err.required_fee(), Some(12_000_000_000));
+    }
+}
@@ -2,6 +2,8 @@
 //! The Bolt sidecar's main purpose is to sit between the beacon node and MEV-Boost,
 //! so most requests are simply proxied to its API.
 
+use std::time::Duration;
+
 use axum::http::StatusCode;
 use beacon_api_client::VersionedValue;
 use ethereum_consensus::{
@@ -10,15 +12,24 @@ use ethereum_consensus::{
 use reqwest::Url;
 use tracing::error;
 
+use super::retry::{
+    BreakerState, CircuitBreaker, RetryPolicy, DEFAULT_BREAKER_COOLDOWN, DEFAULT_BREAKER_THRESHOLD,
+};
 use crate::{
+    alerts::{AlertCondition, Alerts},
     api::{
         builder::GetHeaderParams,
         spec::{
             BuilderApi, BuilderApiError, ConstraintsApi, ErrorResponse, CONSTRAINTS_PATH,
-            GET_PAYLOAD_PATH, REGISTER_VALIDATORS_PATH, STATUS_PATH,
+            DELEGATE_PATH, GET_PAYLOAD_PATH, REGISTER_VALIDATORS_PATH, REVOKE_PATH, STATUS_PATH,
         },
     },
-    primitives::{BatchedSignedConstraints, GetPayloadResponse, SignedBuilderBid},
+    common::rng::Rng,
+    primitives::{
+        BatchedSignedConstraints, DelegationAction, GetPayloadResponse, SignedBuilderBid,
+        SignedDelegation,
+    },
+    telemetry,
 };
 
 /// A client for interacting with the MEV-Boost API.
@@ -26,14 +37,146 @@ use crate::{
 pub struct MevBoostClient {
     url: Url,
     client: reqwest::Client,
+    retry: RetryPolicy,
+    breaker: CircuitBreaker,
 }
 
 impl MevBoostClient {
     /// Creates a new MEV-Boost client with the given URL.
     pub fn new<U: Into<Url>>(url: U) -> Self {
+        let url = url.into();
+        let breaker =
+            CircuitBreaker::new(url.as_str(), DEFAULT_BREAKER_THRESHOLD, DEFAULT_BREAKER_COOLDOWN);
         Self {
-            url: url.into(),
+            url,
             client: reqwest::ClientBuilder::new().user_agent("bolt-sidecar").build().unwrap(),
+            retry: RetryPolicy::default(),
+            breaker,
+        }
+    }
+
+    /// Sets the attempts of the constraint submissions, and the delays between them.
+    pub fn with_retry_policy(self, retry: RetryPolicy) -> Self {
+        Self { retry, ..self }
+    }
+
+    /// Opens the circuit of the relay for `cooldown` after `threshold` successive transient
+    /// failures of the constraint submissions.
+    pub fn with_circuit_breaker(self, threshold: u32, cooldown: Duration) -> Self {
+        let breaker = CircuitBreaker::new(self.url.as_str(), threshold, cooldown);
+        Self { breaker, ..self }
+    }
+
+    /// Returns the URL of the MEV-Boost API.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Returns the attempts of the constraint submissions, and the delays between them.
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    /// Returns true if the circuit of the relay is open, so that the constraint submissions
+    /// fail without being attempted.
+    pub fn is_circuit_open(&self) -> bool {
+        matches!(self.breaker.state(), BreakerState::Open { .. })
+    }
+
+    /// Submits the JSON-encoded constraints as is, so that the exact bytes of a persisted
+    /// payload are submitted. Fails with [`BuilderApiError::CircuitOpen`] without a request
+    /// while the circuit of the relay is open.
+    pub async fn submit_constraints_payload(
+        &self,
+        payload: Vec<u8>,
+    ) -> Result<(), BuilderApiError> {
+        if !self.breaker.allow() {
+            return Err(BuilderApiError::CircuitOpen(self.url.to_string()));
+        }
+
+        let result = self.post_constraints(payload).await;
+        self.breaker.record(result.as_ref().is_err_and(BuilderApiError::is_transient));
+        result
+    }
+
+    async fn post_constraints(&self, payload: Vec<u8>) -> Result<(), BuilderApiError> {
+        let response = self
+            .client
+            .post(self.endpoint(CONSTRAINTS_PATH))
+            .header("content-type", "application/json")
+            .body(payload)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if status != StatusCode::OK {
+            // Keep the status code of the relays that don't respond with an error object
+            let error = response.json::<ErrorResponse>().await;
+            let error = error.unwrap_or_else(|_| ErrorResponse::from_status(status));
+            return Err(BuilderApiError::FailedSubmittingConstraints(error));
+        }
+
+        Ok(())
+    }
+
+    /// Submits the signed delegations to the delegate endpoint, and the signed revocations
+    /// to the revoke endpoint.
+    pub async fn submit_delegations(
+        &self,
+        delegations: &[SignedDelegation],
+    ) -> Result<(), BuilderApiError> {
+        let (delegations, revocations): (Vec<_>, Vec<_>) = delegations
+            .iter()
+            .partition(|delegation| delegation.message.action == DelegationAction::Delegate);
+
+        for (path, messages) in [(DELEGATE_PATH, delegations), (REVOKE_PATH, revocations)] {
+            if messages.is_empty() {
+                continue;
+            }
+            let response = self
+                .client
+                .post(self.endpoint(path))
+                .header("content-type", "application/json")
+                .body(serde_json::to_vec(&messages)?)
+                .send()
+                .await?;
+
+            if response.status() != StatusCode::OK {
+                let error = response.json::<ErrorResponse>().await?;
+                return Err(BuilderApiError::FailedSubmittingDelegations(error));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Submits the constraints, retrying the transient failures with the retry policy. The
+    /// relay submission alert is raised if the submission fails, and cleared once one
+    /// succeeds.
+    pub async fn submit_constraints_with_retries(
+        &self,
+        constraints: &BatchedSignedConstraints,
+        alerts: &Alerts,
+    ) -> Result<(), BuilderApiError> {
+        let (mut attempts, rng) = (0, Rng::from_entropy());
+        loop {
+            let Err(err) = self.submit_constraints(constraints).await else {
+                alerts.clear(AlertCondition::RelaySubmissionFailure);
+                return Ok(());
+            };
+
+            attempts += 1;
+            if attempts >= self.retry.max_attempts || !err.is_transient() {
+                error!(?err, attempts, "Failed to submit the constraints to MEV-Boost");
+                let message = format!("Failed to submit constraints to MEV-Boost: {err}");
+                alerts.raise(AlertCondition::RelaySubmissionFailure, message);
+                return Err(err);
+            }
+
+            error!(?err, "Error submitting constraints to mev-boost, retrying...");
+            metrics::counter!(telemetry::RELAY_RETRIES, "relay" => self.url.to_string())
+                .increment(1);
+            tokio::time::sleep(self.retry.delay(attempts, &rng)).await;
         }
     }
 
@@ -137,20 +280,7 @@ impl ConstraintsApi for MevBoostClient {
         &self,
         constraints: &BatchedSignedConstraints,
     ) -> Result<(), BuilderApiError> {
-        let response = self
-            .client
-            .post(self.endpoint(CONSTRAINTS_PATH))
-            .header("content-type", "application/json")
-            .body(serde_json::to_vec(&constraints)?)
-            .send()
-            .await?;
-
-        if response.status() != StatusCode::OK {
-            let error = response.json::<ErrorResponse>().await?;
-            return Err(BuilderApiError::FailedSubmittingConstraints(error));
-        }
-
-        Ok(())
+        self.submit_constraints_payload(serde_json::to_vec(&constraints)?).await
     }
 
     async fn get_header_with_proofs(
@@ -193,6 +323,15 @@ mod tests {
 
     use crate::MevBoostClient;
 
+    /// Serves the router on a free local port, returning its URL.
+    #[cfg(feature = "webhooks")]
+    async fn serve(router: axum::Router) -> Url {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap()).parse().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        url
+    }
+
     #[test]
     fn test_join_endpoints() {
         let client = MevBoostClient::new(Url::parse("http://localhost:8080/").unwrap());
@@ -206,4 +345,58 @@ mod tests {
             Url::parse("http://localhost:8080/eth/v1/builder/validators").unwrap()
         );
     }
+
+    #[cfg(feature = "webhooks")]
+    #[tokio::test]
+    async fn test_single_alert_on_persistent_relay_failures() {
+        use std::{sync::Arc, time::Duration};
+
+        use axum::{routing::post, Json, Router};
+        use serde_json::{json, Value};
+
+        use crate::{
+            alerts::{AlertConfig, AlertCondition, Alerts},
+            api::spec::CONSTRAINTS_PATH,
+            client::retry::RetryPolicy,
+        };
+
+        // A relay that fails all the constraint submissions
+        let relay = Router::new().route(
+            CONSTRAINTS_PATH,
+            post(|| async {
+                let error = json!({ "code": 500, "message": "relay unavailable" });
+                (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+            }),
+        );
+        let retry = RetryPolicy { initial_delay: Duration::from_millis(1), ..Default::default() };
+        let client = MevBoostClient::new(serve(relay).await).with_retry_policy(retry);
+
+        // A webhook that records the alerts it receives
+        let received = Arc::new(parking_lot::Mutex::new(Vec::<Value>::new()));
+        let sink_received = received.clone();
+        let sink = Router::new().route(
+            "/alerts",
+            post(move |Json(alert): Json<Value>| {
+                sink_received.lock().push(alert);
+                async {}
+            }),
+        );
+        let webhook_url = serve(sink).await.join("alerts").unwrap();
+
+        let config = AlertConfig { webhook_url: Some(webhook_url), ..Default::default() };
+        let alerts = Alerts::spawn(&config, "test");
+
+        // Repeated failures within the hold window only deliver a single alert
+        for _ in 0..3 {
+            client.submit_constraints_with_retries(&Vec::new(), &alerts).await.unwrap_err();
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let received = received.lock();
+        assert_eq!(received.len(), 1);
+        let alert = &received[0];
+        assert_eq!(alert["condition"], AlertCondition::RelaySubmissionFailure.to_string());
+        assert_eq!(alert["status"], "raised");
+        assert_eq!(alert["instance_id"], "test");
+    }
 }
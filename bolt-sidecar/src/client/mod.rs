@@ -1,7 +1,11 @@
 pub mod commit_boost;
+pub mod commitments;
 pub mod mevboost;
 pub mod pubsub;
+pub mod relay;
+pub mod retry;
 pub mod rpc;
+pub mod web3signer;
 
 // Re-export the beacon_api_client
 pub use beacon_api_client::mainnet::Client as BeaconClient;
@@ -0,0 +1,134 @@
+use std::{fmt, sync::Arc};
+
+use alloy::{
+    primitives::{Address, B256},
+    signers::Signer,
+};
+use reqwest::{header::HeaderMap, Url};
+use serde_json::Value;
+use thiserror::Error;
+use tracing::warn;
+
+use crate::{
+    api::commitments::{
+        relay::{signed_request, RelayAttestation, RELAY_ATTESTATION_HEADER},
+        spec::SIGNATURE_HEADER,
+    },
+    config::ChainConfig,
+    state::clock::Clock,
+};
+
+/// An error relaying a request to the downstream sidecar.
+#[derive(Debug, Error)]
+pub enum RelayError {
+    /// The gateway failed to sign the attestation.
+    #[error("Failed to sign the relay attestation: {0}")]
+    Signing(#[from] alloy::signers::Error),
+    /// The request couldn't be forwarded.
+    #[error("Failed to forward the request: {0}")]
+    Http(#[from] reqwest::Error),
+}
+
+/// Forwards the JSON-RPC requests received by a gateway to the downstream sidecar holding
+/// the commitment key, attesting the original signer of the signed requests.
+///
+/// The downstream sidecar only attributes the relayed requests to their original signer if
+/// the gateway is one of its trusted gateways, see
+/// [`TrustedGateways`](crate::api::commitments::relay::TrustedGateways).
+#[derive(Clone)]
+pub struct RelayForwarder {
+    client: reqwest::Client,
+    downstream: Url,
+    gateway: Arc<dyn Signer + Send + Sync>,
+    clock: Clock,
+    chain_id: u64,
+}
+
+impl fmt::Debug for RelayForwarder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RelayForwarder")
+            .field("downstream", &self.downstream.as_str())
+            .field("gateway", &self.gateway.address())
+            .finish()
+    }
+}
+
+impl RelayForwarder {
+    /// Creates a forwarder to the downstream sidecar, attesting the requests with the key of
+    /// the gateway.
+    pub fn new<S: Signer + Send + Sync + 'static>(downstream: Url, gateway: S) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            downstream,
+            gateway: Arc::new(gateway),
+            clock: Clock::System,
+            chain_id: ChainConfig::default().chain_id(),
+        }
+    }
+
+    /// Sets the id of the chain of the downstream sidecar, which the digests of the requests
+    /// are scoped to. It is the one of mainnet by default.
+    pub fn with_chain_id(self, chain_id: u64) -> Self {
+        Self { chain_id, ..self }
+    }
+
+    /// Sets the clock the attestations are timestamped with, which is the system clock by
+    /// default.
+    pub fn with_clock(self, clock: Clock) -> Self {
+        Self { clock, ..self }
+    }
+
+    /// Returns the address of the gateway.
+    pub fn address(&self) -> Address {
+        self.gateway.address()
+    }
+
+    /// Returns the attestation of a signed request, or `None` if the request isn't signed.
+    ///
+    /// The gateway only attests the requests whose signature matches the signer in the
+    /// header: the downstream sidecar rejects the other ones anyway.
+    pub async fn attest(
+        &self,
+        headers: &HeaderMap,
+        payload: &Value,
+    ) -> Result<Option<RelayAttestation>, RelayError> {
+        let method = payload["method"].as_str().unwrap_or_default();
+        let params = payload["params"].as_array().map(Vec::as_slice).unwrap_or_default();
+        let Some((signer, signature, digests)) =
+            signed_request(headers, method, params, self.chain_id)
+        else {
+            return Ok(None);
+        };
+
+        let signed_by = |digest: &B256| signature.recover_address_from_prehash(digest).ok();
+        let Some(digest) = digests.into_iter().find(|digest| signed_by(digest) == Some(signer))
+        else {
+            warn!(%signer, "Not attesting request with an invalid signature");
+            return Ok(None);
+        };
+
+        let timestamp = self.clock.unix_secs();
+        let gateway = self.gateway.as_ref();
+        Ok(Some(RelayAttestation::sign(gateway, signer, digest, timestamp).await?))
+    }
+
+    /// Forwards a request to the downstream sidecar, along with the signature header of its
+    /// original signer and the attestation of the gateway. Returns the downstream response
+    /// as is.
+    pub async fn forward(
+        &self,
+        headers: &HeaderMap,
+        payload: &Value,
+    ) -> Result<reqwest::Response, RelayError> {
+        let mut forwarded = HeaderMap::new();
+        if let Some(signature) = headers.get(SIGNATURE_HEADER) {
+            forwarded.insert(SIGNATURE_HEADER, signature.clone());
+        }
+        if let Some(attestation) = self.attest(headers, payload).await? {
+            forwarded.insert(RELAY_ATTESTATION_HEADER, attestation.to_header_value());
+        }
+
+        let request = self.client.post(self.downstream.clone()).headers(forwarded).json(payload);
+        Ok(request.send().await?)
+    }
+}
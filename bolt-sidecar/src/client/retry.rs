@@ -0,0 +1,205 @@
+//! The retry policy and the circuit breaker of the constraint submissions to a relay.
+//!
+//! A failed submission is retried with an exponential backoff, only if it failed for a
+//! transient reason such as a 5xx response or a connection reset: the submissions of the
+//! constraints are idempotent, as the relays ignore a payload they already received. After a
+//! number of successive transient failures, the circuit of the relay opens and the
+//! submissions to it fail right away, until a single probe is let through after a cooldown.
+
+use std::{sync::Arc, time::Duration};
+
+use parking_lot::Mutex;
+use tokio::time::Instant;
+use tracing::warn;
+
+use crate::{common::rng::Rng, telemetry};
+
+/// Default maximum number of attempts to submit the constraints of a slot to a relay.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+
+/// Default delay before the first retry of a submission.
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Default maximum delay between two attempts of a submission.
+pub const DEFAULT_MAX_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Default number of successive transient failures after which the circuit of a relay opens.
+pub const DEFAULT_BREAKER_THRESHOLD: u32 = 5;
+
+/// Default time the circuit of a relay stays open before a probe is let through.
+pub const DEFAULT_BREAKER_COOLDOWN: Duration = Duration::from_secs(12);
+
+/// The attempts of a submission to a relay, and the delays between them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts of a submission.
+    pub max_attempts: u32,
+    /// The delay before the first retry, doubled on each following one.
+    pub initial_delay: Duration,
+    /// The maximum delay between two attempts, before the jitter.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_delay: DEFAULT_RETRY_DELAY,
+            max_delay: DEFAULT_MAX_RETRY_DELAY,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns the delay before the retry following the given number of failed attempts,
+    /// extended by a random jitter of up to half of it.
+    pub fn delay(&self, attempts: u32, rng: &Rng) -> Duration {
+        let factor = 1u32.checked_shl(attempts.saturating_sub(1)).unwrap_or(u32::MAX);
+        let delay = self.initial_delay.saturating_mul(factor).min(self.max_delay);
+        delay + rng.jitter(delay / 2)
+    }
+}
+
+/// The state of the circuit of a relay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    /// The submissions go through, counting the successive transient failures.
+    Closed {
+        /// The number of successive transient failures.
+        failures: u32,
+    },
+    /// The submissions fail right away, until the cooldown has elapsed.
+    Open {
+        /// The end of the cooldown.
+        until: Instant,
+    },
+    /// A single probe goes through, which closes the circuit if it succeeds.
+    HalfOpen,
+}
+
+impl BreakerState {
+    /// Returns the value of the state in the breaker state gauge.
+    fn gauge(&self) -> f64 {
+        match self {
+            Self::Closed { .. } => 0.0,
+            Self::HalfOpen => 1.0,
+            Self::Open { .. } => 2.0,
+        }
+    }
+}
+
+/// A circuit breaker of the submissions to a relay. Cheap to clone, the clones sharing the
+/// same circuit.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    relay: String,
+    threshold: u32,
+    cooldown: Duration,
+    state: Arc<Mutex<BreakerState>>,
+}
+
+impl CircuitBreaker {
+    /// Creates a closed circuit for the relay, opening after `threshold` successive transient
+    /// failures for `cooldown`.
+    pub fn new(relay: impl Into<String>, threshold: u32, cooldown: Duration) -> Self {
+        let state = Arc::new(Mutex::new(BreakerState::Closed { failures: 0 }));
+        Self { relay: relay.into(), threshold: threshold.max(1), cooldown, state }
+    }
+
+    /// Returns the current state of the circuit.
+    pub fn state(&self) -> BreakerState {
+        *self.state.lock()
+    }
+
+    /// Returns true if a submission may be attempted. Once the cooldown of the open circuit
+    /// has elapsed, a single probe is allowed until its outcome is recorded.
+    pub fn allow(&self) -> bool {
+        let mut state = self.state.lock();
+        match *state {
+            BreakerState::Closed { .. } => true,
+            BreakerState::Open { until } if Instant::now() >= until => {
+                self.set(&mut state, BreakerState::HalfOpen);
+                true
+            }
+            BreakerState::Open { .. } | BreakerState::HalfOpen => false,
+        }
+    }
+
+    /// Records the outcome of an attempt: a success or a permanent failure closes the
+    /// circuit, and a transient failure counts towards opening it.
+    pub fn record(&self, transient_failure: bool) {
+        let mut state = self.state.lock();
+        let next = match *state {
+            _ if !transient_failure => BreakerState::Closed { failures: 0 },
+            BreakerState::Closed { failures } if failures + 1 < self.threshold => {
+                BreakerState::Closed { failures: failures + 1 }
+            }
+            BreakerState::Closed { .. } | BreakerState::HalfOpen => {
+                BreakerState::Open { until: Instant::now() + self.cooldown }
+            }
+            open @ BreakerState::Open { .. } => open,
+        };
+        self.set(&mut state, next);
+    }
+
+    fn set(&self, state: &mut BreakerState, next: BreakerState) {
+        let opened = !matches!(state, BreakerState::Open { .. });
+        if opened && matches!(next, BreakerState::Open { .. }) {
+            warn!(relay = %self.relay, cooldown = ?self.cooldown, "Relay circuit opened");
+        }
+        *state = next;
+        metrics::gauge!(telemetry::RELAY_BREAKER_STATE, "relay" => self.relay.clone())
+            .set(next.gauge());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delays() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(350),
+        };
+        let rng = Rng::seeded(1);
+
+        // The delays double up to the maximum, with a jitter of up to half of them
+        for (attempts, base) in [(1, 100), (2, 200), (3, 350), (10, 350)] {
+            let base = Duration::from_millis(base);
+            let delay = policy.delay(attempts, &rng);
+            assert!(delay >= base && delay < base + base / 2, "{attempts}: {delay:?}");
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_circuit_breaker() {
+        let breaker = CircuitBreaker::new("relay", 2, Duration::from_secs(12));
+
+        // Permanent failures don't count towards opening the circuit
+        breaker.record(true);
+        breaker.record(false);
+        breaker.record(true);
+        assert_eq!(breaker.state(), BreakerState::Closed { failures: 1 });
+
+        // Successive transient failures open it until the cooldown has elapsed
+        breaker.record(true);
+        assert!(matches!(breaker.state(), BreakerState::Open { .. }));
+        assert!(!breaker.allow());
+        tokio::time::advance(Duration::from_secs(12)).await;
+
+        // A single probe is then let through, and reopens the circuit if it fails
+        assert!(breaker.clone().allow());
+        assert!(!breaker.allow());
+        breaker.record(true);
+        assert!(!breaker.allow());
+
+        // Or closes it if it succeeds
+        tokio::time::advance(Duration::from_secs(12)).await;
+        assert!(breaker.allow());
+        breaker.record(false);
+        assert_eq!(breaker.state(), BreakerState::Closed { failures: 0 });
+    }
+}
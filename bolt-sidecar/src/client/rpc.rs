@@ -3,7 +3,7 @@
 
 use futures::future::join_all;
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     ops::{Deref, DerefMut},
 };
 
@@ -17,13 +17,15 @@ use alloy::{
                 geth::{GethDebugTracingCallOptions, GethTrace},
                 parity::{TraceResults, TraceType},
             },
-            Block, EIP1186AccountProofResponse, FeeHistory, TransactionRequest,
+            Block, EIP1186AccountProofResponse, FeeHistory, Transaction, TransactionReceipt,
+            TransactionRequest,
         },
     },
     transports::{http::Http, TransportErrorKind, TransportResult},
 };
 
 use reqwest::{Client, Url};
+use serde::Deserialize;
 
 use crate::primitives::AccountState;
 
@@ -114,6 +116,45 @@ impl RpcClient {
         Ok(AccountState { balance, transaction_count: tx_count.to(), has_code: !code.is_empty() })
     }
 
+    /// Gets the latest account states of the given addresses in a single batch.
+    pub async fn get_account_states(
+        &self,
+        addresses: &[Address],
+    ) -> TransportResult<HashMap<Address, AccountState>> {
+        if addresses.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let mut batch = self.0.new_batch();
+        let tag = BlockNumberOrTag::Latest;
+
+        let mut calls: Vec<(Address, Waiter<U256>, Waiter<U64>, Waiter<Bytes>)> = Vec::new();
+        for address in addresses {
+            let balance =
+                batch.add_call("eth_getBalance", &(address, tag)).expect("Correct parameters");
+            let tx_count = batch
+                .add_call("eth_getTransactionCount", &(address, tag))
+                .expect("Correct parameters");
+            let code = batch.add_call("eth_getCode", &(address, tag)).expect("Correct parameters");
+            calls.push((*address, balance, tx_count, code));
+        }
+
+        batch.send().await?;
+
+        let mut states = HashMap::with_capacity(calls.len());
+        for (address, balance, tx_count, code) in calls {
+            let (balance, tx_count, code) = (balance.await?, tx_count.await?, code.await?);
+            let state = AccountState {
+                balance,
+                transaction_count: tx_count.to(),
+                has_code: !code.is_empty(),
+            };
+            states.insert(address, state);
+        }
+
+        Ok(states)
+    }
+
     /// Get the block with the given number. If `None`, the latest block is returned.
     pub async fn get_block(&self, block_number: Option<u64>, full: bool) -> TransportResult<Block> {
         let tag = block_number.map_or(BlockNumberOrTag::Latest, BlockNumberOrTag::Number);
@@ -121,6 +162,20 @@ impl RpcClient {
         self.0.request("eth_getBlockByNumber", (tag, full)).await
     }
 
+    /// Get the receipts of all transactions in the block with the given number.
+    /// If `None`, the receipts of the latest block are returned.
+    pub async fn get_block_receipts(
+        &self,
+        block_number: Option<u64>,
+    ) -> TransportResult<Vec<TransactionReceipt>> {
+        let tag = block_number.map_or(BlockNumberOrTag::Latest, BlockNumberOrTag::Number);
+
+        let receipts: Option<Vec<TransactionReceipt>> =
+            self.0.request("eth_getBlockReceipts", (tag,)).await?;
+
+        Ok(receipts.unwrap_or_default())
+    }
+
     /// Returns the account and storage values of the specified account including the Merkle-proof.
     /// If the block number is `None`, the latest block is used.
     pub async fn get_proof(
@@ -182,10 +237,34 @@ impl RpcClient {
         self.0.request("debug_traceCall", params).await
     }
 
+    /// Executes a call without creating a transaction, and returns its output.
+    /// If the block number is `None`, the call is executed on the latest block.
+    pub async fn call(
+        &self,
+        tx: &TransactionRequest,
+        block_number: Option<u64>,
+    ) -> TransportResult<Bytes> {
+        let tag = block_number.map_or(BlockNumberOrTag::Latest, BlockNumberOrTag::Number);
+
+        self.0.request("eth_call", (tx.clone(), tag)).await
+    }
+
     /// Send a raw transaction to the network.
     pub async fn send_raw_transaction(&self, raw: Bytes) -> TransportResult<B256> {
         self.0.request("eth_sendRawTransaction", [raw]).await
     }
+
+    /// Get the pending transactions of the mempool, with the `txpool_content` method.
+    pub async fn get_pending_transactions(&self) -> TransportResult<Vec<Transaction>> {
+        let content: TxpoolContent = self.0.request("txpool_content", ()).await?;
+        Ok(content.pending.into_values().flat_map(HashMap::into_values).collect())
+    }
+}
+
+/// The transactions of the mempool returned by `txpool_content`, by sender and nonce.
+#[derive(Debug, Deserialize)]
+struct TxpoolContent {
+    pending: HashMap<Address, HashMap<String, Transaction>>,
 }
 
 impl Deref for RpcClient {
@@ -0,0 +1,444 @@
+//! A signer backend calling the REST API of Web3Signer, so that the keys the sidecar signs
+//! with are held by Web3Signer rather than by the sidecar.
+//!
+//! The keys are identified by their public key: a BLS key of the `eth2` keys of the signer
+//! for the constraints and the identity documents, and a secp256k1 key of its `eth1` keys for
+//! the commitments. At startup, the sidecar checks that both are loaded on the signer.
+//!
+//! The signatures are on the path of every commitment, so the requests share a pool of
+//! connections kept alive between the slots. The connections can be authenticated with a TLS
+//! client certificate. A request failing on the transport, timing out, or failing with a
+//! server error or a rate limit is retried with an exponential backoff, after which the
+//! signer is reported as unavailable.
+
+use std::{
+    fmt, fs,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use alloy::{
+    primitives::{Address, Bytes, FixedBytes, Signature, B256},
+    signers::{k256::ecdsa::VerifyingKey, utils::public_key_to_address},
+};
+use eyre::{bail, eyre, WrapErr};
+use reqwest::{Certificate, Identity, StatusCode, Url};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    crypto::{
+        backend::retry_remote,
+        bls::{BLSSig, BlsPublicKey},
+        SignerBackend, SignerError,
+    },
+    telemetry,
+};
+
+/// The path of the BLS public keys loaded on the signer.
+const ETH2_PUBLIC_KEYS_PATH: &str = "/api/v1/eth2/publicKeys";
+
+/// The path of the secp256k1 public keys loaded on the signer.
+const ETH1_PUBLIC_KEYS_PATH: &str = "/api/v1/eth1/publicKeys";
+
+/// Default timeout of each attempt of a request to the signer.
+pub const DEFAULT_WEB3SIGNER_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Delay before the first retry of a request to the signer, doubled on each retry.
+const SIGNER_RETRY_DELAY: Duration = Duration::from_millis(20);
+
+/// How long the idle connections to the signer are kept in the pool, longer than a slot so
+/// that the first signatures of a slot don't pay for a new TLS handshake.
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The client certificate and key the connections to the signer are authenticated with.
+#[derive(Debug, Clone)]
+pub struct ClientCert {
+    /// Path to the PEM certificate chain.
+    pub cert: PathBuf,
+    /// Path to the PEM PKCS#8 private key.
+    pub key: PathBuf,
+}
+
+/// The config of the Web3Signer signer backend.
+#[derive(Debug, Clone)]
+pub struct Web3SignerConfig {
+    /// The URL of the REST API of the signer.
+    pub url: Url,
+    /// The BLS public key the constraints are signed with.
+    pub constraint_pubkey: FixedBytes<48>,
+    /// The secp256k1 public key the commitments are signed with.
+    pub commitment_pubkey: VerifyingKey,
+    /// The client certificate of the TLS connections, if the signer requires one.
+    pub client_cert: Option<ClientCert>,
+    /// Path to the PEM CA certificate the certificate of the signer is verified with, in
+    /// addition to the system roots.
+    pub ca_cert: Option<PathBuf>,
+    /// Timeout of each attempt of a request to the signer.
+    pub timeout: Duration,
+}
+
+/// A signature request of a BLS key, of a signing root computed by the sidecar.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Eth2SignRequest {
+    signing_root: B256,
+}
+
+/// A signature request of a secp256k1 key, of the digest of a commitment request.
+#[derive(Debug, Serialize)]
+struct Eth1SignRequest {
+    data: B256,
+}
+
+/// The response to a signature request.
+#[derive(Debug, Deserialize)]
+struct SignResponse {
+    signature: Bytes,
+}
+
+/// A signer backend with the keys held by Web3Signer.
+#[derive(Clone)]
+pub struct Web3SignerBackend {
+    url: Url,
+    client: reqwest::Client,
+    timeout: Duration,
+    retry_delay: Duration,
+    /// The identifier of the BLS key on the signer.
+    constraint_key: FixedBytes<48>,
+    constraint_pubkey: BlsPublicKey,
+    /// The identifier of the secp256k1 key on the signer.
+    commitment_key: String,
+    commitment_address: Address,
+}
+
+impl fmt::Debug for Web3SignerBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Web3SignerBackend")
+            .field("url", &self.url)
+            .field("constraint_key", &self.constraint_key)
+            .field("commitment_address", &self.commitment_address)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Web3SignerBackend {
+    /// Connects to the signer, and checks that the configured keys are loaded on it.
+    pub async fn connect(config: Web3SignerConfig) -> eyre::Result<Self> {
+        let mut client = reqwest::Client::builder()
+            .user_agent("bolt-sidecar")
+            .timeout(config.timeout)
+            .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+            .tcp_nodelay(true);
+        if let Some(ref path) = config.ca_cert {
+            let pem =
+                fs::read(path).wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+            client = client.add_root_certificate(Certificate::from_pem(&pem)?);
+        }
+        if let Some(ClientCert { ref cert, ref key }) = config.client_cert {
+            let read = |path: &PathBuf| {
+                fs::read(path).wrap_err_with(|| format!("Failed to read {}", path.display()))
+            };
+            client = client.identity(Identity::from_pkcs8_pem(&read(cert)?, &read(key)?)?);
+        }
+
+        let constraint_key = config.constraint_pubkey;
+        let constraint_pubkey = BlsPublicKey::from_bytes(constraint_key.as_slice())
+            .map_err(|err| eyre!("Invalid constraint public key {constraint_key}: {err:?}"))?;
+        let backend = Self {
+            url: config.url,
+            client: client.build()?,
+            timeout: config.timeout,
+            retry_delay: SIGNER_RETRY_DELAY,
+            constraint_key,
+            constraint_pubkey,
+            commitment_key: eth1_identifier(&config.commitment_pubkey),
+            commitment_address: public_key_to_address(&config.commitment_pubkey),
+        };
+
+        // Fail fast if a key is missing, rather than on the first commitment
+        let eth2_keys: Vec<FixedBytes<48>> =
+            backend.call(ETH2_PUBLIC_KEYS_PATH, None::<&()>).await?;
+        let eth1_keys: Vec<Bytes> = backend.call(ETH1_PUBLIC_KEYS_PATH, None::<&()>).await?;
+        let mut missing = Vec::new();
+        if !eth2_keys.contains(&constraint_key) {
+            missing.push(constraint_key.to_string());
+        }
+        if !eth1_keys.iter().any(|key| parse_eth1_key(key) == Some(config.commitment_pubkey)) {
+            missing.push(backend.commitment_key.clone());
+        }
+        if !missing.is_empty() {
+            bail!("Keys missing on the Web3Signer at {}: {}", backend.url, missing.join(", "));
+        }
+
+        let (url, address) = (&backend.url, backend.commitment_address);
+        info!(%url, %constraint_key, %address, "Using the Web3Signer signer");
+        Ok(backend)
+    }
+
+    /// Sets the delay before the first retry of a request to the signer.
+    pub fn with_retry_delay(self, retry_delay: Duration) -> Self {
+        Self { retry_delay, ..self }
+    }
+
+    /// Calls the API on the path, with a GET request or a POST request of the body, retrying
+    /// while the signer is unavailable.
+    async fn call<T, B>(&self, path: &str, body: Option<&B>) -> Result<T, SignerError>
+    where
+        T: DeserializeOwned,
+        B: Serialize + Sync,
+    {
+        retry_remote("web3signer", self.retry_delay, || self.attempt(path, body)).await
+    }
+
+    async fn attempt<T, B>(&self, path: &str, body: Option<&B>) -> Result<T, SignerError>
+    where
+        T: DeserializeOwned,
+        B: Serialize + Sync,
+    {
+        let unavailable = |message: String| SignerError::Unavailable { attempts: 1, message };
+
+        let url = self.url.join(path).map_err(|err| SignerError::Local(err.to_string()))?;
+        let request = match body {
+            Some(body) => self.client.post(url).json(body),
+            None => self.client.get(url),
+        };
+        let response = match request.header("accept", "application/json").send().await {
+            Ok(response) => response,
+            Err(err) if err.is_timeout() => {
+                return Err(unavailable(format!("timed out after {:?}", self.timeout)))
+            }
+            Err(err) => return Err(unavailable(err.to_string())),
+        };
+
+        let status = response.status();
+        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(unavailable(format!("responded with status {status}")));
+        }
+        let text = response.text().await.map_err(|err| unavailable(err.to_string()))?;
+        match status {
+            // The signer doesn't have the key the path identifies
+            StatusCode::NOT_FOUND => Err(SignerError::Rejected(format!("unknown key at {path}"))),
+            status if !status.is_success() => {
+                Err(SignerError::Rejected(format!("status {status}: {text}")))
+            }
+            _ => serde_json::from_str(&text)
+                .map_err(|err| SignerError::Rejected(format!("invalid response: {err}"))),
+        }
+    }
+
+    /// Requests the signature of the body from the key on the path, recording its latency.
+    async fn sign<B: Serialize + Sync>(
+        &self,
+        path: String,
+        body: &B,
+    ) -> Result<Bytes, SignerError> {
+        let start = Instant::now();
+        let result = self.call::<SignResponse, _>(&path, Some(body)).await;
+        let elapsed = start.elapsed().as_secs_f64();
+        metrics::histogram!(telemetry::REMOTE_SIGNING_LATENCY, "signer" => "web3signer")
+            .record(elapsed);
+        result.map(|response| response.signature)
+    }
+}
+
+#[async_trait::async_trait]
+impl SignerBackend for Web3SignerBackend {
+    fn commitment_address(&self) -> Address {
+        self.commitment_address
+    }
+
+    fn constraint_pubkey(&self) -> BlsPublicKey {
+        self.constraint_pubkey
+    }
+
+    async fn sign_commitment(&self, digest: &B256) -> Result<Signature, SignerError> {
+        let path = format!("/api/v1/eth1/sign/{}", self.commitment_key);
+        let signature = self.sign(path, &Eth1SignRequest { data: *digest }).await?;
+        Signature::try_from(signature.as_ref())
+            .map_err(|err| SignerError::Rejected(format!("invalid signature: {err}")))
+    }
+
+    async fn sign_bls(&self, data: &[u8]) -> Result<BLSSig, SignerError> {
+        let Ok(signing_root) = B256::try_from(data) else {
+            let message = format!("expected a 32-byte root to sign, got {} bytes", data.len());
+            return Err(SignerError::Local(message));
+        };
+        let path = format!("/api/v1/eth2/sign/{}", self.constraint_key);
+        let signature = self.sign(path, &Eth2SignRequest { signing_root }).await?;
+        BLSSig::try_from(signature.as_ref())
+            .map_err(|_| SignerError::Rejected("invalid signature length".to_string()))
+    }
+}
+
+/// Returns the identifier of a secp256k1 key on the signer: its uncompressed public key,
+/// without the SEC1 prefix.
+fn eth1_identifier(key: &VerifyingKey) -> String {
+    format!("0x{}", hex::encode(&key.to_encoded_point(false).as_bytes()[1..]))
+}
+
+/// Parses a secp256k1 public key listed by the signer, compressed or uncompressed, with or
+/// without the SEC1 prefix.
+fn parse_eth1_key(key: &[u8]) -> Option<VerifyingKey> {
+    match key.len() {
+        64 => VerifyingKey::from_sec1_bytes(&[&[0x04], key].concat()).ok(),
+        _ => VerifyingKey::from_sec1_bytes(key).ok(),
+    }
+}
+
+/// Parses a secp256k1 public key given as an option, in any of the forms the signer lists.
+pub fn parse_commitment_pubkey(key: &str) -> eyre::Result<VerifyingKey> {
+    let bytes = hex::decode(key.strip_prefix("0x").unwrap_or(key))?;
+    parse_eth1_key(&bytes).ok_or_else(|| eyre!("Invalid secp256k1 public key {key}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy::signers::{local::PrivateKeySigner, Signer};
+    use axum::{
+        extract::{Path, State},
+        routing::{get, post},
+        Json, Router,
+    };
+    use parking_lot::Mutex;
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::{
+        crypto::{
+            backend::MAX_REMOTE_ATTEMPTS,
+            bls::{Signer as BlsSigner, SignerBLS},
+        },
+        test_util::test_bls_secret_key,
+    };
+
+    /// A Web3Signer with a BLS and a secp256k1 key, recording the signature requests, and
+    /// responding to them after the given delay.
+    #[derive(Clone)]
+    struct MockSigner {
+        eth2: BlsSigner,
+        eth1: PrivateKeySigner,
+        requests: Arc<Mutex<Vec<(String, Value)>>>,
+        delay: Duration,
+    }
+
+    impl MockSigner {
+        fn eth2_key(&self) -> FixedBytes<48> {
+            FixedBytes::from_slice(&self.eth2.pubkey().to_bytes())
+        }
+
+        fn eth1_key(&self) -> VerifyingKey {
+            *self.eth1.credential().verifying_key()
+        }
+    }
+
+    async fn sign(
+        State(mock): State<MockSigner>,
+        Path((namespace, identifier)): Path<(String, String)>,
+        Json(request): Json<Value>,
+    ) -> (StatusCode, Json<Value>) {
+        mock.requests.lock().push((format!("{namespace}/{identifier}"), request.clone()));
+        tokio::time::sleep(mock.delay).await;
+
+        let signature = match namespace.as_str() {
+            "eth2" if identifier == mock.eth2_key().to_string() => {
+                let root: B256 = request["signingRoot"].as_str().unwrap().parse().unwrap();
+                mock.eth2.sign(root.as_slice()).unwrap().to_vec()
+            }
+            "eth1" if identifier == eth1_identifier(&mock.eth1_key()) => {
+                let digest: B256 = request["data"].as_str().unwrap().parse().unwrap();
+                mock.eth1.sign_hash(&digest).await.unwrap().as_bytes().to_vec()
+            }
+            _ => return (StatusCode::NOT_FOUND, Json(json!({ "message": "key not found" }))),
+        };
+        (StatusCode::OK, Json(json!({ "signature": format!("0x{}", hex::encode(signature)) })))
+    }
+
+    async fn mock_signer(delay: Duration) -> (Web3SignerConfig, MockSigner) {
+        let mock = MockSigner {
+            eth2: BlsSigner::new(test_bls_secret_key()),
+            eth1: PrivateKeySigner::random(),
+            requests: Arc::default(),
+            delay,
+        };
+        let eth2_keys = json!([mock.eth2_key()]);
+        let eth1_keys = json!([format!("0x{}", hex::encode(&mock.eth1_key().to_sec1_bytes()))]);
+        let router = Router::new()
+            .route(ETH2_PUBLIC_KEYS_PATH, get(|| async move { Json(eth2_keys) }))
+            .route(ETH1_PUBLIC_KEYS_PATH, get(|| async move { Json(eth1_keys) }))
+            .route("/api/v1/:namespace/sign/:identifier", post(sign))
+            .with_state(mock.clone());
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        let config = Web3SignerConfig {
+            url,
+            constraint_pubkey: mock.eth2_key(),
+            commitment_pubkey: mock.eth1_key(),
+            client_cert: None,
+            ca_cert: None,
+            timeout: Duration::from_millis(100),
+        };
+        (config, mock)
+    }
+
+    #[tokio::test]
+    async fn test_web3signer_signatures() -> eyre::Result<()> {
+        let (config, mock) = mock_signer(Duration::ZERO).await;
+        let signer = Web3SignerBackend::connect(config).await?;
+        assert_eq!(signer.commitment_address(), mock.eth1.address());
+
+        let digest = B256::repeat_byte(3);
+        let signature = signer.sign_commitment(&digest).await?;
+        assert_eq!(signature.recover_address_from_prehash(&digest)?, mock.eth1.address());
+
+        let signature = signer.sign_bls(digest.as_slice()).await?;
+        assert_eq!(signature, mock.eth2.sign(digest.as_slice())?);
+
+        // The keys are identified by their public key
+        let requests = mock.requests.lock().clone();
+        let eth1_path = format!("eth1/{}", eth1_identifier(&mock.eth1_key()));
+        assert_eq!(requests[0], (eth1_path, json!({ "data": digest })));
+        let eth2_path = format!("eth2/{}", mock.eth2_key());
+        assert_eq!(requests[1], (eth2_path, json!({ "signingRoot": digest })));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_web3signer_errors() -> eyre::Result<()> {
+        // The keys missing on the signer are listed at startup
+        let (config, _) = mock_signer(Duration::ZERO).await;
+        let other = PrivateKeySigner::random();
+        let commitment_pubkey = *other.credential().verifying_key();
+        let err = Web3SignerBackend::connect(Web3SignerConfig { commitment_pubkey, ..config })
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains(&eth1_identifier(&commitment_pubkey)), "{err}");
+
+        // A key removed from the signer after startup is rejected without retries
+        let (config, mock) = mock_signer(Duration::ZERO).await;
+        let signer = Web3SignerBackend::connect(config).await?;
+        let signer = Web3SignerBackend { commitment_key: "0x01".to_string(), ..signer };
+        let err = signer.sign_commitment(&B256::ZERO).await.unwrap_err();
+        assert!(matches!(err, SignerError::Rejected(_)), "{err}");
+        assert_eq!(mock.requests.lock().len(), 1);
+
+        // A signer that doesn't respond in time is retried, and then unavailable
+        let (config, mock) = mock_signer(Duration::from_secs(1)).await;
+        let signer = Web3SignerBackend::connect(config).await?.with_retry_delay(Duration::ZERO);
+        let err = signer.sign_bls(B256::ZERO.as_slice()).await.unwrap_err();
+        let attempts = MAX_REMOTE_ATTEMPTS;
+        assert!(matches!(err, SignerError::Unavailable { attempts: n, .. } if n == attempts));
+        assert!(err.to_string().contains("timed out"), "{err}");
+        assert!(err.is_remote());
+        assert_eq!(mock.requests.lock().len(), MAX_REMOTE_ATTEMPTS as usize);
+
+        Ok(())
+    }
+}
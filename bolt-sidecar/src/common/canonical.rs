@@ -0,0 +1,266 @@
+//! Canonical JSON serialization, for every payload that is hashed or signed as JSON.
+//!
+//! The canonical form doesn't depend on the serde representation of the value beyond its JSON
+//! data model, so that incidental changes like a different field order don't silently change
+//! the hashes across versions:
+//!
+//! - object keys are sorted by their UTF-8 bytes, and no insignificant whitespace is written;
+//! - strings only escape `"`, `\` and the control characters, with the short escapes when
+//!   they exist and lowercase `\u00xx` otherwise;
+//! - integers are written in decimal. Other numbers are written in decimal if they are
+//!   integral and within the safe integer range (`1.0` is written `1`), and in the shortest
+//!   exponent notation that round-trips otherwise (`0.25` is written `2.5e-1`).
+//!
+//! Digests are computed over [`hash_input`], which prefixes the canonical bytes with the
+//! version tag of the canonicalization, so that a future change of the rules is detectable
+//! instead of silently incompatible.
+
+use serde::Serialize;
+use serde_json::{Number, Value};
+
+/// The version tag of the canonicalization, prefixed to the canonical bytes in what's hashed.
+pub const CANONICAL_JSON_TAG: &[u8] = b"bolt-canonical-json/v1";
+
+/// Floats with a larger magnitude are not written as integers, as they may not be exact.
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+/// Serializes the value to its canonical JSON form.
+pub fn to_canonical_string<T: Serialize + ?Sized>(value: &T) -> Result<String, serde_json::Error> {
+    let value = serde_json::to_value(value)?;
+    let mut out = String::new();
+    write_value(&value, &mut out);
+    Ok(out)
+}
+
+/// Serializes the value to the bytes of its canonical JSON form.
+pub fn to_canonical_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, serde_json::Error> {
+    to_canonical_string(value).map(String::into_bytes)
+}
+
+/// Returns the bytes to hash for the value: the canonicalization tag followed by the
+/// canonical JSON form of the value.
+pub fn hash_input<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, serde_json::Error> {
+    let mut input = CANONICAL_JSON_TAG.to_vec();
+    input.extend_from_slice(&to_canonical_vec(value)?);
+    Ok(input)
+}
+
+fn write_value(value: &Value, out: &mut String) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(number) => write_number(number, out),
+        Value::String(s) => write_string(s, out),
+        Value::Array(values) => {
+            out.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(value, out);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries = map.iter().collect::<Vec<_>>();
+            entries.sort_unstable_by(|(a, _), (b, _)| a.as_bytes().cmp(b.as_bytes()));
+
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(key, out);
+                out.push(':');
+                write_value(value, out);
+            }
+            out.push('}');
+        }
+    }
+}
+
+fn write_number(number: &Number, out: &mut String) {
+    if let Some(n) = number.as_u64() {
+        out.push_str(&n.to_string());
+    } else if let Some(n) = number.as_i64() {
+        out.push_str(&n.to_string());
+    } else if let Some(f) = number.as_f64() {
+        if f.fract() == 0.0 && f.abs() < MAX_SAFE_INTEGER {
+            // Also writes -0 as 0
+            out.push_str(&(f as i64).to_string());
+        } else {
+            out.push_str(&format!("{f:e}"));
+        }
+    }
+}
+
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{08}' => out.push_str("\\b"),
+            '\u{0c}' => out.push_str("\\f"),
+            c if c < ' ' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::B256;
+    use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+    use serde_json::json;
+
+    use super::*;
+
+    /// Generates a random JSON value of at most the given depth.
+    fn random_value(rng: &mut StdRng, depth: u32) -> Value {
+        let kinds = if depth == 0 { 5 } else { 7 };
+        match rng.gen_range(0..kinds) {
+            0 => Value::Null,
+            1 => Value::Bool(rng.gen()),
+            2 => json!(rng.gen::<i64>() >> rng.gen_range(0..64)),
+            3 => json!(rng.gen_range(-1e6..1e6f64)),
+            4 => {
+                let chars = ['a', 'b', 'é', '"', '\\', '\n', '\u{1}', '€', '😀', ' '];
+                let len = rng.gen_range(0..6);
+                Value::String((0..len).map(|_| *chars.choose(rng).unwrap()).collect())
+            }
+            5 => {
+                let len = rng.gen_range(0..4);
+                Value::Array((0..len).map(|_| random_value(rng, depth - 1)).collect())
+            }
+            _ => {
+                let len = rng.gen_range(0..5);
+                let entries = (0..len).map(|i| {
+                    let key = format!("k{}{i}", rng.gen_range(0..3));
+                    (key, random_value(rng, depth - 1))
+                });
+                Value::Object(entries.collect())
+            }
+        }
+    }
+
+    fn random_space(rng: &mut StdRng) -> &'static str {
+        if rng.gen() {
+            " "
+        } else {
+            "\n"
+        }
+    }
+
+    /// Encodes the value as JSON text with its object keys in a random order, and random
+    /// whitespace between the tokens.
+    fn shuffled_json(value: &Value, rng: &mut StdRng) -> String {
+        match value {
+            Value::Array(values) => {
+                let values = values.iter().map(|value| shuffled_json(value, rng));
+                let values = values.collect::<Vec<_>>();
+                format!("[{}{}]", values.join(","), random_space(rng))
+            }
+            Value::Object(map) => {
+                let mut entries = map.iter().collect::<Vec<_>>();
+                entries.shuffle(rng);
+                let entries = entries
+                    .into_iter()
+                    .map(|(key, value)| {
+                        let key = serde_json::to_string(key).unwrap();
+                        let space = random_space(rng);
+                        format!("{space}{key}:{}", shuffled_json(value, rng))
+                    })
+                    .collect::<Vec<_>>();
+                format!("{{{}}}", entries.join(","))
+            }
+            value => serde_json::to_string(value).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_key_order_and_whitespace_are_irrelevant() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..500 {
+            let value = random_value(&mut rng, 3);
+            let canonical = to_canonical_string(&value).unwrap();
+
+            for _ in 0..3 {
+                let reordered = shuffled_json(&value, &mut rng);
+                let reordered: Value = serde_json::from_str(&reordered).unwrap();
+                assert_eq!(to_canonical_string(&reordered).unwrap(), canonical);
+            }
+
+            // The canonical form is valid JSON for the same value, and a fixed point
+            let parsed: Value = serde_json::from_str(&canonical).unwrap();
+            assert_eq!(to_canonical_string(&parsed).unwrap(), canonical);
+        }
+    }
+
+    #[test]
+    fn test_struct_field_order_is_irrelevant() {
+        #[derive(Serialize)]
+        struct Before {
+            slot: u64,
+            digest: B256,
+        }
+
+        #[derive(Serialize)]
+        struct After {
+            digest: B256,
+            slot: u64,
+        }
+
+        let digest = B256::repeat_byte(0xab);
+        let (before, after) = (Before { slot: 42, digest }, After { digest, slot: 42 });
+        assert_eq!(hash_input(&before).unwrap(), hash_input(&after).unwrap());
+        assert_eq!(
+            to_canonical_string(&before).unwrap(),
+            format!(r#"{{"digest":"0x{}","slot":42}}"#, "ab".repeat(32))
+        );
+    }
+
+    #[test]
+    fn test_fixed_vectors() {
+        let vectors = [
+            (json!(null), "null"),
+            (json!({}), "{}"),
+            (json!([1, [], {}]), "[1,[],{}]"),
+            (json!({ "b": 2, "a": [true, null, false] }), r#"{"a":[true,null,false],"b":2}"#),
+            // Keys are sorted by their UTF-8 bytes, so non-ASCII keys come last
+            (
+                json!({ "é": 1, "z": 2, "Z": 3, "aa": 4, "a": 5 }),
+                r#"{"Z":3,"a":5,"aa":4,"z":2,"é":1}"#,
+            ),
+            (
+                json!("quote\" backslash\\ tab\t nl\n ctrl\u{1} é €"),
+                r#""quote\" backslash\\ tab\t nl\n ctrl\u0001 é €""#,
+            ),
+            (json!("\u{8}\u{c}\r\u{1f}/"), r#""\b\f\r\u001f/""#),
+            (json!(u64::MAX), "18446744073709551615"),
+            (json!(i64::MIN), "-9223372036854775808"),
+            (json!(1.0), "1"),
+            (json!(-3.0), "-3"),
+            (json!(-0.0), "0"),
+            (json!(1.5), "1.5e0"),
+            (json!(0.25), "2.5e-1"),
+            (json!(-1234.5), "-1.2345e3"),
+            (json!(1e300), "1e300"),
+        ];
+
+        for (value, expected) in vectors {
+            assert_eq!(to_canonical_string(&value).unwrap(), expected, "{value}");
+        }
+    }
+
+    #[test]
+    fn test_hash_input_is_tagged() {
+        let value = json!({ "jsonrpc": "2.0", "id": 1 });
+        let input = hash_input(&value).unwrap();
+        assert_eq!(input, b"bolt-canonical-json/v1{\"id\":1,\"jsonrpc\":\"2.0\"}");
+    }
+}
@@ -6,6 +6,15 @@ use crate::{
     state::ValidationError,
 };
 
+/// Streaming quantile sketches.
+pub mod quantile;
+
+/// Canonical JSON serialization for hashed and signed payloads.
+pub mod canonical;
+
+/// A shareable, seedable source of randomness.
+pub mod rng;
+
 /// The version of the Bolt sidecar binary.
 pub const CARGO_PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -37,26 +46,32 @@ pub fn calculate_max_basefee(current: u128, block_diff: u64) -> Option<u128> {
 /// - For legacy transactions: `gas_price * gas_limit + tx_value`.
 /// - For EIP-4844 blob transactions: `max_fee_per_gas * gas_limit + tx_value + max_blob_fee_per_gas
 ///   * blob_gas_used`.
+///
+/// The priority fee is paid out of the max fee, so it isn't added to it.
 pub fn max_transaction_cost(transaction: &PooledTransactionsElement) -> U256 {
-    let gas_limit = transaction.gas_limit() as u128;
-
-    let mut fee_cap = transaction.max_fee_per_gas();
-    fee_cap += transaction.max_priority_fee_per_gas().unwrap_or(0);
+    let gas_limit = U256::from(transaction.gas_limit());
+    let mut cost = gas_limit * U256::from(transaction.max_fee_per_gas()) + transaction.value();
 
     if let Some(eip4844) = transaction.as_eip4844() {
-        fee_cap += eip4844.max_fee_per_blob_gas + eip4844.blob_gas() as u128;
+        let blob_gas = U256::from(eip4844.blob_gas());
+        cost += blob_gas * U256::from(eip4844.max_fee_per_blob_gas);
     }
 
-    U256::from(gas_limit * fee_cap) + transaction.value()
+    cost
 }
 
 /// This function validates a transaction against an account state. It checks 2 things:
 /// 1. The nonce of the transaction must be higher than the account's nonce, but not higher than
 ///    current + 1.
 /// 2. The balance of the account must be higher than the transaction's max cost.
+///
+/// The account state has the diffs of the committed transactions of the sender, and of the
+/// earlier transactions of the same request, applied. `reserved` is the balance they take,
+/// reported when the balance left is insufficient.
 pub fn validate_transaction(
     account_state: &AccountState,
     transaction: &PooledTransactionsElement,
+    reserved: U256,
 ) -> Result<(), ValidationError> {
     // Check if the nonce is correct (should be the same as the transaction count)
     if transaction.nonce() < account_state.transaction_count {
@@ -74,8 +89,10 @@ pub fn validate_transaction(
     }
 
     // Check if the balance is enough
-    if max_transaction_cost(transaction) > account_state.balance {
-        return Err(ValidationError::InsufficientBalance);
+    let required = max_transaction_cost(transaction);
+    if required > account_state.balance {
+        let available = account_state.balance;
+        return Err(ValidationError::InsufficientBalance { required, available, reserved });
     }
 
     // Check if the account has code (i.e. is a smart contract)
@@ -88,7 +105,13 @@ pub fn validate_transaction(
 
 #[cfg(test)]
 mod tests {
+    use alloy::signers::{k256::SecretKey, local::PrivateKeySigner};
+
     use super::*;
+    use crate::test_util::{
+        create_signed_commitment_request, default_blob_transaction, default_test_transaction,
+        test_blob_sidecar,
+    };
 
     #[test]
     fn test_calculate_max_basefee() {
@@ -98,4 +121,26 @@ mod tests {
         let result = calculate_max_basefee(current, slot_diff);
         assert_eq!(result, Some(28865075793))
     }
+
+    #[tokio::test]
+    async fn test_max_transaction_cost() -> eyre::Result<()> {
+        let sk = SecretKey::random(&mut rand::thread_rng());
+        let sender = PrivateKeySigner::from(sk.clone()).address();
+
+        // The gas at the max fee plus the value: 21_000 gas at 20 gwei, and 100 wei
+        let execution_cost = U256::from(21_000u128 * 20_000_000_000 + 100);
+        let tx = default_test_transaction(sender, None);
+        let request = create_signed_commitment_request(&[tx], &sk, 10).await?;
+        let tx = &request.as_inclusion_request().unwrap().txs[0];
+        assert_eq!(max_transaction_cost(tx), execution_cost);
+
+        // Plus the blob gas at the max blob fee: 2 blobs of 131_072 gas, at 1 gwei
+        let blob_tx = default_blob_transaction(sender, None, test_blob_sidecar(2, 0xb0));
+        let request = create_signed_commitment_request(&[blob_tx], &sk, 10).await?;
+        let tx = &request.as_inclusion_request().unwrap().txs[0];
+        let blob_cost = U256::from(2u128 * 131_072 * 1_000_000_000);
+        assert_eq!(max_transaction_cost(tx), execution_cost + blob_cost);
+
+        Ok(())
+    }
 }
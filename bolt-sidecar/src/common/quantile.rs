@@ -0,0 +1,241 @@
+use std::{collections::BTreeMap, mem};
+
+/// Values at or below this are counted as zero, as their logarithm isn't usable.
+const MIN_VALUE: f64 = 1e-9;
+
+/// A streaming quantile sketch with a relative accuracy guarantee, after DDSketch
+/// (Masson et al., 2019).
+///
+/// Values are counted in buckets of logarithmically increasing width, so that every
+/// estimated quantile is within `relative_accuracy` of the exact one, using memory that
+/// grows with the logarithm of the range of the values rather than with their number.
+#[derive(Debug, Clone)]
+pub struct QuantileSketch {
+    /// The ratio between the upper and lower bound of a bucket.
+    gamma: f64,
+    /// The natural logarithm of `gamma`.
+    ln_gamma: f64,
+    /// The number of values in each bucket, by bucket index. Bucket `i` holds the values
+    /// in `(gamma^(i-1), gamma^i]`.
+    buckets: BTreeMap<i32, u64>,
+    /// The number of values counted as zero.
+    zeros: u64,
+    /// The total number of values.
+    count: u64,
+}
+
+impl QuantileSketch {
+    /// Creates an empty sketch, with `relative_accuracy` in `(0, 1)`.
+    pub fn new(relative_accuracy: f64) -> Self {
+        assert!(
+            relative_accuracy > 0.0 && relative_accuracy < 1.0,
+            "Relative accuracy must be in (0, 1)"
+        );
+
+        let gamma = (1.0 + relative_accuracy) / (1.0 - relative_accuracy);
+        Self { gamma, ln_gamma: gamma.ln(), buckets: BTreeMap::new(), zeros: 0, count: 0 }
+    }
+
+    /// Adds a value to the sketch. Negative and NaN values are counted as zero.
+    pub fn insert(&mut self, value: f64) {
+        self.count += 1;
+
+        if value.is_nan() || value <= MIN_VALUE {
+            self.zeros += 1;
+            return;
+        }
+
+        let index = (value.ln() / self.ln_gamma).ceil() as i32;
+        *self.buckets.entry(index).or_default() += 1;
+    }
+
+    /// Returns the number of values added to the sketch.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the estimated `q`-quantile of the values, with `q` in `[0, 1]`, or `None`
+    /// if the sketch is empty. The estimate is within the relative accuracy of the value
+    /// of rank `floor(q * (count - 1))`.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let rank = (q.clamp(0.0, 1.0) * (self.count - 1) as f64) as u64;
+        let mut seen = self.zeros;
+        if seen > rank {
+            return Some(0.0);
+        }
+
+        for (index, count) in &self.buckets {
+            seen += count;
+            if seen > rank {
+                return Some(self.estimate(*index));
+            }
+        }
+
+        self.buckets.keys().next_back().map(|index| self.estimate(*index))
+    }
+
+    /// Adds all the values of another sketch with the same relative accuracy.
+    pub fn merge(&mut self, other: &Self) {
+        debug_assert_eq!(self.gamma, other.gamma, "Sketches must have the same accuracy");
+
+        for (index, count) in &other.buckets {
+            *self.buckets.entry(*index).or_default() += count;
+        }
+        self.zeros += other.zeros;
+        self.count += other.count;
+    }
+
+    /// Removes all the values from the sketch.
+    pub fn clear(&mut self) {
+        self.buckets.clear();
+        self.zeros = 0;
+        self.count = 0;
+    }
+
+    /// Returns the value representing the bucket with the given index, which is within the
+    /// relative accuracy of all the values of the bucket.
+    fn estimate(&self, index: i32) -> f64 {
+        2.0 * self.gamma.powi(index) / (self.gamma + 1.0)
+    }
+}
+
+/// A quantile sketch over the most recent values, so that old values stop weighing on the
+/// quantiles.
+///
+/// Values are added to the current window until it holds `window` values, at which point it
+/// replaces the previous window. The quantiles are estimated over both windows, so between
+/// `window` and `2 * window` of the most recent values.
+#[derive(Debug, Clone)]
+pub struct RollingQuantiles {
+    /// The number of values in a window.
+    window: u64,
+    /// The window the values are added to.
+    current: QuantileSketch,
+    /// The last full window.
+    previous: QuantileSketch,
+}
+
+impl RollingQuantiles {
+    /// Creates an empty rolling sketch with the given relative accuracy and window size.
+    pub fn new(relative_accuracy: f64, window: u64) -> Self {
+        let sketch = QuantileSketch::new(relative_accuracy);
+        Self { window: window.max(1), current: sketch.clone(), previous: sketch }
+    }
+
+    /// Adds a value, rolling the windows over if the current one is full.
+    pub fn insert(&mut self, value: f64) {
+        if self.current.count() >= self.window {
+            mem::swap(&mut self.current, &mut self.previous);
+            self.current.clear();
+        }
+
+        self.current.insert(value);
+    }
+
+    /// Returns a sketch of the values of both windows.
+    pub fn sketch(&self) -> QuantileSketch {
+        let mut sketch = self.previous.clone();
+        sketch.merge(&self.current);
+        sketch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    const ACCURACY: f64 = 0.01;
+    const QUANTILES: &[f64] = &[0.0, 0.1, 0.25, 0.5, 0.75, 0.9, 0.95, 0.99, 0.999, 1.0];
+
+    /// Returns true if `estimate` is within the accuracy of `exact`.
+    fn is_close(estimate: f64, exact: f64) -> bool {
+        (estimate - exact).abs() / exact <= ACCURACY + 1e-12
+    }
+
+    /// Asserts that the sketch estimates every quantile of `values` within the accuracy.
+    fn assert_accurate(sketch: &QuantileSketch, mut values: Vec<f64>) {
+        values.sort_by(f64::total_cmp);
+
+        for &q in QUANTILES {
+            let exact = values[(q * (values.len() - 1) as f64) as usize];
+            let estimate = sketch.quantile(q).unwrap();
+            assert!(is_close(estimate, exact), "q={q}: estimated {estimate}, exact {exact}");
+        }
+    }
+
+    #[test]
+    fn test_sketch_accuracy() {
+        let mut rng = StdRng::seed_from_u64(42);
+
+        // Uniform values over several orders of magnitude
+        let uniform: Vec<f64> = (0..10_000).map(|_| rng.gen_range(0.001..1_000.0)).collect();
+        // Exponential values above 1ms, with a long tail like latencies
+        let exponential: Vec<f64> =
+            (0..10_000).map(|_| 0.001 - rng.gen_range(f64::EPSILON..1.0f64).ln() * 0.05).collect();
+
+        for values in [uniform, exponential] {
+            let mut sketch = QuantileSketch::new(ACCURACY);
+            values.iter().for_each(|value| sketch.insert(*value));
+            assert_eq!(sketch.count(), values.len() as u64);
+            assert_accurate(&sketch, values);
+        }
+    }
+
+    #[test]
+    fn test_sketch_merge() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let values: Vec<f64> = (0..5_000).map(|_| rng.gen_range(1.0..100.0)).collect();
+
+        let (mut left, mut right) = (QuantileSketch::new(ACCURACY), QuantileSketch::new(ACCURACY));
+        let (first, second) = values.split_at(1_234);
+        first.iter().for_each(|value| left.insert(*value));
+        second.iter().for_each(|value| right.insert(*value));
+
+        left.merge(&right);
+        assert_eq!(left.count(), 5_000);
+        assert_accurate(&left, values);
+    }
+
+    #[test]
+    fn test_sketch_edge_cases() {
+        let mut sketch = QuantileSketch::new(ACCURACY);
+        assert_eq!(sketch.quantile(0.5), None);
+
+        // Zero, negative and NaN values are counted as zero
+        for value in [0.0, -1.0, f64::NAN] {
+            sketch.insert(value);
+        }
+        sketch.insert(10.0);
+        assert_eq!(sketch.quantile(0.5), Some(0.0));
+        assert!(is_close(sketch.quantile(1.0).unwrap(), 10.0));
+
+        sketch.clear();
+        assert_eq!(sketch.count(), 0);
+        assert_eq!(sketch.quantile(0.99), None);
+    }
+
+    #[test]
+    fn test_rolling_window() {
+        let mut rolling = RollingQuantiles::new(ACCURACY, 100);
+
+        // Old values are forgotten once two windows of new ones have been added
+        (0..1_000).for_each(|_| rolling.insert(1.0));
+        (0..150).for_each(|_| rolling.insert(100.0));
+        let sketch = rolling.sketch();
+        assert_eq!(sketch.count(), 150);
+        assert!(is_close(sketch.quantile(0.0).unwrap(), 100.0));
+
+        // Both windows are estimated over
+        (0..50).for_each(|_| rolling.insert(1.0));
+        let sketch = rolling.sketch();
+        assert_eq!(sketch.count(), 200);
+        assert!(is_close(sketch.quantile(0.1).unwrap(), 1.0));
+        assert!(is_close(sketch.quantile(0.9).unwrap(), 100.0));
+    }
+}
@@ -1,7 +1,14 @@
-use alloy::primitives::b256;
+use alloy::{
+    eips::eip4844::MAX_BLOBS_PER_BLOCK,
+    primitives::{b256, Address},
+    sol_types::Eip712Domain,
+};
 use clap::{Args, ValueEnum};
+use ethereum_consensus::phase0::mainnet::SLOTS_PER_EPOCH;
 use std::time::Duration;
 
+use crate::primitives::{commitment::eip712_domain, parse_address};
+
 /// Default commitment deadline duration.
 ///
 /// The sidecar will stop accepting new commitments for the next block
@@ -28,6 +35,32 @@ const BUILDER_DOMAIN_KURTOSIS: [u8; 32] =
 const BUILDER_DOMAIN_HELDER: [u8; 32] =
     b256!("0000000194c41af484fff7964969e0bdd922f82dff0f4be87a60d0664cc9d1ff").0;
 
+/// Maximum number of blobs in a block from Electra on (EIP-7691).
+pub const MAX_BLOBS_PER_BLOCK_ELECTRA: usize = 9;
+
+/// Forks of the chain that change parameters the sidecar depends on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[allow(missing_docs)]
+pub enum Fork {
+    Deneb,
+    Electra,
+}
+
+impl Fork {
+    /// Get the maximum number of blobs in a block.
+    pub fn max_blobs_per_block(&self) -> usize {
+        match self {
+            Fork::Deneb => MAX_BLOBS_PER_BLOCK,
+            Fork::Electra => MAX_BLOBS_PER_BLOCK_ELECTRA,
+        }
+    }
+
+    /// Returns true if the EIP-7702 set code transactions are valid from this fork on.
+    pub fn allows_set_code_transactions(&self) -> bool {
+        *self >= Fork::Electra
+    }
+}
+
 /// Configuration for the chain the sidecar is running on.
 /// This allows to customize the slot time for custom Kurtosis devnets.
 #[derive(Debug, Clone, Args)]
@@ -43,6 +76,12 @@ pub struct ChainConfig {
         default_value_t = DEFAULT_COMMITMENT_DEADLINE_IN_MILLIS
     )]
     commitment_deadline: u64,
+    /// The time in the slot at which the constraints for the next block are first submitted
+    /// to the relays (parsed as milliseconds), before the commitment deadline. The
+    /// commitments made after it are submitted as incremental updates until the deadline.
+    /// If not provided, the constraints are submitted at the commitment deadline.
+    #[clap(long, env = "BOLT_SIDECAR_CONSTRAINT_SUBMISSION_TIME")]
+    constraint_submission_time: Option<u64>,
     /// The slot time duration in seconds. If provided,
     /// it overrides the default for the selected [Chain].
     #[clap(
@@ -51,6 +90,15 @@ pub struct ChainConfig {
         default_value_t = DEFAULT_SLOT_TIME_IN_SECONDS
     )]
     slot_time: u64,
+    /// The verifying contract of the EIP-712 domain of the typed-data request signatures.
+    /// If not provided, the domain only has a name and a chain ID.
+    #[clap(long, env = "BOLT_SIDECAR_EIP712_VERIFYING_CONTRACT", value_parser = parse_address)]
+    eip712_verifying_contract: Option<Address>,
+    /// The epoch at which the Electra fork activates. From then on, the constraints are signed
+    /// in the builder domain, and no local payloads are built.
+    /// If not provided, the chain is considered to be on Deneb.
+    #[clap(long, env = "BOLT_SIDECAR_ELECTRA_FORK_EPOCH")]
+    electra_fork_epoch: Option<u64>,
 }
 
 impl Default for ChainConfig {
@@ -58,7 +106,10 @@ impl Default for ChainConfig {
         Self {
             chain: Chain::Mainnet,
             commitment_deadline: DEFAULT_COMMITMENT_DEADLINE_IN_MILLIS,
+            constraint_submission_time: None,
             slot_time: DEFAULT_SLOT_TIME_IN_SECONDS,
+            eip712_verifying_contract: None,
+            electra_fork_epoch: None,
         }
     }
 }
@@ -100,6 +151,11 @@ impl ChainConfig {
         self.slot_time
     }
 
+    /// Get the number of slots in an epoch for the given chain.
+    pub fn slots_per_epoch(&self) -> u64 {
+        SLOTS_PER_EPOCH
+    }
+
     /// Get the domain for signing messages on the given chain.
     pub fn builder_domain(&self) -> [u8; 32] {
         match self.chain {
@@ -124,6 +180,48 @@ impl ChainConfig {
     pub fn commitment_deadline(&self) -> Duration {
         Duration::from_millis(self.commitment_deadline)
     }
+
+    /// Get the time in the slot at which the constraints for the next block are first
+    /// submitted, which is the commitment deadline by default.
+    pub fn constraint_submission_time(&self) -> Duration {
+        self.constraint_submission_time.map_or(self.commitment_deadline(), Duration::from_millis)
+    }
+
+    /// Get how long before the start of a slot its commitment deadline is, as the deadline
+    /// is relative to the start of the previous slot.
+    pub fn commitment_deadline_offset(&self) -> Duration {
+        Duration::from_secs(self.slot_time).saturating_sub(self.commitment_deadline())
+    }
+
+    /// Get how long before the start of a slot its constraints are first submitted.
+    pub fn constraint_submission_offset(&self) -> Duration {
+        Duration::from_secs(self.slot_time).saturating_sub(self.constraint_submission_time())
+    }
+
+    /// Get the EIP-712 domain of the typed-data request signatures on the given chain.
+    pub fn eip712_domain(&self) -> Eip712Domain {
+        eip712_domain(self.chain_id(), self.eip712_verifying_contract)
+    }
+
+    /// Get the fork that is active at the given slot, according to the configured schedule.
+    pub fn fork_at(&self, slot: u64) -> Fork {
+        match self.electra_fork_epoch {
+            Some(epoch) if slot / self.slots_per_epoch() >= epoch => Fork::Electra,
+            _ => Fork::Deneb,
+        }
+    }
+
+    /// Get the domain the constraints of the given slot are signed in, if any.
+    ///
+    /// Before Electra the constraints are signed over their bare digest, as the relays
+    /// expect. From Electra on they are signed in the builder domain, which the builder specs
+    /// and the relays compute with the genesis fork version rather than the one of the fork.
+    pub fn constraint_domain(&self, slot: u64) -> Option<[u8; 32]> {
+        match self.fork_at(slot) {
+            Fork::Deneb => None,
+            Fork::Electra => Some(self.builder_domain()),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -141,6 +239,49 @@ impl ChainConfig {
     }
 
     pub fn kurtosis(slot_time_in_seconds: u64, commitment_deadline: u64) -> Self {
-        Self { chain: Chain::Kurtosis, slot_time: slot_time_in_seconds, commitment_deadline }
+        Self {
+            chain: Chain::Kurtosis,
+            slot_time: slot_time_in_seconds,
+            commitment_deadline,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_constraint_submission_time(self, time: u64) -> Self {
+        Self { constraint_submission_time: Some(time), ..self }
+    }
+
+    pub fn with_electra_fork_epoch(self, epoch: u64) -> Self {
+        Self { electra_fork_epoch: Some(epoch), ..self }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::signature::compute_builder_domain;
+
+    #[test]
+    fn test_fork_schedule() {
+        // Without a schedule, every slot is on Deneb
+        let chain = ChainConfig::holesky();
+        assert_eq!(chain.fork_at(u64::MAX), Fork::Deneb);
+        assert_eq!(chain.constraint_domain(u64::MAX), None);
+
+        // Electra activates at the first slot of its epoch
+        let chain = chain.with_electra_fork_epoch(10);
+        let fork_slot = 10 * chain.slots_per_epoch();
+        assert_eq!(chain.fork_at(fork_slot - 1), Fork::Deneb);
+        assert_eq!(chain.fork_at(fork_slot), Fork::Electra);
+        assert_eq!(chain.fork_at(fork_slot).max_blobs_per_block(), 9);
+        assert!(!chain.fork_at(fork_slot - 1).allows_set_code_transactions());
+        assert!(chain.fork_at(fork_slot).allows_set_code_transactions());
+
+        // The constraints are signed in the builder domain from then on, which is computed
+        // with the genesis fork version
+        assert_eq!(chain.constraint_domain(fork_slot - 1), None);
+        let domain = chain.constraint_domain(fork_slot).unwrap();
+        assert_eq!(domain, compute_builder_domain(chain.fork_version(), None));
+        assert_eq!(domain, chain.builder_domain());
     }
 }
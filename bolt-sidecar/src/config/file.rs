@@ -0,0 +1,306 @@
+//! Loading of the command-line options from a TOML config file, given with `--config`.
+//!
+//! The file sets the options by their long name, in snake case or kebab case, such as
+//! `max_commitments = 64` or `relay-urls = ["https://relay-a", "https://relay-b"]`. Arrays are
+//! joined into the comma-separated lists the options expect. An option set on the command line
+//! or in its environment variable takes precedence over the file, which takes precedence over
+//! the default value of the option.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error as _,
+    ffi::OsString,
+    fmt::Write,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use clap::{
+    error::{ContextKind, ContextValue, ErrorKind},
+    parser::ValueSource,
+    ArgAction, ArgMatches, Command,
+};
+use toml::{Spanned, Value};
+
+/// The id of the option giving the path to the config file.
+pub const CONFIG_OPTION: &str = "config";
+
+/// The options that are only given on the command line, and can't be set in the file.
+const COMMAND_LINE_ONLY: [&str; 2] = [CONFIG_OPTION, "print_config"];
+
+/// The placeholder of the values of the secret options in the effective options.
+const REDACTED: &str = "<redacted>";
+
+/// Errors of the config file, with the line they are at.
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum ConfigFileError {
+    #[error("Failed to read the config file {}: {source}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+    #[error("Malformed config file {} at line {line}: {message}", path.display())]
+    Malformed { path: PathBuf, line: usize, message: String },
+    #[error("Unknown key '{key}' in the config file {} at line {line}", path.display())]
+    UnknownKey { path: PathBuf, key: String, line: usize },
+    #[error(
+        "Invalid value of '{key}' in the config file {} at line {line}: {message}",
+        path.display()
+    )]
+    InvalidValue { path: PathBuf, key: String, line: usize, message: String },
+    #[error(transparent)]
+    Options(#[from] clap::Error),
+}
+
+/// A value of the config file, with the line of its key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    value: String,
+    line: usize,
+}
+
+/// The options set in a config file, by id.
+#[derive(Debug, Clone)]
+pub struct ConfigFile {
+    path: PathBuf,
+    entries: BTreeMap<String, Entry>,
+}
+
+impl ConfigFile {
+    /// Reads and parses the config file at the given path.
+    pub fn load(path: &Path) -> Result<Self, ConfigFileError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|source| ConfigFileError::Read { path: path.to_path_buf(), source })?;
+        Self::parse(path, &contents)
+    }
+
+    fn parse(path: &Path, contents: &str) -> Result<Self, ConfigFileError> {
+        let path = path.to_path_buf();
+        let line = |offset: usize| contents[..offset].matches('\n').count() + 1;
+
+        let table: BTreeMap<Spanned<String>, Spanned<Value>> =
+            toml::from_str(contents).map_err(|err| ConfigFileError::Malformed {
+                path: path.clone(),
+                line: err.span().map_or(1, |span| line(span.start)),
+                message: err.message().to_string(),
+            })?;
+
+        let mut entries = BTreeMap::new();
+        for (key, value) in table {
+            let (line, key) = (line(key.span().start), key.into_inner().replace('-', "_"));
+            let Some(value) = option_value(value.into_inner()) else {
+                let message = "expected a string, number, boolean or array of them".to_string();
+                return Err(ConfigFileError::InvalidValue { path, key, line, message });
+            };
+
+            if entries.insert(key.clone(), Entry { value, line }).is_some() {
+                let message = format!("duplicate key '{key}'");
+                return Err(ConfigFileError::Malformed { path, line, message });
+            }
+        }
+
+        Ok(Self { path, entries })
+    }
+
+    fn unknown_key(&self, key: &str) -> ConfigFileError {
+        let (path, key, line) = (self.path.clone(), key.to_string(), self.entries[key].line);
+        ConfigFileError::UnknownKey { path, key, line }
+    }
+
+    fn invalid_value(&self, key: &str, message: String) -> ConfigFileError {
+        let (path, key, line) = (self.path.clone(), key.to_string(), self.entries[key].line);
+        ConfigFileError::InvalidValue { path, key, line, message }
+    }
+
+    /// Returns the error of the file behind a clap error, if it is about a value from it.
+    fn locate_clap_error(
+        &self,
+        command: &Command,
+        from_file: &BTreeSet<String>,
+        err: &clap::Error,
+    ) -> Option<ConfigFileError> {
+        if !matches!(err.kind(), ErrorKind::InvalidValue | ErrorKind::ValueValidation) {
+            return None;
+        }
+        let Some(ContextValue::String(invalid)) = err.get(ContextKind::InvalidArg) else {
+            return None;
+        };
+
+        let key = from_file.iter().find(|key| {
+            let arg = command.get_arguments().find(|arg| arg.get_id() == key.as_str());
+            let long = arg.and_then(|arg| arg.get_long());
+            long.is_some_and(|long| invalid.split(' ').next() == Some(format!("--{long}").as_str()))
+        })?;
+        let message = err.source().map_or_else(|| err.kind().to_string(), |err| err.to_string());
+        Some(self.invalid_value(key, message))
+    }
+}
+
+/// Returns the value of a TOML value as given on the command line.
+fn option_value(value: Value) -> Option<String> {
+    match value {
+        Value::String(value) => Some(value),
+        Value::Integer(value) => Some(value.to_string()),
+        Value::Float(value) => Some(value.to_string()),
+        Value::Boolean(value) => Some(value.to_string()),
+        Value::Array(values) => {
+            let values = values.into_iter().map(|value| match value {
+                Value::Array(_) | Value::Table(_) => None,
+                value => option_value(value),
+            });
+            Some(values.collect::<Option<Vec<_>>>()?.join(","))
+        }
+        Value::Datetime(_) | Value::Table(_) => None,
+    }
+}
+
+/// The options of a command, parsed from the command line, the environment and the config
+/// file.
+#[derive(Debug)]
+pub struct ResolvedOptions {
+    /// The parsed options.
+    pub matches: ArgMatches,
+    /// The command the options were parsed for.
+    command: Command,
+    /// The ids of the options set from the config file.
+    from_file: BTreeSet<String>,
+}
+
+impl ResolvedOptions {
+    /// Parses the options of the command from the arguments, and from the config file of the
+    /// `--config` option if it is given. Errors other than those of the config file are
+    /// reported as by clap, exiting the process.
+    pub fn parse(command: Command, args: Vec<OsString>) -> Result<Self, ConfigFileError> {
+        match Self::try_parse(command, args) {
+            Err(ConfigFileError::Options(err)) => err.exit(),
+            resolved => resolved,
+        }
+    }
+
+    /// Parses the options like [`Self::parse`], but returns the errors of the options as
+    /// [`ConfigFileError::Options`] instead of exiting, such as when the config is reloaded.
+    pub fn try_parse(command: Command, args: Vec<OsString>) -> Result<Self, ConfigFileError> {
+        // A first lenient pass finds the config file, and the options already set
+        let lenient = command.clone().ignore_errors(true).try_get_matches_from(&args).ok();
+        let path = lenient.as_ref().and_then(|m| m.get_one::<PathBuf>(CONFIG_OPTION).cloned());
+        let Some((lenient, path)) = lenient.zip(path) else {
+            let matches = command.clone().try_get_matches_from(args)?;
+            return Ok(Self { matches, command, from_file: BTreeSet::new() });
+        };
+        let file = ConfigFile::load(&path)?;
+
+        // The values of the file are passed as arguments, unless the options are already set,
+        // so that they satisfy the required options and groups like values on the command line
+        let (mut args, mut from_file) = (args, BTreeSet::new());
+        for (key, entry) in &file.entries {
+            let arg = command.get_arguments().find(|arg| arg.get_id() == key.as_str());
+            let Some((arg, long)) = arg.and_then(|arg| Some((arg, arg.get_long()?))) else {
+                return Err(file.unknown_key(key));
+            };
+            if COMMAND_LINE_ONLY.contains(&key.as_str()) {
+                return Err(file.unknown_key(key));
+            }
+
+            let source = lenient.value_source(key);
+            if matches!(source, Some(ValueSource::CommandLine | ValueSource::EnvVariable)) {
+                continue;
+            }
+
+            if matches!(arg.get_action(), ArgAction::SetTrue) {
+                match entry.value.as_str() {
+                    "true" => args.push(format!("--{long}").into()),
+                    "false" => {}
+                    _ => return Err(file.invalid_value(key, "expected a boolean".to_string())),
+                }
+            } else {
+                args.push(format!("--{long}={}", entry.value).into());
+            }
+            from_file.insert(key.clone());
+        }
+
+        // Invalid values from the file are reported with their line
+        let matches = command.clone().try_get_matches_from(args).map_err(|err| {
+            file.locate_clap_error(&command, &from_file, &err).unwrap_or_else(|| err.into())
+        })?;
+
+        Ok(Self { matches, command, from_file })
+    }
+
+    /// Returns the raw values of the options that are set, by id, the values of an option
+    /// being joined with commas. The options only given on the command line are omitted.
+    pub fn values(&self) -> BTreeMap<String, String> {
+        let ids = self.command.get_arguments().map(|arg| arg.get_id().as_str());
+        let ids = ids.filter(|id| !COMMAND_LINE_ONLY.contains(id));
+        let values = ids.filter_map(|id| {
+            let values = self.matches.get_raw(id)?.map(|value| value.to_string_lossy());
+            Some((id.to_string(), values.collect::<Vec<_>>().join(",")))
+        });
+        values.collect()
+    }
+
+    /// Returns the effective options, as a config file annotated with where each option was
+    /// set. The values of the secret options, whose environment values are hidden, are
+    /// redacted.
+    pub fn effective_options(&self) -> String {
+        let mut options = String::new();
+        for arg in self.command.get_arguments().filter(|arg| arg.get_long().is_some()) {
+            let id = arg.get_id().as_str();
+            let Some(source) = self.matches.value_source(id) else { continue };
+            let Some(values) = self.matches.get_raw(id) else { continue };
+            if COMMAND_LINE_ONLY.contains(&id) {
+                continue;
+            }
+
+            let source = match source {
+                ValueSource::CommandLine if self.from_file.contains(id) => "config file",
+                ValueSource::CommandLine => "command line",
+                ValueSource::EnvVariable => "environment",
+                _ => "default",
+            };
+            let value = if arg.is_hide_env_values_set() {
+                REDACTED.to_string()
+            } else {
+                values.map(|value| value.to_string_lossy()).collect::<Vec<_>>().join(",")
+            };
+
+            let _ = writeln!(options, "{id} = {} # {source}", Value::String(value));
+        }
+        options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config_file() {
+        let contents = r#"
+            port = 8017
+            relay-urls = ["https://relay-a.test", "https://relay-b.test"]
+            ssz_constraint_digests = true
+        "#;
+        let file = ConfigFile::parse(Path::new("sidecar.toml"), contents).unwrap();
+
+        let entry = |value: &str, line| Entry { value: value.to_string(), line };
+        let relays = "https://relay-a.test,https://relay-b.test";
+        assert_eq!(file.entries["port"], entry("8017", 2));
+        assert_eq!(file.entries["relay_urls"], entry(relays, 3));
+        assert_eq!(file.entries["ssz_constraint_digests"], entry("true", 4));
+
+        // Syntax errors and unsupported values are reported with their line
+        let err = ConfigFile::parse(Path::new("sidecar.toml"), "port = 1\nrelay_urls = ?\n");
+        assert!(matches!(err, Err(ConfigFileError::Malformed { line: 2, .. })), "{err:?}");
+
+        let err = ConfigFile::parse(Path::new("sidecar.toml"), "port = 1\n[chain]\nid = 1\n");
+        let err = err.unwrap_err();
+        assert!(matches!(err, ConfigFileError::InvalidValue { line: 2, .. }), "{err:?}");
+        assert_eq!(
+            err.to_string(),
+            "Invalid value of 'chain' in the config file sidecar.toml at line 2: expected a \
+             string, number, boolean or array of them"
+        );
+
+        let duplicate = "max-commitments = 1\nmax_commitments = 2\n";
+        let err = ConfigFile::parse(Path::new("sidecar.toml"), duplicate);
+        assert!(matches!(err, Err(ConfigFileError::Malformed { .. })), "{err:?}");
+    }
+}
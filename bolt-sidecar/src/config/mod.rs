@@ -1,23 +1,90 @@
-use std::{fs::read_to_string, path::Path, str::FromStr};
+use std::{
+    collections::{BTreeMap, HashSet},
+    ffi::OsString,
+    fs::read_to_string,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
 
-use alloy::primitives::Address;
+use alloy::{consensus::constants::GWEI_TO_WEI, primitives::Address};
 use blst::min_pk::SecretKey;
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 use eyre::{bail, eyre, Report, Result};
+use parking_lot::RwLock;
 use reqwest::Url;
+use reth_rpc_layer::JwtSecret;
+use serde::{Deserialize, Serialize};
 use std::num::NonZero;
-use tracing::info;
+use tracing::{info, level_filters::LevelFilter};
 
-use crate::crypto::bls::random_bls_secret;
+use crate::{
+    alerts::{
+        AlertCondition, AlertConfig, DEFAULT_ALERT_HOLD_TIME, DEFAULT_ALERT_MIN_INTERVAL,
+        DEFAULT_MAX_MISS_RATE, DEFAULT_MAX_PENDING_RESPONSES,
+    },
+    client::{
+        commit_boost::CommitBoostConfig,
+        retry::{
+            RetryPolicy, DEFAULT_BREAKER_COOLDOWN, DEFAULT_BREAKER_THRESHOLD, DEFAULT_MAX_ATTEMPTS,
+            DEFAULT_MAX_RETRY_DELAY, DEFAULT_RETRY_DELAY,
+        },
+        web3signer::{parse_commitment_pubkey, ClientCert, Web3SignerConfig},
+    },
+    commitments::{
+        antireplay::DEFAULT_REPLAY_WINDOW,
+        audit::DEFAULT_AUDIT_LOG_MAX_BYTES,
+        capture::DEFAULT_CAPTURE_MAX_BYTES,
+        link::{LinkConfig, LinkSecret},
+        pending::{DEFAULT_MAX_INFLIGHT_REQUESTS, DEFAULT_RESPONSE_TIMEOUT},
+        profile::{MethodProfile, MethodProfiles, ADMIN_METHODS, INTERNAL_METHODS, RPC_METHODS},
+        ratelimit::RateLimits,
+        relay::TrustedGateways,
+        tls::{CertPair, TlsConfig, UnknownSni},
+        unix::UnixSocketConfig,
+        whitelist::Whitelist,
+    },
+    common::rng::Rng,
+    crypto::KeystoreConfig,
+    drops::DEFAULT_DROP_CONFIRM_DELAY,
+    finality::Finality,
+    inclusion::DEFAULT_STATUS_RETENTION_SLOTS,
+    primitives::{
+        address::parse_address_list, constraint::ConstraintDigest,
+        identity::DEFAULT_IDENTITY_VALIDITY, parse_address, BlsPublicKey, DelegationAction,
+    },
+    state::{
+        basefee::{MarginBounds, DEFAULT_BLOCK_FULLNESS},
+        drift::{
+            ClockDriftAction, ClockDriftConfig, DEFAULT_CLOCK_DRIFT_BOUND, DEFAULT_MAX_CLOCK_DRIFT,
+        },
+        freshness::DEFAULT_MAX_HEAD_AGE_SLOTS,
+        maintenance::MaintenanceWindow,
+        memory::DEFAULT_MAX_QUEUED_BYTES,
+        quotas::SignerQuotas,
+        readiness::DEFAULT_WARMUP_TIMEOUT,
+        relays::{
+            RelayConfig, DEFAULT_RELAY_EWMA_ALPHA, DEFAULT_RELAY_QUORUM, DEFAULT_RELAY_STAGGER,
+        },
+    },
+};
 
 pub mod validator_indexes;
 pub use validator_indexes::ValidatorIndexes;
 
 pub mod chain;
-pub use chain::ChainConfig;
+pub use chain::{ChainConfig, Fork};
 
 pub mod signing;
-pub use signing::SigningOpts;
+pub use signing::{SignerKind, SigningOpts};
+
+pub mod file;
+use file::ResolvedOptions;
+
+pub mod reload;
+pub use reload::{ConfigReload, ReloadTargets};
 
 /// Default port for the JSON-RPC server exposed by the sidecar.
 pub const DEFAULT_RPC_PORT: u16 = 8000;
@@ -28,15 +95,168 @@ pub const DEFAULT_MEV_BOOST_PROXY_PORT: u16 = 18551;
 /// Command-line options for the Bolt sidecar
 #[derive(Parser, Debug)]
 pub struct Opts {
-    /// Port to listen on for incoming JSON-RPC requests
-    #[clap(long, env = "BOLT_SIDECAR_PORT")]
+    /// Path to a TOML config file setting the options by their long name, such as
+    /// `max_commitments = 64`. The options set on the command line or in the environment
+    /// take precedence over the file.
+    #[clap(long, env = "BOLT_SIDECAR_CONFIG")]
+    pub(super) config: Option<PathBuf>,
+    /// Print the effective options, with where each one was set and the secrets redacted,
+    /// once they are validated, and exit
+    #[clap(long, default_value_t = false)]
+    pub(super) print_config: bool,
+    /// Port to listen on for incoming JSON-RPC requests. With port 0, a free port
+    /// is picked and announced on stdout as `COMMITMENTS_ADDR=<address>`
+    #[clap(long, visible_alias = "commitments-port", env = "BOLT_SIDECAR_PORT")]
     pub(super) port: Option<u16>,
+    /// Port to listen on (on localhost only) for admin JSON-RPC requests.
+    /// The admin server is disabled if not set.
+    #[clap(long, env = "BOLT_SIDECAR_ADMIN_PORT")]
+    pub(super) admin_port: Option<u16>,
+    /// Static token required in the `x-bolt-admin-token` header of every request to the
+    /// admin server. No token is required if not set.
+    #[clap(long, env = "BOLT_SIDECAR_ADMIN_TOKEN", hide_env_values = true)]
+    pub(super) admin_token: Option<String>,
+    /// Port to listen on for internal JSON-RPC requests, which serves the
+    /// same methods as the public port with its own method profile.
+    /// The internal server is disabled if not set.
+    #[clap(long, env = "BOLT_SIDECAR_INTERNAL_PORT")]
+    pub(super) internal_port: Option<u16>,
+    /// Comma-separated list of JSON-RPC methods exposed on the public port.
+    /// All methods are exposed if not set.
+    #[clap(long, env = "BOLT_SIDECAR_PUBLIC_METHODS")]
+    pub(super) public_methods: Option<String>,
+    /// Comma-separated list of JSON-RPC methods exposed on the internal port.
+    /// All methods are exposed if not set.
+    #[clap(long, env = "BOLT_SIDECAR_INTERNAL_METHODS")]
+    pub(super) internal_methods: Option<String>,
+    /// Comma-separated list of JSON-RPC methods exposed on the admin port.
+    /// All admin methods are exposed if not set.
+    #[clap(long, env = "BOLT_SIDECAR_ADMIN_METHODS")]
+    pub(super) admin_methods: Option<String>,
+    /// Path to the PEM-encoded certificate chain presented on the public port by default.
+    /// The public port serves TLS if this or `--tls-hosts` is set, and the certificates
+    /// are reloaded from disk with `admin_reloadCertificates` on the admin server.
+    #[clap(long, env = "BOLT_SIDECAR_TLS_CERT", requires = "tls_key")]
+    pub(super) tls_cert: Option<PathBuf>,
+    /// Path to the PEM-encoded private key of the default certificate
+    #[clap(long, env = "BOLT_SIDECAR_TLS_KEY", requires = "tls_cert")]
+    pub(super) tls_key: Option<PathBuf>,
+    /// Comma-separated list of the certificates presented on the public port for specific
+    /// server names (SNI), as `<hostname>=<cert path>:<key path>`
+    #[clap(long, env = "BOLT_SIDECAR_TLS_HOSTS")]
+    pub(super) tls_hosts: Option<String>,
+    /// What to do with TLS handshakes for a server name without a certificate in
+    /// `--tls-hosts`: present the default certificate (`default`) or reject them (`reject`)
+    #[clap(long, env = "BOLT_SIDECAR_TLS_UNKNOWN_SNI", default_value_t = UnknownSni::Default)]
+    pub(super) tls_unknown_sni: UnknownSni,
+    /// Path to a Unix domain socket to serve the public JSON-RPC methods on, instead of
+    /// `--port`. A stale socket file left at the path is removed on startup.
+    #[clap(long, env = "BOLT_SIDECAR_COMMITMENTS_SOCKET")]
+    pub(super) commitments_socket: Option<PathBuf>,
+    /// The permissions of the socket file of `--commitments-socket`, in octal. Only the owner
+    /// can connect by default (600).
+    #[clap(
+        long,
+        env = "BOLT_SIDECAR_COMMITMENTS_SOCKET_MODE",
+        value_parser = parse_socket_mode,
+        requires = "commitments_socket"
+    )]
+    pub(super) commitments_socket_mode: Option<u32>,
+    /// Directory to write debug request captures to. Capture mode
+    /// can only be enabled from the admin server if this is set.
+    #[clap(long, env = "BOLT_SIDECAR_CAPTURE_DIR")]
+    pub(super) capture_dir: Option<PathBuf>,
+    /// Global size cap for all debug request captures, in bytes
+    #[clap(long, env = "BOLT_SIDECAR_CAPTURE_MAX_BYTES", default_value_t = DEFAULT_CAPTURE_MAX_BYTES)]
+    pub(super) capture_max_bytes: u64,
+    /// Path to append the audit log of the commitment requests and the decisions
+    /// on them to, as JSON lines. The log is rotated daily and when full.
+    #[clap(long, env = "BOLT_SIDECAR_AUDIT_LOG_PATH")]
+    pub(super) audit_log_path: Option<PathBuf>,
+    /// Size at which the audit log is rotated, in bytes
+    #[clap(long, env = "BOLT_SIDECAR_AUDIT_LOG_MAX_BYTES", default_value_t = DEFAULT_AUDIT_LOG_MAX_BYTES)]
+    pub(super) audit_log_max_bytes: u64,
+    /// Path to the persistent store of the sidecar. If not set,
+    /// the store is kept in memory and lost on restart.
+    #[clap(long, env = "BOLT_SIDECAR_DB_PATH")]
+    pub(super) db_path: Option<PathBuf>,
+    /// Path to record a replay log of the driver inputs and decisions to,
+    /// for replaying incidents with the `replay` subcommand
+    #[clap(long, env = "BOLT_SIDECAR_REPLAY_LOG")]
+    pub(super) replay_log: Option<PathBuf>,
+    /// Path to the delegations written by the `delegate` subcommand, which are submitted to
+    /// the relays along with the constraints
+    #[clap(long, env = "BOLT_SIDECAR_DELEGATIONS_PATH")]
+    pub(super) delegations_path: Option<PathBuf>,
+    /// Path to write the bound addresses of all listeners to as JSON, once they are
+    /// all bound. The file is removed on clean shutdown
+    #[clap(long, env = "BOLT_SIDECAR_PORTS_FILE")]
+    pub(super) ports_file: Option<PathBuf>,
     /// URL for the beacon client
     #[clap(long, env = "BOLT_SIDECAR_BEACON_API_URL")]
     pub(super) beacon_api_url: String,
     /// URL for the MEV-Boost sidecar client to use
     #[clap(long, env = "BOLT_SIDECAR_MEVBOOST_URL")]
     pub(super) mevboost_url: String,
+    /// Optional comma-separated URLs of relays the constraints are submitted to directly,
+    /// in addition to MEV-Boost
+    #[clap(long, env = "BOLT_SIDECAR_RELAY_URLS")]
+    pub(super) relay_urls: Option<String>,
+    /// Number of constraint submission targets, MEV-Boost included, that must receive the
+    /// constraints of a slot
+    #[clap(long, env = "BOLT_SIDECAR_RELAY_QUORUM", default_value_t = DEFAULT_RELAY_QUORUM)]
+    pub(super) relay_quorum: usize,
+    /// Delay in milliseconds between the starts of the constraint submissions to two
+    /// successive targets, which are attempted by increasing measured latency
+    #[clap(
+        long,
+        env = "BOLT_SIDECAR_RELAY_STAGGER_MS",
+        default_value_t = DEFAULT_RELAY_STAGGER.as_millis() as u64
+    )]
+    pub(super) relay_stagger_ms: u64,
+    /// Weight of the latest sample, between 0 and 1, in the moving averages of the latency
+    /// and success rate of the constraint submission targets
+    #[clap(long, env = "BOLT_SIDECAR_RELAY_EWMA_ALPHA", default_value_t = DEFAULT_RELAY_EWMA_ALPHA)]
+    pub(super) relay_ewma_alpha: f64,
+    /// Maximum number of attempts to submit the constraints of a slot to a target. Only the
+    /// transient failures are retried, until the start of the slot
+    #[clap(long, env = "BOLT_SIDECAR_RELAY_MAX_ATTEMPTS", default_value_t = DEFAULT_MAX_ATTEMPTS)]
+    pub(super) relay_max_attempts: u32,
+    /// Delay in milliseconds before the first retry of a constraint submission, doubled on
+    /// each following one
+    #[clap(
+        long,
+        env = "BOLT_SIDECAR_RELAY_RETRY_DELAY_MS",
+        default_value_t = DEFAULT_RETRY_DELAY.as_millis() as u64
+    )]
+    pub(super) relay_retry_delay_ms: u64,
+    /// Maximum delay in milliseconds between two attempts of a constraint submission
+    #[clap(
+        long,
+        env = "BOLT_SIDECAR_RELAY_MAX_RETRY_DELAY_MS",
+        default_value_t = DEFAULT_MAX_RETRY_DELAY.as_millis() as u64
+    )]
+    pub(super) relay_max_retry_delay_ms: u64,
+    /// Number of successive transient failures after which the circuit of a target opens,
+    /// failing its constraint submissions right away
+    #[clap(
+        long,
+        env = "BOLT_SIDECAR_RELAY_BREAKER_THRESHOLD",
+        default_value_t = DEFAULT_BREAKER_THRESHOLD
+    )]
+    pub(super) relay_breaker_threshold: u32,
+    /// Time in milliseconds the circuit of a target stays open before a probe is let through
+    #[clap(
+        long,
+        env = "BOLT_SIDECAR_RELAY_BREAKER_COOLDOWN_MS",
+        default_value_t = DEFAULT_BREAKER_COOLDOWN.as_millis() as u64
+    )]
+    pub(super) relay_breaker_cooldown_ms: u64,
+    /// Sign the constraints messages over their SSZ hash tree root, as computed by the relays,
+    /// instead of their keccak256 digest. The messages with atomic, range or exclusion
+    /// constraints have no SSZ encoding, and are still signed over their keccak256 digest.
+    #[clap(long, env = "BOLT_SIDECAR_SSZ_CONSTRAINT_DIGESTS", default_value_t = false)]
+    pub(super) ssz_constraint_digests: bool,
     /// Execution client API URL
     #[clap(long, env = "BOLT_SIDECAR_EXECUTION_API_URL")]
     pub(super) execution_api_url: String,
@@ -52,6 +272,37 @@ pub struct Opts {
     /// Max committed gas per slot
     #[clap(long, env = "BOLT_SIDECAR_MAX_COMMITTED_GAS")]
     pub(super) max_committed_gas: Option<NonZero<u64>>,
+    /// Max bytes the committed transactions can take in the block of a slot, without their
+    /// blob sidecars
+    #[clap(long, env = "BOLT_SIDECAR_MAX_COMMITTED_BYTES")]
+    pub(super) max_committed_bytes: Option<NonZero<usize>>,
+    /// Percentage of the blobs of a block that can be committed to, leaving the rest to
+    /// the builders
+    #[clap(
+        long,
+        env = "BOLT_SIDECAR_MAX_COMMITTED_BLOBS_PERCENT",
+        value_parser = clap::value_parser!(u8).range(0..=100)
+    )]
+    pub(super) max_committed_blobs_percent: Option<u8>,
+    /// Max bytes held by the transactions of accepted requests across all slots. New
+    /// requests are rejected once it is reached, until earlier ones expire
+    #[clap(long, env = "BOLT_SIDECAR_MAX_QUEUED_BYTES")]
+    pub(super) max_queued_bytes: Option<NonZero<usize>>,
+    /// Minimum priority fee per gas (in wei) the transactions of a request must tip
+    #[clap(long, env = "BOLT_SIDECAR_MIN_PRIORITY_FEE")]
+    pub(super) min_priority_fee: Option<u128>,
+    /// Margin (in gwei) enabling the dynamic priority fee floor: the transactions must also
+    /// tip the base fee rise projected until their target slot, plus the margin
+    #[clap(long, env = "BOLT_SIDECAR_PRIORITY_FEE_MARGIN_GWEI")]
+    pub(super) priority_fee_margin_gwei: Option<u64>,
+    /// Percentage of their gas limit the blocks until the target slot are assumed to use
+    /// when projecting its base fee under EIP-1559. Defaults to 100, the worst case
+    #[clap(
+        long,
+        env = "BOLT_SIDECAR_BASEFEE_BLOCK_FULLNESS",
+        value_parser = clap::value_parser!(u8).range(0..=100)
+    )]
+    pub(super) basefee_block_fullness: Option<u8>,
     /// Validator indexes of connected validators that the sidecar
     /// should accept commitments on behalf of. Accepted values:
     /// - a comma-separated list of indexes (e.g. "1,2,3,4")
@@ -63,21 +314,504 @@ pub struct Opts {
     ///
     /// It can either be a hex-encoded string or a file path to a file
     /// containing the hex-encoded secret.
-    #[clap(long, env = "BOLT_SIDECAR_JWT_HEX")]
+    #[clap(long, env = "BOLT_SIDECAR_JWT_HEX", hide_env_values = true)]
     pub(super) jwt_hex: String,
     /// The fee recipient address for fallback blocks
-    #[clap(long, env = "BOLT_SIDECAR_FEE_RECIPIENT")]
+    #[clap(long, env = "BOLT_SIDECAR_FEE_RECIPIENT", value_parser = parse_address)]
     pub(super) fee_recipient: Address,
+    /// Address of an external commitment signer. If set, commitments are not signed
+    /// by the sidecar: valid requests wait until the signature over their digest is
+    /// imported through the admin server with `admin_importSignedCommitment`.
+    #[clap(long, env = "BOLT_SIDECAR_EXTERNAL_SIGNER_ADDRESS", value_parser = parse_address)]
+    pub(super) external_signer_address: Option<Address>,
+    /// Sign every commitments API response with the commitment key, so that clients can
+    /// verify them offline. Each response costs a signature, which is slow with remote signers.
+    #[clap(long, env = "BOLT_SIDECAR_SIGN_RESPONSES", default_value_t = false)]
+    pub(super) sign_responses: bool,
+    /// Optional path to a file with the hex-encoded secret of the JWT bearer tokens required
+    /// on the JSON-RPC listeners, like the `jwt.hex` of the engine API. If set, requests
+    /// without an HS256 token signed with it are rejected before their signature is checked.
+    #[clap(long, env = "BOLT_SIDECAR_JWT_SECRET_FILE")]
+    pub(super) jwt_secret_file: Option<PathBuf>,
+    /// Validity period of the identity document served at `GET /identity`, in seconds.
+    /// The document is signed with the BLS key and renewed ahead of its expiry.
+    #[clap(
+        long,
+        env = "BOLT_SIDECAR_IDENTITY_VALIDITY_SECS",
+        default_value_t = DEFAULT_IDENTITY_VALIDITY.as_secs()
+    )]
+    pub(super) identity_validity_secs: u64,
+    /// Optional comma-separated whitelist of request signer addresses.
+    /// If set, requests from any other signer are rejected.
+    #[clap(long, env = "BOLT_SIDECAR_WHITELIST")]
+    pub(super) whitelist: Option<String>,
+    /// Optional path to a whitelist file, instead of `--whitelist`: either a plain list of
+    /// addresses, or a JSON object of the entries by address, with their optional expiry,
+    /// label and quota overrides. Requests from signers with an expired entry are rejected.
+    #[clap(long, env = "BOLT_SIDECAR_WHITELIST_FILE", conflicts_with("whitelist"))]
+    pub(super) whitelist_file: Option<PathBuf>,
+    /// Optional comma-separated addresses of the gateways trusted to relay requests, such as
+    /// sidecars in forwarding mode. Requests they relay with a valid attestation are attributed
+    /// to their original signer, instead of the gateway.
+    #[clap(long, env = "BOLT_SIDECAR_TRUSTED_GATEWAYS")]
+    pub(super) trusted_gateways: Option<String>,
+    /// Accept the EIP-1271 signatures of smart-contract wallets, such as Safes. A signature
+    /// that wasn't made by the signer in the header is checked by the contract at its address,
+    /// which costs a call to the execution client.
+    #[clap(long, env = "BOLT_SIDECAR_ENABLE_ERC1271_AUTH", default_value_t = false)]
+    pub(super) enable_erc1271_auth: bool,
+    /// Reject the inclusion requests signed over their legacy digest, which isn't scoped to the
+    /// chain and can be replayed on other chains. They are accepted with a warning otherwise,
+    /// until the end of their deprecation window.
+    #[clap(long, env = "BOLT_SIDECAR_REJECT_LEGACY_DIGESTS", default_value_t = false)]
+    pub(super) reject_legacy_digests: bool,
+    /// Require the signature header of the signed requests to carry the UNIX timestamp in
+    /// milliseconds of the signature, as `<signer>:<signature>:<timestamp>`, and reject the
+    /// requests signed outside of `--replay-window-secs` or already received within it.
+    #[clap(long, env = "BOLT_SIDECAR_ENABLE_REPLAY_PROTECTION", default_value_t = false)]
+    pub(super) enable_replay_protection: bool,
+    /// Window around the current time in which the signature timestamps are accepted by the
+    /// replay protection, in seconds.
+    #[clap(
+        long,
+        env = "BOLT_SIDECAR_REPLAY_WINDOW_SECS",
+        default_value_t = DEFAULT_REPLAY_WINDOW.as_secs()
+    )]
+    pub(super) replay_window_secs: u64,
+    /// Maximum sustained number of inclusion requests per second of each signer.
+    /// Requests above it are rejected with a hint of when to retry. Unlimited if not set.
+    #[clap(long, env = "BOLT_SIDECAR_RATE_LIMIT_PER_SEC")]
+    pub(super) rate_limit_per_sec: Option<u32>,
+    /// Number of inclusion requests a signer can send at once above the sustained rate,
+    /// after being idle. Defaults to `--rate-limit-per-sec`.
+    #[clap(long, env = "BOLT_SIDECAR_RATE_LIMIT_BURST", requires("rate_limit_per_sec"))]
+    pub(super) rate_limit_burst: Option<u32>,
+    /// Max gas each signer can hold committed in a slot. Unlimited if not set, and
+    /// overridden by the quotas of the whitelist entries
+    #[clap(long, env = "BOLT_SIDECAR_MAX_GAS_PER_SIGNER_PER_SLOT")]
+    pub(super) max_gas_per_signer_per_slot: Option<NonZero<u64>>,
+    /// Max number of transactions each signer can hold committed in a slot. Unlimited if
+    /// not set, and overridden by the quotas of the whitelist entries
+    #[clap(long, env = "BOLT_SIDECAR_MAX_TXS_PER_SIGNER_PER_SLOT")]
+    pub(super) max_txs_per_signer_per_slot: Option<NonZero<u64>>,
+    /// Max number of commitments each signer can obtain for the slots of an epoch.
+    /// Unlimited if not set, and overridden by the quotas of the whitelist entries
+    #[clap(long, env = "BOLT_SIDECAR_MAX_COMMITMENTS_PER_SIGNER_PER_EPOCH")]
+    pub(super) max_commitments_per_signer_per_epoch: Option<NonZero<u64>>,
+    /// Delay after which the drop of a commitment from the admin API is confirmed, in
+    /// seconds, unless it is confirmed or restored earlier. Pending drops are still submitted.
+    #[clap(
+        long,
+        env = "BOLT_SIDECAR_DROP_CONFIRM_DELAY_SECS",
+        default_value_t = DEFAULT_DROP_CONFIRM_DELAY.as_secs()
+    )]
+    pub(super) drop_confirm_delay_secs: u64,
     /// Secret BLS key to sign fallback payloads with
     /// (If not provided, a random key will be used)
-    #[clap(long, env = "BOLT_SIDECAR_BUILDER_PRIVATE_KEY")]
+    #[clap(long, env = "BOLT_SIDECAR_BUILDER_PRIVATE_KEY", hide_env_values = true)]
     pub(super) builder_private_key: Option<String>,
+    /// Optional URL of a registry where clients can discover the sidecar
+    /// responsible for a slot that isn't served by this one. It is returned
+    /// as a hint when rejecting requests for slots of other proposers.
+    #[clap(long, env = "BOLT_SIDECAR_REGISTRY_HINT_URL")]
+    pub(super) registry_hint_url: Option<Url>,
+    /// Comma-separated list of maintenance windows during which a validator won't propose,
+    /// formatted as `<validator index>=epoch:<from>-<to>` or
+    /// `<validator index>=time:<from>-<to>` with UNIX timestamps, bounds included.
+    /// Requests targeting their slots are rejected.
+    #[clap(long, env = "BOLT_SIDECAR_MAINTENANCE_WINDOWS")]
+    pub(super) maintenance_windows: Option<String>,
+    /// Maximum time to wait at startup for proposer duties and execution state to be
+    /// prefetched before accepting requests, in milliseconds. If it elapses, the
+    /// sidecar starts accepting requests anyway in a degraded state.
+    #[clap(long, env = "BOLT_SIDECAR_WARMUP_TIMEOUT_MS")]
+    pub(super) warmup_timeout_ms: Option<u64>,
+    /// Maximum time the driver has to respond to a commitment request, in milliseconds.
+    /// If it elapses, the client gets a timeout error, and the request may still be
+    /// committed to.
+    #[clap(
+        long,
+        env = "BOLT_SIDECAR_RESPONSE_TIMEOUT_MS",
+        default_value_t = DEFAULT_RESPONSE_TIMEOUT.as_millis() as u64
+    )]
+    pub(super) response_timeout_ms: u64,
+    /// Maximum number of commitment requests dispatched to the driver, or waiting to be.
+    /// Requests above it are rejected as overloaded instead of waiting.
+    #[clap(
+        long,
+        env = "BOLT_SIDECAR_MAX_INFLIGHT_REQUESTS",
+        default_value_t = DEFAULT_MAX_INFLIGHT_REQUESTS
+    )]
+    pub(super) max_inflight_requests: usize,
+    /// Maximum age of the latest beacon chain and execution heads, in slots. If either head
+    /// gets older, the sidecar rejects requests as not synced until fresh heads resume.
+    #[clap(
+        long,
+        env = "BOLT_SIDECAR_MAX_HEAD_AGE_SLOTS",
+        default_value_t = DEFAULT_MAX_HEAD_AGE_SLOTS
+    )]
+    pub(super) max_head_age_slots: u64,
+    /// Drift of the host clock from the chain above which a warning is logged, in
+    /// milliseconds
+    #[clap(
+        long,
+        env = "BOLT_SIDECAR_MAX_CLOCK_DRIFT_MS",
+        default_value_t = DEFAULT_MAX_CLOCK_DRIFT.as_millis() as u64
+    )]
+    pub(super) max_clock_drift_ms: u64,
+    /// Drift of the host clock from the chain above which requests are rejected as not
+    /// synced with the `pause` action, in milliseconds
+    #[clap(
+        long,
+        env = "BOLT_SIDECAR_CLOCK_DRIFT_BOUND_MS",
+        default_value_t = DEFAULT_CLOCK_DRIFT_BOUND.as_millis() as u64
+    )]
+    pub(super) clock_drift_bound_ms: u64,
+    /// What to do when the host clock drifts from the chain: `warn` only logs it, `correct`
+    /// also corrects the slot clock by the estimated drift, and `pause` also rejects
+    /// requests while the drift exceeds the bound
+    #[clap(long, env = "BOLT_SIDECAR_CLOCK_DRIFT_ACTION", default_value_t = ClockDriftAction::Warn)]
+    pub(super) clock_drift_action: ClockDriftAction,
+    /// Time-to-live of the validator leases in the persistent store, in milliseconds.
+    /// If set, the sidecar only commits for validators whose lease it holds, so that
+    /// several instances sharing the same `--db-path` never commit for the same validator.
+    #[clap(long, env = "BOLT_SIDECAR_VALIDATOR_LEASE_TTL_MS")]
+    pub(super) validator_lease_ttl_ms: Option<u64>,
+    /// Identifier of this sidecar instance in the validator leases.
+    /// A random identifier is used if not set.
+    #[clap(long, env = "BOLT_SIDECAR_INSTANCE_ID")]
+    pub(super) instance_id: Option<String>,
+    /// Port to serve Prometheus metrics on. Metrics are disabled if not set.
+    #[clap(long, env = "BOLT_SIDECAR_METRICS_PORT")]
+    pub(super) metrics_port: Option<u16>,
+    /// URL of a webhook to post alerts on critical conditions to, as JSON.
+    /// It must use HTTPS, unless it points to the local host.
+    #[clap(long, env = "BOLT_SIDECAR_ALERT_WEBHOOK_URL")]
+    pub(super) alert_webhook_url: Option<Url>,
+    /// Command to execute for each alert on critical conditions, with the alert JSON on its stdin
+    #[clap(long, env = "BOLT_SIDECAR_ALERT_COMMAND")]
+    pub(super) alert_command: Option<PathBuf>,
+    /// Comma-separated list of the conditions to raise alerts for, among `signer_unavailable`,
+    /// `miss_rate_breached`, `store_write_failure`, `store_degraded`,
+    /// `relay_submission_failure` and `pending_responses_backlog`.
+    /// Alerts are raised for all of them if not set.
+    #[clap(long, env = "BOLT_SIDECAR_ALERT_CONDITIONS")]
+    pub(super) alert_conditions: Option<String>,
+    /// Time a condition must go without being raised before its alert is resolved, in seconds
+    #[clap(
+        long,
+        env = "BOLT_SIDECAR_ALERT_HOLD_SECS",
+        default_value_t = DEFAULT_ALERT_HOLD_TIME.as_secs()
+    )]
+    pub(super) alert_hold_secs: u64,
+    /// Minimum time between two alerts raised for the same condition, in seconds
+    #[clap(
+        long,
+        env = "BOLT_SIDECAR_ALERT_MIN_INTERVAL_SECS",
+        default_value_t = DEFAULT_ALERT_MIN_INTERVAL.as_secs()
+    )]
+    pub(super) alert_min_interval_secs: u64,
+    /// Share of the committed transactions missed from their slot over the last hour,
+    /// between 0 and 1, above which the miss-rate alert is raised
+    #[clap(long, env = "BOLT_SIDECAR_ALERT_MAX_MISS_RATE", default_value_t = DEFAULT_MAX_MISS_RATE)]
+    pub(super) alert_max_miss_rate: f64,
+    /// Number of commitment requests awaiting the response of the driver above which the
+    /// pending-responses alert is raised
+    #[clap(
+        long,
+        env = "BOLT_SIDECAR_ALERT_MAX_PENDING_RESPONSES",
+        default_value_t = DEFAULT_MAX_PENDING_RESPONSES
+    )]
+    pub(super) alert_max_pending_responses: usize,
+    /// Finality the reconciliation of a commitment must reach before it counts towards the
+    /// miss-rate alert: `seen`, `justified` or `finalized`
+    #[clap(long, env = "BOLT_SIDECAR_RECONCILIATION_FINALITY", default_value_t = Finality::Seen)]
+    pub(super) reconciliation_finality: Finality,
+    /// Number of slots the inclusion status of a commitment is kept for after its target
+    /// slot, after which `bolt_getCommitmentStatus` reports it as unknown
+    #[clap(
+        long,
+        env = "BOLT_SIDECAR_COMMITMENT_STATUS_RETENTION_SLOTS",
+        default_value_t = DEFAULT_STATUS_RETENTION_SLOTS
+    )]
+    pub(super) commitment_status_retention_slots: u64,
+    /// Interval at which a synthetic probe request is run through the signers, the store and
+    /// the relay, in seconds. The sidecar reports as unready while the probe fails.
+    /// Probes are disabled if not set.
+    #[clap(long, env = "BOLT_SIDECAR_PROBE_INTERVAL_SECS")]
+    pub(super) probe_interval_secs: Option<u64>,
+    /// Adapt the safety margin applied to base fee projections to the observed
+    /// projection errors, within `--basefee-margin-min` and `--basefee-margin-max`.
+    #[clap(long, env = "BOLT_SIDECAR_ADAPTIVE_BASEFEE_MARGIN", default_value_t = false)]
+    pub(super) adaptive_basefee_margin: bool,
+    /// Minimum safety margin applied to base fee projections, as a multiplier
+    /// of the maximum EIP-1559 base fee
+    #[clap(long, env = "BOLT_SIDECAR_BASEFEE_MARGIN_MIN", default_value_t = 1.0)]
+    pub(super) basefee_margin_min: f64,
+    /// Maximum safety margin applied to base fee projections, as a multiplier
+    /// of the maximum EIP-1559 base fee
+    #[clap(long, env = "BOLT_SIDECAR_BASEFEE_MARGIN_MAX", default_value_t = 2.0)]
+    pub(super) basefee_margin_max: f64,
+    /// Maximum level of the logs, one of `off`, `error`, `warn`, `info`, `debug` or `trace`.
+    /// It can be changed without a restart by reloading the config with SIGHUP
+    #[clap(long, env = "BOLT_SIDECAR_LOG_LEVEL", default_value_t = LevelFilter::INFO)]
+    pub(super) log_level: LevelFilter,
     /// Chain config for the chain on which the sidecar is running
     #[clap(flatten)]
     pub(super) chain: ChainConfig,
     /// Commitment signing options.
     #[clap(flatten)]
     pub(super) signing: SigningOpts,
+    /// The raw values of the options, by id, against which a reloaded config is diffed
+    #[clap(skip)]
+    pub(super) options: BTreeMap<String, String>,
+}
+
+/// Subcommands of the sidecar binary, which run instead of the sidecar itself.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Print a report of the fees earned by commitments
+    Earnings(EarningsOpts),
+    /// Replay a recorded replay log, checking that the decisions match the recorded ones
+    Replay(ReplayOpts),
+    /// Manage the persistent store of the sidecar
+    Db(DbOpts),
+    /// Validate a commitment request against a snapshot of the chain state, without running
+    /// the sidecar
+    Simulate(SimulateOpts),
+    /// Run the commitments API alone, forwarding the requests to a driver in another process
+    Api(ApiOpts),
+    /// Run the sidecar, also accepting the requests of commitments API processes over a link
+    Driver(Box<DriverOpts>),
+    /// Sign the delegations of the validator keys to a delegatee key, or their revocations
+    Delegate(DelegateOpts),
+}
+
+/// Command-line options for the `api` subcommand
+#[derive(Parser, Debug)]
+pub struct ApiOpts {
+    /// Port to listen on for incoming JSON-RPC requests
+    #[clap(
+        long,
+        visible_alias = "commitments-port",
+        env = "BOLT_SIDECAR_PORT",
+        default_value_t = DEFAULT_RPC_PORT
+    )]
+    pub port: u16,
+    /// Address of the link listener of the driver, as `<host>:<port>`
+    #[clap(long, env = "BOLT_SIDECAR_LINK_ADDR")]
+    pub link_addr: String,
+    /// Secret shared with the driver to authenticate the link, as 32 bytes in hex
+    #[clap(long, env = "BOLT_SIDECAR_LINK_SECRET", hide_env_values = true)]
+    pub link_secret: LinkSecret,
+    /// Maximum time the driver has to respond to a commitment request, in milliseconds
+    #[clap(
+        long,
+        env = "BOLT_SIDECAR_RESPONSE_TIMEOUT_MS",
+        default_value_t = DEFAULT_RESPONSE_TIMEOUT.as_millis() as u64
+    )]
+    pub response_timeout_ms: u64,
+    /// Maximum number of commitment requests forwarded to the driver, or waiting to be
+    #[clap(
+        long,
+        env = "BOLT_SIDECAR_MAX_INFLIGHT_REQUESTS",
+        default_value_t = DEFAULT_MAX_INFLIGHT_REQUESTS
+    )]
+    pub max_inflight_requests: usize,
+    /// UNIX timestamp of the genesis of the chain, reported by `bolt_getChainInfo`
+    #[clap(long, env = "BOLT_SIDECAR_GENESIS_TIME", default_value_t = 0)]
+    pub genesis_time: u64,
+    /// Chain config for the chain the driver is running on
+    #[clap(flatten)]
+    pub chain: ChainConfig,
+}
+
+/// Command-line options for the `driver` subcommand: those of the sidecar, and of the link
+/// listener.
+#[derive(Parser, Debug)]
+pub struct DriverOpts {
+    /// Port to accept the links of the commitments API processes on
+    #[clap(long, env = "BOLT_SIDECAR_LINK_PORT")]
+    pub(super) link_port: u16,
+    /// Secret shared with the commitments API processes to authenticate the link, as 32
+    /// bytes in hex
+    #[clap(long, env = "BOLT_SIDECAR_LINK_SECRET", hide_env_values = true)]
+    pub(super) link_secret: LinkSecret,
+    #[clap(flatten)]
+    pub(super) sidecar: Opts,
+}
+
+/// Command-line options for the `delegate` subcommand
+#[derive(Parser, Debug)]
+pub struct DelegateOpts {
+    /// Public key of the delegatee, which may sign the constraints of the validators
+    #[clap(long, value_parser = parse_bls_pubkey)]
+    pub delegatee_pubkey: BlsPublicKey,
+    /// Address of the slasher contract of the constraints signed by the delegatee
+    #[clap(long, value_parser = parse_address, default_value_t = Address::ZERO)]
+    pub slasher: Address,
+    /// Sign revocations of the delegations instead
+    #[clap(long, default_value_t = false)]
+    pub revoke: bool,
+    /// Path to write the signed delegations to, as JSON
+    #[clap(long, default_value = "delegations.json")]
+    pub out: PathBuf,
+    /// The signer holding the validator keys
+    #[clap(flatten)]
+    pub signing: SigningOpts,
+}
+
+impl DelegateOpts {
+    /// Returns the action of the delegation messages to sign.
+    pub fn action(&self) -> DelegationAction {
+        if self.revoke {
+            DelegationAction::Revoke
+        } else {
+            DelegationAction::Delegate
+        }
+    }
+}
+
+/// Command-line options for the `db` subcommand
+#[derive(Parser, Debug)]
+pub struct DbOpts {
+    #[clap(subcommand)]
+    #[allow(missing_docs)]
+    pub command: DbCommand,
+}
+
+/// Subcommands of the `db` subcommand
+#[derive(Subcommand, Debug)]
+pub enum DbCommand {
+    /// Apply the pending schema migrations of the store
+    Migrate(MigrateOpts),
+}
+
+/// Command-line options for the `db migrate` subcommand
+#[derive(Parser, Debug)]
+pub struct MigrateOpts {
+    /// Path to the persistent store of the sidecar
+    #[clap(long, env = "BOLT_SIDECAR_DB_PATH")]
+    pub db_path: PathBuf,
+    /// Only report the pending migrations, without applying them
+    #[clap(long, default_value_t = false)]
+    pub dry_run: bool,
+}
+
+/// Command-line options for the `replay` subcommand
+#[derive(Parser, Debug)]
+pub struct ReplayOpts {
+    /// Path to the replay log recorded with `--replay-log`
+    pub log: PathBuf,
+}
+
+/// Command-line options for the `simulate` subcommand
+#[derive(Parser, Debug)]
+pub struct SimulateOpts {
+    /// Path to the inclusion request to validate, as sent to `bolt_requestInclusion`
+    #[clap(long)]
+    pub request: PathBuf,
+    /// Path to the snapshot of the slot context and account states to validate against
+    #[clap(long)]
+    pub state: Option<PathBuf>,
+    /// Execution client API URL to fetch the account states from, instead of the snapshot
+    #[clap(long)]
+    pub execution_api_url: Option<Url>,
+    /// The signer of the request, as authenticated by the signature header. Defaults to the
+    /// zero address
+    #[clap(long, value_parser = parse_address)]
+    pub signer: Option<Address>,
+    /// Chain config for the chain the request targets
+    #[clap(flatten)]
+    pub chain: ChainConfig,
+}
+
+/// Command-line options for the `earnings` subcommand
+#[derive(Parser, Debug)]
+pub struct EarningsOpts {
+    /// Path to the persistent store of the sidecar
+    #[clap(long, env = "BOLT_SIDECAR_DB_PATH")]
+    pub db_path: PathBuf,
+    /// First day of the report (inclusive), formatted as YYYY-MM-DD (UTC)
+    #[clap(long, value_parser = parse_day_arg)]
+    pub from: u64,
+    /// Last day of the report (inclusive), formatted as YYYY-MM-DD (UTC)
+    #[clap(long, value_parser = parse_day_arg)]
+    pub to: u64,
+    /// Print the report as CSV instead of JSON
+    #[clap(long, default_value_t = false)]
+    pub csv: bool,
+}
+
+fn parse_day_arg(date: &str) -> Result<u64> {
+    crate::earnings::parse_day(date).ok_or_else(|| eyre!("Invalid date, expected YYYY-MM-DD"))
+}
+
+fn parse_bls_pubkey(pubkey: &str) -> Result<BlsPublicKey> {
+    let bytes = hex::decode(pubkey.trim_start_matches("0x"))?;
+    BlsPublicKey::try_from(bytes.as_slice()).map_err(|e| eyre!("Invalid BLS public key: {e:?}"))
+}
+
+fn parse_socket_mode(mode: &str) -> Result<u32> {
+    match u32::from_str_radix(mode.trim_start_matches("0o"), 8) {
+        Ok(mode) if mode <= 0o777 => Ok(mode),
+        _ => bail!("Invalid socket mode, expected octal permissions such as 600"),
+    }
+}
+
+#[derive(Parser)]
+struct CommandCli {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+impl Command {
+    /// Parse a subcommand from the command-line, if the first argument is one.
+    /// Returns `None` if the sidecar should run normally.
+    pub fn parse_from_cli() -> Result<Option<Self>> {
+        let Some(name) = std::env::args().nth(1) else { return Ok(None) };
+        if !Self::has_subcommand(&name) {
+            return Ok(None);
+        }
+
+        // The driver takes the options of the sidecar, which can be set in a config file
+        if name == "driver" {
+            let args = std::env::args_os().skip(1).collect();
+            let (mut opts, options) = parse_opts::<DriverOpts>(driver_command(), args)?;
+            opts.sidecar.options = options;
+            return Ok(Some(Self::Driver(Box::new(opts))));
+        }
+
+        Ok(Some(CommandCli::parse().command))
+    }
+}
+
+/// Returns the command of the `driver` subcommand, parsed from the arguments after its name.
+fn driver_command() -> clap::Command {
+    DriverOpts::command().name("driver").bin_name("bolt-sidecar driver")
+}
+
+/// Parses the options of a command from the arguments, the environment and the config file
+/// of `--config`, in this order of precedence, along with their raw values. With
+/// `--print-config`, the effective options are printed once they are validated, and the
+/// process exits.
+fn parse_opts<T>(
+    command: clap::Command,
+    args: Vec<OsString>,
+) -> Result<(T, BTreeMap<String, String>)>
+where
+    T: FromArgMatches,
+    Config: TryFrom<T, Error = Report>,
+{
+    let resolved = ResolvedOptions::parse(command, args)?;
+    let opts = || T::from_arg_matches(&resolved.matches).unwrap_or_else(|err| err.exit());
+
+    if resolved.matches.get_flag("print_config") {
+        Config::try_from(opts())?;
+        print!("{}", resolved.effective_options());
+        std::process::exit(0);
+    }
+    Ok((opts(), resolved.values()))
 }
 
 /// Configuration options for the sidecar. These are parsed from
@@ -86,24 +820,85 @@ pub struct Opts {
 pub struct Config {
     /// Port to listen on for incoming JSON-RPC requests
     pub rpc_port: u16,
+    /// Port to listen on for admin JSON-RPC requests, if enabled
+    pub admin_port: Option<u16>,
+    /// Token required in the headers of the admin requests, if any
+    pub admin_token: Option<String>,
+    /// Port to listen on for internal JSON-RPC requests, if enabled
+    pub internal_port: Option<u16>,
+    /// The JSON-RPC methods exposed by each listener
+    pub method_profiles: MethodProfiles,
+    /// The TLS certificates of the public listener, if it serves TLS
+    pub tls: Option<TlsConfig>,
+    /// The Unix domain socket the public listener is served on instead of `rpc_port`, if any
+    pub unix_socket: Option<UnixSocketConfig>,
+    /// Directory to write debug request captures to
+    pub capture_dir: Option<PathBuf>,
+    /// Global size cap for all debug request captures, in bytes
+    pub capture_max_bytes: u64,
+    /// Path to append the audit log of the commitment decisions to, if any
+    pub audit_log: Option<PathBuf>,
+    /// Size at which the audit log is rotated, in bytes
+    pub audit_log_max_bytes: u64,
+    /// Path to the persistent store, in memory if not set
+    pub db_path: Option<PathBuf>,
+    /// Path to record the replay log to, if recording
+    pub replay_log: Option<PathBuf>,
+    /// Path to the signed delegations submitted along with the constraints, if any
+    pub delegations_path: Option<PathBuf>,
+    /// Path to write the bound addresses of the listeners to, if set
+    pub ports_file: Option<PathBuf>,
     /// The MEV-Boost proxy server port to listen on
     pub mevboost_proxy_port: u16,
     /// URL for the MEV-Boost sidecar client to use
     pub mevboost_url: Url,
+    /// The additional relays the constraints are submitted to, and their submission order
+    pub relays: RelayConfig,
+    /// The digest of the constraints messages signed by the sidecar
+    pub constraint_digest: ConstraintDigest,
     /// URL for the beacon client API URL
     pub beacon_api_url: Url,
     /// The execution API url
     pub execution_api_url: Url,
     /// The engine API url
     pub engine_api_url: Url,
-    /// URL for the commit-boost sidecar
-    pub commit_boost_url: Option<Url>,
+    /// The Commit-Boost signer, if it holds the keys rather than the sidecar
+    pub commit_boost: Option<CommitBoostConfig>,
+    /// The Web3Signer signer, if it holds the keys rather than the sidecar
+    pub web3signer: Option<Web3SignerConfig>,
     /// Private key to use for signing preconfirmation requests
     pub private_key: Option<SecretKey>,
+    /// The keystores of the validator keys, decrypted at startup, if the local signer loads
+    /// its keys from them rather than from the private key
+    pub keystore: Option<KeystoreConfig>,
     /// The jwt.hex secret to authenticate calls to the engine API
     pub jwt_hex: String,
+    /// The secret of the JWT bearer tokens required on the JSON-RPC listeners, if any
+    pub api_jwt_secret: Option<JwtSecret>,
     /// The fee recipient address for fallback blocks
     pub fee_recipient: Address,
+    /// The address of the external commitment signer, if commitments are signed externally
+    pub external_signer: Option<Address>,
+    /// Whether the commitments API responses are signed with the commitment key
+    pub sign_responses: bool,
+    /// Validity period of the identity documents signed by the sidecar
+    pub identity_validity: Duration,
+    /// Optional whitelist of request signers
+    pub whitelist: Option<Whitelist>,
+    /// The gateways trusted to attest the original signer of the requests they relay
+    pub trusted_gateways: TrustedGateways,
+    /// Whether the EIP-1271 signatures of smart-contract wallets are accepted
+    pub erc1271_auth: bool,
+    /// Whether the signatures over the legacy digests of the inclusion requests are accepted
+    pub accept_legacy_digests: bool,
+    /// The window of the signature timestamps, if the replay protection is enabled
+    pub replay_window: Option<Duration>,
+    /// The rate of the inclusion requests of each signer, if limited
+    pub rate_limits: Option<RateLimits>,
+    /// The quotas of committed space of each signer
+    pub signer_quotas: SignerQuotas,
+    /// Delay after which the drops of commitments from the admin API are confirmed
+    pub drop_confirm_delay: Duration,
     /// Operating limits for the sidecar
     pub limits: Limits,
     /// Validator indexes of connected validators that the
@@ -112,37 +907,239 @@ pub struct Config {
     /// Local bulider private key for signing fallback payloads.
     /// If not provided, a random key will be used.
     pub builder_private_key: SecretKey,
+    /// Registry URL returned as a hint to clients targeting slots
+    /// that are not assigned to our validators
+    pub registry_hint_url: Option<Url>,
+    /// The maintenance windows during which our validators won't propose
+    pub maintenance_windows: Vec<MaintenanceWindow>,
+    /// Maximum time to wait for the startup warmup before accepting requests
+    pub warmup_timeout: Duration,
+    /// Maximum time the driver has to respond to a commitment request
+    pub response_timeout: Duration,
+    /// Maximum number of commitment requests in flight, above which new ones are rejected
+    pub max_inflight_requests: usize,
+    /// The link listener accepting the requests of commitments API processes, if enabled
+    pub link: Option<LinkConfig>,
+    /// Maximum age of the chain heads, in slots, before requests are rejected as not synced
+    pub max_head_age_slots: u64,
+    /// The detection of the drift of the host clock, and what to do about it
+    pub clock_drift: ClockDriftConfig,
+    /// Time-to-live of the validator leases, if coordination between instances is enabled
+    pub validator_lease_ttl: Option<Duration>,
+    /// Identifier of this sidecar instance in the validator leases
+    pub instance_id: String,
+    /// Port to serve Prometheus metrics on, if enabled
+    pub metrics_port: Option<u16>,
+    /// The alerts on critical conditions and the sinks they are delivered to
+    pub alerts: AlertConfig,
+    /// Finality the reconciliation of a commitment must reach before it counts towards the
+    /// miss-rate alert
+    pub reconciliation_finality: Finality,
+    /// Number of slots the inclusion statuses of the commitments are kept for
+    pub commitment_status_retention_slots: u64,
+    /// Interval of the synthetic probes of the pipeline, if enabled
+    pub probe_interval: Option<Duration>,
+    /// Bounds of the adaptive base fee projection margin, if enabled
+    pub basefee_margin: Option<MarginBounds>,
+    /// Maximum level of the logs
+    pub log_level: LevelFilter,
     /// The chain on which the sidecar is running
     pub chain: ChainConfig,
+    /// The raw values of the options the config was parsed from, by id
+    pub options: BTreeMap<String, String>,
+    /// Source of the randomness of the sidecar, such as its generated keys
+    pub rng: Rng,
 }
 
 impl Default for Config {
     fn default() -> Self {
+        let rng = Rng::from_entropy();
         Self {
             rpc_port: DEFAULT_RPC_PORT,
+            admin_port: None,
+            admin_token: None,
+            internal_port: None,
+            method_profiles: MethodProfiles::default(),
+            tls: None,
+            unix_socket: None,
+            capture_dir: None,
+            capture_max_bytes: DEFAULT_CAPTURE_MAX_BYTES,
+            audit_log: None,
+            audit_log_max_bytes: DEFAULT_AUDIT_LOG_MAX_BYTES,
+            db_path: None,
+            replay_log: None,
+            delegations_path: None,
+            ports_file: None,
             mevboost_proxy_port: DEFAULT_MEV_BOOST_PROXY_PORT,
-            commit_boost_url: None,
+            commit_boost: None,
+            web3signer: None,
+            keystore: None,
             mevboost_url: "http://localhost:3030".parse().expect("Valid URL"),
+            relays: RelayConfig::default(),
+            constraint_digest: ConstraintDigest::default(),
             beacon_api_url: "http://localhost:5052".parse().expect("Valid URL"),
             execution_api_url: "http://localhost:8545".parse().expect("Valid URL"),
             engine_api_url: "http://localhost:8551".parse().expect("Valid URL"),
-            private_key: Some(random_bls_secret()),
+            private_key: Some(rng.bls_secret()),
             jwt_hex: String::new(),
+            api_jwt_secret: None,
             fee_recipient: Address::ZERO,
-            builder_private_key: random_bls_secret(),
+            external_signer: None,
+            sign_responses: false,
+            identity_validity: DEFAULT_IDENTITY_VALIDITY,
+            whitelist: None,
+            trusted_gateways: TrustedGateways::default(),
+            erc1271_auth: false,
+            accept_legacy_digests: true,
+            replay_window: None,
+            rate_limits: None,
+            signer_quotas: SignerQuotas::default(),
+            drop_confirm_delay: DEFAULT_DROP_CONFIRM_DELAY,
+            builder_private_key: rng.bls_secret(),
             limits: Limits::default(),
             validator_indexes: ValidatorIndexes::default(),
+            registry_hint_url: None,
+            maintenance_windows: Vec::new(),
+            warmup_timeout: DEFAULT_WARMUP_TIMEOUT,
+            response_timeout: DEFAULT_RESPONSE_TIMEOUT,
+            max_inflight_requests: DEFAULT_MAX_INFLIGHT_REQUESTS,
+            link: None,
+            max_head_age_slots: DEFAULT_MAX_HEAD_AGE_SLOTS,
+            clock_drift: ClockDriftConfig::default(),
+            validator_lease_ttl: None,
+            instance_id: rng.hex_id(),
+            metrics_port: None,
+            alerts: AlertConfig::default(),
+            reconciliation_finality: Finality::Seen,
+            commitment_status_retention_slots: DEFAULT_STATUS_RETENTION_SLOTS,
+            probe_interval: None,
+            basefee_margin: None,
+            log_level: LevelFilter::INFO,
             chain: ChainConfig::default(),
+            options: BTreeMap::new(),
+            rng,
+        }
+    }
+}
+
+/// Parses a comma-separated whitelist of addresses, reporting the position of any invalid entry.
+fn parse_whitelist(list: &str) -> Result<Whitelist> {
+    let addresses = parse_address_list(list).map_err(|e| eyre!("Invalid whitelist {e}"))?;
+    Ok(Whitelist::from_addresses(addresses))
+}
+
+/// Parses a comma-separated list of alert conditions.
+fn parse_alert_conditions(list: &str) -> Result<HashSet<AlertCondition>> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| name.parse().map_err(|e| eyre!("Invalid alert conditions: {e}")))
+        .collect()
+}
+
+/// Parses a comma-separated list of maintenance windows.
+fn parse_maintenance_windows(list: &str) -> Result<Vec<MaintenanceWindow>> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|window| !window.is_empty())
+        .map(|window| window.parse().map_err(|e| eyre!("{e}")))
+        .collect()
+}
+
+/// Parses a comma-separated list of `<hostname>=<cert path>:<key path>` TLS certificates,
+/// by lowercase hostname.
+fn parse_tls_hosts(list: &str) -> Result<BTreeMap<String, CertPair>> {
+    let mut hosts = BTreeMap::new();
+    for entry in list.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+        let invalid = || eyre!("Invalid TLS host '{entry}', expected <hostname>=<cert>:<key>");
+        let (hostname, paths) = entry.split_once('=').ok_or_else(invalid)?;
+        let (cert, key) = paths.split_once(':').ok_or_else(invalid)?;
+        let (hostname, cert, key) = (hostname.trim(), cert.trim(), key.trim());
+        if hostname.is_empty() || cert.is_empty() || key.is_empty() {
+            return Err(invalid());
         }
+
+        let pair = CertPair { cert: cert.into(), key: key.into() };
+        if hosts.insert(hostname.to_ascii_lowercase(), pair).is_some() {
+            bail!("Duplicate TLS host '{hostname}'");
+        }
+    }
+    Ok(hosts)
+}
+
+/// Checks that alerts are only posted in the clear to a webhook on the local host.
+fn validate_webhook_url(url: &Url) -> Result<()> {
+    let local = match url.host_str() {
+        Some("localhost") => true,
+        Some(host) => host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback()),
+        None => false,
+    };
+
+    if url.scheme() != "https" && !(url.scheme() == "http" && local) {
+        bail!("The alert webhook must be an HTTPS URL, unless it is on the local host: {url}");
     }
+
+    Ok(())
+}
+
+/// Rejects an option whose subsystem was compiled out of this build of the sidecar.
+fn require_feature(option: &str, feature: &str, enabled: bool) -> Result<()> {
+    if !enabled {
+        bail!("{option} requires the `{feature}` feature, which this sidecar was built without");
+    }
+    Ok(())
 }
 
 /// Limits for the sidecar.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Limits {
     /// Maximum number of commitments to accept per block
     pub max_commitments_per_slot: NonZero<usize>,
     pub max_committed_gas_per_slot: NonZero<u64>,
+    /// Maximum bytes the committed transactions can take in a block
+    #[serde(default = "default_max_committed_bytes_per_slot")]
+    pub max_committed_bytes_per_slot: NonZero<usize>,
+    /// Percentage of the maximum blobs per block of the active fork that can be committed to
+    #[serde(default = "default_max_committed_blobs_percent")]
+    pub max_committed_blobs_percent: u8,
+    /// Maximum bytes held by the transactions of accepted requests across all slots
+    #[serde(default = "default_max_queued_bytes")]
+    pub max_queued_bytes: NonZero<usize>,
+    /// Minimum priority fee per gas (in wei) the transactions of a request must tip, 0 if any
+    #[serde(default)]
+    pub min_priority_fee: u128,
+    /// Margin (in wei) over the base fee rise projected until the target slot that the
+    /// transactions must also tip, if the priority fee floor is dynamic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority_fee_margin: Option<u128>,
+    /// Percentage of their gas limit the blocks until the target slot are assumed to use in
+    /// the projection of its base fee
+    #[serde(default = "default_basefee_block_fullness")]
+    pub basefee_block_fullness: u8,
+}
+
+/// The limits of the sidecar, shared between the driver and the commitments API so that
+/// they can be adjusted at runtime from the admin server.
+pub type SharedLimits = Arc<RwLock<Limits>>;
+
+fn default_max_committed_bytes_per_slot() -> NonZero<usize> {
+    NonZero::new(512 * 1024).expect("Valid non-zero")
+}
+
+fn default_max_committed_blobs_percent() -> u8 {
+    100
+}
+
+fn default_max_queued_bytes() -> NonZero<usize> {
+    NonZero::new(DEFAULT_MAX_QUEUED_BYTES).expect("Valid non-zero")
+}
+
+fn default_basefee_block_fullness() -> u8 {
+    DEFAULT_BLOCK_FULLNESS
 }
 
 impl Default for Limits {
@@ -150,16 +1147,98 @@ impl Default for Limits {
         Self {
             max_commitments_per_slot: NonZero::new(128).expect("Valid non-zero"),
             max_committed_gas_per_slot: NonZero::new(10_000_000).expect("Valid non-zero"),
+            max_committed_bytes_per_slot: default_max_committed_bytes_per_slot(),
+            max_committed_blobs_percent: default_max_committed_blobs_percent(),
+            max_queued_bytes: default_max_queued_bytes(),
+            min_priority_fee: 0,
+            priority_fee_margin: None,
+            basefee_block_fullness: default_basefee_block_fullness(),
         }
     }
 }
 
 impl Config {
-    /// Parse the command-line options and return a new [`Config`] instance
+    /// Parse the command-line options, along with the config file of `--config` if given,
+    /// and return a new [`Config`] instance
     pub fn parse_from_cli() -> Result<Self> {
-        let opts = Opts::parse();
+        let args = std::env::args_os().collect();
+        let (mut opts, options) = parse_opts::<Opts>(Opts::command(), args)?;
+        opts.options = options;
         Self::try_from(opts)
     }
+
+    /// Sets the signer backend of the signing options, which hold the validator keys.
+    fn apply_signing(&mut self, signing: SigningOpts) -> Result<()> {
+        self.private_key = if let Some(sk) = signing.private_key {
+            let hex_sk = sk.strip_prefix("0x").unwrap_or(&sk);
+            let sk = SecretKey::from_bytes(&hex::decode(hex_sk)?)
+                .map_err(|e| eyre!("Failed decoding BLS signer secret key: {:?}", e))?;
+            Some(sk)
+        } else {
+            None
+        };
+
+        self.keystore = signing
+            .keystore_dir
+            .zip(signing.keystore_password_file)
+            .map(|(dir, password_file)| KeystoreConfig { dir, password_file });
+        let local_keys = self.private_key.is_some() || self.keystore.is_some();
+        match signing.signer {
+            SignerKind::Local if !local_keys => {
+                bail!("--private-key or --keystore-dir is required with the local signer")
+            }
+            SignerKind::Local => {}
+            _ if local_keys => {
+                bail!("--private-key and --keystore-dir can only be used with the local signer")
+            }
+            SignerKind::CommitBoost => {
+                let (Some(url), Some(jwt)) = (signing.cb_url, signing.cb_jwt) else {
+                    bail!("--cb-url and --cb-jwt are required with the Commit-Boost signer")
+                };
+                self.commit_boost = Some(CommitBoostConfig { url: Url::parse(&url)?, jwt });
+            }
+            SignerKind::Web3Signer => {
+                let (Some(url), Some(constraint_pubkey), Some(commitment_pubkey)) = (
+                    signing.web3signer_url,
+                    signing.web3signer_constraint_pubkey,
+                    signing.web3signer_commitment_pubkey,
+                ) else {
+                    bail!(
+                        "--web3signer-url, --web3signer-constraint-pubkey and \
+                         --web3signer-commitment-pubkey are required with the Web3Signer signer"
+                    )
+                };
+                let client_cert = opts
+                    .signing
+                    .web3signer_client_cert
+                    .zip(signing.web3signer_client_key)
+                    .map(|(cert, key)| ClientCert { cert, key });
+                self.web3signer = Some(Web3SignerConfig {
+                    url: Url::parse(&url)?,
+                    constraint_pubkey: constraint_pubkey
+                        .parse()
+                        .map_err(|e| eyre!("Invalid --web3signer-constraint-pubkey: {e}"))?,
+                    commitment_pubkey: parse_commitment_pubkey(&commitment_pubkey)?,
+                    client_cert,
+                    ca_cert: signing.web3signer_ca_cert,
+                    timeout: Duration::from_millis(signing.web3signer_timeout_ms),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl TryFrom<SigningOpts> for Config {
+    type Error = Report;
+
+    /// The config of the signer backend alone, such as for the `delegate` subcommand.
+    fn try_from(signing: SigningOpts) -> Result<Self, Self::Error> {
+        let mut config = Config::default();
+        config.apply_signing(signing)?;
+        Ok(config)
+    }
 }
 
 impl TryFrom<Opts> for Config {
@@ -172,6 +1251,69 @@ impl TryFrom<Opts> for Config {
             config.rpc_port = port;
         }
 
+        if opts.admin_port.is_some() {
+            require_feature("--admin-port", "admin", cfg!(feature = "admin"))?;
+        }
+        config.admin_port = opts.admin_port;
+        if opts.admin_token.as_ref().is_some_and(|token| token.trim().is_empty()) {
+            bail!("--admin-token must not be empty");
+        }
+        config.admin_token = opts.admin_token;
+        config.internal_port = opts.internal_port;
+
+        let profile = |list: Option<String>, methods| {
+            list.map(|list| MethodProfile::parse(&list, methods)).transpose()
+        };
+        if let Some(public) = profile(opts.public_methods, RPC_METHODS)? {
+            config.method_profiles.public = public;
+        }
+        if let Some(internal) = profile(opts.internal_methods, INTERNAL_METHODS)? {
+            config.method_profiles.internal = internal;
+        }
+        if let Some(admin) = profile(opts.admin_methods, ADMIN_METHODS)? {
+            config.method_profiles.admin = admin;
+        }
+
+        let default_cert = match (opts.tls_cert, opts.tls_key) {
+            (Some(cert), Some(key)) => Some(CertPair { cert, key }),
+            (None, None) => None,
+            _ => bail!("The TLS certificate and key must be set together"),
+        };
+        let tls_hosts = opts.tls_hosts.as_deref().map(parse_tls_hosts).transpose()?;
+        if default_cert.is_some() || tls_hosts.is_some() {
+            require_feature("--tls-cert and --tls-hosts", "tls", cfg!(feature = "tls"))?;
+            if default_cert.is_none() && opts.tls_unknown_sni == UnknownSni::Default {
+                bail!("--tls-unknown-sni default requires --tls-cert and --tls-key");
+            }
+            config.tls = Some(TlsConfig {
+                default: default_cert,
+                hosts: tls_hosts.unwrap_or_default(),
+                unknown_sni: opts.tls_unknown_sni,
+            });
+        }
+        if let Some(path) = opts.commitments_socket {
+            if config.tls.is_some() {
+                bail!("The public listener can't serve TLS on --commitments-socket");
+            }
+            let socket = UnixSocketConfig::new(path);
+            config.unix_socket = Some(match opts.commitments_socket_mode {
+                Some(mode) => socket.with_mode(mode),
+                None => socket,
+            });
+        }
+
+        config.capture_dir = opts.capture_dir;
+        config.capture_max_bytes = opts.capture_max_bytes;
+        config.audit_log = opts.audit_log_path;
+        config.audit_log_max_bytes = opts.audit_log_max_bytes;
+        if opts.db_path.is_some() {
+            require_feature("--db-path", "persistence", cfg!(feature = "persistence"))?;
+        }
+        config.db_path = opts.db_path;
+        config.replay_log = opts.replay_log;
+        config.delegations_path = opts.delegations_path;
+        config.ports_file = opts.ports_file;
+
         if let Some(max_commitments) = opts.max_commitments {
             config.limits.max_commitments_per_slot = max_commitments;
         }
@@ -180,17 +1322,30 @@ impl TryFrom<Opts> for Config {
             config.limits.max_committed_gas_per_slot = max_committed_gas;
         }
 
-        config.commit_boost_url =
-            opts.signing.commit_boost_url.as_ref().map(|url| Url::parse(url)).transpose()?;
+        if let Some(max_committed_bytes) = opts.max_committed_bytes {
+            config.limits.max_committed_bytes_per_slot = max_committed_bytes;
+        }
 
-        config.private_key = if let Some(sk) = opts.signing.private_key {
-            let hex_sk = sk.strip_prefix("0x").unwrap_or(&sk);
-            let sk = SecretKey::from_bytes(&hex::decode(hex_sk)?)
-                .map_err(|e| eyre!("Failed decoding BLS signer secret key: {:?}", e))?;
-            Some(sk)
-        } else {
-            None
-        };
+        if let Some(percent) = opts.max_committed_blobs_percent {
+            config.limits.max_committed_blobs_percent = percent;
+        }
+
+        if let Some(max_queued_bytes) = opts.max_queued_bytes {
+            config.limits.max_queued_bytes = max_queued_bytes;
+        }
+
+        if let Some(min_priority_fee) = opts.min_priority_fee {
+            config.limits.min_priority_fee = min_priority_fee;
+        }
+
+        config.limits.priority_fee_margin =
+            opts.priority_fee_margin_gwei.map(|margin| margin as u128 * GWEI_TO_WEI as u128);
+
+        if let Some(fullness) = opts.basefee_block_fullness {
+            config.limits.basefee_block_fullness = fullness;
+        }
+
+        config.apply_signing(opts.signing)?;
 
         if let Some(builder_sk) = opts.builder_private_key {
             let hex_sk = builder_sk.strip_prefix("0x").unwrap_or(&builder_sk);
@@ -217,18 +1372,422 @@ impl TryFrom<Opts> for Config {
             info!("Engine JWT secret loaded successfully");
         }
 
+        if let Some(ref path) = opts.jwt_secret_file {
+            let secret = JwtSecret::from_file(path)
+                .map_err(|e| eyre!("Failed reading the API JWT secret file: {e}"))?;
+            config.api_jwt_secret = Some(secret);
+            info!(path = %path.display(), "API JWT secret loaded successfully");
+        }
+
         config.mevboost_proxy_port = opts.mevboost_proxy_port;
         config.engine_api_url = opts.engine_api_url.parse()?;
         config.execution_api_url = opts.execution_api_url.parse()?;
         config.beacon_api_url = opts.beacon_api_url.parse()?;
         config.mevboost_url = opts.mevboost_url.parse()?;
 
+        if let Some(ref list) = opts.relay_urls {
+            config.relays.urls = list
+                .split(',')
+                .map(str::trim)
+                .filter(|url| !url.is_empty())
+                .map(|url| url.parse().map_err(|e| eyre!("Invalid relay URL '{url}': {e}")))
+                .collect::<Result<_>>()?;
+        }
+        let targets = config.relays.urls.len() + 1;
+        if opts.relay_quorum == 0 || opts.relay_quorum > targets {
+            bail!("The relay quorum must be between 1 and the {targets} submission targets");
+        }
+        if !(opts.relay_ewma_alpha > 0.0 && opts.relay_ewma_alpha <= 1.0) {
+            bail!("The relay EWMA weight must be greater than 0 and at most 1");
+        }
+        if opts.relay_max_attempts == 0 || opts.relay_breaker_threshold == 0 {
+            bail!("The relay max attempts and breaker threshold must be at least 1");
+        }
+        config.relays.quorum = opts.relay_quorum;
+        config.relays.stagger = Duration::from_millis(opts.relay_stagger_ms);
+        config.relays.ewma_alpha = opts.relay_ewma_alpha;
+        config.relays.retry = RetryPolicy {
+            max_attempts: opts.relay_max_attempts,
+            initial_delay: Duration::from_millis(opts.relay_retry_delay_ms),
+            max_delay: Duration::from_millis(opts.relay_max_retry_delay_ms),
+        };
+        config.relays.breaker_threshold = opts.relay_breaker_threshold;
+        config.relays.breaker_cooldown = Duration::from_millis(opts.relay_breaker_cooldown_ms);
+        if opts.ssz_constraint_digests {
+            config.constraint_digest = ConstraintDigest::HashTreeRoot;
+        }
+
         config.fee_recipient = opts.fee_recipient;
+        config.external_signer = opts.external_signer_address;
+        config.sign_responses = opts.sign_responses;
+        config.identity_validity = Duration::from_secs(opts.identity_validity_secs);
+        if config.identity_validity.is_zero() {
+            bail!("The identity document validity must be positive");
+        }
+
+        config.whitelist = opts.whitelist.as_deref().map(parse_whitelist).transpose()?;
+        if let Some(ref path) = opts.whitelist_file {
+            let whitelist = Whitelist::load(path)?;
+            info!(path = %path.display(), entries = whitelist.len(), "Loaded the whitelist");
+            config.whitelist = Some(whitelist);
+        }
+
+        if let Some(ref list) = opts.trusted_gateways {
+            let gateways =
+                parse_address_list(list).map_err(|e| eyre!("Invalid trusted gateways {e}"))?;
+            config.trusted_gateways = TrustedGateways::new(gateways);
+        }
+        config.erc1271_auth = opts.enable_erc1271_auth;
+        config.accept_legacy_digests = !opts.reject_legacy_digests;
+        if opts.enable_replay_protection {
+            if opts.replay_window_secs == 0 {
+                bail!("The replay window must be positive");
+            }
+            config.replay_window = Some(Duration::from_secs(opts.replay_window_secs));
+        }
+
+        if let Some(requests_per_sec) = opts.rate_limit_per_sec {
+            let burst = opts.rate_limit_burst.unwrap_or(requests_per_sec);
+            if requests_per_sec == 0 || burst == 0 {
+                bail!("The rate limit and its burst must be positive");
+            }
+            config.rate_limits = Some(RateLimits { requests_per_sec, burst });
+        }
+
+        config.signer_quotas = SignerQuotas {
+            max_gas_per_slot: opts.max_gas_per_signer_per_slot.map(NonZero::get),
+            max_txs_per_slot: opts.max_txs_per_signer_per_slot.map(NonZero::get),
+            max_commitments_per_epoch: opts.max_commitments_per_signer_per_epoch.map(NonZero::get),
+        };
+
+        config.drop_confirm_delay = Duration::from_secs(opts.drop_confirm_delay_secs);
 
         config.validator_indexes = opts.validator_indexes;
 
+        config.registry_hint_url = opts.registry_hint_url;
+        if let Some(ref list) = opts.maintenance_windows {
+            config.maintenance_windows = parse_maintenance_windows(list)?;
+        }
+
+        if let Some(warmup_timeout_ms) = opts.warmup_timeout_ms {
+            config.warmup_timeout = Duration::from_millis(warmup_timeout_ms);
+        }
+        config.response_timeout = Duration::from_millis(opts.response_timeout_ms);
+        if config.response_timeout.is_zero() {
+            bail!("The response timeout must be positive");
+        }
+        config.max_inflight_requests = opts.max_inflight_requests;
+        if config.max_inflight_requests == 0 {
+            bail!("The maximum number of requests in flight must be positive");
+        }
+        config.max_head_age_slots = opts.max_head_age_slots;
+
+        if opts.clock_drift_bound_ms < opts.max_clock_drift_ms {
+            bail!("The clock drift bound must be at least the maximum clock drift");
+        }
+        config.clock_drift = ClockDriftConfig {
+            max_drift: Duration::from_millis(opts.max_clock_drift_ms),
+            bound: Duration::from_millis(opts.clock_drift_bound_ms),
+            action: opts.clock_drift_action,
+        };
+
+        config.validator_lease_ttl = opts.validator_lease_ttl_ms.map(Duration::from_millis);
+        if let Some(instance_id) = opts.instance_id {
+            config.instance_id = instance_id;
+        }
+        if config.validator_lease_ttl.is_some() && config.db_path.is_none() {
+            bail!("Validator leases require a persistent store shared by instances (--db-path)");
+        }
+
+        if opts.metrics_port.is_some() {
+            require_feature("--metrics-port", "metrics", cfg!(feature = "metrics"))?;
+        }
+        config.metrics_port = opts.metrics_port;
+
+        if let Some(ref url) = opts.alert_webhook_url {
+            require_feature("--alert-webhook-url", "webhooks", cfg!(feature = "webhooks"))?;
+            validate_webhook_url(url)?;
+        }
+        if !(0.0..=1.0).contains(&opts.alert_max_miss_rate) {
+            bail!("The maximum miss rate of the alerts must be between 0 and 1");
+        }
+        config.alerts = AlertConfig {
+            webhook_url: opts.alert_webhook_url,
+            command: opts.alert_command,
+            conditions: match opts.alert_conditions {
+                Some(ref list) => parse_alert_conditions(list)?,
+                None => AlertCondition::ALL.into_iter().collect(),
+            },
+            hold_time: Duration::from_secs(opts.alert_hold_secs),
+            min_interval: Duration::from_secs(opts.alert_min_interval_secs),
+            max_miss_rate: opts.alert_max_miss_rate,
+            max_pending_responses: opts.alert_max_pending_responses,
+        };
+        config.reconciliation_finality = opts.reconciliation_finality;
+        config.commitment_status_retention_slots = opts.commitment_status_retention_slots;
+
+        config.probe_interval = opts.probe_interval_secs.map(Duration::from_secs);
+        if config.probe_interval.is_some_and(|interval| interval.is_zero()) {
+            bail!("The probe interval must be positive");
+        }
+
+        if opts.adaptive_basefee_margin {
+            let bounds =
+                MarginBounds { min: opts.basefee_margin_min, max: opts.basefee_margin_max };
+            if !(bounds.min > 0.0 && bounds.min <= bounds.max) {
+                bail!("Base fee margin bounds must satisfy 0 < min <= max");
+            }
+            config.basefee_margin = Some(bounds);
+        }
+
+        if opts.chain.constraint_submission_time() > opts.chain.commitment_deadline() {
+            bail!("The constraint submission time must be at most the commitment deadline");
+        }
+        config.log_level = opts.log_level;
         config.chain = opts.chain;
+        config.options = opts.options;
 
         Ok(config)
     }
 }
+
+impl TryFrom<DriverOpts> for Config {
+    type Error = Report;
+
+    fn try_from(opts: DriverOpts) -> Result<Self, Self::Error> {
+        let link = LinkConfig { port: opts.link_port, secret: opts.link_secret };
+        Ok(Config { link: Some(link), ..Config::try_from(opts.sidecar)? })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{file::ConfigFileError, *};
+    use crate::test_util::{test_bls_secret_key, write_config_file};
+
+    #[test]
+    fn test_parse_whitelist() {
+        let checksummed = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        let lowercase = "0xfb6916095ca1df60bb79ce92ce3ea74c37c5d359";
+
+        let whitelist = parse_whitelist(&format!("{checksummed},{lowercase}")).unwrap();
+        assert_eq!(whitelist.len(), 2);
+        assert!(whitelist.contains(&Address::from_str(checksummed).unwrap()));
+
+        // The same address in different cases is only added once
+        let whitelist = parse_whitelist(&format!("{checksummed},{}", checksummed.to_lowercase()));
+        assert_eq!(whitelist.unwrap().len(), 1);
+
+        // Wrong checksum in the second entry
+        let err = parse_whitelist(&format!(
+            "{lowercase}, 0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD"
+        ))
+        .unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("entry #2"), "{message}");
+        assert!(message.contains("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD"), "{message}");
+    }
+
+    #[test]
+    fn test_alert_options() {
+        let conditions = parse_alert_conditions("signer_unavailable, store_write_failure").unwrap();
+        assert_eq!(conditions.len(), 2);
+        assert!(conditions.contains(&AlertCondition::StoreWriteFailure));
+        assert!(parse_alert_conditions("signer_unavailable,unknown").is_err());
+
+        let valid = ["https://alerts.example.com/hook", "http://127.0.0.1:9000/", "http://[::1]/"];
+        for url in valid {
+            validate_webhook_url(&url.parse().unwrap()).unwrap();
+        }
+        for url in ["http://alerts.example.com/hook", "ftp://127.0.0.1/"] {
+            assert!(validate_webhook_url(&url.parse().unwrap()).is_err(), "{url}");
+        }
+    }
+
+    #[test]
+    fn test_parse_tls_hosts() {
+        let hosts = parse_tls_hosts("Alpha.test=/certs/a.crt:/certs/a.key, beta.test=b.crt:b.key")
+            .unwrap();
+        assert_eq!(hosts.len(), 2);
+        assert_eq!(
+            hosts["alpha.test"],
+            CertPair { cert: "/certs/a.crt".into(), key: "/certs/a.key".into() }
+        );
+        assert_eq!(hosts["beta.test"].key, PathBuf::from("b.key"));
+
+        for list in ["alpha.test", "alpha.test=a.crt", "=a.crt:a.key", "a=x:y,A=x:y"] {
+            assert!(parse_tls_hosts(list).is_err(), "{list}");
+        }
+    }
+
+    #[test]
+    fn test_config_file_precedence() {
+        let path = write_config_file(
+            r#"
+            max_commitments = 10
+            relay-stagger-ms = 50
+            relay_ewma_alpha = 0.75
+            relay_max_attempts = 3
+            audit_log_max_bytes = 4096
+            "#,
+        );
+        std::env::set_var("BOLT_SIDECAR_RELAY_STAGGER_MS", "75");
+        std::env::set_var("BOLT_SIDECAR_RELAY_EWMA_ALPHA", "0.5");
+
+        let args = ["bolt-sidecar", "--config", path.to_str().unwrap(), "--max-commitments", "20"];
+        let args = args.into_iter().chain(["--relay-ewma-alpha", "0.25"]).map(OsString::from);
+        let resolved = ResolvedOptions::parse(Opts::command(), args.collect()).unwrap();
+        let opts = Opts::from_arg_matches(&resolved.matches).unwrap();
+        let config = Config::try_from(opts).unwrap();
+        std::env::remove_var("BOLT_SIDECAR_RELAY_STAGGER_MS");
+        std::env::remove_var("BOLT_SIDECAR_RELAY_EWMA_ALPHA");
+
+        // Command line > environment > config file > default
+        assert_eq!(config.limits.max_commitments_per_slot.get(), 20);
+        assert_eq!(config.relays.ewma_alpha, 0.25);
+        assert_eq!(config.relays.stagger, Duration::from_millis(75));
+        assert_eq!(config.relays.retry, RetryPolicy { max_attempts: 3, ..Default::default() });
+        assert_eq!(config.audit_log_max_bytes, 4096);
+        assert_eq!(config.capture_max_bytes, DEFAULT_CAPTURE_MAX_BYTES);
+        assert_eq!(config.mevboost_proxy_port, 18551);
+
+        let effective = resolved.effective_options();
+        let lines = effective.lines().collect::<Vec<_>>();
+        for line in [
+            r#"max_commitments = "20" # command line"#,
+            r#"relay_stagger_ms = "75" # environment"#,
+            r#"audit_log_max_bytes = "4096" # config file"#,
+            r#"private_key = "<redacted>" # config file"#,
+            r#"jwt_hex = "<redacted>" # config file"#,
+            r#"chain = "mainnet" # default"#,
+        ] {
+            assert!(lines.contains(&line), "{line} not in {effective}");
+        }
+        assert!(!effective.contains("d4e56740"), "{effective}");
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_config_file_errors() {
+        let parse = |path: &Path| {
+            let args = ["bolt-sidecar", "--config", path.to_str().unwrap()].map(OsString::from);
+            ResolvedOptions::parse(Opts::command(), args.to_vec()).unwrap_err()
+        };
+
+        // The options follow the 10 lines of the required ones
+        let path = write_config_file("max_commitments = 10\nmax_commitment = 10\n");
+        let err = parse(&path);
+        assert!(matches!(err, ConfigFileError::UnknownKey { line: 12, .. }), "{err:?}");
+        let message = format!("Unknown key 'max_commitment' in the config file {}", path.display());
+        assert_eq!(err.to_string(), format!("{message} at line 12"));
+        std::fs::remove_file(path).unwrap();
+
+        let path = write_config_file("max_commitments = \"many\"\n");
+        let err = parse(&path);
+        let ConfigFileError::InvalidValue { key, line: 11, .. } = err else {
+            panic!("expected an invalid value, got {err:?}");
+        };
+        assert_eq!(key, "max_commitments");
+        std::fs::remove_file(path).unwrap();
+
+        let path = write_config_file("config = \"other.toml\"\n");
+        assert!(matches!(parse(&path), ConfigFileError::UnknownKey { line: 11, .. }));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_signer_options() {
+        let path = write_config_file("");
+        let required = std::fs::read_to_string(&path).unwrap();
+        let lines = required.lines().filter(|line| !line.trim().starts_with("private_key"));
+        std::fs::write(&path, lines.collect::<Vec<_>>().join("\n")).unwrap();
+
+        let parse = |options: &[&str]| {
+            let args = ["bolt-sidecar", "--config", path.to_str().unwrap()].iter().chain(options);
+            let args = args.map(OsString::from).collect();
+            let resolved = ResolvedOptions::parse(Opts::command(), args).unwrap();
+            Config::try_from(Opts::from_arg_matches(&resolved.matches).unwrap())
+        };
+
+        // The local signer is the default, and requires the private key
+        let err = parse(&[]).unwrap_err();
+        let message = "--private-key or --keystore-dir is required with the local signer";
+        assert_eq!(err.to_string(), message);
+        let key = "0x0000000000000000000000000000000000000000000000000000000000000001";
+        assert!(parse(&["--private-key", key]).unwrap().commit_boost.is_none());
+
+        // Or loads its keys from the keystores, with their password file
+        let keystores = ["--keystore-dir", "/keys", "--keystore-password-file", "/keys/password"];
+        let keystore = parse(&keystores).unwrap().keystore.unwrap();
+        assert_eq!(keystore.dir, PathBuf::from("/keys"));
+        assert_eq!(keystore.password_file, PathBuf::from("/keys/password"));
+
+        // The Commit-Boost signer holds the keys, and is reached with the JWT of the module
+        let commit_boost = ["--signer", "commit-boost", "--commit-boost-url", "http://cb:20000"];
+        let config = parse(&[&commit_boost[..], &["--cb-jwt", "module-jwt"][..]].concat()).unwrap();
+        let cb = config.commit_boost.unwrap();
+        assert_eq!((cb.url.as_str(), cb.jwt.as_str()), ("http://cb:20000/", "module-jwt"));
+        assert!(parse(&commit_boost).is_err());
+        let with_key = [&commit_boost[..], &["--cb-jwt", "jwt", "--private-key", key][..]].concat();
+        assert!(parse(&with_key).is_err());
+
+        // The Web3Signer signer identifies its keys by their public keys
+        let constraint_pubkey = format!("0x{}", "aa".repeat(48));
+        let commitment_pubkey =
+            "0x0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+        let web3signer = [
+            "--signer",
+            "web3signer",
+            "--web3signer-url",
+            "https://web3signer:9000",
+            "--web3signer-constraint-pubkey",
+            constraint_pubkey.as_str(),
+            "--web3signer-commitment-pubkey",
+            commitment_pubkey,
+        ];
+        let config = parse(&web3signer).unwrap();
+        let signer = config.web3signer.unwrap();
+        assert_eq!(signer.constraint_pubkey.to_string(), constraint_pubkey);
+        assert_eq!(signer.timeout, Duration::from_secs(1));
+        assert!(config.commit_boost.is_none() && signer.client_cert.is_none());
+        let err = parse(&web3signer[..6]).unwrap_err();
+        assert!(err.to_string().contains("--web3signer-commitment-pubkey"), "{err}");
+        let invalid = [&web3signer[..7], &["0x02"][..]].concat();
+        assert!(parse(&invalid).is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_delegate_options() {
+        let delegatee = test_bls_secret_key().sk_to_pk().to_bytes();
+        let hex_delegatee = format!("0x{}", hex::encode(delegatee));
+        let key = "0x0000000000000000000000000000000000000000000000000000000000000001";
+        let args = ["delegate", "--delegatee-pubkey", hex_delegatee.as_str(), "--private-key", key];
+        let opts = DelegateOpts::try_parse_from(args).unwrap();
+        assert_eq!(opts.action(), DelegationAction::Delegate);
+        assert_eq!((opts.slasher, opts.out.to_str()), (Address::ZERO, Some("delegations.json")));
+        assert_eq!(opts.delegatee_pubkey.as_ref(), delegatee.as_slice());
+
+        // The signer of the validator keys is resolved alone
+        let config = Config::try_from(opts.signing).unwrap();
+        assert_eq!(config.private_key.unwrap().to_bytes()[31], 1);
+
+        let revoke = DelegateOpts::try_parse_from(args.iter().chain(&["--revoke"])).unwrap();
+        assert_eq!(revoke.action(), DelegationAction::Revoke);
+        let invalid = ["delegate", "--delegatee-pubkey", "0x01", "--private-key", key];
+        assert!(DelegateOpts::try_parse_from(invalid).is_err());
+    }
+
+    #[test]
+    fn test_require_feature() {
+        assert!(require_feature("--metrics-port", "metrics", true).is_ok());
+
+        let err = require_feature("--metrics-port", "metrics", false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--metrics-port requires the `metrics` feature, which this sidecar was built without"
+        );
+    }
+}
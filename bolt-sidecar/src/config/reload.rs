@@ -0,0 +1,153 @@
+//! Reloading of the config of a running sidecar, on SIGHUP.
+//!
+//! The options are parsed again from the command line, the environment and the config file
+//! of `--config`, and their raw values are diffed against those of the running config. The
+//! operating limits, the whitelist, the rate limits and the log level are applied to the
+//! state shared with the commitments API. The other options, such as the ports, the keys and
+//! the chain, are ignored until the sidecar is restarted.
+
+use std::{collections::BTreeSet, ffi::OsString, sync::Arc};
+
+use clap::{CommandFactory, FromArgMatches};
+use eyre::Result;
+
+use crate::{
+    commitments::{ratelimit::SignerRateLimiter, whitelist::SharedWhitelist},
+    logging,
+};
+
+use super::{driver_command, file::ResolvedOptions, Config, DriverOpts, Opts, SharedLimits};
+
+/// The options of the operating limits.
+const LIMITS_OPTIONS: [&str; 8] = [
+    "max_commitments",
+    "max_committed_gas",
+    "max_committed_bytes",
+    "max_committed_blobs_percent",
+    "max_queued_bytes",
+    "min_priority_fee",
+    "priority_fee_margin_gwei",
+    "basefee_block_fullness",
+];
+
+/// The options of the whitelist, which can be replaced but not enabled or disabled.
+const WHITELIST_OPTIONS: [&str; 2] = ["whitelist", "whitelist_file"];
+
+/// The options of the rate limits, which can be changed but not enabled or disabled.
+const RATE_LIMIT_OPTIONS: [&str; 2] = ["rate_limit_per_sec", "rate_limit_burst"];
+
+/// The option of the log level.
+const LOG_LEVEL_OPTION: &str = "log_level";
+
+/// A config parsed again while the sidecar runs, with the options that changed.
+#[derive(Debug)]
+pub struct ConfigReload {
+    /// The reloaded config.
+    pub config: Config,
+    /// The ids of the changed options that are applied at runtime.
+    pub applied: Vec<String>,
+    /// The ids of the changed options that are ignored until the sidecar is restarted.
+    pub ignored: Vec<String>,
+}
+
+impl Config {
+    /// Parses the config from the arguments of the sidecar, like [`Config::parse_from_cli`],
+    /// but returns the errors of the options instead of exiting.
+    pub fn try_parse_from(args: Vec<OsString>) -> Result<Self> {
+        let resolved = ResolvedOptions::try_parse(Opts::command(), args)?;
+        let mut opts = Opts::from_arg_matches(&resolved.matches)?;
+        opts.options = resolved.values();
+        Self::try_from(opts)
+    }
+
+    /// Parses the config again from the arguments of the process, and diffs it against this
+    /// running one.
+    pub fn reload(&self) -> Result<ConfigReload> {
+        self.reload_from(std::env::args_os().collect())
+    }
+
+    /// Parses the config again from the given arguments of the process, of the `driver`
+    /// subcommand if this config has a link, and diffs it against this running one.
+    pub fn reload_from(&self, args: Vec<OsString>) -> Result<ConfigReload> {
+        let config = if self.link.is_some() {
+            let args = args.into_iter().skip(1).collect();
+            let resolved = ResolvedOptions::try_parse(driver_command(), args)?;
+            let mut opts = DriverOpts::from_arg_matches(&resolved.matches)?;
+            opts.sidecar.options = resolved.values();
+            Self::try_from(opts)?
+        } else {
+            Self::try_parse_from(args)?
+        };
+
+        // The whitelist file is read again, and its entries may change with the same path
+        let ids = self.options.keys().chain(config.options.keys()).collect::<BTreeSet<_>>();
+        let changed = ids.into_iter().filter(|id| {
+            self.options.get(*id) != config.options.get(*id) ||
+                (id.as_str() == "whitelist_file" && self.whitelist != config.whitelist)
+        });
+        let (applied, ignored) = changed.cloned().partition(|id| self.is_reloadable(id, &config));
+
+        Ok(ConfigReload { config, applied, ignored })
+    }
+
+    /// Returns true if the option can be changed to its value in the reloaded config without
+    /// a restart.
+    fn is_reloadable(&self, id: &str, reloaded: &Config) -> bool {
+        if WHITELIST_OPTIONS.contains(&id) {
+            self.whitelist.is_some() == reloaded.whitelist.is_some()
+        } else if RATE_LIMIT_OPTIONS.contains(&id) {
+            self.rate_limits.is_some() == reloaded.rate_limits.is_some()
+        } else {
+            LIMITS_OPTIONS.contains(&id) || id == LOG_LEVEL_OPTION
+        }
+    }
+}
+
+/// The state the reloadable options are applied to, shared with the commitments API.
+#[derive(Debug, Clone)]
+pub struct ReloadTargets {
+    /// The operating limits, shared with the execution state.
+    pub limits: SharedLimits,
+    /// The whitelist of request signers, if enabled.
+    pub whitelist: Option<SharedWhitelist>,
+    /// The rate limiter of the inclusion requests, if enabled.
+    pub rate_limiter: Option<Arc<SignerRateLimiter>>,
+}
+
+impl ReloadTargets {
+    /// Applies the changed options of the reload that are reloadable to the shared state, and
+    /// to the running config. The ignored options keep their running values, so that they are
+    /// reported again by the next reload.
+    pub fn apply(&self, running: &mut Config, reload: ConfigReload) {
+        let ConfigReload { config, applied, .. } = reload;
+        let changed = |options: &[&str]| applied.iter().any(|id| options.contains(&id.as_str()));
+
+        if changed(&LIMITS_OPTIONS) {
+            *self.limits.write() = config.limits;
+            running.limits = config.limits;
+        }
+        if changed(&WHITELIST_OPTIONS) {
+            if let (Some(shared), Some(whitelist)) = (&self.whitelist, &config.whitelist) {
+                *shared.write() = whitelist.clone();
+            }
+            running.whitelist = config.whitelist;
+        }
+        if changed(&RATE_LIMIT_OPTIONS) {
+            if let (Some(limiter), Some(limits)) = (&self.rate_limiter, config.rate_limits) {
+                limiter.set_limits(limits);
+            }
+            running.rate_limits = config.rate_limits;
+        }
+        if changed(&[LOG_LEVEL_OPTION]) {
+            logging::set_level(config.log_level);
+            running.log_level = config.log_level;
+        }
+
+        for id in applied {
+            match config.options.get(&id) {
+                Some(value) => running.options.insert(id, value.clone()),
+                None => running.options.remove(&id),
+            };
+        }
+    }
+}
@@ -1,16 +1,66 @@
-use clap::{ArgGroup, Args};
+use std::path::PathBuf;
+
+use clap::{Args, ValueEnum};
 
 /// Command-line options for signing
 #[derive(Debug, Clone, Args)]
-#[clap(
-    group = ArgGroup::new("signing-opts").required(true)
-        .args(&["private_key", "commit_boost_url"])
-)]
 pub struct SigningOpts {
-    /// Private key to use for signing preconfirmation requests
-    #[clap(long, env = "BOLT_SIDECAR_PRIVATE_KEY", conflicts_with("commit_boost_url"))]
+    /// The backend signing the commitments and the constraints
+    #[clap(long, env = "BOLT_SIDECAR_SIGNER", value_enum, default_value_t = SignerKind::Local)]
+    pub(super) signer: SignerKind,
+    /// Private key to use for signing preconfirmation requests, with the local signer
+    #[clap(long, env = "BOLT_SIDECAR_PRIVATE_KEY", hide_env_values = true)]
     pub(super) private_key: Option<String>,
-    /// URL for the commit-boost sidecar
-    #[clap(long, env = "BOLT_SIDECAR_COMMIT_BOOST_URL", conflicts_with("private_key"))]
-    pub(super) commit_boost_url: Option<String>,
+    /// Directory of the EIP-2335 keystores of the validator keys, with the local signer. The
+    /// constraints are signed with the key of the first keystore, in the order of the names
+    #[clap(
+        long,
+        env = "BOLT_SIDECAR_KEYSTORE_DIR",
+        conflicts_with = "private_key",
+        requires = "keystore_password_file"
+    )]
+    pub(super) keystore_dir: Option<PathBuf>,
+    /// File of the password of the keystores of `--keystore-dir`
+    #[clap(long, env = "BOLT_SIDECAR_KEYSTORE_PASSWORD_FILE", requires = "keystore_dir")]
+    pub(super) keystore_password_file: Option<PathBuf>,
+    /// URL of the signer module API of Commit-Boost, with the Commit-Boost signer
+    #[clap(long, alias = "commit-boost-url", env = "BOLT_SIDECAR_CB_URL")]
+    pub(super) cb_url: Option<String>,
+    /// JWT of the sidecar as a Commit-Boost module, with the Commit-Boost signer
+    #[clap(long, env = "BOLT_SIDECAR_CB_JWT", hide_env_values = true)]
+    pub(super) cb_jwt: Option<String>,
+    /// URL of the REST API of Web3Signer, with the Web3Signer signer
+    #[clap(long, env = "BOLT_SIDECAR_WEB3SIGNER_URL")]
+    pub(super) web3signer_url: Option<String>,
+    /// BLS public key on Web3Signer the constraints are signed with
+    #[clap(long, env = "BOLT_SIDECAR_WEB3SIGNER_CONSTRAINT_PUBKEY")]
+    pub(super) web3signer_constraint_pubkey: Option<String>,
+    /// Secp256k1 public key on Web3Signer the commitments are signed with
+    #[clap(long, env = "BOLT_SIDECAR_WEB3SIGNER_COMMITMENT_PUBKEY")]
+    pub(super) web3signer_commitment_pubkey: Option<String>,
+    /// Path to the PEM client certificate of the TLS connections to Web3Signer
+    #[clap(long, env = "BOLT_SIDECAR_WEB3SIGNER_CLIENT_CERT", requires = "web3signer_client_key")]
+    pub(super) web3signer_client_cert: Option<PathBuf>,
+    /// Path to the PEM PKCS#8 private key of the client certificate
+    #[clap(long, env = "BOLT_SIDECAR_WEB3SIGNER_CLIENT_KEY", requires = "web3signer_client_cert")]
+    pub(super) web3signer_client_key: Option<PathBuf>,
+    /// Path to the PEM CA certificate of Web3Signer, trusted in addition to the system roots
+    #[clap(long, env = "BOLT_SIDECAR_WEB3SIGNER_CA_CERT")]
+    pub(super) web3signer_ca_cert: Option<PathBuf>,
+    /// Timeout of each attempt of a signature request to Web3Signer, in milliseconds
+    #[clap(long, env = "BOLT_SIDECAR_WEB3SIGNER_TIMEOUT_MS", default_value_t = 1000)]
+    pub(super) web3signer_timeout_ms: u64,
+}
+
+/// The backends signing the commitments and the constraints
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[clap(rename_all = "kebab_case")]
+pub enum SignerKind {
+    /// The keys are held by the sidecar, with `--private-key` or `--keystore-dir`
+    Local,
+    /// The keys are held by the Commit-Boost signer at `--cb-url`
+    CommitBoost,
+    /// The keys are held by Web3Signer at `--web3signer-url`
+    #[value(name = "web3signer")]
+    Web3Signer,
 }
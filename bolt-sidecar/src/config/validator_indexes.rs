@@ -1,6 +1,8 @@
 use std::str::FromStr;
 
-#[derive(Debug, Clone, Default)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ValidatorIndexes(Vec<u64>);
 
 impl ValidatorIndexes {
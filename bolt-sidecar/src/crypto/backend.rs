@@ -0,0 +1,230 @@
+use std::{fmt, future::Future, sync::Arc, time::Duration};
+
+use alloy::{
+    primitives::{Address, ChainId, Signature, B256},
+    signers::{self, Signer as SignerECDSA},
+};
+use tracing::warn;
+
+use super::bls::{BLSSig, BlsPublicKey, BlsSecretKey, Signer as BlsSigner, SignerBLS};
+
+/// Errors of the signer backends.
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum SignerError {
+    #[error("Failed to sign with the local key: {0}")]
+    Local(String),
+    #[error("Remote signer unavailable after {attempts} attempts: {message}")]
+    Unavailable { attempts: u32, message: String },
+    #[error("Remote signer rejected the request: {0}")]
+    Rejected(String),
+}
+
+impl SignerError {
+    /// Returns true if the error comes from a remote signer, rather than from the sidecar.
+    pub fn is_remote(&self) -> bool {
+        matches!(self, Self::Unavailable { .. } | Self::Rejected(_))
+    }
+}
+
+/// Maximum number of attempts of a request to a remote signer.
+pub(crate) const MAX_REMOTE_ATTEMPTS: u32 = 4;
+
+/// Makes a request to a remote signer with the given attempt, until it succeeds, fails with
+/// another error than [`SignerError::Unavailable`], or [`MAX_REMOTE_ATTEMPTS`] are made. The
+/// delay between two attempts starts at `retry_delay`, and is doubled on each retry.
+pub(crate) async fn retry_remote<T, F, Fut>(
+    signer: &str,
+    retry_delay: Duration,
+    mut attempt: F,
+) -> Result<T, SignerError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, SignerError>>,
+{
+    let (mut attempts, mut delay) = (1, retry_delay);
+    loop {
+        match attempt().await {
+            Err(SignerError::Unavailable { message, .. }) if attempts < MAX_REMOTE_ATTEMPTS => {
+                warn!(signer, attempts, %message, "Remote signer unavailable, retrying");
+                tokio::time::sleep(delay).await;
+                (attempts, delay) = (attempts + 1, delay * 2);
+            }
+            Err(SignerError::Unavailable { message, .. }) => {
+                return Err(SignerError::Unavailable { attempts, message })
+            }
+            result => return result,
+        }
+    }
+}
+
+/// A backend holding the keys the sidecar signs with: the ECDSA commitment key, and the BLS
+/// constraint key of the validators.
+#[async_trait::async_trait]
+pub trait SignerBackend: fmt::Debug + Send + Sync {
+    /// Returns the address of the commitment key, which the commitment signatures recover to.
+    fn commitment_address(&self) -> Address;
+
+    /// Returns the public key of the constraint key.
+    fn constraint_pubkey(&self) -> BlsPublicKey;
+
+    /// Signs the digest of a commitment request with the commitment key.
+    async fn sign_commitment(&self, digest: &B256) -> Result<Signature, SignerError>;
+
+    /// Signs the data with the constraint key, such as the signing digest of a constraints
+    /// message or of an identity document.
+    async fn sign_bls(&self, data: &[u8]) -> Result<BLSSig, SignerError>;
+
+    /// Returns the public keys of the validator keys of the backend, by default only the
+    /// constraint key.
+    fn validator_pubkeys(&self) -> Vec<BlsPublicKey> {
+        vec![self.constraint_pubkey()]
+    }
+
+    /// Signs the data with the validator key of the public key.
+    async fn sign_bls_with(
+        &self,
+        pubkey: &BlsPublicKey,
+        data: &[u8],
+    ) -> Result<BLSSig, SignerError> {
+        if *pubkey != self.constraint_pubkey() {
+            return Err(unknown_validator_key(pubkey));
+        }
+        self.sign_bls(data).await
+    }
+}
+
+fn unknown_validator_key(pubkey: &BlsPublicKey) -> SignerError {
+    SignerError::Local(format!("unknown validator key 0x{}", hex::encode(pubkey.to_bytes())))
+}
+
+/// A signer backend with the keys held by the sidecar.
+#[derive(Debug, Clone)]
+pub struct LocalSigner<BLS, ECDSA> {
+    constraint_signer: BLS,
+    commitment_signer: ECDSA,
+    /// The validator keys other than the constraint key, such as the ones of the keystores.
+    validator_signers: Vec<BlsSigner>,
+}
+
+impl<BLS, ECDSA> LocalSigner<BLS, ECDSA> {
+    /// Create a local signer backend with the given constraint and commitment signers.
+    pub fn new(constraint_signer: BLS, commitment_signer: ECDSA) -> Self {
+        Self { constraint_signer, commitment_signer, validator_signers: Vec::new() }
+    }
+
+    /// Sets the validator keys the backend signs with, in addition to the constraint key.
+    pub fn with_validator_keys(self, keys: Vec<BlsSecretKey>) -> Self {
+        Self { validator_signers: keys.into_iter().map(BlsSigner::new).collect(), ..self }
+    }
+}
+
+#[async_trait::async_trait]
+impl<BLS, ECDSA> SignerBackend for LocalSigner<BLS, ECDSA>
+where
+    BLS: SignerBLS + fmt::Debug + Send + Sync,
+    ECDSA: SignerECDSA + fmt::Debug + Send + Sync,
+{
+    fn commitment_address(&self) -> Address {
+        self.commitment_signer.address()
+    }
+
+    fn constraint_pubkey(&self) -> BlsPublicKey {
+        self.constraint_signer.pubkey()
+    }
+
+    async fn sign_commitment(&self, digest: &B256) -> Result<Signature, SignerError> {
+        let signature = self.commitment_signer.sign_hash(digest).await;
+        signature.map_err(|err| SignerError::Local(err.to_string()))
+    }
+
+    async fn sign_bls(&self, data: &[u8]) -> Result<BLSSig, SignerError> {
+        self.constraint_signer.sign(data).map_err(|err| SignerError::Local(err.to_string()))
+    }
+
+    fn validator_pubkeys(&self) -> Vec<BlsPublicKey> {
+        let mut pubkeys = vec![self.constraint_signer.pubkey()];
+        for pubkey in self.validator_signers.iter().map(SignerBLS::pubkey) {
+            if !pubkeys.contains(&pubkey) {
+                pubkeys.push(pubkey);
+            }
+        }
+        pubkeys
+    }
+
+    async fn sign_bls_with(
+        &self,
+        pubkey: &BlsPublicKey,
+        data: &[u8],
+    ) -> Result<BLSSig, SignerError> {
+        if *pubkey == self.constraint_signer.pubkey() {
+            return self.sign_bls(data).await;
+        }
+        let signer = self.validator_signers.iter().find(|signer| signer.pubkey() == *pubkey);
+        let signer = signer.ok_or_else(|| unknown_validator_key(pubkey))?;
+        SignerBLS::sign(signer, data).map_err(|err| SignerError::Local(err.to_string()))
+    }
+}
+
+/// The commitment key of a signer backend as an ECDSA signer, such as to sign the responses
+/// of the commitments API.
+#[derive(Debug, Clone)]
+pub struct CommitmentKey(pub Arc<dyn SignerBackend>);
+
+#[async_trait::async_trait]
+impl SignerECDSA for CommitmentKey {
+    async fn sign_hash(&self, hash: &B256) -> signers::Result<Signature> {
+        self.0.sign_commitment(hash).await.map_err(signers::Error::other)
+    }
+
+    fn address(&self) -> Address {
+        self.0.commitment_address()
+    }
+
+    fn chain_id(&self) -> Option<ChainId> {
+        None
+    }
+
+    fn set_chain_id(&mut self, _chain_id: Option<ChainId>) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::signers::local::PrivateKeySigner;
+
+    use super::*;
+    use crate::{crypto::bls::random_bls_secret, test_util::test_bls_secret_key};
+
+    #[tokio::test]
+    async fn test_local_signer() -> eyre::Result<()> {
+        let commitment_signer = PrivateKeySigner::random();
+        let signer = LocalSigner::new(BlsSigner::new(test_bls_secret_key()), commitment_signer);
+        let backend: Arc<dyn SignerBackend> = Arc::new(signer.clone());
+
+        let digest = B256::repeat_byte(1);
+        let signature = backend.sign_commitment(&digest).await?;
+        assert_eq!(signature.recover_address_from_prehash(&digest)?, backend.commitment_address());
+
+        // The commitment key signs the same as the backend
+        let key = CommitmentKey(backend.clone());
+        assert_eq!(key.address(), signer.commitment_signer.address());
+        assert_eq!(key.sign_hash(&digest).await?, signature);
+
+        let signature = backend.sign_bls(digest.as_slice()).await?;
+        assert_eq!(signature, SignerBLS::sign(&signer.constraint_signer, digest.as_slice())?);
+        assert_eq!(backend.constraint_pubkey(), test_bls_secret_key().sk_to_pk());
+
+        // The validator keys sign with their own key, and the constraint key is listed once
+        let validator_key = random_bls_secret();
+        let keys = vec![test_bls_secret_key(), validator_key.clone()];
+        let backend = signer.with_validator_keys(keys);
+        let pubkeys = [test_bls_secret_key().sk_to_pk(), validator_key.sk_to_pk()];
+        assert_eq!(backend.validator_pubkeys(), pubkeys);
+        let signature = backend.sign_bls_with(&pubkeys[1], digest.as_slice()).await?;
+        assert_eq!(signature, SignerBLS::sign(&BlsSigner::new(validator_key), digest.as_slice())?);
+        let unknown = random_bls_secret().sk_to_pk();
+        assert!(backend.sign_bls_with(&unknown, digest.as_slice()).await.is_err());
+
+        Ok(())
+    }
+}
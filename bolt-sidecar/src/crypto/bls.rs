@@ -40,6 +40,9 @@ pub trait SignableBLS {
 pub trait SignerBLS {
     /// Sign the given data and return the signature.
     fn sign(&self, data: &[u8]) -> eyre::Result<BLSSig>;
+
+    /// Returns the public key the signatures can be verified with.
+    fn pubkey(&self) -> BlsPublicKey;
 }
 
 /// A generic signing trait to generate BLS signatures asynchronously.
@@ -83,6 +86,10 @@ impl SignerBLS for Signer {
         let sig = sign_with_prefix(&self.key, data);
         Ok(BLSSig::from(sig.to_bytes()))
     }
+
+    fn pubkey(&self) -> BlsPublicKey {
+        self.key.sk_to_pk()
+    }
 }
 
 #[async_trait::async_trait]
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+
+use alloy::primitives::{Address, Signature, SignatureError, B256};
+
+use crate::primitives::{commitment::SignedCommitment, CommitmentRequest, InclusionRequest};
+
+/// Errors that can occur while importing an externally signed commitment.
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    /// External signing is not enabled on this sidecar.
+    #[error("External commitment signing is not enabled")]
+    NotEnabled,
+    /// No commitment with the given digest is awaiting a signature.
+    #[error("No commitment is awaiting a signature for digest {0}")]
+    UnknownDigest(B256),
+    /// The signature was not made by the configured commitment signer.
+    #[error("Signature was made by {recovered}, expected commitment signer {expected}")]
+    SignerMismatch {
+        /// The configured commitment signer address.
+        expected: Address,
+        /// The address recovered from the signature.
+        recovered: Address,
+    },
+    /// The signature is malformed.
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(#[from] SignatureError),
+}
+
+/// A commitment request that is awaiting its signature.
+#[derive(Debug)]
+struct PendingSignature<T> {
+    request: InclusionRequest,
+    context: T,
+}
+
+/// A commitment signer backend for cold-key setups, where commitments are signed
+/// outside of the sidecar.
+///
+/// Instead of being signed, valid requests are parked until the signature over their digest
+/// is imported. The signature is verified against the configured commitment signer address.
+/// Each pending request carries a context `T` that is returned along with the signed
+/// commitment, to finish processing the request.
+#[derive(Debug)]
+pub struct ExternalSigner<T> {
+    address: Address,
+    pending: HashMap<B256, PendingSignature<T>>,
+}
+
+impl<T> ExternalSigner<T> {
+    /// Create a new external signer backend for the given commitment signer address.
+    pub fn new(address: Address) -> Self {
+        Self { address, pending: HashMap::new() }
+    }
+
+    /// Returns the address of the commitment signer.
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// Parks a request until its signature is imported, returning the digest to sign.
+    pub fn park(&mut self, request: InclusionRequest, context: T) -> B256 {
+        let digest = request.digest();
+        self.pending.insert(digest, PendingSignature { request, context });
+        digest
+    }
+
+    /// Returns the digests of all requests awaiting a signature.
+    pub fn pending_digests(&self) -> Vec<B256> {
+        self.pending.keys().copied().collect()
+    }
+
+    /// Returns the number of requests awaiting a signature.
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Imports the signature over the digest of a pending request. Returns the request,
+    /// its context and the signed commitment.
+    ///
+    /// The request stays pending if the signature is invalid.
+    pub fn import(
+        &mut self,
+        digest: B256,
+        signature: Signature,
+    ) -> Result<(InclusionRequest, T, SignedCommitment), ImportError> {
+        if !self.pending.contains_key(&digest) {
+            return Err(ImportError::UnknownDigest(digest));
+        }
+
+        let recovered = signature.recover_address_from_prehash(&digest)?;
+        if recovered != self.address {
+            return Err(ImportError::SignerMismatch { expected: self.address, recovered });
+        }
+
+        let PendingSignature { request, context } =
+            self.pending.remove(&digest).expect("pending signature exists");
+        let commitment =
+            CommitmentRequest::Inclusion(request.clone()).commit_with_signature(signature);
+
+        Ok((request, context, commitment))
+    }
+
+    /// Removes all pending requests targeting the given slot or earlier, returning their
+    /// contexts. These requests can't be committed to anymore.
+    pub fn expire(&mut self, slot: u64) -> Vec<T> {
+        let expired = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| pending.request.slot <= slot)
+            .map(|(digest, _)| *digest)
+            .collect::<Vec<_>>();
+
+        expired
+            .into_iter()
+            .filter_map(|digest| self.pending.remove(&digest))
+            .map(|pending| pending.context)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::signers::{local::PrivateKeySigner, Signer};
+
+    use crate::test_util::{create_signed_commitment_request, default_test_transaction};
+
+    use super::*;
+
+    async fn test_request(slot: u64) -> InclusionRequest {
+        let sk = alloy::signers::k256::SecretKey::random(&mut rand::thread_rng());
+        let signer = PrivateKeySigner::from(sk.clone());
+        let tx = default_test_transaction(signer.address(), None);
+        let req = create_signed_commitment_request(&[tx], &sk, slot).await.unwrap();
+        req.into_inclusion_request().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_export_sign_import() -> eyre::Result<()> {
+        // The commitment key is only held by the test, the sidecar only knows its address
+        let cold_key = PrivateKeySigner::random();
+        let mut external = ExternalSigner::new(cold_key.address());
+
+        let request = test_request(10).await;
+        let digest = external.park(request.clone(), "ctx");
+        assert_eq!(external.pending_digests(), vec![digest]);
+
+        // Sign the exported digest "offline"
+        let signature = cold_key.sign_hash(&digest).await?;
+
+        let (imported, context, commitment) = external.import(digest, signature)?;
+        assert_eq!(imported, request);
+        assert_eq!(context, "ctx");
+        assert!(external.pending_digests().is_empty());
+
+        // The result is the same as signing with the key directly
+        let expected = CommitmentRequest::Inclusion(request).commit_and_sign(&cold_key).await?;
+        assert_eq!(commitment, expected);
+
+        // The digest can't be imported twice
+        assert!(matches!(external.import(digest, signature), Err(ImportError::UnknownDigest(_))));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_wrong_signer() -> eyre::Result<()> {
+        let cold_key = PrivateKeySigner::random();
+        let other_key = PrivateKeySigner::random();
+        let mut external = ExternalSigner::new(cold_key.address());
+
+        let digest = external.park(test_request(10).await, ());
+
+        let signature = other_key.sign_hash(&digest).await?;
+        let err = external.import(digest, signature).unwrap_err();
+        assert!(matches!(
+            err,
+            ImportError::SignerMismatch { expected, recovered }
+                if expected == cold_key.address() && recovered == other_key.address()
+        ));
+
+        // A signature over another digest doesn't match either
+        let signature = cold_key.sign_hash(&B256::random()).await?;
+        assert!(matches!(
+            external.import(digest, signature),
+            Err(ImportError::SignerMismatch { .. })
+        ));
+
+        // The request is still pending after failed imports
+        assert_eq!(external.pending_digests(), vec![digest]);
+
+        let unknown = B256::random();
+        let signature = cold_key.sign_hash(&unknown).await?;
+        assert!(matches!(external.import(unknown, signature), Err(ImportError::UnknownDigest(_))));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_expire() {
+        let mut external = ExternalSigner::new(Address::random());
+
+        external.park(test_request(10).await, 10);
+        external.park(test_request(11).await, 11);
+        let digest = external.park(test_request(12).await, 12);
+
+        let mut expired = external.expire(11);
+        expired.sort();
+        assert_eq!(expired, vec![10, 11]);
+        assert_eq!(external.pending_digests(), vec![digest]);
+    }
+}
@@ -0,0 +1,319 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use aes::Aes128;
+use ctr::{
+    cipher::{KeyIvInit, StreamCipher},
+    Ctr128BE,
+};
+use serde::{Deserialize, Deserializer};
+use sha2::{Digest, Sha256};
+use unicode_normalization::UnicodeNormalization;
+use zeroize::Zeroizing;
+
+use super::bls::BlsSecretKey;
+
+/// The version of the EIP-2335 keystores.
+const KEYSTORE_VERSION: u32 = 4;
+
+/// The length of the keys derived from the password, half of which is the cipher key, and the
+/// other half is checksummed along with the ciphertext.
+const DERIVED_KEY_LEN: usize = 32;
+
+/// The directory of EIP-2335 keystores of the local validator keys, and their password file.
+#[derive(Debug, Clone)]
+pub struct KeystoreConfig {
+    /// The directory of the keystores, the files with a `.json` extension.
+    pub dir: PathBuf,
+    /// The file of the password of all the keystores.
+    pub password_file: PathBuf,
+}
+
+/// Errors of the loading of the keystores.
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum KeystoreError {
+    #[error("Failed to read {}: {source}", .path.display())]
+    Io { path: PathBuf, source: io::Error },
+    #[error("No keystore in {}", .0.display())]
+    Empty(PathBuf),
+    #[error("Corrupt keystore {file}: {message}")]
+    Corrupt { file: String, message: String },
+    #[error("Wrong password for the keystore {file}")]
+    WrongPassword { file: String },
+    #[error("Duplicate public key {pubkey} in the keystores {first} and {second}")]
+    Duplicate { pubkey: String, first: String, second: String },
+}
+
+/// Errors of the decryption of a keystore.
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum DecryptError {
+    /// The checksum doesn't match, which is a wrong password unless the keystore was altered.
+    #[error("wrong password")]
+    WrongPassword,
+    #[error("{0}")]
+    Invalid(String),
+}
+
+/// An EIP-2335 keystore, securing a BLS secret key with a password.
+#[derive(Debug, Deserialize)]
+pub struct Keystore {
+    crypto: KeystoreCrypto,
+    /// The public key of the secret, if given.
+    #[serde(default, deserialize_with = "hex_bytes")]
+    pubkey: Vec<u8>,
+    version: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeystoreCrypto {
+    kdf: Kdf,
+    checksum: Checksum,
+    cipher: Cipher,
+}
+
+/// The function deriving the decryption key from the password.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "function", rename_all = "lowercase")]
+enum Kdf {
+    Scrypt { params: ScryptParams },
+    Pbkdf2 { params: Pbkdf2Params },
+}
+
+#[derive(Debug, Deserialize)]
+struct ScryptParams {
+    dklen: usize,
+    n: u64,
+    r: u32,
+    p: u32,
+    #[serde(deserialize_with = "hex_bytes")]
+    salt: Vec<u8>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Pbkdf2Params {
+    dklen: usize,
+    c: u32,
+    prf: String,
+    #[serde(deserialize_with = "hex_bytes")]
+    salt: Vec<u8>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Checksum {
+    function: String,
+    #[serde(deserialize_with = "hex_bytes")]
+    message: Vec<u8>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Cipher {
+    function: String,
+    params: CipherParams,
+    #[serde(deserialize_with = "hex_bytes")]
+    message: Vec<u8>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CipherParams {
+    #[serde(deserialize_with = "hex_bytes")]
+    iv: Vec<u8>,
+}
+
+impl Keystore {
+    /// Decrypts the secret key with the password. The password is normalized as EIP-2335
+    /// specifies: NFKD, without the control codes.
+    pub fn decrypt(&self, password: &str) -> Result<BlsSecretKey, DecryptError> {
+        let invalid = DecryptError::Invalid;
+        if self.version != KEYSTORE_VERSION {
+            return Err(invalid(format!("unsupported version {}", self.version)));
+        }
+        let password: Zeroizing<String> = Zeroizing::new(
+            password.nfkd().filter(|c| !matches!(*c as u32, 0x00..=0x1f | 0x7f..=0x9f)).collect(),
+        );
+
+        let mut key = Zeroizing::new([0u8; DERIVED_KEY_LEN]);
+        match self.crypto.kdf {
+            Kdf::Scrypt { ref params } => {
+                if params.dklen != DERIVED_KEY_LEN || !params.n.is_power_of_two() || params.n < 2 {
+                    return Err(invalid("invalid scrypt parameters".to_string()));
+                }
+                let log_n = params.n.trailing_zeros() as u8;
+                let scrypt = scrypt::Params::new(log_n, params.r, params.p, DERIVED_KEY_LEN)
+                    .map_err(|err| invalid(format!("invalid scrypt parameters: {err}")))?;
+                scrypt::scrypt(password.as_bytes(), &params.salt, &scrypt, &mut key[..])
+                    .map_err(|err| invalid(format!("invalid scrypt parameters: {err}")))?;
+            }
+            Kdf::Pbkdf2 { ref params } => {
+                if params.dklen != DERIVED_KEY_LEN || params.prf != "hmac-sha256" {
+                    return Err(invalid("invalid pbkdf2 parameters".to_string()));
+                }
+                let (salt, rounds) = (&params.salt, params.c);
+                pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, rounds, &mut key[..]);
+            }
+        }
+
+        let KeystoreCrypto { checksum, cipher, .. } = &self.crypto;
+        if checksum.function != "sha256" {
+            return Err(invalid(format!("unsupported checksum function {}", checksum.function)));
+        }
+        let digest = Sha256::new().chain_update(&key[16..]).chain_update(&cipher.message);
+        if digest.finalize().as_slice() != checksum.message {
+            return Err(DecryptError::WrongPassword);
+        }
+
+        if cipher.function != "aes-128-ctr" {
+            return Err(invalid(format!("unsupported cipher function {}", cipher.function)));
+        }
+        let mut secret = Zeroizing::new(cipher.message.clone());
+        Ctr128BE::<Aes128>::new_from_slices(&key[..16], &cipher.params.iv)
+            .map_err(|_| invalid("invalid cipher IV".to_string()))?
+            .apply_keystream(&mut secret[..]);
+        let secret = BlsSecretKey::from_bytes(&secret)
+            .map_err(|err| invalid(format!("invalid secret key: {err:?}")))?;
+
+        if !self.pubkey.is_empty() && self.pubkey != secret.sk_to_pk().to_bytes() {
+            return Err(invalid("the public key doesn't match the secret key".to_string()));
+        }
+        Ok(secret)
+    }
+}
+
+/// Loads the secret keys of the keystores of the directory, the files with a `.json`
+/// extension in the order of their names, all secured with the password of the file.
+///
+/// The password and the intermediate key material are zeroized once the keys are decrypted,
+/// and the secret keys when they are dropped.
+pub fn load_keystores(
+    dir: &Path,
+    password_file: &Path,
+) -> Result<Vec<BlsSecretKey>, KeystoreError> {
+    let io_error = |path: &Path| {
+        let path = path.to_path_buf();
+        move |source| KeystoreError::Io { path, source }
+    };
+    let password =
+        Zeroizing::new(fs::read_to_string(password_file).map_err(io_error(password_file))?);
+    let password = password.trim_end_matches(['\n', '\r']);
+
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir).map_err(io_error(dir))? {
+        let path = entry.map_err(io_error(dir))?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
+            paths.push(path);
+        }
+    }
+    if paths.is_empty() {
+        return Err(KeystoreError::Empty(dir.to_path_buf()));
+    }
+    paths.sort();
+
+    let mut keys = Vec::with_capacity(paths.len());
+    let mut files = HashMap::new();
+    for path in paths {
+        let file = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let corrupt = |message: String| KeystoreError::Corrupt { file: file.clone(), message };
+
+        let contents = fs::read_to_string(&path).map_err(io_error(&path))?;
+        let keystore: Keystore =
+            serde_json::from_str(&contents).map_err(|err| corrupt(err.to_string()))?;
+        let secret = keystore.decrypt(password).map_err(|err| match err {
+            DecryptError::WrongPassword => KeystoreError::WrongPassword { file: file.clone() },
+            DecryptError::Invalid(message) => corrupt(message),
+        })?;
+
+        let pubkey = format!("0x{}", hex::encode(secret.sk_to_pk().to_bytes()));
+        if let Some(first) = files.insert(pubkey.clone(), file.clone()) {
+            return Err(KeystoreError::Duplicate { pubkey, first, second: file });
+        }
+        keys.push(secret);
+    }
+
+    Ok(keys)
+}
+
+/// Deserializes hex bytes, with or without the `0x` prefix.
+fn hex_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    hex::decode(value.strip_prefix("0x").unwrap_or(&value)).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRYPT_PUBKEY: &str = concat!(
+        "0x9612d7a727c9d0a22e185a1c768478dfe919cada9266988c",
+        "b32359c11f2b7b27f4ae4040902382ae2910c15e2b420d07"
+    );
+    const PBKDF2_PUBKEY: &str = concat!(
+        "0xb346477f7d1d242d28e8b4d0d28f87215358faedab9bd14b",
+        "fa903567e85c63d873e53336329595208c6b1d2cef5863a0"
+    );
+
+    fn testdata() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("src/crypto/testdata/keystores")
+    }
+
+    /// Copies the given test keystores to a new directory, with the given names.
+    fn keystore_dir(files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bolt-keystores-{}", rand::random::<u64>()));
+        fs::create_dir(&dir).unwrap();
+        for (name, fixture) in files {
+            fs::copy(testdata().join(fixture), dir.join(name)).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_load_keystores() {
+        // The password of the fixtures is the one of the EIP-2335 test vectors, normalized
+        let password = testdata().join("password.txt");
+        let keys = load_keystores(&testdata(), &password).unwrap();
+
+        let pubkeys =
+            keys.iter().map(|key| format!("0x{}", hex::encode(key.sk_to_pk().to_bytes())));
+        assert_eq!(pubkeys.collect::<Vec<_>>(), [PBKDF2_PUBKEY, SCRYPT_PUBKEY]);
+
+        let keystore = fs::read_to_string(testdata().join("keystore-scrypt.json")).unwrap();
+        let keystore: Keystore = serde_json::from_str(&keystore).unwrap();
+        let secret = keystore.decrypt("testpassword🔑").unwrap();
+        assert_eq!(
+            hex::encode(secret.to_bytes()),
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+        );
+    }
+
+    #[test]
+    fn test_load_keystores_errors() {
+        let wrong_password = std::env::temp_dir().join(format!("bolt-{}", rand::random::<u64>()));
+        fs::write(&wrong_password, "wrong password\n").unwrap();
+        let err = load_keystores(&testdata(), &wrong_password).unwrap_err();
+        assert_eq!(err.to_string(), "Wrong password for the keystore keystore-pbkdf2.json");
+        fs::remove_file(wrong_password).unwrap();
+
+        let password = testdata().join("password.txt");
+        let dir =
+            keystore_dir(&[("a.json", "keystore-scrypt.json"), ("b.json", "keystore-scrypt.json")]);
+        let err = load_keystores(&dir, &password).unwrap_err();
+        let message =
+            format!("Duplicate public key {SCRYPT_PUBKEY} in the keystores a.json and b.json");
+        assert_eq!(err.to_string(), message);
+
+        fs::write(dir.join("b.json"), r#"{"crypto": {}, "version": 4}"#).unwrap();
+        let err = load_keystores(&dir, &password).unwrap_err();
+        assert!(
+            matches!(err, KeystoreError::Corrupt { ref file, .. } if file == "b.json"),
+            "{err}"
+        );
+        fs::remove_dir_all(dir).unwrap();
+
+        let dir = keystore_dir(&[]);
+        assert!(matches!(load_keystores(&dir, &password), Err(KeystoreError::Empty(_))));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -2,5 +2,16 @@
 pub mod bls;
 pub use bls::{SignableBLS, SignerBLS, SignerBLSAsync};
 
+/// Signer backends of the commitments and the constraints, local or remote.
+pub mod backend;
+pub use backend::{CommitmentKey, LocalSigner, SignerBackend, SignerError};
+
+/// EIP-2335 keystores of the local validator keys.
+pub mod keystore;
+pub use keystore::{load_keystores, KeystoreConfig, KeystoreError};
+
 /// ECDSA signatures and verification functions.
 pub mod ecdsa;
+
+/// External signer backend, for commitments signed outside of the sidecar.
+pub mod external;
@@ -0,0 +1,73 @@
+//! Compact binary encoding of the records kept in the store.
+//!
+//! Every record starts with a version byte, followed by its bincode encoding. This lets
+//! the layout of a record change without rewriting the whole table at once.
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// The version of the record layout written by this version of the sidecar.
+pub const RECORD_VERSION: u8 = 1;
+
+/// Errors that can occur while encoding or decoding a record.
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum CodecError {
+    #[error("Empty record")]
+    Empty,
+    #[error("Unsupported record version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Invalid record encoding: {0}")]
+    Bincode(#[from] bincode::Error),
+}
+
+/// Encodes a record, prefixed with the current record version.
+pub fn encode<T: Serialize>(record: &T) -> Result<Vec<u8>, CodecError> {
+    let mut buf = vec![RECORD_VERSION];
+    bincode::serialize_into(&mut buf, record)?;
+    Ok(buf)
+}
+
+/// Decodes a record written by [`encode`].
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, CodecError> {
+    match bytes.split_first() {
+        Some((&RECORD_VERSION, payload)) => Ok(bincode::deserialize(payload)?),
+        Some((&version, _)) => Err(CodecError::UnsupportedVersion(version)),
+        None => Err(CodecError::Empty),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{Address, TxHash, B256};
+
+    use super::*;
+    use crate::earnings::CommitmentFee;
+
+    #[test]
+    fn test_record_roundtrip() -> eyre::Result<()> {
+        let fee = CommitmentFee {
+            digest: B256::random(),
+            slot: 10,
+            signer: Address::random(),
+            timestamp: 1_000,
+            tx_hashes: vec![TxHash::random()],
+            expected_fee: u128::MAX,
+            captured_fee: Some(21_000),
+            included_txs: Some(1),
+        };
+
+        let bytes = encode(&fee)?;
+        assert_eq!(bytes[0], RECORD_VERSION);
+        assert_eq!(decode::<CommitmentFee>(&bytes)?, fee);
+
+        let mut future = bytes.clone();
+        future[0] = RECORD_VERSION + 1;
+        assert!(matches!(
+            decode::<CommitmentFee>(&future),
+            Err(CodecError::UnsupportedVersion(v)) if v == RECORD_VERSION + 1
+        ));
+        assert!(matches!(decode::<CommitmentFee>(&[]), Err(CodecError::Empty)));
+
+        Ok(())
+    }
+}
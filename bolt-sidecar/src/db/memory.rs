@@ -0,0 +1,371 @@
+//! The in-memory backend of the store, for the builds without the `persistence` feature.
+//!
+//! The records are kept in the same encoding as in the SQLite backend, and read back in the
+//! same order.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use alloy::primitives::B256;
+use parking_lot::{Mutex, MutexGuard};
+use serde::de::DeserializeOwned;
+
+use super::{codec, Lease, StoreError};
+use crate::{
+    earnings::{AnnotatedFee, CommitmentFee},
+    finality::{FeeFinality, Finality},
+    inclusion::{CommitmentStatus, IssuedRecord},
+    submissions::PendingSubmission,
+};
+
+/// A handle to the sidecar's in-memory store. Cheap to clone.
+#[derive(Clone, Default)]
+pub struct Store {
+    tables: Arc<Mutex<Tables>>,
+}
+
+impl std::fmt::Debug for Store {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Store").finish_non_exhaustive()
+    }
+}
+
+/// The tables of the store.
+#[derive(Debug, Default)]
+struct Tables {
+    /// The commitment fee records, by digest.
+    commitment_fees: HashMap<B256, FeeRow>,
+    /// The constraints pending submission, by slot.
+    pending_submissions: BTreeMap<u64, Vec<u8>>,
+    /// The records of the issued commitments, by digest.
+    issued_commitments: HashMap<B256, IssuedRow>,
+    /// The validator leases, by public key.
+    validator_leases: HashMap<String, Lease>,
+    /// The number of rows inserted so far, which orders the rows inserted at the same time.
+    inserted: u64,
+    /// Whether the store rejects all writes.
+    #[cfg(test)]
+    read_only: bool,
+}
+
+impl Tables {
+    /// Returns the insertion order of a new row.
+    fn next_row(&mut self) -> u64 {
+        self.inserted += 1;
+        self.inserted
+    }
+}
+
+/// A commitment fee record, with the columns it is queried by.
+#[derive(Debug)]
+struct FeeRow {
+    row: u64,
+    slot: u64,
+    timestamp: u64,
+    reconciled: bool,
+    context: Option<String>,
+    finality: Option<FeeFinality>,
+    payload: Vec<u8>,
+}
+
+impl FeeRow {
+    fn fee(&self) -> Result<CommitmentFee, StoreError> {
+        read_record(&self.payload)
+    }
+
+    fn annotated_fee(&self) -> Result<AnnotatedFee, StoreError> {
+        Ok(AnnotatedFee { fee: self.fee()?, finality: self.finality })
+    }
+}
+
+/// An issued commitment record, with its status.
+#[derive(Debug)]
+struct IssuedRow {
+    row: u64,
+    slot: u64,
+    status: CommitmentStatus,
+    payload: Vec<u8>,
+}
+
+impl Store {
+    /// Open a store that only lives in memory.
+    pub fn in_memory() -> Result<Self, StoreError> {
+        Ok(Self::default())
+    }
+
+    /// Locks the tables for a write, which fails if the store rejects writes.
+    fn write(&self) -> Result<MutexGuard<'_, Tables>, StoreError> {
+        let tables = self.tables.lock();
+        #[cfg(test)]
+        if tables.read_only {
+            return Err(StoreError::ReadOnly);
+        }
+
+        Ok(tables)
+    }
+
+    /// Insert a new commitment fee record, with the context the client set on the request.
+    /// Existing records with the same digest are kept.
+    pub fn insert_commitment_fee(
+        &self,
+        fee: &CommitmentFee,
+        context: Option<&str>,
+    ) -> Result<(), StoreError> {
+        let payload = codec::encode(fee)?;
+        let mut tables = self.write()?;
+        if tables.commitment_fees.contains_key(&fee.digest) {
+            return Ok(());
+        }
+
+        let row = FeeRow {
+            row: tables.next_row(),
+            slot: fee.slot,
+            timestamp: fee.timestamp,
+            reconciled: fee.captured_fee.is_some(),
+            context: context.map(str::to_owned),
+            finality: None,
+            payload,
+        };
+        tables.commitment_fees.insert(fee.digest, row);
+
+        Ok(())
+    }
+
+    /// Checks that the store accepts writes. Nothing is ever written.
+    pub fn check_writable(&self, _digest: &B256) -> Result<(), StoreError> {
+        self.write().map(drop)
+    }
+
+    /// Removes the commitment fee record with the given digest, if any.
+    pub fn remove_commitment_fee(&self, digest: &B256) -> Result<(), StoreError> {
+        self.write()?.commitment_fees.remove(digest);
+        Ok(())
+    }
+
+    /// Returns the commitment fee record with the given digest, if any.
+    pub fn get_commitment_fee(&self, digest: &B256) -> Result<Option<CommitmentFee>, StoreError> {
+        self.tables.lock().commitment_fees.get(digest).map(FeeRow::fee).transpose()
+    }
+
+    /// Returns all commitment fee records for the given slot that haven't been reconciled yet.
+    pub fn unreconciled_fees(&self, slot: u64) -> Result<Vec<CommitmentFee>, StoreError> {
+        let tables = self.tables.lock();
+        let rows = sorted_fees(&tables, |row| row.slot == slot && !row.reconciled, |_| ());
+        rows.into_iter().map(FeeRow::fee).collect()
+    }
+
+    /// Stores the reconciled fee and number of included transactions for a commitment, with
+    /// the finality of the block it was reconciled against.
+    pub fn set_reconciled_fee(
+        &self,
+        digest: &B256,
+        captured_fee: u128,
+        included_txs: usize,
+        finality: &FeeFinality,
+    ) -> Result<(), StoreError> {
+        let mut tables = self.write()?;
+        let Some(row) = tables.commitment_fees.get_mut(digest) else {
+            return Ok(());
+        };
+
+        let mut fee = row.fee()?;
+        fee.captured_fee = Some(captured_fee);
+        fee.included_txs = Some(included_txs);
+        row.payload = codec::encode(&fee)?;
+        row.reconciled = true;
+        row.finality = Some(*finality);
+
+        Ok(())
+    }
+
+    /// Returns the reconciled commitment fee records that aren't finalized yet, with their
+    /// finality, ordered by slot.
+    pub fn fees_pending_finality(&self) -> Result<Vec<(CommitmentFee, FeeFinality)>, StoreError> {
+        let tables = self.tables.lock();
+        let pending = |row: &FeeRow| {
+            let level = row.finality.map_or(0, |finality| finality.level.as_u8());
+            row.reconciled && level < Finality::Finalized.as_u8()
+        };
+        let rows = sorted_fees(&tables, pending, |row| row.slot);
+        rows.into_iter().map(|row| Ok((row.fee()?, row.finality.unwrap_or_default()))).collect()
+    }
+
+    /// Returns all commitment fee records with a timestamp in `[from, to)`.
+    pub fn fees_in_range(&self, from: u64, to: u64) -> Result<Vec<CommitmentFee>, StoreError> {
+        let tables = self.tables.lock();
+        let in_range = |row: &FeeRow| (from..to).contains(&row.timestamp);
+        let rows = sorted_fees(&tables, in_range, |row| row.timestamp);
+        rows.into_iter().map(FeeRow::fee).collect()
+    }
+
+    /// Returns all commitment fee records with a timestamp in `[from, to)`, with their
+    /// finality.
+    pub fn annotated_fees_in_range(
+        &self,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<AnnotatedFee>, StoreError> {
+        let tables = self.tables.lock();
+        let in_range = |row: &FeeRow| (from..to).contains(&row.timestamp);
+        let rows = sorted_fees(&tables, in_range, |row| row.timestamp);
+        rows.into_iter().map(FeeRow::annotated_fee).collect()
+    }
+
+    /// Returns the commitment fee records of the requests with the given context, with their
+    /// finality, oldest first.
+    pub fn fees_by_context(&self, context: &str) -> Result<Vec<AnnotatedFee>, StoreError> {
+        let tables = self.tables.lock();
+        let with_context = |row: &FeeRow| row.context.as_deref() == Some(context);
+        let rows = sorted_fees(&tables, with_context, |row| (row.timestamp, row.slot));
+        rows.into_iter().map(FeeRow::annotated_fee).collect()
+    }
+
+    /// Inserts or replaces the constraints pending submission for their slot.
+    pub fn put_pending_submission(&self, submission: &PendingSubmission) -> Result<(), StoreError> {
+        let payload = codec::encode(submission)?;
+        self.write()?.pending_submissions.insert(submission.slot, payload);
+        Ok(())
+    }
+
+    /// Returns the constraints pending submission for the given slot, if any.
+    pub fn get_pending_submission(
+        &self,
+        slot: u64,
+    ) -> Result<Option<PendingSubmission>, StoreError> {
+        let tables = self.tables.lock();
+        tables.pending_submissions.get(&slot).map(|payload| read_record(payload)).transpose()
+    }
+
+    /// Returns all the constraints pending submission, ordered by slot.
+    pub fn pending_submissions(&self) -> Result<Vec<PendingSubmission>, StoreError> {
+        let tables = self.tables.lock();
+        tables.pending_submissions.values().map(|payload| read_record(payload)).collect()
+    }
+
+    /// Removes the constraints pending submission for the given slot.
+    pub fn remove_pending_submission(&self, slot: u64) -> Result<(), StoreError> {
+        self.write()?.pending_submissions.remove(&slot);
+        Ok(())
+    }
+
+    /// Removes the constraints pending submission for all the slots before `slot`.
+    pub fn remove_pending_submissions_before(&self, slot: u64) -> Result<usize, StoreError> {
+        let mut tables = self.write()?;
+        let kept = tables.pending_submissions.split_off(&slot);
+        let removed = std::mem::replace(&mut tables.pending_submissions, kept);
+        Ok(removed.len())
+    }
+
+    /// Inserts or replaces the record of an issued commitment, as pending.
+    pub fn insert_issued_commitment(&self, record: &IssuedRecord) -> Result<(), StoreError> {
+        let payload = codec::encode(record)?;
+        let mut tables = self.write()?;
+        let row = tables.next_row();
+        let status = CommitmentStatus::Pending;
+        let issued = IssuedRow { row, slot: record.slot, status, payload };
+        tables.issued_commitments.insert(record.digest, issued);
+
+        Ok(())
+    }
+
+    /// Updates the status of the issued commitment with the given digest, if it is recorded.
+    pub fn set_commitment_status(
+        &self,
+        digest: &B256,
+        status: &CommitmentStatus,
+    ) -> Result<(), StoreError> {
+        if let Some(row) = self.write()?.issued_commitments.get_mut(digest) {
+            row.status = status.clone();
+        }
+
+        Ok(())
+    }
+
+    /// Returns the issued commitments targeting `slot` or a later one, with their status,
+    /// ordered by slot.
+    pub fn issued_commitments(
+        &self,
+        slot: u64,
+    ) -> Result<Vec<(IssuedRecord, CommitmentStatus)>, StoreError> {
+        let tables = self.tables.lock();
+        let mut rows =
+            tables.issued_commitments.values().filter(|row| row.slot >= slot).collect::<Vec<_>>();
+        rows.sort_by_key(|row| (row.slot, row.row));
+        rows.into_iter().map(|row| Ok((read_record(&row.payload)?, row.status.clone()))).collect()
+    }
+
+    /// Removes the issued commitments targeting the slots before `slot`.
+    pub fn remove_issued_commitments_before(&self, slot: u64) -> Result<usize, StoreError> {
+        let mut tables = self.write()?;
+        let before = tables.issued_commitments.len();
+        tables.issued_commitments.retain(|_, row| row.slot >= slot);
+        Ok(before - tables.issued_commitments.len())
+    }
+
+    /// Makes the store reject all writes, or accept them again, as when its disk is full.
+    #[cfg(test)]
+    pub(crate) fn set_read_only(&self, read_only: bool) {
+        self.tables.lock().read_only = read_only;
+    }
+
+    /// Acquires or renews the lease on a validator for `holder` until `now + ttl` (in
+    /// milliseconds). Returns false if the lease is held by another instance and hasn't
+    /// expired yet.
+    pub fn acquire_lease(
+        &self,
+        pubkey: &str,
+        holder: &str,
+        now: u64,
+        ttl: u64,
+    ) -> Result<bool, StoreError> {
+        let mut tables = self.write()?;
+        if let Some(lease) = tables.validator_leases.get(pubkey) {
+            if lease.holder != holder && lease.expires_at > now {
+                return Ok(false);
+            }
+        }
+
+        let lease = Lease {
+            pubkey: pubkey.to_owned(),
+            holder: holder.to_owned(),
+            expires_at: now.saturating_add(ttl),
+        };
+        tables.validator_leases.insert(pubkey.to_owned(), lease);
+
+        Ok(true)
+    }
+
+    /// Releases the lease on a validator, if it is held by `holder`.
+    pub fn release_lease(&self, pubkey: &str, holder: &str) -> Result<(), StoreError> {
+        let mut tables = self.write()?;
+        if tables.validator_leases.get(pubkey).is_some_and(|lease| lease.holder == holder) {
+            tables.validator_leases.remove(pubkey);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the current lease on a validator, if any. The lease may have expired.
+    pub fn get_lease(&self, pubkey: &str) -> Result<Option<Lease>, StoreError> {
+        Ok(self.tables.lock().validator_leases.get(pubkey).cloned())
+    }
+}
+
+/// Returns the commitment fee rows matching the filter, ordered by the given key, then by
+/// insertion.
+fn sorted_fees<K: Ord>(
+    tables: &Tables,
+    filter: impl Fn(&FeeRow) -> bool,
+    key: impl Fn(&FeeRow) -> K,
+) -> Vec<&FeeRow> {
+    let mut rows = tables.commitment_fees.values().filter(|row| filter(row)).collect::<Vec<_>>();
+    rows.sort_by_key(|row| (key(row), row.row));
+    rows
+}
+
+/// Reads a record from its encoded payload.
+fn read_record<T: DeserializeOwned>(payload: &[u8]) -> Result<T, StoreError> {
+    Ok(codec::decode(payload)?)
+}
@@ -0,0 +1,481 @@
+//! Versioning of the store schema, and the forward migrations between versions.
+
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use alloy::primitives::{Address, TxHash};
+use rusqlite::{
+    params, types::Type, Connection, DatabaseName, OptionalExtension, Row, Transaction,
+    TransactionBehavior,
+};
+use serde::Serialize;
+use tracing::info;
+
+use super::{codec, StoreError};
+use crate::earnings::CommitmentFee;
+
+/// The version of the store schema written by this version of the sidecar.
+pub const SCHEMA_VERSION: u32 = 6;
+
+/// A forward migration of the store schema.
+struct Migration {
+    /// The schema version after the migration is applied.
+    version: u32,
+    description: &'static str,
+    apply: fn(&Transaction<'_>) -> Result<(), StoreError>,
+}
+
+/// All migrations, ordered by version.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Create the commitment fee and validator lease tables",
+        apply: create_tables,
+    },
+    Migration {
+        version: 2,
+        description: "Store commitment fee records in the compact binary encoding",
+        apply: encode_fee_records,
+    },
+    Migration {
+        version: 3,
+        description: "Create the table of the constraints pending submission",
+        apply: create_pending_submissions,
+    },
+    Migration {
+        version: 4,
+        description: "Store the client context of the commitments with their fee records",
+        apply: add_commitment_contexts,
+    },
+    Migration {
+        version: 5,
+        description: "Annotate the reconciled commitment fees with the finality of their block",
+        apply: add_fee_finality,
+    },
+    Migration {
+        version: 6,
+        description: "Create the table of the issued commitments and their status",
+        apply: create_issued_commitments,
+    },
+];
+
+/// A migration that hasn't been applied to a store yet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PendingMigration {
+    /// The schema version after the migration is applied.
+    pub version: u32,
+    /// What the migration does.
+    pub description: &'static str,
+}
+
+/// The schema version of a store, and the migrations needed to bring it up to date.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MigrationStatus {
+    /// The current schema version of the store.
+    pub version: u32,
+    /// The schema version written by this version of the sidecar.
+    pub target: u32,
+    /// The migrations that will be applied when the store is next opened.
+    pub pending: Vec<PendingMigration>,
+}
+
+impl MigrationStatus {
+    /// Returns the migration status of the store on the given connection.
+    pub(crate) fn of(conn: &Connection) -> Result<Self, StoreError> {
+        let version = schema_version(conn)?;
+        if version > SCHEMA_VERSION {
+            return Err(StoreError::NewerVersion { found: version, supported: SCHEMA_VERSION });
+        }
+
+        let pending = MIGRATIONS
+            .iter()
+            .filter(|migration| migration.version > version)
+            .map(|migration| PendingMigration {
+                version: migration.version,
+                description: migration.description,
+            })
+            .collect();
+
+        Ok(Self { version, target: SCHEMA_VERSION, pending })
+    }
+}
+
+/// Returns the schema version of the store. Stores created before the schema was
+/// versioned are at version 1, and empty databases at version 0.
+fn schema_version(conn: &Connection) -> Result<u32, StoreError> {
+    if table_exists(conn, "schema_version")? {
+        return Ok(conn.query_row("SELECT version FROM schema_version", [], |row| row.get(0))?);
+    }
+
+    Ok(if table_exists(conn, "commitment_fees")? { 1 } else { 0 })
+}
+
+fn table_exists(conn: &Connection, name: &str) -> Result<bool, StoreError> {
+    let found = conn
+        .query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            params![name],
+            |_| Ok(()),
+        )
+        .optional()?;
+
+    Ok(found.is_some())
+}
+
+/// Applies all pending migrations in a single transaction, so that a failed migration
+/// leaves the store untouched. Stores on disk are backed up next to the database file
+/// before they're migrated; the path of the backup is returned.
+///
+/// Fails with [`StoreError::NewerVersion`] if the store was written by a newer sidecar.
+pub(crate) fn migrate(
+    conn: &mut Connection,
+    path: Option<&Path>,
+) -> Result<Option<PathBuf>, StoreError> {
+    // Take the write lock before reading the version, in case another instance sharing
+    // the database file is migrating it at the same time
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let status = MigrationStatus::of(&tx)?;
+    if status.pending.is_empty() {
+        return Ok(None);
+    }
+
+    // Empty databases have nothing worth backing up
+    let backup = match path {
+        Some(path) if status.version > 0 => {
+            let backup = backup_path(path, status.version);
+            tx.backup(DatabaseName::Main, &backup, None)?;
+            Some(backup)
+        }
+        _ => None,
+    };
+
+    for migration in MIGRATIONS.iter().filter(|migration| migration.version > status.version) {
+        info!(version = migration.version, "Applying store migration: {}", migration.description);
+        (migration.apply)(&tx)?;
+    }
+
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_version (version INTEGER NOT NULL);
+         DELETE FROM schema_version;",
+    )?;
+    tx.execute("INSERT INTO schema_version (version) VALUES (?1)", params![SCHEMA_VERSION])?;
+    tx.commit()?;
+
+    Ok(backup)
+}
+
+/// Returns a path for the backup of the store at `path`, taken at schema `version`.
+/// Existing backups are never overwritten.
+fn backup_path(path: &Path, version: u32) -> PathBuf {
+    let with_suffix = |suffix: String| {
+        let mut name = path.as_os_str().to_owned();
+        name.push(suffix);
+        PathBuf::from(name)
+    };
+
+    let mut backup = with_suffix(format!(".v{version}.bak"));
+    let mut n = 1;
+    while backup.exists() {
+        backup = with_suffix(format!(".v{version}.{n}.bak"));
+        n += 1;
+    }
+
+    backup
+}
+
+/// Version 1: the original schema, from before the store was versioned.
+fn create_tables(tx: &Transaction<'_>) -> Result<(), StoreError> {
+    tx.execute_batch(
+        "CREATE TABLE commitment_fees (
+            digest        TEXT PRIMARY KEY,
+            slot          INTEGER NOT NULL,
+            signer        TEXT NOT NULL,
+            timestamp     INTEGER NOT NULL,
+            tx_hashes     TEXT NOT NULL,
+            expected_fee  TEXT NOT NULL,
+            captured_fee  TEXT,
+            included_txs  INTEGER
+        );
+        CREATE INDEX commitment_fees_slot ON commitment_fees (slot);
+        CREATE INDEX commitment_fees_timestamp ON commitment_fees (timestamp);
+        CREATE TABLE validator_leases (
+            pubkey      TEXT PRIMARY KEY,
+            holder      TEXT NOT NULL,
+            expires_at  INTEGER NOT NULL
+        );",
+    )?;
+
+    Ok(())
+}
+
+/// Version 2: commitment fee records are stored as encoded payloads, with only the
+/// columns needed for lookups kept alongside them.
+fn encode_fee_records(tx: &Transaction<'_>) -> Result<(), StoreError> {
+    tx.execute_batch(
+        "CREATE TABLE commitment_fees_v2 (
+            digest      BLOB PRIMARY KEY,
+            slot        INTEGER NOT NULL,
+            timestamp   INTEGER NOT NULL,
+            reconciled  INTEGER NOT NULL,
+            payload     BLOB NOT NULL
+        );",
+    )?;
+
+    let fees = {
+        let mut stmt = tx.prepare("SELECT * FROM commitment_fees")?;
+        let rows = stmt.query_map([], read_v1_fee)?;
+        rows.collect::<Result<Vec<_>, _>>()?
+    };
+
+    for fee in fees {
+        tx.execute(
+            "INSERT INTO commitment_fees_v2 (digest, slot, timestamp, reconciled, payload)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                fee.digest.as_slice(),
+                fee.slot,
+                fee.timestamp,
+                fee.captured_fee.is_some(),
+                codec::encode(&fee)?,
+            ],
+        )?;
+    }
+
+    tx.execute_batch(
+        "DROP TABLE commitment_fees;
+         ALTER TABLE commitment_fees_v2 RENAME TO commitment_fees;
+         CREATE INDEX commitment_fees_slot ON commitment_fees (slot, reconciled);
+         CREATE INDEX commitment_fees_timestamp ON commitment_fees (timestamp);",
+    )?;
+
+    Ok(())
+}
+
+/// Version 3: the constraints of each slot are kept until they're submitted to the relays,
+/// as encoded payloads.
+fn create_pending_submissions(tx: &Transaction<'_>) -> Result<(), StoreError> {
+    tx.execute_batch(
+        "CREATE TABLE pending_submissions (
+            slot     INTEGER PRIMARY KEY,
+            payload  BLOB NOT NULL
+        );",
+    )?;
+
+    Ok(())
+}
+
+/// Version 4: the context set by the client on a commitment request is kept next to its
+/// fee record, to look the commitments up by context.
+fn add_commitment_contexts(tx: &Transaction<'_>) -> Result<(), StoreError> {
+    tx.execute_batch(
+        "ALTER TABLE commitment_fees ADD COLUMN context TEXT;
+         CREATE INDEX commitment_fees_context ON commitment_fees (context);",
+    )?;
+
+    Ok(())
+}
+
+/// Version 5: the reconciled fee records are annotated with the finality of the block they
+/// were reconciled against, and its hash. Records reconciled before are annotated as seen
+/// in an unknown block, and are checked again once their slot is justified.
+fn add_fee_finality(tx: &Transaction<'_>) -> Result<(), StoreError> {
+    tx.execute_batch(
+        "ALTER TABLE commitment_fees ADD COLUMN finality INTEGER;
+         ALTER TABLE commitment_fees ADD COLUMN block_hash BLOB;
+         UPDATE commitment_fees SET finality = 0 WHERE reconciled = 1;",
+    )?;
+
+    Ok(())
+}
+
+/// Version 6: the commitments issued by the driver are kept with their constraints and
+/// inclusion status, as encoded payloads, to restore them after a restart. The status is
+/// stored as JSON.
+fn create_issued_commitments(tx: &Transaction<'_>) -> Result<(), StoreError> {
+    tx.execute_batch(
+        "CREATE TABLE issued_commitments (
+            digest   BLOB PRIMARY KEY,
+            slot     INTEGER NOT NULL,
+            status   TEXT NOT NULL,
+            payload  BLOB NOT NULL
+        );
+        CREATE INDEX issued_commitments_slot ON issued_commitments (slot);",
+    )?;
+
+    Ok(())
+}
+
+/// Parses a text column into `T`, mapping parse errors to a conversion failure.
+fn parse_column<T>(row: &Row<'_>, name: &str) -> rusqlite::Result<T>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let value: String = row.get(name)?;
+    value.parse().map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(
+            row.as_ref().column_index(name).unwrap_or_default(),
+            Type::Text,
+            Box::new(e),
+        )
+    })
+}
+
+/// Reads a commitment fee record from a row of the version 1 schema.
+fn read_v1_fee(row: &Row<'_>) -> rusqlite::Result<CommitmentFee> {
+    let tx_hashes: String = row.get("tx_hashes")?;
+    let tx_hashes = tx_hashes
+        .split(',')
+        .filter(|s| !s.is_empty())
+        .map(TxHash::from_str)
+        .collect::<Result<_, _>>()
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(e)))?;
+
+    let captured_fee: Option<String> = row.get("captured_fee")?;
+    let captured_fee = captured_fee
+        .map(|fee| fee.parse::<u128>())
+        .transpose()
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(e)))?;
+
+    Ok(CommitmentFee {
+        digest: parse_column(row, "digest")?,
+        slot: row.get("slot")?,
+        signer: parse_column::<Address>(row, "signer")?,
+        timestamp: row.get("timestamp")?,
+        tx_hashes,
+        expected_fee: parse_column(row, "expected_fee")?,
+        captured_fee,
+        included_txs: row.get::<_, Option<u64>>("included_txs")?.map(|n| n as usize),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::B256;
+
+    use super::*;
+    use crate::db::Store;
+
+    fn fee(slot: u64, captured_fee: Option<u128>) -> CommitmentFee {
+        CommitmentFee {
+            digest: B256::random(),
+            slot,
+            signer: Address::random(),
+            timestamp: 1_000 + slot,
+            tx_hashes: vec![TxHash::random(), TxHash::random()],
+            expected_fee: 42_000_000_000_000,
+            captured_fee,
+            included_txs: captured_fee.map(|_| 2),
+        }
+    }
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bolt-migrations-{}", rand::random::<u64>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Writes a store at version 1, the way sidecars did before the schema was versioned.
+    fn v1_fixture(path: &Path, fees: &[CommitmentFee]) -> eyre::Result<()> {
+        let mut conn = Connection::open(path)?;
+        let tx = conn.transaction()?;
+        create_tables(&tx)?;
+
+        for fee in fees {
+            let tx_hashes = fee.tx_hashes.iter().map(|h| h.to_string()).collect::<Vec<_>>();
+            tx.execute(
+                "INSERT INTO commitment_fees
+                    (digest, slot, signer, timestamp, tx_hashes, expected_fee, captured_fee,
+                     included_txs)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    fee.digest.to_string(),
+                    fee.slot,
+                    fee.signer.to_string(),
+                    fee.timestamp,
+                    tx_hashes.join(","),
+                    fee.expected_fee.to_string(),
+                    fee.captured_fee.map(|fee| fee.to_string()),
+                    fee.included_txs.map(|n| n as u64),
+                ],
+            )?;
+        }
+        tx.execute(
+            "INSERT INTO validator_leases (pubkey, holder, expires_at) VALUES ('0xaa', 'a', 10)",
+            [],
+        )?;
+
+        Ok(tx.commit()?)
+    }
+
+    #[test]
+    fn test_migrate_v1_store() -> eyre::Result<()> {
+        let dir = temp_dir();
+        let path = dir.join("sidecar.db");
+        let fees = [fee(10, None), fee(10, Some(21_000)), fee(11, None)];
+        v1_fixture(&path, &fees)?;
+
+        let status = Store::migration_status(&path)?;
+        assert_eq!(status.version, 1);
+        let pending = status.pending.iter().map(|m| m.version).collect::<Vec<_>>();
+        assert_eq!(pending, vec![2, 3, 4, 5, 6]);
+
+        let store = Store::open(&path)?;
+        for fee in &fees {
+            assert_eq!(store.get_commitment_fee(&fee.digest)?.as_ref(), Some(fee));
+        }
+        assert_eq!(store.unreconciled_fees(10)?, vec![fees[0].clone()]);
+        assert_eq!(store.fees_pending_finality()?, vec![(fees[1].clone(), Default::default())]);
+        assert_eq!(store.get_lease("0xaa")?.map(|lease| lease.expires_at), Some(10));
+        drop(store);
+
+        assert!(path.with_extension("db.v1.bak").exists());
+        let status = Store::migration_status(&path)?;
+        assert_eq!((status.version, status.pending.len()), (SCHEMA_VERSION, 0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_migration_keeps_backup() -> eyre::Result<()> {
+        let dir = temp_dir();
+        let path = dir.join("sidecar.db");
+        let fees = [fee(10, None)];
+        v1_fixture(&path, &fees)?;
+
+        // Corrupt the record so that it can't be converted
+        Connection::open(&path)?
+            .execute("UPDATE commitment_fees SET tx_hashes = 'not-a-hash'", [])?;
+
+        assert!(Store::open(&path).is_err());
+
+        // The store is left at version 1, and the backup still has the original record
+        assert_eq!(Store::migration_status(&path)?.version, 1);
+        let backup = Connection::open(path.with_extension("db.v1.bak"))?;
+        let tx_hashes: String =
+            backup.query_row("SELECT tx_hashes FROM commitment_fees", [], |row| row.get(0))?;
+        assert_eq!(tx_hashes, "not-a-hash");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_refuse_newer_version() -> eyre::Result<()> {
+        let dir = temp_dir();
+        let path = dir.join("sidecar.db");
+        drop(Store::open(&path)?);
+
+        Connection::open(&path)?
+            .execute("UPDATE schema_version SET version = ?1", params![SCHEMA_VERSION + 1])?;
+
+        let found = SCHEMA_VERSION + 1;
+        assert!(matches!(
+            Store::open(&path),
+            Err(StoreError::NewerVersion { found: f, .. }) if f == found
+        ));
+
+        Ok(())
+    }
+}
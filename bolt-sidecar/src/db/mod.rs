@@ -0,0 +1,181 @@
+//! Storage for the sidecar. With the `persistence` feature, the store is backed by SQLite,
+//! on disk or in memory. Without it, the store only lives in memory.
+
+mod codec;
+pub use codec::CodecError;
+
+#[cfg(feature = "persistence")]
+mod migrations;
+#[cfg(feature = "persistence")]
+pub use migrations::{MigrationStatus, PendingMigration, SCHEMA_VERSION};
+
+#[cfg(feature = "persistence")]
+mod sqlite;
+#[cfg(feature = "persistence")]
+pub use sqlite::Store;
+
+#[cfg(not(feature = "persistence"))]
+mod memory;
+#[cfg(not(feature = "persistence"))]
+pub use memory::Store;
+
+mod resilient;
+pub use resilient::{CommitmentStore, NonCriticalWrite, ResilientStore};
+
+/// Errors that can occur while reading from or writing to the store.
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum StoreError {
+    #[cfg(feature = "persistence")]
+    #[error("Database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[cfg(not(feature = "persistence"))]
+    #[error("Store is read-only")]
+    ReadOnly,
+    #[error("Record error: {0}")]
+    Codec(#[from] CodecError),
+    #[error("Commitment status error: {0}")]
+    Status(#[from] serde_json::Error),
+    #[error("Store is at schema version {found}, but only up to {supported} is supported")]
+    NewerVersion { found: u32, supported: u32 },
+}
+
+/// A lease on a validator, held by a sidecar instance until it expires.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lease {
+    /// The validator public key.
+    pub pubkey: String,
+    /// The identifier of the sidecar instance holding the lease.
+    pub holder: String,
+    /// The expiry of the lease, as a UNIX timestamp in milliseconds.
+    pub expires_at: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{Address, TxHash, B256};
+
+    use super::*;
+    use crate::{
+        earnings::{AnnotatedFee, CommitmentFee},
+        finality::{FeeFinality, Finality},
+        inclusion::{CommitmentStatus, IssuedRecord},
+    };
+
+    fn fee(slot: u64, timestamp: u64) -> CommitmentFee {
+        CommitmentFee {
+            digest: B256::random(),
+            slot,
+            signer: Address::random(),
+            timestamp,
+            tx_hashes: vec![TxHash::random(), TxHash::random()],
+            expected_fee: 42_000_000_000_000,
+            captured_fee: None,
+            included_txs: None,
+        }
+    }
+
+    #[test]
+    fn test_validator_leases() -> eyre::Result<()> {
+        let store = Store::in_memory()?;
+
+        assert!(store.acquire_lease("0xaa", "first", 1_000, 500)?);
+        assert!(!store.acquire_lease("0xaa", "second", 1_200, 500)?);
+
+        // The holder can renew its lease
+        assert!(store.acquire_lease("0xaa", "first", 1_400, 500)?);
+        let lease = store.get_lease("0xaa")?.unwrap();
+        assert_eq!((lease.holder.as_str(), lease.expires_at), ("first", 1_900));
+
+        // Another instance can take over an expired lease
+        assert!(!store.acquire_lease("0xaa", "second", 1_899, 500)?);
+        assert!(store.acquire_lease("0xaa", "second", 1_900, 500)?);
+
+        // Releasing only works for the holder
+        store.release_lease("0xaa", "first")?;
+        assert_eq!(store.get_lease("0xaa")?.unwrap().holder, "second");
+        store.release_lease("0xaa", "second")?;
+        assert_eq!(store.get_lease("0xaa")?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_commitment_fees_roundtrip() -> eyre::Result<()> {
+        let store = Store::in_memory()?;
+
+        let first = fee(10, 1_000);
+        let second = fee(10, 1_010);
+        let third = fee(11, 2_000);
+
+        store.insert_commitment_fee(&first, Some("user-1"))?;
+        store.insert_commitment_fee(&second, None)?;
+        store.insert_commitment_fee(&third, Some("user-1"))?;
+
+        assert_eq!(store.get_commitment_fee(&first.digest)?, Some(first.clone()));
+        assert_eq!(store.unreconciled_fees(10)?.len(), 2);
+
+        let seen = FeeFinality { level: Finality::Seen, block_hash: Some(B256::random()) };
+        store.set_reconciled_fee(&first.digest, 21_000, 1, &seen)?;
+        assert_eq!(store.unreconciled_fees(10)?, vec![second.clone()]);
+
+        let reconciled = store.get_commitment_fee(&first.digest)?.unwrap();
+        assert_eq!(reconciled.captured_fee, Some(21_000));
+        assert_eq!(reconciled.included_txs, Some(1));
+
+        let in_range = store.fees_in_range(1_000, 2_000)?;
+        assert_eq!(in_range.len(), 2);
+        assert!(in_range.iter().all(|fee| fee.slot == 10));
+
+        let by_context = store.fees_by_context("user-1")?;
+        let expected = vec![
+            AnnotatedFee { fee: reconciled.clone(), finality: Some(seen) },
+            AnnotatedFee { fee: third, finality: None },
+        ];
+        assert_eq!(by_context, expected);
+        assert_eq!(store.fees_by_context("user-2")?, vec![]);
+
+        // Finalized fees aren't pending finality anymore
+        assert_eq!(store.fees_pending_finality()?, vec![(reconciled, seen)]);
+        let finalized = FeeFinality { level: Finality::Finalized, ..seen };
+        store.set_reconciled_fee(&first.digest, 21_000, 1, &finalized)?;
+        assert_eq!(store.fees_pending_finality()?, vec![]);
+
+        // Write checks leave no record behind
+        let digest = B256::random();
+        store.check_writable(&digest)?;
+        assert_eq!(store.get_commitment_fee(&digest)?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_issued_commitments_roundtrip() -> eyre::Result<()> {
+        let store = Store::in_memory()?;
+
+        let record = |slot| IssuedRecord {
+            digest: B256::random(),
+            slot,
+            signer: Some(Address::random()),
+            validator_index: 7,
+            commitment: b"{}".to_vec(),
+            constraints_signature: Default::default(),
+        };
+        let (first, second, third) = (record(10), record(12), record(11));
+        for record in [&first, &second, &third] {
+            store.insert_issued_commitment(record)?;
+        }
+
+        // The records are read back by slot, with their latest status
+        let cancelled = CommitmentStatus::Cancelled;
+        store.set_commitment_status(&third.digest, &cancelled)?;
+        let issued = store.issued_commitments(11)?;
+        let pending = CommitmentStatus::Pending;
+        assert_eq!(issued, vec![(third.clone(), cancelled), (second.clone(), pending.clone())]);
+
+        store.remove_issued_commitments_before(12)?;
+        assert_eq!(store.issued_commitments(0)?, vec![(second, pending)]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,349 @@
+//! The degradation policy of the store, for when it stops accepting writes at runtime (e.g.
+//! a full disk, or a database file locked by another process).
+//!
+//! Writes are split in two kinds by the [`CommitmentStore`] trait:
+//! - critical writes record a commitment before it is signed, and again with its signed
+//!   constraints before it is returned. If they fail, the request is rejected, so that the
+//!   sidecar never issues a commitment it has no record of.
+//! - non-critical writes, such as the submission state of the constraints, the reconciled
+//!   fees and the inclusion statuses, are buffered in memory while the store is unavailable,
+//!   and replayed in order once it recovers.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use alloy::primitives::B256;
+use parking_lot::Mutex;
+use tracing::{info, warn};
+
+use super::{Store, StoreError};
+use crate::{
+    alerts::{AlertCondition, Alerts},
+    earnings::CommitmentFee,
+    finality::FeeFinality,
+    inclusion::{CommitmentStatus, IssuedRecord},
+    submissions::PendingSubmission,
+    telemetry,
+};
+
+/// The writes of the commitment pipeline to the store, by how their failures are handled.
+pub trait CommitmentStore {
+    /// Records the fee of a commitment that is about to be signed. This write is critical:
+    /// if it fails, the commitment must not be signed.
+    fn record_commitment(
+        &self,
+        fee: &CommitmentFee,
+        context: Option<&str>,
+    ) -> Result<(), StoreError>;
+
+    /// Records a commitment whose constraints were signed, before it is returned to the
+    /// client. This write is critical: if it fails, the commitment must not be issued.
+    fn record_issued(&self, record: &IssuedRecord) -> Result<(), StoreError>;
+
+    /// Applies a non-critical write. It never fails: while the store is unavailable, the
+    /// write is buffered in memory until it recovers.
+    fn write(&self, write: NonCriticalWrite);
+}
+
+/// A write that can be deferred while the store is unavailable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NonCriticalWrite {
+    /// Inserts or replaces the constraints pending submission for their slot.
+    PutSubmission(PendingSubmission),
+    /// Removes the constraints pending submission for the slot.
+    RemoveSubmission(u64),
+    /// Removes the constraints pending submission for all the slots before `before`.
+    PruneSubmissions {
+        /// The first slot whose submission is kept.
+        before: u64,
+    },
+    /// Stores the reconciled fee and number of included transactions for a commitment.
+    ReconcileFee {
+        /// The digest of the commitment request.
+        digest: B256,
+        /// The captured priority fee, in wei.
+        captured_fee: u128,
+        /// The number of committed transactions that were included.
+        included_txs: usize,
+        /// The finality of the block the commitment was reconciled against.
+        finality: FeeFinality,
+    },
+    /// Removes the fee record of a commitment that was recorded but not issued.
+    DiscardCommitment(B256),
+    /// Updates the inclusion status of an issued commitment.
+    SetCommitmentStatus {
+        /// The digest of the commitment request.
+        digest: B256,
+        /// The new status of the commitment.
+        status: CommitmentStatus,
+    },
+    /// Removes the issued commitments targeting the slots before `before`.
+    PruneCommitments {
+        /// The first slot whose commitments are kept.
+        before: u64,
+    },
+}
+
+impl NonCriticalWrite {
+    fn apply(&self, store: &Store) -> Result<(), StoreError> {
+        match self {
+            Self::PutSubmission(submission) => store.put_pending_submission(submission),
+            Self::RemoveSubmission(slot) => store.remove_pending_submission(*slot),
+            Self::PruneSubmissions { before } => {
+                store.remove_pending_submissions_before(*before).map(|_| ())
+            }
+            Self::ReconcileFee { digest, captured_fee, included_txs, finality } => {
+                store.set_reconciled_fee(digest, *captured_fee, *included_txs, finality)
+            }
+            Self::DiscardCommitment(digest) => store.remove_commitment_fee(digest),
+            Self::SetCommitmentStatus { digest, status } => {
+                store.set_commitment_status(digest, status)
+            }
+            Self::PruneCommitments { before } => {
+                store.remove_issued_commitments_before(*before).map(|_| ())
+            }
+        }
+    }
+
+    /// Applies the write to the pending submissions by slot.
+    fn apply_to_submissions(&self, submissions: &mut BTreeMap<u64, PendingSubmission>) {
+        match self {
+            Self::PutSubmission(submission) => {
+                submissions.insert(submission.slot, submission.clone());
+            }
+            Self::RemoveSubmission(slot) => {
+                submissions.remove(slot);
+            }
+            Self::PruneSubmissions { before } => submissions.retain(|slot, _| slot >= before),
+            Self::ReconcileFee { .. } |
+            Self::DiscardCommitment(_) |
+            Self::SetCommitmentStatus { .. } |
+            Self::PruneCommitments { .. } => {}
+        }
+    }
+}
+
+/// A handle to the store that applies the degradation policy of [`CommitmentStore`].
+/// Cheap to clone, the buffered writes are shared by all the handles.
+///
+/// Once a non-critical write fails, the following ones are buffered as well, so that they
+/// are replayed in order. Critical writes are only attempted once the buffer is replayed.
+#[derive(Debug, Clone)]
+pub struct ResilientStore {
+    store: Store,
+    buffer: Arc<Mutex<Vec<NonCriticalWrite>>>,
+    alerts: Alerts,
+}
+
+impl ResilientStore {
+    /// Create a new handle writing to `store`.
+    pub fn new(store: Store) -> Self {
+        Self { store, buffer: Arc::default(), alerts: Alerts::default() }
+    }
+
+    /// Raise an alert while the non-critical writes are buffered in memory.
+    pub fn with_alerts(self, alerts: Alerts) -> Self {
+        Self { alerts, ..self }
+    }
+
+    /// Returns the underlying store, to read from or write to directly.
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /// Returns true if non-critical writes are buffered in memory, waiting for the store
+    /// to recover.
+    pub fn is_degraded(&self) -> bool {
+        !self.buffer.lock().is_empty()
+    }
+
+    /// Returns the constraints pending submission for the given slot, if any, including the
+    /// buffered writes.
+    pub fn pending_submission(&self, slot: u64) -> Result<Option<PendingSubmission>, StoreError> {
+        let buffer = self.buffer.lock();
+        let mut submissions = BTreeMap::new();
+        if let Some(submission) = self.store.get_pending_submission(slot)? {
+            submissions.insert(slot, submission);
+        }
+
+        buffer.iter().for_each(|write| write.apply_to_submissions(&mut submissions));
+        Ok(submissions.remove(&slot))
+    }
+
+    /// Returns all the constraints pending submission, ordered by slot, including the
+    /// buffered writes.
+    pub fn pending_submissions(&self) -> Result<Vec<PendingSubmission>, StoreError> {
+        let buffer = self.buffer.lock();
+        let submissions = self.store.pending_submissions()?.into_iter();
+        let mut submissions: BTreeMap<_, _> =
+            submissions.map(|submission| (submission.slot, submission)).collect();
+
+        buffer.iter().for_each(|write| write.apply_to_submissions(&mut submissions));
+        Ok(submissions.into_values().collect())
+    }
+
+    /// Checks that the store accepts writes again, and replays the buffered writes in order.
+    /// Returns the number of replayed writes.
+    ///
+    /// If a write fails, it is kept in the buffer with the ones after it.
+    pub fn recover(&self) -> Result<usize, StoreError> {
+        let mut buffer = self.buffer.lock();
+        if buffer.is_empty() {
+            return Ok(0);
+        }
+
+        self.store.check_writable(&B256::ZERO)?;
+
+        let mut replayed = 0;
+        let mut result = Ok(());
+        for write in buffer.iter() {
+            result = write.apply(&self.store);
+            if result.is_err() {
+                break;
+            }
+            replayed += 1;
+        }
+
+        buffer.drain(..replayed);
+        metrics::gauge!(telemetry::STORE_BUFFERED_WRITES).set(buffer.len() as f64);
+        result?;
+
+        info!(replayed, "Store recovered, replayed the writes buffered in memory");
+        self.alerts.clear(AlertCondition::StoreDegraded);
+        Ok(replayed)
+    }
+}
+
+impl CommitmentStore for ResilientStore {
+    fn record_commitment(
+        &self,
+        fee: &CommitmentFee,
+        context: Option<&str>,
+    ) -> Result<(), StoreError> {
+        // The buffered writes may discard a previous record with the same digest
+        self.recover()?;
+        self.store.insert_commitment_fee(fee, context)
+    }
+
+    fn record_issued(&self, record: &IssuedRecord) -> Result<(), StoreError> {
+        // The buffered writes may update the status of a previous record with the same digest
+        self.recover()?;
+        self.store.insert_issued_commitment(record)
+    }
+
+    fn write(&self, write: NonCriticalWrite) {
+        let mut buffer = self.buffer.lock();
+        if buffer.is_empty() {
+            let Err(err) = write.apply(&self.store) else { return };
+
+            warn!(?err, "Store unavailable, buffering the non-critical writes in memory");
+            let message = format!("Store unavailable, buffering writes in memory: {err}");
+            self.alerts.raise(AlertCondition::StoreDegraded, message);
+        }
+
+        // Submissions are replaced as a whole, so only the last one of a slot is kept
+        if let NonCriticalWrite::PutSubmission(ref submission) = write {
+            let slot = submission.slot;
+            buffer.retain(|buffered| {
+                !matches!(buffered, NonCriticalWrite::PutSubmission(s) if s.slot == slot)
+            });
+        }
+
+        buffer.push(write);
+        metrics::counter!(telemetry::STORE_DEGRADED_WRITES).increment(1);
+        metrics::gauge!(telemetry::STORE_BUFFERED_WRITES).set(buffer.len() as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{Address, TxHash};
+
+    use super::*;
+    use crate::submissions::SubmissionTarget;
+
+    fn fee(slot: u64) -> CommitmentFee {
+        CommitmentFee {
+            digest: B256::random(),
+            slot,
+            signer: Address::random(),
+            timestamp: 1_000,
+            tx_hashes: vec![TxHash::random()],
+            expected_fee: 21_000,
+            captured_fee: None,
+            included_txs: None,
+        }
+    }
+
+    #[test]
+    fn test_critical_writes_fail_while_unavailable() -> eyre::Result<()> {
+        let store = ResilientStore::new(Store::in_memory()?);
+        let (first, second) = (fee(10), fee(10));
+
+        store.store().set_read_only(true);
+        assert!(store.record_commitment(&first, None).is_err());
+        assert_eq!(store.store().get_commitment_fee(&first.digest)?, None);
+
+        // A buffered write is replayed before the next commitment is recorded
+        store.write(NonCriticalWrite::DiscardCommitment(first.digest));
+        assert!(store.is_degraded());
+        assert!(store.record_commitment(&second, None).is_err());
+
+        store.store().set_read_only(false);
+        store.record_commitment(&second, Some("user-1"))?;
+        assert!(!store.is_degraded());
+        assert_eq!(store.store().get_commitment_fee(&second.digest)?, Some(second));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_critical_writes_are_buffered_and_replayed() -> eyre::Result<()> {
+        let store = ResilientStore::new(Store::in_memory()?);
+        let recorded = fee(10);
+        store.record_commitment(&recorded, None)?;
+
+        let (mut first, second) = (PendingSubmission::new(10)?, PendingSubmission::new(11)?);
+        store.write(NonCriticalWrite::PutSubmission(first.clone()));
+        assert!(!store.is_degraded());
+
+        store.store().set_read_only(true);
+        first.targets.push(SubmissionTarget {
+            url: "http://relay".to_string(),
+            attempts: 1,
+            last_error: Some("unreachable".to_string()),
+            delivered: false,
+            quarantined: false,
+        });
+        for write in [
+            NonCriticalWrite::PutSubmission(first.clone()),
+            NonCriticalWrite::PutSubmission(second.clone()),
+            NonCriticalWrite::ReconcileFee {
+                digest: recorded.digest,
+                captured_fee: 20_000,
+                included_txs: 1,
+                finality: FeeFinality::default(),
+            },
+            NonCriticalWrite::RemoveSubmission(11),
+        ] {
+            store.write(write);
+        }
+
+        // The buffered writes are visible, but not persisted
+        assert!(store.is_degraded());
+        assert_eq!(store.pending_submission(10)?, Some(first.clone()));
+        assert_eq!(store.pending_submission(11)?, None);
+        assert_eq!(store.pending_submissions()?, vec![first.clone()]);
+        assert_eq!(store.store().get_pending_submission(10)?.unwrap().targets, vec![]);
+        assert!(store.recover().is_err());
+
+        store.store().set_read_only(false);
+        assert_eq!(store.recover()?, 4);
+        assert!(!store.is_degraded());
+        assert_eq!(store.store().pending_submissions()?, vec![first]);
+        let reconciled = store.store().get_commitment_fee(&recorded.digest)?.unwrap();
+        assert_eq!(reconciled.captured_fee, Some(20_000));
+        assert_eq!(store.recover()?, 0);
+
+        Ok(())
+    }
+}
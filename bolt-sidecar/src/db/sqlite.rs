@@ -0,0 +1,395 @@
+//! The SQLite backend of the store, persisted on disk or in memory.
+
+use std::{path::Path, sync::Arc};
+
+use alloy::primitives::B256;
+use parking_lot::Mutex;
+use rusqlite::{params, types::Type, Connection, OpenFlags, OptionalExtension, Row};
+use serde::de::DeserializeOwned;
+use tracing::info;
+
+use super::{codec, migrations, Lease, MigrationStatus, StoreError, SCHEMA_VERSION};
+use crate::{
+    earnings::{AnnotatedFee, CommitmentFee},
+    finality::{FeeFinality, Finality},
+    inclusion::{CommitmentStatus, IssuedRecord},
+    submissions::PendingSubmission,
+};
+
+/// A handle to the sidecar's persistent store. Cheap to clone.
+#[derive(Clone)]
+pub struct Store {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl std::fmt::Debug for Store {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Store").finish_non_exhaustive()
+    }
+}
+
+impl Store {
+    /// Open the store at the given path, creating it if it doesn't exist. Stores written
+    /// by older versions of the sidecar are migrated, after being backed up.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, StoreError> {
+        let path = path.as_ref();
+        Self::from_connection(Connection::open(path)?, Some(path))
+    }
+
+    /// Open a store that only lives in memory, for when no path is configured.
+    pub fn in_memory() -> Result<Self, StoreError> {
+        Self::from_connection(Connection::open_in_memory()?, None)
+    }
+
+    fn from_connection(mut conn: Connection, path: Option<&Path>) -> Result<Self, StoreError> {
+        // Wait for other instances sharing the same database file instead of failing
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        if let Some(backup) = migrations::migrate(&mut conn, path)? {
+            info!(backup = %backup.display(), version = SCHEMA_VERSION, "Migrated the store");
+        }
+
+        Ok(Self { conn: Arc::new(Mutex::new(conn)) })
+    }
+
+    /// Returns the schema version of the existing store at the given path, and the
+    /// migrations that opening it would apply. The store is not modified.
+    pub fn migration_status<P: AsRef<Path>>(path: P) -> Result<MigrationStatus, StoreError> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        MigrationStatus::of(&conn)
+    }
+
+    /// Insert a new commitment fee record, with the context the client set on the request.
+    /// Existing records with the same digest are kept.
+    pub fn insert_commitment_fee(
+        &self,
+        fee: &CommitmentFee,
+        context: Option<&str>,
+    ) -> Result<(), StoreError> {
+        self.conn.lock().execute(
+            "INSERT OR IGNORE INTO commitment_fees
+                (digest, slot, timestamp, reconciled, context, payload)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                fee.digest.as_slice(),
+                fee.slot,
+                fee.timestamp,
+                fee.captured_fee.is_some(),
+                context,
+                codec::encode(fee)?,
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Checks that the store accepts writes, by inserting a record with the given digest in a
+    /// transaction that is rolled back: nothing is ever persisted.
+    pub fn check_writable(&self, digest: &B256) -> Result<(), StoreError> {
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO commitment_fees (digest, slot, timestamp, reconciled, payload)
+             VALUES (?1, 0, 0, 0, x'')",
+            params![digest.as_slice()],
+        )?;
+        tx.rollback()?;
+
+        Ok(())
+    }
+
+    /// Removes the commitment fee record with the given digest, if any.
+    pub fn remove_commitment_fee(&self, digest: &B256) -> Result<(), StoreError> {
+        self.conn
+            .lock()
+            .execute("DELETE FROM commitment_fees WHERE digest = ?1", params![digest.as_slice()])?;
+        Ok(())
+    }
+
+    /// Returns the commitment fee record with the given digest, if any.
+    pub fn get_commitment_fee(&self, digest: &B256) -> Result<Option<CommitmentFee>, StoreError> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare("SELECT payload FROM commitment_fees WHERE digest = ?1")?;
+        Ok(stmt.query_row(params![digest.as_slice()], read_fee).optional()?)
+    }
+
+    /// Returns all commitment fee records for the given slot that haven't been reconciled yet.
+    pub fn unreconciled_fees(&self, slot: u64) -> Result<Vec<CommitmentFee>, StoreError> {
+        let conn = self.conn.lock();
+        let mut stmt = conn
+            .prepare("SELECT payload FROM commitment_fees WHERE slot = ?1 AND reconciled = 0")?;
+        let rows = stmt.query_map(params![slot], read_fee)?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Stores the reconciled fee and number of included transactions for a commitment, with
+    /// the finality of the block it was reconciled against.
+    pub fn set_reconciled_fee(
+        &self,
+        digest: &B256,
+        captured_fee: u128,
+        included_txs: usize,
+        finality: &FeeFinality,
+    ) -> Result<(), StoreError> {
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+
+        let fee = tx
+            .query_row(
+                "SELECT payload FROM commitment_fees WHERE digest = ?1",
+                params![digest.as_slice()],
+                read_fee,
+            )
+            .optional()?;
+
+        if let Some(mut fee) = fee {
+            fee.captured_fee = Some(captured_fee);
+            fee.included_txs = Some(included_txs);
+            tx.execute(
+                "UPDATE commitment_fees
+                 SET reconciled = 1, payload = ?2, finality = ?3, block_hash = ?4
+                 WHERE digest = ?1",
+                params![
+                    digest.as_slice(),
+                    codec::encode(&fee)?,
+                    finality.level.as_u8(),
+                    finality.block_hash.as_ref().map(|hash| hash.as_slice()),
+                ],
+            )?;
+        }
+
+        Ok(tx.commit()?)
+    }
+
+    /// Returns the reconciled commitment fee records that aren't finalized yet, with their
+    /// finality, ordered by slot.
+    pub fn fees_pending_finality(&self) -> Result<Vec<(CommitmentFee, FeeFinality)>, StoreError> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(
+            "SELECT payload, finality, block_hash FROM commitment_fees
+             WHERE reconciled = 1 AND IFNULL(finality, 0) < ?1 ORDER BY slot",
+        )?;
+        let rows = stmt.query_map(params![Finality::Finalized.as_u8()], |row| {
+            let AnnotatedFee { fee, finality } = read_annotated_fee(row)?;
+            Ok((fee, finality.unwrap_or_default()))
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Returns all commitment fee records with a timestamp in `[from, to)`.
+    pub fn fees_in_range(&self, from: u64, to: u64) -> Result<Vec<CommitmentFee>, StoreError> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(
+            "SELECT payload FROM commitment_fees WHERE timestamp >= ?1 AND timestamp < ?2
+             ORDER BY timestamp",
+        )?;
+        let rows = stmt.query_map(params![from, to], read_fee)?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Returns all commitment fee records with a timestamp in `[from, to)`, with their
+    /// finality.
+    pub fn annotated_fees_in_range(
+        &self,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<AnnotatedFee>, StoreError> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(
+            "SELECT payload, finality, block_hash FROM commitment_fees
+             WHERE timestamp >= ?1 AND timestamp < ?2 ORDER BY timestamp",
+        )?;
+        let rows = stmt.query_map(params![from, to], read_annotated_fee)?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Returns the commitment fee records of the requests with the given context, with their
+    /// finality, oldest first.
+    pub fn fees_by_context(&self, context: &str) -> Result<Vec<AnnotatedFee>, StoreError> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(
+            "SELECT payload, finality, block_hash FROM commitment_fees
+             WHERE context = ?1 ORDER BY timestamp, slot",
+        )?;
+        let rows = stmt.query_map(params![context], read_annotated_fee)?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Inserts or replaces the constraints pending submission for their slot.
+    pub fn put_pending_submission(&self, submission: &PendingSubmission) -> Result<(), StoreError> {
+        self.conn.lock().execute(
+            "INSERT OR REPLACE INTO pending_submissions (slot, payload) VALUES (?1, ?2)",
+            params![submission.slot, codec::encode(submission)?],
+        )?;
+
+        Ok(())
+    }
+
+    /// Returns the constraints pending submission for the given slot, if any.
+    pub fn get_pending_submission(
+        &self,
+        slot: u64,
+    ) -> Result<Option<PendingSubmission>, StoreError> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare("SELECT payload FROM pending_submissions WHERE slot = ?1")?;
+        Ok(stmt.query_row(params![slot], read_record).optional()?)
+    }
+
+    /// Returns all the constraints pending submission, ordered by slot.
+    pub fn pending_submissions(&self) -> Result<Vec<PendingSubmission>, StoreError> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare("SELECT payload FROM pending_submissions ORDER BY slot")?;
+        let rows = stmt.query_map([], read_record)?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Removes the constraints pending submission for the given slot.
+    pub fn remove_pending_submission(&self, slot: u64) -> Result<(), StoreError> {
+        self.conn.lock().execute("DELETE FROM pending_submissions WHERE slot = ?1", params![slot])?;
+        Ok(())
+    }
+
+    /// Removes the constraints pending submission for all the slots before `slot`.
+    pub fn remove_pending_submissions_before(&self, slot: u64) -> Result<usize, StoreError> {
+        let conn = self.conn.lock();
+        Ok(conn.execute("DELETE FROM pending_submissions WHERE slot < ?1", params![slot])?)
+    }
+
+    /// Inserts or replaces the record of an issued commitment, as pending.
+    pub fn insert_issued_commitment(&self, record: &IssuedRecord) -> Result<(), StoreError> {
+        let status = serde_json::to_string(&CommitmentStatus::Pending)?;
+        self.conn.lock().execute(
+            "INSERT OR REPLACE INTO issued_commitments (digest, slot, status, payload)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![record.digest.as_slice(), record.slot, status, codec::encode(record)?],
+        )?;
+
+        Ok(())
+    }
+
+    /// Updates the status of the issued commitment with the given digest, if it is recorded.
+    pub fn set_commitment_status(
+        &self,
+        digest: &B256,
+        status: &CommitmentStatus,
+    ) -> Result<(), StoreError> {
+        self.conn.lock().execute(
+            "UPDATE issued_commitments SET status = ?2 WHERE digest = ?1",
+            params![digest.as_slice(), serde_json::to_string(status)?],
+        )?;
+
+        Ok(())
+    }
+
+    /// Returns the issued commitments targeting `slot` or a later one, with their status,
+    /// ordered by slot.
+    pub fn issued_commitments(
+        &self,
+        slot: u64,
+    ) -> Result<Vec<(IssuedRecord, CommitmentStatus)>, StoreError> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(
+            "SELECT payload, status FROM issued_commitments WHERE slot >= ?1 ORDER BY slot",
+        )?;
+        let rows = stmt.query_map(params![slot], read_issued_commitment)?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Removes the issued commitments targeting the slots before `slot`.
+    pub fn remove_issued_commitments_before(&self, slot: u64) -> Result<usize, StoreError> {
+        let conn = self.conn.lock();
+        Ok(conn.execute("DELETE FROM issued_commitments WHERE slot < ?1", params![slot])?)
+    }
+
+    /// Makes the store reject all writes, or accept them again, as when its disk is full.
+    #[cfg(test)]
+    pub(crate) fn set_read_only(&self, read_only: bool) {
+        self.conn.lock().pragma_update(None, "query_only", read_only).expect("valid pragma");
+    }
+
+    /// Acquires or renews the lease on a validator for `holder` until `now + ttl` (in
+    /// milliseconds). Returns false if the lease is held by another instance and hasn't
+    /// expired yet.
+    pub fn acquire_lease(
+        &self,
+        pubkey: &str,
+        holder: &str,
+        now: u64,
+        ttl: u64,
+    ) -> Result<bool, StoreError> {
+        let changed = self.conn.lock().execute(
+            "INSERT INTO validator_leases (pubkey, holder, expires_at) VALUES (?1, ?2, ?4)
+             ON CONFLICT (pubkey) DO UPDATE
+             SET holder = excluded.holder, expires_at = excluded.expires_at
+             WHERE validator_leases.holder = excluded.holder OR validator_leases.expires_at <= ?3",
+            params![pubkey, holder, now, now.saturating_add(ttl)],
+        )?;
+
+        Ok(changed == 1)
+    }
+
+    /// Releases the lease on a validator, if it is held by `holder`.
+    pub fn release_lease(&self, pubkey: &str, holder: &str) -> Result<(), StoreError> {
+        self.conn.lock().execute(
+            "DELETE FROM validator_leases WHERE pubkey = ?1 AND holder = ?2",
+            params![pubkey, holder],
+        )?;
+
+        Ok(())
+    }
+
+    /// Returns the current lease on a validator, if any. The lease may have expired.
+    pub fn get_lease(&self, pubkey: &str) -> Result<Option<Lease>, StoreError> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare("SELECT * FROM validator_leases WHERE pubkey = ?1")?;
+        let lease = stmt.query_row(params![pubkey], |row| {
+            Ok(Lease {
+                pubkey: row.get("pubkey")?,
+                holder: row.get("holder")?,
+                expires_at: row.get("expires_at")?,
+            })
+        });
+
+        Ok(lease.optional()?)
+    }
+}
+
+/// Reads a commitment fee record from the payload column of a row.
+fn read_fee(row: &Row<'_>) -> rusqlite::Result<CommitmentFee> {
+    read_record(row)
+}
+
+/// Reads a commitment fee record and its finality, if reconciled, from a row.
+fn read_annotated_fee(row: &Row<'_>) -> rusqlite::Result<AnnotatedFee> {
+    let fee = read_fee(row)?;
+    let level: Option<u8> = row.get("finality")?;
+    let block_hash: Option<Vec<u8>> = row.get("block_hash")?;
+
+    let Some(level) = level else {
+        return Ok(AnnotatedFee { fee, finality: None });
+    };
+    let level = Finality::from_u8(level).ok_or_else(|| {
+        rusqlite::Error::FromSqlConversionFailure(0, Type::Integer, "invalid finality".into())
+    })?;
+    let block_hash = block_hash
+        .as_deref()
+        .map(B256::try_from)
+        .transpose()
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, Type::Blob, Box::new(e)))?;
+
+    Ok(AnnotatedFee { fee, finality: Some(FeeFinality { level, block_hash }) })
+}
+
+/// Reads an issued commitment record and its status from a row.
+fn read_issued_commitment(row: &Row<'_>) -> rusqlite::Result<(IssuedRecord, CommitmentStatus)> {
+    let status: String = row.get("status")?;
+    let status = serde_json::from_str(&status)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(e)))?;
+    Ok((read_record(row)?, status))
+}
+
+/// Reads a record from the payload column of a row.
+fn read_record<T: DeserializeOwned>(row: &Row<'_>) -> rusqlite::Result<T> {
+    let payload: Vec<u8> = row.get("payload")?;
+    codec::decode(&payload)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, Type::Blob, Box::new(e)))
+}
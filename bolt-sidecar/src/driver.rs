@@ -1,108 +1,436 @@
 use core::fmt;
-use std::time::{Duration, Instant};
-
-use alloy::{
-    rpc::types::beacon::events::HeadEvent,
-    signers::{local::PrivateKeySigner, Signer as SignerECDSA},
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
+
+use alloy::{primitives::B256, rpc::types::beacon::events::HeadEvent};
 use beacon_api_client::mainnet::Client as BeaconClient;
-use ethereum_consensus::{
-    clock::{self, SlotStream, SystemTimeProvider},
-    phase0::mainnet::SLOTS_PER_EPOCH,
+use ethereum_consensus::phase0::mainnet::SLOTS_PER_EPOCH;
+use eyre::{eyre, Report};
+use tokio::{
+    net::TcpListener,
+    signal::unix::{signal, SignalKind},
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
 };
-use futures::StreamExt;
-use tokio::sync::mpsc;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, error, info, instrument, warn};
 
+#[cfg(feature = "tls")]
+use crate::commitments::tls::SniResolver;
 use crate::{
+    alerts::{AlertCondition, Alerts},
+    builder::BuilderError,
     commitments::{
-        server::{CommitmentsApiServer, Event as CommitmentEvent},
-        spec::Error as CommitmentError,
+        audit::AuditLog,
+        capture::RequestCapture,
+        erc1271::Erc1271Verifier,
+        link,
+        server::{
+            CancelEvent, CommitmentsApiServer, DropAction, DropEvent, Event as CommitmentEvent,
+            ImportEvent, MaintenanceEvent,
+        },
+        signing::ResponseSigner,
+        spec::{CancelError, CancelOutcome, Error as CommitmentError, RejectionError},
     },
-    crypto::{bls::Signer as BlsSigner, SignableBLS, SignerBLS},
+    crypto::{
+        bls::Signer as BlsSigner,
+        external::{ExternalSigner, ImportError},
+        load_keystores, CommitmentKey, LocalSigner, SignerBackend, SignerError,
+    },
+    db::{ResilientStore, Store, StoreError},
+    drops::{CommitmentDrops, AUDIT_TARGET},
+    earnings::{CommitmentFee, EarningsTracker},
+    finality::{CanonicalChain, ClientChain},
+    inclusion::{CommitmentStatus, InclusionTracker, IssuedRecord},
     primitives::{
-        CommitmentRequest, ConstraintsMessage, FetchPayloadRequest, LocalPayloadFetcher,
-        SignedConstraints,
+        commitment::SignedCommitment, constraint::ConstraintDigest, delegation::load_delegations,
+        BatchedSignedConstraints, ChainHead, CommitmentRequest, ConstraintsMessage,
+        ExclusionRequest, FetchPayloadRequest, IdentityDocument, InclusionRequest,
+        LocalPayloadFetcher, ServedIdentity, SignedConstraints, SignedIdentity, TransactionExt,
     },
+    probe::{PipelineChecks, Probe},
+    replay::{Decision, Recorded, ReplayEvent, ReplayRecorder},
     start_builder_proxy_server,
-    state::{fetcher::StateFetcher, ConsensusState, ExecutionState, HeadTracker, StateClient},
-    BuilderProxyConfig, Config, ConstraintsApi, LocalBuilder, MevBoostClient,
+    state::{
+        clock::{SlotClock, SystemTimeSource, TimeSource},
+        consensus::{ConsensusError, ProcessingStage, ProposerDutiesFetcher},
+        fetcher::StateFetcher,
+        freshness::ChainLayer,
+        health::Dependency,
+        latency::PipelineStage,
+        maintenance::{MaintenanceDeclaration, CONFIGURED_MAINTENANCE},
+        quotas::QuotaUsage,
+        scheduler::{SlotOffset, SlotScheduler, SlotTick},
+        ClockDriftMonitor, ConsensusState, ExecutionState, HeadFreshness, HeadTracker,
+        HealthPublisher, LatencyTracker, MaintenanceWindows, ManifestPublisher, QuotaTracker,
+        Readiness, RelayTracker, SnapshotPublisher, StateClient, StateSnapshot, ValidatorLeases,
+    },
+    submissions::{ConstraintSubmissions, SubmissionError},
+    telemetry, BuilderApi, BuilderProxyConfig, ChainConfig, CommitBoostSigner, Config,
+    LocalBuilder, MevBoostClient, ReloadTargets, RpcClient, Web3SignerBackend,
 };
 
+/// Interval at which the age of the chain heads is checked.
+const HEAD_FRESHNESS_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Interval at which the identity document is checked for renewal.
+const IDENTITY_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Interval at which the store is probed for recovery, while it doesn't accept writes.
+const STORE_RECOVERY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Interval at which the pending drops of commitments are checked for confirmation.
+const DROP_CONFIRMATION_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Interval at which the relay is checked for the dependency health, between submissions.
+const RELAY_CHECK_INTERVAL: Duration = Duration::from_secs(12);
+
+/// Maximum time to write the audit log and deliver the alerts on shutdown, above the time to
+/// deliver an alert to its sinks.
+const SHUTDOWN_FLUSH_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// The tasks of the driver that run at fixed offsets within each slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriverTask {
+    /// Advance the consensus state to the new slot.
+    SlotStart,
+    /// Submit the constraints of the slot ahead of its commitment deadline, the later ones
+    /// being submitted as incremental updates.
+    ConstraintSubmission,
+    /// Stop accepting commitments for the slot, build the fallback
+    /// payload and submit the constraints.
+    CommitmentDeadline,
+}
+
+impl fmt::Display for DriverTask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SlotStart => write!(f, "slot_start"),
+            Self::ConstraintSubmission => write!(f, "constraint_submission"),
+            Self::CommitmentDeadline => write!(f, "commitment_deadline"),
+        }
+    }
+}
+
+/// A validated commitment request that is awaiting its signature from the external signer.
+#[derive(Debug)]
+struct AwaitingSignature {
+    validator_index: u64,
+    fee: CommitmentFee,
+    response: oneshot::Sender<Result<SignedCommitment, CommitmentError>>,
+}
+
+/// A request for a range of slots, none of which could be committed to yet.
+#[derive(Debug)]
+struct DeferredRequest {
+    /// The digest of the request.
+    digest: B256,
+    event: CommitmentEvent,
+}
+
 /// The driver for the sidecar, responsible for managing the main event loop.
-pub struct SidecarDriver<C, BLS, ECDSA> {
+pub struct SidecarDriver<C> {
     head_tracker: HeadTracker,
-    execution: ExecutionState<C>,
-    consensus: ConsensusState,
-    constraint_signer: BLS,
-    commitment_signer: ECDSA,
+    execution: ExecutionState<Recorded<C>>,
+    consensus: ConsensusState<Recorded<BeaconClient>>,
+    /// The backend signing the commitments and the constraints
+    signer: Arc<dyn SignerBackend>,
+    /// The digest of the constraints messages signed with the constraint key
+    constraint_digest: ConstraintDigest,
     local_builder: LocalBuilder,
     mevboost_client: MevBoostClient,
     api_events_rx: mpsc::Receiver<CommitmentEvent>,
     payload_requests_rx: mpsc::Receiver<FetchPayloadRequest>,
-    /// Stream of slots made from the consensus clock
-    slot_stream: SlotStream<SystemTimeProvider>,
+    /// Imports of externally signed commitments from the admin API
+    import_events_rx: mpsc::Receiver<ImportEvent>,
+    /// Drops and restorations of commitments from the admin API
+    drop_events_rx: mpsc::Receiver<DropEvent>,
+    /// Cancellations of commitments by the signers of their requests
+    cancel_events_rx: mpsc::Receiver<CancelEvent>,
+    /// The requests for a range of slots retried at the start of the next slot
+    deferred: Vec<DeferredRequest>,
+    /// Declarations and cancellations of maintenance windows from the admin API
+    maintenance_events_rx: mpsc::Receiver<MaintenanceEvent>,
+    /// The commitments dropped by the operators, until their slot has passed
+    drops: CommitmentDrops,
+    /// Interval at which the pending drops are confirmed once their delay elapsed
+    drop_confirmation: tokio::time::Interval,
+    /// The external commitment signer backend, if commitments are signed externally
+    external_signer: Option<ExternalSigner<AwaitingSignature>>,
+    /// Ticks of the slot-relative tasks of the driver
+    schedule_rx: mpsc::Receiver<SlotTick<DriverTask>>,
+    /// The latest chain head, shared with the commitments API
+    chain_head: ChainHead,
+    /// Publisher of the snapshots of the driver state, read by the commitments API
+    snapshots: SnapshotPublisher,
+    /// Publisher of the manifests of the commitments of each slot, read by the commitments API
+    manifests: ManifestPublisher,
+    /// The validator leases shared with other sidecar instances, if coordination is enabled
+    leases: Option<ValidatorLeases>,
+    /// Interval at which the validator leases are renewed
+    lease_renewal: tokio::time::Interval,
+    /// The store, shared with the earnings and the submissions
+    store: ResilientStore,
+    /// Interval at which the store is probed for recovery while it is degraded
+    store_recovery: tokio::time::Interval,
+    /// Fee accounting of the issued commitments
+    earnings: EarningsTracker,
+    /// Inclusion statuses of the issued commitments, read by the commitments API
+    inclusions: InclusionTracker,
+    /// The committed space held by each signer, against its quotas
+    quotas: QuotaTracker,
+    /// The signed constraints persisted until they are submitted to the relays
+    submissions: ConstraintSubmissions,
+    /// The slot whose constraints were submitted ahead of its commitment deadline, the later
+    /// constraints of which are submitted as incremental updates
+    early_submission: Option<u64>,
+    /// The latest submission of the constraints of a slot, which its updates wait for
+    submission_task: Option<(u64, JoinHandle<()>)>,
+    /// The recorder of the driver inputs and decisions, if recording a replay log
+    recorder: Option<ReplayRecorder>,
+    /// The file the bound addresses of the listeners were written to, removed on shutdown
+    ports_file: Option<PathBuf>,
+    /// The slot clock, used to compute the age of the chain heads
+    clock: SlotClock,
+    /// The timestamps of the latest chain heads, to detect stale nodes
+    freshness: HeadFreshness,
+    /// Interval at which the age of the chain heads is checked
+    freshness_check: tokio::time::Interval,
+    /// The estimated drift of the host clock from the chain
+    drift: ClockDriftMonitor,
+    /// The readiness of the sidecar, shared with the commitments API
+    readiness: Readiness,
+    /// The connectivity to the upstream services, published to the commitments API
+    health: HealthPublisher,
+    /// Interval at which the relay is checked for the dependency health
+    relay_check: tokio::time::Interval,
+    /// The rolling latencies of the commitment pipeline stages
+    latency: LatencyTracker,
+    /// The identity document signed with the BLS key, shared with the commitments API
+    identity: ServedIdentity,
+    /// The validity period of the identity documents
+    identity_validity: Duration,
+    /// Interval at which the identity document is checked for renewal
+    identity_check: tokio::time::Interval,
+    /// The chain id the identity documents are signed for
+    chain_id: u64,
+    /// The handle to raise alerts on critical conditions
+    alerts: Alerts,
+    /// The audit log of the commitment requests, shared with the commitments API
+    audit: Option<AuditLog>,
+    /// The synthetic probe of the pipeline, if enabled
+    probe: Option<Probe>,
+    /// Interval at which the pipeline is probed
+    probe_interval: tokio::time::Interval,
+    /// The latest probe run, which must complete before the next one starts
+    probe_task: Option<JoinHandle<()>>,
+    /// The running config, against which the config reloaded on SIGHUP is diffed
+    config: Config,
+    /// The state shared with the commitments API that the reloaded options are applied to
+    reload_targets: ReloadTargets,
 }
 
-impl fmt::Debug for SidecarDriver<StateClient, BlsSigner, PrivateKeySigner> {
+impl fmt::Debug for SidecarDriver<StateClient> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("SidecarDriver")
             .field("head_tracker", &self.head_tracker)
             .field("execution", &self.execution)
             .field("consensus", &self.consensus)
-            .field("constraint_signer", &self.constraint_signer)
-            .field("commitment_signer", &self.commitment_signer)
+            .field("signer", &self.signer)
             .field("local_builder", &self.local_builder)
             .field("mevboost_client", &self.mevboost_client)
             .field("api_events_rx", &self.api_events_rx)
             .field("payload_requests_rx", &self.payload_requests_rx)
+            .field("import_events_rx", &self.import_events_rx)
+            .field("drop_events_rx", &self.drop_events_rx)
+            .field("cancel_events_rx", &self.cancel_events_rx)
+            .field("deferred", &self.deferred)
+            .field("maintenance_events_rx", &self.maintenance_events_rx)
+            .field("drops", &self.drops)
+            .field("external_signer", &self.external_signer)
+            .field("chain_head", &self.chain_head)
+            .field("snapshots", &self.snapshots)
+            .field("manifests", &self.manifests)
+            .field("leases", &self.leases)
+            .field("store", &self.store)
+            .field("earnings", &self.earnings)
+            .field("inclusions", &self.inclusions)
+            .field("submissions", &self.submissions)
+            .field("early_submission", &self.early_submission)
+            .field("recorder", &self.recorder)
+            .field("ports_file", &self.ports_file)
+            .field("clock", &self.clock)
+            .field("freshness", &self.freshness)
+            .field("readiness", &self.readiness)
+            .field("health", &self.health)
+            .field("latency", &self.latency)
+            .field("identity", &self.identity)
+            .field("alerts", &self.alerts)
+            .field("probe", &self.probe)
+            .field("reload_targets", &self.reload_targets)
             .finish()
     }
 }
 
-impl SidecarDriver<StateClient, BlsSigner, PrivateKeySigner> {
+/// Connects to the signer backend of the config, or loads the keys of the local signer.
+pub async fn signer_backend(cfg: &Config) -> eyre::Result<Arc<dyn SignerBackend>> {
+    let remote = (cfg.commit_boost.clone(), cfg.web3signer.clone());
+    let signer: Arc<dyn SignerBackend> = match remote {
+        (Some(commit_boost), _) => Arc::new(CommitBoostSigner::connect(commit_boost).await?),
+        (_, Some(web3signer)) => Arc::new(Web3SignerBackend::connect(web3signer).await?),
+        (None, None) => {
+            // Constraints are signed with a BLS private key, either the one of the CLI
+            // argument or the first of the keystores, which hold all the validator keys.
+            let keys = match (cfg.keystore.as_ref(), cfg.private_key.clone()) {
+                (Some(keystore), _) => {
+                    let keys = load_keystores(&keystore.dir, &keystore.password_file)?;
+                    let dir = keystore.dir.display();
+                    info!(count = keys.len(), %dir, "Loaded the validator keys");
+                    keys
+                }
+                (None, Some(key)) => vec![key],
+                (None, None) => return Err(eyre!("The local signer requires a private key")),
+            };
+            let constraint_signer = BlsSigner::new(keys[0].clone());
+
+            // Commitment responses are signed with a regular Ethereum wallet private key.
+            // This is now generated randomly because slashing is not yet implemented.
+            let commitment_signer = cfg.rng.ecdsa_signer();
+
+            let signer = LocalSigner::new(constraint_signer, commitment_signer);
+            Arc::new(signer.with_validator_keys(keys))
+        }
+    };
+
+    Ok(signer)
+}
+
+impl SidecarDriver<StateClient> {
     /// Create a new sidecar driver with the given [Config] and default components.
     pub async fn new(cfg: Config) -> eyre::Result<Self> {
         // The default state client simply uses the execution API URL to fetch state updates.
         let state_client = StateClient::new(cfg.execution_api_url.clone());
 
-        // Constraints are signed with a BLS private key, for now this is provided
-        // via CLI argument but this is expected to change soon.
-        let constraint_signer = BlsSigner::new(cfg.private_key.clone().unwrap());
-
-        // Commitment responses are signed with a regular Ethereum wallet private key.
-        // This is now generated randomly because slashing is not yet implemented.
-        let commitment_signer = PrivateKeySigner::random();
-
-        Self::from_components(cfg, constraint_signer, commitment_signer, state_client).await
+        let signer = signer_backend(&cfg).await?;
+        Self::from_components(cfg, signer, state_client).await
     }
 }
 
-impl<C: StateFetcher, BLS: SignerBLS, ECDSA: SignerECDSA> SidecarDriver<C, BLS, ECDSA> {
+impl<C> SidecarDriver<C>
+where
+    C: StateFetcher + Sync,
+{
     /// Create a new sidecar driver with the given components
     pub async fn from_components(
         cfg: Config,
-        constraint_signer: BLS,
-        commitment_signer: ECDSA,
+        signer: Arc<dyn SignerBackend>,
         fetcher: C,
     ) -> eyre::Result<Self> {
-        let mevboost_client = MevBoostClient::new(cfg.mevboost_url.clone());
+        let relay_client = |url: reqwest::Url| {
+            let relays = &cfg.relays;
+            MevBoostClient::new(url)
+                .with_retry_policy(relays.retry)
+                .with_circuit_breaker(relays.breaker_threshold, relays.breaker_cooldown)
+        };
+        let mevboost_client = relay_client(cfg.mevboost_url.clone());
         let beacon_client = BeaconClient::new(cfg.beacon_api_url.clone());
-        let execution = ExecutionState::new(fetcher, cfg.limits).await?;
+        #[cfg(feature = "metrics")]
+        let metrics_addr = match cfg.metrics_port {
+            Some(port) => {
+                Some(telemetry::install_prometheus_exporter(([0, 0, 0, 0], port).into()).await?)
+            }
+            None => None,
+        };
+        // The configuration rejects the metrics port without the exporter
+        #[cfg(not(feature = "metrics"))]
+        let metrics_addr = None;
+
+        let recorder = cfg.replay_log.as_deref().map(ReplayRecorder::create).transpose()?;
+        if let Some(ref recorder) = recorder {
+            info!(path = ?cfg.replay_log, "Recording the driver inputs to a replay log");
+            recorder.record(ReplayEvent::start(&cfg));
+        }
+
+        let fetcher = Recorded::new(fetcher, recorder.clone());
+        let execution = ExecutionState::new(fetcher, cfg.limits)
+            .await?
+            .with_basefee_margin(cfg.basefee_margin)
+            .with_chain(cfg.chain.clone());
 
         let genesis_time = beacon_client.get_genesis_details().await?.genesis_time;
-        let slot_stream =
-            clock::from_system_time(genesis_time, cfg.chain.slot_time(), SLOTS_PER_EPOCH)
-                .into_stream();
+        let clock = SlotClock::new(genesis_time, cfg.chain.slot_time());
+        let current_slot = clock.current_slot();
+
+        let deadline_offset = cfg.chain.commitment_deadline_offset();
+        let mut scheduler = SlotScheduler::new(clock.clone());
+        register_tasks(&mut scheduler, &cfg.chain);
+
+        // The path is only accepted with the persistence feature
+        let store = match cfg.db_path {
+            #[cfg(feature = "persistence")]
+            Some(ref path) => Store::open(path)?,
+            _ => Store::in_memory()?,
+        };
+        let alerts = Alerts::spawn(&cfg.alerts, cfg.instance_id.clone());
+        let resilient = ResilientStore::new(store.clone()).with_alerts(alerts.clone());
+        let chain = ClientChain::new(
+            cfg.beacon_api_url.clone(),
+            RpcClient::new(cfg.execution_api_url.clone()),
+            genesis_time,
+            cfg.chain.slot_time(),
+        );
+        let chain: Arc<dyn CanonicalChain> = Arc::new(chain);
+        let (inclusions, inclusions_rx) =
+            InclusionTracker::new(chain.clone(), cfg.commitment_status_retention_slots);
+        let inclusions = inclusions.with_store(resilient.clone());
+        let earnings =
+            EarningsTracker::new(resilient.clone(), chain, genesis_time, cfg.chain.slot_time())
+                .with_alerts(alerts.clone(), cfg.alerts.max_miss_rate)
+                .with_finality_trigger(cfg.reconciliation_finality);
+        let targets = std::iter::once(mevboost_client.clone())
+            .chain(cfg.relays.urls.iter().cloned().map(relay_client))
+            .collect::<Vec<_>>();
+        let submissions = ConstraintSubmissions::new(resilient.clone(), targets)
+            .with_alerts(alerts.clone())
+            .with_rng(cfg.rng.fork())
+            .with_quorum(cfg.relays.quorum)
+            .with_stagger(cfg.relays.stagger)
+            .with_relay_tracker(RelayTracker::new(cfg.relays.ewma_alpha))
+            .with_clock(clock.clone());
+        let submissions = match cfg.delegations_path {
+            Some(ref path) => {
+                let delegations = load_delegations(path)?;
+                info!(count = delegations.len(), ?path, "Loaded the signed delegations");
+                submissions.with_delegations(delegations)
+            }
+            None => submissions,
+        };
+
+        let leases = cfg
+            .validator_lease_ttl
+            .map(|ttl| ValidatorLeases::new(store.clone(), cfg.instance_id.clone(), ttl));
+        let lease_renewal = tokio::time::interval(
+            leases.as_ref().map_or(Duration::from_secs(60), ValidatorLeases::renewal_interval),
+        );
 
         let local_builder = LocalBuilder::new(&cfg, beacon_client.clone(), genesis_time);
         let head_tracker = HeadTracker::start(beacon_client.clone());
 
+        let mut maintenance = MaintenanceWindows::new(genesis_time, cfg.chain.slot_time());
+        for window in &cfg.maintenance_windows {
+            let declared = maintenance.declare(*window, CONFIGURED_MAINTENANCE);
+            info!(id = declared.id, window = %window, "Declared configured maintenance window");
+        }
+
         let consensus = ConsensusState::new(
-            beacon_client,
+            Recorded::new(beacon_client, recorder.clone()),
             cfg.validator_indexes.clone(),
             cfg.chain.commitment_deadline(),
-        );
+        )
+        .with_registry_hint_url(cfg.registry_hint_url.clone())
+        .with_maintenance(maintenance);
 
         let (payload_requests_tx, payload_requests_rx) = mpsc::channel(16);
         let builder_proxy_cfg = BuilderProxyConfig {
@@ -121,27 +449,235 @@ impl<C: StateFetcher, BLS: SignerBLS, ECDSA: SignerECDSA> SidecarDriver<C, BLS,
         // start the commitments api server
         let api_addr = format!("0.0.0.0:{}", cfg.rpc_port);
         let (api_events_tx, api_events_rx) = mpsc::channel(1024);
-        CommitmentsApiServer::new(api_addr).run(api_events_tx).await;
+        let (import_events_tx, import_events_rx) = mpsc::channel(16);
+        let (drop_events_tx, drop_events_rx) = mpsc::channel(16);
+        let (cancel_events_tx, cancel_events_rx) = mpsc::channel(16);
+        let (maintenance_events_tx, maintenance_events_rx) = mpsc::channel(16);
+        let chain_head = ChainHead::default();
+        let capture = RequestCapture::new(cfg.capture_dir.clone(), cfg.capture_max_bytes);
+        let audit =
+            cfg.audit_log.clone().map(|path| AuditLog::spawn(path, cfg.audit_log_max_bytes));
+        let readiness = Readiness::new(false);
+        let (health, health_rx) = HealthPublisher::new();
+        let identity = ServedIdentity::default();
+        let (snapshots, snapshots_rx) = SnapshotPublisher::new();
+        let (manifests, manifests_rx) = ManifestPublisher::new();
+        let mut api_server = CommitmentsApiServer::new(api_addr)?
+            .with_chain(cfg.chain.clone(), genesis_time, chain_head.clone())
+            .with_limits(execution.limits())
+            .with_readiness(readiness.clone())
+            .with_health(health_rx)
+            .with_schedule(scheduler.schedule())
+            .with_snapshots(snapshots_rx)
+            .with_manifests(manifests_rx)
+            .with_inclusions(inclusions_rx)
+            .with_capture(capture)
+            .with_audit_log(audit.clone())
+            .with_whitelist(cfg.whitelist.clone())
+            .with_trusted_gateways(cfg.trusted_gateways.clone())
+            .with_jwt_secret(cfg.api_jwt_secret)
+            .with_rate_limits(cfg.rate_limits)
+            .with_replay_window(cfg.replay_window)
+            .with_legacy_digests(cfg.accept_legacy_digests)
+            .with_profiles(cfg.method_profiles.clone())
+            .with_cancellations(cancel_events_tx)
+            .with_identity(identity.clone())
+            .with_response_timeout(cfg.response_timeout)
+            .with_max_inflight_requests(cfg.max_inflight_requests)
+            .with_alerts(alerts.clone(), cfg.alerts.max_pending_responses);
+        if let Some(internal_port) = cfg.internal_port {
+            api_server = api_server.with_internal_addr(format!("0.0.0.0:{internal_port}"))?;
+        }
+        // The admin port is only accepted with the admin feature, without which nothing
+        // imports, drops or declares maintenance windows from the API
+        #[cfg(feature = "admin")]
+        {
+            api_server = api_server
+                .with_store(store.clone())
+                .with_imports(import_events_tx)
+                .with_drops(drop_events_tx)
+                .with_maintenance(maintenance_events_tx);
+            if let Some(admin_port) = cfg.admin_port {
+                api_server = api_server
+                    .with_admin_addr(format!("127.0.0.1:{admin_port}"))?
+                    .with_admin_token(cfg.admin_token.clone());
+            }
+        }
+        #[cfg(not(feature = "admin"))]
+        drop((import_events_tx, drop_events_tx, maintenance_events_tx));
+        if let Some(metrics_addr) = metrics_addr {
+            api_server = api_server.with_metrics_addr(metrics_addr);
+        }
+        if cfg.erc1271_auth {
+            info!("Accepting the EIP-1271 signatures of smart-contract wallets");
+            let verifier = Erc1271Verifier::new(RpcClient::new(cfg.execution_api_url.clone()));
+            api_server = api_server.with_erc1271(verifier);
+        }
+        if cfg.sign_responses {
+            info!("Signing the commitments API responses with the commitment key");
+            let signer = ResponseSigner::new(CommitmentKey(signer.clone()));
+            api_server = api_server.with_response_signer(signer);
+        }
+        // The certificates are only accepted with the tls feature
+        #[cfg(feature = "tls")]
+        if let Some(ref tls) = cfg.tls {
+            let resolver = SniResolver::new(tls.clone())?;
+            info!(hostnames = tls.hosts.len(), "Serving the commitments API over TLS");
+            api_server = api_server.with_tls(Arc::new(resolver));
+        }
+        if let Some(ref socket) = cfg.unix_socket {
+            info!(path = %socket.path.display(), "Serving the commitments API on a Unix socket");
+            api_server = api_server.with_unix_socket(socket.clone());
+        }
+        // Commitments API processes on other hosts forward their requests over the link
+        if let Some(ref link_cfg) = cfg.link {
+            let listener = TcpListener::bind(("0.0.0.0", link_cfg.port)).await?;
+            let addr = listener.local_addr()?;
+            info!(%addr, "Accepting the requests of commitments API processes over the link");
+            let secret = link_cfg.secret.clone();
+            tokio::spawn(link::serve(listener, secret, api_events_tx.clone()));
+        }
+        api_server.run(api_events_tx).await?;
+        let reload_targets = ReloadTargets {
+            limits: execution.limits(),
+            whitelist: api_server.whitelist(),
+            rate_limiter: api_server.rate_limiter(),
+        };
+
+        // Announce the bound addresses for orchestration, which may start us with port 0
+        let listeners = api_server.listener_addrs();
+        for line in listeners.env_lines() {
+            println!("{line}");
+        }
+        if let Some(ref path) = cfg.ports_file {
+            listeners.write_ports_file(path)?;
+            info!(path = %path.display(), "Wrote the bound addresses to the ports file");
+        }
+
+        let probe = cfg.probe_interval.map(|interval| {
+            info!(?interval, "Probing the pipeline periodically");
+            let checks = PipelineChecks {
+                signer: signer.clone(),
+                // Externally signed commitments can't be probed without reaching the signer
+                signs_commitments: cfg.external_signer.is_none(),
+                store,
+                relay: mevboost_client.clone(),
+            };
+            Probe::new(checks, cfg.chain.chain_id(), readiness.clone())
+                .with_rng(&cfg.rng)
+                .with_alerts(alerts.clone())
+        });
+
+        // The first probe runs at a random offset within the interval, so that instances
+        // started together don't probe the shared relay in lockstep
+        let probe_period = cfg.probe_interval.unwrap_or(Duration::from_secs(60));
+        let probe_start = tokio::time::Instant::now() + cfg.rng.jitter(probe_period);
+        let config = cfg.clone();
 
-        Ok(SidecarDriver {
+        let mut driver = SidecarDriver {
             head_tracker,
             execution,
             consensus,
-            constraint_signer,
-            commitment_signer,
+            signer,
+            constraint_digest: cfg.constraint_digest,
             local_builder,
             mevboost_client,
             api_events_rx,
             payload_requests_rx,
-            slot_stream,
-        })
+            import_events_rx,
+            drop_events_rx,
+            cancel_events_rx,
+            deferred: Vec::new(),
+            maintenance_events_rx,
+            drops: CommitmentDrops::new(cfg.drop_confirm_delay),
+            drop_confirmation: tokio::time::interval(DROP_CONFIRMATION_INTERVAL),
+            external_signer: cfg.external_signer.map(ExternalSigner::new),
+            schedule_rx: scheduler.spawn(),
+            chain_head,
+            snapshots,
+            manifests,
+            leases,
+            lease_renewal,
+            store: resilient,
+            store_recovery: tokio::time::interval(STORE_RECOVERY_INTERVAL),
+            earnings,
+            inclusions,
+            quotas: QuotaTracker::new(cfg.signer_quotas, cfg.chain.slots_per_epoch()),
+            submissions,
+            early_submission: None,
+            submission_task: None,
+            recorder,
+            ports_file: cfg.ports_file,
+            freshness: HeadFreshness::new(clock.slot_time() * cfg.max_head_age_slots as u32),
+            // The head of a slot is seen before its attestation deadline, a third into it
+            drift: ClockDriftMonitor::new(cfg.clock_drift, clock.slot_time() / 3),
+            clock,
+            freshness_check: tokio::time::interval(HEAD_FRESHNESS_CHECK_INTERVAL),
+            readiness: readiness.clone(),
+            health,
+            relay_check: tokio::time::interval(RELAY_CHECK_INTERVAL),
+            latency: LatencyTracker::new(deadline_offset),
+            identity,
+            identity_validity: cfg.identity_validity,
+            identity_check: tokio::time::interval(IDENTITY_CHECK_INTERVAL),
+            chain_id: cfg.chain.chain_id(),
+            alerts,
+            audit,
+            probe,
+            probe_interval: tokio::time::interval_at(probe_start, probe_period),
+            probe_task: None,
+            config,
+            reload_targets,
+        };
+
+        // Prefetch the proposer duties and the execution state of the current head before
+        // accepting requests, so that the first ones don't pay for the cold caches.
+        if let Some(slot) = current_slot {
+            driver.record(|at_ms| ReplayEvent::Warmup { at_ms, slot });
+            let (consensus, execution) = (&mut driver.consensus, &mut driver.execution);
+            let (health, now) = (&driver.health, driver.clock.now().as_secs());
+            let prefetch = async {
+                tokio::try_join!(
+                    async {
+                        let result = consensus.warmup(slot).await;
+                        health.record(Dependency::Consensus, result.is_ok(), now);
+                        result.map_err(Report::from)
+                    },
+                    async {
+                        let result = execution.update_head(None, slot).await;
+                        health.record(Dependency::Execution, result.is_ok(), now);
+                        result.map_err(Report::from)
+                    },
+                )
+                .map(|_| ())
+            };
+
+            if readiness.warm_up(cfg.warmup_timeout, prefetch).await {
+                driver.chain_head.update_slot(slot);
+            }
+            driver.observe_execution_head();
+        } else {
+            warn!("Chain has not started yet, skipping warmup");
+            readiness.set_ready();
+        }
+        driver.restore_commitments(current_slot.unwrap_or_default());
+        driver.resume_submissions(current_slot.unwrap_or_default());
+        driver.renew_leases();
+        driver.check_identity().await;
+        driver.publish_snapshot();
+
+        Ok(driver)
     }
 
-    /// Run the main event loop endlessly for the sidecar driver.
+    /// Run the main event loop of the sidecar driver, until it's interrupted and shut down.
     ///
     /// Any errors encountered are contained to the specific `handler` in which
     /// they occurred, and the driver will continue to run as long as possible.
-    pub async fn run_forever(mut self) -> ! {
+    pub async fn run_forever(mut self) {
+        let shutdown = tokio::signal::ctrl_c();
+        tokio::pin!(shutdown);
+        let mut hangup = signal(SignalKind::hangup()).expect("Failed to listen for SIGHUP");
+
         loop {
             tokio::select! {
                 Some(api_event) = self.api_events_rx.recv() => {
@@ -150,44 +686,404 @@ impl<C: StateFetcher, BLS: SignerBLS, ECDSA: SignerECDSA> SidecarDriver<C, BLS,
                 Ok(head_event) = self.head_tracker.next_head() => {
                     self.handle_new_head_event(head_event).await;
                 }
-                Some(slot) = self.consensus.commitment_deadline.wait() => {
-                    self.handle_commitment_deadline(slot).await;
-                }
                 Some(payload_request) = self.payload_requests_rx.recv() => {
                     self.handle_fetch_payload_request(payload_request);
                 }
-                Some(slot) = self.slot_stream.next() => {
-                    if let Err(e) = self.consensus.update_slot(slot).await {
-                        error!(err = ?e, "Failed to update consensus state slot");
-                    }
+                Some(import_event) = self.import_events_rx.recv() => {
+                    self.handle_import_event(import_event).await;
+                }
+                Some(drop_event) = self.drop_events_rx.recv() => {
+                    self.handle_drop_event(drop_event);
+                }
+                Some(cancel_event) = self.cancel_events_rx.recv() => {
+                    self.handle_cancel_event(cancel_event);
+                }
+                Some(maintenance_event) = self.maintenance_events_rx.recv() => {
+                    self.handle_maintenance_event(maintenance_event);
+                }
+                Some(tick) = self.schedule_rx.recv() => {
+                    self.handle_scheduled_task(tick).await;
+                }
+                _ = self.lease_renewal.tick(), if self.leases.is_some() => {
+                    self.renew_leases();
+                }
+                _ = self.freshness_check.tick() => {
+                    self.check_head_freshness();
+                }
+                _ = self.identity_check.tick() => {
+                    self.check_identity().await;
+                }
+                _ = self.relay_check.tick() => {
+                    self.check_relay();
+                }
+                _ = self.probe_interval.tick(), if self.probe.is_some() => {
+                    self.run_probe();
+                }
+                _ = self.drop_confirmation.tick(), if self.drops.has_pending() => {
+                    self.confirm_due_drops();
+                }
+                _ = self.store_recovery.tick(), if self.store.is_degraded() => {
+                    self.recover_store();
+                }
+                Some(()) = hangup.recv() => {
+                    self.reload_config();
+                }
+                _ = &mut shutdown => {
+                    self.drain_commitment_requests().await;
+                    self.shutdown().await;
+                    return;
                 }
             }
+
+            // Mutations only happen in the handlers above, so the API reads a fresh snapshot
+            self.publish_snapshot();
+            self.manifests.update(self.execution.block_templates());
         }
     }
 
+    /// Restore the pending commitments issued before a restart: their constraints are added
+    /// back to the block templates, and their usage to the quotas of their signers. Their
+    /// submissions are resumed with the others.
+    fn restore_commitments(&mut self, current_slot: u64) {
+        let restored = match self.inclusions.restore(current_slot) {
+            Ok(restored) => restored,
+            Err(err) => {
+                error!(?err, "Failed to restore the issued commitments");
+                return;
+            }
+        };
+
+        let count = restored.len();
+        for (record, constraints) in restored {
+            if let Some(signer) = record.signer {
+                let transactions = constraints.message.constraints.iter();
+                let gas = transactions.map(|constraint| constraint.transaction.gas_limit()).sum();
+                let txs = constraints.message.constraints.len() as u64;
+                self.quotas.hold(record.digest, signer, record.slot, QuotaUsage { gas, txs });
+            }
+            self.execution.add_constraint(record.slot, constraints);
+        }
+
+        if count > 0 {
+            info!(count, "Restored the pending commitments issued before restart");
+        }
+    }
+
+    /// Resume the submissions of the constraints persisted before a restart. The constraints
+    /// of the slots whose submission time has already passed are submitted right away, and
+    /// the others at their submission time, as usual.
+    fn resume_submissions(&mut self, current_slot: u64) {
+        let slots = match self.submissions.resume(current_slot) {
+            Ok(slots) => slots,
+            Err(err) => {
+                error!(?err, "Failed to resume the constraint submissions");
+                return;
+            }
+        };
+
+        let now = self.clock.now();
+        let chain = &self.config.chain;
+        let (submission, deadline) =
+            (chain.constraint_submission_offset(), chain.commitment_deadline_offset());
+        for slot in slots {
+            let start = self.clock.slot_start(slot);
+            if start.saturating_sub(submission) > now {
+                continue;
+            }
+            if start.saturating_sub(deadline) > now {
+                self.early_submission = Some(slot);
+            }
+            self.submit_constraints(slot, Vec::new(), None);
+        }
+    }
+
+    /// Check in the background that the relay the constraints are submitted to is reachable,
+    /// and record it in the dependency health.
+    fn check_relay(&self) {
+        let (relay, health, clock) =
+            (self.mevboost_client.clone(), self.health.clone(), self.clock.clone());
+        tokio::spawn(async move {
+            let connected = match relay.status().await {
+                Ok(status) if status.is_success() => true,
+                Ok(status) => {
+                    warn!(%status, "Relay is unhealthy");
+                    false
+                }
+                Err(err) => {
+                    warn!(?err, "Relay is unreachable");
+                    false
+                }
+            };
+            health.record(Dependency::Relay, connected, clock.now().as_secs());
+        });
+    }
+
+    /// Acquire or renew the leases on the validators with known proposer duties.
+    fn renew_leases(&mut self) {
+        let Some(ref mut leases) = self.leases else { return };
+
+        let pubkeys = self.consensus.our_validator_pubkeys().iter().map(ToString::to_string);
+        if let Err(err) = leases.renew(pubkeys.collect::<Vec<_>>()) {
+            error!(?err, "Failed to renew validator leases");
+        }
+    }
+
+    /// Record the timestamp of the latest execution head, if one was fetched.
+    fn observe_execution_head(&mut self) {
+        if let Some(timestamp) = self.execution.block_timestamp() {
+            self.freshness.observe(ChainLayer::Execution, Duration::from_secs(timestamp));
+        }
+    }
+
+    /// Reload the config on SIGHUP, applying the changed options that are safe to change at
+    /// runtime and logging those that require a restart. Only the names of the options are
+    /// logged, as some of them are secrets.
+    fn reload_config(&mut self) {
+        let reload = match self.config.reload() {
+            Ok(reload) => reload,
+            Err(err) => {
+                error!(?err, "Failed to reload the config, keeping the running one");
+                return;
+            }
+        };
+
+        if !reload.ignored.is_empty() {
+            let ignored = &reload.ignored;
+            warn!(options = ?ignored, "Ignored the changed options, which require a restart");
+        }
+        info!(options = ?reload.applied, "Reloaded the config");
+        self.reload_targets.apply(&mut self.config, reload);
+    }
+
+    /// Run the synthetic probe of the pipeline in the background, unless the previous run is
+    /// still in progress.
+    fn run_probe(&mut self) {
+        let Some(ref probe) = self.probe else { return };
+        if self.probe_task.as_ref().is_some_and(|task| !task.is_finished()) {
+            warn!("Previous probe still running, skipping");
+            return;
+        }
+
+        let probe = probe.clone();
+        self.probe_task = Some(tokio::spawn(async move {
+            match probe.run().await {
+                Ok(report) => debug!(failing = ?report.failing_stages(), "Probed the pipeline"),
+                Err(err) => error!(?err, "Failed to build the probe request"),
+            }
+        }));
+    }
+
+    /// Replay the writes buffered while the store was unavailable, if it accepts writes again.
+    fn recover_store(&self) {
+        if let Err(err) = self.store.recover() {
+            debug!(?err, "Store still unavailable, keeping the writes buffered in memory");
+        }
+    }
+
+    /// Flip the readiness when the chain heads get stale, and back when they are fresh again.
+    fn check_head_freshness(&self) {
+        let now = self.clock.now();
+        let stale = self.freshness.is_stale(now);
+        if !self.readiness.set_stale_heads(stale) {
+            return;
+        }
+
+        if stale {
+            let ages = self.freshness.status(now);
+            warn!(?ages, "Chain heads are stale, rejecting requests until they resume");
+        } else {
+            info!("Chain heads are fresh again, accepting requests");
+        }
+    }
+
+    /// Warn when the host clock drifts from the chain, and correct the slot clock or reject
+    /// requests if configured to.
+    fn check_clock_drift(&mut self) {
+        let previous = self.drift.verdict();
+        let verdict = self.drift.check();
+
+        if verdict.exceeded != previous.exceeded {
+            let drift = self.drift.status().drift;
+            if verdict.exceeded {
+                warn!(?drift, "Host clock drifts from the chain, check its time synchronization");
+            } else {
+                info!(?drift, "Host clock is in sync with the chain again");
+            }
+        }
+
+        if verdict.correction_ms != previous.correction_ms {
+            debug!(correction_ms = verdict.correction_ms, "Correcting the slot clock");
+            self.clock.set_correction_ms(verdict.correction_ms);
+        }
+
+        if self.readiness.set_clock_drift(verdict.pause) {
+            if verdict.pause {
+                warn!("Host clock drifts beyond the safety bound, rejecting requests until fixed");
+            } else {
+                info!("Host clock is within the safety bound again, accepting requests");
+            }
+        }
+    }
+
+    /// Sign a new identity document once less than a quarter of the validity period of the
+    /// served one is left. If it can't be renewed, the served document is kept until it
+    /// lapses, and then no longer served.
+    async fn check_identity(&self) {
+        let now = self.clock.now().as_secs();
+        let remaining = self
+            .identity
+            .get()
+            .map_or(0, |identity| identity.message.valid_until.saturating_sub(now));
+        if remaining > self.identity_validity.as_secs() / 4 {
+            return;
+        }
+
+        let address = self
+            .external_signer
+            .as_ref()
+            .map_or_else(|| self.signer.commitment_address(), ExternalSigner::address);
+        let document = IdentityDocument::new(address, self.chain_id, now, self.identity_validity);
+
+        match SignedIdentity::sign_with_backend(document, self.signer.as_ref()).await {
+            Ok(identity) => {
+                info!(valid_until = identity.message.valid_until, "Signed new identity document");
+                self.identity.set(Some(identity));
+            }
+            Err(err) if remaining > 0 => {
+                warn!(?err, expires_in_secs = remaining, "Failed to renew the identity document");
+            }
+            Err(err) => {
+                error!(?err, "Failed to sign the identity document, not serving any");
+                let message = format!("Failed to sign the identity document: {err}");
+                self.alerts.raise(AlertCondition::SignerUnavailable, message);
+                self.identity.set(None);
+            }
+        }
+    }
+
+    /// Stop accepting commitment requests, and respond to the ones the API already dispatched
+    /// before returning. The requests awaiting an external signature are dropped, and their
+    /// clients see the service as unavailable.
+    async fn drain_commitment_requests(&mut self) {
+        self.readiness.set_shutting_down();
+        self.api_events_rx.close();
+
+        let mut drained = 0;
+        while let Some(api_event) = self.api_events_rx.recv().await {
+            self.handle_incoming_api_event(api_event).await;
+            drained += 1;
+        }
+        info!(drained, "Responded to the commitment requests in flight");
+    }
+
+    /// Release the validator leases for a clean handover to another instance, and wait for the
+    /// audit log to be written and the alerts to be delivered, for a bounded time.
+    async fn shutdown(&mut self) {
+        info!("Shutting down");
+        if let Some(ref mut leases) = self.leases {
+            if let Err(err) = leases.release_all() {
+                error!(?err, "Failed to release validator leases");
+            }
+        }
+        if let Some(ref path) = self.ports_file {
+            if let Err(err) = std::fs::remove_file(path) {
+                error!(?err, "Failed to remove the ports file");
+            }
+        }
+
+        let audit = async {
+            if let Some(ref audit) = self.audit {
+                audit.close().await;
+            }
+        };
+        let flushed = async { tokio::join!(audit, self.alerts.close()) };
+        if tokio::time::timeout(SHUTDOWN_FLUSH_TIMEOUT, flushed).await.is_err() {
+            warn!("Timed out writing the audit log and delivering the alerts");
+        }
+    }
+
+    /// Append an input or decision of the driver to the replay log, if recording.
+    fn record(&self, event: impl FnOnce(u64) -> ReplayEvent) {
+        if let Some(ref recorder) = self.recorder {
+            recorder.record(event(SystemTimeSource.now().as_millis() as u64));
+        }
+    }
+
+    /// Publish a snapshot of the read-mostly driver state for the commitments API.
+    fn publish_snapshot(&self) {
+        let next_slot = self.consensus.our_next_slots(1).first().copied();
+        self.snapshots.publish(StateSnapshot {
+            generation: 0,
+            head_slot: self.chain_head.slot(),
+            head_block: self.execution.block_number(),
+            lookahead: self.consensus.our_next_slots(SLOTS_PER_EPOCH as usize),
+            lookahead_end_slot: self.consensus.lookahead_end_slot(),
+            maintenance: self.consensus.maintenance_windows(),
+            slots: self.execution.slot_budgets(),
+            pending_signatures: self
+                .external_signer
+                .as_ref()
+                .map_or(0, ExternalSigner::pending_len),
+            basefee: self.execution.basefee_status(),
+            priority_fee_floor: next_slot.map(|slot| self.execution.priority_fee_floor(slot)),
+            projected_basefee: next_slot.and_then(|slot| self.execution.projected_basefee(slot)),
+            lease_conflicts: self
+                .leases
+                .as_ref()
+                .map(ValidatorLeases::conflicts)
+                .unwrap_or_default(),
+            head_ages: self.freshness.status(self.clock.now()),
+            clock_drift: self.drift.status(),
+            latency: self.latency.status(),
+            queue_memory: self.execution.queue_memory_status(),
+            relays: self.submissions.relay_status(),
+        });
+    }
+
     /// Handle an incoming API event, validating the request and responding with a commitment.
+    /// The logs of the request carry the id of the API call that made it.
+    #[instrument(skip_all, name = "request", fields(request_id = %event.request_id))]
     async fn handle_incoming_api_event(&mut self, event: CommitmentEvent) {
-        let CommitmentEvent { mut request, response } = event;
+        let Some(event) = self.resolve_slot_range(event) else { return };
+        let CommitmentEvent { mut request, response, quotas, .. } = event;
         info!("Received new commitment request: {:?}", request);
         let start = Instant::now();
 
-        let validator_index = match self.consensus.validate_request(&request) {
+        let req = match request {
+            CommitmentRequest::Inclusion(ref req) => req,
+            CommitmentRequest::Exclusion(exclusion) => {
+                return self.handle_exclusion_request(exclusion, response).await
+            }
+        };
+        let leased = self.holds_lease(req.slot);
+        self.record(|at_ms| ReplayEvent::Request {
+            at_ms,
+            request: req.clone(),
+            signature: req.signature.map(|sig| sig.as_bytes().to_vec().into()),
+            signer: req.signer,
+            leased,
+        });
+
+        let result =
+            validate_commitment(&self.consensus, &mut self.execution, leased, &mut request).await;
+        self.record_validation_latency(start.elapsed());
+
+        let Some(inclusion_request) = request.as_inclusion_request().cloned() else {
+            unreachable!("Exclusion requests are handled separately")
+        };
+        self.record(|_| ReplayEvent::Decision {
+            digest: inclusion_request.digest(),
+            decision: Decision::new(&inclusion_request, &result),
+        });
+
+        let validator_index = match result {
             Ok(index) => index,
             Err(err) => {
-                error!(?err, "Consensus: failed to validate request");
-                let _ = response.send(Err(CommitmentError::Consensus(err)));
+                let _ = response.send(Err(err));
                 return;
             }
         };
 
-        if let Err(err) = self.execution.validate_request(&mut request).await {
-            error!(?err, "Execution: failed to commit request");
-            let _ = response.send(Err(CommitmentError::Validation(err)));
-            return;
-        }
-
-        // TODO: match when we have more request types
-        let CommitmentRequest::Inclusion(inclusion_request) = request.clone();
         let target_slot = inclusion_request.slot;
 
         info!(
@@ -196,39 +1092,530 @@ impl<C: StateFetcher, BLS: SignerBLS, ECDSA: SignerECDSA> SidecarDriver<C, BLS,
             "Validation against execution state passed"
         );
 
-        // parse the request into constraints and sign them
-        let slot = inclusion_request.slot;
-        let message = ConstraintsMessage::build(validator_index, inclusion_request);
-        let signed_constraints = match self.constraint_signer.sign(&message.digest()) {
+        // The request counts against the quotas of its signer until it's cancelled
+        if let Some(signer) = inclusion_request.signer {
+            let digest = inclusion_request.digest();
+            let txs = inclusion_request.txs.len() as u64;
+            let usage = QuotaUsage { gas: inclusion_request.gas_limit(), txs };
+            if let Err(err) = self.quotas.check(&signer, target_slot, usage, &quotas) {
+                warn!(%signer, target_slot, %err, "Signer quota exceeded, request rejected");
+                self.cancel_commitment(target_slot, digest);
+                let _ = response.send(Err(err.into()));
+                return;
+            }
+            self.quotas.hold(digest, signer, target_slot, usage);
+        }
+
+        // The deadline may have passed while waiting for the execution client
+        let stage = ProcessingStage::ExecutionValidation;
+        if let Err(err) = self.recheck_deadline(target_slot, stage) {
+            self.cancel_commitment(target_slot, inclusion_request.digest());
+            let _ = response.send(Err(err));
+            return;
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let fee = CommitmentFee::new(&inclusion_request, now, self.execution.basefee());
+
+        // The commitment is recorded before it is signed, so that none is issued unrecorded
+        let persistence_start = Instant::now();
+        let context = inclusion_request.context.as_deref();
+        if self.earnings.record(&fee, context).is_err() {
+            self.cancel_commitment(target_slot, inclusion_request.digest());
+            let _ = response.send(Err(CommitmentError::PersistenceUnavailable));
+            return;
+        }
+        self.latency.record(PipelineStage::Persistence, persistence_start.elapsed());
+
+        // With an external signer, the request waits until its signature is imported
+        if let Some(ref mut external) = self.external_signer {
+            let context = AwaitingSignature { validator_index, fee, response };
+            let digest = external.park(inclusion_request, context);
+            info!(%digest, target_slot, "Commitment request awaiting external signature");
+            return;
+        }
+
+        // Create a commitment by signing the request
+        let signing_start = Instant::now();
+        let commitment = match request.commit_with_backend(self.signer.as_ref()).await {
+            Ok(commitment) => commitment,
+            Err(err) => {
+                error!(?err, "Failed to sign commitment");
+                let message = format!("Failed to sign commitment: {err}");
+                self.alerts.raise(AlertCondition::SignerUnavailable, message);
+                self.abandon_commitment(target_slot, inclusion_request.digest());
+                let _ = response.send(Err(signer_error(&err)));
+                return;
+            }
+        };
+        self.latency.record(PipelineStage::Signing, signing_start.elapsed());
+
+        let stage = ProcessingStage::CommitmentSigning;
+        let result =
+            self.complete_commitment(validator_index, inclusion_request, commitment, stage).await;
+        let _ = response.send(result);
+    }
+
+    /// Targets a request for a range of slots at the earliest one proposed by our validators.
+    /// If none of the lookahead is, the request is deferred to the next slot and `None` is
+    /// returned, as for a rejected request.
+    ///
+    /// The signer of the request was recovered by the API, from the digest of the range.
+    fn resolve_slot_range(&mut self, mut event: CommitmentEvent) -> Option<CommitmentEvent> {
+        let CommitmentRequest::Inclusion(ref mut req) = event.request else { return Some(event) };
+        let Some(max_slot) = req.max_slot else { return Some(event) };
+
+        let (digest, signer) = (req.digest(), req.signer);
+        match self.consensus.resolve_slot_range(req.slot, max_slot) {
+            Ok(Some(slot)) => {
+                debug!(%digest, slot, max_slot, "Slot range request targeted");
+                req.slot = slot;
+                self.inclusions.alias(digest, req.digest());
+                Some(event)
+            }
+            Ok(None) => {
+                debug!(%digest, max_slot, "No proposer slot in the lookahead, request deferred");
+                self.inclusions.defer(digest, max_slot, signer);
+                self.deferred.push(DeferredRequest { digest, event });
+                None
+            }
+            Err(err) => {
+                if self.inclusions.expire(&digest) {
+                    info!(%digest, max_slot, %err, "Deferred request expired");
+                }
+                let _ = event.response.send(Err(CommitmentError::Consensus(err)));
+                None
+            }
+        }
+    }
+
+    /// Returns true if this instance may commit for the proposer of the slot, i.e. if its
+    /// validator isn't leased by another instance.
+    fn holds_lease(&self, slot: u64) -> bool {
+        match self.leases {
+            Some(ref leases) => {
+                let pubkey = self.consensus.proposer_pubkey(slot);
+                pubkey.is_some_and(|pubkey| leases.holds(&pubkey.to_string()))
+            }
+            None => true,
+        }
+    }
+
+    /// Handle an exclusion request: validate it against the commitments of its slot, sign
+    /// it, and add its exclusion constraints to the block template.
+    ///
+    /// Exclusions are signed by the commitment signer of the sidecar, so they are only
+    /// supported without an external signer.
+    async fn handle_exclusion_request(
+        &mut self,
+        request: ExclusionRequest,
+        response: oneshot::Sender<Result<SignedCommitment, CommitmentError>>,
+    ) {
+        if self.external_signer.is_some() {
+            let err = RejectionError::NotEnabled("exclusion commitments");
+            let _ = response.send(Err(err.into()));
+            return;
+        }
+
+        let start = Instant::now();
+        let (slot, digest) = (request.slot, request.digest());
+        let leased = self.holds_lease(slot);
+        let mut request = CommitmentRequest::Exclusion(request);
+        let result =
+            validate_commitment(&self.consensus, &mut self.execution, leased, &mut request).await;
+        self.record_validation_latency(start.elapsed());
+
+        let validator_index = match result {
+            Ok(index) => index,
+            Err(err) => {
+                let _ = response.send(Err(err));
+                return;
+            }
+        };
+
+        let commitment = match request.clone().commit_with_backend(self.signer.as_ref()).await {
+            Ok(commitment) => commitment,
+            Err(err) => {
+                error!(?err, "Failed to sign exclusion commitment");
+                let message = format!("Failed to sign commitment: {err}");
+                self.alerts.raise(AlertCondition::SignerUnavailable, message);
+                let _ = response.send(Err(signer_error(&err)));
+                return;
+            }
+        };
+
+        // Only the constraints are signed between this check and adding them to the template
+        if let Err(err) = self.recheck_deadline(slot, ProcessingStage::CommitmentSigning) {
+            let _ = response.send(Err(err));
+            return;
+        }
+
+        let CommitmentRequest::Exclusion(request) = request else {
+            unreachable!("The request is an exclusion request")
+        };
+        let message = ConstraintsMessage::build_exclusion(validator_index, request);
+        let signing_data = self.constraint_signing_data(&message);
+        let signed_constraints = match self.signer.sign_bls(&signing_data).await {
+            Ok(signature) => SignedConstraints { message, signature },
+            Err(err) => {
+                error!(?err, "Failed to sign exclusion constraints");
+                let message = format!("Failed to sign constraints: {err}");
+                self.alerts.raise(AlertCondition::SignerUnavailable, message);
+                let _ = response.send(Err(signer_error(&err)));
+                return;
+            }
+        };
+        self.alerts.clear(AlertCondition::SignerUnavailable);
+
+        self.execution.add_constraint(slot, signed_constraints.clone());
+        self.enqueue_constraints(signed_constraints);
+
+        let commitment_id = commitment.commitment_id();
+        info!(%digest, %commitment_id, slot, "Exclusion commitment issued");
+        let _ = response.send(Ok(commitment));
+    }
+
+    /// Finish processing a signed commitment: sign the constraints, add them to the block
+    /// template and persist them for submission. Returns the commitment to respond with.
+    ///
+    /// Aborts if the deadline of the target slot has passed since the request reached `stage`.
+    async fn complete_commitment(
+        &mut self,
+        validator_index: u64,
+        request: InclusionRequest,
+        commitment: SignedCommitment,
+        stage: ProcessingStage,
+    ) -> Result<SignedCommitment, CommitmentError> {
+        // Only the constraints are signed between this check and adding them to the template
+        let slot = request.slot;
+        if let Err(err) = self.recheck_deadline(slot, stage) {
+            self.abandon_commitment(slot, request.digest());
+            return Err(err);
+        }
+
+        // parse the request into constraints and sign them. From here on, the blobs of the
+        // request are either in the block template or not committed to at all.
+        let (digest, signer, commitment_id) =
+            (request.digest(), request.signer, request.commitment_id());
+        self.record(|_| ReplayEvent::Committed { digest });
+        self.execution.release_reserved(slot, digest);
+        let message = ConstraintsMessage::build(validator_index, request);
+        let signing_data = self.constraint_signing_data(&message);
+        let signed_constraints = match self.signer.sign_bls(&signing_data).await {
             Ok(signature) => SignedConstraints { message, signature },
             Err(err) => {
                 error!(?err, "Failed to sign constraints");
-                let _ = response.send(Err(CommitmentError::Internal));
+                let message = format!("Failed to sign constraints: {err}");
+                self.alerts.raise(AlertCondition::SignerUnavailable, message);
+                self.execution.release_queued(slot, digest);
+                self.quotas.release(digest);
+                self.earnings.discard(digest);
+                return Err(signer_error(&err));
+            }
+        };
+        self.alerts.clear(AlertCondition::SignerUnavailable);
+
+        // The commitment is recorded with its constraints before it is returned, so that it
+        // is restored after a restart
+        let record = IssuedRecord::new(digest, signer, &commitment, &signed_constraints);
+        let result = record.map_err(StoreError::from);
+        if let Err(err) = result.and_then(|record| self.inclusions.record(&record)) {
+            error!(?err, %digest, "Failed to record the issued commitment");
+            let message = format!("Failed to record issued commitment: {err}");
+            self.alerts.raise(AlertCondition::StoreWriteFailure, message);
+            self.execution.release_queued(slot, digest);
+            self.quotas.release(digest);
+            self.earnings.discard(digest);
+            return Err(CommitmentError::PersistenceUnavailable);
+        }
+
+        let tx_hashes = signed_constraints.message.constraints.iter();
+        let tx_hashes = tx_hashes.map(|constraint| *constraint.transaction.hash()).collect();
+        self.inclusions.issue(digest, slot, signer, tx_hashes);
+        self.execution.add_constraint(slot, signed_constraints.clone());
+        self.enqueue_constraints(signed_constraints);
+
+        info!(%digest, %commitment_id, slot, "Commitment issued");
+        Ok(commitment)
+    }
+
+    /// Record the latency of the validation stages of a request, given the total time
+    /// its validation took.
+    fn record_validation_latency(&mut self, elapsed: Duration) {
+        let Some(timings) = self.execution.take_validation_timings() else {
+            // The request was rejected before reaching the execution state
+            self.latency.record(PipelineStage::Validation, elapsed);
+            return;
+        };
+
+        let simulation = timings.total.saturating_sub(timings.lookups);
+        self.latency.record(PipelineStage::Validation, elapsed.saturating_sub(timings.total));
+        self.latency.record(PipelineStage::ExecutionLookups, timings.lookups);
+        self.latency.record(PipelineStage::Simulation, simulation);
+    }
+
+    /// Abandon an accepted request that won't be committed, releasing the blobs it reserved,
+    /// the memory held by its transactions and its part of the quotas of its signer.
+    fn cancel_commitment(&mut self, slot: u64, digest: B256) {
+        self.record(|_| ReplayEvent::Cancelled { digest });
+        self.execution.release_reserved(slot, digest);
+        self.execution.release_queued(slot, digest);
+        self.quotas.release(digest);
+    }
+
+    /// Abandon a request whose fee was already recorded, discarding the record as well.
+    fn abandon_commitment(&mut self, slot: u64, digest: B256) {
+        self.cancel_commitment(slot, digest);
+        self.earnings.discard(digest);
+    }
+
+    /// Returns the data the constraint key signs for the message, in the signing domain of
+    /// the fork of its slot.
+    fn constraint_signing_data(&self, message: &ConstraintsMessage) -> Vec<u8> {
+        let domain = self.config.chain.constraint_domain(message.slot);
+        message.signing_data(self.constraint_digest, domain)
+    }
+
+    /// Check again that the deadline of the target slot hasn't passed while the request
+    /// was at the given processing stage, recording the stage at which time ran out.
+    fn recheck_deadline(&self, slot: u64, stage: ProcessingStage) -> Result<(), CommitmentError> {
+        self.consensus.check_deadline(slot, stage).map_err(|err| {
+            warn!(slot, %stage, "Deadline passed during processing, aborting the commitment");
+            let label = stage.to_string();
+            metrics::counter!(telemetry::DEADLINE_PASSED_DURING_PROCESSING, "stage" => label)
+                .increment(1);
+            CommitmentError::Consensus(err)
+        })
+    }
+
+    /// Handle the import of an externally signed commitment, completing the
+    /// request that was awaiting its signature.
+    async fn handle_import_event(&mut self, event: ImportEvent) {
+        let ImportEvent { digest, signature, response } = event;
+
+        let Some(ref mut external) = self.external_signer else {
+            let _ = response.send(Err(ImportError::NotEnabled.into()));
+            return;
+        };
+
+        let (request, context, commitment) = match external.import(digest, signature) {
+            Ok(imported) => imported,
+            Err(err) => {
+                warn!(%digest, ?err, "Failed to import externally signed commitment");
+                let _ = response.send(Err(err.into()));
                 return;
             }
         };
 
-        self.execution.add_constraint(slot, signed_constraints);
+        info!(%digest, slot = request.slot, "Imported externally signed commitment");
+        let AwaitingSignature { validator_index, response: client, .. } = context;
 
-        // Create a commitment by signing the request
-        match request.commit_and_sign(&self.commitment_signer).await {
-            Ok(commitment) => response.send(Ok(commitment)).ok(),
+        let stage = ProcessingStage::ExternalSignature;
+        match self.complete_commitment(validator_index, request, commitment, stage).await {
+            Ok(commitment) => {
+                if client.send(Ok(commitment.clone())).is_err() {
+                    warn!(%digest, "Client request closed before the commitment was imported");
+                }
+                let _ = response.send(Ok(commitment));
+            }
             Err(err) => {
-                error!(?err, "Failed to sign commitment");
-                response.send(Err(CommitmentError::Internal)).ok()
+                let _ = client.send(Err(err));
+                let _ = response.send(Err(CommitmentError::Internal));
+            }
+        }
+    }
+
+    /// Handle a drop, confirmation or restoration of a commitment from the admin API.
+    fn handle_drop_event(&mut self, event: DropEvent) {
+        let DropEvent { action, digest, admin, response } = event;
+        let (templates, submissions) = (self.execution.block_templates_mut(), &self.submissions);
+
+        let result = match action {
+            DropAction::Drop => {
+                let now = SystemTimeSource.now().as_secs();
+                self.drops.drop_commitment(templates, digest, &admin, now)
+            }
+            DropAction::Confirm => self.drops.confirm(templates, submissions, digest, &admin),
+            DropAction::Restore => {
+                let head = self.chain_head.slot();
+                self.drops.restore(templates, submissions, digest, &admin, head)
             }
         };
+
+        if let Err(ref err) = result {
+            warn!(%digest, ?action, %admin, %err, "Failed to update dropped commitment");
+        }
+        let result = result.map_err(RejectionError::Drop);
+        let _ = response.send(result.map_err(Into::into));
+    }
+
+    /// Handle the cancellation of a commitment by the signer of its request. The commitment
+    /// is removed from its block template and the pending submissions, unless the deadline
+    /// of its slot has passed.
+    fn handle_cancel_event(&mut self, event: CancelEvent) {
+        let CancelEvent { digest, signer, response } = event;
+        let digest = self.inclusions.commitment_digest(&digest);
+        let Some((slot, requester, status)) = self.inclusions.issued(&digest) else {
+            let _ = response.send(Err(CancelError::UnknownCommitment(digest).into()));
+            return;
+        };
+        if requester != Some(signer) {
+            let _ = response.send(Err(CancelError::SignerMismatch(signer).into()));
+            return;
+        }
+
+        let outcome = match status {
+            CommitmentStatus::Cancelled => CancelOutcome::Cancelled,
+            CommitmentStatus::Pending if !self.consensus.is_past_deadline(slot) => {
+                let template = self.execution.block_templates_mut().get_mut(&slot);
+                if let Some(constraints) = template.and_then(|t| t.remove_commitment(&digest)) {
+                    self.submissions.remove(&constraints);
+                }
+                self.execution.release_queued(slot, digest);
+                self.quotas.release(digest);
+                self.earnings.discard(digest);
+                self.inclusions.cancel(&digest);
+                self.deferred.retain(|deferred| deferred.digest != digest);
+                info!(target: AUDIT_TARGET, %digest, slot, %signer, "Commitment cancelled");
+                CancelOutcome::Cancelled
+            }
+            _ => CancelOutcome::TooLate,
+        };
+        let _ = response.send(Ok(outcome));
+    }
+
+    /// Handle a declaration or cancellation of a maintenance window from the admin API.
+    fn handle_maintenance_event(&mut self, event: MaintenanceEvent) {
+        match event {
+            MaintenanceEvent::Declare { window, admin, response } => {
+                let templates = self.execution.block_templates();
+                let (window, conflicts) =
+                    self.consensus.declare_maintenance(window, &admin, templates);
+                info!(
+                    target: AUDIT_TARGET,
+                    id = window.id,
+                    window = %window.window,
+                    admin,
+                    conflicts = conflicts.len(),
+                    "Maintenance window declared"
+                );
+                let _ = response.send(Ok(MaintenanceDeclaration { window, conflicts }));
+            }
+            MaintenanceEvent::Cancel { id, admin, response } => {
+                let result = match self.consensus.cancel_maintenance(id) {
+                    Some(window) => {
+                        info!(target: AUDIT_TARGET, id, admin, "Maintenance window cancelled");
+                        Ok(window)
+                    }
+                    None => Err(RejectionError::UnknownMaintenanceWindow(id).into()),
+                };
+                let _ = response.send(result);
+            }
+        }
+    }
+
+    /// Confirm the drops of the commitments pending for longer than the confirmation delay.
+    fn confirm_due_drops(&mut self) {
+        let (templates, now) = (self.execution.block_templates_mut(), SystemTimeSource.now());
+        self.drops.confirm_due(templates, &self.submissions, now.as_secs());
     }
 
     /// Handle a new head event, updating the execution state.
     async fn handle_new_head_event(&mut self, head_event: HeadEvent) {
         let slot = head_event.slot;
         info!(slot, "Received new head event");
+        self.record(|at_ms| ReplayEvent::Head { at_ms, slot });
+        self.chain_head.update_slot(slot);
+        let slot_start = self.clock.slot_start(slot);
+        self.freshness.observe(ChainLayer::Consensus, slot_start);
+        self.drift.observe(self.clock.local_now(), slot_start);
+        let now = self.clock.now().as_secs();
+        self.health.record(Dependency::Consensus, true, now);
 
         // We use None to signal that we want to fetch the latest EL head
-        if let Err(e) = self.execution.update_head(None, slot).await {
+        let result = self.execution.update_head(None, slot).await;
+        self.health.record(Dependency::Execution, result.is_ok(), now);
+        if let Err(e) = result {
             error!(err = ?e, "Failed to update execution state head");
         }
+        self.observe_execution_head();
+        if self.execution.block_timestamp() == Some(slot_start.as_secs()) {
+            self.drift.observe(self.clock.local_now(), slot_start);
+        }
+        self.check_head_freshness();
+        self.check_clock_drift();
+
+        // Resolve the inclusion of the commitments for this slot and reconcile their fees in
+        // the background, and upgrade the finality of the earlier ones
+        let (earnings, inclusions) = (self.earnings.clone(), self.inclusions.clone());
+        tokio::spawn(async move {
+            if let Err(e) = inclusions.observe(slot).await {
+                error!(err = ?e, slot, "Failed to resolve the inclusion of the commitments");
+            }
+            if let Err(e) = earnings.reconcile_slot(slot).await {
+                error!(err = ?e, slot, "Failed to reconcile commitment fees");
+            }
+            if let Err(e) = earnings.update_finality().await {
+                error!(err = ?e, "Failed to update the finality of commitment fees");
+            }
+        });
+    }
+
+    /// Handle a tick of a slot-relative task of the driver.
+    async fn handle_scheduled_task(&mut self, tick: SlotTick<DriverTask>) {
+        match tick.task {
+            DriverTask::SlotStart => {
+                self.record(|at_ms| ReplayEvent::SlotStart { at_ms, slot: tick.slot });
+                self.expire_passed_slots(tick.slot);
+                let result = self.consensus.update_slot(tick.slot).await;
+                let now = self.clock.now().as_secs();
+                self.health.record(Dependency::Consensus, result.is_ok(), now);
+                match result {
+                    Ok(()) => self.expire_lost_duties(tick.slot),
+                    Err(e) => error!(err = ?e, "Failed to update consensus state slot"),
+                }
+
+                // The deferred requests are targeted again with the duties of the new slot
+                for deferred in std::mem::take(&mut self.deferred) {
+                    self.handle_incoming_api_event(deferred.event).await;
+                }
+            }
+            DriverTask::ConstraintSubmission => self.handle_constraint_submission(tick.slot),
+            DriverTask::CommitmentDeadline => self.handle_commitment_deadline(tick.slot).await,
+        }
+    }
+
+    /// Expire the commitments of the slots before the given one whose constraints weren't
+    /// submitted, and release the space held in those slots.
+    fn expire_passed_slots(&mut self, slot: u64) {
+        self.quotas.expire(slot);
+        self.execution.expire_before(slot);
+        for (digest, reason) in self.inclusions.expire_passed(slot) {
+            info!(%digest, ?reason, "Commitment expired");
+        }
+    }
+
+    /// Expire the commitments of the upcoming slots whose proposer duty was lost since they
+    /// were issued, withdrawing their constraints and releasing the space they held.
+    fn expire_lost_duties(&mut self, slot: u64) {
+        let is_lost =
+            |target| self.consensus.proposer_pubkey(target).is_none() || !self.holds_lease(target);
+        let upcoming = self.execution.block_templates().keys().copied();
+        let lost = upcoming.filter(|&target| target >= slot && is_lost(target)).collect::<Vec<_>>();
+
+        for target in lost {
+            let Some(template) = self.execution.remove_block_template(target) else { continue };
+            for constraints in &template.signed_constraints_list {
+                let digest = constraints.message.request_digest();
+                self.submissions.remove(constraints);
+                self.execution.release_queued(target, digest);
+                self.quotas.release(digest);
+                self.earnings.discard(digest);
+            }
+
+            let expired = self.inclusions.lose_duty(target).len();
+            warn!(slot = target, expired, "Proposer duty lost, commitments expired");
+        }
     }
 
     /// Handle a commitment deadline event, submitting constraints to the MEV-Boost service
@@ -236,31 +1623,136 @@ impl<C: StateFetcher, BLS: SignerBLS, ECDSA: SignerECDSA> SidecarDriver<C, BLS,
     async fn handle_commitment_deadline(&mut self, slot: u64) {
         debug!(slot, "Commitment deadline reached, building local block");
 
+        // Requests still awaiting an external signature can't be committed to anymore
+        let expired = self.external_signer.as_mut().map(|external| external.expire(slot));
+        for awaiting in expired.unwrap_or_default() {
+            self.abandon_commitment(awaiting.fee.slot, awaiting.fee.digest);
+            let err = CommitmentError::Consensus(self.consensus.deadline_exceeded(slot));
+            let _ = awaiting.response.send(Err(err));
+        }
+
+        // Drops past their confirmation delay are excluded from the submitted constraints,
+        // the ones still pending are submitted
+        self.confirm_due_drops();
+
+        // The persisted constraints are submitted even if the block template was lost in a
+        // restart since they were signed. If they were submitted ahead of the deadline, only
+        // the ones still pending are submitted again.
+        self.submissions.prune(slot);
+        let constraints = if self.early_submission == Some(slot) {
+            self.early_submission = None;
+            Vec::new()
+        } else {
+            self.template_constraints(slot)
+        };
+        self.submit_constraints(slot, constraints, None);
+        self.drops.prune(slot);
+
+        // The submitted constraints are the final obligations of the slot
+        self.manifests.update(self.execution.block_templates());
+        self.manifests.freeze(slot);
+
         let Some(template) = self.execution.get_block_template(slot) else {
             warn!("No block template found for slot {slot} when requested");
             return;
         };
 
-        if let Err(e) = self.local_builder.build_new_local_payload(slot, template).await {
-            error!(err = ?e, "Error while building local payload at deadline for slot {slot}");
-        };
+        match self.local_builder.build_new_local_payload(slot, template).await {
+            Ok(transactions) => self.inclusions.record_payload(slot, transactions),
+            Err(BuilderError::UnsupportedFork(_)) => {
+                debug!(slot, "No local payload is built from the Electra fork on")
+            }
+            Err(e) => {
+                error!(err = ?e, "Error while building local payload at deadline for slot {slot}")
+            }
+        }
+    }
+
+    /// Handle the constraint submission time of a slot, submitting the constraints signed so
+    /// far ahead of its commitment deadline. The constraints signed afterwards are submitted
+    /// as incremental updates, until the deadline.
+    fn handle_constraint_submission(&mut self, slot: u64) {
+        debug!(slot, "Constraint submission time reached, submitting the constraints");
+
+        self.confirm_due_drops();
+        self.submissions.prune(slot);
+        let constraints = self.template_constraints(slot);
+        self.submit_constraints(slot, constraints, None);
+        self.early_submission = Some(slot);
+    }
 
-        // TODO: fix retry logic, and move this to separate task in the mevboost client itself
-        let constraints = template.signed_constraints_list.clone();
+    /// Returns the signed constraints of the block template of the slot.
+    fn template_constraints(&self, slot: u64) -> BatchedSignedConstraints {
+        self.execution
+            .get_block_template(slot)
+            .map(|template| template.signed_constraints_list.clone())
+            .unwrap_or_default()
+    }
+
+    /// Persist the signed constraints for submission. If the constraints of their slot were
+    /// already submitted, they are submitted right away as an incremental update.
+    fn enqueue_constraints(&mut self, constraints: SignedConstraints) {
+        let slot = constraints.message.slot;
+        if self.early_submission == Some(slot) {
+            debug!(slot, "Submitting the late constraints as an incremental update");
+            self.submit_constraints(slot, vec![constraints.clone()], Some(constraints));
+        } else {
+            self.submissions.enqueue(&constraints);
+        }
+    }
+
+    /// Submit the persisted constraints of the slot to the relays in the background. If
+    /// none were persisted, the constraints of the block template are submitted instead.
+    ///
+    /// The submission starts once the previous one of the slot has completed, so that the
+    /// `late` constraints persisted beforehand aren't overwritten by its delivery state.
+    fn submit_constraints(
+        &mut self,
+        slot: u64,
+        template: BatchedSignedConstraints,
+        late: Option<SignedConstraints>,
+    ) {
+        let previous = self.submission_task.take().filter(|(previous, _)| *previous == slot);
+        let submissions = self.submissions.clone();
         let mevboost = self.mevboost_client.clone();
-        tokio::spawn(async move {
-            let max_retries = 5;
-            let mut i = 0;
-            while let Err(e) = mevboost.submit_constraints(&constraints).await {
-                error!(err = ?e, "Error submitting constraints to mev-boost, retrying...");
-                tokio::time::sleep(Duration::from_millis(100)).await;
-                i += 1;
-                if i >= max_retries {
-                    error!("Max retries reached while submitting to MEV-Boost");
-                    break;
+        let latency = self.latency.clone();
+        let alerts = self.alerts.clone();
+        let inclusions = self.inclusions.clone();
+        let task = tokio::spawn(async move {
+            if let Some((_, previous)) = previous {
+                let _ = previous.await;
+            }
+            if let Some(late) = late {
+                submissions.enqueue(&late);
+            }
+
+            let start = Instant::now();
+            let submitted = match submissions.submit(slot).await {
+                Ok(true) => true,
+                Ok(false) if template.is_empty() => return,
+                Ok(false) => {
+                    mevboost.submit_constraints_with_retries(&template, &alerts).await.is_ok()
+                }
+                Err(err @ SubmissionError::CircuitOpen { .. }) => {
+                    warn!(
+                        %err,
+                        slot,
+                        "Relay circuit open, the proposer falls back to the local payload"
+                    );
+                    false
+                }
+                Err(err) => {
+                    error!(?err, slot, "Failed to submit the constraints");
+                    false
                 }
+            };
+
+            if submitted {
+                latency.record(PipelineStage::ConstraintSubmission, start.elapsed());
             }
+            inclusions.record_submission(slot, submitted);
         });
+        self.submission_task = Some((slot, task));
     }
 
     /// Handle a fetch payload request, responding with the local payload if available.
@@ -278,3 +1770,109 @@ impl<C: StateFetcher, BLS: SignerBLS, ECDSA: SignerECDSA> SidecarDriver<C, BLS,
         }
     }
 }
+
+/// Validate a commitment request against the consensus and execution state, returning the
+/// index of the validator proposing at the target slot. `leased` is false if the validator
+/// of the target slot is leased by another sidecar instance.
+///
+/// This is the decision that is compared with the recorded one when replaying a log, so it
+/// must only depend on its arguments.
+pub(crate) async fn validate_commitment<B, T, C>(
+    consensus: &ConsensusState<B, T>,
+    execution: &mut ExecutionState<C>,
+    leased: bool,
+    request: &mut CommitmentRequest,
+) -> Result<u64, CommitmentError>
+where
+    B: ProposerDutiesFetcher,
+    T: TimeSource,
+    C: StateFetcher,
+{
+    let validator_index = match consensus.validate_request(request) {
+        Ok(index) => index,
+        Err(err) => {
+            error!(?err, "Consensus: failed to validate request");
+            return Err(CommitmentError::Consensus(err));
+        }
+    };
+
+    // Only commit for validators that aren't leased by another instance
+    if !leased {
+        let slot = request.slot();
+        warn!(slot, "Rejecting request, validator leased by another instance");
+        return Err(CommitmentError::Consensus(ConsensusError::LeaseNotHeld(slot)));
+    }
+
+    if let Err(err) = execution.validate_request(request).await {
+        error!(?err, "Execution: failed to commit request");
+        return Err(CommitmentError::Validation(err));
+    }
+
+    Ok(validator_index)
+}
+
+/// Returns the error the client is responded with when signing its commitment fails. A remote
+/// signer that fails is reported as unavailable, so that the client retries later.
+fn signer_error(err: &SignerError) -> CommitmentError {
+    if err.is_remote() {
+        CommitmentError::SignerUnavailable
+    } else {
+        CommitmentError::Internal
+    }
+}
+
+/// Registers the tasks of the driver at their offsets within the slots of the chain. The
+/// offsets of the deadline and of the constraint submission are relative to the start of
+/// the slot they target.
+fn register_tasks<T: TimeSource>(
+    scheduler: &mut SlotScheduler<DriverTask, T>,
+    chain: &ChainConfig,
+) {
+    let (submission, deadline) =
+        (chain.constraint_submission_offset(), chain.commitment_deadline_offset());
+    scheduler.register(DriverTask::SlotStart, SlotOffset::START);
+    if submission > deadline {
+        scheduler.register(DriverTask::ConstraintSubmission, SlotOffset::Before(submission));
+    }
+    scheduler.register(DriverTask::CommitmentDeadline, SlotOffset::Before(deadline));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{clock::ManualTimeSource, scheduler::ScheduleEntry};
+
+    fn scheduler(
+        chain: &ChainConfig,
+        time: ManualTimeSource,
+    ) -> SlotScheduler<DriverTask, ManualTimeSource> {
+        let mut scheduler = SlotScheduler::new(SlotClock::with_time_source(1_000, 12, time));
+        register_tasks(&mut scheduler, chain);
+        scheduler
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_constraint_submission_time() {
+        // Start 1 second into slot 0
+        let time = ManualTimeSource::new(Duration::from_secs(1_001));
+        let chain = ChainConfig::kurtosis(12, 8_000).with_constraint_submission_time(6_000);
+        let mut ticks = scheduler(&chain, time.clone()).spawn();
+
+        // The constraints of slot 1 are submitted 6 seconds into slot 0, before the deadline
+        let expected = [
+            (DriverTask::ConstraintSubmission, 1, 1_006),
+            (DriverTask::CommitmentDeadline, 1, 1_008),
+            (DriverTask::SlotStart, 1, 1_012),
+            (DriverTask::ConstraintSubmission, 2, 1_018),
+        ];
+        for (task, slot, at) in expected {
+            assert_eq!(ticks.recv().await.unwrap(), SlotTick { task, slot });
+            assert_eq!(time.now(), Duration::from_secs(at));
+        }
+
+        // Without a submission time, the constraints are submitted at the deadline
+        let tasks = scheduler(&ChainConfig::kurtosis(12, 8_000), time).schedule();
+        let tasks = tasks.into_iter().map(|ScheduleEntry { task, .. }| task).collect::<Vec<_>>();
+        assert_eq!(tasks, ["commitment_deadline", "slot_start"]);
+    }
+}
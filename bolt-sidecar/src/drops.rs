@@ -0,0 +1,336 @@
+//! Soft deletion of the commitments dropped by the operators from the admin API.
+//!
+//! Dropping a commitment discards a binding obligation, so a mistyped digest must not take
+//! effect right away. A dropped commitment is first pending confirmation: its constraints
+//! stay in its block template and are still submitted at the deadline. The drop is confirmed
+//! with `admin_confirmDrop`, or automatically once the confirmation delay has elapsed, which
+//! removes its constraints from the block template and from the pending submissions.
+//!
+//! Until its slot has passed, a dropped commitment can be restored with
+//! `admin_restoreCommitment`, whether its drop was confirmed or not: the constraints of a
+//! confirmed one re-enter its block template and the pending submissions.
+//!
+//! Every transition is logged to the [`AUDIT_TARGET`], along with the operator.
+
+use std::{collections::HashMap, time::Duration};
+
+use alloy::primitives::B256;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    builder::template::BlockTemplate,
+    primitives::{SignedConstraints, Slot},
+    submissions::ConstraintSubmissions,
+};
+
+/// Default delay after which the drop of a commitment is confirmed automatically.
+pub const DEFAULT_DROP_CONFIRM_DELAY: Duration = Duration::from_secs(60);
+
+/// The tracing target of the audit log of the drops.
+pub const AUDIT_TARGET: &str = "bolt_sidecar::audit";
+
+/// The operator recorded for the drops confirmed after the confirmation delay.
+pub const AUTOMATIC_CONFIRMATION: &str = "automatic";
+
+/// Errors that can occur while dropping or restoring a commitment.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DropError {
+    /// No block template holds a commitment with the digest.
+    #[error("No commitment with digest {0} in the block templates")]
+    NotFound(B256),
+    /// The commitment is already dropped.
+    #[error("Commitment {0} is already dropped")]
+    AlreadyDropped(B256),
+    /// The commitment is not dropped.
+    #[error("Commitment {0} is not dropped")]
+    NotDropped(B256),
+    /// The drop of the commitment is already confirmed.
+    #[error("Drop of commitment {0} is already confirmed")]
+    AlreadyConfirmed(B256),
+    /// The slot of the commitment has passed, so it can't be restored.
+    #[error("Slot {1} of commitment {0} has passed")]
+    SlotPassed(B256, u64),
+}
+
+/// Whether the drop of a commitment is confirmed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DropStatus {
+    /// The commitment is still part of the constraints of its slot.
+    Pending,
+    /// The commitment is excluded from the constraints of its slot.
+    Confirmed,
+}
+
+/// A commitment dropped by an operator.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DroppedCommitment {
+    /// The digest of the commitment request.
+    pub digest: B256,
+    /// The slot of the commitment.
+    pub slot: u64,
+    /// Whether the drop is confirmed.
+    pub status: DropStatus,
+    /// The operator who dropped the commitment.
+    pub dropped_by: String,
+    /// The UNIX timestamp at which the commitment was dropped, in seconds.
+    pub dropped_at: u64,
+    /// The UNIX timestamp at which the drop is confirmed automatically, in seconds.
+    pub confirm_at: u64,
+    /// The operator who confirmed the drop, or [`AUTOMATIC_CONFIRMATION`].
+    pub confirmed_by: Option<String>,
+}
+
+#[derive(Debug)]
+struct DropEntry {
+    commitment: DroppedCommitment,
+    /// The constraints of the commitment, restored to its block template if needed.
+    constraints: SignedConstraints,
+}
+
+/// The commitments dropped by the operators, until their slot has passed.
+#[derive(Debug)]
+pub struct CommitmentDrops {
+    entries: HashMap<B256, DropEntry>,
+    confirm_delay: Duration,
+}
+
+impl CommitmentDrops {
+    /// Create an empty set of drops, confirmed automatically after `confirm_delay`.
+    pub fn new(confirm_delay: Duration) -> Self {
+        Self { entries: HashMap::new(), confirm_delay }
+    }
+
+    /// Returns true if a drop is pending confirmation.
+    pub fn has_pending(&self) -> bool {
+        self.entries.values().any(|entry| entry.commitment.status == DropStatus::Pending)
+    }
+
+    /// Returns the dropped commitments, in the order they were dropped.
+    pub fn entries(&self) -> Vec<DroppedCommitment> {
+        let mut entries = self.entries.values().map(|e| e.commitment.clone()).collect::<Vec<_>>();
+        entries.sort_by_key(|commitment| (commitment.dropped_at, commitment.digest));
+        entries
+    }
+
+    /// Drops the commitment in the block templates with the given digest, pending the
+    /// confirmation of the drop.
+    pub fn drop_commitment(
+        &mut self,
+        templates: &HashMap<Slot, BlockTemplate>,
+        digest: B256,
+        admin: &str,
+        now: u64,
+    ) -> Result<DroppedCommitment, DropError> {
+        if self.entries.contains_key(&digest) {
+            return Err(DropError::AlreadyDropped(digest));
+        }
+
+        let constraints = templates.values().find_map(|template| template.commitment(&digest));
+        let constraints = constraints.ok_or(DropError::NotFound(digest))?.clone();
+
+        let (slot, confirm_at) = (constraints.message.slot, now + self.confirm_delay.as_secs());
+        let commitment = DroppedCommitment {
+            digest,
+            slot,
+            status: DropStatus::Pending,
+            dropped_by: admin.to_string(),
+            dropped_at: now,
+            confirm_at,
+            confirmed_by: None,
+        };
+
+        info!(target: AUDIT_TARGET, %digest, slot, admin, confirm_at, "Commitment dropped");
+        let entry = DropEntry { commitment: commitment.clone(), constraints };
+        self.entries.insert(digest, entry);
+        Ok(commitment)
+    }
+
+    /// Confirms the drop of a commitment, removing its constraints from its block template
+    /// and from the pending submissions.
+    pub fn confirm(
+        &mut self,
+        templates: &mut HashMap<Slot, BlockTemplate>,
+        submissions: &ConstraintSubmissions,
+        digest: B256,
+        admin: &str,
+    ) -> Result<DroppedCommitment, DropError> {
+        let entry = self.entries.get_mut(&digest).ok_or(DropError::NotDropped(digest))?;
+        if entry.commitment.status == DropStatus::Confirmed {
+            return Err(DropError::AlreadyConfirmed(digest));
+        }
+
+        let slot = entry.commitment.slot;
+        if let Some(template) = templates.get_mut(&slot) {
+            template.remove_commitment(&digest);
+        }
+        submissions.remove(&entry.constraints);
+
+        entry.commitment.status = DropStatus::Confirmed;
+        entry.commitment.confirmed_by = Some(admin.to_string());
+        info!(target: AUDIT_TARGET, %digest, slot, admin, "Commitment drop confirmed");
+        Ok(entry.commitment.clone())
+    }
+
+    /// Confirms the drops pending for longer than the confirmation delay, at the UNIX
+    /// timestamp `now`. Returns the confirmed drops.
+    pub fn confirm_due(
+        &mut self,
+        templates: &mut HashMap<Slot, BlockTemplate>,
+        submissions: &ConstraintSubmissions,
+        now: u64,
+    ) -> Vec<DroppedCommitment> {
+        let due = self.entries.values().map(|entry| &entry.commitment).filter(|commitment| {
+            commitment.status == DropStatus::Pending && commitment.confirm_at <= now
+        });
+        let due = due.map(|commitment| commitment.digest).collect::<Vec<_>>();
+
+        let mut confirmed = Vec::with_capacity(due.len());
+        for digest in due {
+            let admin = AUTOMATIC_CONFIRMATION;
+            confirmed.extend(self.confirm(templates, submissions, digest, admin).ok());
+        }
+        confirmed
+    }
+
+    /// Restores a dropped commitment, if its slot is after `head_slot`. If the drop was
+    /// confirmed, its constraints re-enter its block template and the pending submissions.
+    pub fn restore(
+        &mut self,
+        templates: &mut HashMap<Slot, BlockTemplate>,
+        submissions: &ConstraintSubmissions,
+        digest: B256,
+        admin: &str,
+        head_slot: u64,
+    ) -> Result<DroppedCommitment, DropError> {
+        let entry = self.entries.get(&digest).ok_or(DropError::NotDropped(digest))?;
+        let slot = entry.commitment.slot;
+        if slot <= head_slot {
+            return Err(DropError::SlotPassed(digest, slot));
+        }
+
+        let DropEntry { commitment, constraints } =
+            self.entries.remove(&digest).expect("Dropped commitment");
+        if commitment.status == DropStatus::Confirmed {
+            submissions.enqueue(&constraints);
+            templates.entry(slot).or_default().add_constraints(constraints);
+        }
+
+        let status = commitment.status;
+        info!(target: AUDIT_TARGET, %digest, slot, admin, ?status, "Commitment restored");
+        Ok(commitment)
+    }
+
+    /// Forgets the dropped commitments of the slots up to `slot`, whose commitment deadline
+    /// has passed. The constraints of the pending ones were submitted with their slot.
+    pub fn prune(&mut self, slot: u64) {
+        self.entries.retain(|digest, entry| {
+            let DroppedCommitment { slot: dropped_slot, status, .. } = entry.commitment;
+            if dropped_slot > slot {
+                return true;
+            }
+
+            if status == DropStatus::Pending {
+                let (slot, dropped_by) = (dropped_slot, &entry.commitment.dropped_by);
+                warn!(target: AUDIT_TARGET, %digest, slot, %dropped_by, "Drop never confirmed");
+            }
+            false
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::signers::local::PrivateKeySigner;
+
+    use super::*;
+    use crate::{
+        db::{ResilientStore, Store},
+        primitives::ConstraintsMessage,
+        test_util::{create_signed_commitment_request, default_test_transaction, DeterministicEnv},
+    };
+
+    /// Returns the block template of slot 10 with two commitments, and their digests.
+    async fn templates(env: &DeterministicEnv) -> (HashMap<Slot, BlockTemplate>, [B256; 2]) {
+        let sk = env.secret_key();
+        let sender = PrivateKeySigner::from(sk.clone()).address();
+        let mut template = BlockTemplate::default();
+        let mut digests = [B256::ZERO; 2];
+
+        for (nonce, digest) in digests.iter_mut().enumerate() {
+            let tx = default_test_transaction(sender, Some(nonce as u64));
+            let request = create_signed_commitment_request(&[tx], &sk, 10).await.unwrap();
+            let request = request.into_inclusion_request().unwrap();
+            *digest = request.digest();
+
+            let message = ConstraintsMessage::build(0, request);
+            template.add_constraints(SignedConstraints { message, ..Default::default() });
+        }
+
+        (HashMap::from([(10, template)]), digests)
+    }
+
+    fn digests(templates: &HashMap<Slot, BlockTemplate>) -> Vec<B256> {
+        let list = &templates[&10].signed_constraints_list;
+        list.iter().map(|constraints| constraints.message.request_digest()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_drop_and_restore() {
+        let env = DeterministicEnv::new(1);
+        let (mut templates, [first, second]) = templates(&env).await;
+        let submissions =
+            ConstraintSubmissions::new(ResilientStore::new(Store::in_memory().unwrap()), vec![]);
+        let mut drops = CommitmentDrops::new(Duration::from_secs(60));
+
+        // Pending drops are still part of the constraints
+        let dropped = drops.drop_commitment(&templates, first, "127.0.0.1", 1_000).unwrap();
+        assert_eq!((dropped.status, dropped.confirm_at), (DropStatus::Pending, 1_060));
+        let err = drops.drop_commitment(&templates, first, "127.0.0.1", 1_000);
+        assert_eq!(err, Err(DropError::AlreadyDropped(first)));
+        assert!(drops.confirm_due(&mut templates, &submissions, 1_059).is_empty());
+        assert_eq!(digests(&templates), [first, second]);
+
+        // Restored before the confirmation, the commitment is never excluded
+        drops.restore(&mut templates, &submissions, first, "127.0.0.1", 9).unwrap();
+        assert!(drops.confirm_due(&mut templates, &submissions, 1_060).is_empty());
+        assert_eq!(digests(&templates), [first, second]);
+        assert!(!drops.has_pending());
+    }
+
+    #[tokio::test]
+    async fn test_drop_and_confirm() {
+        let env = DeterministicEnv::new(1);
+        let (mut templates, [first, second]) = templates(&env).await;
+        let store = ResilientStore::new(Store::in_memory().unwrap());
+        let submissions = ConstraintSubmissions::new(store.clone(), vec![]);
+        templates[&10].signed_constraints_list.iter().for_each(|sc| submissions.enqueue(sc));
+        let mut drops = CommitmentDrops::new(Duration::from_secs(60));
+
+        // Confirmed drops are excluded from the template and the submission
+        drops.drop_commitment(&templates, first, "127.0.0.1", 1_000).unwrap();
+        drops.drop_commitment(&templates, second, "127.0.0.1", 1_000).unwrap();
+        let confirmed = drops.confirm(&mut templates, &submissions, first, "127.0.0.1").unwrap();
+        assert_eq!(confirmed.confirmed_by.as_deref(), Some("127.0.0.1"));
+        assert_eq!(digests(&templates), [second]);
+        let payload = store.pending_submission(10).unwrap().unwrap().payload;
+        assert_eq!(serde_json::from_slice::<Vec<serde_json::Value>>(&payload).unwrap().len(), 1);
+
+        // The other one is confirmed automatically once the delay elapsed
+        let confirmed = drops.confirm_due(&mut templates, &submissions, 1_060);
+        assert_eq!(confirmed[0].confirmed_by.as_deref(), Some(AUTOMATIC_CONFIRMATION));
+        assert!(digests(&templates).is_empty());
+
+        // Confirmed drops can be restored until their slot has passed
+        let err = drops.restore(&mut templates, &submissions, first, "127.0.0.1", 10);
+        assert_eq!(err, Err(DropError::SlotPassed(first, 10)));
+        drops.restore(&mut templates, &submissions, second, "127.0.0.1", 9).unwrap();
+        assert_eq!(digests(&templates), [second]);
+
+        drops.prune(10);
+        assert!(drops.entries().is_empty());
+        let err = drops.restore(&mut templates, &submissions, first, "127.0.0.1", 9);
+        assert_eq!(err, Err(DropError::NotDropped(first)));
+    }
+}
@@ -0,0 +1,690 @@
+//! Commitment fee accounting and earnings reports.
+//!
+//! For every commitment the sidecar signs, the priority fee it expects to capture is recorded
+//! in the [`Store`] beforehand. Once the target slot is proposed, the record is reconciled
+//! against the real receipts of the block, and the reconciled amounts are aggregated into an
+//! [`EarningsReport`] on request.
+//!
+//! The block a commitment was reconciled against can still be reorged out, so each
+//! reconciliation is annotated with its [`Finality`], which is upgraded as the checkpoints
+//! advance. The miss-rate alert only accounts for the reconciliations that reached the
+//! configured finality.
+//!
+//! Only priority fees are accounted for at the moment, as there is no in-band payment mechanism.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use alloy::{
+    primitives::{Address, TxHash, B256},
+    rpc::types::TransactionReceipt,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    alerts::{AlertCondition, Alerts, DEFAULT_MAX_MISS_RATE},
+    db::{CommitmentStore, NonCriticalWrite, ResilientStore, Store, StoreError},
+    finality::{CanonicalChain, FeeFinality, Finality, SlotBlock},
+    primitives::{FullTransaction, InclusionRequest, TransactionExt},
+};
+
+/// Number of seconds in a day.
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Time window over which the miss rate of the committed transactions is computed, in seconds.
+const MISS_RATE_WINDOW_SECS: u64 = 3_600;
+
+/// Minimum number of reconciled transactions in the window for the miss rate to be checked,
+/// so that a single missed slot right after startup doesn't breach the SLO.
+const MIN_MISS_RATE_SAMPLES: usize = 16;
+
+/// The fee accounting record of a single commitment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitmentFee {
+    /// The digest of the commitment request.
+    pub digest: B256,
+    /// The target slot of the commitment.
+    pub slot: u64,
+    /// The signer of the commitment request.
+    pub signer: Address,
+    /// The UNIX timestamp (in seconds) at which the commitment was issued.
+    pub timestamp: u64,
+    /// The hashes of the committed transactions.
+    pub tx_hashes: Vec<TxHash>,
+    /// The priority fee expected at the time of the commitment, in wei.
+    pub expected_fee: u128,
+    /// The priority fee actually captured, in wei. `None` until reconciled.
+    pub captured_fee: Option<u128>,
+    /// The number of committed transactions that were included. `None` until reconciled.
+    pub included_txs: Option<usize>,
+}
+
+impl CommitmentFee {
+    /// Create a new unreconciled fee record for the given request,
+    /// estimating the expected priority fee at the given base fee.
+    pub fn new(request: &InclusionRequest, timestamp: u64, basefee: u128) -> Self {
+        Self {
+            digest: request.digest(),
+            slot: request.slot,
+            signer: request.signer.unwrap_or_default(),
+            timestamp,
+            tx_hashes: request.txs.iter().map(|tx| *tx.hash()).collect(),
+            expected_fee: request.txs.iter().map(|tx| expected_priority_fee(tx, basefee)).sum(),
+            captured_fee: None,
+            included_txs: None,
+        }
+    }
+
+    /// Returns true if the fee has been reconciled against the included block.
+    pub fn is_reconciled(&self) -> bool {
+        self.captured_fee.is_some()
+    }
+}
+
+/// A commitment fee record, with the finality of the block it was reconciled against.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AnnotatedFee {
+    /// The fee record.
+    #[serde(flatten)]
+    pub fee: CommitmentFee,
+    /// The finality of the reconciliation. `None` until reconciled.
+    pub finality: Option<FeeFinality>,
+}
+
+/// Returns the maximum priority fee (in wei) a transaction pays at the given base fee,
+/// assuming all of its gas limit is used.
+pub fn expected_priority_fee(tx: &FullTransaction, basefee: u128) -> u128 {
+    let max_fee = tx.max_fee_per_gas();
+    let tip = tx.max_priority_fee_per_gas().unwrap_or(max_fee).min(max_fee.saturating_sub(basefee));
+    tip.saturating_mul(tx.gas_limit() as u128)
+}
+
+/// The subset of a transaction receipt that is needed for fee reconciliation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeReceipt {
+    /// The hash of the transaction.
+    pub tx_hash: TxHash,
+    /// The gas used by the transaction.
+    pub gas_used: u128,
+    /// The effective gas price paid by the transaction, in wei.
+    pub effective_gas_price: u128,
+}
+
+impl From<&TransactionReceipt> for FeeReceipt {
+    fn from(receipt: &TransactionReceipt) -> Self {
+        Self {
+            tx_hash: receipt.transaction_hash,
+            gas_used: receipt.gas_used,
+            effective_gas_price: receipt.effective_gas_price,
+        }
+    }
+}
+
+/// Reconciles a commitment against the receipts of the block of its target slot,
+/// returning the captured priority fee and the number of included transactions.
+///
+/// Committed transactions that are missing from the receipts don't contribute any fee,
+/// so partially-included commitments are only credited for their included transactions.
+pub fn reconcile(fee: &CommitmentFee, receipts: &[FeeReceipt], basefee: u128) -> (u128, usize) {
+    fee.tx_hashes
+        .iter()
+        .filter_map(|hash| receipts.iter().find(|receipt| receipt.tx_hash == *hash))
+        .fold((0, 0), |(captured, included), receipt| {
+            let tip = receipt.effective_gas_price.saturating_sub(basefee);
+            (captured + tip.saturating_mul(receipt.gas_used), included + 1)
+        })
+}
+
+/// Returns the share of the committed transactions of the reconciled fees that weren't
+/// included, or `None` if there are fewer than `min_samples` of them.
+pub fn miss_rate(fees: &[CommitmentFee], min_samples: usize) -> Option<f64> {
+    let (committed, included) = fees
+        .iter()
+        .filter_map(|fee| Some((fee.tx_hashes.len(), fee.included_txs?)))
+        .fold((0, 0), |(committed, included), (txs, inc)| (committed + txs, included + inc));
+
+    if committed == 0 || committed < min_samples {
+        return None;
+    }
+
+    Some(committed.saturating_sub(included) as f64 / committed as f64)
+}
+
+/// Aggregated earnings over a time range. All amounts are in wei and
+/// only include reconciled commitments.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EarningsReport {
+    /// The start of the range (inclusive), as a UNIX timestamp in seconds.
+    pub from: u64,
+    /// The end of the range (exclusive), as a UNIX timestamp in seconds.
+    pub to: u64,
+    /// The total captured fees.
+    pub total: u128,
+    /// The number of reconciled commitments.
+    pub commitments: usize,
+    /// The number of commitments that were only partially included.
+    pub partially_included: usize,
+    /// Captured fees per target slot.
+    pub per_slot: BTreeMap<u64, u128>,
+    /// Captured fees per UTC day (formatted as `YYYY-MM-DD`).
+    pub per_day: BTreeMap<String, u128>,
+    /// Captured fees per request signer.
+    pub per_signer: BTreeMap<Address, u128>,
+}
+
+impl EarningsReport {
+    /// Aggregate the given fee records into a report for the range `[from, to)`.
+    /// Unreconciled records are skipped.
+    pub fn new(from: u64, to: u64, fees: &[CommitmentFee]) -> Self {
+        let mut report = Self { from, to, ..Default::default() };
+
+        for fee in fees {
+            let (Some(captured), Some(included)) = (fee.captured_fee, fee.included_txs) else {
+                continue;
+            };
+
+            report.total += captured;
+            report.commitments += 1;
+            if included > 0 && included < fee.tx_hashes.len() {
+                report.partially_included += 1;
+            }
+
+            *report.per_slot.entry(fee.slot).or_default() += captured;
+            *report.per_day.entry(format_day(fee.timestamp)).or_default() += captured;
+            *report.per_signer.entry(fee.signer).or_default() += captured;
+        }
+
+        report
+    }
+
+    /// Load the fee records in the range `[from, to)` from the store and aggregate them.
+    pub fn from_store(store: &Store, from: u64, to: u64) -> Result<Self, StoreError> {
+        Ok(Self::new(from, to, &store.fees_in_range(from, to)?))
+    }
+
+    /// Formats the report as CSV, with one row per aggregation bucket.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("kind,key,captured_wei\n");
+        csv.push_str(&format!("total,,{}\n", self.total));
+
+        for (slot, amount) in &self.per_slot {
+            csv.push_str(&format!("slot,{slot},{amount}\n"));
+        }
+        for (day, amount) in &self.per_day {
+            csv.push_str(&format!("day,{day},{amount}\n"));
+        }
+        for (signer, amount) in &self.per_signer {
+            csv.push_str(&format!("signer,{signer},{amount}\n"));
+        }
+
+        csv
+    }
+}
+
+/// Formats a UNIX timestamp (in seconds) as a UTC `YYYY-MM-DD` date.
+pub fn format_day(timestamp: u64) -> String {
+    // Civil from days algorithm, see <http://howardhinnant.github.io/date_algorithms.html>
+    let days = (timestamp / SECONDS_PER_DAY) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Parses a UTC `YYYY-MM-DD` date into the UNIX timestamp (in seconds) of its start.
+pub fn parse_day(date: &str) -> Option<u64> {
+    let mut parts = date.splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Days from civil algorithm, see <http://howardhinnant.github.io/date_algorithms.html>
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    u64::try_from(days).ok().map(|days| days * SECONDS_PER_DAY)
+}
+
+/// Records commitment fees and reconciles them once their target slot is proposed.
+#[derive(Debug, Clone)]
+pub struct EarningsTracker {
+    store: ResilientStore,
+    chain: Arc<dyn CanonicalChain>,
+    genesis_time: u64,
+    slot_time: u64,
+    alerts: Alerts,
+    max_miss_rate: f64,
+    finality_trigger: Finality,
+}
+
+impl EarningsTracker {
+    /// Create a new tracker that persists fees to `store` and
+    /// reconciles them against the blocks of the canonical `chain`.
+    pub fn new(
+        store: ResilientStore,
+        chain: Arc<dyn CanonicalChain>,
+        genesis_time: u64,
+        slot_time: u64,
+    ) -> Self {
+        Self {
+            store,
+            chain,
+            genesis_time,
+            slot_time,
+            alerts: Alerts::default(),
+            max_miss_rate: DEFAULT_MAX_MISS_RATE,
+            finality_trigger: Finality::Seen,
+        }
+    }
+
+    /// Raise alerts on store write failures, and when the share of committed transactions
+    /// missed from their slot over the last hour exceeds `max_miss_rate`.
+    pub fn with_alerts(self, alerts: Alerts, max_miss_rate: f64) -> Self {
+        Self { alerts, max_miss_rate, ..self }
+    }
+
+    /// Only account for the reconciliations that reached the given finality in the miss
+    /// rate, so that the alert isn't raised on a classification a reorg can overturn.
+    pub fn with_finality_trigger(self, finality_trigger: Finality) -> Self {
+        Self { finality_trigger, ..self }
+    }
+
+    /// Returns the store the tracker persists fees to.
+    pub fn store(&self) -> &Store {
+        self.store.store()
+    }
+
+    /// Record the expected fee of a new commitment, with the context of its request. This
+    /// is a critical write: the commitment must not be signed if it fails.
+    pub fn record(&self, fee: &CommitmentFee, context: Option<&str>) -> Result<(), StoreError> {
+        let result = self.store.record_commitment(fee, context);
+        match result {
+            Ok(()) => self.alerts.clear(AlertCondition::StoreWriteFailure),
+            Err(ref err) => {
+                error!(?err, digest = %fee.digest, "Failed to record commitment fee");
+                let message = format!("Failed to record commitment fee: {err}");
+                self.alerts.raise(AlertCondition::StoreWriteFailure, message);
+            }
+        }
+
+        result
+    }
+
+    /// Discard the fee record of a commitment that was recorded, but won't be issued.
+    pub fn discard(&self, digest: B256) {
+        self.store.write(NonCriticalWrite::DiscardCommitment(digest));
+    }
+
+    /// Reconcile all pending commitments for the given slot against its canonical block,
+    /// as seen.
+    ///
+    /// If no block was proposed in `slot` (i.e. the slot was missed), the pending
+    /// commitments are reconciled as not included.
+    pub async fn reconcile_slot(&self, slot: u64) -> eyre::Result<()> {
+        let pending = self.store().unreconciled_fees(slot)?;
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let block = self.chain.block_at_slot(slot).await?;
+        if block.is_none() {
+            debug!(slot, "No block was proposed in the target slot, slot was missed");
+        }
+
+        let block_hash = block.as_ref().map(|block| block.hash);
+        for fee in pending {
+            let (captured, included) = reconcile_block(&fee, block.as_ref());
+            self.store.write(NonCriticalWrite::ReconcileFee {
+                digest: fee.digest,
+                captured_fee: captured,
+                included_txs: included,
+                finality: FeeFinality { level: Finality::Seen, block_hash },
+            });
+            info!(slot, digest = %fee.digest, captured, included, "Reconciled commitment fee");
+        }
+
+        if self.finality_trigger == Finality::Seen {
+            self.check_miss_rate(self.slot_timestamp(slot))?;
+        }
+        Ok(())
+    }
+
+    /// Upgrade the finality of the reconciled commitments covered by the latest checkpoints.
+    ///
+    /// Before each upgrade, the block the commitment was reconciled against is checked to
+    /// still be the canonical block of its slot. If it was reorged out, the commitment is
+    /// reconciled again against the canonical one.
+    pub async fn update_finality(&self) -> eyre::Result<()> {
+        let pending = self.store().fees_pending_finality()?;
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let checkpoints = self.chain.checkpoints().await?;
+        let mut latest_upgrade = None;
+        for (fee, finality) in pending {
+            let level = checkpoints.finality(fee.slot);
+            if level <= finality.level {
+                continue;
+            }
+
+            let block = self.chain.block_at_slot(fee.slot).await?;
+            let block_hash = block.as_ref().map(|block| block.hash);
+            let (mut captured, mut included) =
+                (fee.captured_fee.unwrap_or_default(), fee.included_txs.unwrap_or_default());
+
+            if block_hash != finality.block_hash {
+                (captured, included) = reconcile_block(&fee, block.as_ref());
+                if (Some(captured), Some(included)) != (fee.captured_fee, fee.included_txs) {
+                    warn!(
+                        slot = fee.slot,
+                        digest = %fee.digest,
+                        previous = ?fee.included_txs,
+                        included,
+                        "Reconciled commitment fee changed after a reorg"
+                    );
+                }
+            }
+
+            self.store.write(NonCriticalWrite::ReconcileFee {
+                digest: fee.digest,
+                captured_fee: captured,
+                included_txs: included,
+                finality: FeeFinality { level, block_hash },
+            });
+            debug!(slot = fee.slot, digest = %fee.digest, %level, "Upgraded commitment finality");
+            latest_upgrade = Some(fee.slot);
+        }
+
+        match latest_upgrade {
+            Some(slot) => self.check_miss_rate(self.slot_timestamp(slot)),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the UNIX timestamp (in seconds) of the start of the slot.
+    fn slot_timestamp(&self, slot: u64) -> u64 {
+        self.genesis_time + slot * self.slot_time
+    }
+
+    /// Raises the miss-rate alert if too many of the transactions committed in the window
+    /// ending at `timestamp` were missed from their slot, and clears it otherwise. Only the
+    /// reconciliations that reached the finality trigger are accounted for.
+    fn check_miss_rate(&self, timestamp: u64) -> eyre::Result<()> {
+        let from = timestamp.saturating_sub(MISS_RATE_WINDOW_SECS);
+        let fees = self
+            .store()
+            .annotated_fees_in_range(from, timestamp.saturating_add(1))?
+            .into_iter()
+            .filter(|annotated| {
+                annotated.finality.is_some_and(|finality| finality.level >= self.finality_trigger)
+            })
+            .map(|annotated| annotated.fee)
+            .collect::<Vec<_>>();
+        let Some(rate) = miss_rate(&fees, MIN_MISS_RATE_SAMPLES) else {
+            return Ok(());
+        };
+
+        if rate > self.max_miss_rate {
+            let message = format!(
+                "{:.1}% of the committed transactions were missed over the last hour (max {:.1}%)",
+                rate * 100.0,
+                self.max_miss_rate * 100.0
+            );
+            self.alerts.raise(AlertCondition::MissRateBreached, message);
+        } else {
+            self.alerts.clear(AlertCondition::MissRateBreached);
+        }
+
+        Ok(())
+    }
+}
+
+/// Reconciles a commitment against the block of its target slot, or as not included if
+/// the slot was missed.
+fn reconcile_block(fee: &CommitmentFee, block: Option<&SlotBlock>) -> (u128, usize) {
+    block.map_or((0, 0), |block| reconcile(fee, &block.receipts, block.basefee))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use parking_lot::Mutex;
+
+    use super::*;
+    use crate::finality::Checkpoints;
+
+    /// A canonical chain whose checkpoints and blocks are set by the test.
+    #[derive(Debug, Default)]
+    struct ScriptedChain {
+        checkpoints: Mutex<Checkpoints>,
+        blocks: Mutex<HashMap<u64, SlotBlock>>,
+    }
+
+    #[async_trait::async_trait]
+    impl CanonicalChain for ScriptedChain {
+        async fn checkpoints(&self) -> eyre::Result<Checkpoints> {
+            Ok(*self.checkpoints.lock())
+        }
+
+        async fn block_at_slot(&self, slot: u64) -> eyre::Result<Option<SlotBlock>> {
+            Ok(self.blocks.lock().get(&slot).cloned())
+        }
+    }
+
+    /// Serves the router on a free local port, returning its URL.
+    #[cfg(feature = "webhooks")]
+    async fn serve(router: axum::Router) -> reqwest::Url {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap()).parse().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        url
+    }
+
+    fn fee(slot: u64, timestamp: u64, signer: Address, txs: usize) -> CommitmentFee {
+        CommitmentFee {
+            digest: B256::random(),
+            slot,
+            signer,
+            timestamp,
+            tx_hashes: (0..txs).map(|_| TxHash::random()).collect(),
+            expected_fee: 0,
+            captured_fee: None,
+            included_txs: None,
+        }
+    }
+
+    fn receipt(tx_hash: TxHash, gas_used: u128, effective_gas_price: u128) -> FeeReceipt {
+        FeeReceipt { tx_hash, gas_used, effective_gas_price }
+    }
+
+    #[test]
+    fn test_reconcile_partial_inclusion() {
+        let basefee = 10_000_000_000;
+        let commitment = fee(1, 0, Address::random(), 3);
+
+        // Only the first and last transactions were included, plus another unrelated one
+        let receipts = vec![
+            receipt(commitment.tx_hashes[0], 21_000, basefee + 2_000_000_000),
+            receipt(TxHash::random(), 50_000, basefee + 5_000_000_000),
+            receipt(commitment.tx_hashes[2], 30_000, basefee + 1_000_000_000),
+        ];
+
+        let (captured, included) = reconcile(&commitment, &receipts, basefee);
+        assert_eq!(included, 2);
+        assert_eq!(captured, 21_000 * 2_000_000_000 + 30_000 * 1_000_000_000);
+
+        // Nothing is captured if the commitment wasn't included at all
+        assert_eq!(reconcile(&commitment, &[], basefee), (0, 0));
+    }
+
+    #[test]
+    fn test_miss_rate() {
+        let mut fees = vec![fee(1, 0, Address::random(), 3), fee(2, 0, Address::random(), 1)];
+        fees[0].included_txs = Some(2);
+        fees[1].included_txs = Some(0);
+
+        assert_eq!(miss_rate(&fees, 4), Some(0.5));
+        assert_eq!(miss_rate(&fees, 5), None);
+
+        // Unreconciled fees are skipped
+        fees.push(fee(3, 0, Address::random(), 4));
+        assert_eq!(miss_rate(&fees, 4), Some(0.5));
+        assert_eq!(miss_rate(&[], 0), None);
+    }
+
+    #[test]
+    fn test_earnings_report() -> eyre::Result<()> {
+        let store = Store::in_memory()?;
+        let basefee = 1_000;
+
+        let alice = Address::random();
+        let bob = Address::random();
+
+        // 2024-01-01T00:00:00Z and 2024-01-02T00:00:00Z
+        let day_one = parse_day("2024-01-01").unwrap();
+        let day_two = parse_day("2024-01-02").unwrap();
+        assert_eq!(day_one, 1_704_067_200);
+        assert_eq!(day_two - day_one, SECONDS_PER_DAY);
+
+        let full = fee(100, day_one + 10, alice, 1);
+        let partial = fee(100, day_one + 20, bob, 2);
+        let next_day = fee(200, day_two + 5, alice, 1);
+        let pending = fee(300, day_two + 10, bob, 1);
+
+        let receipts = vec![
+            receipt(full.tx_hashes[0], 21_000, basefee + 3),
+            receipt(partial.tx_hashes[1], 10_000, basefee + 2),
+            receipt(next_day.tx_hashes[0], 21_000, basefee + 1),
+        ];
+
+        for fee in [&full, &partial, &next_day, &pending] {
+            store.insert_commitment_fee(fee, None)?;
+        }
+
+        for fee in [&full, &partial, &next_day] {
+            let (captured, included) = reconcile(fee, &receipts, basefee);
+            store.set_reconciled_fee(&fee.digest, captured, included, &FeeFinality::default())?;
+        }
+
+        let report = EarningsReport::from_store(&store, day_one, day_two + SECONDS_PER_DAY)?;
+
+        assert_eq!(report.commitments, 3);
+        assert_eq!(report.partially_included, 1);
+        assert_eq!(report.total, 63_000 + 20_000 + 21_000);
+        assert_eq!(report.per_slot[&100], 83_000);
+        assert_eq!(report.per_slot[&200], 21_000);
+        assert!(!report.per_slot.contains_key(&300));
+        assert_eq!(report.per_day["2024-01-01"], 83_000);
+        assert_eq!(report.per_day["2024-01-02"], 21_000);
+        assert_eq!(report.per_signer[&alice], 84_000);
+        assert_eq!(report.per_signer[&bob], 20_000);
+
+        let csv = report.to_csv();
+        assert!(csv.starts_with("kind,key,captured_wei\ntotal,,104000\n"));
+        assert!(csv.contains("day,2024-01-02,21000\n"));
+
+        // The range end is exclusive
+        let report = EarningsReport::from_store(&store, day_one, day_two)?;
+        assert_eq!(report.total, 83_000);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "webhooks")]
+    #[tokio::test]
+    async fn test_finality_annotations_across_reorg() -> eyre::Result<()> {
+        use std::time::Duration;
+
+        use axum::{routing::post, Json, Router};
+        use serde_json::Value;
+
+        use crate::alerts::AlertConfig;
+
+        // A webhook that records the alerts it receives
+        let received = Arc::new(Mutex::new(Vec::<Value>::new()));
+        let sink_received = received.clone();
+        let sink = Router::new().route(
+            "/alerts",
+            post(move |Json(alert): Json<Value>| {
+                sink_received.lock().push(alert);
+                async {}
+            }),
+        );
+        let webhook_url = serve(sink).await.join("alerts")?;
+        let config = AlertConfig { webhook_url: Some(webhook_url), ..Default::default() };
+
+        let chain = Arc::new(ScriptedChain::default());
+        let store = ResilientStore::new(Store::in_memory()?);
+        let tracker = EarningsTracker::new(store, chain.clone(), 0, 12)
+            .with_alerts(Alerts::spawn(&config, "test"), 0.1)
+            .with_finality_trigger(Finality::Justified);
+
+        let (early, late) = (fee(1, 0, Address::random(), 16), fee(40, 0, Address::random(), 1));
+        for fee in [&early, &late] {
+            tracker.record(fee, Some("test"))?;
+            let receipts = fee.tx_hashes.iter().map(|hash| receipt(*hash, 21_000, 2)).collect();
+            let block = SlotBlock { hash: B256::random(), number: 1, receipts, basefee: 1 };
+            chain.blocks.lock().insert(fee.slot, block);
+            tracker.reconcile_slot(fee.slot).await?;
+        }
+
+        let finality = |fee: &CommitmentFee| -> eyre::Result<(Finality, Option<usize>)> {
+            let annotated = tracker.store().fees_by_context("test")?;
+            let annotated = annotated.into_iter().find(|a| a.fee.digest == fee.digest).unwrap();
+            Ok((annotated.finality.unwrap().level, annotated.fee.included_txs))
+        };
+        assert_eq!(finality(&early)?, (Finality::Seen, Some(16)));
+
+        // The block of the early slot is reorged out before it is justified: the seen
+        // reconciliation is kept until the slot is justified
+        chain.blocks.lock().remove(&early.slot);
+        tracker.update_finality().await?;
+        assert_eq!(finality(&early)?, (Finality::Seen, Some(16)));
+
+        // Once justified, the commitment is reclassified as missed, which breaches the SLO
+        *chain.checkpoints.lock() = Checkpoints { justified_epoch: 1, finalized_epoch: 0 };
+        tracker.update_finality().await?;
+        assert_eq!(finality(&early)?, (Finality::Justified, Some(0)));
+        assert_eq!(finality(&late)?, (Finality::Seen, Some(1)));
+
+        *chain.checkpoints.lock() = Checkpoints { justified_epoch: 1, finalized_epoch: 1 };
+        tracker.update_finality().await?;
+        assert_eq!(finality(&early)?, (Finality::Finalized, Some(0)));
+        assert_eq!(finality(&late)?, (Finality::Seen, Some(1)));
+        assert_eq!(tracker.store().fees_pending_finality()?.len(), 1);
+
+        // The alert was only delivered once the reclassification was justified
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let received = received.lock();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0]["condition"], AlertCondition::MissRateBreached.to_string());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_day_roundtrip() {
+        for date in ["1970-01-01", "2000-02-29", "2024-12-31"] {
+            assert_eq!(format_day(parse_day(date).unwrap()), date);
+        }
+        assert_eq!(parse_day("2024-13-01"), None);
+        assert_eq!(parse_day("not-a-date"), None);
+    }
+}
@@ -0,0 +1,264 @@
+//! Finality of the reconciled commitments.
+//!
+//! A commitment is reconciled as soon as the block of its target slot is seen, but that
+//! block can still be reorged out. The reconciliation is annotated with its [`Finality`],
+//! which is upgraded as the consensus client reports justified and finalized checkpoints.
+//! Before each upgrade, the block the commitment was reconciled against is checked to still
+//! be canonical: if it was reorged out, the commitment is reconciled again against the
+//! canonical block of the slot.
+
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use alloy::{consensus::TxEnvelope, eips::eip2718::Encodable2718, primitives::B256};
+use ethereum_consensus::phase0::mainnet::SLOTS_PER_EPOCH;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::{earnings::FeeReceipt, primitives::PayloadTransactions, RpcClient};
+
+/// Path of the beacon API endpoint returning the finality checkpoints of the head state.
+const FINALITY_CHECKPOINTS_PATH: &str = "eth/v1/beacon/states/head/finality_checkpoints";
+
+/// How final the block a commitment was reconciled against is, from the least to the most.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Finality {
+    /// The block was seen, and may still be reorged out.
+    #[default]
+    Seen,
+    /// The block is part of a justified checkpoint.
+    Justified,
+    /// The block is part of a finalized checkpoint, and can't be reorged out anymore.
+    Finalized,
+}
+
+impl Finality {
+    /// Returns the finality stored with the given integer, as written by [`Self::as_u8`].
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Seen),
+            1 => Some(Self::Justified),
+            2 => Some(Self::Finalized),
+            _ => None,
+        }
+    }
+
+    /// Returns the integer the finality is stored as, increasing with finality.
+    pub fn as_u8(self) -> u8 {
+        self as u8
+    }
+}
+
+impl FromStr for Finality {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "seen" => Ok(Self::Seen),
+            "justified" => Ok(Self::Justified),
+            "finalized" => Ok(Self::Finalized),
+            other => Err(format!(
+                "Unknown finality '{other}', expected 'seen', 'justified' or 'finalized'"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Finality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Seen => write!(f, "seen"),
+            Self::Justified => write!(f, "justified"),
+            Self::Finalized => write!(f, "finalized"),
+        }
+    }
+}
+
+/// The finality annotation of a reconciled commitment.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeFinality {
+    /// The finality of the block the commitment was reconciled against.
+    pub level: Finality,
+    /// The hash of the execution block of the target slot, or `None` if the slot was missed.
+    pub block_hash: Option<B256>,
+}
+
+/// The latest justified and finalized checkpoints reported by the consensus client.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Checkpoints {
+    /// The epoch of the current justified checkpoint.
+    pub justified_epoch: u64,
+    /// The epoch of the finalized checkpoint.
+    pub finalized_epoch: u64,
+}
+
+impl Checkpoints {
+    /// Returns the finality of the block of a slot: the checkpoint of an epoch covers its
+    /// first slot and all the earlier ones.
+    pub fn finality(&self, slot: u64) -> Finality {
+        if slot <= self.finalized_epoch * SLOTS_PER_EPOCH {
+            Finality::Finalized
+        } else if slot <= self.justified_epoch * SLOTS_PER_EPOCH {
+            Finality::Justified
+        } else {
+            Finality::Seen
+        }
+    }
+}
+
+/// The canonical execution block of a slot, with what's needed for fee reconciliation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotBlock {
+    /// The hash of the block.
+    pub hash: B256,
+    /// The number of the block.
+    pub number: u64,
+    /// The receipts of the transactions of the block.
+    pub receipts: Vec<FeeReceipt>,
+    /// The base fee of the block.
+    pub basefee: u128,
+}
+
+/// The view of the canonical chain the commitments are reconciled against.
+#[async_trait::async_trait]
+pub trait CanonicalChain: fmt::Debug + Send + Sync {
+    /// Returns the latest justified and finalized checkpoints.
+    async fn checkpoints(&self) -> eyre::Result<Checkpoints>;
+
+    /// Returns the canonical block proposed in the slot, or `None` if the slot was missed.
+    async fn block_at_slot(&self, slot: u64) -> eyre::Result<Option<SlotBlock>>;
+
+    /// Returns the transactions of the execution payload of the block, or `None` if they
+    /// aren't available.
+    async fn payload_transactions(
+        &self,
+        _block: &SlotBlock,
+    ) -> eyre::Result<Option<PayloadTransactions>> {
+        Ok(None)
+    }
+}
+
+/// The canonical chain of the consensus and execution clients of the sidecar.
+#[derive(Debug, Clone)]
+pub struct ClientChain {
+    http: reqwest::Client,
+    beacon_api_url: Url,
+    execution: RpcClient,
+    genesis_time: u64,
+    slot_time: u64,
+}
+
+impl ClientChain {
+    /// Creates a view of the canonical chain of the given clients.
+    pub fn new(
+        beacon_api_url: Url,
+        execution: RpcClient,
+        genesis_time: u64,
+        slot_time: u64,
+    ) -> Self {
+        Self { http: reqwest::Client::new(), beacon_api_url, execution, genesis_time, slot_time }
+    }
+}
+
+#[async_trait::async_trait]
+impl CanonicalChain for ClientChain {
+    async fn checkpoints(&self) -> eyre::Result<Checkpoints> {
+        #[derive(Deserialize)]
+        struct Checkpoint {
+            epoch: String,
+        }
+        #[derive(Deserialize)]
+        struct Data {
+            current_justified: Checkpoint,
+            finalized: Checkpoint,
+        }
+        #[derive(Deserialize)]
+        struct Response {
+            data: Data,
+        }
+
+        let url = self.beacon_api_url.join(FINALITY_CHECKPOINTS_PATH)?;
+        let response: Response =
+            self.http.get(url).send().await?.error_for_status()?.json().await?;
+        Ok(Checkpoints {
+            justified_epoch: response.data.current_justified.epoch.parse()?,
+            finalized_epoch: response.data.finalized.epoch.parse()?,
+        })
+    }
+
+    async fn block_at_slot(&self, slot: u64) -> eyre::Result<Option<SlotBlock>> {
+        let timestamp = self.genesis_time + slot * self.slot_time;
+        let latest = self.execution.get_block(None, false).await?;
+        if latest.header.timestamp < timestamp {
+            return Ok(None);
+        }
+
+        // There is at most one block per slot, so the block of the slot is at most as many
+        // blocks behind the latest one as there are slots in between
+        let latest_number = latest.header.number.unwrap_or_default();
+        let behind = (latest.header.timestamp - timestamp) / self.slot_time;
+        let (mut low, mut high) = (latest_number.saturating_sub(behind), latest_number);
+
+        while low <= high {
+            let number = low + (high - low) / 2;
+            let block = self.execution.get_block(Some(number), false).await?;
+            match block.header.timestamp.cmp(&timestamp) {
+                Ordering::Less => low = number + 1,
+                Ordering::Greater if number == 0 => break,
+                Ordering::Greater => high = number - 1,
+                Ordering::Equal => {
+                    let receipts = self.execution.get_block_receipts(Some(number)).await?;
+                    return Ok(Some(SlotBlock {
+                        hash: block.header.hash.unwrap_or_default(),
+                        number,
+                        receipts: receipts.iter().map(FeeReceipt::from).collect(),
+                        basefee: block.header.base_fee_per_gas.unwrap_or_default(),
+                    }));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn payload_transactions(
+        &self,
+        block: &SlotBlock,
+    ) -> eyre::Result<Option<PayloadTransactions>> {
+        let full = self.execution.get_block(Some(block.number), true).await?;
+        // The block was reorged out in the meantime
+        if full.header.hash != Some(block.hash) {
+            return Ok(None);
+        }
+        let Some(transactions) = full.transactions.as_transactions() else {
+            return Ok(None);
+        };
+
+        let encoded = transactions.iter().map(|tx| {
+            let envelope = TxEnvelope::try_from(tx.clone())?;
+            Ok((tx.hash, envelope.encoded_2718()))
+        });
+        let encoded = encoded.collect::<eyre::Result<Vec<_>>>()?;
+        Ok(Some(PayloadTransactions::new(block.hash, encoded)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_finality() {
+        let checkpoints = Checkpoints { justified_epoch: 3, finalized_epoch: 2 };
+        assert_eq!(checkpoints.finality(2 * SLOTS_PER_EPOCH), Finality::Finalized);
+        assert_eq!(checkpoints.finality(2 * SLOTS_PER_EPOCH + 1), Finality::Justified);
+        assert_eq!(checkpoints.finality(3 * SLOTS_PER_EPOCH + 1), Finality::Seen);
+
+        for finality in [Finality::Seen, Finality::Justified, Finality::Finalized] {
+            assert_eq!(finality.to_string().parse::<Finality>().unwrap(), finality);
+            assert_eq!(Finality::from_u8(finality.as_u8()), Some(finality));
+        }
+    }
+}
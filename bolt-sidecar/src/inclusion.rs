@@ -0,0 +1,920 @@
+//! Inclusion status of the issued commitments, queried with `bolt_getCommitmentStatus`.
+//!
+//! The driver indexes every commitment it issues by digest and by commitment id, as pending.
+//! Once the chain head reaches its target slot, the canonical block of the slot is checked for
+//! the committed transactions, and the commitment is marked as included in that block, or as
+//! failed. The status is the one seen at the head, and isn't revised after a reorg: the
+//! finality of the inclusions is tracked by the [`earnings`](crate::earnings) instead.
+//!
+//! The status of an included commitment carries the Merkle proofs of its transactions against
+//! the transactions root of the block, see [`InclusionProof`]. They are built from the payload
+//! of the local builder if the block is the one it built, and from the transactions of the
+//! block otherwise.
+//!
+//! Commitments cancelled by the signer of their request with `bolt_cancelInclusion` aren't
+//! resolved, and stay cancelled.
+//!
+//! Only the commitments whose constraints were submitted to the relays are resolved against
+//! the chain. The others expire once their target slot has passed, with the reason they
+//! couldn't be honored: see [`ExpiryReason`].
+//!
+//! Requests for a range of slots with no proposer duty yet are tracked as pending under the
+//! digest of the request until they are committed to, or expire with their range. Their
+//! commitment names the slot it targets, so its digest differs from the one of the request:
+//! the status can be queried with either.
+//!
+//! Commitments are forgotten a configurable number of slots after their target slot, after
+//! which their status is unknown.
+//!
+//! With a store, every commitment is recorded with its raw transactions before it is returned,
+//! and its status is persisted as it changes. After a restart, the commitments within the
+//! retention are restored with their status, and the pending ones are returned to the driver
+//! with their constraints, see [`InclusionTracker::restore`].
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+};
+
+use alloy::primitives::{Address, TxHash, B256};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::{
+    crypto::bls::BLSSig,
+    db::{CommitmentStore, NonCriticalWrite, ResilientStore, StoreError},
+    finality::{CanonicalChain, SlotBlock},
+    primitives::{
+        commitment::{commitment_id, SignedCommitment},
+        ConstraintsMessage, InclusionProof, PayloadTransactions, SignedConstraints, Slot,
+    },
+};
+
+/// Default number of slots the commitments are tracked for after their target slot.
+pub const DEFAULT_STATUS_RETENTION_SLOTS: u64 = 64;
+
+/// The inclusion status of a commitment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum CommitmentStatus {
+    /// The head of the chain hasn't reached the target slot yet.
+    Pending,
+    /// All the committed transactions were included in the block of the target slot.
+    Included {
+        /// The hash of the block.
+        block_hash: B256,
+        /// The number of the block.
+        block_number: u64,
+        /// The proofs of inclusion of the transactions in the block, if they could be built.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        proof: Option<InclusionProof>,
+    },
+    /// The committed transactions weren't all included in the target slot.
+    Failed {
+        /// Why the commitment wasn't honored.
+        reason: String,
+    },
+    /// The commitment was cancelled by the signer of its request.
+    Cancelled,
+    /// The target slot passed without the constraints of the commitment being submitted, or
+    /// the request targeted a range of slots that passed before it could be committed to.
+    Expired {
+        /// Why the commitment couldn't be honored.
+        reason: ExpiryReason,
+    },
+    /// No commitment was issued, or it was forgotten since.
+    Unknown,
+}
+
+/// Why a commitment expired without being resolved against the chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpiryReason {
+    /// The target slot passed before the constraints were submitted.
+    SlotPassed,
+    /// The constraints couldn't be submitted to the relays.
+    SubmissionFailed,
+    /// Our validator is no longer the proposer of the target slot, or another instance holds
+    /// its lease.
+    DutyLost,
+}
+
+/// The record of an issued commitment in the store, from which it is restored with its
+/// constraints after a restart.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IssuedRecord {
+    /// The digest of the commitment request.
+    pub digest: B256,
+    /// The target slot of the commitment.
+    pub slot: Slot,
+    /// The signer of the commitment request.
+    pub signer: Option<Address>,
+    /// The index of the validator the constraints were signed for.
+    pub validator_index: u64,
+    /// The JSON encoding of the signed commitment, with the raw transactions of its request.
+    pub commitment: Vec<u8>,
+    /// The signature of the constraints of the commitment.
+    pub constraints_signature: BLSSig,
+}
+
+impl IssuedRecord {
+    /// Creates the record of a commitment issued to the signer of its request, with its
+    /// signed constraints.
+    pub fn new(
+        digest: B256,
+        signer: Option<Address>,
+        commitment: &SignedCommitment,
+        constraints: &SignedConstraints,
+    ) -> Result<Self, serde_json::Error> {
+        Ok(Self {
+            digest,
+            slot: constraints.message.slot,
+            signer,
+            validator_index: constraints.message.validator_index,
+            commitment: serde_json::to_vec(commitment)?,
+            constraints_signature: constraints.signature,
+        })
+    }
+
+    /// Rebuilds the signed constraints of the commitment from its request, with the senders
+    /// of their transactions recovered.
+    pub fn signed_constraints(&self) -> eyre::Result<SignedConstraints> {
+        let commitment: SignedCommitment = serde_json::from_slice(&self.commitment)?;
+        let mut request = commitment
+            .into_inclusion_request()
+            .ok_or_else(|| eyre::eyre!("Not an inclusion commitment"))?;
+        request.recover_signers()?;
+
+        let message = ConstraintsMessage::build(self.validator_index, request);
+        Ok(SignedConstraints { message, signature: self.constraints_signature })
+    }
+}
+
+/// A commitment issued by the driver.
+#[derive(Debug, Clone)]
+struct IssuedCommitment {
+    slot: Slot,
+    signer: Option<Address>,
+    tx_hashes: Vec<TxHash>,
+    status: CommitmentStatus,
+    /// Whether this is a request for a range of slots that wasn't committed to yet, whose
+    /// slot is the last of its range.
+    deferred: bool,
+}
+
+/// The issued commitments, by digest, by commitment id and by committed transaction.
+#[derive(Debug, Default)]
+struct Index {
+    commitments: HashMap<B256, IssuedCommitment>,
+    ids: HashMap<B256, B256>,
+    transactions: HashMap<(TxHash, Slot), B256>,
+    /// The digests of the commitments to slot-range requests, by digest of the request.
+    ranges: HashMap<B256, B256>,
+    /// The transactions of the payloads built by the local builder, by slot.
+    payloads: HashMap<Slot, PayloadTransactions>,
+    /// The slots whose constraints were submitted to the relays.
+    submitted: BTreeSet<Slot>,
+    /// The slots whose commitments can't be honored anymore, with the reason.
+    lapsed: HashMap<Slot, ExpiryReason>,
+}
+
+impl Index {
+    /// Returns the target slots of the pending commitments, up to the given one.
+    fn pending_slots(&self, head: Slot) -> BTreeSet<Slot> {
+        self.commitments
+            .values()
+            .filter(|issued| issued.slot <= head && issued.is_resolvable())
+            .filter(|issued| !self.lapsed.contains_key(&issued.slot))
+            .map(|issued| issued.slot)
+            .collect()
+    }
+
+    /// Marks the pending commitments matching the filter as expired, with the reason their
+    /// slot lapsed if any. Returns their digests and the reason they expired.
+    fn expire(&mut self, filter: impl Fn(&IssuedCommitment) -> bool) -> Vec<(B256, ExpiryReason)> {
+        let mut expired = Vec::new();
+        for (digest, issued) in self.commitments.iter_mut() {
+            if issued.status != CommitmentStatus::Pending || !filter(issued) {
+                continue;
+            }
+
+            let reason = self.lapsed.get(&issued.slot).copied();
+            let reason = reason.filter(|_| !issued.deferred).unwrap_or(ExpiryReason::SlotPassed);
+            issued.status = CommitmentStatus::Expired { reason };
+            issued.deferred = false;
+            expired.push((*digest, reason));
+        }
+        expired
+    }
+}
+
+impl IssuedCommitment {
+    /// Returns true if the commitment is pending against the block of its slot.
+    fn is_resolvable(&self) -> bool {
+        self.status == CommitmentStatus::Pending && !self.deferred
+    }
+}
+
+/// The write side of the inclusion statuses, owned by the driver. Cheap to clone.
+#[derive(Debug, Clone)]
+pub struct InclusionTracker {
+    index: Arc<RwLock<Index>>,
+    chain: Arc<dyn CanonicalChain>,
+    retention_slots: u64,
+    store: Option<ResilientStore>,
+}
+
+impl InclusionTracker {
+    /// Create a tracker checking the commitments against the given chain, and forgetting them
+    /// `retention_slots` after their target slot. Returns the reader handle to share with
+    /// the API.
+    pub fn new(chain: Arc<dyn CanonicalChain>, retention_slots: u64) -> (Self, InclusionReader) {
+        let index = Arc::new(RwLock::new(Index::default()));
+        let tracker = Self { index: index.clone(), chain, retention_slots, store: None };
+        (tracker, InclusionReader(index))
+    }
+
+    /// Persist the issued commitments and their status to the store, to restore them after
+    /// a restart.
+    pub fn with_store(self, store: ResilientStore) -> Self {
+        Self { store: Some(store), ..self }
+    }
+
+    /// Record a commitment about to be issued in the store. This is a critical write: the
+    /// commitment must not be issued if it fails. Does nothing without a store.
+    pub fn record(&self, record: &IssuedRecord) -> Result<(), StoreError> {
+        match self.store {
+            Some(ref store) => store.record_issued(record),
+            None => Ok(()),
+        }
+    }
+
+    /// Index a commitment issued to the signer for the given transactions, as pending.
+    pub fn issue(&self, digest: B256, slot: Slot, signer: Option<Address>, tx_hashes: Vec<TxHash>) {
+        self.insert(digest, slot, signer, tx_hashes, CommitmentStatus::Pending);
+    }
+
+    /// Index a commitment issued to the signer with the given status.
+    fn insert(
+        &self,
+        digest: B256,
+        slot: Slot,
+        signer: Option<Address>,
+        tx_hashes: Vec<TxHash>,
+        status: CommitmentStatus,
+    ) {
+        let mut index = self.index.write();
+        for hash in &tx_hashes {
+            index.transactions.insert((*hash, slot), digest);
+        }
+        index.ids.insert(commitment_id(digest, slot), digest);
+        let issued = IssuedCommitment { slot, signer, tx_hashes, status, deferred: false };
+        index.commitments.insert(digest, issued);
+    }
+
+    /// Restore the commitments recorded in the store before a restart, with their status,
+    /// unless they are past the retention. Returns the pending ones targeting a slot after
+    /// `current_slot`, with their constraints, to add back to the block templates.
+    ///
+    /// The pending commitments of the earlier slots are resolved against the chain, as their
+    /// constraints may have been submitted before the restart.
+    pub fn restore(
+        &self,
+        current_slot: Slot,
+    ) -> Result<Vec<(IssuedRecord, SignedConstraints)>, StoreError> {
+        let Some(ref store) = self.store else { return Ok(Vec::new()) };
+
+        let oldest = current_slot.saturating_sub(self.retention_slots);
+        let mut pending = Vec::new();
+        for (record, status) in store.store().issued_commitments(oldest)? {
+            let constraints = match record.signed_constraints() {
+                Ok(constraints) => constraints,
+                Err(err) => {
+                    warn!(?err, digest = %record.digest, "Failed to restore issued commitment");
+                    continue;
+                }
+            };
+
+            let tx_hashes = constraints.message.constraints.iter();
+            let tx_hashes = tx_hashes.map(|constraint| *constraint.transaction.hash()).collect();
+            let is_pending = status == CommitmentStatus::Pending;
+            self.insert(record.digest, record.slot, record.signer, tx_hashes, status);
+            if is_pending && record.slot > current_slot {
+                pending.push((record, constraints));
+            } else if is_pending {
+                self.index.write().submitted.insert(record.slot);
+            }
+        }
+        Ok(pending)
+    }
+
+    /// Persist the current status of the given commitments, if the tracker has a store.
+    fn persist(&self, digests: impl IntoIterator<Item = B256>) {
+        let Some(ref store) = self.store else { return };
+
+        let statuses = {
+            let index = self.index.read();
+            let statuses = digests.into_iter().filter_map(|digest| {
+                index.commitments.get(&digest).map(|issued| (digest, issued.status.clone()))
+            });
+            statuses.collect::<Vec<_>>()
+        };
+        for (digest, status) in statuses {
+            store.write(NonCriticalWrite::SetCommitmentStatus { digest, status });
+        }
+    }
+
+    /// Index a request of the signer for a range of slots ending at `max_slot` as pending,
+    /// until it is committed to or expires.
+    pub fn defer(&self, digest: B256, max_slot: Slot, signer: Option<Address>) {
+        let status = CommitmentStatus::Pending;
+        let deferred = IssuedCommitment {
+            slot: max_slot,
+            signer,
+            tx_hashes: Vec::new(),
+            status,
+            deferred: true,
+        };
+        self.index.write().commitments.entry(digest).or_insert(deferred);
+    }
+
+    /// Index the commitment to a request for a range of slots under the digest of the
+    /// request, replacing the deferred request if any.
+    pub fn alias(&self, requested: B256, committed: B256) {
+        if requested == committed {
+            return;
+        }
+
+        let mut index = self.index.write();
+        if index.commitments.get(&requested).is_some_and(|issued| issued.deferred) {
+            index.commitments.remove(&requested);
+        }
+        index.ranges.insert(requested, committed);
+    }
+
+    /// Returns the digest of the commitment issued for the request with the given digest,
+    /// which differs for the requests for a range of slots.
+    pub fn commitment_digest(&self, digest: &B256) -> B256 {
+        self.index.read().ranges.get(digest).copied().unwrap_or(*digest)
+    }
+
+    /// Marks a deferred request as expired, once its range has passed. Returns false if it
+    /// isn't deferred.
+    pub fn expire(&self, digest: &B256) -> bool {
+        let mut index = self.index.write();
+        match index.commitments.get_mut(digest) {
+            Some(issued) if issued.deferred => {
+                issued.status = CommitmentStatus::Expired { reason: ExpiryReason::SlotPassed };
+                issued.deferred = false;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the target slot, the request signer and the status of an issued commitment.
+    pub fn issued(&self, digest: &B256) -> Option<(Slot, Option<Address>, CommitmentStatus)> {
+        let index = self.index.read();
+        let issued = index.commitments.get(digest)?;
+        Some((issued.slot, issued.signer, issued.status.clone()))
+    }
+
+    /// Records the transactions of the payload built by the local builder for the slot, to
+    /// prove the inclusion of the commitments if the block is the one proposed.
+    pub fn record_payload(&self, slot: Slot, payload: PayloadTransactions) {
+        self.index.write().payloads.insert(slot, payload);
+    }
+
+    /// Records the outcome of the submission of the constraints of the slot to the relays.
+    /// The commitments of a slot whose submission failed aren't resolved, and expire once
+    /// the slot has passed.
+    pub fn record_submission(&self, slot: Slot, submitted: bool) {
+        let mut index = self.index.write();
+        if submitted {
+            index.submitted.insert(slot);
+        } else {
+            index.lapsed.entry(slot).or_insert(ExpiryReason::SubmissionFailed);
+        }
+    }
+
+    /// Marks the pending commitments of a slot whose proposer duty was lost as expired.
+    /// Returns their digests.
+    pub fn lose_duty(&self, slot: Slot) -> Vec<B256> {
+        let expired = {
+            let mut index = self.index.write();
+            index.lapsed.insert(slot, ExpiryReason::DutyLost);
+            index.expire(|issued| issued.slot == slot && !issued.deferred)
+        };
+        let expired = expired.into_iter().map(|(digest, _)| digest).collect::<Vec<_>>();
+        self.persist(expired.iter().copied());
+        expired
+    }
+
+    /// Marks the pending commitments of the slots before the given one as expired, unless
+    /// their constraints were submitted. Returns their digests and the reason they expired.
+    ///
+    /// This runs at the start of each slot, and covers the deferred requests as well.
+    pub fn expire_passed(&self, slot: Slot) -> Vec<(B256, ExpiryReason)> {
+        let expired = {
+            let mut index = self.index.write();
+            let submitted = index.submitted.clone();
+            index.expire(|issued| {
+                issued.slot < slot && (issued.deferred || !submitted.contains(&issued.slot))
+            })
+        };
+        self.persist(expired.iter().map(|(digest, _)| *digest));
+        expired
+    }
+
+    /// Marks a pending commitment as cancelled. Returns false if it isn't pending.
+    pub fn cancel(&self, digest: &B256) -> bool {
+        let cancelled = match self.index.write().commitments.get_mut(digest) {
+            Some(issued) if issued.status == CommitmentStatus::Pending => {
+                issued.status = CommitmentStatus::Cancelled;
+                true
+            }
+            _ => false,
+        };
+        if cancelled {
+            self.persist([*digest]);
+        }
+        cancelled
+    }
+
+    /// Resolve the pending commitments up to the slot of the new chain head against the
+    /// canonical blocks of their slots, and forget the ones past the retention.
+    ///
+    /// The slots missed since the previous head are resolved as well, as no head is
+    /// received for them.
+    pub async fn observe(&self, head: Slot) -> eyre::Result<()> {
+        let slots = self.index.read().pending_slots(head);
+        for slot in slots {
+            let block = self.chain.block_at_slot(slot).await?;
+
+            let (mut resolved, mut included) = (Vec::new(), Vec::new());
+            for (digest, issued) in self.index.write().commitments.iter_mut() {
+                if issued.slot == slot && issued.is_resolvable() {
+                    issued.status = resolve(&issued.tx_hashes, block.as_ref());
+                    debug!(slot, %digest, status = ?issued.status, "Resolved commitment status");
+                    if matches!(issued.status, CommitmentStatus::Included { .. }) {
+                        included.push(*digest);
+                    }
+                    resolved.push(*digest);
+                }
+            }
+
+            if let Some(block) = block.filter(|_| !included.is_empty()) {
+                self.prove(slot, &block, &included).await;
+            }
+            self.persist(resolved);
+        }
+
+        self.prune(head);
+        Ok(())
+    }
+
+    /// Attaches the proofs of inclusion of their transactions to the commitments included in
+    /// the block of the slot. The proofs are missing if the transactions of the block can't
+    /// be fetched.
+    async fn prove(&self, slot: Slot, block: &SlotBlock, digests: &[B256]) {
+        let built = self.index.read().payloads.get(&slot).cloned();
+        let payload = match built.filter(|payload| payload.block_hash() == block.hash) {
+            Some(payload) => payload,
+            None => match self.chain.payload_transactions(block).await {
+                Ok(Some(payload)) => payload,
+                Ok(None) => return,
+                Err(err) => {
+                    warn!(?err, slot, "Failed to fetch the transactions of the block to prove");
+                    return;
+                }
+            },
+        };
+
+        let mut index = self.index.write();
+        for digest in digests {
+            let Some(issued) = index.commitments.get_mut(digest) else { continue };
+            if let CommitmentStatus::Included { ref mut proof, .. } = issued.status {
+                *proof = payload.prove(&issued.tx_hashes);
+            }
+        }
+    }
+
+    /// Forget the commitments whose target slot is more than the retention behind the head.
+    fn prune(&self, head: Slot) {
+        let oldest = head.saturating_sub(self.retention_slots);
+        if let Some(ref store) = self.store {
+            store.write(NonCriticalWrite::PruneCommitments { before: oldest });
+        }
+
+        let mut index = self.index.write();
+        let index = &mut *index;
+        index.commitments.retain(|_, issued| issued.slot >= oldest);
+        index.ids.retain(|_, digest| index.commitments.contains_key(digest));
+        index.ranges.retain(|_, digest| index.commitments.contains_key(digest));
+        index.transactions.retain(|(_, slot), _| *slot >= oldest);
+        index.payloads.retain(|slot, _| *slot >= oldest);
+        index.submitted.retain(|slot| *slot >= oldest);
+        index.lapsed.retain(|slot, _| *slot >= oldest);
+    }
+}
+
+/// The read side of the inclusion statuses. Cheap to clone.
+#[derive(Debug, Clone, Default)]
+pub struct InclusionReader(Arc<RwLock<Index>>);
+
+impl InclusionReader {
+    /// Returns the status of the commitment with the given request digest, or with the
+    /// digest of the slot-range request it was issued for.
+    pub fn status(&self, digest: &B256) -> CommitmentStatus {
+        let index = self.0.read();
+        let digest = index.ranges.get(digest).unwrap_or(digest);
+        let issued = index.commitments.get(digest);
+        issued.map_or(CommitmentStatus::Unknown, |issued| issued.status.clone())
+    }
+
+    /// Returns the request digest of the commitment with the given id, if it is tracked.
+    pub fn digest_of(&self, commitment_id: &B256) -> Option<B256> {
+        self.0.read().ids.get(commitment_id).copied()
+    }
+
+    /// Returns the status of the commitment with the given id.
+    pub fn status_of_commitment(&self, commitment_id: &B256) -> CommitmentStatus {
+        let digest = self.digest_of(commitment_id);
+        digest.map_or(CommitmentStatus::Unknown, |digest| self.status(&digest))
+    }
+
+    /// Returns the status of the commitment to include the transaction in the given slot.
+    pub fn status_of_transaction(&self, tx_hash: TxHash, slot: Slot) -> CommitmentStatus {
+        let digest = self.0.read().transactions.get(&(tx_hash, slot)).copied();
+        digest.map_or(CommitmentStatus::Unknown, |digest| self.status(&digest))
+    }
+}
+
+/// Returns the status of a commitment to the transactions, given the canonical block of its
+/// target slot, or `None` if the slot was missed.
+fn resolve(tx_hashes: &[TxHash], block: Option<&SlotBlock>) -> CommitmentStatus {
+    let Some(block) = block else {
+        let reason = "No block was proposed in the target slot".to_string();
+        return CommitmentStatus::Failed { reason };
+    };
+
+    let included = tx_hashes
+        .iter()
+        .filter(|hash| block.receipts.iter().any(|receipt| receipt.tx_hash == **hash))
+        .count();
+    match included {
+        _ if included == tx_hashes.len() => CommitmentStatus::Included {
+            block_hash: block.hash,
+            block_number: block.number,
+            proof: None,
+        },
+        0 => {
+            let reason = "The committed transactions weren't included in the block".to_string();
+            CommitmentStatus::Failed { reason }
+        }
+        _ => {
+            let reason = format!(
+                "Only {included} of the {} committed transactions were included in the block",
+                tx_hashes.len()
+            );
+            CommitmentStatus::Failed { reason }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "persistence")]
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    #[cfg(feature = "persistence")]
+    use alloy::signers::{k256::SecretKey, local::PrivateKeySigner};
+    use parking_lot::Mutex;
+    #[cfg(feature = "persistence")]
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{
+        driver::DriverTask,
+        earnings::FeeReceipt,
+        finality::Checkpoints,
+        state::{
+            clock::{ManualTimeSource, SlotClock},
+            quotas::{QuotaTracker, QuotaUsage, SignerQuotas},
+            scheduler::SlotOffset,
+            SlotScheduler,
+        },
+    };
+    // The restarts are only tested against the persistent store
+    #[cfg(feature = "persistence")]
+    use crate::{
+        db::Store,
+        submissions::ConstraintSubmissions,
+        test_util::{create_signed_commitment_request, default_test_transaction},
+    };
+
+    /// A canonical chain whose blocks are set by the test.
+    #[derive(Debug, Default)]
+    struct ScriptedChain {
+        blocks: Mutex<HashMap<u64, SlotBlock>>,
+    }
+
+    #[async_trait::async_trait]
+    impl CanonicalChain for ScriptedChain {
+        async fn checkpoints(&self) -> eyre::Result<Checkpoints> {
+            Ok(Checkpoints::default())
+        }
+
+        async fn block_at_slot(&self, slot: u64) -> eyre::Result<Option<SlotBlock>> {
+            Ok(self.blocks.lock().get(&slot).cloned())
+        }
+    }
+
+    fn block(number: u64, tx_hashes: &[TxHash]) -> SlotBlock {
+        let receipts = tx_hashes
+            .iter()
+            .map(|&tx_hash| FeeReceipt { tx_hash, gas_used: 21_000, effective_gas_price: 1 })
+            .collect();
+        SlotBlock { hash: B256::random(), number, receipts, basefee: 1 }
+    }
+
+    #[cfg(feature = "persistence")]
+    fn temp_db() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bolt-inclusions-{}", rand::random::<u64>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("sidecar.db")
+    }
+
+    #[tokio::test]
+    async fn test_commitment_status() {
+        let chain = Arc::new(ScriptedChain::default());
+        let (tracker, reader) = InclusionTracker::new(chain.clone(), 4);
+
+        let (included, partial, missed) = (B256::random(), B256::random(), B256::random());
+        let txs = [TxHash::random(), TxHash::random(), TxHash::random()];
+        tracker.issue(included, 10, None, vec![txs[0]]);
+        tracker.issue(partial, 10, None, vec![txs[1], txs[2]]);
+        tracker.issue(missed, 11, None, vec![txs[2]]);
+        assert_eq!(reader.status(&included), CommitmentStatus::Pending);
+        assert_eq!(reader.status(&B256::random()), CommitmentStatus::Unknown);
+
+        // The commitments are resolved once the head reaches their slot
+        let landed = block(100, &[txs[0], txs[1]]);
+        chain.blocks.lock().insert(10, landed.clone());
+        tracker.observe(10).await.unwrap();
+        let (block_hash, block_number) = (landed.hash, 100);
+        let status = CommitmentStatus::Included { block_hash, block_number, proof: None };
+        assert_eq!(reader.status(&included), status);
+        assert_eq!(reader.status_of_transaction(txs[0], 10), status);
+        assert_eq!(reader.status_of_commitment(&commitment_id(included, 10)), status);
+        assert!(matches!(reader.status(&partial), CommitmentStatus::Failed { .. }));
+        assert_eq!(reader.status(&missed), CommitmentStatus::Pending);
+        assert_eq!(reader.status_of_transaction(txs[0], 11), CommitmentStatus::Unknown);
+
+        // Slot 11 was missed, and is resolved with the head of the next slot
+        tracker.observe(12).await.unwrap();
+        let CommitmentStatus::Failed { reason } = reader.status(&missed) else {
+            panic!("The commitment of a missed slot must fail");
+        };
+        assert!(reason.contains("No block"));
+    }
+
+    #[tokio::test]
+    async fn test_inclusion_proof() {
+        let chain = Arc::new(ScriptedChain::default());
+        let (tracker, reader) = InclusionTracker::new(chain.clone(), 4);
+
+        let txs = [(TxHash::random(), vec![1; 40]), (TxHash::random(), vec![2; 90])];
+        let (digest, landed) = (B256::random(), block(100, &[txs[0].0, txs[1].0]));
+        tracker.issue(digest, 10, None, vec![txs[1].0]);
+
+        // The commitment included in the payload of the local builder is proven against it
+        tracker.record_payload(10, PayloadTransactions::new(landed.hash, txs.clone()));
+        chain.blocks.lock().insert(10, landed.clone());
+        tracker.observe(10).await.unwrap();
+        let CommitmentStatus::Included { proof: Some(proof), .. } = reader.status(&digest) else {
+            panic!("The included commitment must be proven");
+        };
+        assert_eq!(proof.block_hash, landed.hash);
+        assert_eq!(proof.transactions[0].index, 1);
+        assert!(proof.transactions[0].verify(&txs[1].1, proof.transactions_root));
+
+        // Another block than the built one can't be proven without its transactions
+        let other = B256::random();
+        tracker.issue(other, 11, None, vec![txs[0].0]);
+        tracker.record_payload(11, PayloadTransactions::new(B256::random(), txs.clone()));
+        chain.blocks.lock().insert(11, block(101, &[txs[0].0]));
+        tracker.observe(11).await.unwrap();
+        let status = reader.status(&other);
+        assert!(matches!(status, CommitmentStatus::Included { proof: None, .. }), "{status:?}");
+    }
+
+    #[tokio::test]
+    async fn test_commitment_status_expiry() {
+        let chain = Arc::new(ScriptedChain::default());
+        let (tracker, reader) = InclusionTracker::new(chain.clone(), 4);
+
+        let digest = B256::random();
+        let tx = TxHash::random();
+        tracker.issue(digest, 10, None, vec![tx]);
+        chain.blocks.lock().insert(10, block(100, &[tx]));
+        tracker.observe(10).await.unwrap();
+        tracker.observe(14).await.unwrap();
+        assert!(matches!(reader.status(&digest), CommitmentStatus::Included { .. }));
+
+        // The commitment is forgotten past the retention
+        tracker.observe(15).await.unwrap();
+        assert_eq!(reader.status(&digest), CommitmentStatus::Unknown);
+        assert_eq!(reader.status_of_transaction(tx, 10), CommitmentStatus::Unknown);
+        assert_eq!(reader.digest_of(&commitment_id(digest, 10)), None);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_commitment() {
+        let chain = Arc::new(ScriptedChain::default());
+        let (tracker, reader) = InclusionTracker::new(chain.clone(), 4);
+
+        let (digest, signer, tx) = (B256::random(), Address::random(), TxHash::random());
+        tracker.issue(digest, 10, Some(signer), vec![tx]);
+        assert_eq!(tracker.issued(&digest), Some((10, Some(signer), CommitmentStatus::Pending)));
+        assert!(tracker.cancel(&digest));
+        assert!(!tracker.cancel(&digest) && !tracker.cancel(&B256::random()));
+
+        // Cancelled commitments aren't resolved, even if their transactions were included
+        chain.blocks.lock().insert(10, block(100, &[tx]));
+        tracker.observe(10).await.unwrap();
+        assert_eq!(reader.status(&digest), CommitmentStatus::Cancelled);
+        assert_eq!(reader.status_of_transaction(tx, 10), CommitmentStatus::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn test_slot_range_status() {
+        let chain = Arc::new(ScriptedChain::default());
+        let (tracker, reader) = InclusionTracker::new(chain.clone(), 4);
+
+        // A request deferred until its range is proposed by our validators is pending
+        let (requested, committed, tx) = (B256::random(), B256::random(), TxHash::random());
+        tracker.defer(requested, 40, None);
+        tracker.observe(12).await.unwrap();
+        assert_eq!(reader.status(&requested), CommitmentStatus::Pending);
+
+        // Its commitment can be queried with the digest of the request too
+        tracker.issue(committed, 36, None, vec![tx]);
+        tracker.alias(requested, committed);
+        assert_eq!(tracker.commitment_digest(&requested), committed);
+        assert!(!tracker.expire(&requested));
+        chain.blocks.lock().insert(36, block(100, &[tx]));
+        tracker.observe(36).await.unwrap();
+        assert!(matches!(reader.status(&requested), CommitmentStatus::Included { .. }));
+        assert_eq!(reader.status(&requested), reader.status(&committed));
+
+        // Requests whose range passes before they are committed to expire
+        let expired = B256::random();
+        tracker.defer(expired, 40, None);
+        assert!(tracker.expire(&expired));
+        let reason = ExpiryReason::SlotPassed;
+        assert_eq!(reader.status(&expired), CommitmentStatus::Expired { reason });
+        tracker.observe(45).await.unwrap();
+        assert_eq!(reader.status(&expired), CommitmentStatus::Unknown);
+        assert_eq!(reader.status(&requested), CommitmentStatus::Unknown);
+    }
+
+    #[tokio::test]
+    async fn test_expired_commitments() {
+        let chain = Arc::new(ScriptedChain::default());
+        let (tracker, reader) = InclusionTracker::new(chain.clone(), 4);
+
+        let digests = [B256::random(), B256::random(), B256::random(), B256::random()];
+        let tx = TxHash::random();
+        for (slot, digest) in (10..).zip(digests) {
+            tracker.issue(digest, slot, None, vec![tx]);
+        }
+
+        // The commitments of a slot whose constraints were submitted are resolved as usual
+        tracker.record_submission(10, true);
+        assert!(tracker.expire_passed(11).is_empty());
+        chain.blocks.lock().insert(10, block(100, &[tx]));
+        tracker.observe(10).await.unwrap();
+        assert!(matches!(reader.status(&digests[0]), CommitmentStatus::Included { .. }));
+
+        // The others expire once their slot has passed, with the reason
+        tracker.record_submission(11, false);
+        chain.blocks.lock().insert(11, block(101, &[tx]));
+        tracker.observe(11).await.unwrap();
+        assert_eq!(reader.status(&digests[1]), CommitmentStatus::Pending);
+        let reason = ExpiryReason::SubmissionFailed;
+        assert_eq!(tracker.expire_passed(12), vec![(digests[1], reason)]);
+        assert_eq!(reader.status(&digests[1]), CommitmentStatus::Expired { reason });
+
+        let reason = ExpiryReason::SlotPassed;
+        assert_eq!(tracker.expire_passed(13), vec![(digests[2], reason)]);
+        assert_eq!(reader.status_of_transaction(tx, 12), CommitmentStatus::Expired { reason });
+
+        // The commitments of a slot whose duty was lost expire right away, even if submitted
+        tracker.record_submission(13, true);
+        assert_eq!(tracker.lose_duty(13), vec![digests[3]]);
+        let status = CommitmentStatus::Expired { reason: ExpiryReason::DutyLost };
+        assert_eq!(reader.status(&digests[3]), status);
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json, serde_json::json!({ "status": "expired", "reason": "duty_lost" }));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_expiry_on_slot_tick() {
+        let chain = Arc::new(ScriptedChain::default());
+        let (tracker, reader) = InclusionTracker::new(chain, 4);
+        let mut quotas = QuotaTracker::new(SignerQuotas::default(), 32);
+
+        // Start 1 second into slot 0, with a commitment for slot 2 whose submission failed
+        let time = ManualTimeSource::new(Duration::from_secs(1_001));
+        let mut scheduler = SlotScheduler::new(SlotClock::with_time_source(1_000, 12, time));
+        scheduler.register(DriverTask::SlotStart, SlotOffset::START);
+        let mut ticks = scheduler.spawn();
+
+        let (digest, signer) = (B256::random(), Address::random());
+        let usage = QuotaUsage { gas: 21_000, txs: 1 };
+        quotas.hold(digest, signer, 2, usage);
+        tracker.issue(digest, 2, Some(signer), vec![TxHash::random()]);
+        tracker.record_submission(2, false);
+
+        // Each slot start expires the commitments of the slots that passed, as the driver does
+        loop {
+            let tick = ticks.recv().await.unwrap();
+            quotas.expire(tick.slot);
+            let expired = tracker.expire_passed(tick.slot);
+            if tick.slot <= 2 {
+                assert!(expired.is_empty());
+                assert_eq!(reader.status(&digest), CommitmentStatus::Pending);
+                assert_eq!(quotas.usage(&signer, 2), usage);
+                continue;
+            }
+
+            assert_eq!(tick.slot, 3);
+            assert_eq!(expired, vec![(digest, ExpiryReason::SubmissionFailed)]);
+            break;
+        }
+
+        let reason = ExpiryReason::SubmissionFailed;
+        assert_eq!(reader.status(&digest), CommitmentStatus::Expired { reason });
+        assert_eq!(quotas.usage(&signer, 2), QuotaUsage::default());
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_restore_commitments_after_restart() -> eyre::Result<()> {
+        let path = temp_db();
+        let sk = SecretKey::random(&mut rand::thread_rng());
+        let signer = PrivateKeySigner::from(sk.clone()).address();
+
+        // The driver issues commitments for three slots, cancels one, and is killed
+        let (before, _) = InclusionTracker::new(Arc::new(ScriptedChain::default()), 4);
+        let store = ResilientStore::new(Store::open(&path)?);
+        let before = before.with_store(store.clone());
+        let submissions = ConstraintSubmissions::new(store.clone(), Vec::new());
+        let mut issued = Vec::new();
+        for slot in [20, 21, 22] {
+            let tx = default_test_transaction(signer, Some(slot));
+            let request = create_signed_commitment_request(&[tx], &sk, slot).await?;
+            let commitment = request.clone().commit_and_sign(&PrivateKeySigner::random()).await?;
+            let request = request.into_inclusion_request().unwrap();
+            let digest = request.digest();
+            let message = ConstraintsMessage::build(1, request);
+            let signature = BLSSig::repeat_byte(slot as u8);
+            let constraints = SignedConstraints { message, signature };
+
+            before.record(&IssuedRecord::new(digest, Some(signer), &commitment, &constraints)?)?;
+            let tx_hashes = constraints.message.constraints.iter();
+            let tx_hashes = tx_hashes.map(|constraint| *constraint.transaction.hash()).collect();
+            before.issue(digest, slot, Some(signer), tx_hashes);
+            submissions.enqueue(&constraints);
+            issued.push((digest, constraints));
+        }
+        assert!(before.cancel(&issued[2].0));
+        drop((before, submissions, store));
+
+        // After the restart at slot 20, only the pending commitment of a future slot is
+        // returned, with the constraints persisted for submission
+        let (after, reader) = InclusionTracker::new(Arc::new(ScriptedChain::default()), 4);
+        let store = ResilientStore::new(Store::open(&path)?);
+        let after = after.with_store(store.clone());
+        let restored = after.restore(20)?;
+        assert_eq!(restored.len(), 1);
+        let (record, constraints) = &restored[0];
+        assert_eq!((record.digest, record.slot, record.signer), (issued[1].0, 21, Some(signer)));
+        assert_eq!(constraints, &issued[1].1);
+        let pending = store.pending_submission(21)?.unwrap();
+        let payload: Vec<Value> = serde_json::from_slice(&pending.payload)?;
+        assert_eq!(payload, vec![serde_json::to_value(constraints)?]);
+
+        // The others are restored with their status, and the one of the current slot is
+        // resolved against the chain rather than expired
+        assert_eq!(reader.status(&issued[1].0), CommitmentStatus::Pending);
+        assert_eq!(reader.status(&issued[2].0), CommitmentStatus::Cancelled);
+        assert!(after.expire_passed(21).is_empty());
+        assert_eq!(reader.status(&issued[0].0), CommitmentStatus::Pending);
+
+        Ok(())
+    }
+}
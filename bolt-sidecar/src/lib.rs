@@ -12,15 +12,26 @@ pub use api::{
 };
 
 mod client;
-pub use client::{mevboost::MevBoostClient, rpc::RpcClient, BeaconClient};
+pub use client::{
+    commit_boost::{CommitBoostConfig, CommitBoostSigner},
+    commitments::{
+        verify_commitment_with_identity, ClientError, ResponseVerificationError, ResponseVerifier,
+    },
+    mevboost::MevBoostClient,
+    relay::{RelayError, RelayForwarder},
+    rpc::RpcClient,
+    web3signer::{ClientCert, Web3SignerBackend, Web3SignerConfig},
+    BeaconClient,
+};
 
 /// Common types and compatibility utilities
 /// (To be refactored)
 mod common;
+pub use common::rng::Rng;
 
 /// Driver for the sidecar, which manages the main event loop
 pub mod driver;
-pub use driver::SidecarDriver;
+pub use driver::{signer_backend, SidecarDriver};
 
 /// Functionality for building local block templates that can
 /// be used as a fallback for proposers. It's also used to keep
@@ -30,7 +41,10 @@ pub use builder::LocalBuilder;
 
 /// Configuration and command-line argument parsing
 mod config;
-pub use config::{ChainConfig, Config, Opts};
+pub use config::{
+    ApiOpts, ChainConfig, Command, Config, ConfigReload, DbCommand, DbOpts, DelegateOpts,
+    DriverOpts, EarningsOpts, MigrateOpts, Opts, ReloadTargets, ReplayOpts, SimulateOpts,
+};
 
 /// Crypto utilities, including BLS and ECDSA
 pub mod crypto;
@@ -38,9 +52,45 @@ pub mod crypto;
 /// Primitive types and utilities
 pub mod primitives;
 
+/// Persistent storage for the sidecar
+pub mod db;
+
+/// Commitment fee accounting and earnings reports
+pub mod earnings;
+
+/// Finality of the reconciled commitments, across reorgs
+pub mod finality;
+
+/// Store-and-forward of the constraint submissions across restarts
+pub mod submissions;
+
+/// Inclusion status of the issued commitments
+pub mod inclusion;
+
+/// Soft deletion and restoration of the commitments dropped from the admin API
+pub mod drops;
+
 /// State management and fetching for EVM simulation
 pub mod state;
 
+/// Prometheus metrics exported by the sidecar
+pub mod telemetry;
+
+/// Log output of the sidecar, with a level that can be changed at runtime
+pub mod logging;
+
+/// Recording of the driver inputs to a replay log, and deterministic replays of it
+pub mod replay;
+
+/// Validation of a commitment request against a snapshot of the chain state, outside the driver
+pub mod simulate;
+
+/// Alerts on critical conditions, delivered to webhooks and external commands
+pub mod alerts;
+
+/// Synthetic probe of the commitment pipeline, feeding the readiness and the alerts
+pub mod probe;
+
 /// Utilities for testing
 #[cfg(test)]
 mod test_util;
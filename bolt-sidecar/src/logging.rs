@@ -0,0 +1,24 @@
+//! Log output of the sidecar, whose maximum level can be changed while it runs.
+
+use std::sync::OnceLock;
+
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::{fmt, layer::SubscriberExt, reload, util::SubscriberInitExt, Registry};
+
+/// The handle to the level filter of the global subscriber, once installed.
+static LEVEL: OnceLock<reload::Handle<LevelFilter, Registry>> = OnceLock::new();
+
+/// Installs the global subscriber, formatting the logs up to the given level to stdout. Does
+/// nothing if a global subscriber is already installed.
+pub fn init(level: LevelFilter) {
+    let (filter, handle) = reload::Layer::new(level);
+    if tracing_subscriber::registry().with(filter).with(fmt::layer()).try_init().is_ok() {
+        let _ = LEVEL.set(handle);
+    }
+}
+
+/// Changes the maximum level of the logs. Returns false if the global subscriber wasn't
+/// installed with [`init`].
+pub fn set_level(level: LevelFilter) -> bool {
+    LEVEL.get().is_some_and(|handle| handle.modify(|filter| *filter = level).is_ok())
+}
@@ -0,0 +1,123 @@
+use std::str::FromStr;
+
+use alloy::primitives::Address;
+use serde::{de, Deserialize, Deserializer};
+
+/// Errors that can occur while parsing a user-supplied address.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AddressError {
+    /// The input is not a 20-byte hex string.
+    #[error("invalid address '{0}': expected 40 hex characters with an optional 0x prefix")]
+    InvalidHex(String),
+    /// The input is mixed-case, but the case doesn't match its EIP-55 checksum.
+    #[error("invalid EIP-55 checksum for address '{0}', expected '{1}'")]
+    BadChecksum(String, String),
+}
+
+/// Parses a user-supplied address.
+///
+/// All-lowercase and all-uppercase hex strings are accepted as is, while mixed-case
+/// strings are treated as EIP-55 checksummed and rejected if the checksum is wrong.
+/// Parsed addresses compare equal regardless of the case of their input; use
+/// [`Address::to_checksum`] to get their canonical string representation.
+pub fn parse_address(input: &str) -> Result<Address, AddressError> {
+    let hex = input.strip_prefix("0x").unwrap_or(input);
+    if hex.len() != 40 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(AddressError::InvalidHex(input.to_string()));
+    }
+
+    let address =
+        Address::from_str(hex).map_err(|_| AddressError::InvalidHex(input.to_string()))?;
+
+    let has_lower = hex.bytes().any(|b| b.is_ascii_lowercase());
+    let has_upper = hex.bytes().any(|b| b.is_ascii_uppercase());
+
+    if has_lower && has_upper {
+        let checksummed = address.to_checksum(None);
+        if checksummed[2..] != *hex {
+            return Err(AddressError::BadChecksum(input.to_string(), checksummed));
+        }
+    }
+
+    Ok(address)
+}
+
+/// Parses a comma-separated list of user-supplied addresses with [`parse_address`].
+/// Errors report the position (starting from 1) of the first invalid entry.
+pub fn parse_address_list(input: &str) -> Result<Vec<Address>, AddressListError> {
+    input
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .enumerate()
+        .map(|(i, entry)| {
+            parse_address(entry).map_err(|error| AddressListError { position: i + 1, error })
+        })
+        .collect()
+}
+
+/// Error returned by [`parse_address_list`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("entry #{position}: {error}")]
+pub struct AddressListError {
+    /// The position (starting from 1) of the invalid entry in the list.
+    pub position: usize,
+    /// The parsing error of the entry.
+    pub error: AddressError,
+}
+
+/// Deserializes an address with [`parse_address`].
+pub fn deserialize_address<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Address, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    parse_address(&s).map_err(de::Error::custom)
+}
+
+/// Deserializes a list of addresses with [`parse_address`].
+pub fn deserialize_addresses<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Address>, D::Error> {
+    let entries = Vec::<String>::deserialize(deserializer)?;
+    entries.iter().map(|s| parse_address(s).map_err(de::Error::custom)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHECKSUMMED: &str = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+
+    #[test]
+    fn test_parse_address() {
+        let expected = Address::from_str(CHECKSUMMED).unwrap();
+
+        assert_eq!(parse_address(CHECKSUMMED), Ok(expected));
+        assert_eq!(parse_address(&CHECKSUMMED.to_lowercase()), Ok(expected));
+        assert_eq!(parse_address(&format!("0x{}", CHECKSUMMED[2..].to_uppercase())), Ok(expected));
+        assert_eq!(parse_address(&CHECKSUMMED[2..]), Ok(expected));
+
+        // Last character flipped to uppercase
+        let wrong = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD";
+        assert_eq!(
+            parse_address(wrong),
+            Err(AddressError::BadChecksum(wrong.to_string(), CHECKSUMMED.to_string()))
+        );
+
+        assert!(matches!(parse_address("0x1234"), Err(AddressError::InvalidHex(_))));
+        assert!(matches!(
+            parse_address("0xzzAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"),
+            Err(AddressError::InvalidHex(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_address_list() {
+        let lower = CHECKSUMMED.to_lowercase();
+        let list = format!("{CHECKSUMMED}, {lower}");
+        assert_eq!(parse_address_list(&list).unwrap().len(), 2);
+
+        let list = format!("{lower},{CHECKSUMMED},0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD");
+        let err = parse_address_list(&list).unwrap_err();
+        assert_eq!(err.position, 3);
+        assert!(matches!(err.error, AddressError::BadChecksum(..)));
+    }
+}
@@ -2,11 +2,103 @@ use serde::{de, Deserialize, Deserializer, Serialize};
 use std::str::FromStr;
 
 use alloy::{
-    primitives::{keccak256, Address, Signature, B256},
+    primitives::{keccak256, Address, Signature, B256, U256},
     signers::{Error, Signer},
+    sol_types::{Eip712Domain, SolStruct},
 };
 
-use super::{FullTransaction, SignatureError, TransactionExt};
+use crate::crypto::{SignerBackend, SignerError};
+
+use super::{proof::InclusionProof, FullTransaction, SignatureError, TransactionExt};
+
+/// The version of the commitment digest computed by [`InclusionRequest::digest`], advertised
+/// in the identity documents of the sidecar.
+pub const COMMITMENT_DIGEST_VERSION: u8 = 1;
+
+/// Returns the identifier of the commitment to the request with the given digest and
+/// target slot. See [`InclusionRequest::commitment_id`].
+pub fn commitment_id(digest: B256, slot: u64) -> B256 {
+    keccak256([digest.as_slice(), &slot.to_le_bytes()].concat())
+}
+
+/// The domain tag of the digest of an [`InclusionRequest`] signed by its signer, see
+/// [`InclusionRequest::chain_digest`].
+pub const INCLUSION_DIGEST_DOMAIN: &[u8] = b"bolt-inclusion-request-v1";
+
+/// The maximum length of the [`InclusionRequest::context`] of a request, in bytes.
+pub const MAX_CONTEXT_LEN: usize = 256;
+
+/// The maximum number of senders and transaction hashes an [`ExclusionRequest`] can exclude.
+pub const MAX_EXCLUSION_TARGETS: usize = 128;
+
+/// The prefix of the digest of an [`ExclusionRequest`], which sets it apart from the digests
+/// of the inclusion requests.
+const EXCLUSION_DIGEST_PREFIX: &[u8] = b"exclusion";
+
+/// Returns the digest of an exclusion request, see [`ExclusionRequest::digest`].
+pub(crate) fn exclusion_digest(slot: u64, senders: &[Address], tx_hashes: &[B256]) -> B256 {
+    let mut data = EXCLUSION_DIGEST_PREFIX.to_vec();
+    data.extend_from_slice(&slot.to_le_bytes());
+    data.extend_from_slice(&(senders.len() as u64).to_le_bytes());
+    senders.iter().for_each(|sender| data.extend_from_slice(sender.as_slice()));
+    tx_hashes.iter().for_each(|hash| data.extend_from_slice(hash.as_slice()));
+
+    keccak256(&data)
+}
+
+/// The name of the EIP-712 domain of the typed-data request signatures.
+pub const EIP712_DOMAIN_NAME: &str = "bolt";
+
+/// Returns the EIP-712 domain of the typed-data request signatures on the given chain, which
+/// has no version and an optional verifying contract.
+pub fn eip712_domain(chain_id: u64, verifying_contract: Option<Address>) -> Eip712Domain {
+    Eip712Domain::new(
+        Some(EIP712_DOMAIN_NAME.into()),
+        None,
+        Some(U256::from(chain_id)),
+        verifying_contract,
+        None,
+    )
+}
+
+mod eip712 {
+    alloy::sol! {
+        /// The EIP-712 struct of an inclusion request, as signed by wallets with
+        /// `eth_signTypedData_v4`.
+        struct InclusionRequest {
+            uint64 slot;
+            bytes32[] txHashes;
+        }
+
+        /// The EIP-712 struct of an atomic inclusion request, whose transactions are
+        /// included together in the given order.
+        struct AtomicInclusionRequest {
+            uint64 slot;
+            bytes32[] txHashes;
+        }
+
+        /// The EIP-712 struct of an inclusion request for a range of slots.
+        struct SlotRangeInclusionRequest {
+            uint64 slot;
+            uint64 maxSlot;
+            bytes32[] txHashes;
+        }
+
+        /// The EIP-712 struct of an atomic inclusion request for a range of slots.
+        struct AtomicSlotRangeInclusionRequest {
+            uint64 slot;
+            uint64 maxSlot;
+            bytes32[] txHashes;
+        }
+
+        /// The EIP-712 struct of an exclusion request.
+        struct ExclusionRequest {
+            uint64 slot;
+            address[] senders;
+            bytes32[] txHashes;
+        }
+    }
+}
 
 /// Commitment requests sent by users or RPC proxies to the sidecar.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -14,6 +106,8 @@ use super::{FullTransaction, SignatureError, TransactionExt};
 pub enum CommitmentRequest {
     /// Request of inclusion of a transaction at a specific slot.
     Inclusion(InclusionRequest),
+    /// Request of exclusion of transactions from the block of a specific slot.
+    Exclusion(ExclusionRequest),
 }
 
 /// A signed commitment with a generic signature.
@@ -21,21 +115,167 @@ pub enum CommitmentRequest {
 #[serde(untagged)]
 pub enum SignedCommitment {
     Inclusion(InclusionCommitment),
+    Exclusion(ExclusionCommitment),
 }
 
 /// A signed inclusion commitment with a generic signature.
+///
+/// Its [`commitment_id`](InclusionRequest::commitment_id) is derived from the request, so
+/// commitments without one are accepted, and those whose id doesn't match are rejected. The
+/// proof of inclusion of its transactions is only known once the block of its slot is seen.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "RawInclusionCommitment")]
 pub struct InclusionCommitment {
+    commitment_id: B256,
     #[serde(flatten)]
     request: InclusionRequest,
     #[serde(deserialize_with = "deserialize_sig", serialize_with = "serialize_sig")]
     signature: Signature,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    proof: Option<InclusionProof>,
 }
 
-impl From<SignedCommitment> for InclusionCommitment {
-    fn from(commitment: SignedCommitment) -> Self {
-        match commitment {
-            SignedCommitment::Inclusion(inclusion) => inclusion,
+/// An inclusion commitment as received, before its id is checked against its request.
+#[derive(Deserialize)]
+struct RawInclusionCommitment {
+    #[serde(default)]
+    commitment_id: Option<B256>,
+    #[serde(flatten)]
+    request: InclusionRequest,
+    #[serde(deserialize_with = "deserialize_sig")]
+    signature: Signature,
+    #[serde(default)]
+    proof: Option<InclusionProof>,
+}
+
+/// Error of a commitment whose id doesn't match its request.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Commitment id {got} doesn't match the one of the request, {expected}")]
+pub struct CommitmentIdMismatch {
+    /// The id of the request.
+    pub expected: B256,
+    /// The id of the commitment.
+    pub got: B256,
+}
+
+impl TryFrom<RawInclusionCommitment> for InclusionCommitment {
+    type Error = CommitmentIdMismatch;
+
+    fn try_from(raw: RawInclusionCommitment) -> Result<Self, Self::Error> {
+        let RawInclusionCommitment { commitment_id, request, signature, proof } = raw;
+        let expected = request.commitment_id();
+        match commitment_id {
+            Some(got) if got != expected => Err(CommitmentIdMismatch { expected, got }),
+            _ => Ok(Self { commitment_id: expected, request, signature, proof }),
+        }
+    }
+}
+
+impl InclusionCommitment {
+    /// Returns the identifier of the commitment, derived from its request.
+    pub fn commitment_id(&self) -> B256 {
+        self.commitment_id
+    }
+
+    /// Returns the committed request.
+    pub fn request(&self) -> &InclusionRequest {
+        &self.request
+    }
+
+    /// Returns the signature of the sidecar over the digest of the request.
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    /// Returns the proof of inclusion of the committed transactions in the block of the
+    /// target slot, once it is known.
+    pub fn proof(&self) -> Option<&InclusionProof> {
+        self.proof.as_ref()
+    }
+
+    /// Attaches the proof of inclusion of the committed transactions in the block of the
+    /// target slot, as reported by `bolt_getCommitmentStatus`.
+    pub fn with_proof(self, proof: InclusionProof) -> Self {
+        Self { proof: Some(proof), ..self }
+    }
+}
+
+/// A signed exclusion commitment with a generic signature.
+///
+/// Like the one of an [`InclusionCommitment`], its commitment id is derived from the request.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "RawExclusionCommitment")]
+pub struct ExclusionCommitment {
+    commitment_id: B256,
+    #[serde(flatten)]
+    request: ExclusionRequest,
+    #[serde(deserialize_with = "deserialize_sig", serialize_with = "serialize_sig")]
+    signature: Signature,
+}
+
+/// An exclusion commitment as received, before its id is checked against its request.
+#[derive(Deserialize)]
+struct RawExclusionCommitment {
+    #[serde(default)]
+    commitment_id: Option<B256>,
+    #[serde(flatten)]
+    request: ExclusionRequest,
+    #[serde(deserialize_with = "deserialize_sig")]
+    signature: Signature,
+}
+
+impl TryFrom<RawExclusionCommitment> for ExclusionCommitment {
+    type Error = CommitmentIdMismatch;
+
+    fn try_from(raw: RawExclusionCommitment) -> Result<Self, Self::Error> {
+        let RawExclusionCommitment { commitment_id, request, signature } = raw;
+        let expected = request.commitment_id();
+        match commitment_id {
+            Some(got) if got != expected => Err(CommitmentIdMismatch { expected, got }),
+            _ => Ok(Self { commitment_id: expected, request, signature }),
+        }
+    }
+}
+
+impl ExclusionCommitment {
+    /// Returns the identifier of the commitment, derived from its request.
+    pub fn commitment_id(&self) -> B256 {
+        self.commitment_id
+    }
+
+    /// Returns the committed request.
+    pub fn request(&self) -> &ExclusionRequest {
+        &self.request
+    }
+
+    /// Returns the signature of the sidecar over the digest of the request.
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+}
+
+impl SignedCommitment {
+    /// Returns the identifier of the commitment, derived from its request.
+    pub fn commitment_id(&self) -> B256 {
+        match self {
+            SignedCommitment::Inclusion(inclusion) => inclusion.commitment_id(),
+            SignedCommitment::Exclusion(exclusion) => exclusion.commitment_id(),
+        }
+    }
+
+    /// Returns the inner commitment if this is an inclusion commitment, otherwise `None`.
+    pub fn into_inclusion(self) -> Option<InclusionCommitment> {
+        match self {
+            SignedCommitment::Inclusion(inclusion) => Some(inclusion),
+            SignedCommitment::Exclusion(_) => None,
+        }
+    }
+
+    /// Returns the inner commitment if this is an exclusion commitment, otherwise `None`.
+    pub fn into_exclusion(self) -> Option<ExclusionCommitment> {
+        match self {
+            SignedCommitment::Exclusion(exclusion) => Some(exclusion),
+            SignedCommitment::Inclusion(_) => None,
         }
     }
 }
@@ -45,19 +285,78 @@ impl CommitmentRequest {
     pub fn as_inclusion_request(&self) -> Option<&InclusionRequest> {
         match self {
             CommitmentRequest::Inclusion(req) => Some(req),
-            // TODO: remove this when we have more request types
-            #[allow(unreachable_patterns)]
-            _ => None,
+            CommitmentRequest::Exclusion(_) => None,
+        }
+    }
+
+    /// Returns the inner request if this is an inclusion request, otherwise `None`.
+    pub fn into_inclusion_request(self) -> Option<InclusionRequest> {
+        match self {
+            CommitmentRequest::Inclusion(req) => Some(req),
+            CommitmentRequest::Exclusion(_) => None,
+        }
+    }
+
+    /// Returns a reference to the inner request if this is an exclusion request, otherwise `None`.
+    pub fn as_exclusion_request(&self) -> Option<&ExclusionRequest> {
+        match self {
+            CommitmentRequest::Exclusion(req) => Some(req),
+            CommitmentRequest::Inclusion(_) => None,
+        }
+    }
+
+    /// Returns the digest of the request, which the commitment signs.
+    pub fn digest(&self) -> B256 {
+        match self {
+            CommitmentRequest::Inclusion(req) => req.digest(),
+            CommitmentRequest::Exclusion(req) => req.digest(),
+        }
+    }
+
+    /// Returns the target slot of the request.
+    pub fn slot(&self) -> u64 {
+        match self {
+            CommitmentRequest::Inclusion(req) => req.slot,
+            CommitmentRequest::Exclusion(req) => req.slot,
         }
     }
 
     /// Commits and signs the request with the provided signer. Returns a [SignedCommitment].
     pub async fn commit_and_sign<S: Signer>(self, signer: &S) -> Result<SignedCommitment, Error> {
+        let signature = signer.sign_hash(&self.digest()).await?;
+        Ok(self.commit_with_signature(signature))
+    }
+
+    /// Commits to the request with its digest signed by the commitment key of the signer
+    /// backend. Returns a [SignedCommitment].
+    pub async fn commit_with_backend(
+        self,
+        backend: &dyn SignerBackend,
+    ) -> Result<SignedCommitment, SignerError> {
+        let signature = backend.sign_commitment(&self.digest()).await?;
+        Ok(self.commit_with_signature(signature))
+    }
+
+    /// Commits to the request with a signature over its digest that was made elsewhere,
+    /// e.g. by an external signer. The signature is not verified.
+    pub fn commit_with_signature(self, signature: Signature) -> SignedCommitment {
         match self {
-            CommitmentRequest::Inclusion(req) => {
-                let digest = req.digest();
-                let signature = signer.sign_hash(&digest).await?;
-                Ok(SignedCommitment::Inclusion(InclusionCommitment { request: req, signature }))
+            CommitmentRequest::Inclusion(request) => {
+                let commitment_id = request.commitment_id();
+                SignedCommitment::Inclusion(InclusionCommitment {
+                    commitment_id,
+                    request,
+                    signature,
+                    proof: None,
+                })
+            }
+            CommitmentRequest::Exclusion(request) => {
+                let commitment_id = request.commitment_id();
+                SignedCommitment::Exclusion(ExclusionCommitment {
+                    commitment_id,
+                    request,
+                    signature,
+                })
             }
         }
     }
@@ -66,6 +365,31 @@ impl CommitmentRequest {
     pub fn signature(&self) -> Option<&Signature> {
         match self {
             CommitmentRequest::Inclusion(req) => req.signature.as_ref(),
+            CommitmentRequest::Exclusion(req) => req.signature.as_ref(),
+        }
+    }
+
+    /// Returns the signer of the request, if authenticated.
+    pub fn signer(&self) -> Option<Address> {
+        match self {
+            CommitmentRequest::Inclusion(req) => req.signer,
+            CommitmentRequest::Exclusion(req) => req.signer,
+        }
+    }
+
+    /// Sets the signature of the request.
+    pub fn set_signature(&mut self, signature: Signature) {
+        match self {
+            CommitmentRequest::Inclusion(req) => req.set_signature(signature),
+            CommitmentRequest::Exclusion(req) => req.signature = Some(signature),
+        }
+    }
+
+    /// Sets the signer of the request.
+    pub fn set_signer(&mut self, signer: Address) {
+        match self {
+            CommitmentRequest::Inclusion(req) => req.set_signer(signer),
+            CommitmentRequest::Exclusion(req) => req.signer = Some(signer),
         }
     }
 }
@@ -84,6 +408,19 @@ pub struct InclusionRequest {
     pub signature: Option<Signature>,
     #[serde(skip)]
     pub signer: Option<Address>,
+    /// An opaque tag set by the client to correlate the request with its own records. It is
+    /// not part of the digest, and is echoed verbatim in the commitment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    /// Whether the transactions must be included together, in their order, or not at all.
+    /// It is part of the digest, so the ordering of an atomic request is signed.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub atomic: bool,
+    /// The last slot at which the transactions may be included, if the request targets the
+    /// range of slots from `slot` to it. The commitment names the earliest slot of the range
+    /// proposed by the validators of the sidecar, as its `slot`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_slot: Option<u64>,
 }
 
 impl InclusionRequest {
@@ -150,11 +487,54 @@ impl InclusionRequest {
         true
     }
 
+    /// Returns the lowest priority fee per gas tipped by the transactions of the request.
+    /// Legacy transactions tip their whole gas price.
+    pub fn min_priority_fee(&self) -> u128 {
+        let tips = self
+            .txs
+            .iter()
+            .map(|tx| tx.max_priority_fee_per_gas().unwrap_or_else(|| tx.max_fee_per_gas()));
+        tips.min().unwrap_or(0)
+    }
+
+    /// Validates the context of the request, if any: it must fit in [`MAX_CONTEXT_LEN`] bytes
+    /// and only contain printable characters.
+    pub fn validate_context(&self) -> bool {
+        self.context.as_ref().map_or(true, |context| {
+            context.len() <= MAX_CONTEXT_LEN && !context.chars().any(char::is_control)
+        })
+    }
+
+    /// Returns the hash of the context of the request, to log it without revealing it.
+    pub fn context_hash(&self) -> Option<B256> {
+        self.context.as_ref().map(keccak256)
+    }
+
     /// Returns the total gas limit of all transactions in this request.
     pub fn gas_limit(&self) -> u64 {
         self.txs.iter().map(|tx| tx.gas_limit()).sum()
     }
 
+    /// Returns the total number of blobs of all transactions in this request.
+    pub fn blob_count(&self) -> usize {
+        self.txs
+            .iter()
+            .filter_map(|tx| tx.as_eip4844())
+            .map(|tx| tx.blob_versioned_hashes.len())
+            .sum()
+    }
+
+    /// Returns the bytes the transactions of this request take in a block, without their
+    /// blob sidecars.
+    pub fn block_bytes(&self) -> usize {
+        self.txs.iter().map(|tx| tx.size()).sum()
+    }
+
+    /// Returns the total size of the encoded transactions of this request, in bytes.
+    pub fn encoded_len(&self) -> usize {
+        self.txs.iter().map(FullTransaction::encoded_len).sum()
+    }
+
     /// Returns the transaction signer.
     pub fn signer(&self) -> Option<Address> {
         self.signer
@@ -180,7 +560,7 @@ impl InclusionRequest {
     }
 }
 
-fn deserialize_sig<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+pub(crate) fn deserialize_sig<'de, D, T>(deserializer: D) -> Result<T, D::Error>
 where
     D: Deserializer<'de>,
     T: FromStr,
@@ -199,9 +579,31 @@ fn serialize_sig<S: serde::Serializer>(sig: &Signature, serializer: S) -> Result
 }
 
 impl InclusionRequest {
-    /// Returns the digest of the request.
+    /// Returns the digest of the request. The context of the request is not part of it.
     /// digest = keccak256(bytes(tx_hash1) | bytes(tx_hash2) | ... | le_bytes(target_slot))
+    ///
+    /// The digest of a request for a range of slots has the little endian encoding of its
+    /// `max_slot` after the target slot. The digest of an atomic request has a trailing `0x01`
+    /// byte, so that it can't be replayed as a non-atomic one.
     pub fn digest(&self) -> B256 {
+        keccak256(self.digest_preimage())
+    }
+
+    /// Returns the digest of the request that its signer signs, scoped to the chain with the
+    /// given id so that the signature can't be replayed on another chain.
+    /// chain_digest = keccak256("bolt-inclusion-request-v1" | le_bytes(chain_id) | preimage)
+    ///
+    /// The preimage is the one of [`Self::digest`], which still identifies the request.
+    pub fn chain_digest(&self, chain_id: u64) -> B256 {
+        let mut data = INCLUSION_DIGEST_DOMAIN.to_vec();
+        data.extend_from_slice(&chain_id.to_le_bytes());
+        data.extend_from_slice(&self.digest_preimage());
+
+        keccak256(&data)
+    }
+
+    /// Returns the preimage of the [`Self::digest`] of the request.
+    fn digest_preimage(&self) -> Vec<u8> {
         let mut data = Vec::new();
         // First field is the concatenation of all the transaction hashes
         data.extend_from_slice(
@@ -211,7 +613,58 @@ impl InclusionRequest {
         // Second field is the little endian encoding of the target slot
         data.extend_from_slice(&self.slot.to_le_bytes());
 
-        keccak256(&data)
+        if let Some(max_slot) = self.max_slot {
+            data.extend_from_slice(&max_slot.to_le_bytes());
+        }
+        if self.atomic {
+            data.push(1);
+        }
+
+        data
+    }
+
+    /// Returns the identifier of the commitment to the request, which clients can compute to
+    /// correlate the responses to their requests, and use to query or cancel the commitment.
+    /// commitment_id = keccak256(digest | le_bytes(target_slot))
+    pub fn commitment_id(&self) -> B256 {
+        commitment_id(self.digest(), self.slot)
+    }
+
+    /// Returns the EIP-712 digest of the request in the domain of the given chain, without a
+    /// verifying contract. Like [`Self::digest`], it covers the transaction hashes and the
+    /// target slot, but not the context.
+    pub fn typed_digest(&self, chain_id: u64) -> B256 {
+        self.typed_digest_in(&eip712_domain(chain_id, None))
+    }
+
+    /// Returns the EIP-712 digest of the request in the given domain.
+    pub fn typed_digest_in(&self, domain: &Eip712Domain) -> B256 {
+        let (slot, tx_hashes) = (self.slot, self.txs.iter().map(|tx| *tx.hash()).collect());
+        match (self.atomic, self.max_slot) {
+            (false, None) => {
+                eip712::InclusionRequest { slot, txHashes: tx_hashes }.eip712_signing_hash(domain)
+            }
+            (true, None) => {
+                let typed = eip712::AtomicInclusionRequest { slot, txHashes: tx_hashes };
+                typed.eip712_signing_hash(domain)
+            }
+            (false, Some(max_slot)) => {
+                let typed = eip712::SlotRangeInclusionRequest {
+                    slot,
+                    maxSlot: max_slot,
+                    txHashes: tx_hashes,
+                };
+                typed.eip712_signing_hash(domain)
+            }
+            (true, Some(max_slot)) => {
+                let typed = eip712::AtomicSlotRangeInclusionRequest {
+                    slot,
+                    maxSlot: max_slot,
+                    txHashes: tx_hashes,
+                };
+                typed.eip712_signing_hash(domain)
+            }
+        }
     }
 }
 
@@ -221,6 +674,75 @@ impl From<InclusionRequest> for CommitmentRequest {
     }
 }
 
+/// Request to exclude the transactions of some senders, or some transactions, from the block
+/// of a specific slot. It conflicts with the commitments to include any of them at the slot.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExclusionRequest {
+    /// The consensus slot number of the block the transactions must not appear in.
+    pub slot: u64,
+    /// The senders whose transactions must not be included.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub senders: Vec<Address>,
+    /// The hashes of the transactions that must not be included.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tx_hashes: Vec<B256>,
+    /// The signature of the user over the digest of the request.
+    #[serde(skip)]
+    pub signature: Option<Signature>,
+    #[serde(skip)]
+    pub signer: Option<Address>,
+}
+
+impl ExclusionRequest {
+    /// Returns the digest of the request.
+    /// digest = keccak256("exclusion" | le_bytes(target_slot) | le_bytes(len(senders)) |
+    ///                    bytes(sender1) | ... | bytes(tx_hash1) | ...)
+    pub fn digest(&self) -> B256 {
+        exclusion_digest(self.slot, &self.senders, &self.tx_hashes)
+    }
+
+    /// Returns the identifier of the commitment to the request, derived like the one of an
+    /// inclusion request. See [`InclusionRequest::commitment_id`].
+    pub fn commitment_id(&self) -> B256 {
+        commitment_id(self.digest(), self.slot)
+    }
+
+    /// Returns the EIP-712 digest of the request in the domain of the given chain, without a
+    /// verifying contract.
+    pub fn typed_digest(&self, chain_id: u64) -> B256 {
+        self.typed_digest_in(&eip712_domain(chain_id, None))
+    }
+
+    /// Returns the EIP-712 digest of the request in the given domain.
+    pub fn typed_digest_in(&self, domain: &Eip712Domain) -> B256 {
+        let typed = eip712::ExclusionRequest {
+            slot: self.slot,
+            senders: self.senders.clone(),
+            txHashes: self.tx_hashes.clone(),
+        };
+        typed.eip712_signing_hash(domain)
+    }
+
+    /// Validates the targets of the request: it must exclude at least one sender or
+    /// transaction, and at most [`MAX_EXCLUSION_TARGETS`].
+    pub fn validate_targets(&self) -> bool {
+        let targets = self.senders.len() + self.tx_hashes.len();
+        (1..=MAX_EXCLUSION_TARGETS).contains(&targets)
+    }
+
+    /// Returns true if the request excludes the transaction, by its sender or its hash.
+    pub fn excludes(&self, tx: &FullTransaction) -> bool {
+        self.tx_hashes.contains(tx.hash()) ||
+            tx.sender().is_some_and(|sender| self.senders.contains(&sender))
+    }
+}
+
+impl From<ExclusionRequest> for CommitmentRequest {
+    fn from(req: ExclusionRequest) -> Self {
+        CommitmentRequest::Exclusion(req)
+    }
+}
+
 pub trait ECDSASignatureExt {
     /// Returns the ECDSA signature as bytes with the correct parity bit.
     fn as_bytes_with_parity(&self) -> [u8; 65];
@@ -245,7 +767,18 @@ impl ECDSASignatureExt for Signature {
 
 #[cfg(test)]
 mod tests {
-    use super::{CommitmentRequest, InclusionRequest};
+    use alloy::{
+        dyn_abi::TypedData,
+        primitives::{b256, Address},
+        signers::{local::PrivateKeySigner, Signer},
+    };
+
+    use super::{
+        eip712_domain, CommitmentRequest, ExclusionRequest, InclusionCommitment, InclusionRequest,
+        SignedCommitment, MAX_CONTEXT_LEN, MAX_EXCLUSION_TARGETS,
+    };
+
+    const TEST_TX: &str = "0x02f86c870c72dd9d5e883e4d0183408f2382520894d2e2adf7177b7a8afddbc12d1634cf23ea1a71020180c001a08556dcfea479b34675db3fe08e29486fe719c2b22f6b0c1741ecbbdce4575cc6a01cd48009ccafd6b9f1290bbe2ceea268f94101d1d322c787018423ebcbc87ab4";
 
     #[test]
     fn test_deserialize_inclusion_request() {
@@ -274,11 +807,190 @@ mod tests {
 
         let req: CommitmentRequest = serde_json::from_str(json_req).unwrap();
 
-        #[allow(irrefutable_let_patterns)]
         if let CommitmentRequest::Inclusion(req) = req {
             assert_eq!(req.slot, 10);
         } else {
             panic!("Expected Inclusion request");
         }
     }
+
+    #[tokio::test]
+    async fn test_request_context() -> eyre::Result<()> {
+        let json_req = format!(r#"{{"slot": 10, "txs": ["{TEST_TX}"], "context": "user-42"}}"#);
+        let req: InclusionRequest = serde_json::from_str(&json_req)?;
+        assert_eq!(req.context.as_deref(), Some("user-42"));
+        assert!(req.validate_context());
+
+        // The context doesn't change the digest
+        let without = InclusionRequest { context: None, ..req.clone() };
+        assert_eq!(req.digest(), without.digest());
+        assert!(!serde_json::to_string(&without)?.contains("context"));
+
+        // It's echoed verbatim in the signed commitment
+        let signer = PrivateKeySigner::random();
+        let commitment = CommitmentRequest::Inclusion(req).commit_and_sign(&signer).await?;
+        let SignedCommitment::Inclusion(ref inclusion) = commitment;
+        assert_eq!(inclusion.request().context.as_deref(), Some("user-42"));
+        let json = serde_json::to_value(&commitment)?;
+        assert_eq!(json["context"], "user-42");
+
+        let with_context =
+            |context: String| InclusionRequest { context: Some(context), ..without.clone() };
+        assert!(with_context("é".repeat(MAX_CONTEXT_LEN / 2)).validate_context());
+        assert!(!with_context("a".repeat(MAX_CONTEXT_LEN + 1)).validate_context());
+        assert!(!with_context("tab\tseparated".to_string()).validate_context());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_typed_digest() -> eyre::Result<()> {
+        let json_req = format!(r#"{{"slot": 10, "txs": ["{TEST_TX}"]}}"#);
+        let req: InclusionRequest = serde_json::from_str(&json_req)?;
+        let tx_hash = req.txs[0].hash().to_string();
+
+        // The typed data a wallet signs with `eth_signTypedData_v4`
+        let typed_data: TypedData = serde_json::from_value(serde_json::json!({
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "chainId", "type": "uint256" },
+                ],
+                "InclusionRequest": [
+                    { "name": "slot", "type": "uint64" },
+                    { "name": "txHashes", "type": "bytes32[]" },
+                ],
+            },
+            "primaryType": "InclusionRequest",
+            "domain": { "name": "bolt", "chainId": 17000 },
+            "message": { "slot": 10, "txHashes": [tx_hash] },
+        }))?;
+        let digest = req.typed_digest(17000);
+        assert_eq!(typed_data.eip712_signing_hash()?, digest);
+
+        let signer = PrivateKeySigner::random();
+        let signature = signer.sign_hash(&digest).await?;
+        assert_eq!(signature.recover_address_from_prehash(&digest)?, signer.address());
+
+        // The digest is bound to the chain and the verifying contract
+        assert_ne!(digest, req.digest());
+        assert_ne!(digest, req.typed_digest(1));
+        let contract = eip712_domain(17000, Some(Address::repeat_byte(1)));
+        assert_ne!(digest, req.typed_digest_in(&contract));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_commitment_id() -> eyre::Result<()> {
+        let json_req = format!(r#"{{"slot": 10, "txs": ["{TEST_TX}"]}}"#);
+        let req: InclusionRequest = serde_json::from_str(&json_req)?;
+
+        // Golden vectors, which clients computing the ids must reproduce
+        let digest = b256!("43c992cee01bb91c5eaab56eab1f53d5330f9ca8a02e5316d3f65050302fea70");
+        let id = b256!("8570c97acaa27a336e162beef519b7d7a498ce587fdac11356a3e59ddaa2f7b5");
+        assert_eq!(req.digest(), digest);
+        assert_eq!(req.commitment_id(), id);
+        let later = InclusionRequest { slot: 11, ..req.clone() };
+        assert_ne!(later.commitment_id(), id);
+
+        // The id is returned with the commitment, and survives a round trip
+        let signer = PrivateKeySigner::random();
+        let commitment = CommitmentRequest::Inclusion(req).commit_and_sign(&signer).await?;
+        let commitment = commitment.into_inclusion().unwrap();
+        assert_eq!(commitment.commitment_id(), id);
+        let mut json = serde_json::to_value(&commitment)?;
+        assert_eq!(json["commitment_id"], id.to_string());
+        assert_eq!(serde_json::from_value::<InclusionCommitment>(json.clone())?, commitment);
+
+        // It is derived when missing, and must otherwise match the request
+        json.as_object_mut().unwrap().remove("commitment_id");
+        assert_eq!(serde_json::from_value::<InclusionCommitment>(json.clone())?, commitment);
+        json["commitment_id"] = serde_json::json!(digest);
+        assert!(serde_json::from_value::<InclusionCommitment>(json).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_atomic_digest() -> eyre::Result<()> {
+        let json_req = format!(r#"{{"slot": 10, "txs": ["{TEST_TX}"], "atomic": true}}"#);
+        let atomic: InclusionRequest = serde_json::from_str(&json_req)?;
+        assert!(atomic.atomic);
+
+        // The flag is bound by the digests, and only serialized when set
+        let digest = b256!("4fe8673665bda12dfd3927848b9a15a36a9354dc04fb755e5fbb55629d52053c");
+        assert_eq!(atomic.digest(), digest);
+        let plain = InclusionRequest { atomic: false, ..atomic.clone() };
+        assert_ne!(plain.digest(), digest);
+        assert_ne!(plain.typed_digest(1), atomic.typed_digest(1));
+        assert!(!serde_json::to_string(&plain)?.contains("atomic"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_slot_range_digest() -> eyre::Result<()> {
+        let json_req = format!(r#"{{"slot": 10, "txs": ["{TEST_TX}"], "max_slot": 14}}"#);
+        let range: InclusionRequest = serde_json::from_str(&json_req)?;
+        assert_eq!(range.max_slot, Some(14));
+
+        // Both slots are bound by the digests, and the range is only serialized when set
+        let digest = b256!("5e955aa5df4284ead29474e680b66918beb3dc605837489ad3147ead7cd9efc3");
+        assert_eq!(range.digest(), digest);
+        let single = InclusionRequest { max_slot: None, ..range.clone() };
+        assert_ne!(single.digest(), digest);
+        assert_ne!(InclusionRequest { slot: 11, ..range.clone() }.digest(), digest);
+        assert_ne!(single.typed_digest(1), range.typed_digest(1));
+        assert!(!serde_json::to_string(&single)?.contains("max_slot"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_chain_digest() -> eyre::Result<()> {
+        let json_req = format!(r#"{{"slot": 10, "txs": ["{TEST_TX}"]}}"#);
+        let req: InclusionRequest = serde_json::from_str(&json_req)?;
+
+        // The signed digest is scoped to the chain, and the legacy one still identifies the request
+        let mainnet = b256!("0cd166e098b27f4c6a9ec08f3d09a92b9517a61568cb5e9c3e024288e0b069d6");
+        let holesky = b256!("7702e06482a8981073d6cbf142055dc97d01b1bb8f893ab0aae87550c390ce5e");
+        assert_eq!(req.chain_digest(1), mainnet);
+        assert_eq!(req.chain_digest(17000), holesky);
+        assert_ne!(mainnet, holesky);
+        let legacy = b256!("43c992cee01bb91c5eaab56eab1f53d5330f9ca8a02e5316d3f65050302fea70");
+        assert_eq!(req.digest(), legacy);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exclusion_commitment() -> eyre::Result<()> {
+        let sender = Address::repeat_byte(1);
+        let json_req = format!(r#"{{"slot": 10, "senders": ["{sender}"]}}"#);
+        let req: CommitmentRequest = serde_json::from_str(&json_req)?;
+        let exclusion = req.as_exclusion_request().cloned().expect("exclusion request");
+        assert_eq!(exclusion.senders, vec![sender]);
+        assert!(exclusion.validate_targets());
+
+        // Golden vector of the digest, prefixed to set it apart from the inclusion ones
+        let digest = b256!("da3c243d859e012d7ef178fc0bb7ab21ac880a68fb8f18440d6d2c8ba9b04fb5");
+        assert_eq!(req.digest(), digest);
+
+        // The commitment survives a round trip, as an exclusion one
+        let signer = PrivateKeySigner::random();
+        let commitment = req.commit_and_sign(&signer).await?;
+        assert_eq!(commitment.commitment_id(), exclusion.commitment_id());
+        let json = serde_json::to_value(&commitment)?;
+        assert!(json.get("tx_hashes").is_none());
+        assert_eq!(serde_json::from_value::<SignedCommitment>(json)?, commitment);
+        let commitment = commitment.into_exclusion().expect("exclusion commitment");
+        assert_eq!(commitment.signature().recover_address_from_prehash(&digest)?, signer.address());
+
+        // Too many targets are rejected
+        let senders = vec![sender; MAX_EXCLUSION_TARGETS + 1];
+        assert!(!ExclusionRequest { senders, ..exclusion }.validate_targets());
+
+        Ok(())
+    }
 }
@@ -1,10 +1,16 @@
-use alloy::primitives::{keccak256, Address};
+use alloy::primitives::{keccak256, Address, B256};
 use secp256k1::Message;
 use serde::Serialize;
 
-use crate::crypto::{bls::BLSSig, ecdsa::SignableECDSA, SignableBLS};
+use crate::{
+    builder::signature::compute_signing_root,
+    crypto::{bls::BLSSig, ecdsa::SignableECDSA, SignableBLS},
+};
 
-use super::{FullTransaction, InclusionRequest};
+use super::{
+    commitment::exclusion_digest, ssz::SszEncoding, ExclusionRequest, FullTransaction,
+    InclusionRequest,
+};
 
 /// What the proposer sidecar will need to sign to confirm the inclusion request.
 impl SignableECDSA for ConstraintsMessage {
@@ -18,6 +24,15 @@ impl SignableECDSA for ConstraintsMessage {
             constraint_bytes.extend_from_slice(&constraint.as_bytes());
         }
         data.extend_from_slice(&constraint_bytes);
+        for exclusion in &self.exclusions {
+            data.extend_from_slice(&exclusion.as_bytes());
+        }
+        if let Some(max_slot) = self.max_slot {
+            data.extend_from_slice(&max_slot.to_le_bytes());
+        }
+        if self.atomic {
+            data.push(1);
+        }
 
         let hash = keccak256(data).0;
         Message::from_digest_slice(&hash).expect("digest")
@@ -50,15 +65,67 @@ pub struct ConstraintsMessage {
     pub slot: u64,
     /// The constraints that need to be signed.
     pub constraints: Vec<Constraint>,
+    /// Whether the constraints must be included together, in their order, or not at all.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub atomic: bool,
+    /// The transactions that must not be included in the block.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exclusions: Vec<Exclusion>,
+    /// The last slot of the range of slots targeted by the request, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_slot: Option<u64>,
 }
 
 impl ConstraintsMessage {
-    /// Builds a constraints message from an inclusion request and metadata
+    /// Builds a constraints message from an inclusion request and metadata. The constraints
+    /// are in the order of the transactions of the request.
     pub fn build(validator_index: u64, request: InclusionRequest) -> Self {
         let constraints =
             request.txs.into_iter().map(|tx| Constraint::from_transaction(tx, None)).collect();
 
-        Self { validator_index, slot: request.slot, constraints }
+        let (slot, atomic, max_slot) = (request.slot, request.atomic, request.max_slot);
+        Self { validator_index, slot, constraints, atomic, max_slot, ..Default::default() }
+    }
+
+    /// Builds a constraints message from an exclusion request and metadata. The senders are
+    /// excluded first, then the transactions, in the order of the request.
+    pub fn build_exclusion(validator_index: u64, request: ExclusionRequest) -> Self {
+        let senders = request.senders.into_iter().map(Exclusion::Sender);
+        let exclusions = senders.chain(request.tx_hashes.into_iter().map(Exclusion::Transaction));
+
+        Self {
+            validator_index,
+            slot: request.slot,
+            exclusions: exclusions.collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the digest of the request the constraints were built from, see
+    /// [`InclusionRequest::digest`] and [`ExclusionRequest::digest`].
+    pub fn request_digest(&self) -> B256 {
+        if !self.exclusions.is_empty() {
+            let (mut senders, mut tx_hashes) = (Vec::new(), Vec::new());
+            for exclusion in &self.exclusions {
+                match *exclusion {
+                    Exclusion::Sender(sender) => senders.push(sender),
+                    Exclusion::Transaction(hash) => tx_hashes.push(hash),
+                }
+            }
+            return exclusion_digest(self.slot, &senders, &tx_hashes);
+        }
+
+        let hashes = self.constraints.iter().map(|c| c.transaction.hash().as_slice());
+        let mut data = hashes.collect::<Vec<_>>().concat();
+        data.extend_from_slice(&self.slot.to_le_bytes());
+        if let Some(max_slot) = self.max_slot {
+            data.extend_from_slice(&max_slot.to_le_bytes());
+        }
+        if self.atomic {
+            data.push(1);
+        }
+
+        keccak256(&data)
     }
 }
 
@@ -73,11 +140,55 @@ impl SignableBLS for ConstraintsMessage {
             constraint_bytes.extend_from_slice(&constraint.as_bytes());
         }
         data.extend_from_slice(&constraint_bytes);
+        for exclusion in &self.exclusions {
+            data.extend_from_slice(&exclusion.as_bytes());
+        }
+        if let Some(max_slot) = self.max_slot {
+            data.extend_from_slice(&max_slot.to_le_bytes());
+        }
+        if self.atomic {
+            data.push(1);
+        }
 
         keccak256(data).0.to_vec()
     }
 }
 
+/// The digest of the constraints messages signed by the proposer sidecar.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConstraintDigest {
+    /// The keccak256 digest of the messages, see [`SignableBLS::digest`].
+    #[default]
+    Keccak,
+    /// The SSZ hash tree root of the messages, as computed by the relays. The messages that
+    /// use the atomic, range or exclusion constraints have no SSZ encoding, and are still
+    /// signed over their keccak256 digest.
+    HashTreeRoot,
+}
+
+impl ConstraintsMessage {
+    /// Returns the digest of the message to sign, under the given scheme.
+    pub fn signing_digest(&self, scheme: ConstraintDigest) -> Vec<u8> {
+        match scheme {
+            ConstraintDigest::Keccak => SignableBLS::digest(self),
+            ConstraintDigest::HashTreeRoot => self
+                .hash_tree_root()
+                .map(|root| root.to_vec())
+                .unwrap_or_else(|_| SignableBLS::digest(self)),
+        }
+    }
+
+    /// Returns the data to sign for the message under the given scheme: its digest, or the
+    /// signing root of the digest in the signing domain of its slot if it has one, see
+    /// [`crate::ChainConfig::constraint_domain`].
+    pub fn signing_data(&self, scheme: ConstraintDigest, domain: Option<[u8; 32]>) -> Vec<u8> {
+        let digest = self.signing_digest(scheme);
+        let Some(domain) = domain else { return digest };
+        let object_root = digest.try_into().expect("32-byte digest");
+        compute_signing_root(object_root, domain).to_vec()
+    }
+}
+
 /// A general constraint on block building.
 ///
 /// Reference: https://chainbound.github.io/bolt-docs/api/builder-api#ethv1builderconstraints
@@ -109,3 +220,32 @@ impl Constraint {
         self.transaction.sender().expect("Recovered sender")
     }
 }
+
+/// A constraint that keeps transactions out of the block.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Exclusion {
+    /// No transaction of the sender may be included.
+    Sender(Address),
+    /// The transaction with the hash may not be included.
+    Transaction(B256),
+}
+
+impl Exclusion {
+    /// Converts the exclusion to a byte representation useful for signing: a tag byte
+    /// followed by the address or the hash.
+    pub fn as_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Sender(sender) => [&[0], sender.as_slice()].concat(),
+            Self::Transaction(hash) => [&[1], hash.as_slice()].concat(),
+        }
+    }
+
+    /// Returns true if the exclusion applies to the transaction.
+    pub fn excludes(&self, tx: &FullTransaction) -> bool {
+        match self {
+            Self::Sender(sender) => tx.sender() == Some(*sender),
+            Self::Transaction(hash) => tx.hash() == hash,
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use std::{fs, io, path::Path};
+
+use alloy::primitives::{Address, B256};
+use blst::min_pk::{PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::crypto::{bls::BLSSig, SignableBLS, SignerBackend, SignerError};
+
+use super::{ssz::SszEncoding, BlsPublicKey};
+
+/// Errors of the loading of the delegations produced with the `delegate` subcommand.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum DelegationError {
+    #[error("Failed to read the delegations: {0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid delegations file: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Invalid signature of the delegation of validator {0}")]
+    InvalidSignature(BlsPublicKey),
+}
+
+/// Whether a delegation message grants or revokes the authority to set constraints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DelegationAction {
+    /// The delegatee may sign the constraints of the validator.
+    Delegate = 0,
+    /// The delegatee may no longer sign the constraints of the validator.
+    Revoke = 1,
+}
+
+/// A message of a validator that delegates the authority to sign its constraints to another
+/// BLS key, or revokes it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DelegationMessage {
+    /// Whether the authority is delegated or revoked.
+    pub action: DelegationAction,
+    /// The public key of the validator.
+    pub validator_pubkey: BlsPublicKey,
+    /// The public key the constraints of the validator are signed with.
+    pub delegatee_pubkey: BlsPublicKey,
+    /// The address of the slasher contract that may slash the validator for the
+    /// constraints signed by the delegatee.
+    pub slasher: Address,
+}
+
+impl DelegationMessage {
+    /// Returns the root the validator signs: the SSZ hash tree root of the message.
+    pub fn signing_root(&self) -> B256 {
+        self.hash_tree_root().expect("Fixed-size container")
+    }
+}
+
+impl SignableBLS for DelegationMessage {
+    fn digest(&self) -> Vec<u8> {
+        self.signing_root().to_vec()
+    }
+}
+
+/// A delegation message signed by the key of its validator.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignedDelegation {
+    /// The delegation message.
+    pub message: DelegationMessage,
+    /// The BLS signature of the validator over the signing root of the message.
+    pub signature: BLSSig,
+}
+
+impl SignedDelegation {
+    /// Signs the delegation message with the key of its validator on the signer backend.
+    pub async fn sign_with_backend(
+        message: DelegationMessage,
+        backend: &dyn SignerBackend,
+    ) -> Result<Self, SignerError> {
+        let validator = PublicKey::from_bytes(message.validator_pubkey.as_ref())
+            .map_err(|err| SignerError::Local(format!("invalid validator key: {err:?}")))?;
+        let root = message.signing_root();
+        let signature = backend.sign_bls_with(&validator, root.as_slice()).await?;
+
+        Ok(Self { message, signature })
+    }
+
+    /// Returns true if the message was signed by the key of its validator.
+    pub fn verify(&self) -> bool {
+        let Ok(pubkey) = PublicKey::from_bytes(self.message.validator_pubkey.as_ref()) else {
+            return false;
+        };
+        Signature::from_bytes(self.signature.as_slice())
+            .is_ok_and(|signature| self.message.verify(&signature, &pubkey))
+    }
+}
+
+/// Signs delegation messages of the given action to the delegatee key, one from each
+/// validator key of the signer backend.
+pub async fn sign_delegations(
+    backend: &dyn SignerBackend,
+    action: DelegationAction,
+    delegatee_pubkey: BlsPublicKey,
+    slasher: Address,
+) -> Result<Vec<SignedDelegation>, SignerError> {
+    let mut delegations = Vec::new();
+    for validator in backend.validator_pubkeys() {
+        let validator_pubkey =
+            BlsPublicKey::try_from(validator.to_bytes().as_slice()).expect("valid pubkey bytes");
+        let delegatee_pubkey = delegatee_pubkey.clone();
+        let message = DelegationMessage { action, validator_pubkey, delegatee_pubkey, slasher };
+        delegations.push(SignedDelegation::sign_with_backend(message, backend).await?);
+    }
+
+    Ok(delegations)
+}
+
+/// Reads the signed delegations of the JSON file written by the `delegate` subcommand,
+/// checking their signatures.
+pub fn load_delegations(path: &Path) -> Result<Vec<SignedDelegation>, DelegationError> {
+    let delegations: Vec<SignedDelegation> = serde_json::from_slice(&fs::read(path)?)?;
+    if let Some(invalid) = delegations.iter().find(|delegation| !delegation.verify()) {
+        return Err(DelegationError::InvalidSignature(invalid.message.validator_pubkey.clone()));
+    }
+
+    Ok(delegations)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy::signers::local::PrivateKeySigner;
+
+    use super::*;
+    use crate::{
+        crypto::{
+            bls::{random_bls_secret, Signer as BlsSigner, BLS_DST_PREFIX},
+            LocalSigner,
+        },
+        test_util::test_bls_secret_key,
+    };
+
+    fn pubkey_of(key: &blst::min_pk::SecretKey) -> BlsPublicKey {
+        BlsPublicKey::try_from(key.sk_to_pk().to_bytes().as_slice()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sign_delegations() -> eyre::Result<()> {
+        let validator_key = random_bls_secret();
+        let signer =
+            LocalSigner::new(BlsSigner::new(test_bls_secret_key()), PrivateKeySigner::random())
+                .with_validator_keys(vec![validator_key.clone()]);
+        let backend: Arc<dyn SignerBackend> = Arc::new(signer);
+
+        let delegatee = pubkey_of(&random_bls_secret());
+        let slasher = Address::repeat_byte(0x51);
+        let delegations = sign_delegations(
+            backend.as_ref(),
+            DelegationAction::Delegate,
+            delegatee.clone(),
+            slasher,
+        )
+        .await?;
+        assert_eq!(delegations.len(), 2);
+
+        // The constraint key signs the SSZ hash tree root of its delegation
+        let delegation = &delegations[0];
+        assert_eq!(delegation.message.validator_pubkey, pubkey_of(&test_bls_secret_key()));
+        assert_eq!(delegation.message.delegatee_pubkey, delegatee);
+        let root = delegation.message.hash_tree_root()?;
+        let signature = Signature::from_bytes(delegation.signature.as_slice()).unwrap();
+        let pubkey = test_bls_secret_key().sk_to_pk();
+        let result = signature.verify(true, root.as_slice(), BLS_DST_PREFIX, &[], &pubkey, true);
+        assert_eq!(result, blst::BLST_ERROR::BLST_SUCCESS);
+        assert!(delegation.verify());
+
+        // Each validator key signs its own delegation
+        assert_eq!(delegations[1].message.validator_pubkey, pubkey_of(&validator_key));
+        assert!(delegations[1].verify());
+
+        // Revocations differ from delegations only by their action
+        let revocations =
+            sign_delegations(backend.as_ref(), DelegationAction::Revoke, delegatee, slasher)
+                .await?;
+        assert!(revocations.iter().all(SignedDelegation::verify));
+        assert_ne!(revocations[0].message.signing_root(), delegation.message.signing_root());
+
+        // The delegations are loaded from their JSON file, with their signatures checked
+        let path = std::env::temp_dir().join(format!("bolt-{}.json", rand::random::<u64>()));
+        fs::write(&path, serde_json::to_vec(&delegations)?)?;
+        assert_eq!(load_delegations(&path)?, delegations);
+
+        let mut forged = delegations.clone();
+        forged[1].message.delegatee_pubkey = pubkey_of(&random_bls_secret());
+        fs::write(&path, serde_json::to_vec(&forged)?)?;
+        let err = load_delegations(&path).unwrap_err();
+        let invalid = pubkey_of(&validator_key);
+        assert!(matches!(err, DelegationError::InvalidSignature(ref key) if *key == invalid));
+        fs::remove_file(path)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,317 @@
+use std::{sync::Arc, time::Duration};
+
+use alloy::primitives::{keccak256, Address, SignatureError};
+use blst::min_pk::{PublicKey, Signature};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::crypto::{bls::BLSSig, SignableBLS, SignerBLS, SignerBackend, SignerError};
+
+use super::{
+    commitment::{InclusionCommitment, COMMITMENT_DIGEST_VERSION},
+    BlsPublicKey,
+};
+
+/// Default validity period of the identity documents signed by the sidecar (7 days).
+pub const DEFAULT_IDENTITY_VALIDITY: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Domain separator of the identity document digest, so that the signature of a document
+/// can't be passed off as the signature of another message of the validator key.
+const IDENTITY_DOMAIN: &[u8] = b"BOLT_SIDECAR_IDENTITY_V1";
+
+/// Errors verifying a commitment against an identity document.
+#[derive(Debug, Error)]
+pub enum IdentityError {
+    /// The identity document was signed by another validator than expected.
+    #[error("Identity document was signed by validator {actual}, expected {expected}")]
+    PubkeyMismatch {
+        /// The public key of the validator the commitment is expected from.
+        expected: BlsPublicKey,
+        /// The public key the identity document was signed with.
+        actual: BlsPublicKey,
+    },
+    /// The signature of the identity document is invalid.
+    #[error("Invalid identity document signature")]
+    InvalidSignature,
+    /// The identity document is not valid yet.
+    #[error("Identity document is only valid from {0}")]
+    NotYetValid(u64),
+    /// The identity document has expired.
+    #[error("Identity document expired at {0}")]
+    Expired(u64),
+    /// The identity document doesn't list the digest version of the commitment.
+    #[error("Identity document doesn't support commitment digest version {0}")]
+    UnsupportedDigestVersion(u8),
+    /// The committed transactions are for another chain than the identity document.
+    #[error("Commitment is not for chain {0} of the identity document")]
+    ChainIdMismatch(u64),
+    /// The commitment was not signed by the address of the identity document.
+    #[error("Commitment was signed by {recovered}, expected {expected}")]
+    SignerMismatch {
+        /// The commitment address of the identity document.
+        expected: Address,
+        /// The address recovered from the commitment signature.
+        recovered: Address,
+    },
+    /// The signature of the commitment is malformed.
+    #[error("Invalid commitment signature: {0}")]
+    InvalidCommitmentSignature(#[from] SignatureError),
+}
+
+/// The identity of a sidecar: the address it signs commitments with on behalf of a validator.
+///
+/// Signed by the validator BLS key, it lets counterparties authenticate the commitment
+/// signer instead of trusting whatever signature is in the commitment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdentityDocument {
+    /// The address the sidecar signs commitments with.
+    pub commitment_address: Address,
+    /// The versions of the commitment digest the sidecar signs.
+    pub digest_versions: Vec<u8>,
+    /// The chain the sidecar commits on.
+    pub chain_id: u64,
+    /// The start of the validity period, as a UNIX timestamp in seconds.
+    pub valid_from: u64,
+    /// The end of the validity period (exclusive), as a UNIX timestamp in seconds.
+    pub valid_until: u64,
+}
+
+impl IdentityDocument {
+    /// Creates an identity document for the current commitment digest version, valid for
+    /// `validity` from the `valid_from` timestamp.
+    pub fn new(
+        commitment_address: Address,
+        chain_id: u64,
+        valid_from: u64,
+        validity: Duration,
+    ) -> Self {
+        Self {
+            commitment_address,
+            digest_versions: vec![COMMITMENT_DIGEST_VERSION],
+            chain_id,
+            valid_from,
+            valid_until: valid_from.saturating_add(validity.as_secs()),
+        }
+    }
+
+    /// Returns true if the document is valid at the given UNIX timestamp, in seconds.
+    pub fn is_valid_at(&self, timestamp: u64) -> bool {
+        self.valid_from <= timestamp && timestamp < self.valid_until
+    }
+}
+
+impl SignableBLS for IdentityDocument {
+    /// digest = keccak256(domain | address | le_bytes(chain_id) | le_bytes(valid_from) |
+    /// le_bytes(valid_until) | digest_versions)
+    fn digest(&self) -> Vec<u8> {
+        let mut data = IDENTITY_DOMAIN.to_vec();
+        data.extend_from_slice(self.commitment_address.as_slice());
+        data.extend_from_slice(&self.chain_id.to_le_bytes());
+        data.extend_from_slice(&self.valid_from.to_le_bytes());
+        data.extend_from_slice(&self.valid_until.to_le_bytes());
+        // The only variable-length field comes last, so the encoding is unambiguous
+        data.extend_from_slice(&self.digest_versions);
+
+        keccak256(data).to_vec()
+    }
+}
+
+/// An identity document signed by a validator BLS key, served at `GET /identity`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignedIdentity {
+    /// The identity document.
+    pub message: IdentityDocument,
+    /// The public key of the validator that signed the document.
+    pub pubkey: BlsPublicKey,
+    /// The BLS signature over the digest of the document.
+    pub signature: BLSSig,
+}
+
+impl SignedIdentity {
+    /// Signs the identity document with the given validator key.
+    pub fn sign<S: SignerBLS>(message: IdentityDocument, signer: &S) -> eyre::Result<Self> {
+        let signature = signer.sign(&message.digest())?;
+        let pubkey = BlsPublicKey::try_from(signer.pubkey().to_bytes().as_slice())
+            .expect("valid pubkey bytes");
+
+        Ok(Self { message, pubkey, signature })
+    }
+
+    /// Signs the identity document with the constraint key of the signer backend.
+    pub async fn sign_with_backend(
+        message: IdentityDocument,
+        backend: &dyn SignerBackend,
+    ) -> Result<Self, SignerError> {
+        let signature = backend.sign_bls(&message.digest()).await?;
+        let pubkey = BlsPublicKey::try_from(backend.constraint_pubkey().to_bytes().as_slice())
+            .expect("valid pubkey bytes");
+
+        Ok(Self { message, pubkey, signature })
+    }
+
+    /// Verifies that the document was signed by the given validator.
+    pub fn verify(&self, validator_pubkey: &BlsPublicKey) -> Result<(), IdentityError> {
+        if self.pubkey != *validator_pubkey {
+            return Err(IdentityError::PubkeyMismatch {
+                expected: validator_pubkey.clone(),
+                actual: self.pubkey.clone(),
+            });
+        }
+
+        let pubkey = PublicKey::from_bytes(self.pubkey.as_ref())
+            .map_err(|_| IdentityError::InvalidSignature)?;
+        let signature = Signature::from_bytes(self.signature.as_slice())
+            .map_err(|_| IdentityError::InvalidSignature)?;
+
+        if !self.message.verify(&signature, &pubkey) {
+            return Err(IdentityError::InvalidSignature);
+        }
+
+        Ok(())
+    }
+
+    /// Verifies that the commitment was signed by the sidecar of the given validator at
+    /// the given UNIX timestamp, in seconds, according to this identity document.
+    pub fn verify_commitment(
+        &self,
+        commitment: &InclusionCommitment,
+        validator_pubkey: &BlsPublicKey,
+        timestamp: u64,
+    ) -> Result<(), IdentityError> {
+        self.verify(validator_pubkey)?;
+
+        let IdentityDocument { commitment_address, chain_id, valid_from, valid_until, .. } =
+            self.message;
+        if timestamp < valid_from {
+            return Err(IdentityError::NotYetValid(valid_from));
+        }
+        if timestamp >= valid_until {
+            return Err(IdentityError::Expired(valid_until));
+        }
+
+        if !self.message.digest_versions.contains(&COMMITMENT_DIGEST_VERSION) {
+            return Err(IdentityError::UnsupportedDigestVersion(COMMITMENT_DIGEST_VERSION));
+        }
+
+        let request = commitment.request();
+        if !request.validate_chain_id(chain_id) {
+            return Err(IdentityError::ChainIdMismatch(chain_id));
+        }
+
+        let recovered = commitment.signature().recover_address_from_prehash(&request.digest())?;
+        if recovered != commitment_address {
+            return Err(IdentityError::SignerMismatch { expected: commitment_address, recovered });
+        }
+
+        Ok(())
+    }
+}
+
+/// The identity document served by the commitments API, shared with the driver that signs
+/// and renews it. Nothing is served while it is unset.
+#[derive(Debug, Clone, Default)]
+pub struct ServedIdentity(Arc<RwLock<Option<SignedIdentity>>>);
+
+impl ServedIdentity {
+    /// Returns the served identity document, if any.
+    pub fn get(&self) -> Option<SignedIdentity> {
+        self.0.read().clone()
+    }
+
+    /// Sets the served identity document, or stops serving one.
+    pub fn set(&self, identity: Option<SignedIdentity>) {
+        *self.0.write() = identity;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::signers::{local::PrivateKeySigner, Signer as _};
+
+    use crate::{
+        crypto::bls::{random_bls_secret, Signer},
+        primitives::{commitment::SignedCommitment, CommitmentRequest},
+        test_util::{
+            create_signed_commitment_request, default_test_transaction, test_bls_secret_key,
+        },
+    };
+
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    async fn test_commitment(commitment_signer: &PrivateKeySigner) -> InclusionCommitment {
+        let sk = alloy::signers::k256::SecretKey::random(&mut rand::thread_rng());
+        let sender = PrivateKeySigner::from(sk.clone());
+        let tx = default_test_transaction(sender.address(), None);
+        let request: CommitmentRequest =
+            create_signed_commitment_request(&[tx], &sk, 10).await.unwrap();
+
+        let commitment: SignedCommitment =
+            request.commit_and_sign(commitment_signer).await.unwrap();
+        commitment.into_inclusion().unwrap()
+    }
+
+    fn pubkey_of(signer: &Signer) -> BlsPublicKey {
+        BlsPublicKey::try_from(signer.pubkey().to_bytes().as_slice()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_verify_commitment_with_identity() {
+        let validator = Signer::new(test_bls_secret_key());
+        let commitment_signer = PrivateKeySigner::random();
+        let commitment = test_commitment(&commitment_signer).await;
+
+        let document = IdentityDocument::new(
+            commitment_signer.address(),
+            1337,
+            NOW,
+            DEFAULT_IDENTITY_VALIDITY,
+        );
+        let identity = SignedIdentity::sign(document, &validator).unwrap();
+        let pubkey = pubkey_of(&validator);
+
+        identity.verify_commitment(&commitment, &pubkey, NOW + 60).unwrap();
+
+        // The document round-trips through its JSON encoding
+        let json = serde_json::to_string(&identity).unwrap();
+        let decoded: SignedIdentity = serde_json::from_str(&json).unwrap();
+        decoded.verify_commitment(&commitment, &pubkey, NOW + 60).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reject_mismatching_identity() {
+        let validator = Signer::new(test_bls_secret_key());
+        let commitment_signer = PrivateKeySigner::random();
+        let commitment = test_commitment(&commitment_signer).await;
+        let pubkey = pubkey_of(&validator);
+
+        let document = |address| IdentityDocument::new(address, 1337, NOW, Duration::from_secs(60));
+        let identity =
+            SignedIdentity::sign(document(commitment_signer.address()), &validator).unwrap();
+
+        // Another validator
+        let other = pubkey_of(&Signer::new(random_bls_secret()));
+        let err = identity.verify_commitment(&commitment, &other, NOW).unwrap_err();
+        assert!(matches!(err, IdentityError::PubkeyMismatch { .. }), "{err}");
+
+        // Outside of the validity period
+        let err = identity.verify_commitment(&commitment, &pubkey, NOW - 1).unwrap_err();
+        assert!(matches!(err, IdentityError::NotYetValid(NOW)), "{err}");
+        let err = identity.verify_commitment(&commitment, &pubkey, NOW + 60).unwrap_err();
+        assert!(matches!(err, IdentityError::Expired(_)), "{err}");
+
+        // A tampered document
+        let mut tampered = identity.clone();
+        tampered.message.valid_until += 3600;
+        let err = tampered.verify_commitment(&commitment, &pubkey, NOW).unwrap_err();
+        assert!(matches!(err, IdentityError::InvalidSignature), "{err}");
+
+        // A commitment signed by another address
+        let identity =
+            SignedIdentity::sign(document(Address::repeat_byte(1)), &validator).unwrap();
+        let err = identity.verify_commitment(&commitment, &pubkey, NOW).unwrap_err();
+        assert!(matches!(err, IdentityError::SignerMismatch { .. }), "{err}");
+    }
+}
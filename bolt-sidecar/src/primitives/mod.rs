@@ -23,21 +23,41 @@ use tokio::sync::{mpsc, oneshot};
 
 pub use ethereum_consensus::crypto::{PublicKey as BlsPublicKey, Signature as BlsSignature};
 
+/// Parsing and validation of user-supplied addresses.
+pub mod address;
+pub use address::parse_address;
+
 /// Commitment types, received by users wishing to receive preconfirmations.
 pub mod commitment;
-pub use commitment::{CommitmentRequest, InclusionRequest};
+pub use commitment::{CommitmentRequest, ExclusionRequest, InclusionRequest};
 
 /// Constraint types, signed by proposers and sent along the PBS pipeline
 /// for validation.
 pub mod constraint;
-pub use constraint::{BatchedSignedConstraints, ConstraintsMessage, SignedConstraints};
+pub use constraint::{BatchedSignedConstraints, ConstraintsMessage, Exclusion, SignedConstraints};
+
+/// Delegations of the authority to sign the constraints of a validator to another key.
+pub mod delegation;
+pub use delegation::{DelegationAction, DelegationMessage, SignedDelegation};
+
+/// Identity documents, binding the commitment signer of the sidecar to a validator key.
+pub mod identity;
+pub use identity::{IdentityDocument, ServedIdentity, SignedIdentity};
+
+/// Merkle proofs of inclusion of transactions in the execution payload of a block.
+pub mod proof;
+pub use proof::{InclusionProof, PayloadTransactions};
+
+/// SSZ encodings of the commitment and constraint types, following the bolt specs.
+pub mod ssz;
+pub use ssz::SszEncoding;
 use tracing::{error, info};
 
 /// An alias for a Beacon Chain slot number
 pub type Slot = u64;
 
 /// Minimal account state needed for commitment validation.
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct AccountState {
     /// The nonce of the account. This is the number of transactions sent from this account
     pub transaction_count: u64,
@@ -212,7 +232,7 @@ impl From<PayloadAndBlobs> for GetPayloadResponse {
 }
 
 /// A struct representing the current chain head.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ChainHead {
     /// The current slot number.
     pub slot: Arc<AtomicU64>,
@@ -235,6 +255,11 @@ impl ChainHead {
     pub fn block(&self) -> u64 {
         self.block.load(std::sync::atomic::Ordering::SeqCst)
     }
+
+    /// Update the slot number (consensus layer).
+    pub fn update_slot(&self, slot: u64) {
+        self.slot.store(slot, std::sync::atomic::Ordering::SeqCst);
+    }
 }
 
 /// Trait that exposes additional information on transaction types that don't already do it
@@ -257,7 +282,7 @@ impl TransactionExt for PooledTransactionsElement {
             PooledTransactionsElement::Eip2930 { transaction, .. } => transaction.gas_limit,
             PooledTransactionsElement::Eip1559 { transaction, .. } => transaction.gas_limit,
             PooledTransactionsElement::BlobTransaction(blob_tx) => blob_tx.transaction.gas_limit,
-            _ => unimplemented!(),
+            PooledTransactionsElement::Eip7702 { transaction, .. } => transaction.gas_limit,
         }
     }
 
@@ -267,7 +292,7 @@ impl TransactionExt for PooledTransactionsElement {
             PooledTransactionsElement::Eip2930 { transaction, .. } => transaction.value,
             PooledTransactionsElement::Eip1559 { transaction, .. } => transaction.value,
             PooledTransactionsElement::BlobTransaction(blob_tx) => blob_tx.transaction.value,
-            _ => unimplemented!(),
+            PooledTransactionsElement::Eip7702 { transaction, .. } => transaction.value,
         }
     }
 
@@ -277,7 +302,7 @@ impl TransactionExt for PooledTransactionsElement {
             PooledTransactionsElement::Eip2930 { .. } => TxType::Eip2930,
             PooledTransactionsElement::Eip1559 { .. } => TxType::Eip1559,
             PooledTransactionsElement::BlobTransaction(_) => TxType::Eip4844,
-            _ => unimplemented!(),
+            PooledTransactionsElement::Eip7702 { .. } => TxType::Eip7702,
         }
     }
 
@@ -289,7 +314,7 @@ impl TransactionExt for PooledTransactionsElement {
             PooledTransactionsElement::BlobTransaction(blob_tx) => {
                 TxKind::Call(blob_tx.transaction.to)
             }
-            _ => unimplemented!(),
+            PooledTransactionsElement::Eip7702 { transaction, .. } => transaction.to,
         }
     }
 
@@ -299,7 +324,7 @@ impl TransactionExt for PooledTransactionsElement {
             PooledTransactionsElement::Eip2930 { transaction, .. } => &transaction.input,
             PooledTransactionsElement::Eip1559 { transaction, .. } => &transaction.input,
             PooledTransactionsElement::BlobTransaction(blob_tx) => &blob_tx.transaction.input,
-            _ => unimplemented!(),
+            PooledTransactionsElement::Eip7702 { transaction, .. } => &transaction.input,
         }
     }
 
@@ -311,7 +336,7 @@ impl TransactionExt for PooledTransactionsElement {
             PooledTransactionsElement::BlobTransaction(blob_tx) => {
                 Some(blob_tx.transaction.chain_id)
             }
-            _ => unimplemented!(),
+            PooledTransactionsElement::Eip7702 { transaction, .. } => Some(transaction.chain_id),
         }
     }
 
@@ -328,21 +353,28 @@ impl TransactionExt for PooledTransactionsElement {
             PooledTransactionsElement::Eip2930 { transaction, .. } => transaction.size(),
             PooledTransactionsElement::Eip1559 { transaction, .. } => transaction.size(),
             PooledTransactionsElement::BlobTransaction(blob_tx) => blob_tx.transaction.size(),
-            _ => unimplemented!(),
+            PooledTransactionsElement::Eip7702 { transaction, .. } => transaction.size(),
         }
     }
 }
 
 /// A wrapper type for a full, complete transaction (i.e. with blob sidecars attached).
+///
+/// The transaction is shared between clones, as blob sidecars can weigh close to a
+/// megabyte and requests are copied along the pipeline (replay records, block templates).
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FullTransaction {
-    tx: PooledTransactionsElement,
+    tx: Arc<PooledTransactionsElement>,
     sender: Option<Address>,
+    /// The size of the EIP-2718 encoding of the transaction, including the blob sidecar.
+    encoded_len: usize,
 }
 
 impl From<PooledTransactionsElement> for FullTransaction {
     fn from(tx: PooledTransactionsElement) -> Self {
-        Self { tx, sender: None }
+        let mut data = Vec::new();
+        tx.encode_enveloped(&mut data);
+        Self { tx: Arc::new(tx), sender: None, encoded_len: data.len() }
     }
 }
 
@@ -356,19 +388,24 @@ impl std::ops::Deref for FullTransaction {
 
 impl std::ops::DerefMut for FullTransaction {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.tx
+        Arc::make_mut(&mut self.tx)
     }
 }
 
 impl FullTransaction {
     pub fn into_inner(self) -> PooledTransactionsElement {
-        self.tx
+        Arc::unwrap_or_clone(self.tx)
     }
 
     /// Returns the sender of the transaction, if recovered.
     pub fn sender(&self) -> Option<Address> {
         self.sender
     }
+
+    /// Returns the size of the encoded transaction in bytes, including the blob sidecar.
+    pub fn encoded_len(&self) -> usize {
+        self.encoded_len
+    }
 }
 
 impl serde::Serialize for FullTransaction {
@@ -385,7 +422,7 @@ impl<'de> serde::Deserialize<'de> for FullTransaction {
         let data = hex::decode(s.trim_start_matches("0x")).map_err(de::Error::custom)?;
         PooledTransactionsElement::decode_enveloped(&mut data.as_slice())
             .map_err(de::Error::custom)
-            .map(|tx| FullTransaction { tx, sender: None })
+            .map(|tx| FullTransaction { tx: Arc::new(tx), sender: None, encoded_len: data.len() })
     }
 }
 
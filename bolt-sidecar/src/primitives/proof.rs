@@ -0,0 +1,223 @@
+//! Merkle proofs of inclusion of transactions in the execution payload of a block.
+//!
+//! The transactions of a payload are an SSZ `List[Transaction, MAX_TRANSACTIONS_PER_PAYLOAD]`,
+//! whose hash tree root is the `transactions_root` of the payload header. The proof of the
+//! transaction at index `i` is the branch of the leaf at generalized index `2**21 + i` of the
+//! list: the 20 sibling nodes from the root of the transaction up to the root of the list
+//! data, then the length of the list.
+
+use alloy::primitives::{TxHash, B256};
+use serde::{Deserialize, Serialize};
+
+/// The depth of the tree of the transactions of a payload, of `MAX_TRANSACTIONS_PER_PAYLOAD`
+/// leaves.
+pub const TRANSACTIONS_TREE_DEPTH: usize = 20;
+
+/// The number of chunks of the tree of the bytes of a transaction, of
+/// `MAX_BYTES_PER_TRANSACTION` bytes.
+const TRANSACTION_CHUNKS: usize = 1 << 25;
+
+/// The proof of inclusion of a transaction in the execution payload of a block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionProof {
+    /// The hash of the transaction.
+    pub tx_hash: TxHash,
+    /// The index of the transaction in the payload.
+    pub index: u64,
+    /// The sibling nodes from the root of the transaction up to the transactions root, the
+    /// last one being the length of the list.
+    pub branch: Vec<B256>,
+}
+
+impl TransactionProof {
+    /// Returns true if the proof shows that the EIP-2718 encoded transaction is at its index
+    /// in the payload with the given transactions root.
+    pub fn verify(&self, transaction: &[u8], transactions_root: B256) -> bool {
+        let depth = TRANSACTIONS_TREE_DEPTH;
+        if self.branch.len() != depth + 1 || self.index >= 1 << depth {
+            return false;
+        }
+
+        let leaf = transaction_root(transaction);
+        let root = self.branch.iter().enumerate().fold(leaf, |node, (level, sibling)| {
+            if (self.index >> level) & 1 == 1 {
+                hash_pair(sibling, &node)
+            } else {
+                hash_pair(&node, sibling)
+            }
+        });
+        root == transactions_root
+    }
+}
+
+/// The proofs of inclusion of the transactions of a commitment in the block of its slot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InclusionProof {
+    /// The hash of the block.
+    pub block_hash: B256,
+    /// The SSZ hash tree root of the transactions of the execution payload of the block.
+    pub transactions_root: B256,
+    /// The proofs of the committed transactions, in the order of the commitment.
+    pub transactions: Vec<TransactionProof>,
+}
+
+/// The transactions of the execution payload of a block, with their Merkle tree.
+#[derive(Debug, Clone)]
+pub struct PayloadTransactions {
+    block_hash: B256,
+    hashes: Vec<TxHash>,
+    /// The layers of the tree, from the roots of the transactions up to the root of the list
+    /// data. The nodes of the zero padding are omitted.
+    layers: Vec<Vec<B256>>,
+}
+
+impl PayloadTransactions {
+    /// Builds the tree of the EIP-2718 encoded transactions of the block, given with their
+    /// hashes in their order in the block.
+    pub fn new<T: AsRef<[u8]>>(
+        block_hash: B256,
+        transactions: impl IntoIterator<Item = (TxHash, T)>,
+    ) -> Self {
+        let transactions = transactions.into_iter();
+        let (hashes, leaves) =
+            transactions.map(|(hash, tx)| (hash, transaction_root(tx.as_ref()))).unzip();
+
+        let mut layers: Vec<Vec<B256>> = vec![leaves];
+        for zero in &zero_hashes()[..TRANSACTIONS_TREE_DEPTH] {
+            let pairs = layers.last().expect("the layer of the leaves").chunks(2);
+            let next = pairs.map(|pair| hash_pair(&pair[0], pair.get(1).unwrap_or(zero)));
+            let next = next.collect();
+            layers.push(next);
+        }
+
+        Self { block_hash, hashes, layers }
+    }
+
+    /// Returns the hash of the block.
+    pub fn block_hash(&self) -> B256 {
+        self.block_hash
+    }
+
+    /// Returns the SSZ hash tree root of the transactions.
+    pub fn transactions_root(&self) -> B256 {
+        let data = self.layers[TRANSACTIONS_TREE_DEPTH].first().copied();
+        let data = data.unwrap_or(zero_hashes()[TRANSACTIONS_TREE_DEPTH]);
+        hash_pair(&data, &length_chunk(self.hashes.len()))
+    }
+
+    /// Returns the proof of the transaction at the given index, or `None` if the payload has
+    /// no such transaction.
+    pub fn prove_index(&self, index: usize) -> Option<TransactionProof> {
+        let tx_hash = *self.hashes.get(index)?;
+
+        let zero = zero_hashes();
+        let siblings = self.layers[..TRANSACTIONS_TREE_DEPTH].iter().enumerate();
+        let siblings = siblings
+            .map(|(depth, layer)| layer.get((index >> depth) ^ 1).copied().unwrap_or(zero[depth]));
+        let branch = siblings.chain([length_chunk(self.hashes.len())]).collect();
+
+        Some(TransactionProof { tx_hash, index: index as u64, branch })
+    }
+
+    /// Returns the proofs of inclusion of the given transactions, or `None` if any of them
+    /// isn't in the payload.
+    pub fn prove(&self, tx_hashes: &[TxHash]) -> Option<InclusionProof> {
+        let proofs = tx_hashes.iter().map(|hash| {
+            let index = self.hashes.iter().position(|tx_hash| tx_hash == hash)?;
+            self.prove_index(index)
+        });
+
+        Some(InclusionProof {
+            block_hash: self.block_hash,
+            transactions_root: self.transactions_root(),
+            transactions: proofs.collect::<Option<_>>()?,
+        })
+    }
+}
+
+/// Returns the SSZ hash tree root of the EIP-2718 encoded transaction, a `ByteList`.
+fn transaction_root(transaction: &[u8]) -> B256 {
+    let data = B256::from(tree_hash::merkle_root(transaction, TRANSACTION_CHUNKS).0);
+    hash_pair(&data, &length_chunk(transaction.len()))
+}
+
+/// Returns the roots of the trees of zero chunks, by depth up to the one of the transactions.
+fn zero_hashes() -> Vec<B256> {
+    let mut zero = vec![B256::ZERO];
+    while zero.len() <= TRANSACTIONS_TREE_DEPTH {
+        let below = zero[zero.len() - 1];
+        zero.push(hash_pair(&below, &below));
+    }
+    zero
+}
+
+/// Returns the chunk of the length of a list, which is mixed in with its data root.
+fn length_chunk(len: usize) -> B256 {
+    let mut chunk = B256::ZERO;
+    chunk[..8].copy_from_slice(&(len as u64).to_le_bytes());
+    chunk
+}
+
+/// Returns the parent of the two nodes of a tree.
+fn hash_pair(left: &B256, right: &B256) -> B256 {
+    let pair = [left.as_slice(), right.as_slice()].concat();
+    B256::from(tree_hash::merkle_root(&pair, 0).0)
+}
+
+#[cfg(test)]
+mod tests {
+    use ethereum_consensus::{
+        bellatrix::mainnet::Transaction,
+        deneb::mainnet::MAX_TRANSACTIONS_PER_PAYLOAD,
+        ssz::prelude::{HashTreeRoot, List},
+    };
+
+    use super::*;
+
+    fn transactions(count: usize) -> Vec<(TxHash, Vec<u8>)> {
+        let bytes = |i: usize| (0..i * 37 + 5).map(|j| (i * 31 + j) as u8).collect::<Vec<_>>();
+        (0..count).map(|i| (TxHash::random(), bytes(i))).collect()
+    }
+
+    #[test]
+    fn test_transactions_root() {
+        for count in [0, 1, 2, 5] {
+            let txs = transactions(count);
+            let payload = PayloadTransactions::new(B256::ZERO, txs.clone());
+
+            // The root is the one of the transactions list of the payload header
+            let list = txs.iter().map(|(_, tx)| Transaction::try_from(tx.as_slice()).unwrap());
+            let list: List<Transaction, MAX_TRANSACTIONS_PER_PAYLOAD> =
+                List::try_from(list.collect::<Vec<_>>()).unwrap();
+            let root = B256::from(list.hash_tree_root().unwrap().0);
+            assert_eq!(payload.transactions_root(), root, "{count} transactions");
+        }
+    }
+
+    #[test]
+    fn test_transaction_proofs() {
+        let txs = transactions(5);
+        let block_hash = B256::random();
+        let payload = PayloadTransactions::new(block_hash, txs.clone());
+        let root = payload.transactions_root();
+
+        for (index, (hash, tx)) in txs.iter().enumerate() {
+            let proof = payload.prove_index(index).unwrap();
+            assert_eq!((proof.tx_hash, proof.index), (*hash, index as u64));
+            assert!(proof.verify(tx, root));
+
+            // The proof doesn't hold for another transaction, index or root
+            assert!(!proof.verify(&txs[(index + 1) % 5].1, root));
+            let moved = TransactionProof { index: index as u64 ^ 1, ..proof.clone() };
+            assert!(!moved.verify(tx, root));
+            assert!(!proof.verify(tx, B256::random()));
+        }
+
+        let proof = payload.prove(&[txs[3].0, txs[1].0]).unwrap();
+        assert_eq!((proof.block_hash, proof.transactions_root), (block_hash, root));
+        let indexes = proof.transactions.iter().map(|proof| proof.index).collect::<Vec<_>>();
+        assert_eq!(indexes, vec![3, 1]);
+        assert!(payload.prove(&[txs[0].0, TxHash::random()]).is_none());
+        assert!(payload.prove_index(5).is_none());
+    }
+}
@@ -0,0 +1,542 @@
+//! SSZ encodings of the commitment and constraint types.
+//!
+//! Each type is encoded through its SSZ container, whose layout follows the bolt specs:
+//!
+//! ```text
+//! class Constraint(Container):
+//!     tx: Transaction
+//!     index: Union[None, uint64]
+//!
+//! class ConstraintsMessage(Container):
+//!     validator_index: uint64
+//!     slot: uint64
+//!     constraints: List[Constraint, MAX_CONSTRAINTS_PER_SLOT]
+//!
+//! class SignedConstraints(Container):
+//!     message: ConstraintsMessage
+//!     signature: BLSSignature
+//!
+//! class InclusionRequest(Container):
+//!     slot: uint64
+//!     txs: List[Transaction, MAX_CONSTRAINTS_PER_SLOT]
+//!     context: Union[None, ByteList[MAX_CONTEXT_LEN]]
+//!     atomic: boolean
+//!     max_slot: Union[None, uint64]
+//!
+//! class ExclusionRequest(Container):
+//!     slot: uint64
+//!     senders: List[ExecutionAddress, MAX_EXCLUSION_TARGETS]
+//!     tx_hashes: List[Bytes32, MAX_EXCLUSION_TARGETS]
+//!
+//! class InclusionCommitment(Container):      class ExclusionCommitment(Container):
+//!     request: InclusionRequest                  request: ExclusionRequest
+//!     signature: ByteVector[65]                  signature: ByteVector[65]
+//!
+//! SignedCommitment = Union[InclusionCommitment, ExclusionCommitment]
+//!
+//! class DelegationMessage(Container):
+//!     action: uint8
+//!     validator_pubkey: BLSPubkey
+//!     delegatee_pubkey: BLSPubkey
+//!     slasher: ExecutionAddress
+//!
+//! class SignedDelegation(Container):
+//!     message: DelegationMessage
+//!     signature: BLSSignature
+//! ```
+//!
+//! Like in their JSON form, the requests don't carry the signature of their signer, and the
+//! commitment id of a commitment is derived from its request. The inclusion proofs of the
+//! commitments aren't encoded. The constraints API has no encoding of the atomic, range and
+//! exclusion constraints, so the constraints messages that use them have no SSZ encoding
+//! either.
+
+use alloy::primitives::{Address, Signature, B256};
+use ethereum_consensus::{bellatrix::mainnet::Transaction, ssz::prelude::*};
+use reth_primitives::PooledTransactionsElement;
+
+use crate::crypto::bls::BLSSig;
+
+use super::{
+    commitment::{
+        ECDSASignatureExt, ExclusionCommitment, InclusionCommitment, SignedCommitment,
+        MAX_CONTEXT_LEN, MAX_EXCLUSION_TARGETS,
+    },
+    constraint::Constraint,
+    delegation::{DelegationAction, DelegationMessage, SignedDelegation},
+    BlsPublicKey, CommitmentRequest, ConstraintsMessage, ExclusionRequest, FullTransaction,
+    InclusionRequest, SignedConstraints,
+};
+
+/// The maximum number of constraints in a constraints message, which is the maximum number
+/// of transactions of an execution payload.
+pub const MAX_CONSTRAINTS_PER_SLOT: usize = 1_048_576;
+
+/// Errors of the SSZ encoding of a type.
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum SszError {
+    #[error("Failed to encode: {0}")]
+    Serialize(#[from] ssz_rs::SerializeError),
+    #[error("Failed to decode: {0}")]
+    Deserialize(#[from] ssz_rs::DeserializeError),
+    #[error("Failed to compute the hash tree root: {0}")]
+    Merkleization(#[from] ssz_rs::MerkleizationError),
+    #[error("The {0} exceed the SSZ limit")]
+    TooLong(&'static str),
+    #[error("Invalid transaction: {0}")]
+    Transaction(String),
+    #[error("Invalid {0}")]
+    Invalid(&'static str),
+    #[error("The {0} of a constraints message have no SSZ encoding")]
+    Unsupported(&'static str),
+}
+
+/// A type with an SSZ encoding, through its SSZ container.
+pub trait SszEncoding: Sized {
+    /// The SSZ container of the type.
+    type Container: SimpleSerialize;
+
+    /// Converts the value to its SSZ container.
+    fn to_ssz_container(&self) -> Result<Self::Container, SszError>;
+
+    /// Converts the SSZ container back to a value.
+    fn from_ssz_container(container: Self::Container) -> Result<Self, SszError>;
+
+    /// Returns the SSZ encoding of the value.
+    fn to_ssz(&self) -> Result<Vec<u8>, SszError> {
+        Ok(ssz_rs::serialize(&self.to_ssz_container()?)?)
+    }
+
+    /// Decodes a value from its SSZ encoding.
+    fn from_ssz(bytes: &[u8]) -> Result<Self, SszError> {
+        Self::from_ssz_container(ssz_rs::deserialize(bytes)?)
+    }
+
+    /// Returns the hash tree root of the value.
+    fn hash_tree_root(&self) -> Result<B256, SszError> {
+        Ok(B256::from(self.to_ssz_container()?.hash_tree_root()?.0))
+    }
+}
+
+/// The SSZ container of a [`Constraint`].
+#[derive(Debug, Default, Clone, PartialEq, SimpleSerialize)]
+pub struct SszConstraint {
+    pub tx: Transaction,
+    pub index: Option<u64>,
+}
+
+/// The SSZ container of a [`ConstraintsMessage`].
+#[derive(Debug, Default, Clone, PartialEq, SimpleSerialize)]
+pub struct SszConstraintsMessage {
+    pub validator_index: u64,
+    pub slot: u64,
+    pub constraints: List<SszConstraint, MAX_CONSTRAINTS_PER_SLOT>,
+}
+
+/// The SSZ container of [`SignedConstraints`].
+#[derive(Debug, Default, Clone, PartialEq, SimpleSerialize)]
+pub struct SszSignedConstraints {
+    pub message: SszConstraintsMessage,
+    pub signature: ByteVector<96>,
+}
+
+/// The SSZ container of an [`InclusionRequest`].
+#[derive(Debug, Default, Clone, PartialEq, SimpleSerialize)]
+pub struct SszInclusionRequest {
+    pub slot: u64,
+    pub txs: List<Transaction, MAX_CONSTRAINTS_PER_SLOT>,
+    pub context: Option<ByteList<MAX_CONTEXT_LEN>>,
+    pub atomic: bool,
+    pub max_slot: Option<u64>,
+}
+
+/// The SSZ container of an [`ExclusionRequest`].
+#[derive(Debug, Default, Clone, PartialEq, SimpleSerialize)]
+pub struct SszExclusionRequest {
+    pub slot: u64,
+    pub senders: List<ByteVector<20>, MAX_EXCLUSION_TARGETS>,
+    pub tx_hashes: List<ByteVector<32>, MAX_EXCLUSION_TARGETS>,
+}
+
+/// The SSZ container of an [`InclusionCommitment`].
+#[derive(Debug, Default, Clone, PartialEq, SimpleSerialize)]
+pub struct SszInclusionCommitment {
+    pub request: SszInclusionRequest,
+    pub signature: ByteVector<65>,
+}
+
+/// The SSZ container of an [`ExclusionCommitment`].
+#[derive(Debug, Default, Clone, PartialEq, SimpleSerialize)]
+pub struct SszExclusionCommitment {
+    pub request: SszExclusionRequest,
+    pub signature: ByteVector<65>,
+}
+
+/// The SSZ container of a [`SignedCommitment`].
+#[derive(Debug, Clone, PartialEq, SimpleSerialize)]
+pub enum SszSignedCommitment {
+    Inclusion(SszInclusionCommitment),
+    Exclusion(SszExclusionCommitment),
+}
+
+impl Default for SszSignedCommitment {
+    fn default() -> Self {
+        Self::Inclusion(SszInclusionCommitment::default())
+    }
+}
+
+/// The SSZ container of a [`DelegationMessage`].
+#[derive(Debug, Default, Clone, PartialEq, SimpleSerialize)]
+pub struct SszDelegationMessage {
+    pub action: u8,
+    pub validator_pubkey: ByteVector<48>,
+    pub delegatee_pubkey: ByteVector<48>,
+    pub slasher: ByteVector<20>,
+}
+
+/// The SSZ container of a [`SignedDelegation`].
+#[derive(Debug, Default, Clone, PartialEq, SimpleSerialize)]
+pub struct SszSignedDelegation {
+    pub message: SszDelegationMessage,
+    pub signature: ByteVector<96>,
+}
+
+fn encode_transaction(tx: &FullTransaction) -> Result<Transaction, SszError> {
+    let mut data = Vec::new();
+    tx.encode_enveloped(&mut data);
+    Transaction::try_from(data.as_slice()).map_err(|_| SszError::TooLong("transaction bytes"))
+}
+
+fn decode_transaction(tx: &Transaction) -> Result<FullTransaction, SszError> {
+    let mut data: &[u8] = tx.as_ref();
+    let tx = PooledTransactionsElement::decode_enveloped(&mut data)
+        .map_err(|err| SszError::Transaction(err.to_string()))?;
+    Ok(FullTransaction::from(tx))
+}
+
+fn encode_list<T, U, const N: usize>(
+    items: &[T],
+    name: &'static str,
+    encode: impl Fn(&T) -> Result<U, SszError>,
+) -> Result<List<U, N>, SszError>
+where
+    U: SimpleSerialize,
+{
+    let items = items.iter().map(encode).collect::<Result<Vec<_>, _>>()?;
+    List::try_from(items).map_err(|_| SszError::TooLong(name))
+}
+
+fn encode_signature(signature: &Signature) -> ByteVector<65> {
+    let bytes = signature.as_bytes_with_parity();
+    ByteVector::try_from(bytes.as_slice()).expect("65 bytes")
+}
+
+fn decode_signature(signature: &ByteVector<65>) -> Result<Signature, SszError> {
+    Signature::try_from(signature.as_ref()).map_err(|_| SszError::Invalid("signature"))
+}
+
+impl SszEncoding for Constraint {
+    type Container = SszConstraint;
+
+    fn to_ssz_container(&self) -> Result<SszConstraint, SszError> {
+        Ok(SszConstraint { tx: encode_transaction(&self.transaction)?, index: self.index })
+    }
+
+    fn from_ssz_container(container: SszConstraint) -> Result<Self, SszError> {
+        Ok(Self::from_transaction(decode_transaction(&container.tx)?, container.index))
+    }
+}
+
+impl SszEncoding for ConstraintsMessage {
+    type Container = SszConstraintsMessage;
+
+    fn to_ssz_container(&self) -> Result<SszConstraintsMessage, SszError> {
+        if self.atomic {
+            return Err(SszError::Unsupported("atomic constraints"));
+        }
+        if self.max_slot.is_some() {
+            return Err(SszError::Unsupported("slot-range constraints"));
+        }
+        if !self.exclusions.is_empty() {
+            return Err(SszError::Unsupported("exclusions"));
+        }
+
+        let constraints =
+            encode_list(&self.constraints, "constraints", Constraint::to_ssz_container);
+        Ok(SszConstraintsMessage {
+            validator_index: self.validator_index,
+            slot: self.slot,
+            constraints: constraints?,
+        })
+    }
+
+    fn from_ssz_container(container: SszConstraintsMessage) -> Result<Self, SszError> {
+        let constraints = container.constraints.iter().cloned().map(Constraint::from_ssz_container);
+        Ok(Self {
+            validator_index: container.validator_index,
+            slot: container.slot,
+            constraints: constraints.collect::<Result<_, _>>()?,
+            ..Default::default()
+        })
+    }
+}
+
+impl SszEncoding for SignedConstraints {
+    type Container = SszSignedConstraints;
+
+    fn to_ssz_container(&self) -> Result<SszSignedConstraints, SszError> {
+        Ok(SszSignedConstraints {
+            message: self.message.to_ssz_container()?,
+            signature: ByteVector::try_from(self.signature.as_slice()).expect("96 bytes"),
+        })
+    }
+
+    fn from_ssz_container(container: SszSignedConstraints) -> Result<Self, SszError> {
+        Ok(Self {
+            message: ConstraintsMessage::from_ssz_container(container.message)?,
+            signature: BLSSig::from_slice(container.signature.as_ref()),
+        })
+    }
+}
+
+impl SszEncoding for InclusionRequest {
+    type Container = SszInclusionRequest;
+
+    fn to_ssz_container(&self) -> Result<SszInclusionRequest, SszError> {
+        let context = self.context.as_ref().map(|context| {
+            ByteList::try_from(context.as_bytes()).map_err(|_| SszError::TooLong("context bytes"))
+        });
+
+        Ok(SszInclusionRequest {
+            slot: self.slot,
+            txs: encode_list(&self.txs, "transactions", encode_transaction)?,
+            context: context.transpose()?,
+            atomic: self.atomic,
+            max_slot: self.max_slot,
+        })
+    }
+
+    fn from_ssz_container(container: SszInclusionRequest) -> Result<Self, SszError> {
+        let context = container.context.map(|context| {
+            String::from_utf8(context.as_ref().to_vec()).map_err(|_| SszError::Invalid("context"))
+        });
+
+        Ok(Self {
+            slot: container.slot,
+            txs: container.txs.iter().map(decode_transaction).collect::<Result<_, _>>()?,
+            signature: None,
+            signer: None,
+            context: context.transpose()?,
+            atomic: container.atomic,
+            max_slot: container.max_slot,
+        })
+    }
+}
+
+impl SszEncoding for ExclusionRequest {
+    type Container = SszExclusionRequest;
+
+    fn to_ssz_container(&self) -> Result<SszExclusionRequest, SszError> {
+        let sender = |sender: &Address| Ok(ByteVector::try_from(sender.as_slice()).expect("20"));
+        let hash = |hash: &B256| Ok(ByteVector::try_from(hash.as_slice()).expect("32"));
+
+        Ok(SszExclusionRequest {
+            slot: self.slot,
+            senders: encode_list(&self.senders, "senders", sender)?,
+            tx_hashes: encode_list(&self.tx_hashes, "transaction hashes", hash)?,
+        })
+    }
+
+    fn from_ssz_container(container: SszExclusionRequest) -> Result<Self, SszError> {
+        let senders = container.senders.iter().map(|sender| Address::from_slice(sender.as_ref()));
+        let tx_hashes = container.tx_hashes.iter().map(|hash| B256::from_slice(hash.as_ref()));
+        Ok(Self {
+            slot: container.slot,
+            senders: senders.collect(),
+            tx_hashes: tx_hashes.collect(),
+            signature: None,
+            signer: None,
+        })
+    }
+}
+
+impl SszEncoding for InclusionCommitment {
+    type Container = SszInclusionCommitment;
+
+    fn to_ssz_container(&self) -> Result<SszInclusionCommitment, SszError> {
+        Ok(SszInclusionCommitment {
+            request: self.request().to_ssz_container()?,
+            signature: encode_signature(self.signature()),
+        })
+    }
+
+    fn from_ssz_container(container: SszInclusionCommitment) -> Result<Self, SszError> {
+        let request = InclusionRequest::from_ssz_container(container.request)?;
+        let signature = decode_signature(&container.signature)?;
+        let commitment = CommitmentRequest::Inclusion(request).commit_with_signature(signature);
+        Ok(commitment.into_inclusion().expect("inclusion commitment"))
+    }
+}
+
+impl SszEncoding for ExclusionCommitment {
+    type Container = SszExclusionCommitment;
+
+    fn to_ssz_container(&self) -> Result<SszExclusionCommitment, SszError> {
+        Ok(SszExclusionCommitment {
+            request: self.request().to_ssz_container()?,
+            signature: encode_signature(self.signature()),
+        })
+    }
+
+    fn from_ssz_container(container: SszExclusionCommitment) -> Result<Self, SszError> {
+        let request = ExclusionRequest::from_ssz_container(container.request)?;
+        let signature = decode_signature(&container.signature)?;
+        let commitment = CommitmentRequest::Exclusion(request).commit_with_signature(signature);
+        Ok(commitment.into_exclusion().expect("exclusion commitment"))
+    }
+}
+
+impl SszEncoding for SignedCommitment {
+    type Container = SszSignedCommitment;
+
+    fn to_ssz_container(&self) -> Result<SszSignedCommitment, SszError> {
+        Ok(match self {
+            Self::Inclusion(inclusion) => {
+                SszSignedCommitment::Inclusion(inclusion.to_ssz_container()?)
+            }
+            Self::Exclusion(exclusion) => {
+                SszSignedCommitment::Exclusion(exclusion.to_ssz_container()?)
+            }
+        })
+    }
+
+    fn from_ssz_container(container: SszSignedCommitment) -> Result<Self, SszError> {
+        Ok(match container {
+            SszSignedCommitment::Inclusion(inclusion) => {
+                Self::Inclusion(InclusionCommitment::from_ssz_container(inclusion)?)
+            }
+            SszSignedCommitment::Exclusion(exclusion) => {
+                Self::Exclusion(ExclusionCommitment::from_ssz_container(exclusion)?)
+            }
+        })
+    }
+}
+
+impl SszEncoding for DelegationMessage {
+    type Container = SszDelegationMessage;
+
+    fn to_ssz_container(&self) -> Result<SszDelegationMessage, SszError> {
+        let pubkey = |pubkey: &BlsPublicKey| ByteVector::try_from(pubkey.as_ref()).expect("48");
+        Ok(SszDelegationMessage {
+            action: self.action as u8,
+            validator_pubkey: pubkey(&self.validator_pubkey),
+            delegatee_pubkey: pubkey(&self.delegatee_pubkey),
+            slasher: ByteVector::try_from(self.slasher.as_slice()).expect("20 bytes"),
+        })
+    }
+
+    fn from_ssz_container(container: SszDelegationMessage) -> Result<Self, SszError> {
+        let action = match container.action {
+            0 => DelegationAction::Delegate,
+            1 => DelegationAction::Revoke,
+            _ => return Err(SszError::Invalid("delegation action")),
+        };
+        let pubkey = |pubkey: &ByteVector<48>| {
+            BlsPublicKey::try_from(pubkey.as_ref()).map_err(|_| SszError::Invalid("public key"))
+        };
+        Ok(Self {
+            action,
+            validator_pubkey: pubkey(&container.validator_pubkey)?,
+            delegatee_pubkey: pubkey(&container.delegatee_pubkey)?,
+            slasher: Address::from_slice(container.slasher.as_ref()),
+        })
+    }
+}
+
+impl SszEncoding for SignedDelegation {
+    type Container = SszSignedDelegation;
+
+    fn to_ssz_container(&self) -> Result<SszSignedDelegation, SszError> {
+        Ok(SszSignedDelegation {
+            message: self.message.to_ssz_container()?,
+            signature: ByteVector::try_from(self.signature.as_slice()).expect("96 bytes"),
+        })
+    }
+
+    fn from_ssz_container(container: SszSignedDelegation) -> Result<Self, SszError> {
+        Ok(Self {
+            message: DelegationMessage::from_ssz_container(container.message)?,
+            signature: BLSSig::from_slice(container.signature.as_ref()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{primitives::b256, signers::local::PrivateKeySigner};
+
+    use super::*;
+
+    const TEST_TX: &str = "0x02f86c870c72dd9d5e883e4d0183408f2382520894d2e2adf7177b7a8afddbc12d1634cf23ea1a71020180c001a08556dcfea479b34675db3fe08e29486fe719c2b22f6b0c1741ecbbdce4575cc6a01cd48009ccafd6b9f1290bbe2ceea268f94101d1d322c787018423ebcbc87ab4";
+
+    fn test_request() -> InclusionRequest {
+        let json_req = format!(r#"{{"slot": 10, "txs": ["{TEST_TX}"]}}"#);
+        serde_json::from_str(&json_req).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_commitment_roundtrip() -> eyre::Result<()> {
+        let signer = PrivateKeySigner::random();
+        let json_req = format!(
+            r#"{{"slot": 10, "txs": ["{TEST_TX}"], "context": "order-42", "atomic": true,
+                "max_slot": 12}}"#
+        );
+        let request: CommitmentRequest = serde_json::from_str(&json_req)?;
+        let json = serde_json::to_value(request.commit_and_sign(&signer).await?)?;
+
+        // JSON -> struct -> SSZ -> struct -> JSON
+        let commitment: SignedCommitment = serde_json::from_value(json.clone())?;
+        let decoded = SignedCommitment::from_ssz(&commitment.to_ssz()?)?;
+        assert_eq!(decoded, commitment);
+        assert_eq!(serde_json::to_value(&decoded)?, json);
+
+        let sender = Address::repeat_byte(1);
+        let exclusion = format!(r#"{{"slot": 10, "senders": ["{sender}"]}}"#);
+        let request: CommitmentRequest = serde_json::from_str(&exclusion)?;
+        let commitment = request.commit_and_sign(&signer).await?;
+        assert_eq!(SignedCommitment::from_ssz(&commitment.to_ssz()?)?, commitment);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_tree_roots() -> eyre::Result<()> {
+        let request = test_request();
+        let root = b256!("46b2b971ba6c68109870182608232d5c47498f27430cc08fbb3357e019e86462");
+        assert_eq!(request.hash_tree_root()?, root);
+        assert_eq!(InclusionRequest::from_ssz(&request.to_ssz()?)?, request);
+
+        // The constraints message has the layout of the constraints API
+        let message = ConstraintsMessage::build(1, request.clone());
+        let root = b256!("98291288e90a6703bfc9e97494832910d6d22e1079e565d3b04ba4c1742c0b8b");
+        assert_eq!(message.hash_tree_root()?, root);
+        let signed = SignedConstraints { message, signature: BLSSig::repeat_byte(7) };
+        assert_eq!(SignedConstraints::from_ssz(&signed.to_ssz()?)?, signed);
+
+        // The empty constraints list has the root of an empty transactions list
+        let empty = ConstraintsMessage::build(1, InclusionRequest { txs: vec![], ..request });
+        let empty = empty.to_ssz_container()?.constraints.hash_tree_root()?;
+        let root = b256!("7ffe241ea60187fdb0187bfa22de35d1f9bed7ab061d9401fd47e34a54fbede1");
+        assert_eq!(B256::from(empty.0), root);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsupported_constraints() {
+        let atomic = InclusionRequest { atomic: true, ..test_request() };
+        let err = ConstraintsMessage::build(1, atomic).to_ssz().unwrap_err();
+        assert!(matches!(err, SszError::Unsupported("atomic constraints")), "{err}");
+    }
+}
@@ -0,0 +1,451 @@
+//! Synthetic probe of the commitment pipeline, for the failures that health checks of the HTTP
+//! layer miss: signing, persistence and relay connectivity.
+//!
+//! On an interval, the driver builds a probe request: an inclusion request signed with a
+//! dedicated probe key, for the sentinel slot [`PROBE_SLOT`]. It is run through each stage of
+//! the pipeline and short-circuited at its boundary: the probe commitment and constraints are
+//! signed but never returned nor submitted, the store write is rolled back, and the relay is
+//! only asked for its status.
+//!
+//! Everything the probe signs is over its [`probe_digest`], in a domain of its own, so that
+//! nothing it produces can be mistaken for a real commitment.
+//!
+//! The outcome of each stage is recorded in the metrics, the sidecar reports as unready while
+//! any stage fails, and each failing stage raises its alert condition.
+
+use std::{
+    collections::HashSet,
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use alloy::{
+    eips::eip2718::Encodable2718,
+    network::{EthereumWallet, TransactionBuilder},
+    primitives::{keccak256, Address, B256, U256},
+    rpc::types::TransactionRequest,
+    signers::{local::PrivateKeySigner, Signer as SignerECDSA},
+};
+use eyre::{bail, eyre};
+use parking_lot::Mutex;
+use reth_primitives::PooledTransactionsElement;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    alerts::{AlertCondition, Alerts},
+    common::rng::Rng,
+    crypto::SignerBackend,
+    db::Store,
+    primitives::{FullTransaction, InclusionRequest},
+    state::Readiness,
+    telemetry, BuilderApi, MevBoostClient,
+};
+
+/// Domain tag prefixed to the request digest in the probe digest.
+pub const PROBE_DIGEST_DOMAIN: &[u8] = b"bolt-probe/v1";
+
+/// The sentinel slot targeted by the probe requests, which is never reached.
+pub const PROBE_SLOT: u64 = u64::MAX;
+
+/// The context set on the probe requests, to tell them apart in the logs.
+pub const PROBE_CONTEXT: &str = "bolt-probe";
+
+/// Maximum time a single probe stage may take before it is considered failed.
+pub const PROBE_STAGE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The stages of the pipeline exercised by the probe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeStage {
+    /// Signing the probe commitment with the commitment key.
+    CommitmentSigning,
+    /// Signing the probe constraints with the constraint key.
+    ConstraintSigning,
+    /// Writing the probe record to the store.
+    Persistence,
+    /// Reaching the relay the constraints are submitted to.
+    Relay,
+}
+
+impl ProbeStage {
+    /// All the probe stages, in the order they are run.
+    pub const ALL: [Self; 4] =
+        [Self::CommitmentSigning, Self::ConstraintSigning, Self::Persistence, Self::Relay];
+
+    /// Returns the alert condition raised while the stage fails.
+    pub fn alert_condition(&self) -> AlertCondition {
+        match self {
+            Self::CommitmentSigning | Self::ConstraintSigning => AlertCondition::SignerUnavailable,
+            Self::Persistence => AlertCondition::StoreWriteFailure,
+            Self::Relay => AlertCondition::RelaySubmissionFailure,
+        }
+    }
+}
+
+impl fmt::Display for ProbeStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CommitmentSigning => write!(f, "commitment_signing"),
+            Self::ConstraintSigning => write!(f, "constraint_signing"),
+            Self::Persistence => write!(f, "persistence"),
+            Self::Relay => write!(f, "relay"),
+        }
+    }
+}
+
+/// Returns the probe digest of the request:
+/// keccak256(PROBE_DIGEST_DOMAIN | digest(request)).
+///
+/// Its preimage is 45 bytes long, which the preimage of a request digest never is (32 bytes
+/// per transaction, plus 8), so a probe digest is never the digest of a real request.
+pub fn probe_digest(request: &InclusionRequest) -> B256 {
+    let mut data = PROBE_DIGEST_DOMAIN.to_vec();
+    data.extend_from_slice(request.digest().as_slice());
+    keccak256(&data)
+}
+
+/// The checks of the probe stages, each stopping at the boundary of its stage.
+#[async_trait::async_trait]
+pub trait ProbeChecks: Send + Sync {
+    /// Runs the stage for the probe request with the given probe digest.
+    async fn check(&self, stage: ProbeStage, digest: B256) -> eyre::Result<()>;
+}
+
+/// The probe checks of the sidecar components.
+#[derive(Debug)]
+pub struct PipelineChecks {
+    /// The backend signing the commitments and the constraints.
+    pub signer: Arc<dyn SignerBackend>,
+    /// Whether the commitments are signed by the backend. Otherwise they are signed
+    /// externally, and the commitment signing stage can't be probed and always passes.
+    pub signs_commitments: bool,
+    /// The persistent store.
+    pub store: Store,
+    /// The client of the relay the constraints are submitted to.
+    pub relay: MevBoostClient,
+}
+
+#[async_trait::async_trait]
+impl ProbeChecks for PipelineChecks {
+    async fn check(&self, stage: ProbeStage, digest: B256) -> eyre::Result<()> {
+        match stage {
+            ProbeStage::CommitmentSigning => {
+                if self.signs_commitments {
+                    self.signer.sign_commitment(&digest).await?;
+                }
+            }
+            ProbeStage::ConstraintSigning => {
+                self.signer.sign_bls(digest.as_slice()).await?;
+            }
+            ProbeStage::Persistence => self.store.check_writable(&digest)?,
+            ProbeStage::Relay => {
+                let status = self.relay.status().await?;
+                if !status.is_success() {
+                    bail!("Relay responded with status {status}");
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The outcome of a probe stage.
+#[derive(Debug, Clone)]
+pub struct StageOutcome {
+    /// The stage.
+    pub stage: ProbeStage,
+    /// The time the stage took.
+    pub latency: Duration,
+    /// The error the stage failed with, if any.
+    pub error: Option<String>,
+}
+
+/// The outcomes of a probe run.
+#[derive(Debug, Clone)]
+pub struct ProbeReport {
+    /// The probe digest of the request.
+    pub digest: B256,
+    /// The outcome of each stage, in the order they were run.
+    pub stages: Vec<StageOutcome>,
+}
+
+impl ProbeReport {
+    /// Returns the stages that failed.
+    pub fn failing_stages(&self) -> Vec<ProbeStage> {
+        let failing = self.stages.iter().filter(|outcome| outcome.error.is_some());
+        failing.map(|outcome| outcome.stage).collect()
+    }
+}
+
+/// The synthetic probe of the pipeline. Cheap to clone.
+#[derive(Clone)]
+pub struct Probe {
+    checks: Arc<dyn ProbeChecks>,
+    key: PrivateKeySigner,
+    chain_id: u64,
+    readiness: Readiness,
+    alerts: Alerts,
+    /// The conditions raised by the probe, which it clears once their stages pass again.
+    /// Conditions raised by other subsystems are left alone, as the probe may not exercise
+    /// what failed there.
+    raised: Arc<Mutex<HashSet<AlertCondition>>>,
+}
+
+impl fmt::Debug for Probe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Probe")
+            .field("address", &self.key.address())
+            .field("chain_id", &self.chain_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Probe {
+    /// Create a new probe running the given checks, reporting its outcomes to `readiness`.
+    /// The probe requests are signed with a random key.
+    pub fn new(checks: impl ProbeChecks + 'static, chain_id: u64, readiness: Readiness) -> Self {
+        Self {
+            checks: Arc::new(checks),
+            key: PrivateKeySigner::random(),
+            chain_id,
+            readiness,
+            alerts: Alerts::default(),
+            raised: Arc::default(),
+        }
+    }
+
+    /// Generate the probe key from the given source of randomness.
+    pub fn with_rng(self, rng: &Rng) -> Self {
+        Self { key: rng.ecdsa_signer(), ..self }
+    }
+
+    /// Raise the alert conditions of the failing stages on the given handle.
+    pub fn with_alerts(self, alerts: Alerts) -> Self {
+        Self { alerts, ..self }
+    }
+
+    /// Returns the address of the probe key.
+    pub fn address(&self) -> Address {
+        self.key.address()
+    }
+
+    /// Builds a probe request: a transfer of nothing from the probe key to itself for the
+    /// sentinel slot, signed over its probe digest.
+    pub async fn request(&self) -> eyre::Result<InclusionRequest> {
+        let wallet = EthereumWallet::from(self.key.clone());
+        let tx = TransactionRequest::default()
+            .with_from(self.address())
+            .with_to(self.address())
+            .with_chain_id(self.chain_id)
+            .with_nonce(0)
+            .with_value(U256::ZERO)
+            .with_gas_limit(21_000)
+            .with_max_priority_fee_per_gas(0)
+            .with_max_fee_per_gas(0)
+            .build(&wallet)
+            .await?;
+        let encoded = tx.encoded_2718();
+        let tx = PooledTransactionsElement::decode_enveloped(&mut encoded.as_slice())?;
+
+        let mut request = InclusionRequest {
+            slot: PROBE_SLOT,
+            txs: vec![FullTransaction::from(tx)],
+            signature: None,
+            signer: None,
+            context: Some(PROBE_CONTEXT.to_string()),
+            atomic: false,
+            max_slot: None,
+        };
+        request.recover_signers()?;
+
+        let signature = self.key.sign_hash(&probe_digest(&request)).await?;
+        request.set_signature(signature);
+        request.set_signer(self.address());
+
+        Ok(request)
+    }
+
+    /// Runs all the stages for a new probe request, and reports their outcomes to the
+    /// readiness, the metrics and the alerts. Fails only if the probe request can't be built.
+    pub async fn run(&self) -> eyre::Result<ProbeReport> {
+        let digest = probe_digest(&self.request().await?);
+
+        let mut stages = Vec::with_capacity(ProbeStage::ALL.len());
+        for stage in ProbeStage::ALL {
+            let start = Instant::now();
+            let result = tokio::time::timeout(PROBE_STAGE_TIMEOUT, self.checks.check(stage, digest))
+                .await
+                .unwrap_or_else(|_| Err(eyre!("Timed out after {PROBE_STAGE_TIMEOUT:?}")));
+            let latency = start.elapsed();
+
+            let label = stage.to_string();
+            metrics::histogram!(telemetry::PROBE_STAGE_LATENCY, "stage" => label.clone())
+                .record(latency.as_secs_f64());
+            if let Err(ref err) = result {
+                warn!(%stage, ?err, "Probe stage failed");
+                metrics::counter!(telemetry::PROBE_STAGE_FAILURES, "stage" => label).increment(1);
+            }
+
+            let error = result.err().map(|err| err.to_string());
+            stages.push(StageOutcome { stage, latency, error });
+        }
+
+        let report = ProbeReport { digest, stages };
+        self.report(&report);
+        Ok(report)
+    }
+
+    /// Reports the outcomes of a probe run to the readiness and the alerts.
+    fn report(&self, report: &ProbeReport) {
+        let failing = report.failing_stages();
+        if self.readiness.set_failing_probe_stages(failing.clone()) {
+            if failing.is_empty() {
+                info!("Probe passed again");
+            } else {
+                warn!(?failing, "Probe failed, reporting as unready");
+            }
+        }
+
+        let mut raised = self.raised.lock();
+        for condition in AlertCondition::ALL {
+            let failures = report
+                .stages
+                .iter()
+                .filter(|outcome| outcome.stage.alert_condition() == condition)
+                .filter_map(|outcome| {
+                    outcome.error.as_ref().map(|err| format!("{}: {err}", outcome.stage))
+                })
+                .collect::<Vec<_>>();
+
+            if !failures.is_empty() {
+                self.alerts.raise(condition, format!("Probe failed at {}", failures.join(", ")));
+                raised.insert(condition);
+            } else if raised.remove(&condition) {
+                self.alerts.clear(condition);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use reqwest::Url;
+
+    use crate::{
+        alerts::AlertConfig,
+        crypto::{bls::Signer as BlsSigner, LocalSigner},
+        test_util,
+    };
+
+    use super::*;
+
+    /// Probe checks that fail at the injected stage, if any.
+    #[derive(Clone, Default)]
+    struct InjectedFailure(Arc<Mutex<Option<ProbeStage>>>);
+
+    #[async_trait::async_trait]
+    impl ProbeChecks for InjectedFailure {
+        async fn check(&self, stage: ProbeStage, _digest: B256) -> eyre::Result<()> {
+            if *self.0.lock() == Some(stage) {
+                bail!("Injected failure");
+            }
+            Ok(())
+        }
+    }
+
+    fn test_alerts() -> Alerts {
+        let config = AlertConfig { command: Some(PathBuf::from("true")), ..Default::default() };
+        Alerts::spawn(&config, "test")
+    }
+
+    #[tokio::test]
+    async fn test_probe_request_is_non_binding() -> eyre::Result<()> {
+        let probe = Probe::new(InjectedFailure::default(), 1, Readiness::new(true));
+        let request = probe.request().await?;
+
+        assert_eq!(request.slot, PROBE_SLOT);
+        assert_eq!(request.context.as_deref(), Some(PROBE_CONTEXT));
+        assert_eq!(request.signer(), Some(probe.address()));
+
+        // The request is signed over its probe digest, never over its regular digest
+        let digest = probe_digest(&request);
+        assert_ne!(digest, request.digest());
+        let signature = request.signature.unwrap();
+        assert_eq!(signature.recover_address_from_prehash(&digest)?, probe.address());
+        assert_ne!(signature.recover_address_from_prehash(&request.digest())?, probe.address());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failing_stages() -> eyre::Result<()> {
+        for stage in ProbeStage::ALL {
+            let readiness = Readiness::new(true);
+            let alerts = test_alerts();
+            let checks = InjectedFailure::default();
+            let probe =
+                Probe::new(checks.clone(), 1, readiness.clone()).with_alerts(alerts.clone());
+
+            *checks.0.lock() = Some(stage);
+            let report = probe.run().await?;
+            assert_eq!(report.failing_stages(), vec![stage]);
+            assert!(!readiness.is_ready());
+            assert_eq!(readiness.failing_probe_stages(), vec![stage]);
+            for condition in AlertCondition::ALL {
+                assert_eq!(alerts.is_raised(condition), condition == stage.alert_condition());
+            }
+
+            // Once the stage recovers, the sidecar is ready again and the condition cleared
+            *checks.0.lock() = None;
+            assert_eq!(probe.run().await?.failing_stages(), vec![]);
+            assert!(readiness.is_ready());
+            assert!(AlertCondition::ALL.iter().all(|condition| !alerts.is_raised(*condition)));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_keeps_conditions_raised_elsewhere() -> eyre::Result<()> {
+        let alerts = test_alerts();
+        let probe = Probe::new(InjectedFailure::default(), 1, Readiness::new(true))
+            .with_alerts(alerts.clone());
+
+        alerts.raise(AlertCondition::RelaySubmissionFailure, "Failed to submit constraints");
+        assert_eq!(probe.run().await?.failing_stages(), vec![]);
+        assert!(alerts.is_raised(AlertCondition::RelaySubmissionFailure));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_checks() -> eyre::Result<()> {
+        let checks = PipelineChecks {
+            signer: Arc::new(LocalSigner::new(
+                BlsSigner::new(test_util::test_bls_secret_key()),
+                PrivateKeySigner::random(),
+            )),
+            signs_commitments: true,
+            store: Store::in_memory()?,
+            // Nothing listens on this port, so the relay is unreachable
+            relay: MevBoostClient::new(Url::parse("http://127.0.0.1:1")?),
+        };
+        let store = checks.store.clone();
+        let readiness = Readiness::new(true);
+        let probe = Probe::new(checks, 1, readiness.clone());
+
+        let report = probe.run().await?;
+        assert_eq!(report.failing_stages(), vec![ProbeStage::Relay]);
+        assert_eq!(readiness.failing_probe_stages(), vec![ProbeStage::Relay]);
+
+        // Nothing was persisted by the probe
+        assert_eq!(store.get_commitment_fee(&report.digest)?, None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,833 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+use alloy::{
+    primitives::{Address, Bytes, Signature, B256},
+    transports::{TransportError, TransportErrorKind},
+};
+use beacon_api_client::ProposerDuty;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    commitments::spec::Error as CommitmentError,
+    config::{Limits, ValidatorIndexes},
+    crypto::{bls::BLSSig, SignableBLS},
+    driver::validate_commitment,
+    primitives::{
+        AccountState, BlsPublicKey, CommitmentRequest, ConstraintsMessage, InclusionRequest,
+        SignedConstraints, Slot,
+    },
+    state::{
+        basefee::MarginBounds,
+        clock::VirtualTimeSource,
+        consensus::{ConsensusError, ProposerDutiesFetcher},
+        execution::StateUpdate,
+        fetcher::StateFetcher,
+        ConsensusState, ExecutionState,
+    },
+    Config,
+};
+
+/// Errors that can occur while reading or replaying a replay log.
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum ReplayError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid replay log entry at line {line}: {err}")]
+    Parse { line: usize, err: serde_json::Error },
+    #[error("The replay log doesn't start with the sidecar parameters")]
+    MissingStart,
+    #[error("Failed to initialize the execution state: {0}")]
+    Execution(#[from] TransportError),
+}
+
+/// The decision of the driver for a commitment request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    /// The request was accepted, and the constraints with the given digest were to be signed.
+    Accepted {
+        /// The index of the validator proposing at the target slot.
+        validator_index: u64,
+        /// The digest of the constraints message to sign.
+        constraints: B256,
+    },
+    /// The request was rejected.
+    Rejected {
+        /// The error returned to the client.
+        error: String,
+    },
+}
+
+impl Decision {
+    /// Returns the decision for a request, given the result of its validation.
+    pub fn new(request: &InclusionRequest, result: &Result<u64, CommitmentError>) -> Self {
+        match result {
+            Ok(validator_index) => {
+                let message = ConstraintsMessage::build(*validator_index, request.clone());
+                let constraints = B256::from_slice(&message.digest());
+                Self::Accepted { validator_index: *validator_index, constraints }
+            }
+            Err(err) => Self::Rejected { error: err.to_string() },
+        }
+    }
+}
+
+/// A proposer duty, as recorded in the replay log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedDuty {
+    /// The slot of the duty.
+    pub slot: Slot,
+    /// The index of the proposer.
+    pub validator_index: u64,
+    /// The public key of the proposer.
+    pub pubkey: BlsPublicKey,
+}
+
+impl From<&ProposerDuty> for RecordedDuty {
+    fn from(duty: &ProposerDuty) -> Self {
+        Self {
+            slot: duty.slot,
+            validator_index: duty.validator_index as u64,
+            pubkey: duty.public_key.clone(),
+        }
+    }
+}
+
+impl From<RecordedDuty> for ProposerDuty {
+    fn from(duty: RecordedDuty) -> Self {
+        Self {
+            public_key: duty.pubkey,
+            slot: duty.slot,
+            validator_index: duty.validator_index as usize,
+        }
+    }
+}
+
+/// An entry of the replay log: an input of the driver, or one of its decisions.
+///
+/// Timestamps are in milliseconds since the UNIX epoch. Backend errors are recorded
+/// as their message. No secrets are recorded: requests are only logged with the
+/// signature of their sender, and the sidecar keys are never part of the log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[allow(missing_docs)]
+pub enum ReplayEvent {
+    /// The parameters of the sidecar that affect its decisions.
+    Start {
+        validator_indexes: ValidatorIndexes,
+        commitment_deadline_ms: u64,
+        limits: Limits,
+        basefee_margin: Option<MarginBounds>,
+    },
+    /// The startup warmup of the consensus and execution state.
+    Warmup { at_ms: u64, slot: Slot },
+    /// A commitment request received by the API. `leased` is false if the validator of the
+    /// target slot was leased by another sidecar instance.
+    Request {
+        at_ms: u64,
+        request: InclusionRequest,
+        signature: Option<Bytes>,
+        signer: Option<Address>,
+        leased: bool,
+    },
+    /// The decision of the driver for the request with the given digest.
+    Decision { digest: B256, decision: Decision },
+    /// The constraints of the request with the given digest were signed and committed.
+    Committed { digest: B256 },
+    /// The request with the given digest was accepted, but abandoned before being committed.
+    Cancelled { digest: B256 },
+    /// A new head event from the beacon node.
+    Head { at_ms: u64, slot: Slot },
+    /// The start of a new slot.
+    SlotStart { at_ms: u64, slot: Slot },
+    /// The response of the beacon node to a proposer duties query.
+    ProposerDuties { epoch: u64, result: Result<Vec<RecordedDuty>, String> },
+    /// The responses of the execution client to the queries of the state fetcher.
+    StateUpdate {
+        addresses: Vec<Address>,
+        block_number: Option<u64>,
+        result: Result<StateUpdate, String>,
+    },
+    BlockNumber { result: Result<u64, String> },
+    Basefee { block_number: Option<u64>, result: Result<u128, String> },
+    BlobBasefee { block_number: Option<u64>, result: Result<u128, String> },
+    AccountState {
+        address: Address,
+        block_number: Option<u64>,
+        result: Result<AccountState, String>,
+    },
+    ChainId { result: Result<u64, String> },
+}
+
+impl ReplayEvent {
+    /// Returns the start event with the parameters of the given configuration.
+    pub fn start(cfg: &Config) -> Self {
+        Self::Start {
+            validator_indexes: cfg.validator_indexes.clone(),
+            commitment_deadline_ms: cfg.chain.commitment_deadline().as_millis() as u64,
+            limits: cfg.limits,
+            basefee_margin: cfg.basefee_margin,
+        }
+    }
+
+    /// Returns the query answered by the event, if it's a recorded backend response.
+    fn query(&self) -> Option<Query> {
+        let query = match self {
+            Self::ProposerDuties { epoch, .. } => Query::ProposerDuties(*epoch),
+            Self::StateUpdate { addresses, block_number, .. } => {
+                Query::StateUpdate(addresses.clone(), *block_number)
+            }
+            Self::BlockNumber { .. } => Query::BlockNumber,
+            Self::Basefee { block_number, .. } => Query::Basefee(*block_number),
+            Self::BlobBasefee { block_number, .. } => Query::BlobBasefee(*block_number),
+            Self::AccountState { address, block_number, .. } => {
+                Query::AccountState(*address, *block_number)
+            }
+            Self::ChainId { .. } => Query::ChainId,
+            _ => return None,
+        };
+
+        Some(query)
+    }
+}
+
+/// A backend query, under which the recorded responses are answered in order.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Query {
+    ProposerDuties(u64),
+    StateUpdate(Vec<Address>, Option<u64>),
+    BlockNumber,
+    Basefee(Option<u64>),
+    BlobBasefee(Option<u64>),
+    AccountState(Address, Option<u64>),
+    ChainId,
+}
+
+/// The destination of the recorded events.
+#[derive(Debug)]
+enum Sink {
+    Memory(Vec<ReplayEvent>),
+    File(BufWriter<File>),
+}
+
+/// Appends the inputs and decisions of the driver to a replay log. Cheap to clone.
+#[derive(Debug, Clone)]
+pub struct ReplayRecorder {
+    sink: Arc<Mutex<Sink>>,
+}
+
+impl ReplayRecorder {
+    /// Create a recorder that writes the log to the given file, as JSON lines.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let file = File::create(path)?;
+        Ok(Self { sink: Arc::new(Mutex::new(Sink::File(BufWriter::new(file)))) })
+    }
+
+    /// Create a recorder that keeps the log in memory.
+    pub fn in_memory() -> Self {
+        Self { sink: Arc::new(Mutex::new(Sink::Memory(Vec::new()))) }
+    }
+
+    /// Appends an event to the log. Write errors are logged, as recording
+    /// must not affect the operation of the sidecar.
+    pub fn record(&self, event: ReplayEvent) {
+        match *self.sink.lock() {
+            Sink::Memory(ref mut events) => events.push(event),
+            Sink::File(ref mut file) => {
+                let written = serde_json::to_writer(&mut *file, &event)
+                    .map_err(io::Error::from)
+                    .and_then(|_| file.write_all(b"\n"))
+                    .and_then(|_| file.flush());
+                if let Err(err) = written {
+                    error!(?err, "Failed to write to the replay log");
+                }
+            }
+        }
+    }
+
+    /// Returns the recorded events, if the log is kept in memory.
+    pub fn events(&self) -> Vec<ReplayEvent> {
+        match *self.sink.lock() {
+            Sink::Memory(ref events) => events.clone(),
+            Sink::File(_) => Vec::new(),
+        }
+    }
+}
+
+/// Reads a replay log written by a [`ReplayRecorder`].
+pub fn read_log(path: &Path) -> Result<Vec<ReplayEvent>, ReplayError> {
+    let mut events = Vec::new();
+    for (i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event = serde_json::from_str(&line);
+        events.push(event.map_err(|err| ReplayError::Parse { line: i + 1, err })?);
+    }
+
+    Ok(events)
+}
+
+/// A backend wrapper that records the responses of the wrapped backend, if recording.
+#[derive(Debug, Clone)]
+pub struct Recorded<T> {
+    inner: T,
+    recorder: Option<ReplayRecorder>,
+}
+
+impl<T> Recorded<T> {
+    /// Wrap the given backend, recording its responses with `recorder` if set.
+    pub fn new(inner: T, recorder: Option<ReplayRecorder>) -> Self {
+        Self { inner, recorder }
+    }
+
+    fn record(&self, event: impl FnOnce() -> ReplayEvent) {
+        if let Some(ref recorder) = self.recorder {
+            recorder.record(event());
+        }
+    }
+}
+
+fn recorded<T: Clone, E: ToString>(result: &Result<T, E>) -> Result<T, String> {
+    result.as_ref().map(Clone::clone).map_err(ToString::to_string)
+}
+
+#[async_trait::async_trait]
+impl<T: ProposerDutiesFetcher> ProposerDutiesFetcher for Recorded<T> {
+    async fn proposer_duties(&self, epoch: u64) -> Result<Vec<ProposerDuty>, ConsensusError> {
+        let result = self.inner.proposer_duties(epoch).await;
+        self.record(|| ReplayEvent::ProposerDuties {
+            epoch,
+            result: match result {
+                Ok(ref duties) => Ok(duties.iter().map(RecordedDuty::from).collect()),
+                Err(ref err) => Err(err.to_string()),
+            },
+        });
+        result
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: StateFetcher + Sync> StateFetcher for Recorded<T> {
+    async fn get_state_update(
+        &self,
+        addresses: Vec<&Address>,
+        block_number: Option<u64>,
+    ) -> Result<StateUpdate, TransportError> {
+        // The addresses come from a map, sort them so that replays look up the same query
+        let mut sorted = addresses.iter().map(|address| **address).collect::<Vec<_>>();
+        sorted.sort();
+
+        let result = self.inner.get_state_update(addresses, block_number).await;
+        self.record(|| ReplayEvent::StateUpdate {
+            addresses: sorted,
+            block_number,
+            result: recorded(&result),
+        });
+        result
+    }
+
+    async fn get_head(&self) -> Result<u64, TransportError> {
+        let result = self.inner.get_head().await;
+        self.record(|| ReplayEvent::BlockNumber { result: recorded(&result) });
+        result
+    }
+
+    async fn get_basefee(&self, block_number: Option<u64>) -> Result<u128, TransportError> {
+        let result = self.inner.get_basefee(block_number).await;
+        self.record(|| ReplayEvent::Basefee { block_number, result: recorded(&result) });
+        result
+    }
+
+    async fn get_blob_basefee(&self, block_number: Option<u64>) -> Result<u128, TransportError> {
+        let result = self.inner.get_blob_basefee(block_number).await;
+        self.record(|| ReplayEvent::BlobBasefee { block_number, result: recorded(&result) });
+        result
+    }
+
+    async fn get_account_state(
+        &self,
+        address: &Address,
+        block_number: Option<u64>,
+    ) -> Result<AccountState, TransportError> {
+        let result = self.inner.get_account_state(address, block_number).await;
+        self.record(|| ReplayEvent::AccountState {
+            address: *address,
+            block_number,
+            result: recorded(&result),
+        });
+        result
+    }
+
+    async fn get_chain_id(&self) -> Result<u64, TransportError> {
+        let result = self.inner.get_chain_id().await;
+        self.record(|| ReplayEvent::ChainId { result: recorded(&result) });
+        result
+    }
+}
+
+/// A mock execution and consensus backend that answers every query with the next
+/// response recorded for it in the replay log.
+#[derive(Debug, Clone, Default)]
+pub struct ReplayBackend {
+    responses: Arc<Mutex<HashMap<Query, VecDeque<ReplayEvent>>>>,
+}
+
+impl ReplayBackend {
+    /// Create a backend answering with the responses recorded in the given events.
+    pub fn new(events: &[ReplayEvent]) -> Self {
+        let mut responses = HashMap::<_, VecDeque<_>>::new();
+        for event in events {
+            if let Some(query) = event.query() {
+                responses.entry(query).or_default().push_back(event.clone());
+            }
+        }
+
+        Self { responses: Arc::new(Mutex::new(responses)) }
+    }
+
+    fn next(&self, query: &Query) -> Option<ReplayEvent> {
+        self.responses.lock().get_mut(query)?.pop_front()
+    }
+}
+
+fn missing_response(query: &Query) -> String {
+    format!("No recorded response left for {query:?}")
+}
+
+fn transport_error(err: String) -> TransportError {
+    TransportErrorKind::custom_str(&err)
+}
+
+#[async_trait::async_trait]
+impl ProposerDutiesFetcher for ReplayBackend {
+    async fn proposer_duties(&self, epoch: u64) -> Result<Vec<ProposerDuty>, ConsensusError> {
+        let query = Query::ProposerDuties(epoch);
+        match self.next(&query) {
+            Some(ReplayEvent::ProposerDuties { result, .. }) => result
+                .map(|duties| duties.into_iter().map(ProposerDuty::from).collect())
+                .map_err(ConsensusError::Recorded),
+            _ => Err(ConsensusError::Recorded(missing_response(&query))),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl StateFetcher for ReplayBackend {
+    async fn get_state_update(
+        &self,
+        addresses: Vec<&Address>,
+        block_number: Option<u64>,
+    ) -> Result<StateUpdate, TransportError> {
+        let mut addresses = addresses.into_iter().copied().collect::<Vec<_>>();
+        addresses.sort();
+
+        let query = Query::StateUpdate(addresses, block_number);
+        match self.next(&query) {
+            Some(ReplayEvent::StateUpdate { result, .. }) => result.map_err(transport_error),
+            _ => Err(transport_error(missing_response(&query))),
+        }
+    }
+
+    async fn get_head(&self) -> Result<u64, TransportError> {
+        match self.next(&Query::BlockNumber) {
+            Some(ReplayEvent::BlockNumber { result }) => result.map_err(transport_error),
+            _ => Err(transport_error(missing_response(&Query::BlockNumber))),
+        }
+    }
+
+    async fn get_basefee(&self, block_number: Option<u64>) -> Result<u128, TransportError> {
+        let query = Query::Basefee(block_number);
+        match self.next(&query) {
+            Some(ReplayEvent::Basefee { result, .. }) => result.map_err(transport_error),
+            _ => Err(transport_error(missing_response(&query))),
+        }
+    }
+
+    async fn get_blob_basefee(&self, block_number: Option<u64>) -> Result<u128, TransportError> {
+        let query = Query::BlobBasefee(block_number);
+        match self.next(&query) {
+            Some(ReplayEvent::BlobBasefee { result, .. }) => result.map_err(transport_error),
+            _ => Err(transport_error(missing_response(&query))),
+        }
+    }
+
+    async fn get_account_state(
+        &self,
+        address: &Address,
+        block_number: Option<u64>,
+    ) -> Result<AccountState, TransportError> {
+        let query = Query::AccountState(*address, block_number);
+        match self.next(&query) {
+            Some(ReplayEvent::AccountState { result, .. }) => result.map_err(transport_error),
+            _ => Err(transport_error(missing_response(&query))),
+        }
+    }
+
+    async fn get_chain_id(&self) -> Result<u64, TransportError> {
+        match self.next(&Query::ChainId) {
+            Some(ReplayEvent::ChainId { result }) => result.map_err(transport_error),
+            _ => Err(transport_error(missing_response(&Query::ChainId))),
+        }
+    }
+}
+
+/// The first decision of a replay that doesn't match the recorded one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Divergence {
+    /// The index of the request in the replay log.
+    pub index: usize,
+    /// The digest of the request.
+    pub digest: B256,
+    /// The recorded decision, if any.
+    pub recorded: Option<Decision>,
+    /// The decision of the replay.
+    pub replayed: Decision,
+}
+
+/// The outcome of a replay.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayReport {
+    /// The number of replayed events, up to the divergence if any.
+    pub events: usize,
+    /// The number of replayed decisions, up to the divergence if any.
+    pub decisions: usize,
+    /// The first divergence from the recorded decisions, if any.
+    pub divergence: Option<Divergence>,
+}
+
+/// Runs the commitment validation of the driver against a replay log, on a virtual
+/// clock and with mock backends answering from the log, and compares its decisions
+/// with the recorded ones. Constraints are signed with a stub signature.
+#[derive(Debug)]
+pub struct Replayer {
+    events: Vec<ReplayEvent>,
+}
+
+impl Replayer {
+    /// Create a replayer for the given events.
+    pub fn new(events: Vec<ReplayEvent>) -> Self {
+        Self { events }
+    }
+
+    /// Create a replayer for the replay log at the given path.
+    pub fn from_file(path: &Path) -> Result<Self, ReplayError> {
+        Ok(Self::new(read_log(path)?))
+    }
+
+    /// Replay the log, stopping at the first divergence.
+    pub async fn run(self) -> Result<ReplayReport, ReplayError> {
+        let Some(ReplayEvent::Start {
+            validator_indexes,
+            commitment_deadline_ms,
+            limits,
+            basefee_margin,
+        }) = self.events.first().cloned()
+        else {
+            return Err(ReplayError::MissingStart);
+        };
+
+        let backend = ReplayBackend::new(&self.events);
+        let clock = VirtualTimeSource::default();
+        let mut execution =
+            ExecutionState::new(backend.clone(), limits).await?.with_basefee_margin(basefee_margin);
+        let deadline = Duration::from_millis(commitment_deadline_ms);
+        let mut consensus = ConsensusState::new(backend, validator_indexes, deadline)
+            .with_time_source(clock.clone());
+
+        // Accepted requests, until their constraints are committed
+        let mut accepted = HashMap::new();
+        let mut report = ReplayReport { events: 0, decisions: 0, divergence: None };
+
+        for (index, event) in self.events.iter().enumerate() {
+            report.events += 1;
+
+            match event.clone() {
+                ReplayEvent::Warmup { at_ms, slot } => {
+                    clock.set(Duration::from_millis(at_ms));
+                    if let Err(err) = consensus.warmup(slot).await {
+                        warn!(?err, "Replayed warmup of the consensus state failed");
+                    }
+                    if let Err(err) = execution.update_head(None, slot).await {
+                        warn!(?err, "Replayed warmup of the execution state failed");
+                    }
+                }
+                ReplayEvent::Head { at_ms, slot } => {
+                    clock.set(Duration::from_millis(at_ms));
+                    if let Err(err) = execution.update_head(None, slot).await {
+                        debug!(?err, slot, "Replayed head update failed");
+                    }
+                }
+                ReplayEvent::SlotStart { at_ms, slot } => {
+                    clock.set(Duration::from_millis(at_ms));
+                    execution.expire_before(slot);
+                    if let Err(err) = consensus.update_slot(slot).await {
+                        debug!(?err, slot, "Replayed slot update failed");
+                    }
+                }
+                ReplayEvent::Request { at_ms, mut request, signature, signer, leased } => {
+                    clock.set(Duration::from_millis(at_ms));
+                    request.signature =
+                        signature.and_then(|sig| Signature::try_from(sig.as_ref()).ok());
+                    request.signer = signer;
+
+                    let digest = request.digest();
+                    let mut commitment = CommitmentRequest::Inclusion(request);
+                    let result =
+                        validate_commitment(&consensus, &mut execution, leased, &mut commitment)
+                            .await;
+                    let CommitmentRequest::Inclusion(request) = commitment else {
+                        unreachable!("validation keeps the kind of the request")
+                    };
+                    let replayed = Decision::new(&request, &result);
+                    report.decisions += 1;
+
+                    let recorded = self.recorded_decision(index, digest);
+                    if recorded.as_ref() != Some(&replayed) {
+                        warn!(index, %digest, ?recorded, ?replayed, "Replay diverged");
+                        report.divergence = Some(Divergence { index, digest, recorded, replayed });
+                        return Ok(report);
+                    }
+
+                    if let Ok(validator_index) = result {
+                        accepted.insert(digest, (validator_index, request));
+                    }
+                }
+                ReplayEvent::Committed { digest } => {
+                    let Some((validator_index, request)) = accepted.remove(&digest) else {
+                        continue;
+                    };
+
+                    // Signing is stubbed, the signature doesn't affect later decisions
+                    let slot = request.slot;
+                    execution.release_reserved(slot, digest);
+                    let message = ConstraintsMessage::build(validator_index, request);
+                    let signed = SignedConstraints { message, signature: BLSSig::default() };
+                    execution.add_constraint(slot, signed);
+                }
+                ReplayEvent::Cancelled { digest } => {
+                    if let Some((_, request)) = accepted.remove(&digest) {
+                        execution.release_reserved(request.slot, digest);
+                        execution.release_queued(request.slot, digest);
+                    }
+                }
+                // Decisions are compared with their request, and responses answered by queries
+                _ => {}
+            }
+        }
+
+        info!(events = report.events, decisions = report.decisions, "Replay matched the log");
+        Ok(report)
+    }
+
+    /// Returns the first decision recorded for the request at `index` with the given digest.
+    fn recorded_decision(&self, index: usize, digest: B256) -> Option<Decision> {
+        self.events[index + 1..].iter().find_map(|event| match event {
+            ReplayEvent::Decision { digest: recorded, decision } if *recorded == digest => {
+                Some(decision.clone())
+            }
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::U256,
+        signers::{k256::SecretKey, local::PrivateKeySigner},
+    };
+
+    use super::*;
+    use crate::test_util::{create_signed_commitment_request, default_test_transaction};
+
+    const DEADLINE_MS: u64 = 8_000;
+    const BASEFEE: u128 = 1_000_000_000;
+
+    /// A backend with fixed responses, where the validator index of each slot is the slot.
+    #[derive(Debug, Clone, Copy)]
+    struct ScriptedBackend;
+
+    fn account() -> AccountState {
+        AccountState { transaction_count: 0, balance: U256::from(10u128.pow(18)), has_code: false }
+    }
+
+    #[async_trait::async_trait]
+    impl ProposerDutiesFetcher for ScriptedBackend {
+        async fn proposer_duties(&self, epoch: u64) -> Result<Vec<ProposerDuty>, ConsensusError> {
+            let slots = epoch * 32..(epoch + 1) * 32;
+            let duty = |slot| ProposerDuty {
+                public_key: Default::default(),
+                slot,
+                validator_index: slot as usize,
+            };
+            Ok(slots.map(duty).collect())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl StateFetcher for ScriptedBackend {
+        async fn get_state_update(
+            &self,
+            addresses: Vec<&Address>,
+            _block_number: Option<u64>,
+        ) -> Result<StateUpdate, TransportError> {
+            let account_states = addresses.into_iter().map(|address| (*address, account()));
+            Ok(StateUpdate {
+                account_states: account_states.collect(),
+                min_basefee: BASEFEE,
+                min_blob_basefee: BASEFEE,
+                block_number: 100,
+                block_timestamp: 1_200,
+                block_gas: Default::default(),
+            })
+        }
+
+        async fn get_head(&self) -> Result<u64, TransportError> {
+            Ok(100)
+        }
+
+        async fn get_basefee(&self, _block_number: Option<u64>) -> Result<u128, TransportError> {
+            Ok(BASEFEE)
+        }
+
+        async fn get_blob_basefee(&self, _block: Option<u64>) -> Result<u128, TransportError> {
+            Ok(BASEFEE)
+        }
+
+        async fn get_account_state(
+            &self,
+            _address: &Address,
+            _block_number: Option<u64>,
+        ) -> Result<AccountState, TransportError> {
+            Ok(account())
+        }
+
+        async fn get_chain_id(&self) -> Result<u64, TransportError> {
+            Ok(1337)
+        }
+    }
+
+    /// Records a scripted run the way the driver does: a warmup at the start of slot 1, then
+    /// requests for one of our slots, for a slot of another validator, and past the deadline.
+    async fn record_run(sk: &SecretKey) -> eyre::Result<Vec<ReplayEvent>> {
+        let sender = PrivateKeySigner::from(sk.clone()).address();
+        let recorder = ReplayRecorder::in_memory();
+        let clock = VirtualTimeSource::default();
+        let backend = Recorded::new(ScriptedBackend, Some(recorder.clone()));
+        let indexes = ValidatorIndexes::from(vec![2, 3]);
+
+        recorder.record(ReplayEvent::Start {
+            validator_indexes: indexes.clone(),
+            commitment_deadline_ms: DEADLINE_MS,
+            limits: Limits::default(),
+            basefee_margin: None,
+        });
+        let mut execution = ExecutionState::new(backend.clone(), Limits::default()).await?;
+        let mut consensus =
+            ConsensusState::new(backend, indexes, Duration::from_millis(DEADLINE_MS))
+                .with_time_source(clock.clone());
+
+        clock.set(Duration::from_secs(12));
+        recorder.record(ReplayEvent::Warmup { at_ms: 12_000, slot: 1 });
+        consensus.warmup(1).await?;
+        execution.update_head(None, 1).await?;
+
+        for (at_ms, slot, nonce) in [(13_000, 2, 0), (14_000, 5, 1), (21_000, 2, 1)] {
+            clock.set(Duration::from_millis(at_ms));
+            let tx = default_test_transaction(sender, Some(nonce));
+            let req = create_signed_commitment_request(&[tx], sk, slot).await?;
+            let req = req.into_inclusion_request().unwrap();
+            recorder.record(ReplayEvent::Request {
+                at_ms,
+                request: req.clone(),
+                signature: req.signature.map(|sig| sig.as_bytes().to_vec().into()),
+                signer: req.signer,
+                leased: true,
+            });
+
+            let digest = req.digest();
+            let mut request = CommitmentRequest::Inclusion(req.clone());
+            let result = validate_commitment(&consensus, &mut execution, true, &mut request).await;
+            let decision = Decision::new(&req, &result);
+            recorder.record(ReplayEvent::Decision { digest, decision });
+
+            if let Ok(validator_index) = result {
+                let message = ConstraintsMessage::build(validator_index, req);
+                let signed = SignedConstraints { message, signature: BLSSig::default() };
+                execution.add_constraint(slot, signed);
+                recorder.record(ReplayEvent::Committed { digest });
+            }
+        }
+
+        Ok(recorder.events())
+    }
+
+    #[tokio::test]
+    async fn test_replay_matches_recording() -> eyre::Result<()> {
+        let sk = SecretKey::random(&mut rand::thread_rng());
+        let events = record_run(&sk).await?;
+
+        let decisions = events
+            .iter()
+            .filter_map(|event| match event {
+                ReplayEvent::Decision { decision, .. } => Some(decision.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert!(matches!(decisions[0], Decision::Accepted { validator_index: 2, .. }));
+        assert!(matches!(decisions[1], Decision::Rejected { .. }));
+        assert!(matches!(decisions[2], Decision::Rejected { .. }));
+
+        // Replay from the JSON lines log, as the `replay` subcommand does
+        let name = format!("bolt-replay-{}.log", rand::random::<u64>());
+        let path = std::env::temp_dir().join(name);
+        let file = ReplayRecorder::create(&path)?;
+        events.iter().cloned().for_each(|event| file.record(event));
+        assert_eq!(read_log(&path)?.len(), events.len());
+
+        let report = Replayer::from_file(&path)?.run().await?;
+        assert_eq!(report.divergence, None);
+        assert_eq!(report.decisions, 3);
+        assert_eq!(report.events, events.len());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_replay_detects_divergence() -> eyre::Result<()> {
+        let sk = SecretKey::random(&mut rand::thread_rng());
+        let events = record_run(&sk).await?;
+
+        // The execution client now reports that the nonce of the sender was already used
+        let mut mutated = events.clone();
+        for event in mutated.iter_mut() {
+            if let ReplayEvent::AccountState { result: Ok(ref mut account), .. } = event {
+                account.transaction_count = 1;
+            }
+        }
+
+        let report = Replayer::new(mutated).run().await?;
+        let divergence = report.divergence.expect("replay should diverge");
+
+        let first_request =
+            events.iter().position(|event| matches!(event, ReplayEvent::Request { .. }));
+        assert_eq!(Some(divergence.index), first_request);
+        assert!(matches!(divergence.recorded, Some(Decision::Accepted { .. })));
+        assert!(matches!(divergence.replayed, Decision::Rejected { .. }));
+        assert_eq!(report.decisions, 1);
+
+        Ok(())
+    }
+}
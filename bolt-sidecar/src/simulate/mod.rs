@@ -0,0 +1,360 @@
+//! Validation of a commitment request outside the sidecar, for the `simulate` subcommand.
+//!
+//! The request goes through the same validation as in the driver, against a snapshot of the
+//! slot context and of the account states read from a JSON file. The account states can
+//! instead be fetched from a live execution client.
+
+use std::{collections::HashMap, io, path::Path, sync::Arc};
+
+use alloy::{
+    primitives::{Address, B256},
+    transports::TransportError,
+};
+use beacon_api_client::ProposerDuty;
+use ethereum_consensus::phase0::mainnet::SLOTS_PER_EPOCH;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    commitments::JsonError,
+    config::{ChainConfig, Limits, SimulateOpts, ValidatorIndexes},
+    crypto::SignableBLS,
+    driver::validate_commitment,
+    earnings::expected_priority_fee,
+    primitives::{AccountState, CommitmentRequest, ConstraintsMessage, InclusionRequest},
+    state::{
+        basefee::GasUsage,
+        clock::VirtualTimeSource,
+        consensus::{ConsensusError, ProposerDutiesFetcher},
+        execution::StateUpdate,
+        fetcher::StateFetcher,
+        ConsensusState, ExecutionState, StateClient,
+    },
+};
+
+/// Errors that can occur while setting up a simulation.
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum SimulationError {
+    #[error("Failed to read the simulation input: {0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid simulation input: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Failed to initialize the execution state: {0}")]
+    Execution(#[from] TransportError),
+    #[error("Failed to initialize the consensus state: {0}")]
+    Consensus(#[from] ConsensusError),
+}
+
+/// A snapshot of the slot context and of the account states to validate a request against.
+/// All the fields are optional.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimulationState {
+    /// The current slot. Defaults to the target slot of the request, so that its commitment
+    /// deadline hasn't passed.
+    pub slot: Option<u64>,
+    /// The index of the validator proposing at the target slot, on whose behalf the request
+    /// is validated.
+    pub proposer_index: u64,
+    /// The chain ID of the execution chain. Defaults to the one of the chain config.
+    pub chain_id: Option<u64>,
+    /// The number of the latest block.
+    pub block_number: u64,
+    /// The timestamp of the latest block, in seconds.
+    pub block_timestamp: u64,
+    /// The base fee of the latest block, in wei.
+    pub basefee: u128,
+    /// The gas used by the latest block, out of its gas limit. If the gas limit is 0, the
+    /// block is assumed as full as the blocks until the target slot.
+    pub block_gas: GasUsage,
+    /// The blob base fee of the latest block, in wei.
+    pub blob_basefee: u128,
+    /// The account states by address. Missing accounts have the default state.
+    pub accounts: HashMap<Address, AccountState>,
+    /// The limits of the sidecar.
+    pub limits: Limits,
+}
+
+/// The outcome of a simulation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SimulationOutcome {
+    /// The request would be accepted.
+    Accepted {
+        /// The digest of the request, signed by the commitment.
+        digest: B256,
+        /// The index of the validator proposing at the target slot.
+        validator_index: u64,
+        /// The digest of the constraints message that would be signed.
+        constraints: B256,
+        /// The priority fee the commitment would earn at the current base fee, in wei.
+        expected_fee: u128,
+    },
+    /// The request would be rejected with the given error.
+    Rejected {
+        /// The error that would be returned to the client.
+        error: JsonError,
+    },
+}
+
+impl SimulationOutcome {
+    /// Returns true if the request would be accepted.
+    pub fn is_accepted(&self) -> bool {
+        matches!(self, Self::Accepted { .. })
+    }
+}
+
+/// A backend answering from the snapshot, for both the consensus and the execution state.
+#[derive(Debug, Clone)]
+struct SnapshotBackend {
+    state: Arc<SimulationState>,
+    target_slot: u64,
+    chain_id: u64,
+}
+
+#[async_trait::async_trait]
+impl ProposerDutiesFetcher for SnapshotBackend {
+    async fn proposer_duties(&self, epoch: u64) -> Result<Vec<ProposerDuty>, ConsensusError> {
+        let duty = ProposerDuty {
+            public_key: Default::default(),
+            slot: self.target_slot,
+            validator_index: self.state.proposer_index as usize,
+        };
+
+        let in_epoch = self.target_slot / SLOTS_PER_EPOCH == epoch;
+        Ok(in_epoch.then_some(duty).into_iter().collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl StateFetcher for SnapshotBackend {
+    async fn get_state_update(
+        &self,
+        addresses: Vec<&Address>,
+        _block_number: Option<u64>,
+    ) -> Result<StateUpdate, TransportError> {
+        let account = |address: &Address| self.state.accounts.get(address).copied();
+        let account_states =
+            addresses.into_iter().map(|address| (*address, account(address).unwrap_or_default()));
+
+        Ok(StateUpdate {
+            account_states: account_states.collect(),
+            min_basefee: self.state.basefee,
+            min_blob_basefee: self.state.blob_basefee,
+            block_number: self.state.block_number,
+            block_timestamp: self.state.block_timestamp,
+            block_gas: self.state.block_gas,
+        })
+    }
+
+    async fn get_head(&self) -> Result<u64, TransportError> {
+        Ok(self.state.block_number)
+    }
+
+    async fn get_basefee(&self, _block_number: Option<u64>) -> Result<u128, TransportError> {
+        Ok(self.state.basefee)
+    }
+
+    async fn get_blob_basefee(&self, _block_number: Option<u64>) -> Result<u128, TransportError> {
+        Ok(self.state.blob_basefee)
+    }
+
+    async fn get_account_state(
+        &self,
+        address: &Address,
+        _block_number: Option<u64>,
+    ) -> Result<AccountState, TransportError> {
+        Ok(self.state.accounts.get(address).copied().unwrap_or_default())
+    }
+
+    async fn get_chain_id(&self) -> Result<u64, TransportError> {
+        Ok(self.chain_id)
+    }
+}
+
+/// Validates a single inclusion request the way the driver does, on a virtual clock and
+/// without a connection to the consensus client. Nothing is signed.
+#[derive(Debug)]
+pub struct Simulation {
+    request: InclusionRequest,
+    state: SimulationState,
+    signer: Address,
+    chain: ChainConfig,
+}
+
+impl Simulation {
+    /// Create a simulation of the request against the snapshot, on the given chain. The
+    /// request is signed by the zero address.
+    pub fn new(request: InclusionRequest, state: SimulationState, chain: ChainConfig) -> Self {
+        Self { request, state, signer: Address::ZERO, chain }
+    }
+
+    /// Validate the request as signed by the given address.
+    pub fn with_signer(self, signer: Address) -> Self {
+        Self { signer, ..self }
+    }
+
+    /// Create a simulation of the request at the given path, against the snapshot at the
+    /// given path if any, or the default one.
+    pub fn from_files(
+        request: &Path,
+        state: Option<&Path>,
+        chain: ChainConfig,
+    ) -> Result<Self, SimulationError> {
+        let request = serde_json::from_slice(&std::fs::read(request)?)?;
+        let state = match state {
+            Some(path) => serde_json::from_slice(&std::fs::read(path)?)?,
+            None => SimulationState::default(),
+        };
+
+        Ok(Self::new(request, state, chain))
+    }
+
+    /// Run the simulation described by the options of the `simulate` subcommand.
+    pub async fn run_opts(opts: SimulateOpts) -> Result<SimulationOutcome, SimulationError> {
+        let simulation = Self::from_files(&opts.request, opts.state.as_deref(), opts.chain)?
+            .with_signer(opts.signer.unwrap_or_default());
+
+        match opts.execution_api_url {
+            Some(url) => simulation.run_with(StateClient::new(url)).await,
+            None => simulation.run().await,
+        }
+    }
+
+    /// Run the simulation, with the account states of the snapshot.
+    pub async fn run(self) -> Result<SimulationOutcome, SimulationError> {
+        let backend = self.backend();
+        self.run_with(backend).await
+    }
+
+    /// Run the simulation, with the account states fetched by the given client. The slot
+    /// context still comes from the snapshot.
+    pub async fn run_with<C: StateFetcher>(
+        self,
+        client: C,
+    ) -> Result<SimulationOutcome, SimulationError> {
+        let backend = self.backend();
+        let slot = self.state.slot.unwrap_or(self.request.slot);
+
+        let indexes = ValidatorIndexes::from(vec![self.state.proposer_index]);
+        let mut consensus = ConsensusState::new(backend, indexes, self.chain.commitment_deadline())
+            .with_time_source(VirtualTimeSource::default());
+        consensus.warmup(slot).await?;
+
+        let mut execution =
+            ExecutionState::new(client, self.state.limits).await?.with_chain(self.chain);
+        execution.update_head(None, slot).await?;
+
+        let mut request = self.request;
+        request.set_signer(self.signer);
+        let digest = request.digest();
+
+        let mut commitment = CommitmentRequest::Inclusion(request);
+        let result = validate_commitment(&consensus, &mut execution, true, &mut commitment).await;
+        let CommitmentRequest::Inclusion(request) = commitment else {
+            unreachable!("validation keeps the kind of the request")
+        };
+
+        let validator_index = match result {
+            Ok(validator_index) => validator_index,
+            Err(err) => return Ok(SimulationOutcome::Rejected { error: err.into_json_error() }),
+        };
+
+        let basefee = execution.basefee();
+        let expected_fee = request.txs.iter().map(|tx| expected_priority_fee(tx, basefee)).sum();
+        let message = ConstraintsMessage::build(validator_index, request);
+        let constraints = B256::from_slice(&message.digest());
+
+        Ok(SimulationOutcome::Accepted { digest, validator_index, constraints, expected_fee })
+    }
+
+    fn backend(&self) -> SnapshotBackend {
+        SnapshotBackend {
+            state: Arc::new(self.state.clone()),
+            target_slot: self.request.slot,
+            chain_id: self.state.chain_id.unwrap_or(self.chain.chain_id()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use alloy::signers::{k256::SecretKey, local::PrivateKeySigner};
+    use clap::Parser;
+
+    use super::*;
+    use crate::test_util::{create_signed_commitment_request, default_test_transaction};
+
+    /// The first key of the default anvil accounts, funded in the fixtures.
+    const SECRET_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("src/simulate/testdata").join(name)
+    }
+
+    /// Writes a signed request for slot 100 to a temporary file, and runs the `simulate`
+    /// subcommand on it against the given fixture.
+    async fn simulate(state: &str) -> eyre::Result<SimulationOutcome> {
+        let sk = SecretKey::from_slice(&alloy::hex::decode(SECRET_KEY)?)?;
+        let signer = PrivateKeySigner::from(sk.clone()).address();
+        let tx = default_test_transaction(signer, Some(0));
+        let request = create_signed_commitment_request(&[tx], &sk, 100).await?;
+        let request = request.into_inclusion_request().unwrap();
+
+        let suffix: u64 = rand::random();
+        let path = std::env::temp_dir().join(format!("bolt-simulate-{suffix}.json"));
+        std::fs::write(&path, serde_json::to_vec(&request)?)?;
+
+        let opts = SimulateOpts::try_parse_from([
+            "simulate".to_string(),
+            format!("--request={}", path.display()),
+            format!("--state={}", fixture(state).display()),
+            format!("--signer={signer}"),
+        ])?;
+        let outcome = Simulation::run_opts(opts).await;
+        std::fs::remove_file(path)?;
+
+        Ok(outcome?)
+    }
+
+    fn reason(outcome: &SimulationOutcome) -> Option<&str> {
+        match outcome {
+            SimulationOutcome::Rejected { error } => error.data.as_ref()?["reason"].as_str(),
+            SimulationOutcome::Accepted { .. } => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_simulate_accepted() -> eyre::Result<()> {
+        let outcome = simulate("accepted.json").await?;
+
+        let SimulationOutcome::Accepted { validator_index, expected_fee, .. } = outcome else {
+            panic!("Expected the request to be accepted, got {outcome:?}");
+        };
+        assert_eq!(validator_index, 7);
+        // The full priority fee of 1 gwei fits below the max fee at a base fee of 10 gwei
+        assert_eq!(expected_fee, 21_000 * 1_000_000_000);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_simulate_rejected() -> eyre::Result<()> {
+        let outcome = simulate("nonce_too_low.json").await?;
+        assert!(!outcome.is_accepted());
+        assert_eq!(reason(&outcome), Some("nonce_too_low"));
+
+        // The max fee of 20 gwei is below the base fee of 30 gwei
+        let outcome = simulate("basefee_too_high.json").await?;
+        assert_eq!(reason(&outcome), Some("base_fee_too_low"));
+
+        // The outcome is printed with the JSON-RPC error of the rejection
+        let json = serde_json::to_value(&outcome)?;
+        assert_eq!(json["status"], "rejected");
+        assert_eq!(json["error"]["code"], -32006);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,419 @@
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::telemetry;
+
+/// The number of projection errors to keep in the rolling window.
+pub const PROJECTION_WINDOW: usize = 128;
+
+/// The minimum number of projection errors needed before adapting the margin.
+pub const MIN_ADAPTATION_SAMPLES: usize = 8;
+
+/// Fixed-point precision used to apply the margin, in parts per million.
+const MARGIN_PRECISION: u128 = 1_000_000;
+
+/// The EIP-1559 elasticity multiplier: the gas target of a block is half of its gas limit.
+const ELASTICITY_MULTIPLIER: u64 = 2;
+
+/// The EIP-1559 bound on the change of the base fee from a block to the next, 1/8 of it.
+const BASEFEE_MAX_CHANGE_DENOMINATOR: u128 = 8;
+
+/// The default fullness of the blocks between the head and the target slot, in percent of
+/// their gas limit: the worst case, in which the base fee rises the most.
+pub const DEFAULT_BLOCK_FULLNESS: u8 = 100;
+
+/// The gas used by a block, out of its gas limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GasUsage {
+    /// The gas used by the transactions of the block.
+    pub used: u64,
+    /// The gas limit of the block.
+    pub limit: u64,
+}
+
+/// Returns the base fee of the child of a block under EIP-1559, given the base fee and the
+/// gas usage of the block. Returns `None` if an overflow would occur.
+pub fn next_basefee(basefee: u128, gas: GasUsage) -> Option<u128> {
+    let target = (gas.limit / ELASTICITY_MULTIPLIER) as u128;
+    let used = gas.used.min(gas.limit) as u128;
+    if target == 0 || used == target {
+        return Some(basefee);
+    }
+
+    let delta =
+        |excess: u128| Some(basefee.checked_mul(excess)? / target / BASEFEE_MAX_CHANGE_DENOMINATOR);
+    if used > target {
+        basefee.checked_add(delta(used - target)?.max(1))
+    } else {
+        Some(basefee - delta(target - used)?)
+    }
+}
+
+/// Projects the base fee of the block at a target slot under EIP-1559, from the head block
+/// and the number of `blocks` that can be proposed between them. The change after the head
+/// follows from its gas usage if known, and each block in between is assumed to use
+/// `fullness` percent of its gas limit. Returns `None` if an overflow would occur.
+///
+/// With a fullness of 100%, this is the maximum base fee reachable at the target slot.
+pub fn project_eip1559(
+    head_basefee: u128,
+    head_gas: Option<GasUsage>,
+    blocks: u64,
+    fullness: u8,
+) -> Option<u128> {
+    // The change only depends on the ratio of the gas used to the gas limit, which a gas
+    // limit of 100 represents exactly for a fullness in percent
+    let assumed = GasUsage { used: fullness.min(100) as u64, limit: 100 };
+
+    let mut basefee = next_basefee(head_basefee, head_gas.unwrap_or(assumed))?;
+    for _ in 0..blocks {
+        basefee = next_basefee(basefee, assumed)?;
+    }
+
+    Some(basefee)
+}
+
+/// Scales a projected base fee by `margin`. The result is rounded up. Returns `None` if an
+/// overflow would occur.
+pub fn apply_margin(projected: u128, margin: f64) -> Option<u128> {
+    let margin = (margin.max(0.0) * MARGIN_PRECISION as f64).round() as u128;
+
+    Some(projected.checked_mul(margin)?.div_ceil(MARGIN_PRECISION))
+}
+
+/// Returns the error ratio of a projection, i.e. `actual / projected`.
+/// Values above 1.0 mean that the base fee was underestimated.
+pub fn projection_error(projected: u128, actual: u128) -> f64 {
+    if projected == 0 {
+        return if actual == 0 { 1.0 } else { f64::INFINITY };
+    }
+
+    actual as f64 / projected as f64
+}
+
+/// The distribution of the recent projection errors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProjectionStats {
+    /// The number of errors in the window.
+    pub samples: usize,
+    /// The mean error ratio.
+    pub mean: f64,
+    /// The median error ratio.
+    pub p50: f64,
+    /// The 95th percentile error ratio.
+    pub p95: f64,
+    /// The largest error ratio.
+    pub max: f64,
+    /// The number of projections that underestimated the actual base fee.
+    pub underestimated: usize,
+}
+
+impl ProjectionStats {
+    /// Computes the distribution of the given error ratios.
+    pub fn from_errors(errors: &[f64]) -> Self {
+        if errors.is_empty() {
+            return Self::default();
+        }
+
+        let mut sorted = errors.to_vec();
+        sorted.sort_by(f64::total_cmp);
+
+        let percentile = |p: f64| {
+            let rank = (p * sorted.len() as f64).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1]
+        };
+
+        Self {
+            samples: sorted.len(),
+            mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            p50: percentile(0.5),
+            p95: percentile(0.95),
+            max: sorted[sorted.len() - 1],
+            underestimated: sorted.iter().filter(|e| **e > 1.0).count(),
+        }
+    }
+}
+
+/// The bounds within which the projection margin adapts.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MarginBounds {
+    /// The minimum margin.
+    pub min: f64,
+    /// The maximum margin.
+    pub max: f64,
+}
+
+impl Default for MarginBounds {
+    fn default() -> Self {
+        Self { min: 1.0, max: 2.0 }
+    }
+}
+
+/// Returns the margin to apply to future projections given the recent errors: the 95th
+/// percentile error ratio, so that 95% of the recent projections would have been covered,
+/// clamped within the bounds. Returns `current` if there are not enough samples yet.
+pub fn adapt_margin(stats: &ProjectionStats, bounds: MarginBounds, current: f64) -> f64 {
+    if stats.samples < MIN_ADAPTATION_SAMPLES || !stats.p95.is_finite() {
+        return current;
+    }
+
+    stats.p95.clamp(bounds.min, bounds.max)
+}
+
+/// The status of the base fee projections, reported in the sidecar status.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BaseFeeStatus {
+    /// The margin currently applied to the projections.
+    pub margin: f64,
+    /// Whether the margin adapts to the projection errors.
+    pub adaptive: bool,
+    /// The distribution of the recent projection errors.
+    pub errors: ProjectionStats,
+}
+
+impl Default for BaseFeeStatus {
+    fn default() -> Self {
+        Self { margin: 1.0, adaptive: false, errors: ProjectionStats::default() }
+    }
+}
+
+/// Tracks the accuracy of the base fee projections made for the slots the sidecar
+/// committed to, and optionally adapts the projection margin to the observed errors.
+#[derive(Debug, Clone)]
+pub struct BaseFeeTracker {
+    /// Projections (without margin) by target slot, waiting for the actual base fee.
+    projections: HashMap<u64, u128>,
+    /// The rolling window of error ratios.
+    errors: VecDeque<f64>,
+    /// The margin applied to the projections.
+    margin: f64,
+    /// The adaptation bounds, if the margin adapts.
+    adaptive: Option<MarginBounds>,
+}
+
+impl Default for BaseFeeTracker {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl BaseFeeTracker {
+    /// Create a new tracker. If `adaptive` is set, the margin adapts within its bounds.
+    pub fn new(adaptive: Option<MarginBounds>) -> Self {
+        let margin = adaptive.map_or(1.0, |bounds| 1.0_f64.clamp(bounds.min, bounds.max));
+        Self { projections: HashMap::new(), errors: VecDeque::new(), margin, adaptive }
+    }
+
+    /// Returns the margin applied to the projections.
+    pub fn margin(&self) -> f64 {
+        self.margin
+    }
+
+    /// Scales a projected base fee by the current margin.
+    pub fn apply_margin(&self, projected: u128) -> Option<u128> {
+        apply_margin(projected, self.margin)
+    }
+
+    /// Records the projection (without margin) of the base fee for a target slot.
+    pub fn record_projection(&mut self, slot: u64, projected: u128) {
+        self.projections.insert(slot, projected);
+    }
+
+    /// Records the actual base fee of the block at `slot`, returning the error ratio
+    /// if a projection was made for it. Projections for earlier slots are discarded.
+    pub fn observe(&mut self, slot: u64, actual: u128) -> Option<f64> {
+        let projected = self.projections.remove(&slot);
+        self.projections.retain(|target, _| *target > slot);
+
+        let error = projection_error(projected?, actual);
+
+        if self.errors.len() == PROJECTION_WINDOW {
+            self.errors.pop_front();
+        }
+        self.errors.push_back(error);
+
+        metrics::histogram!(telemetry::BASEFEE_PROJECTION_ERROR).record(error);
+        if error > 1.0 {
+            metrics::counter!(telemetry::BASEFEE_UNDERESTIMATED).increment(1);
+        }
+
+        if let Some(bounds) = self.adaptive {
+            self.margin = adapt_margin(&self.stats(), bounds, self.margin);
+        }
+        metrics::gauge!(telemetry::BASEFEE_MARGIN).set(self.margin);
+
+        Some(error)
+    }
+
+    /// Returns the distribution of the recent projection errors.
+    pub fn stats(&self) -> ProjectionStats {
+        let errors = self.errors.iter().copied().collect::<Vec<_>>();
+        ProjectionStats::from_errors(&errors)
+    }
+
+    /// Returns the status of the projections.
+    pub fn status(&self) -> BaseFeeStatus {
+        BaseFeeStatus {
+            margin: self.margin,
+            adaptive: self.adaptive.is_some(),
+            errors: self.stats(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::calculate_max_basefee;
+
+    use super::*;
+
+    #[test]
+    fn test_project_basefee() {
+        let current = 10_000_000_000;
+
+        // A margin of 1.0 is the maximum EIP-1559 base fee
+        let projected = project_eip1559(current, None, 1, DEFAULT_BLOCK_FULLNESS).unwrap();
+        assert_eq!(apply_margin(projected, 1.0), Some(projected));
+        assert_eq!(apply_margin(current, 1.0), Some(current));
+
+        assert_eq!(apply_margin(current, 1.5), Some(15_000_000_000));
+        assert_eq!(apply_margin(3, 0.5), Some(2));
+        assert_eq!(apply_margin(u128::MAX, 2.0), None);
+    }
+
+    #[test]
+    fn test_next_basefee() {
+        let basefee = 10_000_000_000;
+        let gas = |used| GasUsage { used, limit: 30_000_000 };
+
+        // The base fee moves by up to 12.5%, in proportion to the gas used off the target
+        assert_eq!(next_basefee(basefee, gas(30_000_000)), Some(11_250_000_000));
+        assert_eq!(next_basefee(basefee, gas(22_500_000)), Some(10_625_000_000));
+        assert_eq!(next_basefee(basefee, gas(15_000_000)), Some(basefee));
+        assert_eq!(next_basefee(basefee, gas(0)), Some(8_750_000_000));
+
+        // A block above its target raises the base fee by at least 1 wei
+        assert_eq!(next_basefee(7, gas(15_000_001)), Some(8));
+        assert_eq!(next_basefee(basefee, GasUsage::default()), Some(basefee));
+        assert_eq!(next_basefee(u128::MAX, gas(30_000_000)), None);
+    }
+
+    #[test]
+    fn test_project_eip1559() {
+        let basefee = 10_000_000_000;
+        let head = |used| Some(GasUsage { used, limit: 30_000_000 });
+
+        // Targeting the next slot, the projection is the exact base fee after the head
+        assert_eq!(project_eip1559(basefee, head(15_000_000), 0, 100), Some(basefee));
+        assert_eq!(project_eip1559(basefee, head(0), 0, 100), Some(8_750_000_000));
+
+        // Full blocks in between: +12.5% after each block, the head's included if unknown,
+        // which is within the maximum of the margin projections
+        let worst = project_eip1559(basefee, None, 2, 100);
+        assert_eq!(worst, Some(14_238_281_250));
+        assert!(worst <= calculate_max_basefee(basefee, 3));
+        assert_eq!(project_eip1559(basefee, head(30_000_000), 2, 100), worst);
+        assert_eq!(project_eip1559(basefee, head(30_000_000), 2, 255), worst);
+
+        // Blocks at 75% of their gas limit raise the base fee by 1/16 each
+        assert_eq!(project_eip1559(basefee, head(30_000_000), 2, 75), Some(12_700_195_312));
+
+        // Blocks at their target keep it, and empty blocks lower it
+        assert_eq!(project_eip1559(basefee, head(15_000_000), 8, 50), Some(basefee));
+        assert_eq!(project_eip1559(basefee, None, 2, 0), Some(6_699_218_750));
+
+        assert_eq!(project_eip1559(u128::MAX, None, 0, 100), None);
+    }
+
+    #[test]
+    fn test_projection_stats() {
+        assert_eq!(projection_error(100, 110), 1.1);
+        assert_eq!(projection_error(0, 0), 1.0);
+
+        let errors = (1..=20).map(|i| i as f64 / 10.0).collect::<Vec<_>>();
+        let stats = ProjectionStats::from_errors(&errors);
+
+        assert_eq!(stats.samples, 20);
+        assert!((stats.mean - 1.05).abs() < 1e-9);
+        assert_eq!(stats.p50, 1.0);
+        assert_eq!(stats.p95, 1.9);
+        assert_eq!(stats.max, 2.0);
+        assert_eq!(stats.underestimated, 10);
+
+        assert_eq!(ProjectionStats::from_errors(&[]), ProjectionStats::default());
+    }
+
+    #[test]
+    fn test_adapt_margin() {
+        let bounds = MarginBounds { min: 0.9, max: 1.5 };
+        let stats = |p95: f64| ProjectionStats { samples: 10, p95, ..Default::default() };
+
+        assert_eq!(adapt_margin(&stats(1.2), bounds, 1.0), 1.2);
+        assert_eq!(adapt_margin(&stats(3.0), bounds, 1.0), 1.5);
+        assert_eq!(adapt_margin(&stats(0.5), bounds, 1.0), 0.9);
+
+        // Not enough samples yet
+        let few = ProjectionStats { samples: 2, p95: 1.4, ..Default::default() };
+        assert_eq!(adapt_margin(&few, bounds, 1.1), 1.1);
+    }
+
+    #[test]
+    fn test_observe_discards_stale_projections() {
+        let mut tracker = BaseFeeTracker::new(None);
+        tracker.record_projection(10, 100);
+        tracker.record_projection(11, 100);
+        tracker.record_projection(12, 100);
+
+        // Slot 10 was missed, so its projection is discarded
+        assert_eq!(tracker.observe(11, 120), Some(1.2));
+        assert_eq!(tracker.observe(10, 100), None);
+        assert_eq!(tracker.observe(12, 80), Some(0.8));
+        assert_eq!(tracker.stats().samples, 2);
+
+        // The margin doesn't move if adaptation is disabled
+        assert_eq!(tracker.margin(), 1.0);
+    }
+
+    /// Scripted volatile chain where the base fee regularly jumps by 50% between
+    /// consecutive slots, well above the EIP-1559 maximum.
+    #[test]
+    fn test_volatile_basefee_widens_margin() {
+        let bounds = MarginBounds { min: 1.0, max: 1.4 };
+        let mut tracker = BaseFeeTracker::new(Some(bounds));
+
+        // Users always bid 20% above the current base fee for the next slot
+        let bid = |basefee: u128| basefee * 12 / 10;
+
+        let mut basefee = 10_000_000_000u128;
+        let mut first_rejections = 0;
+        let mut last_rejections = 0;
+
+        for slot in 0..64u64 {
+            let projected = project_eip1559(basefee, None, 0, DEFAULT_BLOCK_FULLNESS).unwrap();
+            let required = tracker.apply_margin(projected).unwrap();
+            let rejected = bid(basefee) < required;
+            if slot < MIN_ADAPTATION_SAMPLES as u64 && rejected {
+                first_rejections += 1;
+            } else if slot >= 48 && rejected {
+                last_rejections += 1;
+            }
+
+            tracker.record_projection(slot + 1, projected);
+
+            basefee = if slot % 2 == 0 { basefee * 3 / 2 } else { basefee * 2 / 3 };
+            tracker.observe(slot + 1, basefee);
+        }
+
+        // Half of the projections underestimated the base fee, so the margin widened
+        let stats = tracker.stats();
+        assert_eq!(stats.samples, 64);
+        assert_eq!(stats.underestimated, 32);
+        assert!(tracker.margin() > 1.3 && tracker.margin() <= bounds.max);
+
+        // Before adapting, the bids covered the regular EIP-1559 maximum. With the wider
+        // margin they are rejected
+        assert_eq!(first_rejections, 0);
+        assert_eq!(last_rejections, 16);
+    }
+}
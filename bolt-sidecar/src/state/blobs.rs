@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use alloy::primitives::B256;
+
+use crate::primitives::Slot;
+
+/// Tracks the blobs of accepted commitment requests that aren't in a block template yet,
+/// against the share of the blobs of a block that can be committed to.
+///
+/// Blobs are reserved when a request passes validation, so that concurrent requests can't
+/// commit to more blobs than the block holds. The reservation is released when the request
+/// is added to the block template (which then accounts for its blobs), when it is cancelled,
+/// or when its slot expires.
+#[derive(Debug)]
+pub struct BlobBudget {
+    /// The percentage of the maximum blobs per block that can be committed to.
+    percent: u8,
+    /// The blobs reserved by accepted requests, by target slot and request digest.
+    reserved: HashMap<Slot, HashMap<B256, usize>>,
+}
+
+impl BlobBudget {
+    /// Creates a new budget allowing to commit to `percent` of the blobs of a block.
+    pub fn new(percent: u8) -> Self {
+        Self { percent: percent.min(100), reserved: HashMap::new() }
+    }
+
+    /// Returns the maximum number of committed blobs in a block holding `max_blobs` blobs.
+    pub fn ceiling(&self, max_blobs: usize) -> usize {
+        max_blobs * self.percent as usize / 100
+    }
+
+    /// Returns the number of blobs that can still be committed to in the given slot, if its
+    /// block holds `max_blobs` blobs and its template already contains `committed` blobs.
+    pub fn remaining(&self, slot: Slot, committed: usize, max_blobs: usize) -> usize {
+        self.ceiling(max_blobs).saturating_sub(committed + self.reserved(slot))
+    }
+
+    /// Returns the number of blobs reserved for the given slot.
+    pub fn reserved(&self, slot: Slot) -> usize {
+        self.reserved.get(&slot).map(|requests| requests.values().sum()).unwrap_or(0)
+    }
+
+    /// Returns the slots with reserved blobs.
+    pub fn slots(&self) -> impl Iterator<Item = &Slot> {
+        self.reserved.keys()
+    }
+
+    /// Reserves the blobs of the request with the given digest for the given slot.
+    pub fn reserve(&mut self, slot: Slot, digest: B256, blobs: usize) {
+        if blobs > 0 {
+            self.reserved.entry(slot).or_default().insert(digest, blobs);
+        }
+    }
+
+    /// Releases the blobs reserved by the request with the given digest, returning their number.
+    pub fn release(&mut self, slot: Slot, digest: B256) -> usize {
+        let Some(requests) = self.reserved.get_mut(&slot) else {
+            return 0;
+        };
+
+        let released = requests.remove(&digest).unwrap_or(0);
+        if requests.is_empty() {
+            self.reserved.remove(&slot);
+        }
+
+        released
+    }
+
+    /// Releases the blobs reserved for the given slot and all earlier ones.
+    pub fn expire(&mut self, slot: Slot) {
+        self.reserved.retain(|reserved_slot, _| *reserved_slot > slot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::eips::eip4844::MAX_BLOBS_PER_BLOCK;
+
+    use super::*;
+
+    #[test]
+    fn test_blob_budget() {
+        let mut budget = BlobBudget::new(50);
+        assert_eq!(budget.ceiling(MAX_BLOBS_PER_BLOCK), 3);
+
+        // Two requests fill the budget of the slot, with one blob already in the template
+        let (first, second, third) = (B256::random(), B256::random(), B256::random());
+        budget.reserve(10, first, 1);
+        budget.reserve(10, second, 1);
+        assert_eq!(budget.reserved(10), 2);
+
+        // There's no room left for a third one
+        assert_eq!(budget.remaining(10, 1, MAX_BLOBS_PER_BLOCK), 0);
+
+        // Cancelling the first request releases its blobs, and the third one fits
+        assert_eq!(budget.release(10, first), 1);
+        assert_eq!(budget.remaining(10, 1, MAX_BLOBS_PER_BLOCK), 1);
+        budget.reserve(10, third, 1);
+        assert_eq!(budget.remaining(10, 1, MAX_BLOBS_PER_BLOCK), 0);
+
+        // Other slots have their own budget, and expire separately
+        budget.reserve(11, first, 3);
+        budget.expire(10);
+        assert_eq!(budget.reserved(10), 0);
+        assert_eq!(budget.reserved(11), 3);
+        assert_eq!(budget.release(10, second), 0);
+    }
+}
@@ -0,0 +1,223 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use parking_lot::Mutex;
+
+/// A source of wall-clock time, expressed as the duration since the UNIX epoch.
+pub trait TimeSource: fmt::Debug + Clone + Send + Sync + 'static {
+    /// Returns the current time as the duration since the UNIX epoch.
+    fn now(&self) -> Duration;
+}
+
+/// The system wall clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemTimeSource;
+
+impl TimeSource for SystemTimeSource {
+    fn now(&self) -> Duration {
+        SystemTime::now().duration_since(UNIX_EPOCH).expect("system time after UNIX epoch")
+    }
+}
+
+/// A manually controlled clock for tests.
+///
+/// It advances along with the tokio clock, so that it can be used with paused tokio time
+/// (`#[tokio::test(start_paused = true)]`) to run timers at exact virtual offsets, and it can
+/// be made to jump forwards or backwards to simulate wall-clock corrections.
+#[derive(Debug, Clone)]
+pub struct ManualTimeSource {
+    start: tokio::time::Instant,
+    base: Arc<Mutex<Duration>>,
+}
+
+impl ManualTimeSource {
+    /// Create a new manual clock starting at the given time since the UNIX epoch.
+    pub fn new(now: Duration) -> Self {
+        Self { start: tokio::time::Instant::now(), base: Arc::new(Mutex::new(now)) }
+    }
+
+    /// Moves the clock forward by the given duration.
+    pub fn jump_forward(&self, by: Duration) {
+        *self.base.lock() += by;
+    }
+
+    /// Moves the clock backward by the given duration.
+    pub fn jump_backward(&self, by: Duration) {
+        let mut base = self.base.lock();
+        *base = base.saturating_sub(by);
+    }
+}
+
+impl TimeSource for ManualTimeSource {
+    fn now(&self) -> Duration {
+        *self.base.lock() + self.start.elapsed()
+    }
+}
+
+/// A clock that only moves when it is set, used to replay recorded events at their
+/// original timestamps regardless of how long the replay takes.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualTimeSource {
+    now: Arc<Mutex<Duration>>,
+}
+
+impl VirtualTimeSource {
+    /// Create a new virtual clock at the given time since the UNIX epoch.
+    pub fn new(now: Duration) -> Self {
+        Self { now: Arc::new(Mutex::new(now)) }
+    }
+
+    /// Sets the current time of the clock.
+    pub fn set(&self, now: Duration) {
+        *self.now.lock() = now;
+    }
+}
+
+impl TimeSource for VirtualTimeSource {
+    fn now(&self) -> Duration {
+        *self.now.lock()
+    }
+}
+
+/// The wall clock of the components that aren't generic over their [`TimeSource`], such as
+/// the commitments API. It is the system clock, unless a test replaces it.
+#[derive(Debug, Clone, Default)]
+pub enum Clock {
+    /// The system wall clock.
+    #[default]
+    System,
+    /// A manually controlled clock, see [`ManualTimeSource`].
+    Manual(ManualTimeSource),
+    /// A clock that only moves when it is set, see [`VirtualTimeSource`].
+    Virtual(VirtualTimeSource),
+}
+
+impl Clock {
+    /// Returns the current UNIX timestamp, in seconds.
+    pub fn unix_secs(&self) -> u64 {
+        self.now().as_secs()
+    }
+}
+
+impl TimeSource for Clock {
+    fn now(&self) -> Duration {
+        match self {
+            Self::System => SystemTimeSource.now(),
+            Self::Manual(time) => time.now(),
+            Self::Virtual(time) => time.now(),
+        }
+    }
+}
+
+/// A clock that maps wall-clock time to beacon chain slots, computed from the genesis time
+/// so that slot boundaries don't drift.
+///
+/// A correction can be applied to the wall-clock time when the host clock is known to
+/// drift from the chain. It is shared by all the clones of the clock.
+#[derive(Debug, Clone)]
+pub struct SlotClock<T = SystemTimeSource> {
+    genesis_time: Duration,
+    slot_time: Duration,
+    time: T,
+    /// The correction added to the wall-clock time, in milliseconds.
+    correction_ms: Arc<AtomicI64>,
+}
+
+impl SlotClock {
+    /// Create a new slot clock backed by the system wall clock.
+    pub fn new(genesis_time: u64, slot_time: u64) -> Self {
+        Self::with_time_source(genesis_time, slot_time, SystemTimeSource)
+    }
+}
+
+impl<T: TimeSource> SlotClock<T> {
+    /// Create a new slot clock backed by the given time source.
+    pub fn with_time_source(genesis_time: u64, slot_time: u64, time: T) -> Self {
+        Self {
+            genesis_time: Duration::from_secs(genesis_time),
+            slot_time: Duration::from_secs(slot_time),
+            time,
+            correction_ms: Arc::default(),
+        }
+    }
+
+    /// Returns the current time since the UNIX epoch, with the correction applied.
+    pub fn now(&self) -> Duration {
+        let local = self.local_now();
+        let correction_ms = self.correction_ms.load(Ordering::Relaxed);
+        let correction = Duration::from_millis(correction_ms.unsigned_abs());
+        if correction_ms < 0 {
+            local.saturating_sub(correction)
+        } else {
+            local + correction
+        }
+    }
+
+    /// Returns the current time of the host clock since the UNIX epoch, without the
+    /// correction.
+    pub fn local_now(&self) -> Duration {
+        self.time.now()
+    }
+
+    /// Sets the correction added to the time of the host clock, in milliseconds.
+    pub fn set_correction_ms(&self, correction_ms: i64) {
+        self.correction_ms.store(correction_ms, Ordering::Relaxed);
+    }
+
+    /// Returns the duration of a slot.
+    pub fn slot_time(&self) -> Duration {
+        self.slot_time
+    }
+
+    /// Returns the start time of the given slot, since the UNIX epoch.
+    pub fn slot_start(&self, slot: u64) -> Duration {
+        self.genesis_time + self.slot_time * slot as u32
+    }
+
+    /// Returns the current slot, or `None` if the chain hasn't started yet.
+    pub fn current_slot(&self) -> Option<u64> {
+        self.slot_at(self.now())
+    }
+
+    /// Returns the slot at the given time, or `None` if it's before genesis.
+    pub fn slot_at(&self, time: Duration) -> Option<u64> {
+        let since_genesis = time.checked_sub(self.genesis_time)?;
+        Some((since_genesis.as_nanos() / self.slot_time.as_nanos()) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_slot_clock() {
+        let time = ManualTimeSource::new(Duration::from_secs(990));
+        let clock = SlotClock::with_time_source(1_000, 12, time.clone());
+
+        assert_eq!(clock.current_slot(), None);
+
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        assert_eq!(clock.current_slot(), Some(0));
+        assert_eq!(clock.slot_start(2), Duration::from_secs(1_024));
+
+        time.jump_forward(Duration::from_secs(30));
+        assert_eq!(clock.now(), Duration::from_secs(1_030));
+        assert_eq!(clock.current_slot(), Some(2));
+
+        time.jump_backward(Duration::from_secs(20));
+        assert_eq!(clock.current_slot(), Some(0));
+
+        // The correction is shared by the clones, and doesn't apply to the host clock
+        clock.clone().set_correction_ms(-11_000);
+        assert_eq!(clock.now(), Duration::from_secs(999));
+        assert_eq!(clock.local_now(), Duration::from_secs(1_010));
+        assert_eq!(clock.current_slot(), None);
+    }
+}
@@ -1,19 +1,23 @@
-use std::{
-    fmt,
-    time::{Duration, Instant},
-};
+use std::{collections::HashMap, fmt, time::Duration};
 
 use beacon_api_client::{mainnet::Client, ProposerDuty};
 use ethereum_consensus::phase0::mainnet::SLOTS_PER_EPOCH;
-use tracing::debug;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
 
-use super::CommitmentDeadline;
 use crate::{
+    builder::template::BlockTemplate,
     config::ValidatorIndexes,
-    primitives::{CommitmentRequest, Slot},
+    primitives::{BlsPublicKey, CommitmentRequest, Slot},
     BeaconClient,
 };
 
+use super::{
+    clock::{SystemTimeSource, TimeSource},
+    maintenance::{DeclaredWindow, MaintenanceConflict, MaintenanceWindow, MaintenanceWindows},
+};
+
 /// Consensus-related errors
 #[derive(Debug, thiserror::Error)]
 #[allow(missing_docs)]
@@ -23,10 +27,88 @@ pub enum ConsensusError {
     BeaconApiError(#[from] beacon_api_client::Error),
     #[error("Invalid slot: {0}")]
     InvalidSlot(Slot),
-    #[error("Inclusion deadline exceeded")]
-    DeadlineExceeded,
+    #[error("The deadline of slot {slot} passed, {deadline_ms} ms into the previous slot")]
+    DeadlineExceeded {
+        /// The slot targeted by the request.
+        slot: Slot,
+        /// The deadline after which the requests for a slot are rejected, in milliseconds
+        /// after the start of the previous slot.
+        deadline_ms: u64,
+    },
+    #[error("The commitment deadline of slot {slot} passed during {stage}")]
+    DeadlinePassedDuringProcessing {
+        /// The slot targeted by the request.
+        slot: Slot,
+        /// The processing stage during which the deadline passed.
+        stage: ProcessingStage,
+        /// The next slot after the target one that this sidecar can serve, if any.
+        retarget_slot: Option<Slot>,
+    },
     #[error("Validator not found in the slot")]
     ValidatorNotFound,
+    #[error("The validator of slot {0} is leased by another sidecar instance")]
+    LeaseNotHeld(Slot),
+    #[error("Recorded beacon API error: {0}")]
+    Recorded(String),
+    #[error("Slot {slot} is not assigned to this sidecar's validators")]
+    NotOurSlot {
+        /// The slot targeted by the request.
+        slot: Slot,
+        /// The next upcoming slots that this sidecar can serve.
+        our_next_slots: Vec<Slot>,
+        /// Optional registry URL where the client can find the right sidecar for `slot`.
+        registry_hint_url: Option<Url>,
+    },
+    #[error("Invalid slot range: the range from slot {slot} ends before it, at {max_slot}")]
+    InvalidSlotRange {
+        /// The first slot of the range.
+        slot: Slot,
+        /// The last slot of the range.
+        max_slot: Slot,
+    },
+    #[error("The slot range ending at slot {0} has already passed")]
+    SlotRangePassed(Slot),
+    #[error("No slot from {slot} to {max_slot} is proposed by this sidecar's validators")]
+    NoProposerInRange {
+        /// The first slot of the range.
+        slot: Slot,
+        /// The last slot of the range.
+        max_slot: Slot,
+        /// The next upcoming slots that this sidecar can serve.
+        our_next_slots: Vec<Slot>,
+    },
+    #[error("The proposer of slot {slot} is unavailable")]
+    ProposerUnavailable {
+        /// The slot targeted by the request.
+        slot: Slot,
+        /// The next upcoming slots that this sidecar can serve.
+        our_next_slots: Vec<Slot>,
+    },
+}
+
+/// The maximum number of upcoming slots to include in a [`ConsensusError::NotOurSlot`] hint.
+pub const MAX_NEXT_SLOTS_HINT: usize = 4;
+
+/// The stages of the processing of a commitment request that can outlast its deadline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessingStage {
+    /// Validation against the execution state, which may wait for the execution client.
+    ExecutionValidation,
+    /// Signing of the commitment.
+    CommitmentSigning,
+    /// Waiting for the signature of the external commitment signer.
+    ExternalSignature,
+}
+
+impl fmt::Display for ProcessingStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ExecutionValidation => write!(f, "execution_validation"),
+            Self::CommitmentSigning => write!(f, "commitment_signing"),
+            Self::ExternalSignature => write!(f, "external_signature"),
+        }
+    }
 }
 
 /// Represents an epoch in the beacon chain.
@@ -38,92 +120,329 @@ pub struct Epoch {
     pub proposer_duties: Vec<ProposerDuty>,
 }
 
+/// A source of proposer duties.
+#[async_trait::async_trait]
+pub trait ProposerDutiesFetcher: Send + Sync {
+    /// Fetch the proposer duties of the given epoch.
+    async fn proposer_duties(&self, epoch: u64) -> Result<Vec<ProposerDuty>, ConsensusError>;
+}
+
+#[async_trait::async_trait]
+impl ProposerDutiesFetcher for Client {
+    async fn proposer_duties(&self, epoch: u64) -> Result<Vec<ProposerDuty>, ConsensusError> {
+        Ok(self.get_proposer_duties(epoch).await?.1)
+    }
+}
+
 /// Represents the consensus state container for the sidecar.
 #[allow(missing_debug_implementations)]
-pub struct ConsensusState {
-    beacon_api_client: Client,
+pub struct ConsensusState<B = BeaconClient, T = SystemTimeSource> {
+    beacon_api_client: B,
+    time: T,
     epoch: Epoch,
     validator_indexes: ValidatorIndexes,
-    // Timestamp of when the latest slot was received
-    latest_slot_timestamp: Instant,
+    // Timestamp of when the latest slot was received, since the UNIX epoch
+    latest_slot_timestamp: Duration,
     // The latest slot received
     latest_slot: Slot,
-    /// The deadline (expressed in seconds) in the slot for which to
-    /// stop accepting commitments.
+    /// The duration after the start of a slot after which commitments for the next
+    /// slot are not accepted anymore.
     ///
     /// This is used to prevent the sidecar from accepting commitments
     /// which won't have time to be included by the PBS pipeline.
-    // commitment_deadline: u64,
-    pub commitment_deadline: CommitmentDeadline,
-    /// The duration of the commitment deadline.
     commitment_deadline_duration: Duration,
+    /// Optional registry URL returned to clients targeting slots we can't serve.
+    registry_hint_url: Option<Url>,
+    /// Proposer duties of the next epoch, if prefetched.
+    next_epoch: Option<Epoch>,
+    /// The maintenance windows during which our validators won't propose.
+    maintenance: MaintenanceWindows,
 }
 
-impl fmt::Debug for ConsensusState {
+impl<B, T> fmt::Debug for ConsensusState<B, T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ConsensusState")
             .field("epoch", &self.epoch)
             .field("latest_slot", &self.latest_slot)
             .field("latest_slot_timestamp", &self.latest_slot_timestamp)
-            .field("commitment_deadline", &self.commitment_deadline)
+            .field("commitment_deadline_duration", &self.commitment_deadline_duration)
             .finish()
     }
 }
 
-impl ConsensusState {
+impl<B: ProposerDutiesFetcher> ConsensusState<B> {
     /// Create a new `ConsensusState` with the given configuration.
     pub fn new(
-        beacon_api_client: BeaconClient,
+        beacon_api_client: B,
         validator_indexes: ValidatorIndexes,
         commitment_deadline_duration: Duration,
     ) -> Self {
         ConsensusState {
             beacon_api_client,
+            time: SystemTimeSource,
             validator_indexes,
             epoch: Epoch::default(),
             latest_slot: Default::default(),
-            latest_slot_timestamp: Instant::now(),
-            commitment_deadline: CommitmentDeadline::new(0, commitment_deadline_duration),
+            latest_slot_timestamp: SystemTimeSource.now(),
             commitment_deadline_duration,
+            registry_hint_url: None,
+            next_epoch: None,
+            maintenance: MaintenanceWindows::default(),
         }
     }
+}
 
-    /// This function validates the state of the chain against a block. It checks 2 things:
+impl<B: ProposerDutiesFetcher, T: TimeSource> ConsensusState<B, T> {
+    /// Use the given time source for the commitment deadlines instead of the system clock.
+    pub fn with_time_source<U: TimeSource>(self, time: U) -> ConsensusState<B, U> {
+        ConsensusState {
+            beacon_api_client: self.beacon_api_client,
+            latest_slot_timestamp: time.now(),
+            time,
+            validator_indexes: self.validator_indexes,
+            epoch: self.epoch,
+            latest_slot: self.latest_slot,
+            commitment_deadline_duration: self.commitment_deadline_duration,
+            registry_hint_url: self.registry_hint_url,
+            next_epoch: self.next_epoch,
+            maintenance: self.maintenance,
+        }
+    }
+
+    /// Set the registry URL that is returned to clients targeting
+    /// slots that are not assigned to our validators.
+    pub fn with_registry_hint_url(mut self, url: Option<Url>) -> Self {
+        self.registry_hint_url = url;
+        self
+    }
+
+    /// Set the maintenance windows during which our validators won't propose.
+    pub fn with_maintenance(mut self, maintenance: MaintenanceWindows) -> Self {
+        self.maintenance = maintenance;
+        self
+    }
+
+    /// This function validates the state of the chain against a block. It checks 3 things:
     /// 1. The target slot is one of our proposer slots. (TODO)
     /// 2. The request hasn't passed the slot deadline.
+    /// 3. The proposer of the target slot isn't in a maintenance window.
     ///
     /// TODO: Integrate with the registry to check if we are registered.
     pub fn validate_request(&self, request: &CommitmentRequest) -> Result<u64, ConsensusError> {
-        let CommitmentRequest::Inclusion(req) = request;
+        let slot = request.slot();
 
         // Check if the slot is in the current epoch
-        if req.slot < self.epoch.start_slot || req.slot >= self.epoch.start_slot + SLOTS_PER_EPOCH {
-            return Err(ConsensusError::InvalidSlot(req.slot));
+        if slot < self.epoch.start_slot || slot >= self.epoch.start_slot + SLOTS_PER_EPOCH {
+            return Err(ConsensusError::InvalidSlot(slot));
         }
 
         // If the request is for the next slot, check if it's within the commitment deadline
-        if req.slot == self.latest_slot + 1
-            && self.latest_slot_timestamp + self.commitment_deadline_duration < Instant::now()
-        {
-            return Err(ConsensusError::DeadlineExceeded);
+        if self.deadline_passed(slot) {
+            return Err(self.deadline_exceeded(slot));
         }
 
         // Find the validator index for the given slot
-        let validator_index = self.find_validator_index_for_slot(req.slot)?;
+        let validator_index = match self.find_validator_index_for_slot(slot) {
+            Ok(index) => index,
+            Err(ConsensusError::ValidatorNotFound) => {
+                return Err(ConsensusError::NotOurSlot {
+                    slot,
+                    our_next_slots: self.our_next_slots(MAX_NEXT_SLOTS_HINT),
+                    registry_hint_url: self.registry_hint_url.clone(),
+                })
+            }
+            Err(err) => return Err(err),
+        };
+
+        // The validator won't propose during its maintenance windows
+        if self.maintenance.covers(validator_index, slot) {
+            return Err(ConsensusError::ProposerUnavailable {
+                slot,
+                our_next_slots: self.our_next_slots(MAX_NEXT_SLOTS_HINT),
+            });
+        }
 
         Ok(validator_index)
     }
 
+    /// Checks again that the commitment deadline of the target slot hasn't passed, right
+    /// before committing to a request that was validated at the given stage.
+    ///
+    /// If it has, the error includes the next slot of our validators after the target one,
+    /// to which the client may retarget the request.
+    pub fn check_deadline(&self, slot: Slot, stage: ProcessingStage) -> Result<(), ConsensusError> {
+        if !self.deadline_passed(slot) {
+            return Ok(());
+        }
+
+        let retarget_slot = self
+            .epoch
+            .proposer_duties
+            .iter()
+            .filter(|duty| self.is_available(duty))
+            .map(|duty| duty.slot)
+            .find(|next| *next > slot);
+
+        Err(ConsensusError::DeadlinePassedDuringProcessing { slot, stage, retarget_slot })
+    }
+
+    /// Returns the earliest slot from `slot` to `max_slot` that requests can target: proposed
+    /// by one of our validators outside of their maintenance windows, and whose commitment
+    /// deadline hasn't passed.
+    ///
+    /// Returns `None` if there is no such slot in the lookahead, but the range extends past
+    /// it: the duties of the next epoch may still have one.
+    pub fn resolve_slot_range(
+        &self,
+        slot: Slot,
+        max_slot: Slot,
+    ) -> Result<Option<Slot>, ConsensusError> {
+        if max_slot < slot {
+            return Err(ConsensusError::InvalidSlotRange { slot, max_slot });
+        }
+        if self.is_past_deadline(max_slot) {
+            return Err(ConsensusError::SlotRangePassed(max_slot));
+        }
+
+        let earliest = self
+            .epoch
+            .proposer_duties
+            .iter()
+            .filter(|duty| (slot..=max_slot).contains(&duty.slot))
+            .filter(|duty| self.is_available(duty) && !self.is_past_deadline(duty.slot))
+            .map(|duty| duty.slot)
+            .min();
+
+        match earliest {
+            Some(slot) => Ok(Some(slot)),
+            None if max_slot > self.lookahead_end_slot() => Ok(None),
+            None => Err(ConsensusError::NoProposerInRange {
+                slot,
+                max_slot,
+                our_next_slots: self.our_next_slots(MAX_NEXT_SLOTS_HINT),
+            }),
+        }
+    }
+
+    /// Returns the last slot of the current epoch, the last one requests can target.
+    pub fn lookahead_end_slot(&self) -> Slot {
+        self.epoch.start_slot + SLOTS_PER_EPOCH - 1
+    }
+
+    /// Returns true if the commitment deadline of the slot has passed, including for the
+    /// slots already reached by the chain.
+    pub fn is_past_deadline(&self, slot: Slot) -> bool {
+        slot <= self.latest_slot || self.deadline_passed(slot)
+    }
+
+    /// Returns the error of a request for the slot made after its commitment deadline.
+    pub fn deadline_exceeded(&self, slot: Slot) -> ConsensusError {
+        let deadline_ms = self.commitment_deadline_duration.as_millis() as u64;
+        ConsensusError::DeadlineExceeded { slot, deadline_ms }
+    }
+
+    /// Returns true if the slot is the next one and its commitment deadline has passed.
+    fn deadline_passed(&self, slot: Slot) -> bool {
+        slot == self.latest_slot + 1 &&
+            self.latest_slot_timestamp + self.commitment_deadline_duration < self.time.now()
+    }
+
+    /// Returns up to `limit` upcoming slots in the current epoch
+    /// that are proposed by one of our validators, outside of their maintenance windows.
+    pub fn our_next_slots(&self, limit: usize) -> Vec<Slot> {
+        self.epoch
+            .proposer_duties
+            .iter()
+            .filter(|duty| duty.slot > self.latest_slot && self.is_available(duty))
+            .map(|duty| duty.slot)
+            .take(limit)
+            .collect()
+    }
+
+    /// Returns the public keys of our validators with proposer duties in the current
+    /// and next epoch, if known.
+    pub fn our_validator_pubkeys(&self) -> Vec<BlsPublicKey> {
+        let mut pubkeys = Vec::new();
+        for duty in self.our_duties() {
+            if !pubkeys.contains(&duty.public_key) {
+                pubkeys.push(duty.public_key.clone());
+            }
+        }
+        pubkeys
+    }
+
+    /// Returns the public key of our validator proposing at the given slot, if any.
+    pub fn proposer_pubkey(&self, slot: Slot) -> Option<&BlsPublicKey> {
+        self.our_duties().find(|duty| duty.slot == slot).map(|duty| &duty.public_key)
+    }
+
+    /// Returns true if the duty is one of our validators, outside of its maintenance windows.
+    fn is_available(&self, duty: &ProposerDuty) -> bool {
+        let index = duty.validator_index as u64;
+        self.validator_indexes.contains(index) && !self.maintenance.covers(index, duty.slot)
+    }
+
+    /// Returns the declared maintenance windows.
+    pub fn maintenance_windows(&self) -> Vec<DeclaredWindow> {
+        self.maintenance.windows()
+    }
+
+    /// Declares a maintenance window on behalf of the given operator. The commitments
+    /// already signed for the slots of the window are returned as conflicts, with a warning:
+    /// they must still be honored, unless an operator drops them.
+    pub fn declare_maintenance(
+        &mut self,
+        window: MaintenanceWindow,
+        declared_by: &str,
+        templates: &HashMap<Slot, BlockTemplate>,
+    ) -> (DeclaredWindow, Vec<MaintenanceConflict>) {
+        let declared = self.maintenance.declare(window, declared_by);
+
+        let mut conflicts = Vec::new();
+        for duty in self.our_duties() {
+            let index = duty.validator_index as u64;
+            let Some(template) = templates.get(&duty.slot) else { continue };
+            if !self.maintenance.window_covers(&window, index, duty.slot) {
+                continue;
+            }
+
+            for constraints in &template.signed_constraints_list {
+                let digest = constraints.message.request_digest();
+                warn!(
+                    %digest,
+                    slot = duty.slot,
+                    validator_index = index,
+                    "Maintenance window covers a signed commitment, which must still be honored \
+                     or dropped"
+                );
+                conflicts.push(MaintenanceConflict { digest, slot: duty.slot });
+            }
+        }
+
+        (declared, conflicts)
+    }
+
+    /// Cancels the maintenance window with the given identifier, if it was declared.
+    pub fn cancel_maintenance(&mut self, id: u64) -> Option<DeclaredWindow> {
+        self.maintenance.cancel(id)
+    }
+
+    fn our_duties(&self) -> impl Iterator<Item = &ProposerDuty> {
+        let next = self.next_epoch.iter().flat_map(|epoch| epoch.proposer_duties.iter());
+        self.epoch
+            .proposer_duties
+            .iter()
+            .chain(next)
+            .filter(|duty| self.validator_indexes.contains(duty.validator_index as u64))
+    }
+
     /// Update the latest head and fetch the relevant data from the beacon chain.
     pub async fn update_slot(&mut self, slot: u64) -> Result<(), ConsensusError> {
         debug!("Updating slot to {slot}");
-        // Reset the commitment deadline to start counting for the next slot.
-        self.commitment_deadline =
-            CommitmentDeadline::new(slot + 1, self.commitment_deadline_duration);
-
         // Update the timestamp with current time
-        self.latest_slot_timestamp = Instant::now();
+        self.latest_slot_timestamp = self.time.now();
         self.latest_slot = slot;
+        self.maintenance.prune(slot);
 
         // Calculate the current value of epoch
         let epoch = slot / SLOTS_PER_EPOCH;
@@ -131,6 +450,13 @@ impl ConsensusState {
         // If the epoch has changed, update the proposer duties
         if epoch != self.epoch.value {
             debug!("Updating epoch to {epoch}");
+
+            // Use the prefetched duties if we have them
+            if let Some(next) = self.next_epoch.take().filter(|next| next.value == epoch) {
+                self.epoch = next;
+                return Ok(());
+            }
+
             self.epoch.value = epoch;
             self.epoch.start_slot = epoch * SLOTS_PER_EPOCH;
 
@@ -144,11 +470,32 @@ impl ConsensusState {
         Ok(())
     }
 
+    /// Prefetch the proposer duties for the epoch of the given slot and the next one,
+    /// so that the first requests after startup don't hit an unknown lookahead.
+    pub async fn warmup(&mut self, slot: u64) -> Result<(), ConsensusError> {
+        let epoch = slot / SLOTS_PER_EPOCH;
+        let (current, next) = tokio::try_join!(
+            self.beacon_api_client.proposer_duties(epoch),
+            self.beacon_api_client.proposer_duties(epoch + 1)
+        )?;
+
+        self.latest_slot = slot;
+        self.latest_slot_timestamp = self.time.now();
+        self.epoch =
+            Epoch { value: epoch, start_slot: epoch * SLOTS_PER_EPOCH, proposer_duties: current };
+        self.next_epoch = Some(Epoch {
+            value: epoch + 1,
+            start_slot: (epoch + 1) * SLOTS_PER_EPOCH,
+            proposer_duties: next,
+        });
+
+        Ok(())
+    }
+
     /// Fetch proposer duties for the given epoch.
     async fn fetch_proposer_duties(&mut self, epoch: u64) -> Result<(), ConsensusError> {
-        let duties = self.beacon_api_client.get_proposer_duties(epoch).await?;
+        self.epoch.proposer_duties = self.beacon_api_client.proposer_duties(epoch).await?;
 
-        self.epoch.proposer_duties = duties.1;
         Ok(())
     }
 
@@ -169,11 +516,14 @@ impl ConsensusState {
 #[cfg(test)]
 mod tests {
     use beacon_api_client::ProposerDuty;
-    use reqwest::Url;
     use tracing::warn;
 
     use super::*;
-    use crate::test_util::try_get_beacon_api_url;
+    use crate::{
+        primitives::{ConstraintsMessage, InclusionRequest, SignedConstraints},
+        state::{clock::VirtualTimeSource, maintenance::Blackout},
+        test_util::try_get_beacon_api_url,
+    };
 
     #[tokio::test]
     async fn test_find_validator_index_for_slot() {
@@ -191,11 +541,14 @@ mod tests {
         let state = ConsensusState {
             beacon_api_client: Client::new(Url::parse("http://localhost").unwrap()),
             epoch: Epoch { value: 0, start_slot: 0, proposer_duties },
-            latest_slot_timestamp: Instant::now(),
-            commitment_deadline: CommitmentDeadline::new(0, Duration::from_secs(1)),
+            time: SystemTimeSource,
+            latest_slot_timestamp: SystemTimeSource.now(),
             validator_indexes,
             commitment_deadline_duration: Duration::from_secs(1),
             latest_slot: 0,
+            registry_hint_url: None,
+            next_epoch: None,
+            maintenance: MaintenanceWindows::default(),
         };
 
         // Test finding a valid slot
@@ -209,6 +562,221 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_not_our_slot_hint() {
+        let proposer_duties = (0..8)
+            .map(|slot| ProposerDuty {
+                public_key: Default::default(),
+                slot,
+                validator_index: 100 + slot as usize,
+            })
+            .collect();
+
+        let hint_url = Url::parse("https://registry.example.com/lookahead").unwrap();
+
+        let state = ConsensusState {
+            beacon_api_client: Client::new(Url::parse("http://localhost").unwrap()),
+            epoch: Epoch { value: 0, start_slot: 0, proposer_duties },
+            time: SystemTimeSource,
+            latest_slot_timestamp: SystemTimeSource.now(),
+            validator_indexes: ValidatorIndexes::from(vec![101, 103, 105, 106, 107]),
+            commitment_deadline_duration: Duration::from_secs(1),
+            latest_slot: 1,
+            registry_hint_url: Some(hint_url.clone()),
+            next_epoch: None,
+            maintenance: MaintenanceWindows::default(),
+        };
+
+        let request = CommitmentRequest::Inclusion(InclusionRequest {
+            slot: 4,
+            txs: vec![],
+            signature: None,
+            signer: None,
+            context: None,
+            atomic: false,
+            max_slot: None,
+        });
+
+        let Err(ConsensusError::NotOurSlot { slot, our_next_slots, registry_hint_url }) =
+            state.validate_request(&request)
+        else {
+            panic!("expected NotOurSlot error");
+        };
+
+        assert_eq!(slot, 4);
+        // Slot 1 is ours but already in the past, and the hint is limited in size
+        assert_eq!(our_next_slots, vec![3, 5, 6, 7]);
+        assert_eq!(registry_hint_url, Some(hint_url));
+    }
+
+    #[test]
+    fn test_resolve_slot_range() {
+        let proposer_duties = (0..8)
+            .map(|slot| ProposerDuty {
+                public_key: Default::default(),
+                slot,
+                validator_index: 100 + slot as usize,
+            })
+            .collect();
+
+        // Our only duty is at slot 4, two slots after the next one
+        let state = ConsensusState {
+            beacon_api_client: Client::new(Url::parse("http://localhost").unwrap()),
+            epoch: Epoch { value: 0, start_slot: 0, proposer_duties },
+            time: SystemTimeSource,
+            latest_slot_timestamp: SystemTimeSource.now(),
+            validator_indexes: ValidatorIndexes::from(vec![101, 104]),
+            commitment_deadline_duration: Duration::from_secs(1),
+            latest_slot: 1,
+            registry_hint_url: None,
+            next_epoch: None,
+            maintenance: MaintenanceWindows::default(),
+        };
+
+        assert_eq!(state.resolve_slot_range(2, 6).unwrap(), Some(4));
+        assert_eq!(state.resolve_slot_range(4, 4).unwrap(), Some(4));
+
+        // Slot 1 is ours but already in the past
+        assert!(matches!(state.resolve_slot_range(0, 1), Err(ConsensusError::SlotRangePassed(1))));
+        assert!(matches!(
+            state.resolve_slot_range(6, 2),
+            Err(ConsensusError::InvalidSlotRange { slot: 6, max_slot: 2 })
+        ));
+
+        let Err(ConsensusError::NoProposerInRange { slot, max_slot, our_next_slots }) =
+            state.resolve_slot_range(5, 20)
+        else {
+            panic!("expected NoProposerInRange error");
+        };
+        assert_eq!((slot, max_slot, our_next_slots), (5, 20, vec![4]));
+
+        // The duties of the next epoch aren't known yet
+        assert_eq!(state.resolve_slot_range(5, 40).unwrap(), None);
+    }
+
+    #[test]
+    fn test_deadline_passed_during_processing() {
+        let proposer_duties = (0..8)
+            .map(|slot| ProposerDuty {
+                public_key: Default::default(),
+                slot,
+                validator_index: 100 + slot as usize,
+            })
+            .collect();
+
+        let clock = VirtualTimeSource::new(Duration::from_secs(12));
+        let state = ConsensusState {
+            beacon_api_client: Client::new(Url::parse("http://localhost").unwrap()),
+            epoch: Epoch { value: 0, start_slot: 0, proposer_duties },
+            time: clock.clone(),
+            latest_slot_timestamp: clock.now(),
+            validator_indexes: ValidatorIndexes::from(vec![102, 105]),
+            commitment_deadline_duration: Duration::from_secs(8),
+            latest_slot: 1,
+            registry_hint_url: None,
+            next_epoch: None,
+            maintenance: MaintenanceWindows::default(),
+        };
+
+        let request = |slot| {
+            CommitmentRequest::Inclusion(InclusionRequest {
+                slot,
+                txs: vec![],
+                signature: None,
+                signer: None,
+                context: None,
+                atomic: false,
+                max_slot: None,
+            })
+        };
+
+        // The request is validated before the deadline
+        clock.set(Duration::from_secs(19));
+        assert_eq!(state.validate_request(&request(2)).unwrap(), 102);
+        assert!(state.check_deadline(2, ProcessingStage::ExecutionValidation).is_ok());
+
+        // The execution client is slow, the deadline passes before signing
+        clock.set(Duration::from_secs(21));
+        let Err(ConsensusError::DeadlinePassedDuringProcessing { slot, stage, retarget_slot }) =
+            state.check_deadline(2, ProcessingStage::ExecutionValidation)
+        else {
+            panic!("expected DeadlinePassedDuringProcessing error");
+        };
+        assert_eq!(slot, 2);
+        assert_eq!(stage, ProcessingStage::ExecutionValidation);
+        assert_eq!(retarget_slot, Some(5));
+
+        // The new requests are rejected with the deadline they missed
+        let err = state.validate_request(&request(2)).unwrap_err();
+        assert!(matches!(err, ConsensusError::DeadlineExceeded { slot: 2, deadline_ms: 8_000 }));
+
+        // Later slots aren't affected by the deadline of the next one
+        assert!(state.check_deadline(5, ProcessingStage::CommitmentSigning).is_ok());
+    }
+
+    #[test]
+    fn test_maintenance_window() {
+        let proposer_duties = (0..8)
+            .map(|slot| ProposerDuty {
+                public_key: Default::default(),
+                slot,
+                validator_index: 100 + slot as usize % 2,
+            })
+            .collect();
+
+        let mut state = ConsensusState {
+            beacon_api_client: Client::new(Url::parse("http://localhost").unwrap()),
+            epoch: Epoch { value: 0, start_slot: 0, proposer_duties },
+            time: SystemTimeSource,
+            latest_slot_timestamp: SystemTimeSource.now(),
+            validator_indexes: ValidatorIndexes::from(vec![100, 101]),
+            commitment_deadline_duration: Duration::from_secs(1),
+            latest_slot: 1,
+            registry_hint_url: None,
+            next_epoch: None,
+            maintenance: MaintenanceWindows::new(1_000, 12),
+        };
+
+        let request = |slot| InclusionRequest {
+            slot,
+            txs: vec![],
+            signature: None,
+            signer: None,
+            context: None,
+            atomic: false,
+            max_slot: None,
+        };
+
+        // A commitment was already signed for slot 3 of validator 101
+        let message = ConstraintsMessage::build(101, request(3));
+        let digest = message.request_digest();
+        let mut template = BlockTemplate::default();
+        template.add_constraints(SignedConstraints { message, ..Default::default() });
+        let templates = HashMap::from([(3, template)]);
+
+        // The window of validator 101 covers its upcoming duty in slot 3
+        let window = MaintenanceWindow {
+            validator_index: 101,
+            blackout: Blackout::Timestamps { from: 1_030, to: 1_040 },
+        };
+        let (declared, conflicts) = state.declare_maintenance(window, "127.0.0.1", &templates);
+        assert_eq!(conflicts, vec![MaintenanceConflict { digest, slot: 3 }]);
+        assert_eq!(state.maintenance_windows(), vec![declared.clone()]);
+
+        let Err(ConsensusError::ProposerUnavailable { slot, our_next_slots }) =
+            state.validate_request(&CommitmentRequest::Inclusion(request(3)))
+        else {
+            panic!("expected ProposerUnavailable error");
+        };
+        assert_eq!(slot, 3);
+        assert_eq!(our_next_slots, vec![2, 4, 5, 6]);
+        assert_eq!(state.validate_request(&CommitmentRequest::Inclusion(request(5))).unwrap(), 101);
+
+        // Once cancelled, the slot can be served again
+        assert_eq!(state.cancel_maintenance(declared.id), Some(declared));
+        assert_eq!(state.our_next_slots(MAX_NEXT_SLOTS_HINT), vec![2, 3, 4, 5]);
+    }
+
     #[tokio::test]
     async fn test_update_slot() -> eyre::Result<()> {
         let _ = tracing_subscriber::fmt::try_init();
@@ -228,10 +796,13 @@ mod tests {
             beacon_api_client: beacon_client,
             epoch: Epoch::default(),
             latest_slot: Default::default(),
-            latest_slot_timestamp: Instant::now(),
+            time: SystemTimeSource,
+            latest_slot_timestamp: SystemTimeSource.now(),
             validator_indexes,
-            commitment_deadline: CommitmentDeadline::new(0, commitment_deadline_duration),
             commitment_deadline_duration,
+            registry_hint_url: None,
+            next_epoch: None,
+            maintenance: MaintenanceWindows::default(),
         };
 
         // Update the slot to 32
@@ -239,7 +810,7 @@ mod tests {
 
         // Check values were updated correctly
         assert_eq!(state.latest_slot, 32);
-        assert!(state.latest_slot_timestamp.elapsed().as_secs() < 1);
+        assert!((SystemTimeSource.now() - state.latest_slot_timestamp).as_secs() < 1);
         assert_eq!(state.epoch.value, 1);
         assert_eq!(state.epoch.start_slot, 32);
 
@@ -248,7 +819,7 @@ mod tests {
 
         // Check values were updated correctly
         assert_eq!(state.latest_slot, 63);
-        assert!(state.latest_slot_timestamp.elapsed().as_secs() < 1);
+        assert!((SystemTimeSource.now() - state.latest_slot_timestamp).as_secs() < 1);
         assert_eq!(state.epoch.value, 1);
         assert_eq!(state.epoch.start_slot, 32);
 
@@ -0,0 +1,326 @@
+//! Detection of the drift of the host clock from the chain.
+//!
+//! The slot clock of the sidecar is derived from the host clock, so a host whose clock
+//! drifts (e.g. because of a broken NTP setup) mis-times the slot starts and the commitment
+//! deadlines. The drift is estimated from the delay between the start of a slot and the
+//! local time at which its beacon head and execution block are observed: the propagation
+//! of a block is never negative, and the fastest observed blocks arrive shortly after the
+//! start of their slot.
+
+use std::{collections::VecDeque, fmt, str::FromStr, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::telemetry;
+
+/// Default drift of the host clock above which a warning is logged.
+pub const DEFAULT_MAX_CLOCK_DRIFT: Duration = Duration::from_secs(1);
+
+/// Default drift of the host clock above which requests are rejected, with the `pause`
+/// action.
+pub const DEFAULT_CLOCK_DRIFT_BOUND: Duration = Duration::from_secs(3);
+
+/// Number of the latest observations the drift is estimated from.
+const DRIFT_WINDOW: usize = 32;
+
+/// Minimum number of observations for the drift to be estimated, so that a single block
+/// delivered right after a reconnection isn't mistaken for a drift.
+const MIN_DRIFT_SAMPLES: usize = 4;
+
+/// What to do when the drift of the host clock exceeds its thresholds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClockDriftAction {
+    /// Only log a warning.
+    #[default]
+    Warn,
+    /// Correct the slot clock by the estimated drift.
+    Correct,
+    /// Reject requests while the drift exceeds the safety bound.
+    Pause,
+}
+
+impl FromStr for ClockDriftAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "warn" => Ok(Self::Warn),
+            "correct" => Ok(Self::Correct),
+            "pause" => Ok(Self::Pause),
+            other => Err(format!(
+                "Unknown clock drift action '{other}', expected 'warn', 'correct' or 'pause'"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ClockDriftAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Warn => write!(f, "warn"),
+            Self::Correct => write!(f, "correct"),
+            Self::Pause => write!(f, "pause"),
+        }
+    }
+}
+
+/// Configuration of the clock drift detection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockDriftConfig {
+    /// The drift above which a warning is logged, and the slot clock is corrected with the
+    /// `correct` action.
+    pub max_drift: Duration,
+    /// The drift above which requests are rejected with the `pause` action.
+    pub bound: Duration,
+    /// What to do when the drift exceeds its thresholds.
+    pub action: ClockDriftAction,
+}
+
+impl Default for ClockDriftConfig {
+    fn default() -> Self {
+        Self {
+            max_drift: DEFAULT_MAX_CLOCK_DRIFT,
+            bound: DEFAULT_CLOCK_DRIFT_BOUND,
+            action: ClockDriftAction::Warn,
+        }
+    }
+}
+
+/// The estimated drift of the host clock, positive if it is ahead of the chain.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClockDrift {
+    /// The estimated drift, in milliseconds.
+    pub estimate_ms: i64,
+    /// The lower bound of the drift, in milliseconds.
+    pub lower_ms: i64,
+    /// The upper bound of the drift, in milliseconds.
+    pub upper_ms: i64,
+    /// The number of observations the drift is estimated from.
+    pub samples: usize,
+}
+
+/// Estimates the drift of the host clock from the offsets between the local time at
+/// which chain events are observed and their chain timestamp.
+///
+/// Each offset is the drift plus the propagation delay of the event, which is between 0
+/// and the maximum delay. The drift is thus at most the smallest offset, and at least the
+/// largest one minus the maximum delay. It is estimated as the smallest offset, i.e.
+/// assuming the fastest observed event had no delay.
+#[derive(Debug, Clone)]
+pub struct ClockDriftEstimator {
+    /// The latest offsets, in milliseconds.
+    offsets: VecDeque<i64>,
+    /// The maximum propagation delay of an event, in milliseconds.
+    max_delay_ms: i64,
+}
+
+impl ClockDriftEstimator {
+    /// Creates a new estimator for events that propagate within `max_delay`.
+    pub fn new(max_delay: Duration) -> Self {
+        Self { offsets: VecDeque::with_capacity(DRIFT_WINDOW), max_delay_ms: millis(max_delay) }
+    }
+
+    /// Records a chain event with the given timestamp, observed at the given local time.
+    pub fn observe(&mut self, local: Duration, chain: Duration) {
+        if self.offsets.len() == DRIFT_WINDOW {
+            self.offsets.pop_front();
+        }
+        self.offsets.push_back(millis(local) - millis(chain));
+    }
+
+    /// Returns the estimated drift, or `None` if there aren't enough observations yet.
+    pub fn estimate(&self) -> Option<ClockDrift> {
+        if self.offsets.len() < MIN_DRIFT_SAMPLES {
+            return None;
+        }
+
+        let upper_ms = self.offsets.iter().copied().min().unwrap_or_default();
+        let latest = self.offsets.iter().copied().max().unwrap_or_default();
+        // Events delayed beyond the maximum delay would make the bounds cross
+        let lower_ms = (latest - self.max_delay_ms).min(upper_ms);
+
+        Some(ClockDrift { estimate_ms: upper_ms, lower_ms, upper_ms, samples: self.offsets.len() })
+    }
+}
+
+/// What the drift of the host clock requires, as of the latest check.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DriftVerdict {
+    /// Whether the drift exceeds the maximum drift.
+    pub exceeded: bool,
+    /// Whether requests must be rejected.
+    pub pause: bool,
+    /// The correction to apply to the slot clock, in milliseconds.
+    pub correction_ms: i64,
+}
+
+/// The drift of the host clock, as reported in the status.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClockDriftStatus {
+    /// The estimated drift, if there are enough observations.
+    pub drift: Option<ClockDrift>,
+    /// The drift above which a warning is logged, in milliseconds.
+    pub max_drift_ms: u64,
+    /// The drift above which requests are rejected with the `pause` action, in milliseconds.
+    pub bound_ms: u64,
+    /// What to do when the drift exceeds its thresholds.
+    pub action: ClockDriftAction,
+    /// Whether the drift exceeds the maximum drift.
+    pub exceeded: bool,
+    /// Whether requests are rejected because of the drift.
+    pub paused: bool,
+    /// The correction applied to the slot clock, in milliseconds.
+    pub correction_ms: i64,
+}
+
+/// Checks the estimated drift of the host clock against the configured thresholds.
+#[derive(Debug, Clone)]
+pub struct ClockDriftMonitor {
+    config: ClockDriftConfig,
+    estimator: ClockDriftEstimator,
+    verdict: DriftVerdict,
+}
+
+impl ClockDriftMonitor {
+    /// Creates a new monitor for events that propagate within `max_delay`.
+    pub fn new(config: ClockDriftConfig, max_delay: Duration) -> Self {
+        Self { config, estimator: ClockDriftEstimator::new(max_delay), verdict: Default::default() }
+    }
+
+    /// Records a chain event with the given timestamp, observed at the given local time.
+    /// The local time must not include the correction of the slot clock.
+    pub fn observe(&mut self, local: Duration, chain: Duration) {
+        self.estimator.observe(local, chain);
+    }
+
+    /// Returns the verdict of the latest check.
+    pub fn verdict(&self) -> DriftVerdict {
+        self.verdict
+    }
+
+    /// Checks the estimated drift against the thresholds, and returns what it requires.
+    pub fn check(&mut self) -> DriftVerdict {
+        let Some(drift) = self.estimator.estimate() else {
+            return self.verdict;
+        };
+
+        let magnitude = drift.estimate_ms.unsigned_abs();
+        let exceeded = magnitude > self.config.max_drift.as_millis() as u64;
+        let beyond_bound = magnitude > self.config.bound.as_millis() as u64;
+
+        self.verdict = DriftVerdict {
+            exceeded,
+            pause: beyond_bound && self.config.action == ClockDriftAction::Pause,
+            correction_ms: match self.config.action {
+                ClockDriftAction::Correct if exceeded => -drift.estimate_ms,
+                _ => 0,
+            },
+        };
+        self.verdict
+    }
+
+    /// Returns the drift of the host clock, and updates the clock drift gauge.
+    pub fn status(&self) -> ClockDriftStatus {
+        let drift = self.estimator.estimate();
+        if let Some(drift) = drift {
+            metrics::gauge!(telemetry::CLOCK_DRIFT).set(drift.estimate_ms as f64 / 1_000.0);
+        }
+
+        ClockDriftStatus {
+            drift,
+            max_drift_ms: self.config.max_drift.as_millis() as u64,
+            bound_ms: self.config.bound.as_millis() as u64,
+            action: self.config.action,
+            exceeded: self.verdict.exceeded,
+            paused: self.verdict.pause,
+            correction_ms: self.verdict.correction_ms,
+        }
+    }
+}
+
+fn millis(duration: Duration) -> i64 {
+    duration.as_millis() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLOT: Duration = Duration::from_secs(12);
+    const GENESIS: Duration = Duration::from_secs(1_000);
+
+    /// Feeds the heads of `slots` slots to the monitor, observed with the given delays after
+    /// the start of their slot on a host clock ahead of the chain by `drift_ms` (behind if
+    /// negative).
+    fn feed(monitor: &mut ClockDriftMonitor, drift_ms: i64, delays_ms: &[u64]) {
+        for (slot, delay) in delays_ms.iter().enumerate() {
+            let chain = GENESIS + SLOT * slot as u32;
+            let local = (chain + Duration::from_millis(*delay)).as_millis() as i64 + drift_ms;
+            monitor.observe(Duration::from_millis(local as u64), chain);
+        }
+    }
+
+    #[test]
+    fn test_estimate_bounds() {
+        let mut estimator = ClockDriftEstimator::new(Duration::from_secs(4));
+        for slot in 0..3 {
+            estimator.observe(GENESIS + SLOT * slot + Duration::from_millis(300), GENESIS);
+        }
+        assert_eq!(estimator.estimate(), None);
+
+        // A host clock 2s behind, with blocks observed between 200ms and 1.5s after the
+        // start of their slot
+        let mut estimator = ClockDriftEstimator::new(Duration::from_secs(4));
+        for (slot, delay) in [1_500, 200, 800, 1_100].into_iter().enumerate() {
+            let chain = GENESIS + SLOT * slot as u32;
+            estimator.observe(chain + Duration::from_millis(delay) - Duration::from_secs(2), chain);
+        }
+
+        let drift = estimator.estimate().unwrap();
+        assert_eq!((drift.estimate_ms, drift.upper_ms, drift.lower_ms), (-1_800, -1_800, -4_500));
+        assert_eq!(drift.samples, 4);
+    }
+
+    #[test]
+    fn test_pause_at_bound() {
+        let config = ClockDriftConfig {
+            max_drift: Duration::from_secs(1),
+            bound: Duration::from_secs(3),
+            action: ClockDriftAction::Pause,
+        };
+        let mut monitor = ClockDriftMonitor::new(config, Duration::from_secs(4));
+
+        // A healthy host clock
+        feed(&mut monitor, 0, &[400, 250, 600, 300]);
+        assert_eq!(monitor.check(), DriftVerdict::default());
+
+        // The host clock is exactly at the bound: the drift is reported, but requests
+        // are still accepted
+        feed(&mut monitor, 2_750, &[250; DRIFT_WINDOW]);
+        assert_eq!(monitor.check(), DriftVerdict { exceeded: true, ..Default::default() });
+        let status = monitor.status();
+        assert_eq!(status.drift.map(|drift| drift.estimate_ms), Some(3_000));
+        assert!(status.exceeded && !status.paused);
+
+        // Beyond the bound, requests are rejected until the clock is fixed
+        feed(&mut monitor, 2_751, &[250; DRIFT_WINDOW]);
+        assert!(monitor.check().pause);
+        assert!(monitor.status().paused);
+
+        feed(&mut monitor, 0, &[250; DRIFT_WINDOW]);
+        assert_eq!(monitor.check(), DriftVerdict::default());
+    }
+
+    #[test]
+    fn test_correction() {
+        let config = ClockDriftConfig { action: ClockDriftAction::Correct, ..Default::default() };
+        let mut monitor = ClockDriftMonitor::new(config, Duration::from_secs(4));
+
+        // The host clock is 5s behind: it is corrected, and requests are still accepted
+        feed(&mut monitor, -5_000, &[100, 400, 200, 300]);
+        let verdict = monitor.check();
+        assert_eq!(verdict, DriftVerdict { exceeded: true, pause: false, correction_ms: 4_900 });
+        assert_eq!(monitor.status().correction_ms, 4_900);
+    }
+}
@@ -1,20 +1,40 @@
 use alloy::{
-    eips::eip4844::MAX_BLOBS_PER_BLOCK,
-    primitives::{Address, U256},
+    primitives::{Address, B256, U256},
     transports::TransportError,
 };
+use parking_lot::RwLock;
 use reth_primitives::{
     revm_primitives::EnvKzgSettings, BlobTransactionValidationError, PooledTransactionsElement,
+    TxType,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    ops::Deref,
+    sync::Arc,
+    time::{Duration, Instant},
 };
-use std::{collections::HashMap, ops::Deref};
 use thiserror::Error;
 use tracing::{debug, trace};
 
+use super::{
+    basefee::{project_eip1559, BaseFeeStatus, BaseFeeTracker, GasUsage, MarginBounds},
+    blobs::BlobBudget,
+    memory::{QueueMemory, QueueMemoryStatus},
+    reserved::{PendingReservations, Reservation},
+    snapshot::SlotBudget,
+};
+
 use crate::{
     builder::BlockTemplate,
     common::{calculate_max_basefee, max_transaction_cost, validate_transaction},
-    config::Limits,
-    primitives::{AccountState, CommitmentRequest, SignedConstraints, Slot},
+    config::{ChainConfig, Limits, SharedLimits},
+    primitives::{
+        commitment::{MAX_CONTEXT_LEN, MAX_EXCLUSION_TARGETS},
+        AccountState, CommitmentRequest, ExclusionRequest, FullTransaction, InclusionRequest,
+        SignedConstraints, Slot, TransactionExt,
+    },
+    telemetry,
 };
 
 use super::fetcher::StateFetcher;
@@ -22,9 +42,13 @@ use super::fetcher::StateFetcher;
 /// Possible commitment validation errors.
 #[derive(Debug, Error)]
 pub enum ValidationError {
-    /// The transaction fee is too low to cover the maximum base fee.
-    #[error("Transaction fee is too low, need {0} gwei to cover the maximum basefee")]
-    BaseFeeTooLow(u128),
+    /// The transaction fee is too low to cover the base fee projected for the target slot,
+    /// once the margin is applied.
+    #[error(
+        "Transaction fee is too low, need {required} wei per gas to cover the basefee \
+         of {projected} wei projected for the target slot"
+    )]
+    BaseFeeTooLow { required: u128, projected: u128 },
     /// The transaction blob fee is too low to cover the maximum blob base fee.
     #[error("Transaction blob fee is too low, need {0} gwei to cover the maximum blob basefee")]
     BlobBaseFeeTooLow(u128),
@@ -40,6 +64,9 @@ pub enum ValidationError {
     /// The transaction nonce is too high.
     #[error("Transaction nonce too high. Expected {0}, got {1}")]
     NonceTooHigh(u64, u64),
+    /// The transaction nonce is already taken by a committed transaction of the sender.
+    #[error("Transaction nonce {0} is already committed to, cancel its commitment to replace it")]
+    NonceAlreadyCommitted(u64),
     /// The sender account is a smart contract and has code.
     #[error("Account has code")]
     AccountHasCode,
@@ -52,11 +79,19 @@ pub enum ValidationError {
     /// Max priority fee per gas is greater than max fee per gas.
     #[error("Max priority fee per gas is greater than max fee per gas")]
     MaxPriorityFeePerGasTooHigh,
-    /// The sender does not have enough balance to pay for the transaction.
-    #[error("Not enough balance to pay for value + maximum fee")]
-    InsufficientBalance,
-    /// There are too many EIP-4844 transactions in the target block.
-    #[error("Too many EIP-4844 transactions in target block")]
+    /// The priority fee of a transaction is below the minimum of the sidecar.
+    #[error("Transaction priority fee is too low, need at least {0} wei per gas")]
+    PriorityFeeTooLow(u128),
+    /// The sender does not have enough balance to pay for the transaction, once the maximum
+    /// cost of its committed and accepted transactions, and of the earlier transactions of the
+    /// same request, is reserved.
+    #[error(
+        "Not enough balance to pay for value + maximum fee: need {required} wei, {available} \
+         available after {reserved} reserved by earlier commitments and request transactions"
+    )]
+    InsufficientBalance { required: U256, available: U256, reserved: U256 },
+    /// The request has more blobs than can be committed to in a single block.
+    #[error("Too many EIP-4844 blobs for the target block")]
     Eip4844Limit,
     /// The maximum commitments have been reached for the slot.
     #[error("Already requested a preconfirmation for slot {0}. Slot must be >= {0}")]
@@ -67,6 +102,15 @@ pub enum ValidationError {
     /// The maximum committed gas has been reached for the slot.
     #[error("Max committed gas reached for slot {0}: {1}")]
     MaxCommittedGasReachedForSlot(u64, u64),
+    /// The transactions of the request exceed the remaining byte budget of the slot.
+    #[error("Byte budget exceeded for slot {slot}: requested {requested}, remaining {remaining}")]
+    ByteBudgetExceeded { slot: u64, requested: usize, remaining: usize },
+    /// The blobs of the request exceed the remaining blob budget of the slot.
+    #[error("Blob budget exceeded for slot {slot}: requested {requested}, remaining {remaining}")]
+    BlobBudgetExceeded { slot: u64, requested: usize, remaining: usize },
+    /// The transactions of the request don't fit in the memory left for accepted requests.
+    #[error("Insufficient queue memory: requested {requested} bytes, {available} available")]
+    InsufficientQueueMemory { requested: usize, available: usize },
     /// The signature is invalid.
     #[error("Invalid signature")]
     Signature(#[from] crate::primitives::SignatureError),
@@ -76,6 +120,21 @@ pub enum ValidationError {
     /// The transaction chain ID does not match the expected chain ID.
     #[error("Chain ID mismatch")]
     ChainIdMismatch,
+    /// The type of the transaction isn't valid yet at the fork of the target slot.
+    #[error("Transaction type {0} is not active at the target slot")]
+    TransactionTypeNotActive(u8),
+    /// The context of the request is too long or has non-printable characters.
+    #[error("Invalid request context: at most {0} printable bytes are allowed")]
+    InvalidContext(usize),
+    /// A transaction of an atomic request is invalid, which rejects the whole request.
+    #[error("Invalid transaction {index} of the bundle: {source}")]
+    InvalidBundleTransaction { index: usize, source: Box<ValidationError> },
+    /// The exclusion request excludes no sender nor transaction, or too many.
+    #[error("Invalid exclusion: between 1 and {0} senders and transactions can be excluded")]
+    InvalidExclusion(usize),
+    /// The transaction is both committed to for inclusion and excluded at the slot.
+    #[error("Transaction {tx_hash} conflicts with a commitment for slot {slot}")]
+    ExclusionConflict { slot: u64, tx_hash: B256 },
     /// NOTE: this should not be exposed to the user.
     #[error("Internal error: {0}")]
     Internal(String),
@@ -102,10 +161,16 @@ impl ValidationError {
 pub struct ExecutionState<C> {
     /// The latest block number.
     block_number: u64,
+    /// The timestamp of the latest block in seconds, 0 until the first state update.
+    block_timestamp: u64,
     /// The latest slot number.
     slot: u64,
+    /// The latest slot that started. The slots between the head and it passed without a block.
+    current_slot: u64,
     /// The basefee at the head block.
     basefee: u128,
+    /// The gas used by the head block, if known.
+    head_gas: Option<GasUsage>,
     /// The blob basefee at the head block.
     blob_basefee: u128,
     /// The cached account states. This should never be read directly.
@@ -118,14 +183,38 @@ pub struct ExecutionState<C> {
     block_templates: HashMap<Slot, BlockTemplate>,
     /// The chain ID of the chain (constant).
     chain_id: u64,
-    /// The limits set for the sidecar.
-    limits: Limits,
+    /// The limits set for the sidecar, which can be adjusted at runtime.
+    limits: SharedLimits,
     /// The KZG settings for validating blobs.
     kzg_settings: EnvKzgSettings,
     /// The state fetcher client.
     client: C,
     /// Other values used for validation
     validation_params: ValidationParams,
+    /// Tracks the accuracy of the base fee projections and the margin applied to them.
+    basefee_tracker: BaseFeeTracker,
+    /// The configuration of the chain, used to look up the parameters of the active fork.
+    chain: ChainConfig,
+    /// The blobs reserved by accepted requests that aren't in a block template yet.
+    blobs: BlobBudget,
+    /// The nonces and the balance taken by accepted requests that aren't in a block
+    /// template yet.
+    reserved: PendingReservations,
+    /// The memory held by the transactions of accepted requests, until their slot expires.
+    queued: QueueMemory,
+    /// The time spent fetching account states during the current validation.
+    lookup_time: Duration,
+    /// The timings of the last validation, until they are taken.
+    validation_timings: Option<ValidationTimings>,
+}
+
+/// The time spent validating a request against the execution state.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ValidationTimings {
+    /// The time spent fetching account states from the execution client.
+    pub lookups: Duration,
+    /// The total time of the validation, including the lookups.
+    pub total: Duration,
 }
 
 /// Other values used for validation.
@@ -162,23 +251,135 @@ impl<C: StateFetcher> ExecutionState<C> {
             blob_basefee,
             block_number,
             chain_id,
-            limits,
+            limits: Arc::new(RwLock::new(limits)),
             client,
+            block_timestamp: 0,
             slot: 0,
+            current_slot: 0,
+            head_gas: None,
             account_states: HashMap::new(),
             block_templates: HashMap::new(),
             // Load the default KZG settings
             kzg_settings: EnvKzgSettings::default(),
             // TODO: add a way to configure these values from CLI
             validation_params: ValidationParams::default(),
+            basefee_tracker: BaseFeeTracker::default(),
+            chain: ChainConfig::default(),
+            blobs: BlobBudget::new(limits.max_committed_blobs_percent),
+            reserved: PendingReservations::default(),
+            queued: QueueMemory::new(limits.max_queued_bytes.get()),
+            lookup_time: Duration::ZERO,
+            validation_timings: None,
         })
     }
 
+    /// Sets the bounds of the adaptive base fee projection margin.
+    /// If `None`, the margin is fixed to the maximum EIP-1559 base fee.
+    pub fn with_basefee_margin(self, bounds: Option<MarginBounds>) -> Self {
+        Self { basefee_tracker: BaseFeeTracker::new(bounds), ..self }
+    }
+
+    /// Sets the chain configuration, from which the fork parameters are derived.
+    pub fn with_chain(self, chain: ChainConfig) -> Self {
+        Self { chain, ..self }
+    }
+
+    /// Returns a handle to the limits of the state, through which they can be adjusted.
+    pub fn limits(&self) -> SharedLimits {
+        self.limits.clone()
+    }
+
     /// Returns the current base fee in gwei
     pub fn basefee(&self) -> u128 {
         self.basefee
     }
 
+    /// Returns the latest block number.
+    pub fn block_number(&self) -> u64 {
+        self.block_number
+    }
+
+    /// Returns the timestamp of the latest block in seconds, if a state update was applied.
+    pub fn block_timestamp(&self) -> Option<u64> {
+        (self.block_timestamp > 0).then_some(self.block_timestamp)
+    }
+
+    /// Returns the commitment budgets of the slots with a block template or reserved blobs,
+    /// ordered by slot.
+    pub fn slot_budgets(&self) -> Vec<SlotBudget> {
+        let mut slots =
+            self.block_templates.keys().chain(self.blobs.slots()).copied().collect::<Vec<_>>();
+        slots.sort_unstable();
+        slots.dedup();
+
+        let limits = *self.limits.read();
+        slots
+            .into_iter()
+            .map(|slot| {
+                let template = self.block_templates.get(&slot);
+                let max_blobs = self.chain.fork_at(slot).max_blobs_per_block();
+                SlotBudget {
+                    slot,
+                    commitments: template.map(|t| t.transactions_len()).unwrap_or(0),
+                    max_commitments: limits.max_commitments_per_slot.get(),
+                    committed_gas: template.map(|t| t.committed_gas()).unwrap_or(0),
+                    max_committed_gas: limits.max_committed_gas_per_slot.get(),
+                    committed_bytes: template.map(|t| t.committed_bytes()).unwrap_or(0),
+                    max_committed_bytes: limits.max_committed_bytes_per_slot.get(),
+                    committed_blobs: self.committed_blobs(slot),
+                    max_committed_blobs: self.blobs.ceiling(max_blobs),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the blobs committed to in the given slot, including the reserved ones.
+    fn committed_blobs(&self, slot: Slot) -> usize {
+        let template_blobs = self.block_templates.get(&slot).map(|t| t.blob_count()).unwrap_or(0);
+        template_blobs + self.blobs.reserved(slot)
+    }
+
+    /// Returns the base fee of the block at the target slot projected under EIP-1559, before
+    /// the margin, or `None` if an overflow would occur.
+    ///
+    /// It changes after the head block, by its gas usage if known, then after each block that
+    /// can be proposed until the target slot, assumed of the configured fullness. The slots
+    /// between the head and the current one passed without a block and don't count, so that
+    /// targeting the next slot only takes the change after the head.
+    pub fn projected_basefee(&self, target_slot: Slot) -> Option<u128> {
+        let first_slot = (self.slot + 1).max(self.current_slot);
+        let blocks = target_slot.saturating_sub(first_slot);
+        let fullness = self.limits.read().basefee_block_fullness;
+        project_eip1559(self.basefee, self.head_gas, blocks, fullness)
+    }
+
+    /// Returns the minimum priority fee per gas the transactions of a request for the target
+    /// slot must tip.
+    ///
+    /// With a dynamic floor, the tip must also cover the rise of the base fee projected until
+    /// the target slot, plus the configured margin: transactions whose tip would be eaten by
+    /// the base fee by then are rejected up front.
+    pub fn priority_fee_floor(&self, target_slot: Slot) -> u128 {
+        let limits = *self.limits.read();
+        let Some(margin) = limits.priority_fee_margin else {
+            return limits.min_priority_fee;
+        };
+
+        let projected = self.projected_basefee(target_slot).unwrap_or(u128::MAX);
+        let rise = projected.saturating_sub(self.basefee);
+        limits.min_priority_fee.max(rise.saturating_add(margin))
+    }
+
+    /// Returns the status of the base fee projections.
+    pub fn basefee_status(&self) -> BaseFeeStatus {
+        self.basefee_tracker.status()
+    }
+
+    /// Returns the status of the memory held by the transactions of accepted requests.
+    pub fn queue_memory_status(&self) -> QueueMemoryStatus {
+        self.queued.status()
+    }
+
     /// Validates the commitment request against state (historical + intermediate).
     ///
     /// NOTE: This function only simulates against execution state, it does not consider
@@ -194,24 +395,63 @@ impl<C: StateFetcher> ExecutionState<C> {
         &mut self,
         request: &mut CommitmentRequest,
     ) -> Result<(), ValidationError> {
-        let CommitmentRequest::Inclusion(req) = request;
+        let start = Instant::now();
+        self.lookup_time = Duration::ZERO;
+
+        let result = match request {
+            CommitmentRequest::Inclusion(req) => self.check_request(req).await,
+            CommitmentRequest::Exclusion(req) => self.check_exclusion(req),
+        };
+        self.validation_timings =
+            Some(ValidationTimings { lookups: self.lookup_time, total: start.elapsed() });
+
+        result
+    }
 
-        let signer = req.signer().expect("Set signer");
+    /// Takes the timings of the last validation, if one ran since they were last taken.
+    pub fn take_validation_timings(&mut self) -> Option<ValidationTimings> {
+        self.validation_timings.take()
+    }
+
+    /// Runs the checks of [`Self::validate_request`] for an inclusion request, timing the
+    /// account state lookups.
+    async fn check_request(&mut self, req: &mut InclusionRequest) -> Result<(), ValidationError> {
         req.recover_signers()?;
 
         let target_slot = req.slot;
+        let limits = *self.limits.read();
+
+        // The transactions can't be committed to if they are excluded from the block
+        if let Some(template) = self.block_templates.get(&target_slot) {
+            let excluded = |tx: &&FullTransaction| template.exclusions().any(|e| e.excludes(tx));
+            if let Some(tx) = req.txs.iter().find(excluded) {
+                let tx_hash = *tx.hash();
+                return Err(ValidationError::ExclusionConflict { slot: target_slot, tx_hash });
+            }
+        }
 
         // Validate the chain ID
         if !req.validate_chain_id(self.chain_id) {
             return Err(ValidationError::ChainIdMismatch);
         }
 
+        // The set code transactions are only valid from Electra on
+        let set_code = req.txs.iter().any(|tx| tx.tx_type() == TxType::Eip7702);
+        if set_code && !self.chain.fork_at(target_slot).allows_set_code_transactions() {
+            return Err(ValidationError::TransactionTypeNotActive(TxType::Eip7702 as u8));
+        }
+
+        // Validate the context set by the client
+        if !req.validate_context() {
+            return Err(ValidationError::InvalidContext(MAX_CONTEXT_LEN));
+        }
+
         // Check if there is room for more commitments
         if let Some(template) = self.get_block_template(target_slot) {
-            if template.transactions_len() >= self.limits.max_commitments_per_slot.get() {
+            if template.transactions_len() >= limits.max_commitments_per_slot.get() {
                 return Err(ValidationError::MaxCommitmentsReachedForSlot(
                     self.slot,
-                    self.limits.max_commitments_per_slot.get(),
+                    limits.max_commitments_per_slot.get(),
                 ));
             }
         }
@@ -220,14 +460,59 @@ impl<C: StateFetcher> ExecutionState<C> {
         let template_committed_gas =
             self.get_block_template(target_slot).map(|t| t.committed_gas()).unwrap_or(0);
 
-        if template_committed_gas + req.gas_limit() >= self.limits.max_committed_gas_per_slot.get()
-        {
+        if template_committed_gas + req.gas_limit() >= limits.max_committed_gas_per_slot.get() {
             return Err(ValidationError::MaxCommittedGasReachedForSlot(
                 self.slot,
-                self.limits.max_committed_gas_per_slot.get(),
+                limits.max_committed_gas_per_slot.get(),
             ));
         }
 
+        // Check if the transactions of the request fit in the remaining byte budget of the slot.
+        // Gas under-prices calldata, which weighs on the propagation of the block.
+        let max_committed_bytes = limits.max_committed_bytes_per_slot.get();
+        let template_committed_bytes =
+            self.get_block_template(target_slot).map(|t| t.committed_bytes()).unwrap_or(0);
+        let block_bytes = req.block_bytes();
+        let remaining = max_committed_bytes.saturating_sub(template_committed_bytes);
+        if block_bytes > remaining {
+            return Err(ValidationError::ByteBudgetExceeded {
+                slot: target_slot,
+                requested: block_bytes,
+                remaining,
+            });
+        }
+
+        // Check if the blobs of the request fit in the remaining blob budget of the slot
+        let request_blobs = req.blob_count();
+        if request_blobs > 0 {
+            let max_blobs = self.chain.fork_at(target_slot).max_blobs_per_block();
+            if request_blobs > self.blobs.ceiling(max_blobs) {
+                return Err(ValidationError::Eip4844Limit);
+            }
+
+            let template_blobs =
+                self.block_templates.get(&target_slot).map(|t| t.blob_count()).unwrap_or(0);
+            let remaining = self.blobs.remaining(target_slot, template_blobs, max_blobs);
+            if request_blobs > remaining {
+                return Err(ValidationError::BlobBudgetExceeded {
+                    slot: target_slot,
+                    requested: request_blobs,
+                    remaining,
+                });
+            }
+        }
+
+        // Check if the transactions of the request fit in the memory left for accepted requests.
+        // Accepted requests are never evicted for new ones, so earlier slots keep their place.
+        let request_bytes = req.encoded_len();
+        let available = self.queued.available();
+        if request_bytes > available {
+            return Err(ValidationError::InsufficientQueueMemory {
+                requested: request_bytes,
+                available,
+            });
+        }
+
         // Check if the transaction size exceeds the maximum
         if !req.validate_tx_size_limit(self.validation_params.max_tx_input_bytes) {
             return Err(ValidationError::TransactionSizeTooHigh);
@@ -249,18 +534,33 @@ impl<C: StateFetcher> ExecutionState<C> {
             return Err(ValidationError::MaxPriorityFeePerGasTooHigh);
         }
 
-        // Check if the max_fee_per_gas would cover the maximum possible basefee.
-        let slot_diff = target_slot.saturating_sub(self.slot);
+        // Check if the transactions tip at least the priority fee floor of the target slot
+        let priority_fee_floor = self.priority_fee_floor(target_slot);
+        if req.min_priority_fee() < priority_fee_floor {
+            return Err(ValidationError::PriorityFeeTooLow(priority_fee_floor));
+        }
 
-        // Calculate the max possible basefee given the slot diff
-        let max_basefee = calculate_max_basefee(self.basefee, slot_diff)
+        // Check if the max_fee_per_gas would cover the basefee projected for the target slot:
+        // the basefee of the target block under EIP-1559, with the margin applied
+        let projected_basefee =
+            self.projected_basefee(target_slot).ok_or(ValidationError::MaxBaseFeeCalcOverflow)?;
+        let max_basefee = self
+            .basefee_tracker
+            .apply_margin(projected_basefee)
             .ok_or(ValidationError::MaxBaseFeeCalcOverflow)?;
 
-        debug!(%slot_diff, basefee = self.basefee, %max_basefee, "Validating basefee");
+        debug!(
+            basefee = self.basefee,
+            %projected_basefee,
+            %max_basefee,
+            margin = self.basefee_tracker.margin(),
+            "Validating basefee"
+        );
 
         // Validate the base fee
         if !req.validate_basefee(max_basefee) {
-            return Err(ValidationError::BaseFeeTooLow(max_basefee));
+            let projected = projected_basefee;
+            return Err(ValidationError::BaseFeeTooLow { required: max_basefee, projected });
         }
 
         if target_slot < self.slot {
@@ -268,6 +568,9 @@ impl<C: StateFetcher> ExecutionState<C> {
             return Err(ValidationError::SlotTooLow(self.slot));
         }
 
+        // The blob basefee of the transactions is projected over the slots up to the target
+        let slot_diff = target_slot.saturating_sub(self.slot);
+
         // Validate each transaction in the request against the account state,
         // keeping track of the nonce and balance diffs, including:
         // - any existing state in the account trie
@@ -277,123 +580,220 @@ impl<C: StateFetcher> ExecutionState<C> {
         // NOTE: it's also possible for a request to contain multiple transactions
         // from different senders, in this case each sender will have its own nonce
         // and balance diffs that will be applied to the account state.
-        let mut bundle_nonce_diff_map = HashMap::new();
-        let mut bundle_balance_diff_map = HashMap::new();
-        for tx in req.txs.iter() {
-            let sender = tx.sender().expect("Recovered sender");
-
-            // From previous preconfirmations requests retrieve
-            // - the nonce difference from the account state.
-            // - the balance difference from the account state.
-            // - the highest slot number for which the user has requested a preconfirmation.
-            //
-            // If the templates do not exist, or this is the first request for this sender,
-            // its diffs will be zero.
-            let (nonce_diff, balance_diff, highest_slot_for_account) =
-                self.block_templates.iter().fold(
-                    (0, U256::ZERO, 0),
-                    |(nonce_diff_acc, balance_diff_acc, highest_slot), (slot, block_template)| {
-                        let (nonce_diff, balance_diff, slot) = block_template
-                            .get_diff(&sender)
-                            .map(|(nonce, balance)| (nonce, balance, *slot))
-                            .unwrap_or((0, U256::ZERO, 0));
-
-                        (
-                            nonce_diff_acc + nonce_diff,
-                            balance_diff_acc.saturating_add(balance_diff),
-                            u64::max(highest_slot, slot),
-                        )
-                    },
-                );
-
-            if target_slot < highest_slot_for_account {
-                debug!(%target_slot, %highest_slot_for_account, "There is a request for a higher slot");
-                return Err(ValidationError::SlotTooLow(highest_slot_for_account));
-            }
+        let mut bundle_diffs = HashMap::new();
+        for (index, tx) in req.txs.iter().enumerate() {
+            let checked =
+                self.check_transaction(tx, target_slot, slot_diff, &mut bundle_diffs).await;
+
+            // The transactions of an atomic request are committed to in order or not at all,
+            // so the offending one is named
+            checked.map_err(|err| match req.atomic {
+                true => ValidationError::InvalidBundleTransaction { index, source: Box::new(err) },
+                false => err,
+            })?;
+        }
 
-            trace!(?signer, nonce_diff, %balance_diff, "Applying diffs to account state");
-
-            let account_state = match self.account_state(&sender).copied() {
-                Some(account) => account,
-                None => {
-                    // Fetch the account state from the client if it does not exist
-                    let account = match self.client.get_account_state(&sender, None).await {
-                        Ok(account) => account,
-                        Err(err) => {
-                            return Err(ValidationError::Internal(format!(
-                                "Error fetching account state: {:?}",
-                                err
-                            )))
-                        }
-                    };
-
-                    self.account_states.insert(sender, account);
-                    account
-                }
-            };
+        // Track the accuracy of the projection once the target block is known
+        self.basefee_tracker.record_projection(target_slot, projected_basefee);
 
-            debug!(?account_state, ?nonce_diff, ?balance_diff, "Validating transaction");
+        // Hold the blobs, the nonces and the cost of the request until it is committed or
+        // cancelled, and its transactions until it is cancelled or its slot expires
+        let digest = req.digest();
+        self.blobs.reserve(target_slot, digest, request_blobs);
+        self.reserved.reserve(target_slot, digest, bundle_diffs);
+        self.queued.hold(target_slot, digest, request_bytes);
+        self.report_queue_memory();
 
-            let sender_nonce_diff = bundle_nonce_diff_map.entry(sender).or_insert(0);
-            let sender_balance_diff = bundle_balance_diff_map.entry(sender).or_insert(U256::ZERO);
+        Ok(())
+    }
 
-            // Apply the diffs to this account according to the info fetched from the templates
-            // and the current bundle diffs for this sender.
-            let account_state_with_diffs = AccountState {
-                transaction_count: account_state
-                    .transaction_count
-                    .saturating_add(nonce_diff)
-                    .saturating_add(*sender_nonce_diff),
+    /// Runs the checks of [`Self::validate_request`] for an exclusion request.
+    fn check_exclusion(&self, req: &ExclusionRequest) -> Result<(), ValidationError> {
+        if !req.validate_targets() {
+            return Err(ValidationError::InvalidExclusion(MAX_EXCLUSION_TARGETS));
+        }
 
-                balance: account_state
-                    .balance
-                    .saturating_sub(balance_diff)
-                    .saturating_sub(*sender_balance_diff),
+        if req.slot < self.slot {
+            debug!(target_slot = req.slot, %self.slot, "Target slot lower than current slot");
+            return Err(ValidationError::SlotTooLow(self.slot));
+        }
 
-                has_code: account_state.has_code,
-            };
+        // The transactions already committed to for inclusion at the slot can't be excluded
+        if let Some(template) = self.block_templates.get(&req.slot) {
+            if let Some(tx) = template.transactions().iter().find(|tx| req.excludes(tx)) {
+                let tx_hash = *tx.hash();
+                return Err(ValidationError::ExclusionConflict { slot: req.slot, tx_hash });
+            }
+        }
 
-            // Validate the transaction against the account state with existing diffs
-            validate_transaction(&account_state_with_diffs, tx)?;
+        Ok(())
+    }
 
-            // Check EIP-4844-specific limits
-            if let Some(transaction) = tx.as_eip4844() {
-                if let Some(template) = self.block_templates.get(&target_slot) {
-                    if template.blob_count() >= MAX_BLOBS_PER_BLOCK {
-                        return Err(ValidationError::Eip4844Limit);
-                    }
-                }
+    /// Validates a transaction of a request against the account state of its sender, with
+    /// the diffs of the earlier transactions of the same request applied.
+    async fn check_transaction(
+        &mut self,
+        tx: &FullTransaction,
+        target_slot: Slot,
+        slot_diff: u64,
+        bundle_diffs: &mut HashMap<Address, Reservation>,
+    ) -> Result<(), ValidationError> {
+        let sender = tx.sender().expect("Recovered sender");
 
-                let PooledTransactionsElement::BlobTransaction(ref blob_transaction) = tx.deref()
-                else {
-                    unreachable!("EIP-4844 transaction should be a blob transaction")
+        // From previous preconfirmations requests retrieve
+        // - the nonce difference from the account state.
+        // - the balance difference from the account state.
+        // - the highest slot number for which the user has requested a preconfirmation.
+        //
+        // If the templates do not exist, or this is the first request for this sender,
+        // its diffs will be zero.
+        let (nonce_diff, balance_diff, highest_slot_for_account) =
+            self.block_templates.iter().fold(
+                (0, U256::ZERO, 0),
+                |(nonce_diff_acc, balance_diff_acc, highest_slot), (slot, block_template)| {
+                    let (nonce_diff, balance_diff, slot) = block_template
+                        .get_diff(&sender)
+                        .map(|(nonce, balance)| (nonce, balance, *slot))
+                        .unwrap_or((0, U256::ZERO, 0));
+
+                    (
+                        nonce_diff_acc + nonce_diff,
+                        balance_diff_acc.saturating_add(balance_diff),
+                        u64::max(highest_slot, slot),
+                    )
+                },
+            );
+
+        // The accepted requests that aren't in a template yet are ordered by slot as well
+        let pending = self.reserved.reserved(&sender);
+        let highest_slot_for_account =
+            highest_slot_for_account.max(self.reserved.highest_slot(&sender).unwrap_or(0));
+
+        if target_slot < highest_slot_for_account {
+            debug!(%target_slot, %highest_slot_for_account, "There is a request for a higher slot");
+            return Err(ValidationError::SlotTooLow(highest_slot_for_account));
+        }
+
+        trace!(?sender, nonce_diff, %balance_diff, "Applying diffs to account state");
+
+        let account_state = match self.account_state(&sender).copied() {
+            Some(account) => account,
+            None => {
+                // Fetch the account state from the client if it does not exist
+                let lookup_start = Instant::now();
+                let fetched = self.client.get_account_state(&sender, None).await;
+                self.lookup_time += lookup_start.elapsed();
+
+                let account = match fetched {
+                    Ok(account) => account,
+                    Err(err) => {
+                        return Err(ValidationError::Internal(format!(
+                            "Error fetching account state: {:?}",
+                            err
+                        )))
+                    }
                 };
 
-                // Calculate max possible increase in blob basefee
-                let max_blob_basefee = calculate_max_basefee(self.blob_basefee, slot_diff)
-                    .ok_or(ValidationError::MaxBaseFeeCalcOverflow)?;
+                self.account_states.insert(sender, account);
+                account
+            }
+        };
 
-                debug!(%max_blob_basefee, blob_basefee = blob_transaction.transaction.max_fee_per_blob_gas, "Validating blob basefee");
-                if blob_transaction.transaction.max_fee_per_blob_gas < max_blob_basefee {
-                    return Err(ValidationError::BlobBaseFeeTooLow(max_blob_basefee));
-                }
+        // The nonces taken by the committed transactions of the templates, which follow the
+        // account nonce, by the accepted requests that aren't in a template yet, and by the
+        // earlier transactions of this request. The cost of the latter two is taken as well.
+        let bundle = bundle_diffs.entry(sender).or_default();
+        let account_nonce = account_state.transaction_count;
+        let mut taken = pending.nonces;
+        taken.extend(account_nonce..account_nonce.saturating_add(nonce_diff));
+        taken.extend(&bundle.nonces);
+        let reserved = balance_diff.saturating_add(pending.cost).saturating_add(bundle.cost);
+
+        // A taken nonce would replace a committed transaction, which must be cancelled first
+        if tx.nonce() >= account_nonce && taken.contains(&tx.nonce()) {
+            return Err(ValidationError::NonceAlreadyCommitted(tx.nonce()));
+        }
+
+        // The next nonce to commit to is the lowest one left free, so that the committed
+        // nonces of the sender stay contiguous
+        let next_nonce =
+            (account_nonce..).find(|nonce| !taken.contains(nonce)).expect("Finite taken nonces");
+        debug!(?account_state, %next_nonce, %reserved, "Validating transaction");
+
+        // Apply the diffs to this account according to the info fetched from the templates
+        // and the current bundle diffs for this sender.
+        let account_state_with_diffs = AccountState {
+            transaction_count: next_nonce,
+            balance: account_state.balance.saturating_sub(reserved),
+            has_code: account_state.has_code,
+        };
+
+        // Validate the transaction against the account state with existing diffs
+        validate_transaction(&account_state_with_diffs, tx, reserved)?;
 
-                // Validate blob against KZG settings
-                transaction.validate_blob(&blob_transaction.sidecar, self.kzg_settings.get())?;
+        // Check EIP-4844-specific limits
+        if let Some(transaction) = tx.as_eip4844() {
+            let PooledTransactionsElement::BlobTransaction(ref blob_transaction) = tx.deref()
+            else {
+                unreachable!("EIP-4844 transaction should be a blob transaction")
+            };
+
+            // Calculate max possible increase in blob basefee
+            let max_blob_basefee = calculate_max_basefee(self.blob_basefee, slot_diff)
+                .ok_or(ValidationError::MaxBaseFeeCalcOverflow)?;
+
+            debug!(%max_blob_basefee, blob_basefee = blob_transaction.transaction.max_fee_per_blob_gas, "Validating blob basefee");
+            if blob_transaction.transaction.max_fee_per_blob_gas < max_blob_basefee {
+                return Err(ValidationError::BlobBaseFeeTooLow(max_blob_basefee));
             }
 
-            // Increase the bundle nonce and balance diffs for this sender for the next iteration
-            *sender_nonce_diff += 1;
-            *sender_balance_diff += max_transaction_cost(tx);
+            // Validate blob against KZG settings
+            transaction.validate_blob(&blob_transaction.sidecar, self.kzg_settings.get())?;
         }
 
+        // Add the nonce and the cost of the transaction to the bundle diffs of this sender for
+        // the next iteration
+        bundle.add(tx.nonce(), max_transaction_cost(tx));
+
         Ok(())
     }
 
+    /// Releases the blobs, the nonces and the cost reserved by an accepted request. This must
+    /// be called when the request is committed, as they are then accounted for by the block
+    /// template, or when it is cancelled.
+    pub fn release_reserved(&mut self, slot: Slot, digest: B256) {
+        let released = self.blobs.release(slot, digest);
+        if released > 0 {
+            trace!(%slot, %digest, released, "Released reserved blobs");
+        }
+        let released = self.reserved.release(slot, digest);
+        if released > 0 {
+            trace!(%slot, %digest, released, "Released reserved nonces and cost");
+        }
+    }
+
+    /// Releases the memory held by the transactions of an accepted request. This must be
+    /// called when the request is cancelled, including after its blobs were released.
+    pub fn release_queued(&mut self, slot: Slot, digest: B256) {
+        let released = self.queued.release(slot, digest);
+        if released > 0 {
+            trace!(%slot, %digest, released, "Released queued request memory");
+            self.report_queue_memory();
+        }
+    }
+
+    /// Updates the gauge of the memory held by the transactions of accepted requests.
+    fn report_queue_memory(&self) {
+        metrics::gauge!(telemetry::QUEUED_REQUEST_BYTES).set(self.queued.used() as f64);
+    }
+
     /// Commits the transaction to the target block. Initializes a new block template
     /// if one does not exist for said block number.
     /// TODO: remove `pub` modifier once `try_commit` is fully implemented.
     pub fn add_constraint(&mut self, target_slot: u64, signed_constraints: SignedConstraints) {
+        // The template accounts for the blobs, nonces and cost of the request from now on
+        self.release_reserved(target_slot, signed_constraints.message.request_digest());
+
         if let Some(template) = self.block_templates.get_mut(&target_slot) {
             template.add_constraints(signed_constraints);
         } else {
@@ -412,6 +812,7 @@ impl<C: StateFetcher> ExecutionState<C> {
         slot: u64,
     ) -> Result<(), TransportError> {
         self.slot = slot;
+        self.current_slot = self.current_slot.max(slot);
 
         let accounts = self.account_states.keys().collect::<Vec<_>>();
         let update = self.client.get_state_update(accounts, block_number).await?;
@@ -419,8 +820,20 @@ impl<C: StateFetcher> ExecutionState<C> {
 
         self.apply_state_update(update);
 
-        // Remove any block templates that are no longer valid
+        // Compare the base fee of the new head with the projection made for its slot
+        if let Some(error) = self.basefee_tracker.observe(slot, self.basefee) {
+            let margin = self.basefee_tracker.margin();
+            debug!(%slot, %error, %margin, "Observed basefee projection error");
+        }
+
+        // Remove any block templates, blob and sender reservations and queued requests that
+        // are no longer valid
         self.remove_block_template(slot);
+        self.blobs.expire(slot);
+        self.reserved.expire(slot);
+        if self.queued.expire(slot) > 0 {
+            self.report_queue_memory();
+        }
 
         Ok(())
     }
@@ -428,7 +841,9 @@ impl<C: StateFetcher> ExecutionState<C> {
     fn apply_state_update(&mut self, update: StateUpdate) {
         // Update head and basefee
         self.block_number = update.block_number;
+        self.block_timestamp = update.block_timestamp;
         self.basefee = update.min_basefee;
+        self.head_gas = (update.block_gas.limit > 0).then_some(update.block_gas);
 
         // `extend` will overwrite existing values. This is what we want.
         self.account_states.extend(update.account_states);
@@ -469,6 +884,31 @@ impl<C: StateFetcher> ExecutionState<C> {
         self.block_templates.get(&slot)
     }
 
+    /// Returns the block templates by slot.
+    pub fn block_templates(&self) -> &HashMap<Slot, BlockTemplate> {
+        &self.block_templates
+    }
+
+    /// Returns the block templates by slot, for operations on individual commitments.
+    pub fn block_templates_mut(&mut self) -> &mut HashMap<Slot, BlockTemplate> {
+        &mut self.block_templates
+    }
+
+    /// Removes the block templates, blob and sender reservations and queued requests of the
+    /// slots before the given one, which have passed. The templates of the slots whose head
+    /// wasn't received would otherwise hold their committed budget forever. Those slots are
+    /// left out of the base fee projections, since they can no longer have a block.
+    pub fn expire_before(&mut self, slot: Slot) {
+        self.current_slot = self.current_slot.max(slot);
+        let Some(last) = slot.checked_sub(1) else { return };
+        self.block_templates.retain(|template_slot, _| *template_slot > last);
+        self.blobs.expire(last);
+        self.reserved.expire(last);
+        if self.queued.expire(last) > 0 {
+            self.report_queue_memory();
+        }
+    }
+
     /// Gets the block template for the given slot number and removes it from the cache.
     /// This should be called when we need to propose a block for the given slot,
     /// or when a new head comes in which makes an older block template useless.
@@ -477,12 +917,20 @@ impl<C: StateFetcher> ExecutionState<C> {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StateUpdate {
     pub account_states: HashMap<Address, AccountState>,
     pub min_basefee: u128,
     pub min_blob_basefee: u128,
     pub block_number: u64,
+    /// The timestamp of the block in seconds. Missing from replay logs recorded before it
+    /// was added.
+    #[serde(default)]
+    pub block_timestamp: u64,
+    /// The gas used by the block, out of its gas limit. Missing from replay logs recorded
+    /// before it was added, in which case it is unknown and assumed.
+    #[serde(default)]
+    pub block_gas: GasUsage,
 }
 
 #[cfg(test)]
@@ -502,9 +950,14 @@ mod tests {
 
     use crate::{
         crypto::{bls::Signer, SignableBLS, SignerBLS},
-        primitives::{ConstraintsMessage, SignedConstraints},
+        primitives::{
+            constraint::ConstraintDigest, ConstraintsMessage, Exclusion, SignedConstraints,
+        },
         state::fetcher,
-        test_util::{create_signed_commitment_request, default_test_transaction, launch_anvil},
+        test_util::{
+            create_signed_atomic_request, create_signed_commitment_request,
+            default_blob_transaction, default_test_transaction, launch_anvil, test_blob_sidecar,
+        },
     };
 
     use super::*;
@@ -596,14 +1049,14 @@ mod tests {
             BlockTemplate { state_diff: StateDiff { diffs }, signed_constraints_list: vec![] },
         );
 
-        // Create a transaction with a nonce that is too low
+        // Create a transaction with a nonce that is already committed to
         let tx = default_test_transaction(*sender, Some(0));
 
         let mut request = create_signed_commitment_request(&[tx], sender_pk, 10).await?;
 
         assert!(matches!(
             state.validate_request(&mut request).await,
-            Err(ValidationError::NonceTooLow(1, 0))
+            Err(ValidationError::NonceAlreadyCommitted(0))
         ));
 
         assert!(state.account_states.get(sender).unwrap().transaction_count == 0);
@@ -621,6 +1074,74 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_nonce_continuity() -> eyre::Result<()> {
+        let _ = tracing_subscriber::fmt::try_init();
+
+        let anvil = launch_anvil();
+        let client = StateClient::new(anvil.endpoint_url());
+
+        let mut state = ExecutionState::new(client.clone(), Limits::default()).await?;
+        let slot = client.get_head().await?;
+        state.update_head(None, slot).await?;
+
+        let (sender, sender_pk) = (anvil.addresses()[0], &anvil.keys()[0]);
+        let request_at = |nonce, slot| async move {
+            let tx = default_test_transaction(sender, Some(nonce));
+            create_signed_commitment_request(&[tx], sender_pk, slot).await
+        };
+        let request = |nonce| request_at(nonce, 10);
+
+        // The accepted requests take their nonces before they are committed, so the next
+        // requests of the sender must follow them contiguously
+        let mut first = request(0).await?;
+        state.validate_request(&mut first).await?;
+        let mut second = request(1).await?;
+        state.validate_request(&mut second).await?;
+
+        // A gap is rejected with the expected nonce
+        assert!(matches!(
+            state.validate_request(&mut request(3).await?).await,
+            Err(ValidationError::NonceTooHigh(2, 3))
+        ));
+
+        // And a taken nonce can't be replaced while its commitment stands
+        assert!(matches!(
+            state.validate_request(&mut request(1).await?).await,
+            Err(ValidationError::NonceAlreadyCommitted(1))
+        ));
+
+        // Unless it's cancelled first
+        state.release_reserved(10, second.digest());
+        state.validate_request(&mut request(1).await?).await?;
+
+        // The nonce of a cancelled request is the next one to commit to, while the later
+        // nonces of the sender stay taken
+        state.release_reserved(10, first.digest());
+        assert!(matches!(
+            state.validate_request(&mut request(1).await?).await,
+            Err(ValidationError::NonceAlreadyCommitted(1))
+        ));
+        assert!(matches!(
+            state.validate_request(&mut request(2).await?).await,
+            Err(ValidationError::NonceTooHigh(0, 2))
+        ));
+        state.validate_request(&mut request(0).await?).await?;
+
+        // Once the target slot has passed, the reservations are reset to the account nonce
+        state.update_head(None, 10).await?;
+        state.validate_request(&mut request(0).await?).await?;
+
+        // And the accepted requests of a sender are ordered by slot, like the committed ones
+        state.validate_request(&mut request_at(1, 12).await?).await?;
+        assert!(matches!(
+            state.validate_request(&mut request_at(2, 11).await?).await,
+            Err(ValidationError::SlotTooLow(12))
+        ));
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_invalid_inclusion_request_balance() -> eyre::Result<()> {
         let _ = tracing_subscriber::fmt::try_init();
@@ -645,7 +1166,7 @@ mod tests {
 
         assert!(matches!(
             state.validate_request(&mut request).await,
-            Err(ValidationError::InsufficientBalance)
+            Err(ValidationError::InsufficientBalance { .. })
         ));
 
         Ok(())
@@ -704,9 +1225,57 @@ mod tests {
         // all of it on the previous preconfirmation
         assert!(matches!(
             state.validate_request(&mut request).await,
-            Err(ValidationError::InsufficientBalance)
+            Err(ValidationError::InsufficientBalance { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_balance_reservations() -> eyre::Result<()> {
+        let _ = tracing_subscriber::fmt::try_init();
+
+        let anvil = launch_anvil();
+        let client = StateClient::new(anvil.endpoint_url());
+
+        let mut state = ExecutionState::new(client.clone(), Limits::default()).await?;
+        let slot = client.get_head().await?;
+        state.update_head(None, slot).await?;
+
+        // The sender holds 10 000 ETH, enough for two of the 4 000 ETH transfers
+        let (sender, sender_pk) = (anvil.addresses()[0], &anvil.keys()[0]);
+        let request = |nonce, eth: u64| async move {
+            let value = U256::from(eth) * Uint::from(ETH_TO_WEI);
+            let tx = default_test_transaction(sender, Some(nonce)).with_value(value);
+            create_signed_commitment_request(&[tx], sender_pk, 10).await
+        };
+        let cost = |request: &CommitmentRequest| {
+            max_transaction_cost(&request.as_inclusion_request().unwrap().txs[0])
+        };
+
+        // The accepted requests reserve their cost until they are committed, so the third
+        // one exhausts the balance, with the reserved cost reported
+        let mut first = request(0, 4_000).await?;
+        state.validate_request(&mut first).await?;
+        let mut second = request(1, 4_000).await?;
+        state.validate_request(&mut second).await?;
+        let mut third = request(2, 4_000).await?;
+        let err = state.validate_request(&mut third).await.unwrap_err();
+        let reserved_cost = cost(&first) + cost(&second);
+        assert!(matches!(
+            err,
+            ValidationError::InsufficientBalance { required, reserved, .. }
+                if required == cost(&third) && reserved == reserved_cost
         ));
 
+        // The rejected request doesn't take its nonce, which a smaller transfer can use
+        state.validate_request(&mut request(2, 1).await?).await?;
+
+        // Once the target slot has passed, the whole balance is available again
+        state.update_head(None, 10).await?;
+        state.validate_request(&mut request(0, 4_000).await?).await?;
+        state.validate_request(&mut request(1, 4_000).await?).await?;
+
         Ok(())
     }
 
@@ -735,9 +1304,11 @@ mod tests {
 
         let mut request = create_signed_commitment_request(&[tx], sender_pk, 10).await?;
 
+        let projected_basefee = state.projected_basefee(10).unwrap();
         assert!(matches!(
             state.validate_request(&mut request).await,
-            Err(ValidationError::BaseFeeTooLow(_))
+            Err(ValidationError::BaseFeeTooLow { required, projected })
+                if projected == projected_basefee && required == projected
         ));
 
         Ok(())
@@ -753,6 +1324,7 @@ mod tests {
         let limits: Limits = Limits {
             max_commitments_per_slot: NonZero::new(10).unwrap(),
             max_committed_gas_per_slot: NonZero::new(5_000_000).unwrap(),
+            ..Default::default()
         };
         let mut state = ExecutionState::new(client.clone(), limits).await?;
 
@@ -881,6 +1453,7 @@ mod tests {
         let limits: Limits = Limits {
             max_commitments_per_slot: NonZero::new(10).unwrap(),
             max_committed_gas_per_slot: NonZero::new(5_000_000).unwrap(),
+            ..Default::default()
         };
         let mut state = ExecutionState::new(client.clone(), limits).await?;
 
@@ -921,6 +1494,273 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_committed_byte_budget() -> eyre::Result<()> {
+        let _ = tracing_subscriber::fmt::try_init();
+
+        let anvil = launch_anvil();
+        let client = StateClient::new(anvil.endpoint_url());
+
+        // Room for a single transaction with 60 KB of calldata, and plenty of gas
+        let max_committed_bytes_per_slot = NonZero::new(100_000).unwrap();
+        let limits = Limits { max_committed_bytes_per_slot, ..Default::default() };
+        let mut state = ExecutionState::new(client.clone(), limits).await?;
+
+        // initialize the state by updating the head once
+        let slot = client.get_head().await?;
+        state.update_head(None, slot).await?;
+
+        let large_request = |i: usize| {
+            let tx = default_test_transaction(anvil.addresses()[i], None)
+                .with_input(vec![0xff; 60_000])
+                .with_gas_limit(1_000_000);
+            let sk = anvil.keys()[i].clone();
+            async move { create_signed_commitment_request(&[tx], &sk, 10).await }
+        };
+
+        let target_slot = 10;
+        let mut first = large_request(0).await?;
+        state.validate_request(&mut first).await?;
+
+        let bls_signer = Signer::random();
+        let message = ConstraintsMessage::build(0, first.as_inclusion_request().unwrap().clone());
+        let signature = bls_signer.sign(&message.digest()).unwrap();
+        state.add_constraint(target_slot, SignedConstraints { message, signature });
+        let committed_bytes = state.get_block_template(target_slot).unwrap().committed_bytes();
+        assert!(committed_bytes > 60_000);
+
+        // The byte budget is exhausted while the gas budget isn't
+        let mut second = large_request(1).await?;
+        let result = state.validate_request(&mut second).await;
+        assert!(matches!(
+            result,
+            Err(ValidationError::ByteBudgetExceeded { slot: 10, requested, remaining })
+                if requested > 60_000 && remaining == 100_000 - committed_bytes
+        ));
+
+        // Small transactions still fit
+        let tx = default_test_transaction(anvil.addresses()[2], None);
+        let mut small = create_signed_commitment_request(&[tx], &anvil.keys()[2], 10).await?;
+        state.validate_request(&mut small).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blob_inclusion_request() -> eyre::Result<()> {
+        let _ = tracing_subscriber::fmt::try_init();
+
+        let anvil = launch_anvil();
+        let client = StateClient::new(anvil.endpoint_url());
+
+        let mut state = ExecutionState::new(client.clone(), Limits::default()).await?;
+
+        // initialize the state by updating the head once
+        let slot = client.get_head().await?;
+        state.update_head(None, slot).await?;
+
+        let sidecar = test_blob_sidecar(2, 0xb0);
+        let tx = default_blob_transaction(anvil.addresses()[0], None, sidecar.clone());
+        let mut request = create_signed_commitment_request(&[tx], &anvil.keys()[0], 10).await?;
+        assert_eq!(request.as_inclusion_request().unwrap().blob_count(), 2);
+        state.validate_request(&mut request).await?;
+
+        // The blobs of the committed request are part of the locally built payload
+        let bls_signer = Signer::random();
+        let message = ConstraintsMessage::build(0, request.as_inclusion_request().unwrap().clone());
+        let signature = bls_signer.sign(&message.digest()).unwrap();
+        state.add_constraint(10, SignedConstraints { message, signature });
+        let bundle = state.get_block_template(10).unwrap().as_blobs_bundle();
+        assert_eq!(bundle.blobs.len(), 2);
+        let commitments = bundle.commitments.iter().map(|c| c.as_ref().to_vec());
+        let expected = sidecar.commitments.iter().map(|c| c.to_vec());
+        assert!(commitments.eq(expected));
+
+        // The proofs of other blobs don't verify against the versioned hashes
+        let mut sidecar = test_blob_sidecar(2, 0xb0);
+        sidecar.proofs = test_blob_sidecar(2, 0xb1).proofs;
+        let tx = default_blob_transaction(anvil.addresses()[1], None, sidecar);
+        let mut request = create_signed_commitment_request(&[tx], &anvil.keys()[1], 10).await?;
+        assert!(matches!(
+            state.validate_request(&mut request).await,
+            Err(ValidationError::BlobValidation(_))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blob_budget_exceeded() -> eyre::Result<()> {
+        let _ = tracing_subscriber::fmt::try_init();
+
+        let anvil = launch_anvil();
+        let client = StateClient::new(anvil.endpoint_url());
+
+        let limits = Limits { max_committed_blobs_percent: 50, ..Default::default() };
+        let mut state = ExecutionState::new(client.clone(), limits).await?;
+
+        // initialize the state by updating the head once
+        let slot = client.get_head().await?;
+        state.update_head(None, slot).await?;
+
+        let ceiling = BlobBudget::new(50).ceiling(state.chain.fork_at(10).max_blobs_per_block());
+        let blob_request = |i: usize, blobs: usize| {
+            let sidecar = test_blob_sidecar(blobs, i as u8);
+            let tx = default_blob_transaction(anvil.addresses()[i], None, sidecar);
+            let sk = anvil.keys()[i].clone();
+            async move { create_signed_commitment_request(&[tx], &sk, 10).await }
+        };
+
+        // A request can't take more than the committed share of the blobs of a block
+        let mut request = blob_request(0, ceiling + 1).await?;
+        let result = state.validate_request(&mut request).await;
+        assert!(matches!(result, Err(ValidationError::Eip4844Limit)));
+
+        // The blobs of an accepted request are reserved until it is committed or cancelled
+        let mut first = blob_request(1, ceiling - 1).await?;
+        state.validate_request(&mut first).await?;
+        let mut second = blob_request(2, 2).await?;
+        let result = state.validate_request(&mut second).await;
+        assert!(matches!(
+            result,
+            Err(ValidationError::BlobBudgetExceeded { slot: 10, requested: 2, remaining: 1 })
+        ));
+
+        // Once committed, the blobs are released by the digest of the request the constraints
+        // are built from, and not by the digest their signature covers
+        let message = ConstraintsMessage::build(0, first.as_inclusion_request().unwrap().clone());
+        assert_eq!(message.request_digest(), first.digest());
+        let signing_digest = message.signing_digest(ConstraintDigest::HashTreeRoot);
+        state.release_reserved(10, B256::from_slice(&signing_digest));
+        let result = state.validate_request(&mut second).await;
+        assert!(matches!(result, Err(ValidationError::BlobBudgetExceeded { .. })));
+
+        state.release_reserved(10, message.request_digest());
+        state.validate_request(&mut second).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_min_priority_fee() -> eyre::Result<()> {
+        let _ = tracing_subscriber::fmt::try_init();
+
+        let anvil = launch_anvil();
+        let client = StateClient::new(anvil.endpoint_url());
+
+        let mut state = ExecutionState::new(client.clone(), Limits::default()).await?;
+        let slot = client.get_head().await?;
+        state.update_head(None, slot).await?;
+
+        // The limits are read on every validation, so they can be adjusted in between
+        let tip = 1_000_000_000;
+        let tx = default_test_transaction(anvil.addresses()[0], None);
+        let mut request = create_signed_commitment_request(&[tx], &anvil.keys()[0], 10).await?;
+        state.limits().write().min_priority_fee = tip + 1;
+        assert!(matches!(
+            state.validate_request(&mut request).await,
+            Err(ValidationError::PriorityFeeTooLow(min)) if min == tip + 1
+        ));
+
+        // A tip exactly at the minimum is accepted
+        state.limits().write().min_priority_fee = tip;
+        state.validate_request(&mut request).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dynamic_priority_fee_floor() -> eyre::Result<()> {
+        let _ = tracing_subscriber::fmt::try_init();
+
+        let anvil = launch_anvil();
+        let client = StateClient::new(anvil.endpoint_url());
+
+        let margin = 100_000_000;
+        let limits = Limits { priority_fee_margin: Some(margin), ..Default::default() };
+        let mut state = ExecutionState::new(client.clone(), limits).await?;
+        let slot = client.get_head().await?;
+        state.update_head(None, slot).await?;
+
+        // The head block is at its gas target, so the base fee of the next slot doesn't rise
+        // and the floor is the margin
+        state.basefee = 1_000_000_000;
+        state.head_gas = Some(GasUsage { used: 15_000_000, limit: 30_000_000 });
+        assert_eq!(state.priority_fee_floor(state.slot + 1), margin);
+
+        // A full block in between raises the base fee of the following slot by 12.5%
+        let target_slot = state.slot + 2;
+        let floor = state.priority_fee_floor(target_slot);
+        assert_eq!(floor, 125_000_000 + margin);
+        assert!(floor < state.priority_fee_floor(target_slot + 1));
+
+        // The error reports the floor, and a tip exactly at it is accepted
+        let sender = anvil.addresses()[0];
+        let tx = default_test_transaction(sender, None).with_max_priority_fee_per_gas(floor - 1);
+        let mut request =
+            create_signed_commitment_request(&[tx], &anvil.keys()[0], target_slot).await?;
+        assert!(matches!(
+            state.validate_request(&mut request).await,
+            Err(ValidationError::PriorityFeeTooLow(required)) if required == floor
+        ));
+
+        let tx = default_test_transaction(sender, None).with_max_priority_fee_per_gas(floor);
+        let mut request =
+            create_signed_commitment_request(&[tx], &anvil.keys()[0], target_slot).await?;
+        state.validate_request(&mut request).await?;
+
+        // The static minimum still applies when it is above the dynamic floor
+        state.limits().write().min_priority_fee = floor + 1;
+        assert_eq!(state.priority_fee_floor(target_slot), floor + 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_basefee_projection() -> eyre::Result<()> {
+        let _ = tracing_subscriber::fmt::try_init();
+
+        let anvil = launch_anvil();
+        let client = StateClient::new(anvil.endpoint_url());
+
+        let mut state = ExecutionState::new(client.clone(), Limits::default()).await?;
+        state.update_head(None, 10).await?;
+        assert!(state.head_gas.is_some_and(|gas| gas.limit > 0));
+
+        // Targeting the next slot, the base fee only changes after the full head block
+        state.basefee = 10_000_000_000;
+        state.head_gas = Some(GasUsage { used: 30_000_000, limit: 30_000_000 });
+        assert_eq!(state.projected_basefee(11), Some(11_250_000_000));
+
+        // Each of the slots in between is assumed to have a full block
+        assert_eq!(state.projected_basefee(13), Some(14_238_281_250));
+
+        // Once slots 11 and 12 passed without a block, they no longer raise it
+        state.expire_before(13);
+        assert_eq!(state.projected_basefee(13), Some(11_250_000_000));
+        assert_eq!(state.projected_basefee(14), Some(12_656_250_000));
+
+        // Blocks assumed at their gas target keep the base fee after the head
+        state.limits().write().basefee_block_fullness = 50;
+        assert_eq!(state.projected_basefee(20), Some(11_250_000_000));
+        state.limits().write().basefee_block_fullness = 100;
+
+        // The rejection reports the projection, and a max fee exactly at it is accepted
+        let (sender, sender_pk) = (anvil.addresses()[0], &anvil.keys()[0]);
+        let request = |max_fee| async move {
+            let tx = default_test_transaction(sender, None).with_max_fee_per_gas(max_fee);
+            create_signed_commitment_request(&[tx], sender_pk, 14).await
+        };
+        assert!(matches!(
+            state.validate_request(&mut request(12_656_250_000 - 1).await?).await,
+            Err(ValidationError::BaseFeeTooLow { required: 12_656_250_000, projected })
+                if projected == 12_656_250_000
+        ));
+        state.validate_request(&mut request(12_656_250_000).await?).await?;
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_valid_bundle_inclusion_request() -> eyre::Result<()> {
         let _ = tracing_subscriber::fmt::try_init();
@@ -978,6 +1818,107 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_atomic_inclusion_request() -> eyre::Result<()> {
+        let _ = tracing_subscriber::fmt::try_init();
+
+        let anvil = launch_anvil();
+        let client = StateClient::new(anvil.endpoint_url());
+
+        let mut state = ExecutionState::new(client.clone(), Limits::default()).await?;
+
+        let sender = anvil.addresses().first().unwrap();
+        let sender_pk = anvil.keys().first().unwrap();
+
+        // initialize the state by updating the head once
+        let slot = client.get_head().await?;
+        state.update_head(None, slot).await?;
+
+        let txs = [0, 1, 2].map(|nonce| default_test_transaction(*sender, Some(nonce)));
+        let mut request = create_signed_atomic_request(&txs, sender_pk, 10).await?;
+        assert!(state.validate_request(&mut request).await.is_ok());
+
+        // The constraints list the transactions in the order of the request, and are bound
+        // to it as a whole
+        let inclusion = request.as_inclusion_request().unwrap().clone();
+        let message = ConstraintsMessage::build(0, inclusion.clone());
+        let nonces = message.constraints.iter().map(|c| c.transaction.nonce());
+        assert_eq!(nonces.collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert!(message.atomic);
+        assert_eq!(message.request_digest(), inclusion.digest());
+
+        // A nonce gap rejects the whole request, naming the offending transaction
+        let txs = [0, 1, 3].map(|nonce| default_test_transaction(*sender, Some(nonce)));
+        let mut request = create_signed_atomic_request(&txs, sender_pk, 10).await?;
+        assert!(matches!(
+            state.validate_request(&mut request).await,
+            Err(ValidationError::InvalidBundleTransaction { index: 2, source })
+                if matches!(*source, ValidationError::NonceTooHigh(2, 3))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exclusion_conflicts() -> eyre::Result<()> {
+        let _ = tracing_subscriber::fmt::try_init();
+
+        let anvil = launch_anvil();
+        let client = StateClient::new(anvil.endpoint_url());
+
+        let mut state = ExecutionState::new(client.clone(), Limits::default()).await?;
+
+        let sender = anvil.addresses().first().unwrap();
+        let sender_pk = anvil.keys().first().unwrap();
+
+        // initialize the state by updating the head once
+        let slot = client.get_head().await?;
+        state.update_head(None, slot).await?;
+
+        let exclusion = |slot, senders| {
+            let tx_hashes = Vec::new();
+            let request =
+                ExclusionRequest { slot, senders, tx_hashes, signature: None, signer: None };
+            CommitmentRequest::Exclusion(request)
+        };
+
+        // An exclusion must target at least one sender or transaction
+        assert!(matches!(
+            state.validate_request(&mut exclusion(10, vec![])).await,
+            Err(ValidationError::InvalidExclusion(MAX_EXCLUSION_TARGETS))
+        ));
+
+        // A sender with a transaction committed to at a slot can't be excluded from it
+        let tx = default_test_transaction(*sender, Some(0));
+        let mut request = create_signed_commitment_request(&[tx.clone()], sender_pk, 10).await?;
+        state.validate_request(&mut request).await?;
+        let inclusion = request.as_inclusion_request().unwrap().clone();
+        let tx_hash = *inclusion.txs[0].hash();
+        let message = ConstraintsMessage::build(0, inclusion);
+        state.add_constraint(10, SignedConstraints { message, ..Default::default() });
+
+        assert!(matches!(
+            state.validate_request(&mut exclusion(10, vec![*sender])).await,
+            Err(ValidationError::ExclusionConflict { slot: 10, tx_hash: hash }) if hash == tx_hash
+        ));
+
+        // Once excluded from a slot, its transactions can't be committed to at that slot
+        let mut request = exclusion(11, vec![*sender]);
+        state.validate_request(&mut request).await?;
+        let message =
+            ConstraintsMessage::build_exclusion(0, request.as_exclusion_request().unwrap().clone());
+        assert_eq!(message.exclusions, vec![Exclusion::Sender(*sender)]);
+        state.add_constraint(11, SignedConstraints { message, ..Default::default() });
+
+        let mut request = create_signed_commitment_request(&[tx], sender_pk, 11).await?;
+        assert!(matches!(
+            state.validate_request(&mut request).await,
+            Err(ValidationError::ExclusionConflict { slot: 11, tx_hash: hash }) if hash == tx_hash
+        ));
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_invalid_bundle_inclusion_request_balance() -> eyre::Result<()> {
         let _ = tracing_subscriber::fmt::try_init();
@@ -1003,8 +1944,70 @@ mod tests {
 
         assert!(matches!(
             state.validate_request(&mut request).await,
-            Err(ValidationError::InsufficientBalance)
+            Err(ValidationError::InsufficientBalance { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_queue_memory_limit() -> eyre::Result<()> {
+        let _ = tracing_subscriber::fmt::try_init();
+
+        let anvil = launch_anvil();
+        let client = StateClient::new(anvil.endpoint_url());
+
+        // Room for two requests with 60 KB of calldata each
+        let max_queued_bytes = NonZero::new(150_000).unwrap();
+        let limits = Limits { max_queued_bytes, ..Default::default() };
+        let mut state = ExecutionState::new(client.clone(), limits).await?;
+
+        // initialize the state by updating the head once
+        let slot = client.get_head().await?;
+        state.update_head(None, slot).await?;
+
+        let large_request = |i: usize, target_slot| {
+            let tx = default_test_transaction(anvil.addresses()[i], None)
+                .with_input(vec![0xff; 60_000])
+                .with_gas_limit(1_000_000);
+            let sk = anvil.keys()[i].clone();
+            async move { create_signed_commitment_request(&[tx], &sk, target_slot).await }
+        };
+
+        let mut first = large_request(0, 10).await?;
+        let mut second = large_request(1, 10).await?;
+        state.validate_request(&mut first).await?;
+        state.validate_request(&mut second).await?;
+
+        let used = state.queue_memory_status().used_bytes;
+        assert!(used > 120_000);
+
+        // The cap is reached, later requests are rejected without evicting the accepted ones
+        let mut third = large_request(2, 11).await?;
+        let result = state.validate_request(&mut third).await;
+        assert!(matches!(
+            result,
+            Err(ValidationError::InsufficientQueueMemory { available, .. })
+                if available == 150_000 - used
         ));
+        assert_eq!(state.queue_memory_status().used_bytes, used);
+
+        // Cancelling a request releases its memory
+        let digest = second.as_inclusion_request().unwrap().digest();
+        state.release_queued(10, digest);
+        state.validate_request(&mut third).await?;
+
+        // Expiring the slot of the first request releases its memory, and acceptance resumes
+        let mut fourth = large_request(3, 11).await?;
+        assert!(matches!(
+            state.validate_request(&mut fourth).await,
+            Err(ValidationError::InsufficientQueueMemory { .. })
+        ));
+        state.update_head(None, 10).await?;
+        state.validate_request(&mut fourth).await?;
+
+        let held = [third, fourth].map(|r| r.as_inclusion_request().unwrap().encoded_len());
+        assert_eq!(state.queue_memory_status().used_bytes, held.iter().sum::<usize>());
 
         Ok(())
     }
@@ -15,7 +15,7 @@ use tracing::error;
 
 use crate::{client::rpc::RpcClient, primitives::AccountState};
 
-use super::execution::StateUpdate;
+use super::{basefee::GasUsage, execution::StateUpdate};
 
 /// Maximum retries for RPC requests.
 const MAX_RETRIES: u32 = 8;
@@ -110,14 +110,16 @@ impl StateFetcher for StateClient {
 
         let basefee = self.client.get_basefee(None);
         let blob_basefee = self.client.get_blob_basefee(None);
+        let block = self.client.get_block(Some(block_number), false);
 
         // Collect the results
-        let (nonce_vec, balance_vec, code_vec, basefee, blob_basefee) = tokio::join!(
+        let (nonce_vec, balance_vec, code_vec, basefee, blob_basefee, block) = tokio::join!(
             nonce_futs.collect::<Vec<_>>(),
             balance_futs.collect::<Vec<_>>(),
             code_futs.collect::<Vec<_>>(),
             basefee,
             blob_basefee,
+            block,
         );
 
         // Insert the results
@@ -162,11 +164,14 @@ impl StateFetcher for StateClient {
                 });
         }
 
+        let header = block?.header;
         Ok(StateUpdate {
             account_states,
             min_basefee: basefee?,
             min_blob_basefee: blob_basefee?,
             block_number,
+            block_timestamp: header.timestamp,
+            block_gas: GasUsage { used: header.gas_used as u64, limit: header.gas_limit as u64 },
         })
     }
 
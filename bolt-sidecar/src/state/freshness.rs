@@ -0,0 +1,158 @@
+use std::{fmt, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::telemetry;
+
+/// Default maximum age of the chain heads, in slots. A single missed slot
+/// shouldn't make the heads stale.
+pub const DEFAULT_MAX_HEAD_AGE_SLOTS: u64 = 3;
+
+/// A layer of the chain whose head is tracked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainLayer {
+    /// The beacon chain, whose head is reported by the beacon node.
+    Consensus,
+    /// The execution chain, whose head is reported by the execution client.
+    Execution,
+}
+
+impl fmt::Display for ChainLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Consensus => write!(f, "consensus"),
+            Self::Execution => write!(f, "execution"),
+        }
+    }
+}
+
+/// The age of the chain heads, as reported in the status.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeadAges {
+    /// The age of the latest beacon chain head in milliseconds, if one was observed.
+    pub consensus_ms: Option<u64>,
+    /// The age of the latest execution head in milliseconds, if one was observed.
+    pub execution_ms: Option<u64>,
+    /// The age above which a head is stale, in milliseconds.
+    pub max_ms: u64,
+    /// Whether any of the heads is stale.
+    pub stale: bool,
+}
+
+/// Tracks the timestamps of the latest heads of the consensus and execution layers, to
+/// detect nodes that stopped following the chain even though they report being synced.
+///
+/// The heads are stale if the age of either of them exceeds the maximum age. Layers
+/// without an observed head yet are not considered stale.
+#[derive(Debug)]
+pub struct HeadFreshness {
+    /// The age above which a head is stale.
+    max_age: Duration,
+    /// The timestamp of the latest beacon chain head, since the UNIX epoch.
+    consensus: Option<Duration>,
+    /// The timestamp of the latest execution head, since the UNIX epoch.
+    execution: Option<Duration>,
+}
+
+impl HeadFreshness {
+    /// Creates a new tracker with the given maximum head age.
+    pub fn new(max_age: Duration) -> Self {
+        Self { max_age, consensus: None, execution: None }
+    }
+
+    /// Records a head of the given layer with the given timestamp. Older heads are ignored.
+    pub fn observe(&mut self, layer: ChainLayer, timestamp: Duration) {
+        let latest = match layer {
+            ChainLayer::Consensus => &mut self.consensus,
+            ChainLayer::Execution => &mut self.execution,
+        };
+
+        if latest.map_or(true, |latest| timestamp > latest) {
+            *latest = Some(timestamp);
+        }
+    }
+
+    /// Returns the age of the latest head of the given layer at time `now`.
+    pub fn age(&self, layer: ChainLayer, now: Duration) -> Option<Duration> {
+        let latest = match layer {
+            ChainLayer::Consensus => self.consensus,
+            ChainLayer::Execution => self.execution,
+        };
+
+        latest.map(|timestamp| now.saturating_sub(timestamp))
+    }
+
+    /// Returns true if the head of any layer is older than the maximum age at time `now`.
+    pub fn is_stale(&self, now: Duration) -> bool {
+        [ChainLayer::Consensus, ChainLayer::Execution]
+            .into_iter()
+            .any(|layer| self.age(layer, now).is_some_and(|age| age > self.max_age))
+    }
+
+    /// Returns the age of the heads at time `now`, and updates the head age gauges.
+    pub fn status(&self, now: Duration) -> HeadAges {
+        for layer in [ChainLayer::Consensus, ChainLayer::Execution] {
+            if let Some(age) = self.age(layer, now) {
+                metrics::gauge!(telemetry::HEAD_AGE, "layer" => layer.to_string())
+                    .set(age.as_secs_f64());
+            }
+        }
+
+        let millis = |age: Duration| age.as_millis() as u64;
+        HeadAges {
+            consensus_ms: self.age(ChainLayer::Consensus, now).map(millis),
+            execution_ms: self.age(ChainLayer::Execution, now).map(millis),
+            max_ms: millis(self.max_age),
+            stale: self.is_stale(now),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_at_threshold() {
+        let slot = Duration::from_secs(12);
+        let genesis = Duration::from_secs(1_000);
+        let mut freshness = HeadFreshness::new(slot * 2);
+
+        // Both layers follow the chain for a few slots
+        for i in 0..4 {
+            freshness.observe(ChainLayer::Consensus, genesis + slot * i);
+            freshness.observe(ChainLayer::Execution, genesis + slot * i);
+            assert!(!freshness.is_stale(genesis + slot * i + Duration::from_secs(4)));
+        }
+
+        // The execution heads stop, while the beacon heads keep coming
+        let frozen = genesis + slot * 3;
+        for i in 4..6 {
+            freshness.observe(ChainLayer::Consensus, genesis + slot * i);
+        }
+
+        let threshold = frozen + slot * 2;
+        assert!(!freshness.is_stale(threshold));
+        assert!(freshness.is_stale(threshold + Duration::from_millis(1)));
+
+        let status = freshness.status(threshold + Duration::from_millis(1));
+        assert_eq!(status.execution_ms, Some(24_001));
+        assert_eq!(status.consensus_ms, Some(1));
+        assert!(status.stale);
+
+        // A fresh execution head recovers, and an older one is ignored
+        freshness.observe(ChainLayer::Execution, genesis + slot * 6);
+        freshness.observe(ChainLayer::Execution, frozen);
+        assert!(!freshness.is_stale(genesis + slot * 6 + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_unobserved_layer() {
+        let mut freshness = HeadFreshness::new(Duration::from_secs(24));
+        assert!(!freshness.is_stale(Duration::from_secs(1_000)));
+
+        freshness.observe(ChainLayer::Consensus, Duration::from_secs(1_000));
+        assert_eq!(freshness.age(ChainLayer::Execution, Duration::from_secs(1_030)), None);
+        assert!(freshness.is_stale(Duration::from_secs(1_030)));
+    }
+}
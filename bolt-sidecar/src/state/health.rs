@@ -0,0 +1,122 @@
+//! Connectivity of the driver to its upstream services, served at `GET /readyz`.
+//!
+//! The driver records the outcome of its calls to the execution client, the beacon node
+//! and the constraints relay, and publishes them to the API over a `watch` channel: the API
+//! never probes the upstream services itself. The sidecar is ready once the latest call to
+//! each of them succeeded.
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+/// An upstream service of the driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dependency {
+    /// The execution client.
+    Execution,
+    /// The beacon node.
+    Consensus,
+    /// The relay the constraints are submitted to.
+    Relay,
+}
+
+/// The connectivity of the driver to an upstream service.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DependencyStatus {
+    /// Whether the latest call to the service succeeded.
+    pub connected: bool,
+    /// The UNIX timestamp of the latest successful call, in seconds, if any.
+    pub last_success: Option<u64>,
+}
+
+/// The connectivity of the driver to each of its upstream services.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DependencyHealth {
+    /// The execution client.
+    pub execution: DependencyStatus,
+    /// The beacon node.
+    pub consensus: DependencyStatus,
+    /// The constraints relay.
+    pub relay: DependencyStatus,
+}
+
+impl DependencyHealth {
+    /// Returns true if the latest call to every service succeeded.
+    pub fn is_ready(&self) -> bool {
+        self.execution.connected && self.consensus.connected && self.relay.connected
+    }
+
+    fn status_mut(&mut self, dependency: Dependency) -> &mut DependencyStatus {
+        match dependency {
+            Dependency::Execution => &mut self.execution,
+            Dependency::Consensus => &mut self.consensus,
+            Dependency::Relay => &mut self.relay,
+        }
+    }
+}
+
+/// The write side of the dependency health, owned by the driver. Cheap to clone, so that
+/// the checks running in the background can record their outcome.
+#[derive(Debug, Clone)]
+pub struct HealthPublisher {
+    tx: Arc<watch::Sender<DependencyHealth>>,
+}
+
+impl HealthPublisher {
+    /// Create a new publisher, with no successful call yet. Returns the reader handle to
+    /// share with the API.
+    pub fn new() -> (Self, HealthReader) {
+        let (tx, rx) = watch::channel(DependencyHealth::default());
+        (Self { tx: Arc::new(tx) }, HealthReader(rx))
+    }
+
+    /// Records the outcome of a call to the service, at the UNIX timestamp `now`.
+    pub fn record(&self, dependency: Dependency, success: bool, now: u64) {
+        self.tx.send_if_modified(|health| {
+            let status = health.status_mut(dependency);
+            let updated = DependencyStatus {
+                connected: success,
+                last_success: if success { Some(now) } else { status.last_success },
+            };
+            let modified = *status != updated;
+            *status = updated;
+            modified
+        });
+    }
+}
+
+/// The read side of the dependency health. Cheap to clone.
+#[derive(Debug, Clone)]
+pub struct HealthReader(watch::Receiver<DependencyHealth>);
+
+impl HealthReader {
+    /// Returns the latest connectivity to the upstream services. This never waits for the
+    /// driver.
+    pub fn load(&self) -> DependencyHealth {
+        *self.0.borrow()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dependency_health() {
+        let (publisher, reader) = HealthPublisher::new();
+        assert!(!reader.load().is_ready());
+
+        publisher.record(Dependency::Execution, true, 10);
+        publisher.record(Dependency::Consensus, true, 11);
+        assert!(!reader.load().is_ready(), "The relay hasn't been reached yet");
+        publisher.record(Dependency::Relay, true, 12);
+        assert!(reader.load().is_ready());
+
+        // A failure keeps the timestamp of the latest success
+        publisher.record(Dependency::Execution, false, 20);
+        let health = reader.load();
+        assert!(!health.is_ready());
+        assert_eq!(health.execution, DependencyStatus { connected: false, last_success: Some(10) });
+    }
+}
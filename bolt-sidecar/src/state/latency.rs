@@ -0,0 +1,212 @@
+use std::{collections::HashMap, fmt, sync::Arc, time::Duration};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::{common::quantile::RollingQuantiles, telemetry};
+
+/// Relative accuracy of the estimated stage latencies.
+const LATENCY_ACCURACY: f64 = 0.01;
+
+/// Number of samples per window of the rolling stage latencies, so that the percentiles
+/// follow changes of the hardware or network within a few hundred requests.
+const LATENCY_WINDOW: u64 = 512;
+
+/// Headroom applied to the sum of the p99 stage latencies in the recommended deadline
+/// margin, as the estimates can be off by their accuracy and the tails of the stages
+/// can coincide.
+const MARGIN_HEADROOM: f64 = 1.2;
+
+/// The stages of the commitment pipeline whose latency is tracked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineStage {
+    /// Validation against the consensus state.
+    Validation,
+    /// Fetching the account states of the senders from the execution client.
+    ExecutionLookups,
+    /// Simulation of the request against the execution state and block templates.
+    Simulation,
+    /// Signing of the commitment with the local signer.
+    Signing,
+    /// Recording the commitment fee in the store.
+    Persistence,
+    /// Submission of the constraints to MEV-Boost at the commitment deadline.
+    ConstraintSubmission,
+}
+
+impl PipelineStage {
+    /// All stages, in pipeline order.
+    pub const ALL: [Self; 6] = [
+        Self::Validation,
+        Self::ExecutionLookups,
+        Self::Simulation,
+        Self::Signing,
+        Self::Persistence,
+        Self::ConstraintSubmission,
+    ];
+}
+
+impl fmt::Display for PipelineStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Validation => write!(f, "validation"),
+            Self::ExecutionLookups => write!(f, "execution_lookups"),
+            Self::Simulation => write!(f, "simulation"),
+            Self::Signing => write!(f, "signing"),
+            Self::Persistence => write!(f, "persistence"),
+            Self::ConstraintSubmission => write!(f, "constraint_submission"),
+        }
+    }
+}
+
+/// The rolling latency percentiles of a pipeline stage, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StageLatency {
+    /// The pipeline stage.
+    pub stage: PipelineStage,
+    /// The number of samples the percentiles are estimated over.
+    pub samples: u64,
+    /// The median latency.
+    pub p50_ms: f64,
+    /// The 95th percentile latency.
+    pub p95_ms: f64,
+    /// The 99th percentile latency.
+    pub p99_ms: f64,
+}
+
+/// The latency budgets of the commitment pipeline, as reported in the status.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencyBudgets {
+    /// The latencies of the stages with samples, in pipeline order.
+    pub stages: Vec<StageLatency>,
+    /// The recommended minimum time between the commitment deadline and the start of the
+    /// target slot, derived from the p99 latencies of all the stages.
+    pub recommended_deadline_margin_ms: f64,
+    /// The configured time between the commitment deadline and the start of the target slot.
+    pub configured_deadline_margin_ms: u64,
+}
+
+/// Tracks the rolling latency percentiles of each stage of the commitment pipeline, for
+/// operators to size the lookahead and the commitment deadline to their own hardware.
+///
+/// The tracker is shared with the tasks that run stages outside of the driver loop, like
+/// the submission of the constraints.
+#[derive(Debug, Clone)]
+pub struct LatencyTracker {
+    /// The latencies of each stage, in seconds.
+    stages: Arc<Mutex<HashMap<PipelineStage, RollingQuantiles>>>,
+    /// The configured time between the commitment deadline and the start of the target slot.
+    deadline_margin: Duration,
+}
+
+impl LatencyTracker {
+    /// Creates a new tracker, comparing the recommendation against the configured margin
+    /// between the commitment deadline and the start of the target slot.
+    pub fn new(deadline_margin: Duration) -> Self {
+        Self { stages: Arc::default(), deadline_margin }
+    }
+
+    /// Records the latency of a stage.
+    pub fn record(&self, stage: PipelineStage, elapsed: Duration) {
+        self.stages
+            .lock()
+            .entry(stage)
+            .or_insert_with(|| RollingQuantiles::new(LATENCY_ACCURACY, LATENCY_WINDOW))
+            .insert(elapsed.as_secs_f64());
+    }
+
+    /// Returns the latency budgets of the pipeline, and updates the latency gauges.
+    pub fn status(&self) -> LatencyBudgets {
+        let stages = self.stages.lock();
+
+        let mut latencies = Vec::new();
+        for stage in PipelineStage::ALL {
+            let Some(sketch) = stages.get(&stage).map(RollingQuantiles::sketch) else {
+                continue;
+            };
+            let [p50, p95, p99] = [0.5, 0.95, 0.99].map(|q| sketch.quantile(q).unwrap_or(0.0));
+
+            for (quantile, value) in [("0.5", p50), ("0.95", p95), ("0.99", p99)] {
+                let label = stage.to_string();
+                metrics::gauge!(telemetry::STAGE_LATENCY, "stage" => label, "quantile" => quantile)
+                    .set(value);
+            }
+
+            latencies.push(StageLatency {
+                stage,
+                samples: sketch.count(),
+                p50_ms: p50 * 1_000.0,
+                p95_ms: p95 * 1_000.0,
+                p99_ms: p99 * 1_000.0,
+            });
+        }
+
+        let p99_total: f64 = latencies.iter().map(|latency| latency.p99_ms).sum();
+        let recommended = p99_total * MARGIN_HEADROOM;
+        metrics::gauge!(telemetry::RECOMMENDED_DEADLINE_MARGIN).set(recommended / 1_000.0);
+
+        LatencyBudgets {
+            stages: latencies,
+            recommended_deadline_margin_ms: recommended,
+            configured_deadline_margin_ms: self.deadline_margin.as_millis() as u64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_recommended_margin_covers_requests() {
+        let tracker = LatencyTracker::new(Duration::from_secs(4));
+        let mut rng = StdRng::seed_from_u64(1);
+
+        // Mock requests with exponentially distributed stage latencies around their means
+        let means_ms = [
+            (PipelineStage::Validation, 0.2),
+            (PipelineStage::ExecutionLookups, 15.0),
+            (PipelineStage::Simulation, 1.0),
+            (PipelineStage::Signing, 0.5),
+            (PipelineStage::Persistence, 2.0),
+            (PipelineStage::ConstraintSubmission, 40.0),
+        ];
+
+        let mut totals = Vec::new();
+        for _ in 0..300 {
+            let mut total = 0.0;
+            for (stage, mean) in means_ms {
+                let elapsed_ms = -rng.gen_range(f64::EPSILON..1.0f64).ln() * mean;
+                tracker.record(stage, Duration::from_secs_f64(elapsed_ms / 1_000.0));
+                total += elapsed_ms;
+            }
+            totals.push(total);
+        }
+
+        totals.sort_by(f64::total_cmp);
+        let observed_p99 = totals[(0.99 * (totals.len() - 1) as f64) as usize];
+
+        let status = tracker.status();
+        assert_eq!(status.stages.len(), PipelineStage::ALL.len());
+        assert!(status.stages.iter().all(|latency| latency.samples == 300));
+        assert!(status.stages.iter().all(|l| l.p50_ms <= l.p95_ms && l.p95_ms <= l.p99_ms));
+        assert!(status.recommended_deadline_margin_ms > observed_p99);
+        assert_eq!(status.configured_deadline_margin_ms, 4_000);
+    }
+
+    #[test]
+    fn test_stages_without_samples() {
+        let tracker = LatencyTracker::new(Duration::from_secs(4));
+        assert_eq!(tracker.status().stages, vec![]);
+        assert_eq!(tracker.status().recommended_deadline_margin_ms, 0.0);
+
+        tracker.record(PipelineStage::Signing, Duration::from_millis(10));
+        let status = tracker.status();
+        assert_eq!(status.stages.len(), 1);
+        assert_eq!(status.stages[0].stage, PipelineStage::Signing);
+        assert!((status.stages[0].p99_ms - 10.0).abs() <= 10.0 * LATENCY_ACCURACY + 1e-9);
+    }
+}
@@ -0,0 +1,219 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::{
+    db::{Store, StoreError},
+    telemetry,
+};
+
+/// A validator whose lease is held by another sidecar instance.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeaseConflict {
+    /// The validator public key.
+    pub pubkey: String,
+    /// The identifier of the sidecar instance holding the lease.
+    pub holder: String,
+    /// The expiry of the lease, as a UNIX timestamp in milliseconds.
+    pub expires_at: u64,
+}
+
+/// Coordinates sidecar instances sharing the same persistent store, so that only one of
+/// them commits on behalf of each validator.
+///
+/// A lease is acquired for every validator pubkey and renewed on an interval shorter than
+/// its TTL. Validators whose lease is held by another instance are reported as conflicts,
+/// and their slots must not be committed to. Leases are taken over once they expire, or
+/// as soon as the other instance releases them on shutdown.
+#[derive(Debug)]
+pub struct ValidatorLeases {
+    store: Store,
+    /// The identifier of this sidecar instance.
+    holder: String,
+    /// The duration for which a lease is valid without being renewed.
+    ttl: Duration,
+    /// The validator pubkeys leased by this instance.
+    held: HashSet<String>,
+    /// The validator pubkeys leased by other instances.
+    conflicts: HashMap<String, LeaseConflict>,
+}
+
+impl ValidatorLeases {
+    /// Create a new lease coordinator for the instance `holder`.
+    pub fn new(store: Store, holder: impl Into<String>, ttl: Duration) -> Self {
+        Self { store, holder: holder.into(), ttl, held: HashSet::new(), conflicts: HashMap::new() }
+    }
+
+    /// Returns the identifier of this sidecar instance.
+    pub fn holder(&self) -> &str {
+        &self.holder
+    }
+
+    /// Returns the interval at which the leases should be renewed.
+    pub fn renewal_interval(&self) -> Duration {
+        self.ttl / 3
+    }
+
+    /// Returns true if this instance holds the lease on the given validator.
+    pub fn holds(&self, pubkey: &str) -> bool {
+        self.held.contains(pubkey)
+    }
+
+    /// Returns the validators whose lease is held by other instances, ordered by pubkey.
+    pub fn conflicts(&self) -> Vec<LeaseConflict> {
+        let mut conflicts = self.conflicts.values().cloned().collect::<Vec<_>>();
+        conflicts.sort_by(|a, b| a.pubkey.cmp(&b.pubkey));
+        conflicts
+    }
+
+    /// Acquires or renews the leases on the given validators.
+    pub fn renew<I, S>(&mut self, pubkeys: I) -> Result<(), StoreError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.renew_at(pubkeys, unix_millis())
+    }
+
+    fn renew_at<I, S>(&mut self, pubkeys: I, now: u64) -> Result<(), StoreError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let ttl = self.ttl.as_millis() as u64;
+
+        for pubkey in pubkeys.into_iter().map(Into::into) {
+            if self.store.acquire_lease(&pubkey, &self.holder, now, ttl)? {
+                if self.conflicts.remove(&pubkey).is_some() {
+                    info!(%pubkey, "Took over validator lease from another sidecar instance");
+                }
+                self.held.insert(pubkey);
+                continue;
+            }
+
+            if self.held.remove(&pubkey) {
+                warn!(%pubkey, "Lost validator lease to another sidecar instance");
+            }
+
+            // The lease can be released in the meantime, it will be acquired on the next renewal
+            let Some(lease) = self.store.get_lease(&pubkey)? else { continue };
+            if !self.conflicts.contains_key(&pubkey) {
+                error!(
+                    %pubkey,
+                    holder = %lease.holder,
+                    "Validator is leased by another sidecar instance, refusing to commit for it. \
+                     Make sure only one sidecar runs for each validator"
+                );
+            }
+
+            let conflict = LeaseConflict {
+                pubkey: pubkey.clone(),
+                holder: lease.holder,
+                expires_at: lease.expires_at,
+            };
+            self.conflicts.insert(pubkey, conflict);
+        }
+
+        metrics::gauge!(telemetry::LEASE_CONFLICTS).set(self.conflicts.len() as f64);
+        Ok(())
+    }
+
+    /// Releases all the leases held by this instance, for a clean handover on shutdown.
+    pub fn release_all(&mut self) -> Result<(), StoreError> {
+        for pubkey in self.held.drain() {
+            self.store.release_lease(&pubkey, &self.holder)?;
+        }
+
+        info!(holder = %self.holder, "Released all validator leases");
+        Ok(())
+    }
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBKEY: &str = "0xa1b2";
+
+    #[cfg(feature = "persistence")]
+    fn shared_store() -> (Store, Store) {
+        let path = std::env::temp_dir().join(format!("bolt-leases-{}.db", rand::random::<u64>()));
+        (Store::open(&path).unwrap(), Store::open(&path).unwrap())
+    }
+
+    /// Without the persistence feature, the instances share the handles of an in-memory store.
+    #[cfg(not(feature = "persistence"))]
+    fn shared_store() -> (Store, Store) {
+        let store = Store::in_memory().unwrap();
+        (store.clone(), store)
+    }
+
+    #[test]
+    fn test_single_holder_and_release() -> eyre::Result<()> {
+        let (first_store, second_store) = shared_store();
+        let ttl = Duration::from_secs(10);
+        let mut first = ValidatorLeases::new(first_store, "first", ttl);
+        let mut second = ValidatorLeases::new(second_store, "second", ttl);
+
+        first.renew_at([PUBKEY], 1_000)?;
+        second.renew_at([PUBKEY], 1_000)?;
+
+        // Only one instance accepts commitments for the validator
+        assert!(first.holds(PUBKEY));
+        assert!(!second.holds(PUBKEY));
+        assert_eq!(
+            second.conflicts(),
+            vec![LeaseConflict {
+                pubkey: PUBKEY.to_string(),
+                holder: "first".to_string(),
+                expires_at: 11_000,
+            }]
+        );
+
+        // Renewals keep the lease with the first instance
+        first.renew_at([PUBKEY], 5_000)?;
+        second.renew_at([PUBKEY], 12_000)?;
+        assert!(first.holds(PUBKEY));
+        assert!(!second.holds(PUBKEY));
+
+        // After a graceful shutdown of the first instance, the second takes over
+        first.release_all()?;
+        assert!(!first.holds(PUBKEY));
+        second.renew_at([PUBKEY], 12_001)?;
+        assert!(second.holds(PUBKEY));
+        assert!(second.conflicts().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_takeover_after_expiry() -> eyre::Result<()> {
+        let (first_store, second_store) = shared_store();
+        let ttl = Duration::from_secs(10);
+        let mut first = ValidatorLeases::new(first_store, "first", ttl);
+        let mut second = ValidatorLeases::new(second_store, "second", ttl);
+
+        first.renew_at([PUBKEY], 1_000)?;
+
+        // The first instance stops renewing (e.g. it crashed), its lease expires
+        second.renew_at([PUBKEY], 10_999)?;
+        assert!(!second.holds(PUBKEY));
+        second.renew_at([PUBKEY], 11_000)?;
+        assert!(second.holds(PUBKEY));
+
+        // If it comes back, it finds out it lost the lease
+        first.renew_at([PUBKEY], 12_000)?;
+        assert!(!first.holds(PUBKEY));
+        assert_eq!(first.conflicts()[0].holder, "second");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,241 @@
+//! Maintenance windows of our validators, during which they won't propose.
+//!
+//! Operators sometimes know in advance that a validator will miss its slots: during a
+//! maintenance, the doppelganger protection window after a migration, or while a voluntary
+//! exit is pending. The sidecar must not sell commitments for those slots, so requests
+//! targeting them are rejected as the proposer being unavailable, and they are excluded
+//! from the lookahead.
+//!
+//! Windows are declared at startup with `--maintenance-windows`, or at runtime with
+//! `admin_declareMaintenance`. A window doesn't affect the commitments signed before it was
+//! declared: they are reported as conflicts, and must still be honored or dropped by an
+//! operator with `admin_dropCommitment`.
+
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use alloy::primitives::B256;
+use ethereum_consensus::phase0::mainnet::SLOTS_PER_EPOCH;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::chain::DEFAULT_SLOT_TIME_IN_SECONDS, primitives::Slot};
+
+/// The operator recorded for the windows declared in the configuration.
+pub const CONFIGURED_MAINTENANCE: &str = "config";
+
+/// The slots during which a validator won't propose, both bounds included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Blackout {
+    /// The slots of a range of epochs.
+    Epochs {
+        /// The first epoch of the range.
+        from: u64,
+        /// The last epoch of the range.
+        to: u64,
+    },
+    /// The slots starting in a range of UNIX timestamps, in seconds.
+    Timestamps {
+        /// The first timestamp of the range.
+        from: u64,
+        /// The last timestamp of the range.
+        to: u64,
+    },
+}
+
+/// A validator that won't propose during a blackout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    /// The index of the validator.
+    pub validator_index: u64,
+    /// The slots the validator won't propose.
+    #[serde(flatten)]
+    pub blackout: Blackout,
+}
+
+impl FromStr for MaintenanceWindow {
+    type Err = String;
+
+    /// Parses a window formatted as `<validator index>=epoch:<from>-<to>` or
+    /// `<validator index>=time:<from>-<to>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid maintenance window '{s}', expected <validator index>=epoch:<from>-<to> \
+                 or <validator index>=time:<from>-<to>"
+            )
+        };
+
+        let (index, range) = s.split_once('=').ok_or_else(invalid)?;
+        let (kind, range) = range.split_once(':').ok_or_else(invalid)?;
+        let (from, to) = range.split_once('-').ok_or_else(invalid)?;
+        let validator_index = index.trim().parse().map_err(|_| invalid())?;
+        let from = from.trim().parse().map_err(|_| invalid())?;
+        let to = to.trim().parse().map_err(|_| invalid())?;
+        if from > to {
+            return Err(format!("Maintenance window '{s}' ends before it starts"));
+        }
+
+        let blackout = match kind.trim() {
+            "epoch" => Blackout::Epochs { from, to },
+            "time" => Blackout::Timestamps { from, to },
+            _ => return Err(invalid()),
+        };
+        Ok(Self { validator_index, blackout })
+    }
+}
+
+impl fmt::Display for MaintenanceWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.blackout {
+            Blackout::Epochs { from, to } => {
+                write!(f, "{}=epoch:{from}-{to}", self.validator_index)
+            }
+            Blackout::Timestamps { from, to } => {
+                write!(f, "{}=time:{from}-{to}", self.validator_index)
+            }
+        }
+    }
+}
+
+/// A maintenance window declared by an operator.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeclaredWindow {
+    /// The identifier of the window, to cancel it.
+    pub id: u64,
+    /// The validator and the slots it won't propose.
+    #[serde(flatten)]
+    pub window: MaintenanceWindow,
+    /// The operator who declared the window, or [`CONFIGURED_MAINTENANCE`].
+    pub declared_by: String,
+}
+
+/// A commitment signed for a slot of a maintenance window before it was declared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceConflict {
+    /// The digest of the commitment request.
+    pub digest: B256,
+    /// The slot of the commitment.
+    pub slot: Slot,
+}
+
+/// The declaration of a maintenance window, with the commitments it conflicts with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceDeclaration {
+    /// The declared window.
+    pub window: DeclaredWindow,
+    /// The commitments already signed for the slots of the window, which must still be
+    /// honored or dropped by an operator.
+    pub conflicts: Vec<MaintenanceConflict>,
+}
+
+/// The maintenance windows of our validators, until they have passed.
+#[derive(Debug, Clone)]
+pub struct MaintenanceWindows {
+    windows: BTreeMap<u64, DeclaredWindow>,
+    next_id: u64,
+    genesis_time: u64,
+    slot_time: u64,
+}
+
+impl Default for MaintenanceWindows {
+    fn default() -> Self {
+        Self::new(0, DEFAULT_SLOT_TIME_IN_SECONDS)
+    }
+}
+
+impl MaintenanceWindows {
+    /// Create an empty set of windows, for a chain with the given genesis time and slot time.
+    pub fn new(genesis_time: u64, slot_time: u64) -> Self {
+        Self { windows: BTreeMap::new(), next_id: 1, genesis_time, slot_time }
+    }
+
+    /// Returns the declared windows, in the order they were declared.
+    pub fn windows(&self) -> Vec<DeclaredWindow> {
+        self.windows.values().cloned().collect()
+    }
+
+    /// Declares a window, on behalf of the given operator.
+    pub fn declare(&mut self, window: MaintenanceWindow, declared_by: &str) -> DeclaredWindow {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let declared = DeclaredWindow { id, window, declared_by: declared_by.to_string() };
+        self.windows.insert(id, declared.clone());
+        declared
+    }
+
+    /// Cancels the window with the given identifier, returning it if it was declared.
+    pub fn cancel(&mut self, id: u64) -> Option<DeclaredWindow> {
+        self.windows.remove(&id)
+    }
+
+    /// Returns true if the validator won't propose the slot, according to any window.
+    pub fn covers(&self, validator_index: u64, slot: Slot) -> bool {
+        self.windows
+            .values()
+            .any(|declared| self.window_covers(&declared.window, validator_index, slot))
+    }
+
+    /// Returns true if the window covers the slot, when proposed by the validator.
+    pub fn window_covers(
+        &self,
+        window: &MaintenanceWindow,
+        validator_index: u64,
+        slot: Slot,
+    ) -> bool {
+        window.validator_index == validator_index && self.blackout_covers(&window.blackout, slot)
+    }
+
+    /// Forgets the windows that ended before the given slot.
+    pub fn prune(&mut self, slot: Slot) {
+        let slot_start = self.slot_start(slot);
+        self.windows.retain(|_, declared| match declared.window.blackout {
+            Blackout::Epochs { to, .. } => slot < (to + 1) * SLOTS_PER_EPOCH,
+            Blackout::Timestamps { to, .. } => slot_start <= to,
+        });
+    }
+
+    fn blackout_covers(&self, blackout: &Blackout, slot: Slot) -> bool {
+        match *blackout {
+            Blackout::Epochs { from, to } => (from..=to).contains(&(slot / SLOTS_PER_EPOCH)),
+            Blackout::Timestamps { from, to } => (from..=to).contains(&self.slot_start(slot)),
+        }
+    }
+
+    fn slot_start(&self, slot: Slot) -> u64 {
+        self.genesis_time + slot * self.slot_time
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_maintenance_windows() {
+        let epochs: MaintenanceWindow = "12=epoch:2-3".parse().unwrap();
+        let timestamps: MaintenanceWindow = "13 = time:1000-1100".parse().unwrap();
+        assert_eq!(timestamps.blackout, Blackout::Timestamps { from: 1_000, to: 1_100 });
+        assert_eq!(epochs.to_string().parse::<MaintenanceWindow>().unwrap(), epochs);
+        for invalid in ["12", "12=epoch:3-2", "12=slot:1-2", "x=time:1-2"] {
+            assert!(invalid.parse::<MaintenanceWindow>().is_err(), "{invalid}");
+        }
+
+        let mut windows = MaintenanceWindows::new(1_000, 12);
+        let first = windows.declare(epochs, "127.0.0.1");
+        windows.declare(timestamps, CONFIGURED_MAINTENANCE);
+
+        // Only the slots of the validator in the window are covered
+        assert!(windows.covers(12, 2 * SLOTS_PER_EPOCH));
+        assert!(windows.covers(12, 4 * SLOTS_PER_EPOCH - 1));
+        assert!(!windows.covers(12, 4 * SLOTS_PER_EPOCH));
+        assert!(!windows.covers(13, 2 * SLOTS_PER_EPOCH));
+        assert!(windows.covers(13, 0) && windows.covers(13, 8) && !windows.covers(13, 9));
+
+        // Windows are forgotten once they have passed
+        windows.prune(9);
+        assert_eq!(windows.windows(), vec![first.clone()]);
+        assert_eq!(windows.cancel(first.id), Some(first));
+        assert!(windows.cancel(1).is_none());
+    }
+}
@@ -0,0 +1,316 @@
+//! Manifests of the obligations of our proposers in each slot, for the component that builds
+//! or validates their blocks.
+//!
+//! The manifests are assembled from the block templates and published by the driver after
+//! every mutation, like the [`StateSnapshot`](super::StateSnapshot). Each change of the
+//! commitments of a slot bumps the revision of its manifest, and subscribers receive every
+//! revision they didn't see yet. Once the commitment deadline of a slot has passed, its
+//! manifest is frozen and kept for [`MANIFEST_HISTORY_SLOTS`].
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use alloy::primitives::{Address, B256};
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::{
+    builder::template::BlockTemplate,
+    primitives::{FullTransaction, Slot},
+};
+
+/// Number of slots the frozen manifests are kept for after their deadline.
+pub const MANIFEST_HISTORY_SLOTS: u64 = 64;
+
+/// A committed transaction, in inclusion order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestTransaction {
+    /// The hash of the transaction.
+    pub hash: B256,
+    /// The recovered sender of the transaction.
+    pub sender: Option<Address>,
+    /// The index at which the transaction must be included, if constrained.
+    pub index: Option<u64>,
+    /// The gas limit of the transaction.
+    pub gas_limit: u64,
+    /// The number of blobs of the transaction.
+    pub blobs: usize,
+    /// The EIP-2718 encoding of the transaction, including its blob sidecar.
+    pub raw: FullTransaction,
+}
+
+/// The transactions of a commitment, which are included together or not at all.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestGroup {
+    /// The digest of the commitment request.
+    pub digest: B256,
+    /// The hashes of the transactions of the commitment, in inclusion order.
+    pub transactions: Vec<B256>,
+}
+
+/// The obligations of the proposer of a slot.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlotManifest {
+    /// The target slot.
+    pub slot: Slot,
+    /// The revision of the manifest, increased with every change of its content.
+    pub revision: u64,
+    /// Whether the commitment deadline of the slot has passed, after which the manifest
+    /// doesn't change anymore.
+    pub frozen: bool,
+    /// The committed transactions, in inclusion order.
+    pub transactions: Vec<ManifestTransaction>,
+    /// The atomic groups of transactions, one for each commitment.
+    pub groups: Vec<ManifestGroup>,
+    /// The gas reserved by the committed transactions.
+    pub reserved_gas: u64,
+    /// The blobs reserved by the committed transactions.
+    pub reserved_blobs: usize,
+}
+
+impl SlotManifest {
+    /// Assembles the manifest of the commitments in the block template of a slot, at
+    /// revision 0.
+    pub fn from_template(slot: Slot, template: &BlockTemplate) -> Self {
+        let mut manifest = Self { slot, ..Default::default() };
+
+        for constraints in &template.signed_constraints_list {
+            let digest = constraints.message.request_digest();
+            let mut group = ManifestGroup { digest, transactions: Vec::new() };
+
+            for constraint in &constraints.message.constraints {
+                let raw = constraint.transaction.clone();
+                let blobs = raw.as_eip4844().map_or(0, |tx| tx.blob_versioned_hashes.len());
+                let transaction = ManifestTransaction {
+                    hash: *raw.hash(),
+                    sender: raw.sender(),
+                    index: constraint.index,
+                    gas_limit: raw.gas_limit(),
+                    blobs,
+                    raw,
+                };
+
+                manifest.reserved_gas += transaction.gas_limit;
+                manifest.reserved_blobs += transaction.blobs;
+                group.transactions.push(transaction.hash);
+                manifest.transactions.push(transaction);
+            }
+            manifest.groups.push(group);
+        }
+
+        manifest
+    }
+
+    /// Returns true if both manifests hold the same obligations, regardless of their revision.
+    fn same_content(&self, other: &Self) -> bool {
+        self.transactions == other.transactions && self.groups == other.groups
+    }
+}
+
+type Manifests = BTreeMap<Slot, Arc<SlotManifest>>;
+
+/// The write side of the slot manifests, owned by the driver.
+#[derive(Debug)]
+pub struct ManifestPublisher {
+    tx: watch::Sender<Arc<Manifests>>,
+}
+
+impl ManifestPublisher {
+    /// Create a new publisher, returning the reader handle to share with the API.
+    pub fn new() -> (Self, ManifestReader) {
+        let (tx, rx) = watch::channel(Arc::default());
+        (Self { tx }, ManifestReader(rx))
+    }
+
+    /// Updates the manifests of the slots that aren't frozen from their block template.
+    /// Only the manifests whose content changed get a new revision, and subscribers are only
+    /// notified if one did.
+    pub fn update(&self, templates: &HashMap<Slot, BlockTemplate>) {
+        self.tx.send_if_modified(|current| {
+            let mut slots = templates.keys().copied().collect::<Vec<_>>();
+            slots.extend(current.iter().filter(|(_, m)| !m.frozen).map(|(slot, _)| *slot));
+            slots.sort_unstable();
+            slots.dedup();
+
+            let mut updated = current.as_ref().clone();
+            let mut modified = false;
+            for slot in slots {
+                let previous = current.get(&slot);
+                if previous.is_some_and(|manifest| manifest.frozen) {
+                    continue;
+                }
+
+                // The manifest of a template that was dropped before its deadline is empty
+                let mut manifest = templates
+                    .get(&slot)
+                    .map(|template| SlotManifest::from_template(slot, template))
+                    .unwrap_or_else(|| SlotManifest { slot, ..Default::default() });
+
+                match previous {
+                    Some(previous) if previous.same_content(&manifest) => continue,
+                    Some(previous) => manifest.revision = previous.revision + 1,
+                    None if manifest.transactions.is_empty() => continue,
+                    None => {}
+                }
+                updated.insert(slot, Arc::new(manifest));
+                modified = true;
+            }
+
+            if modified {
+                *current = Arc::new(updated);
+            }
+            modified
+        });
+    }
+
+    /// Freezes the manifest of a slot whose commitment deadline has passed, in a final
+    /// revision, and forgets the ones older than [`MANIFEST_HISTORY_SLOTS`].
+    pub fn freeze(&self, slot: Slot) {
+        self.tx.send_if_modified(|current| {
+            let oldest = slot.saturating_sub(MANIFEST_HISTORY_SLOTS);
+            let mut updated = current.as_ref().clone().split_off(&oldest);
+            let mut modified = updated.len() < current.len();
+
+            if let Some(manifest) = updated.get_mut(&slot).filter(|manifest| !manifest.frozen) {
+                let revision = manifest.revision + 1;
+                *manifest =
+                    Arc::new(SlotManifest { revision, frozen: true, ..(**manifest).clone() });
+                modified = true;
+            }
+
+            if modified {
+                *current = Arc::new(updated);
+            }
+            modified
+        });
+    }
+}
+
+/// The read side of the slot manifests. Cheap to clone.
+#[derive(Debug, Clone)]
+pub struct ManifestReader(watch::Receiver<Arc<Manifests>>);
+
+impl Default for ManifestReader {
+    /// A reader without any manifest, for when there is no driver.
+    fn default() -> Self {
+        Self(watch::channel(Arc::default()).1)
+    }
+}
+
+impl ManifestReader {
+    /// Returns the latest manifest of a slot, or `None` if we have no commitment for it or
+    /// it is older than [`MANIFEST_HISTORY_SLOTS`].
+    pub fn get(&self, slot: Slot) -> Option<Arc<SlotManifest>> {
+        self.0.borrow().get(&slot).cloned()
+    }
+
+    /// Returns the stream of the revisions of the manifest of a slot, starting with the
+    /// current one if any. Revisions published in quick succession may be skipped, but the
+    /// latest one is always delivered. The stream ends with the frozen revision.
+    pub fn subscribe(&self, slot: Slot) -> impl Stream<Item = Arc<SlotManifest>> {
+        let state = (self.0.clone(), None::<Arc<SlotManifest>>, true);
+
+        futures::stream::unfold(state, move |(mut rx, last, mut first)| async move {
+            loop {
+                if last.as_ref().is_some_and(|manifest| manifest.frozen) {
+                    return None;
+                }
+                if !first {
+                    rx.changed().await.ok()?;
+                }
+                first = false;
+
+                let manifest = rx.borrow_and_update().get(&slot).cloned();
+                let seen = |manifest: &SlotManifest| {
+                    last.as_ref().is_some_and(|last| last.revision == manifest.revision)
+                };
+                match manifest {
+                    Some(manifest) if !seen(&manifest) => {
+                        return Some((manifest.clone(), (rx, Some(manifest), false)));
+                    }
+                    // The manifest was forgotten
+                    None if last.is_some() => return None,
+                    _ => continue,
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::signers::local::PrivateKeySigner;
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::{
+        primitives::{ConstraintsMessage, SignedConstraints},
+        test_util::{create_signed_commitment_request, default_test_transaction, DeterministicEnv},
+    };
+
+    async fn constraints(env: &DeterministicEnv, nonce: u64) -> SignedConstraints {
+        let sk = env.secret_key();
+        let tx =
+            default_test_transaction(PrivateKeySigner::from(sk.clone()).address(), Some(nonce));
+        let request = create_signed_commitment_request(&[tx], &sk, 10).await.unwrap();
+        let request = request.into_inclusion_request().unwrap();
+        SignedConstraints { message: ConstraintsMessage::build(0, request), ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn test_manifest_updates() {
+        let env = DeterministicEnv::new(1);
+        let (publisher, reader) = ManifestPublisher::new();
+        let mut updates = Box::pin(reader.subscribe(10));
+        let mut templates = HashMap::from([(10, BlockTemplate::default())]);
+
+        // Empty templates have no manifest
+        publisher.update(&templates);
+        assert!(reader.get(10).is_none());
+
+        let (first, second, replacement) =
+            (constraints(&env, 0).await, constraints(&env, 1).await, constraints(&env, 2).await);
+        let template = templates.get_mut(&10).unwrap();
+        template.add_constraints(first.clone());
+        publisher.update(&templates);
+        assert_eq!(updates.next().await.unwrap().revision, 0);
+
+        templates.get_mut(&10).unwrap().add_constraints(second.clone());
+        publisher.update(&templates);
+        assert_eq!(updates.next().await.unwrap().groups.len(), 2);
+
+        // Unchanged templates don't bump the revision
+        publisher.update(&templates);
+        assert_eq!(reader.get(10).unwrap().revision, 1);
+
+        // The second commitment is replaced by another one
+        let template = templates.get_mut(&10).unwrap();
+        template.remove_commitment(&second.message.request_digest());
+        template.add_constraints(replacement.clone());
+        publisher.update(&templates);
+        publisher.freeze(10);
+
+        // Later changes don't affect the frozen manifest
+        templates.get_mut(&10).unwrap().add_constraints(second);
+        publisher.update(&templates);
+
+        let manifest = reader.get(10).unwrap();
+        assert_eq!((manifest.revision, manifest.frozen), (3, true));
+        let digests = manifest.groups.iter().map(|group| group.digest).collect::<Vec<_>>();
+        let expected = [first.message.request_digest(), replacement.message.request_digest()];
+        assert_eq!(digests, expected);
+        assert_eq!(manifest.reserved_gas, 42_000);
+        assert_eq!(manifest.transactions[1].raw, replacement.message.constraints[0].transaction);
+
+        // The stream skips to the latest revision, and ends with the frozen one
+        let updates = updates.collect::<Vec<_>>().await;
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0], manifest);
+
+        publisher.freeze(10 + MANIFEST_HISTORY_SLOTS + 1);
+        assert!(reader.get(10).is_none());
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::{BTreeMap, HashMap};
+
+use alloy::primitives::B256;
+use serde::{Deserialize, Serialize};
+
+use crate::primitives::Slot;
+
+/// Default cap of the memory held by the transactions of accepted requests (256 MiB).
+pub const DEFAULT_MAX_QUEUED_BYTES: usize = 256 * 1024 * 1024;
+
+/// The memory held by the transactions of accepted requests, as reported in the status.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueMemoryStatus {
+    /// The bytes held by the transactions of accepted requests.
+    pub used_bytes: usize,
+    /// The maximum bytes that accepted requests can hold.
+    pub max_bytes: usize,
+}
+
+/// Tracks the bytes of the transactions of accepted commitment requests, which the sidecar
+/// holds until their slot expires, against a global cap.
+///
+/// A request holds its memory from the moment it passes validation, while it awaits its
+/// signature and then in the block template of its slot. The memory is released when the
+/// request is cancelled, or when its slot expires.
+///
+/// Held requests are never evicted to make room for new ones: once the cap is reached, new
+/// requests are rejected, so that the requests for the earlier slots keep their place.
+#[derive(Debug)]
+pub struct QueueMemory {
+    /// The maximum bytes that accepted requests can hold.
+    max_bytes: usize,
+    /// The bytes held by accepted requests, by target slot and request digest.
+    held: BTreeMap<Slot, HashMap<B256, usize>>,
+    /// The total bytes held by accepted requests.
+    used: usize,
+}
+
+impl QueueMemory {
+    /// Creates a new tracker, allowing accepted requests to hold up to `max_bytes`.
+    pub fn new(max_bytes: usize) -> Self {
+        Self { max_bytes, held: BTreeMap::new(), used: 0 }
+    }
+
+    /// Returns the bytes held by accepted requests.
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    /// Returns the bytes that new requests can still hold.
+    pub fn available(&self) -> usize {
+        self.max_bytes.saturating_sub(self.used)
+    }
+
+    /// Returns the status of the memory held by accepted requests.
+    pub fn status(&self) -> QueueMemoryStatus {
+        QueueMemoryStatus { used_bytes: self.used, max_bytes: self.max_bytes }
+    }
+
+    /// Holds `bytes` for the request with the given digest until it is released, replacing
+    /// any memory already held by the same request.
+    pub fn hold(&mut self, slot: Slot, digest: B256, bytes: usize) {
+        let previous = self.held.entry(slot).or_default().insert(digest, bytes);
+        self.used = self.used - previous.unwrap_or(0) + bytes;
+    }
+
+    /// Releases the memory held by the request with the given digest, returning its size.
+    pub fn release(&mut self, slot: Slot, digest: B256) -> usize {
+        let Some(requests) = self.held.get_mut(&slot) else {
+            return 0;
+        };
+
+        let released = requests.remove(&digest).unwrap_or(0);
+        if requests.is_empty() {
+            self.held.remove(&slot);
+        }
+
+        self.used -= released;
+        released
+    }
+
+    /// Releases the memory held for the given slot and all earlier ones, returning its size.
+    pub fn expire(&mut self, slot: Slot) -> usize {
+        let later = self.held.split_off(&slot.saturating_add(1));
+        let expired = std::mem::replace(&mut self.held, later);
+
+        let released = expired.values().flat_map(HashMap::values).sum::<usize>();
+        self.used -= released;
+        released
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_memory() {
+        let mut memory = QueueMemory::new(1_000);
+        let (first, second, third) = (B256::random(), B256::random(), B256::random());
+
+        memory.hold(10, first, 400);
+        memory.hold(11, second, 500);
+        assert_eq!(memory.used(), 900);
+        assert_eq!(memory.available(), 100);
+
+        // Holding again for the same request replaces its memory
+        memory.hold(10, first, 300);
+        assert_eq!(memory.used(), 800);
+
+        // Releasing twice only releases once
+        assert_eq!(memory.release(11, second), 500);
+        assert_eq!(memory.release(11, second), 0);
+        assert_eq!(memory.used(), 300);
+
+        // Expiring a slot releases it and the earlier ones, but not the later ones
+        memory.hold(12, third, 200);
+        memory.hold(13, second, 100);
+        assert_eq!(memory.expire(12), 500);
+        assert_eq!(memory.status(), QueueMemoryStatus { used_bytes: 100, max_bytes: 1_000 });
+        assert_eq!(memory.release(10, first), 0);
+    }
+}
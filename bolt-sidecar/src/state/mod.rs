@@ -2,17 +2,8 @@
 //! to simulate commitments against. It is updated on every block. It has both execution state and
 //! consensus state.
 
-use std::{
-    pin::Pin,
-    task::{Context, Poll},
-    time::Duration,
-};
-
-use futures::{future::poll_fn, Future, FutureExt};
-use tokio::time::Sleep;
-
 mod execution;
-pub use execution::{ExecutionState, ValidationError};
+pub use execution::{ExecutionState, ValidationError, ValidationTimings};
 
 /// Module to fetch state from the Execution layer.
 pub mod fetcher;
@@ -26,64 +17,71 @@ pub use consensus::ConsensusState;
 pub mod head_tracker;
 pub use head_tracker::HeadTracker;
 
-/// The deadline for a which a commitment is considered valid.
-#[derive(Debug)]
-pub struct CommitmentDeadline {
-    slot: u64,
-    sleep: Option<Pin<Box<Sleep>>>,
-}
-
-impl CommitmentDeadline {
-    /// Create a new deadline for a given slot and duration.
-    pub fn new(slot: u64, duration: Duration) -> Self {
-        let sleep = Some(Box::pin(tokio::time::sleep(duration)));
-        Self { slot, sleep }
-    }
-
-    /// Poll the deadline until it is reached.
-    pub async fn wait(&mut self) -> Option<u64> {
-        let slot = poll_fn(|cx| self.poll_unpin(cx)).await;
-        self.sleep = None;
-        slot
-    }
-}
-
-/// Poll the deadline until it is reached.
-///
-/// - If already reached, the future will return `None` immediately.
-/// - If not reached, the future will return `Some(slot)` when the deadline is reached.
-impl Future for CommitmentDeadline {
-    type Output = Option<u64>;
-
-    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let Some(ref mut sleep) = self.sleep else {
-            return Poll::Ready(None);
-        };
-
-        match sleep.as_mut().poll(cx) {
-            Poll::Ready(_) => Poll::Ready(Some(self.slot)),
-            Poll::Pending => Poll::Pending,
-        }
-    }
-}
-
-#[cfg(test)]
-mod tests {
-
-    use super::*;
-
-    #[tokio::test]
-    async fn test_commitment_deadline() {
-        let time = std::time::Instant::now();
-        let mut deadline = CommitmentDeadline::new(0, Duration::from_secs(1));
-
-        let slot = deadline.wait().await;
-        println!("Deadline reached. Passed {:?}", time.elapsed());
-        assert_eq!(slot, Some(0));
-
-        let time = std::time::Instant::now();
-        let slot = deadline.wait().await;
-        println!("Deadline reached. Passed {:?}", time.elapsed());
-        assert_eq!(slot, None);
-    }
-}
+/// Module to map wall-clock time to slots.
+pub mod clock;
+pub use clock::SlotClock;
+
+/// Module to run tasks at fixed offsets within each slot.
+pub mod scheduler;
+pub use scheduler::SlotScheduler;
+
+/// Module to project base fees and track the accuracy of the projections.
+pub mod basefee;
+pub use basefee::BaseFeeTracker;
+
+/// Module to track the blobs committed to in each slot.
+pub mod blobs;
+pub use blobs::BlobBudget;
+
+/// Module to track the nonces and the balance taken by accepted requests that aren't
+/// committed yet.
+pub mod reserved;
+pub use reserved::PendingReservations;
+
+/// Module to account for the memory held by the transactions of accepted requests.
+pub mod memory;
+pub use memory::QueueMemory;
+
+/// Module to track the committed space held by each signer against its quotas.
+pub mod quotas;
+pub use quotas::QuotaTracker;
+
+/// Module to publish snapshots of the driver state to the API.
+pub mod snapshot;
+pub use snapshot::{SnapshotPublisher, SnapshotReader, StateSnapshot};
+
+/// Module to publish the manifests of the commitments of each slot to the API.
+pub mod manifest;
+pub use manifest::{ManifestPublisher, ManifestReader, SlotManifest};
+
+/// Module to coordinate validator leases between sidecar instances.
+pub mod lease;
+pub use lease::ValidatorLeases;
+
+/// Module to track the readiness of the sidecar after startup.
+pub mod readiness;
+pub use readiness::Readiness;
+
+/// Module to publish the connectivity of the driver to its upstream services to the API.
+pub mod health;
+pub use health::{HealthPublisher, HealthReader};
+
+/// Module to detect stale chain heads.
+pub mod freshness;
+pub use freshness::HeadFreshness;
+
+/// Module to detect the drift of the host clock from the chain.
+pub mod drift;
+pub use drift::ClockDriftMonitor;
+
+/// Module to track the latency of the stages of the commitment pipeline.
+pub mod latency;
+pub use latency::LatencyTracker;
+
+/// Module to track the latency and success rate of the constraint submissions to each relay.
+pub mod relays;
+pub use relays::RelayTracker;
+
+/// Module to declare the maintenance windows during which our validators won't propose.
+pub mod maintenance;
+pub use maintenance::MaintenanceWindows;
@@ -0,0 +1,297 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+use alloy::primitives::{Address, B256};
+use serde::{Deserialize, Serialize};
+
+use crate::{api::commitments::whitelist::QuotaOverrides, primitives::Slot};
+
+/// The quotas of committed space each signer may hold. The quotas that aren't set are
+/// unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignerQuotas {
+    /// Maximum gas a signer may hold committed in a single slot.
+    pub max_gas_per_slot: Option<u64>,
+    /// Maximum number of transactions a signer may hold committed in a single slot.
+    pub max_txs_per_slot: Option<u64>,
+    /// Maximum number of commitments a signer may obtain for the slots of a single epoch.
+    pub max_commitments_per_epoch: Option<u64>,
+}
+
+impl SignerQuotas {
+    /// Returns the quotas with the overrides of the whitelist entry of a signer applied.
+    pub fn with_overrides(self, overrides: &QuotaOverrides) -> Self {
+        Self {
+            max_gas_per_slot: overrides.max_gas_per_slot.or(self.max_gas_per_slot),
+            max_txs_per_slot: overrides.max_txs_per_slot.or(self.max_txs_per_slot),
+            max_commitments_per_epoch: overrides
+                .max_commitments_per_epoch
+                .or(self.max_commitments_per_epoch),
+        }
+    }
+}
+
+/// A quota of committed space of a signer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Quota {
+    /// The gas committed in a slot.
+    GasPerSlot,
+    /// The transactions committed in a slot.
+    TxsPerSlot,
+    /// The commitments for the slots of an epoch.
+    CommitmentsPerEpoch,
+}
+
+impl Quota {
+    /// Returns the name of the quota, which is also the reason of its error.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::GasPerSlot => "gas_per_slot",
+            Self::TxsPerSlot => "txs_per_slot",
+            Self::CommitmentsPerEpoch => "commitments_per_epoch",
+        }
+    }
+}
+
+impl fmt::Display for Quota {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Error of a request that would exceed a quota of its signer.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Signer quota {quota} of {limit} exceeded: {used} held, {requested} requested")]
+pub struct QuotaError {
+    /// The quota that would be exceeded.
+    pub quota: Quota,
+    /// The limit of the quota for the signer.
+    pub limit: u64,
+    /// The part of the quota the signer already holds.
+    pub used: u64,
+    /// The part of the quota the request would take.
+    pub requested: u64,
+    /// The first slot that is counted against a fresh quota.
+    pub resets_at_slot: Slot,
+}
+
+/// The committed space held by a signer in a slot.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QuotaUsage {
+    /// The gas of the committed transactions.
+    pub gas: u64,
+    /// The number of committed transactions.
+    pub txs: u64,
+}
+
+/// The usage held by an accepted request, until it is cancelled or its slot expires.
+#[derive(Debug, Clone, Copy)]
+struct HeldUsage {
+    signer: Address,
+    slot: Slot,
+    usage: QuotaUsage,
+}
+
+/// Tracks the committed space held by each signer against its quotas.
+///
+/// Accepted requests count against the quotas of the slot and the epoch they target, from
+/// the moment they pass validation. They stop counting when they are cancelled, before they
+/// are signed or by their signer. The counters of a slot are reset once it has passed, and
+/// those of an epoch once the next one starts.
+#[derive(Debug)]
+pub struct QuotaTracker {
+    quotas: SignerQuotas,
+    slots_per_epoch: u64,
+    /// The usage of each signer, by target slot.
+    slots: BTreeMap<Slot, HashMap<Address, QuotaUsage>>,
+    /// The number of commitments of each signer, by epoch of their target slot.
+    epochs: BTreeMap<u64, HashMap<Address, u64>>,
+    /// The usage held by each accepted request, by digest.
+    held: HashMap<B256, HeldUsage>,
+}
+
+impl QuotaTracker {
+    /// Creates a tracker of the given quotas, with no usage yet.
+    pub fn new(quotas: SignerQuotas, slots_per_epoch: u64) -> Self {
+        Self {
+            quotas,
+            slots_per_epoch,
+            slots: BTreeMap::new(),
+            epochs: BTreeMap::new(),
+            held: HashMap::new(),
+        }
+    }
+
+    /// Returns the usage held by the signer in the given slot.
+    pub fn usage(&self, signer: &Address, slot: Slot) -> QuotaUsage {
+        self.slots.get(&slot).and_then(|usages| usages.get(signer)).copied().unwrap_or_default()
+    }
+
+    /// Returns the number of commitments of the signer for the slots of the given epoch.
+    pub fn commitments_in_epoch(&self, signer: &Address, epoch: u64) -> u64 {
+        self.epochs.get(&epoch).and_then(|counts| counts.get(signer)).copied().unwrap_or(0)
+    }
+
+    /// Checks that a request of the signer for the given slot fits in its quotas, with the
+    /// overrides of its whitelist entry applied.
+    pub fn check(
+        &self,
+        signer: &Address,
+        slot: Slot,
+        requested: QuotaUsage,
+        overrides: &QuotaOverrides,
+    ) -> Result<(), QuotaError> {
+        let quotas = self.quotas.with_overrides(overrides);
+        let held = self.usage(signer, slot);
+        let epoch = slot / self.slots_per_epoch;
+        let commitments = self.commitments_in_epoch(signer, epoch);
+
+        // The slot quotas reset at the next slot, and the epoch one at the next epoch
+        let (next_slot, next_epoch) = (slot + 1, (epoch + 1) * self.slots_per_epoch);
+        let per_epoch = quotas.max_commitments_per_epoch;
+        let checks = [
+            (Quota::GasPerSlot, quotas.max_gas_per_slot, held.gas, requested.gas, next_slot),
+            (Quota::TxsPerSlot, quotas.max_txs_per_slot, held.txs, requested.txs, next_slot),
+            (Quota::CommitmentsPerEpoch, per_epoch, commitments, 1, next_epoch),
+        ];
+
+        for (quota, limit, used, requested, resets_at_slot) in checks {
+            if let Some(limit) = limit.filter(|limit| used.saturating_add(requested) > *limit) {
+                return Err(QuotaError { quota, limit, used, requested, resets_at_slot });
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts the usage of an accepted request against the quotas of its signer, until it is
+    /// released or its slot expires.
+    pub fn hold(&mut self, digest: B256, signer: Address, slot: Slot, usage: QuotaUsage) {
+        self.release(digest);
+
+        let held = self.slots.entry(slot).or_default().entry(signer).or_default();
+        held.gas += usage.gas;
+        held.txs += usage.txs;
+        let epoch = slot / self.slots_per_epoch;
+        *self.epochs.entry(epoch).or_default().entry(signer).or_default() += 1;
+
+        self.held.insert(digest, HeldUsage { signer, slot, usage });
+    }
+
+    /// Stops counting the usage of a cancelled request. Returns false if it wasn't held.
+    pub fn release(&mut self, digest: B256) -> bool {
+        let Some(HeldUsage { signer, slot, usage }) = self.held.remove(&digest) else {
+            return false;
+        };
+
+        if let Some(usages) = self.slots.get_mut(&slot) {
+            if let Some(held) = usages.get_mut(&signer) {
+                held.gas = held.gas.saturating_sub(usage.gas);
+                held.txs = held.txs.saturating_sub(usage.txs);
+                if *held == QuotaUsage::default() {
+                    usages.remove(&signer);
+                }
+            }
+            if usages.is_empty() {
+                self.slots.remove(&slot);
+            }
+        }
+
+        let epoch = slot / self.slots_per_epoch;
+        if let Some(counts) = self.epochs.get_mut(&epoch) {
+            if let Some(count) = counts.get_mut(&signer) {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    counts.remove(&signer);
+                }
+            }
+            if counts.is_empty() {
+                self.epochs.remove(&epoch);
+            }
+        }
+        true
+    }
+
+    /// Resets the counters of the slots before the given one, and of the epochs before the
+    /// one of the slot.
+    pub fn expire(&mut self, slot: Slot) {
+        self.slots = self.slots.split_off(&slot);
+        self.epochs = self.epochs.split_off(&(slot / self.slots_per_epoch));
+        self.held.retain(|_, held| held.slot >= slot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLOTS_PER_EPOCH: u64 = 32;
+
+    #[test]
+    fn test_slot_quotas() {
+        let quotas = SignerQuotas {
+            max_gas_per_slot: Some(100_000),
+            max_txs_per_slot: Some(3),
+            ..Default::default()
+        };
+        let mut tracker = QuotaTracker::new(quotas, SLOTS_PER_EPOCH);
+        let (alice, bob) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let none = QuotaOverrides::default();
+        let request = QuotaUsage { gas: 42_000, txs: 2 };
+
+        tracker.check(&alice, 10, request, &none).unwrap();
+        tracker.hold(B256::repeat_byte(1), alice, 10, request);
+
+        // The slot quota of a signer doesn't apply to the others, nor to other slots
+        let err = tracker.check(&alice, 10, request, &none).unwrap_err();
+        let (quota, resets_at_slot) = (Quota::TxsPerSlot, 11);
+        assert_eq!(err, QuotaError { quota, limit: 3, used: 2, requested: 2, resets_at_slot });
+        tracker.check(&bob, 10, request, &none).unwrap();
+        tracker.check(&alice, 11, request, &none).unwrap();
+
+        let large = QuotaUsage { gas: 60_000, txs: 1 };
+        let err = tracker.check(&alice, 10, large, &none).unwrap_err();
+        assert_eq!((err.quota, err.used, err.requested), (Quota::GasPerSlot, 42_000, 60_000));
+
+        // The overrides of a whitelisted signer apply instead of the configured quotas
+        let overrides = QuotaOverrides { max_txs_per_slot: Some(10), ..Default::default() };
+        tracker.check(&alice, 10, request, &overrides).unwrap();
+
+        // A cancelled request stops counting, once
+        assert!(tracker.release(B256::repeat_byte(1)));
+        assert!(!tracker.release(B256::repeat_byte(1)));
+        assert_eq!(tracker.usage(&alice, 10), QuotaUsage::default());
+        tracker.check(&alice, 10, request, &none).unwrap();
+    }
+
+    #[test]
+    fn test_epoch_quota() {
+        let quotas = SignerQuotas { max_commitments_per_epoch: Some(2), ..Default::default() };
+        let mut tracker = QuotaTracker::new(quotas, SLOTS_PER_EPOCH);
+        let alice = Address::repeat_byte(1);
+        let none = QuotaOverrides::default();
+        let request = QuotaUsage { gas: 21_000, txs: 1 };
+
+        tracker.hold(B256::repeat_byte(1), alice, 33, request);
+        tracker.hold(B256::repeat_byte(2), alice, 40, request);
+        let err = tracker.check(&alice, 63, request, &none).unwrap_err();
+        assert_eq!(err.quota, Quota::CommitmentsPerEpoch);
+        assert_eq!(err.resets_at_slot, 64);
+        tracker.check(&alice, 64, request, &none).unwrap();
+
+        // The counters of the slots are reset once they have passed, but not the ones of
+        // their epoch until the next one starts
+        tracker.expire(40);
+        assert_eq!(tracker.usage(&alice, 40), request);
+        tracker.expire(41);
+        assert_eq!(tracker.usage(&alice, 40), QuotaUsage::default());
+        assert_eq!(tracker.commitments_in_epoch(&alice, 1), 2);
+        assert!(tracker.check(&alice, 63, request, &none).is_err());
+
+        tracker.expire(64);
+        assert_eq!(tracker.commitments_in_epoch(&alice, 1), 0);
+        tracker.check(&alice, 65, request, &none).unwrap();
+    }
+}
@@ -0,0 +1,195 @@
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use parking_lot::Mutex;
+use tracing::{info, warn};
+
+use crate::probe::ProbeStage;
+
+/// Default timeout of the warmup phase at startup.
+pub const DEFAULT_WARMUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Shared flags indicating whether the sidecar is ready to accept commitment requests:
+/// it must have completed its warmup, the chain heads it observes must be fresh, its clock
+/// must not drift beyond the safety bound, the latest probe of its pipeline must have
+/// passed, if probes are enabled, and it must not be shutting down.
+#[derive(Debug, Clone, Default)]
+pub struct Readiness(Arc<Flags>);
+
+#[derive(Debug, Default)]
+struct Flags {
+    warmed_up: AtomicBool,
+    stale_heads: AtomicBool,
+    clock_drift: AtomicBool,
+    shutting_down: AtomicBool,
+    failing_probe_stages: Mutex<Vec<ProbeStage>>,
+}
+
+impl Readiness {
+    /// Create a new readiness flag with the given initial warmup state.
+    pub fn new(ready: bool) -> Self {
+        Self(Arc::new(Flags { warmed_up: AtomicBool::new(ready), ..Default::default() }))
+    }
+
+    /// Returns true if the sidecar is ready to accept requests.
+    pub fn is_ready(&self) -> bool {
+        self.is_warmed_up() &&
+            self.is_synced() &&
+            !self.is_shutting_down() &&
+            self.0.failing_probe_stages.lock().is_empty()
+    }
+
+    /// Returns true if the sidecar completed its warmup.
+    pub fn is_warmed_up(&self) -> bool {
+        self.0.warmed_up.load(Ordering::SeqCst)
+    }
+
+    /// Returns true if the chain heads observed by the sidecar are fresh, and its clock is
+    /// within the safety bound of the chain.
+    pub fn is_synced(&self) -> bool {
+        !self.0.stale_heads.load(Ordering::SeqCst) && !self.0.clock_drift.load(Ordering::SeqCst)
+    }
+
+    /// Marks the sidecar as ready to accept requests.
+    pub fn set_ready(&self) {
+        self.0.warmed_up.store(true, Ordering::SeqCst);
+    }
+
+    /// Sets whether the chain heads observed by the sidecar are stale. Returns true if
+    /// the state changed.
+    pub fn set_stale_heads(&self, stale: bool) -> bool {
+        self.0.stale_heads.swap(stale, Ordering::SeqCst) != stale
+    }
+
+    /// Sets whether the clock of the sidecar drifts beyond the safety bound. Returns true if
+    /// the state changed.
+    pub fn set_clock_drift(&self, drifting: bool) -> bool {
+        self.0.clock_drift.swap(drifting, Ordering::SeqCst) != drifting
+    }
+
+    /// Returns true if the sidecar is shutting down.
+    pub fn is_shutting_down(&self) -> bool {
+        self.0.shutting_down.load(Ordering::SeqCst)
+    }
+
+    /// Marks the sidecar as shutting down: new commitment requests are rejected, while the
+    /// ones already received are still responded to. This can't be undone.
+    pub fn set_shutting_down(&self) {
+        self.0.shutting_down.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns the stages of the pipeline that failed the latest probe.
+    pub fn failing_probe_stages(&self) -> Vec<ProbeStage> {
+        self.0.failing_probe_stages.lock().clone()
+    }
+
+    /// Sets the stages of the pipeline that failed the latest probe. Returns true if they
+    /// changed.
+    pub fn set_failing_probe_stages(&self, stages: Vec<ProbeStage>) -> bool {
+        let mut failing = self.0.failing_probe_stages.lock();
+        let changed = *failing != stages;
+        *failing = stages;
+        changed
+    }
+
+    /// Runs the given prefetch future, and only then marks the sidecar as ready.
+    ///
+    /// If the prefetch fails or doesn't complete within `timeout`, the sidecar is marked as ready
+    /// anyway, in a degraded state where the first requests may be slower or spuriously rejected.
+    /// Returns true if the warmup completed successfully.
+    pub async fn warm_up<F, E>(&self, timeout: Duration, prefetch: F) -> bool
+    where
+        F: Future<Output = Result<(), E>>,
+        E: std::fmt::Debug,
+    {
+        let completed = match tokio::time::timeout(timeout, prefetch).await {
+            Ok(Ok(())) => {
+                info!("Warmup completed, accepting requests");
+                true
+            }
+            Ok(Err(err)) => {
+                warn!(?err, "Warmup failed, accepting requests in a degraded state");
+                false
+            }
+            Err(_) => {
+                warn!(?timeout, "Warmup timed out, accepting requests in a degraded state");
+                false
+            }
+        };
+
+        self.set_ready();
+        completed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_ready_after_prefetch() {
+        let readiness = Readiness::new(false);
+        let observed = Arc::new(Mutex::new(Vec::new()));
+
+        let prefetch = {
+            let readiness = readiness.clone();
+            let observed = observed.clone();
+            async move {
+                observed.lock().push(("duties", readiness.is_ready()));
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                observed.lock().push(("basefee", readiness.is_ready()));
+                Ok::<_, ()>(())
+            }
+        };
+
+        assert!(readiness.warm_up(Duration::from_secs(1), prefetch).await);
+        assert!(readiness.is_ready());
+
+        // The flag must only flip after all the prefetches completed
+        assert_eq!(*observed.lock(), vec![("duties", false), ("basefee", false)]);
+    }
+
+    #[tokio::test]
+    async fn test_ready_after_timeout() {
+        let readiness = Readiness::new(false);
+
+        let prefetch = async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok::<_, ()>(())
+        };
+
+        assert!(!readiness.warm_up(Duration::from_millis(10), prefetch).await);
+        assert!(readiness.is_ready());
+
+        // A failing prefetch also falls back to the degraded state
+        let readiness = Readiness::new(false);
+        assert!(!readiness.warm_up(Duration::from_secs(1), async { Err("offline") }).await);
+        assert!(readiness.is_ready());
+    }
+
+    #[test]
+    fn test_stale_heads() {
+        let readiness = Readiness::new(true);
+
+        assert!(readiness.set_stale_heads(true));
+        assert!(!readiness.set_stale_heads(true));
+        assert!(readiness.is_warmed_up());
+        assert!(!readiness.is_synced());
+        assert!(!readiness.is_ready());
+
+        assert!(readiness.set_stale_heads(false));
+        assert!(readiness.is_ready());
+
+        // A drifting clock also rejects requests
+        assert!(readiness.set_clock_drift(true));
+        assert!(!readiness.is_synced());
+        assert!(readiness.set_clock_drift(false));
+        assert!(readiness.is_ready());
+    }
+}
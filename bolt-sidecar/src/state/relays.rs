@@ -0,0 +1,268 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use parking_lot::Mutex;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client::retry::{RetryPolicy, DEFAULT_BREAKER_COOLDOWN, DEFAULT_BREAKER_THRESHOLD},
+    telemetry,
+};
+
+/// Default weight of the latest sample in the moving averages of the relays.
+pub const DEFAULT_RELAY_EWMA_ALPHA: f64 = 0.3;
+
+/// Default delay between the starts of the submissions to two successive relays.
+pub const DEFAULT_RELAY_STAGGER: Duration = Duration::from_millis(25);
+
+/// Default number of targets that must accept the constraints of a slot.
+pub const DEFAULT_RELAY_QUORUM: usize = 1;
+
+/// Average success rate under which a relay is attempted after the healthy ones.
+const HEALTHY_SUCCESS_RATE: f64 = 0.5;
+
+/// Number of successive slots a relay must fail to receive the constraints of before it is
+/// marked unhealthy and backs off.
+const UNHEALTHY_FAILED_SLOTS: u32 = 3;
+
+/// Maximum number of slots an unhealthy relay is skipped for before it is attempted again.
+const MAX_BACKOFF_SLOTS: u64 = 32;
+
+/// Configuration of the additional relays the constraints are submitted to, and of the
+/// order in which they are attempted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelayConfig {
+    /// The relays the constraints are submitted to, in addition to MEV-Boost.
+    pub urls: Vec<Url>,
+    /// The number of targets that must accept the constraints of a slot.
+    pub quorum: usize,
+    /// The delay between the starts of the submissions to two successive targets.
+    pub stagger: Duration,
+    /// The weight of the latest sample in the moving averages of the targets.
+    pub ewma_alpha: f64,
+    /// The attempts of the submissions to each target, and the delays between them.
+    pub retry: RetryPolicy,
+    /// The number of successive transient failures after which the circuit of a target opens.
+    pub breaker_threshold: u32,
+    /// The time the circuit of a target stays open.
+    pub breaker_cooldown: Duration,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self {
+            urls: Vec::new(),
+            quorum: DEFAULT_RELAY_QUORUM,
+            stagger: DEFAULT_RELAY_STAGGER,
+            ewma_alpha: DEFAULT_RELAY_EWMA_ALPHA,
+            retry: RetryPolicy::default(),
+            breaker_threshold: DEFAULT_BREAKER_THRESHOLD,
+            breaker_cooldown: DEFAULT_BREAKER_COOLDOWN,
+        }
+    }
+}
+
+/// The measured performance of a submission target, as reported in the status.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RelayPerformance {
+    /// The URL of the target.
+    pub url: String,
+    /// The position of the target in the submission order, starting at 0.
+    pub rank: usize,
+    /// The number of submission attempts the averages are measured over.
+    pub samples: u64,
+    /// The moving average of the submission latency, in milliseconds.
+    pub latency_ms: f64,
+    /// The moving average of the share of successful submissions.
+    pub success_rate: f64,
+    /// Whether the target is attempted along with the healthy ones.
+    pub healthy: bool,
+    /// The number of successive slots the target failed to receive the constraints of.
+    pub failed_slots: u32,
+    /// The slot until which the target is skipped, if it is backing off.
+    pub backoff_until_slot: Option<u64>,
+    /// The outcome of the latest submission attempt, if any.
+    pub last_outcome: Option<SubmissionOutcome>,
+}
+
+/// The outcome of a submission attempt to a target.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubmissionOutcome {
+    /// The HTTP status code of the response, if the target responded.
+    pub status: Option<u16>,
+    /// The error of the attempt, if it failed.
+    pub error: Option<String>,
+}
+
+impl SubmissionOutcome {
+    /// Returns true if the target accepted the submission.
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Returns the label of the status code in the metrics, `none` without a response.
+    fn status_label(&self) -> String {
+        self.status.map_or_else(|| "none".to_string(), |status| status.to_string())
+    }
+}
+
+/// The submission order of the targets, as reported in the status.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RelayStatus {
+    /// The targets, in submission order.
+    pub relays: Vec<RelayPerformance>,
+    /// The number of targets that must accept the constraints of a slot.
+    pub quorum: usize,
+    /// The delay between the starts of the submissions to two successive targets.
+    pub stagger_ms: u64,
+}
+
+/// The moving averages of a target, and its failures over the latest slots.
+#[derive(Debug, Clone, Default)]
+struct Averages {
+    /// The submission latency, in seconds.
+    latency: f64,
+    success_rate: f64,
+    samples: u64,
+    failed_slots: u32,
+    backoff_until_slot: Option<u64>,
+    last_outcome: Option<SubmissionOutcome>,
+}
+
+impl Averages {
+    fn is_healthy(&self) -> bool {
+        let failing = self.failed_slots >= UNHEALTHY_FAILED_SLOTS;
+        (self.samples == 0 || self.success_rate >= HEALTHY_SUCCESS_RATE) && !failing
+    }
+}
+
+/// Tracks the exponentially weighted moving averages of the latency and success rate of the
+/// constraint submissions to each target, so that the fastest healthy targets are attempted
+/// first when the submission window is tight.
+///
+/// A target that fails to receive the constraints of several successive slots is marked
+/// unhealthy, and skipped for a number of slots that doubles on each further failure, so
+/// that the submissions don't wait on it.
+#[derive(Debug, Clone)]
+pub struct RelayTracker {
+    relays: Arc<Mutex<HashMap<String, Averages>>>,
+    alpha: f64,
+}
+
+impl Default for RelayTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_RELAY_EWMA_ALPHA)
+    }
+}
+
+impl RelayTracker {
+    /// Creates a new tracker, weighting the latest sample by `alpha` in the averages.
+    pub fn new(alpha: f64) -> Self {
+        Self { relays: Arc::default(), alpha }
+    }
+
+    /// Records a submission attempt to the target and its outcome, in the averages and in
+    /// the metrics.
+    pub fn record(&self, url: &str, elapsed: Duration, outcome: SubmissionOutcome) {
+        let latency = elapsed.as_secs_f64();
+        let success = if outcome.is_success() { 1.0 } else { 0.0 };
+        let (relay, status) = (url.to_string(), outcome.status_label());
+        metrics::counter!(telemetry::RELAY_SUBMISSIONS, "relay" => relay, "status" => status)
+            .increment(1);
+        metrics::histogram!(telemetry::RELAY_SUBMISSION_DURATION, "relay" => url.to_string())
+            .record(latency);
+
+        let mut relays = self.relays.lock();
+        let averages = relays.entry(url.to_string()).or_default();
+        if averages.samples == 0 {
+            (averages.latency, averages.success_rate) = (latency, success);
+        } else {
+            averages.latency += self.alpha * (latency - averages.latency);
+            averages.success_rate += self.alpha * (success - averages.success_rate);
+        }
+        averages.samples += 1;
+        averages.last_outcome = Some(outcome);
+    }
+
+    /// Records whether the target received the constraints of the slot. Once it failed
+    /// [`UNHEALTHY_FAILED_SLOTS`] successive slots, it backs off for a number of slots
+    /// doubling on each further failure, up to [`MAX_BACKOFF_SLOTS`].
+    pub fn record_slot(&self, url: &str, slot: u64, delivered: bool) {
+        let mut relays = self.relays.lock();
+        let averages = relays.entry(url.to_string()).or_default();
+        if delivered {
+            (averages.failed_slots, averages.backoff_until_slot) = (0, None);
+            return;
+        }
+
+        averages.failed_slots += 1;
+        if let Some(exponent) = averages.failed_slots.checked_sub(UNHEALTHY_FAILED_SLOTS) {
+            let backoff = 1u64.checked_shl(exponent).unwrap_or(u64::MAX).min(MAX_BACKOFF_SLOTS);
+            averages.backoff_until_slot = Some(slot + backoff);
+        }
+    }
+
+    /// Returns the slot until which the target is skipped, if it is backing off at `slot`.
+    pub fn backoff(&self, url: &str, slot: u64) -> Option<u64> {
+        let relays = self.relays.lock();
+        let until = relays.get(url)?.backoff_until_slot?;
+        (slot <= until).then_some(until)
+    }
+
+    /// Returns the indexes of the targets in the order they should be attempted: the
+    /// healthy ones first, each group by increasing latency. Targets without samples are
+    /// attempted first, and ties keep the configured order.
+    pub fn ordering(&self, urls: &[String]) -> Vec<usize> {
+        let relays = self.relays.lock();
+        let key = |index: &usize| match relays.get(&urls[*index]) {
+            Some(averages) => (!averages.is_healthy(), averages.latency),
+            None => (false, 0.0),
+        };
+
+        let mut order = (0..urls.len()).collect::<Vec<_>>();
+        order.sort_by(|a, b| {
+            let ((a_unhealthy, a_latency), (b_unhealthy, b_latency)) = (key(a), key(b));
+            a_unhealthy.cmp(&b_unhealthy).then(a_latency.total_cmp(&b_latency))
+        });
+        order
+    }
+
+    /// Returns the submission order of the targets, and updates the relay gauges.
+    pub fn status(&self, urls: &[String], quorum: usize, stagger: Duration) -> RelayStatus {
+        let order = self.ordering(urls);
+        let relays = self.relays.lock();
+
+        let mut performances = Vec::with_capacity(order.len());
+        for (rank, index) in order.into_iter().enumerate() {
+            let url = urls[index].clone();
+            let averages = relays.get(&url).cloned().unwrap_or_default();
+            let sampled = averages.samples > 0;
+
+            let performance = RelayPerformance {
+                rank,
+                samples: averages.samples,
+                latency_ms: averages.latency * 1_000.0,
+                success_rate: if sampled { averages.success_rate } else { 1.0 },
+                healthy: averages.is_healthy(),
+                failed_slots: averages.failed_slots,
+                backoff_until_slot: averages.backoff_until_slot,
+                last_outcome: averages.last_outcome.clone(),
+                url,
+            };
+
+            let label = performance.url.clone();
+            metrics::gauge!(telemetry::RELAY_RANK, "relay" => label.clone()).set(rank as f64);
+            metrics::gauge!(telemetry::RELAY_HEALTHY, "relay" => label.clone())
+                .set(if performance.healthy { 1.0 } else { 0.0 });
+            if sampled {
+                metrics::gauge!(telemetry::RELAY_LATENCY, "relay" => label.clone())
+                    .set(averages.latency);
+                metrics::gauge!(telemetry::RELAY_SUCCESS_RATE, "relay" => label)
+                    .set(averages.success_rate);
+            }
+            performances.push(performance);
+        }
+
+        RelayStatus { relays: performances, quorum, stagger_ms: stagger.as_millis() as u64 }
+    }
+}
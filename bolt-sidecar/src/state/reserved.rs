@@ -0,0 +1,132 @@
+use std::collections::{BTreeSet, HashMap};
+
+use alloy::primitives::{Address, B256, U256};
+
+use crate::primitives::Slot;
+
+/// The nonces and the maximum cost reserved by the transactions of a sender.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Reservation {
+    /// The nonces of the transactions.
+    pub nonces: BTreeSet<u64>,
+    /// The sum of the maximum costs of the transactions.
+    pub cost: U256,
+}
+
+impl Reservation {
+    /// Adds the nonce and the maximum cost of a transaction to the reservation.
+    pub fn add(&mut self, nonce: u64, cost: U256) {
+        self.nonces.insert(nonce);
+        self.cost = self.cost.saturating_add(cost);
+    }
+}
+
+/// Tracks the nonces and the balance taken by the transactions of accepted commitment
+/// requests that aren't in a block template yet, such as the ones awaiting an external
+/// signature.
+///
+/// The nonces of a sender are committed to contiguously, from its account nonce on, and the
+/// maximum cost of its committed transactions can't exceed its balance: the next request of
+/// a sender must use the lowest nonce left free, and be paid for by the balance left, after
+/// its committed and accepted transactions. Like the blob reservations, they are reserved
+/// when a request passes validation, and released when it is added to the block template
+/// (which then accounts for them), when it is cancelled, or when its slot expires.
+///
+/// The nonces themselves are reserved rather than their number, so that the nonce of a
+/// cancelled request can be taken again while the later ones of the sender stand.
+#[derive(Debug, Default)]
+pub struct PendingReservations {
+    /// The reservations of accepted requests, by target slot, request digest and sender.
+    reserved: HashMap<Slot, HashMap<B256, HashMap<Address, Reservation>>>,
+}
+
+impl PendingReservations {
+    /// Returns the nonces and the cost reserved for the sender across all slots.
+    pub fn reserved(&self, sender: &Address) -> Reservation {
+        let requests = self.reserved.values().flat_map(HashMap::values);
+        requests.filter_map(|senders| senders.get(sender)).fold(
+            Reservation::default(),
+            |mut reserved, request| {
+                reserved.nonces.extend(&request.nonces);
+                reserved.cost = reserved.cost.saturating_add(request.cost);
+                reserved
+            },
+        )
+    }
+
+    /// Returns the highest target slot of the accepted requests of the sender, if any.
+    pub fn highest_slot(&self, sender: &Address) -> Option<Slot> {
+        let slots = self
+            .reserved
+            .iter()
+            .filter(|(_, requests)| requests.values().any(|senders| senders.contains_key(sender)));
+        slots.map(|(slot, _)| *slot).max()
+    }
+
+    /// Reserves the nonces and the cost of each sender of the request with the given digest.
+    pub fn reserve(&mut self, slot: Slot, digest: B256, senders: HashMap<Address, Reservation>) {
+        if !senders.is_empty() {
+            self.reserved.entry(slot).or_default().insert(digest, senders);
+        }
+    }
+
+    /// Releases the reservations of the request with the given digest, returning the number
+    /// of its nonces.
+    pub fn release(&mut self, slot: Slot, digest: B256) -> usize {
+        let Some(requests) = self.reserved.get_mut(&slot) else {
+            return 0;
+        };
+
+        let released = requests.remove(&digest);
+        if requests.is_empty() {
+            self.reserved.remove(&slot);
+        }
+
+        released.map_or(0, |senders| senders.values().map(|r| r.nonces.len()).sum())
+    }
+
+    /// Releases the reservations for the given slot and all earlier ones.
+    pub fn expire(&mut self, slot: Slot) {
+        self.reserved.retain(|reserved_slot, _| *reserved_slot > slot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reservation(nonces: &[u64], cost: U256) -> Reservation {
+        Reservation { nonces: nonces.iter().copied().collect(), cost }
+    }
+
+    #[test]
+    fn test_pending_reservations() {
+        let (alice, bob) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let (one, two) = (U256::from(1_000), U256::from(2_000));
+        let mut reservations = PendingReservations::default();
+
+        // The reservations of a sender add up across requests and slots
+        let first =
+            HashMap::from([(alice, reservation(&[0, 1], two)), (bob, reservation(&[4], one))]);
+        reservations.reserve(10, B256::repeat_byte(1), first);
+        let second = HashMap::from([(alice, reservation(&[2], one))]);
+        reservations.reserve(11, B256::repeat_byte(2), second);
+        assert_eq!(reservations.reserved(&alice), reservation(&[0, 1, 2], two + one));
+        assert_eq!(reservations.reserved(&bob), reservation(&[4], one));
+        assert_eq!(reservations.highest_slot(&alice), Some(11));
+        assert_eq!(reservations.highest_slot(&bob), Some(10));
+
+        // Releasing a request frees the reservations of all its senders, once, and leaves
+        // the nonces of the later requests reserved
+        assert_eq!(reservations.release(10, B256::repeat_byte(1)), 3);
+        assert_eq!(reservations.release(10, B256::repeat_byte(1)), 0);
+        assert_eq!(reservations.reserved(&alice), reservation(&[2], one));
+        assert_eq!(reservations.reserved(&bob), Reservation::default());
+        assert_eq!(reservations.highest_slot(&bob), None);
+
+        // And the reservations of the passed slots expire
+        reservations.expire(11);
+        assert_eq!(reservations.reserved(&alice), Reservation::default());
+        assert_eq!(reservations.highest_slot(&alice), None);
+    }
+}
@@ -0,0 +1,269 @@
+use std::{fmt, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+use super::clock::{SlotClock, SystemTimeSource, TimeSource};
+
+/// The maximum time the scheduler sleeps before reading the wall clock again,
+/// so that clock jumps (e.g. NTP corrections) are noticed in a timely manner.
+const MAX_SLEEP: Duration = Duration::from_secs(1);
+
+/// A point in time relative to the start of a slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotOffset {
+    /// The given duration before the start of the slot.
+    Before(Duration),
+    /// The given duration after the start of the slot.
+    After(Duration),
+}
+
+impl SlotOffset {
+    /// The start of the slot.
+    pub const START: Self = Self::After(Duration::ZERO);
+
+    /// Returns the offset in nanoseconds, negative if before the start of the slot.
+    fn as_nanos(&self) -> i128 {
+        match self {
+            Self::Before(d) => -(d.as_nanos() as i128),
+            Self::After(d) => d.as_nanos() as i128,
+        }
+    }
+}
+
+/// A task registered on the scheduler, as reported in the sidecar status.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduleEntry {
+    /// The name of the task.
+    pub task: String,
+    /// The offset relative to the start of the slot, in milliseconds.
+    /// Negative values are before the start of the slot.
+    pub offset_ms: i64,
+}
+
+/// A tick of a scheduled task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotTick<K> {
+    /// The task that is due.
+    pub task: K,
+    /// The slot that the task's offset is relative to.
+    pub slot: u64,
+}
+
+/// A scheduler that fires registered tasks at precise offsets relative to slot boundaries.
+///
+/// All tasks share a single timer task. Wakeups are computed from the genesis time
+/// instead of accumulating intervals, so they don't drift. If the wall clock jumps
+/// forward, only the latest missed occurrence of each task fires. If it jumps backward,
+/// occurrences that already fired don't fire again.
+#[derive(Debug)]
+pub struct SlotScheduler<K, T = SystemTimeSource> {
+    clock: SlotClock<T>,
+    tasks: Vec<(K, SlotOffset)>,
+    /// The last slot for which each task fired, by task index.
+    last_fired: Vec<Option<u64>>,
+    /// Whether the first evaluation of the schedule has happened.
+    started: bool,
+}
+
+impl<K, T> SlotScheduler<K, T>
+where
+    K: fmt::Display + Clone + Send + 'static,
+    T: TimeSource,
+{
+    /// Create a new scheduler on top of the given slot clock.
+    pub fn new(clock: SlotClock<T>) -> Self {
+        Self { clock, tasks: Vec::new(), last_fired: Vec::new(), started: false }
+    }
+
+    /// Registers a task to fire at the given offset relative to the start of every slot.
+    pub fn register(&mut self, task: K, offset: SlotOffset) {
+        self.tasks.push((task, offset));
+        self.last_fired.push(None);
+    }
+
+    /// Returns the registered schedule, ordered by offset within the slot.
+    pub fn schedule(&self) -> Vec<ScheduleEntry> {
+        let mut entries = self
+            .tasks
+            .iter()
+            .map(|(task, offset)| ScheduleEntry {
+                task: task.to_string(),
+                offset_ms: (offset.as_nanos() / 1_000_000) as i64,
+            })
+            .collect::<Vec<_>>();
+
+        entries.sort_by_key(|entry| entry.offset_ms);
+        entries
+    }
+
+    /// Spawns the timer task, returning a receiver of the ticks of the registered tasks.
+    /// The timer task stops when the receiver is dropped.
+    pub fn spawn(self) -> mpsc::Receiver<SlotTick<K>> {
+        let (tx, rx) = mpsc::channel(64);
+        tokio::spawn(self.run(tx));
+        rx
+    }
+
+    async fn run(mut self, tx: mpsc::Sender<SlotTick<K>>) {
+        loop {
+            let now = self.clock.now();
+
+            for tick in self.poll(now) {
+                if tx.send(tick).await.is_err() {
+                    debug!("Scheduler receiver dropped, stopping");
+                    return;
+                }
+            }
+
+            let sleep = self.next_wakeup().map_or(MAX_SLEEP, |at| at.saturating_sub(now));
+            tokio::time::sleep(sleep.min(MAX_SLEEP)).await;
+        }
+    }
+
+    /// Returns the time when the given task fires for the given slot.
+    fn fire_time(&self, offset: SlotOffset, slot: u64) -> Duration {
+        let at = self.clock.slot_start(slot).as_nanos() as i128 + offset.as_nanos();
+        Duration::from_nanos(at.max(0) as u64)
+    }
+
+    /// Returns the latest slot for which the given offset was reached at `now`, if any.
+    fn latest_due_slot(&self, offset: SlotOffset, now: Duration) -> Option<u64> {
+        let genesis = self.clock.slot_start(0).as_nanos() as i128;
+        let since_first = now.as_nanos() as i128 - genesis - offset.as_nanos();
+        if since_first < 0 {
+            return None;
+        }
+
+        Some((since_first / self.clock.slot_time().as_nanos() as i128) as u64)
+    }
+
+    /// Returns the ticks that are due at `now`, ordered by their fire time.
+    ///
+    /// On the first call, occurrences that are already in the past are skipped.
+    fn poll(&mut self, now: Duration) -> Vec<SlotTick<K>> {
+        let mut due = Vec::new();
+
+        for (i, (task, offset)) in self.tasks.iter().enumerate() {
+            let Some(slot) = self.latest_due_slot(*offset, now) else {
+                continue;
+            };
+
+            if !self.started {
+                self.last_fired[i] = Some(slot);
+                continue;
+            }
+
+            match self.last_fired[i] {
+                Some(last) if slot <= last => continue,
+                Some(last) if slot > last + 1 => {
+                    warn!(%task, skipped = slot - last - 1, "Clock jumped forward, skipping ticks");
+                }
+                _ => {}
+            }
+
+            self.last_fired[i] = Some(slot);
+            due.push((self.fire_time(*offset, slot), SlotTick { task: task.clone(), slot }));
+        }
+
+        self.started = true;
+
+        due.sort_by_key(|(at, _)| *at);
+        due.into_iter().map(|(_, tick)| tick).collect()
+    }
+
+    /// Returns the earliest time at which a task is due next.
+    fn next_wakeup(&self) -> Option<Duration> {
+        self.tasks
+            .iter()
+            .zip(&self.last_fired)
+            .map(|((_, offset), last)| {
+                let next = last.map_or(0, |slot| slot + 1);
+                self.fire_time(*offset, next)
+            })
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::state::clock::ManualTimeSource;
+
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Task {
+        BeforeStart,
+        Start,
+    }
+
+    impl fmt::Display for Task {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Self::BeforeStart => write!(f, "before_start"),
+                Self::Start => write!(f, "start"),
+            }
+        }
+    }
+
+    fn scheduler(time: ManualTimeSource) -> SlotScheduler<Task, ManualTimeSource> {
+        let mut scheduler = SlotScheduler::new(SlotClock::with_time_source(1_000, 12, time));
+        scheduler.register(Task::Start, SlotOffset::START);
+        scheduler.register(Task::BeforeStart, SlotOffset::Before(Duration::from_secs(4)));
+        scheduler
+    }
+
+    #[test]
+    fn test_schedule() {
+        let scheduler = scheduler(ManualTimeSource::new(Duration::ZERO));
+        assert_eq!(
+            scheduler.schedule(),
+            vec![
+                ScheduleEntry { task: "before_start".to_string(), offset_ms: -4_000 },
+                ScheduleEntry { task: "start".to_string(), offset_ms: 0 },
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ticks_at_exact_offsets() {
+        // Start 1 second into slot 0
+        let time = ManualTimeSource::new(Duration::from_secs(1_001));
+        let mut ticks = scheduler(time.clone()).spawn();
+
+        let expected = [
+            (Task::BeforeStart, 1, 1_008),
+            (Task::Start, 1, 1_012),
+            (Task::BeforeStart, 2, 1_020),
+            (Task::Start, 2, 1_024),
+        ];
+
+        for (task, slot, at) in expected {
+            let tick = ticks.recv().await.unwrap();
+            assert_eq!(tick, SlotTick { task, slot });
+            assert_eq!(time.now(), Duration::from_secs(at));
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_clock_jumps() {
+        let time = ManualTimeSource::new(Duration::from_secs(1_001));
+        let mut ticks = scheduler(time.clone()).spawn();
+
+        assert_eq!(ticks.recv().await.unwrap(), SlotTick { task: Task::BeforeStart, slot: 1 });
+        assert_eq!(ticks.recv().await.unwrap(), SlotTick { task: Task::Start, slot: 1 });
+
+        // Jump forward past slot 2: only the latest missed occurrences fire, in order
+        time.jump_forward(Duration::from_secs(30));
+        assert_eq!(ticks.recv().await.unwrap(), SlotTick { task: Task::BeforeStart, slot: 3 });
+        assert_eq!(ticks.recv().await.unwrap(), SlotTick { task: Task::Start, slot: 3 });
+
+        // Jump backward into slot 2: nothing fires again until slot 4 is approached
+        time.jump_backward(Duration::from_secs(20));
+        assert_eq!(ticks.recv().await.unwrap(), SlotTick { task: Task::BeforeStart, slot: 4 });
+        assert_eq!(time.now(), Duration::from_secs(1_044));
+        assert_eq!(ticks.recv().await.unwrap(), SlotTick { task: Task::Start, slot: 4 });
+        assert_eq!(time.now(), Duration::from_secs(1_048));
+    }
+}
@@ -0,0 +1,180 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::primitives::Slot;
+
+use super::{
+    basefee::BaseFeeStatus, drift::ClockDriftStatus, freshness::HeadAges, latency::LatencyBudgets,
+    lease::LeaseConflict, maintenance::DeclaredWindow, memory::QueueMemoryStatus,
+    relays::RelayStatus,
+};
+
+/// The commitment budget of a slot that has a block template or pending commitments.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlotBudget {
+    /// The target slot.
+    pub slot: Slot,
+    /// The number of committed transactions.
+    pub commitments: usize,
+    /// The maximum number of commitments for the slot.
+    pub max_commitments: usize,
+    /// The committed gas.
+    pub committed_gas: u64,
+    /// The maximum committed gas for the slot.
+    pub max_committed_gas: u64,
+    /// The bytes the committed transactions take in the block.
+    pub committed_bytes: usize,
+    /// The maximum committed bytes for the slot.
+    pub max_committed_bytes: usize,
+    /// The committed blobs, including those of accepted requests that aren't committed yet.
+    pub committed_blobs: usize,
+    /// The maximum committed blobs for the slot.
+    pub max_committed_blobs: usize,
+}
+
+/// An immutable snapshot of the read-mostly state of the driver.
+///
+/// Snapshots are published by the driver after every mutation, and read by the API
+/// handlers without going through the driver loop. The generation increases with every
+/// published snapshot, so that readers can tell how fresh the data is.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    /// The generation of the snapshot, starting at 0 before the first publication.
+    pub generation: u64,
+    /// The latest head slot observed by the driver.
+    pub head_slot: Slot,
+    /// The latest execution block number observed by the driver.
+    pub head_block: u64,
+    /// The upcoming slots of our validators in the current epoch.
+    pub lookahead: Vec<Slot>,
+    /// The last slot of the epoch of the known proposer duties, after which requests
+    /// can't target slots yet.
+    pub lookahead_end_slot: Slot,
+    /// The maintenance windows during which our validators won't propose, excluded from
+    /// the lookahead.
+    pub maintenance: Vec<DeclaredWindow>,
+    /// The commitment budgets of the slots with pending commitments, ordered by slot.
+    pub slots: Vec<SlotBudget>,
+    /// The number of commitments awaiting their signature from the external signer.
+    pub pending_signatures: usize,
+    /// The margin applied to base fee projections and their recent accuracy.
+    pub basefee: BaseFeeStatus,
+    /// The minimum priority fee per gas of the next slot of our validators, if any.
+    pub priority_fee_floor: Option<u128>,
+    /// The base fee projected for the next slot of our validators, before the margin, if any.
+    pub projected_basefee: Option<u128>,
+    /// Our validators whose lease is held by another sidecar instance.
+    pub lease_conflicts: Vec<LeaseConflict>,
+    /// The age of the chain heads, at the time of the snapshot.
+    pub head_ages: HeadAges,
+    /// The estimated drift of the host clock from the chain.
+    pub clock_drift: ClockDriftStatus,
+    /// The rolling latency percentiles of the commitment pipeline stages.
+    pub latency: LatencyBudgets,
+    /// The memory held by the transactions of accepted requests.
+    pub queue_memory: QueueMemoryStatus,
+    /// The measured performance of the constraint submission targets, in submission order.
+    pub relays: RelayStatus,
+}
+
+/// The write side of the state snapshots, owned by the driver.
+#[derive(Debug)]
+pub struct SnapshotPublisher {
+    tx: watch::Sender<Arc<StateSnapshot>>,
+}
+
+impl SnapshotPublisher {
+    /// Create a new publisher, returning the reader handle to share with the API.
+    pub fn new() -> (Self, SnapshotReader) {
+        let (tx, rx) = watch::channel(Arc::new(StateSnapshot::default()));
+        (Self { tx }, SnapshotReader(rx))
+    }
+
+    /// Publishes a new snapshot, replacing the current one. Its generation is set
+    /// to the one after the current snapshot. Returns the new generation.
+    pub fn publish(&self, mut snapshot: StateSnapshot) -> u64 {
+        let mut generation = 0;
+        self.tx.send_modify(|current| {
+            generation = current.generation + 1;
+            snapshot.generation = generation;
+            *current = Arc::new(snapshot);
+        });
+        generation
+    }
+}
+
+/// The read side of the state snapshots. Cheap to clone.
+#[derive(Debug, Clone)]
+pub struct SnapshotReader(watch::Receiver<Arc<StateSnapshot>>);
+
+impl Default for SnapshotReader {
+    /// A reader that always returns the default snapshot, for when there is no driver.
+    fn default() -> Self {
+        Self(watch::channel(Arc::new(StateSnapshot::default())).1)
+    }
+}
+
+impl SnapshotReader {
+    /// Returns the latest published snapshot. This never waits for the driver.
+    pub fn load(&self) -> Arc<StateSnapshot> {
+        self.0.borrow().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generations_increase() {
+        let (publisher, reader) = SnapshotPublisher::new();
+        assert_eq!(reader.load().generation, 0);
+
+        let mut last = 0;
+        for head_slot in 1..=10 {
+            let generation = publisher.publish(StateSnapshot { head_slot, ..Default::default() });
+            assert!(generation > last);
+
+            let snapshot = reader.load();
+            assert_eq!(snapshot.generation, generation);
+            assert_eq!(snapshot.head_slot, head_slot);
+            last = generation;
+        }
+
+        // A snapshot with a stale generation can't move the counter back
+        publisher.publish(StateSnapshot { generation: 1, ..Default::default() });
+        assert_eq!(reader.load().generation, 11);
+    }
+
+    #[test]
+    fn test_concurrent_reads() {
+        let (publisher, reader) = SnapshotPublisher::new();
+
+        let readers = (0..4)
+            .map(|_| {
+                let reader = reader.clone();
+                std::thread::spawn(move || {
+                    let mut last = 0;
+                    for _ in 0..10_000 {
+                        let snapshot = reader.load();
+                        assert!(snapshot.generation >= last);
+                        // The snapshot is consistent with its generation
+                        assert_eq!(snapshot.head_slot, snapshot.generation);
+                        last = snapshot.generation;
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for head_slot in 1..=1_000 {
+            publisher.publish(StateSnapshot { head_slot, ..Default::default() });
+        }
+
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        assert_eq!(reader.load().generation, 1_000);
+    }
+}
@@ -0,0 +1,962 @@
+//! Store-and-forward of the constraint submissions.
+//!
+//! The signed constraints of a slot are persisted in the store as soon as they're added
+//! to its block template, and only removed once they have been delivered to every relay
+//! target. If the sidecar restarts before the commitment deadline of a slot, its constraints
+//! are still submitted at the deadline, with the same payload as before the restart.
+//!
+//! The attempts and the last error of each target are persisted along with the payload, so
+//! that a target which exhausted its attempts stays quarantined for the slot across restarts.
+//! Only the transient failures are retried, with the retry policy of the target, and never
+//! past the start of the slot. A target is quarantined as soon as its circuit is open.
+//!
+//! These are non-critical writes of the [`CommitmentStore`]: while the store is unavailable,
+//! the submissions are kept in memory, and they are only lost if the sidecar restarts then.
+//!
+//! The targets are submitted to concurrently, in the order of their measured latency and
+//! success rate: the fastest healthy target is attempted first, and each of the following
+//! ones after a short stagger. The constraints of a slot are submitted once a quorum of
+//! the targets received them. A target that failed several successive slots is skipped
+//! while it backs off, and attempted again afterwards.
+//!
+//! The delegations of the validators, if any, are attached to the first submission to each
+//! target since the sidecar started, so that the target accepts the constraints signed by
+//! the delegatee keys.
+
+use std::{
+    collections::HashSet,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use alloy::primitives::{keccak256, B256};
+use futures::future::join_all;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{error, info, warn};
+
+use crate::{
+    alerts::{AlertCondition, Alerts},
+    api::spec::BuilderApiError,
+    common::{canonical, rng::Rng},
+    db::{CommitmentStore, NonCriticalWrite, ResilientStore, StoreError},
+    primitives::{SignedConstraints, SignedDelegation},
+    state::{
+        clock::SlotClock,
+        relays::{RelayStatus, RelayTracker, SubmissionOutcome, DEFAULT_RELAY_STAGGER},
+    },
+    telemetry, MevBoostClient,
+};
+
+/// Errors that can occur while persisting or submitting the constraints of a slot.
+#[derive(Debug, thiserror::Error)]
+pub enum SubmissionError {
+    /// The pending submission couldn't be read from the store.
+    #[error("Store error: {0}")]
+    Store(#[from] StoreError),
+    /// The constraints couldn't be encoded, or the persisted payload couldn't be decoded.
+    #[error("Invalid constraints payload: {0}")]
+    Payload(#[from] serde_json::Error),
+    /// A target exhausted its submission attempts for the slot.
+    #[error("Constraints of slot {slot} not delivered to {url}: {error}")]
+    Quarantined {
+        /// The slot of the constraints.
+        slot: u64,
+        /// The URL of the target.
+        url: String,
+        /// The error of the last attempt.
+        error: String,
+    },
+    /// A target was skipped for the slot, as it is backing off after failing earlier slots.
+    #[error("Constraints of slot {slot} not delivered to {url}: backing off until slot {until}")]
+    BackingOff {
+        /// The slot of the constraints.
+        slot: u64,
+        /// The URL of the target.
+        url: String,
+        /// The last slot the target is skipped for.
+        until: u64,
+    },
+    /// The circuit of a target is open after successive failures, so that the proposer
+    /// should fall back to the local payload.
+    #[error("Constraints of slot {slot} not delivered to {url}: circuit open")]
+    CircuitOpen {
+        /// The slot of the constraints.
+        slot: u64,
+        /// The URL of the target.
+        url: String,
+    },
+}
+
+/// The delivery state of a pending submission to one relay target.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubmissionTarget {
+    /// The URL of the target.
+    pub url: String,
+    /// The number of failed attempts.
+    pub attempts: u32,
+    /// The error of the last failed attempt.
+    pub last_error: Option<String>,
+    /// Whether the payload was delivered to the target.
+    pub delivered: bool,
+    /// Whether the submissions to the target stopped for the slot.
+    #[serde(default)]
+    pub quarantined: bool,
+}
+
+impl SubmissionTarget {
+    fn new(url: String) -> Self {
+        Self { url, attempts: 0, last_error: None, delivered: false, quarantined: false }
+    }
+
+    /// Returns true if the submissions to the target stopped without the payload being
+    /// delivered: its attempts were exhausted, it failed permanently, or the slot started.
+    pub fn is_quarantined(&self) -> bool {
+        !self.delivered && self.quarantined
+    }
+}
+
+/// The signed constraints of a slot that are pending submission to the relays.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingSubmission {
+    /// The slot of the constraints.
+    pub slot: u64,
+    /// The hash of the canonical payload, tagged with the canonicalization version.
+    pub payload_hash: B256,
+    /// The canonical JSON encoding of the signed constraints, as submitted.
+    pub payload: Vec<u8>,
+    /// The delivery state of each target.
+    pub targets: Vec<SubmissionTarget>,
+}
+
+impl PendingSubmission {
+    /// Creates an empty submission for the slot.
+    pub fn new(slot: u64) -> Result<Self, serde_json::Error> {
+        let mut submission =
+            Self { slot, payload_hash: B256::ZERO, payload: Vec::new(), targets: Vec::new() };
+        submission.set_constraints(&[])?;
+        Ok(submission)
+    }
+
+    /// Appends the signed constraints to the payload. The changed payload is delivered again
+    /// to every target, including the ones that received or were quarantined for the
+    /// previous one.
+    pub fn push(&mut self, constraints: &SignedConstraints) -> Result<(), serde_json::Error> {
+        let mut list: Vec<Value> = serde_json::from_slice(&self.payload)?;
+        list.push(serde_json::to_value(constraints)?);
+        self.targets.clear();
+        self.set_constraints(&list)
+    }
+
+    /// Removes the signed constraints from the payload. Returns false if they were not in it.
+    pub fn remove(&mut self, constraints: &SignedConstraints) -> Result<bool, serde_json::Error> {
+        let mut list: Vec<Value> = serde_json::from_slice(&self.payload)?;
+        let removed = serde_json::to_value(constraints)?;
+        let len = list.len();
+        list.retain(|constraints| *constraints != removed);
+        self.set_constraints(&list)?;
+        Ok(list.len() < len)
+    }
+
+    /// Returns true if the payload was delivered to every target.
+    pub fn is_delivered(&self) -> bool {
+        self.targets.iter().all(|target| target.delivered)
+    }
+
+    fn set_constraints(&mut self, list: &[Value]) -> Result<(), serde_json::Error> {
+        self.payload = canonical::to_canonical_vec(list)?;
+        self.payload_hash = keccak256(canonical::hash_input(list)?);
+        Ok(())
+    }
+
+    /// Returns the index of the state of the target, adding it if it is new.
+    fn target_index(&mut self, url: &str) -> usize {
+        match self.targets.iter().position(|target| target.url == url) {
+            Some(index) => index,
+            None => {
+                self.targets.push(SubmissionTarget::new(url.to_string()));
+                self.targets.len() - 1
+            }
+        }
+    }
+}
+
+/// Persists the signed constraints of each slot until they are submitted to the relay
+/// targets, so that they survive restarts of the sidecar.
+#[derive(Debug, Clone)]
+pub struct ConstraintSubmissions {
+    store: ResilientStore,
+    targets: Vec<MevBoostClient>,
+    alerts: Alerts,
+    rng: Rng,
+    quorum: usize,
+    stagger: Duration,
+    relays: RelayTracker,
+    delegations: Arc<Vec<SignedDelegation>>,
+    /// The URLs of the targets the delegations were delivered to.
+    delegated: Arc<Mutex<HashSet<String>>>,
+    /// The clock of the slot deadlines of the retries, if any.
+    clock: Option<SlotClock>,
+}
+
+impl ConstraintSubmissions {
+    /// Creates the submissions, persisted to `store` and delivered to each of the targets.
+    pub fn new(store: ResilientStore, targets: Vec<MevBoostClient>) -> Self {
+        Self {
+            store,
+            quorum: targets.len(),
+            targets,
+            alerts: Alerts::default(),
+            rng: Rng::from_entropy(),
+            stagger: DEFAULT_RELAY_STAGGER,
+            relays: RelayTracker::default(),
+            delegations: Arc::default(),
+            delegated: Arc::default(),
+            clock: None,
+        }
+    }
+
+    /// Raise alerts on store read failures, and when a target exhausts its attempts.
+    pub fn with_alerts(self, alerts: Alerts) -> Self {
+        Self { alerts, ..self }
+    }
+
+    /// Draw the retry jitter from the given source of randomness.
+    pub fn with_rng(self, rng: Rng) -> Self {
+        Self { rng, ..self }
+    }
+
+    /// Sets the number of targets that must receive the constraints of a slot for it to be
+    /// submitted, which is all of them by default. The targets backing off count towards
+    /// it as not having received the constraints.
+    pub fn with_quorum(self, quorum: usize) -> Self {
+        Self { quorum, ..self }
+    }
+
+    /// Sets the delay between the starts of the submissions to two successive targets.
+    pub fn with_stagger(self, stagger: Duration) -> Self {
+        Self { stagger, ..self }
+    }
+
+    /// Order the targets by the latency and success rate measured by the given tracker.
+    pub fn with_relay_tracker(self, relays: RelayTracker) -> Self {
+        Self { relays, ..self }
+    }
+
+    /// Attach the signed delegations and revocations to the first submission to each target.
+    pub fn with_delegations(self, delegations: Vec<SignedDelegation>) -> Self {
+        Self { delegations: Arc::new(delegations), ..self }
+    }
+
+    /// Stop retrying the submissions of a slot at its start on the given clock.
+    pub fn with_clock(self, clock: SlotClock) -> Self {
+        Self { clock: Some(clock), ..self }
+    }
+
+    /// Returns the targets in submission order, with their measured performance.
+    pub fn relay_status(&self) -> RelayStatus {
+        self.relays.status(&self.target_urls(), self.quorum, self.stagger)
+    }
+
+    fn target_urls(&self) -> Vec<String> {
+        self.targets.iter().map(|client| client.url().to_string()).collect()
+    }
+
+    /// Persists the signed constraints, to be submitted with the others of their slot.
+    pub fn enqueue(&self, constraints: &SignedConstraints) {
+        let slot = constraints.message.slot;
+        let result = self.store.pending_submission(slot).map_err(SubmissionError::from);
+        let result = result.and_then(|submission| {
+            let mut submission = match submission {
+                Some(submission) => submission,
+                None => PendingSubmission::new(slot)?,
+            };
+            submission.push(constraints)?;
+            self.store.write(NonCriticalWrite::PutSubmission(submission));
+            Ok(())
+        });
+
+        match result {
+            Ok(()) => self.alerts.clear(AlertCondition::StoreWriteFailure),
+            Err(err) => {
+                error!(?err, slot, "Failed to persist the constraints pending submission");
+                let message = format!("Failed to persist the constraints of slot {slot}: {err}");
+                self.alerts.raise(AlertCondition::StoreWriteFailure, message);
+            }
+        }
+    }
+
+    /// Removes the signed constraints from the ones pending submission with their slot.
+    pub fn remove(&self, constraints: &SignedConstraints) {
+        let slot = constraints.message.slot;
+        let result = self.store.pending_submission(slot).map_err(SubmissionError::from);
+        let result = result.and_then(|submission| {
+            let Some(mut submission) = submission else { return Ok(()) };
+            if submission.remove(constraints)? {
+                self.store.write(NonCriticalWrite::PutSubmission(submission));
+            }
+            Ok(())
+        });
+
+        if let Err(err) = result {
+            error!(?err, slot, "Failed to remove the constraints pending submission");
+        }
+    }
+
+    /// Returns the slots after `current_slot` with constraints pending submission, which
+    /// were persisted before a restart. The submissions of the earlier slots are dropped,
+    /// as they can't be included anymore.
+    pub fn resume(&self, current_slot: u64) -> Result<Vec<u64>, SubmissionError> {
+        let mut slots = Vec::new();
+        for submission in self.store.pending_submissions()? {
+            if submission.slot > current_slot {
+                slots.push(submission.slot);
+            } else if !submission.is_delivered() {
+                warn!(slot = submission.slot, "Dropping the constraints of a past slot");
+            }
+        }
+
+        let before = current_slot.saturating_add(1);
+        self.store.write(NonCriticalWrite::PruneSubmissions { before });
+        if !slots.is_empty() {
+            info!(?slots, "Resuming the submission of the constraints persisted before restart");
+        }
+        Ok(slots)
+    }
+
+    /// Drops the submissions of the slots before `slot`, delivered or not.
+    pub fn prune(&self, slot: u64) {
+        self.store.write(NonCriticalWrite::PruneSubmissions { before: slot });
+    }
+
+    /// Submits the persisted constraints of the slot to the targets that haven't received
+    /// them yet, retrying each target until it is quarantined. The attempts are persisted
+    /// as they're made.
+    ///
+    /// Returns false if no constraints are pending submission for the slot, and an error if
+    /// less than the quorum of the targets received them. The submission is removed from
+    /// the store once the quorum is reached.
+    pub async fn submit(&self, slot: u64) -> Result<bool, SubmissionError> {
+        let deadline = self.clock.as_ref().map(|clock| {
+            let remaining = clock.slot_start(slot).saturating_sub(clock.now());
+            Instant::now() + remaining
+        });
+        self.submit_before(slot, deadline).await
+    }
+
+    /// Submits the constraints of the slot, without retrying a target after the deadline.
+    async fn submit_before(
+        &self,
+        slot: u64,
+        deadline: Option<Instant>,
+    ) -> Result<bool, SubmissionError> {
+        let Some(mut submission) = self.store.pending_submission(slot)? else {
+            return Ok(false);
+        };
+
+        let urls = self.target_urls();
+        let indexes = urls.iter().map(|url| submission.target_index(url)).collect::<Vec<_>>();
+        let submission = Mutex::new(submission);
+
+        // The targets backing off are skipped, so that the others don't wait on them
+        let (order, skipped): (Vec<_>, Vec<_>) = self
+            .relays
+            .ordering(&urls)
+            .into_iter()
+            .partition(|target| self.relays.backoff(&urls[*target], slot).is_none());
+        for target in &skipped {
+            warn!(slot, url = %urls[*target], "Skipping the relay target backing off");
+        }
+
+        let attempts = order.into_iter().enumerate().map(|(rank, target)| {
+            let client = &self.targets[target];
+            self.submit_to(&submission, client, indexes[target], rank, deadline)
+        });
+        let quarantined = join_all(attempts).await.into_iter().flatten().collect::<Vec<_>>();
+
+        let submission = submission.into_inner();
+        let delivered = indexes.iter().filter(|index| submission.targets[**index].delivered);
+        if delivered.count() < self.quorum.min(indexes.len()) {
+            // The targets that didn't receive the constraints were quarantined, or skipped.
+            // An open circuit is reported first, so that the proposer falls back right away.
+            let open = quarantined.iter().find(|target| {
+                self.targets
+                    .iter()
+                    .any(|client| client.url().as_str() == target.url && client.is_circuit_open())
+            });
+            let err = match (open, quarantined.first(), skipped.first()) {
+                (Some(target), _, _) => {
+                    SubmissionError::CircuitOpen { slot, url: target.url.clone() }
+                }
+                (None, Some(target), _) => {
+                    let error = target.last_error.clone().unwrap_or_default();
+                    SubmissionError::Quarantined { slot, url: target.url.clone(), error }
+                }
+                (None, None, Some(&target)) => {
+                    let until = self.relays.backoff(&urls[target], slot).unwrap_or(slot);
+                    SubmissionError::BackingOff { slot, url: urls[target].clone(), until }
+                }
+                (None, None, None) => unreachable!("A target didn't receive the constraints"),
+            };
+            let message = format!("Failed to submit constraints: {err}");
+            self.alerts.raise(AlertCondition::RelaySubmissionFailure, message);
+            return Err(err);
+        }
+
+        for target in quarantined {
+            warn!(slot, url = %target.url, "Reached the quorum without a quarantined target");
+        }
+
+        self.alerts.clear(AlertCondition::RelaySubmissionFailure);
+        self.store.write(NonCriticalWrite::RemoveSubmission(slot));
+        info!(slot, hash = %submission.payload_hash, "Submitted the constraints of the slot");
+        Ok(true)
+    }
+
+    /// Submits the payload to the target at `index` of the submission until it is delivered
+    /// or quarantined, starting after the stagger of its `rank` in the submission order. A
+    /// retry that would start after the deadline quarantines the target instead. Returns the
+    /// state of the target if it was quarantined.
+    async fn submit_to(
+        &self,
+        submission: &Mutex<PendingSubmission>,
+        client: &MevBoostClient,
+        index: usize,
+        rank: usize,
+        deadline: Option<Instant>,
+    ) -> Option<SubmissionTarget> {
+        let (slot, payload) = {
+            let submission = submission.lock();
+            let target = &submission.targets[index];
+            if target.delivered || target.is_quarantined() {
+                return target.is_quarantined().then(|| target.clone());
+            }
+            (submission.slot, submission.payload.clone())
+        };
+        tokio::time::sleep(self.stagger * rank as u32).await;
+
+        loop {
+            let start = Instant::now();
+            let result = match self.deliver_delegations(client).await {
+                Ok(()) => client.submit_constraints_payload(payload.clone()).await,
+                Err(err) => Err(err),
+            };
+            let elapsed = start.elapsed();
+            let outcome = match result {
+                Ok(()) => SubmissionOutcome { status: Some(200), error: None },
+                Err(ref err) => {
+                    SubmissionOutcome { status: err.status_code(), error: Some(err.to_string()) }
+                }
+            };
+            // A submission short-circuited by the breaker didn't reach the relay
+            if !matches!(result, Err(BuilderApiError::CircuitOpen(_))) {
+                self.relays.record(client.url().as_str(), elapsed, outcome.clone());
+            }
+
+            let policy = client.retry_policy();
+            let mut delay = Duration::ZERO;
+
+            let (target, persisted) = {
+                let mut submission = submission.lock();
+                let target = &mut submission.targets[index];
+                let latency_ms = elapsed.as_millis() as u64;
+                let (url, status) = (&target.url, outcome.status);
+                match result {
+                    Ok(()) => {
+                        info!(slot, %url, ?status, latency_ms, "Delivered the constraints");
+                        target.delivered = true;
+                    }
+                    Err(err) => {
+                        error!(
+                            ?err,
+                            slot,
+                            %url,
+                            ?status,
+                            latency_ms,
+                            "Failed to submit constraints"
+                        );
+                        target.attempts += 1;
+                        target.last_error = outcome.error;
+                        delay = policy.delay(target.attempts, &self.rng);
+                        let retry_at = Instant::now() + delay;
+                        let late = deadline.is_some_and(|deadline| retry_at >= deadline);
+                        if late {
+                            let url = &target.url;
+                            warn!(slot, %url, "Stopped retrying the constraints at the slot start");
+                        }
+                        target.quarantined =
+                            !err.is_transient() || target.attempts >= policy.max_attempts || late;
+                    }
+                }
+                (target.clone(), submission.clone())
+            };
+
+            self.store.write(NonCriticalWrite::PutSubmission(persisted));
+            if target.delivered || target.is_quarantined() {
+                self.relays.record_slot(&target.url, slot, target.delivered);
+                return target.is_quarantined().then_some(target);
+            }
+
+            metrics::counter!(telemetry::RELAY_RETRIES, "relay" => target.url).increment(1);
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Submits the delegations to the target, unless they were already delivered to it.
+    async fn deliver_delegations(&self, client: &MevBoostClient) -> Result<(), BuilderApiError> {
+        let url = client.url().to_string();
+        if self.delegations.is_empty() || self.delegated.lock().contains(&url) {
+            return Ok(());
+        }
+
+        client.submit_delegations(&self.delegations).await?;
+        info!(%url, count = self.delegations.len(), "Submitted the delegations");
+        self.delegated.lock().insert(url);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::PathBuf,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use axum::{body::Bytes, http::StatusCode, routing::post, Json, Router};
+    use parking_lot::Mutex;
+    use reqwest::Url;
+    use serde_json::json;
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::{
+        api::spec::{CONSTRAINTS_PATH, DELEGATE_PATH, REVOKE_PATH},
+        client::retry::{RetryPolicy, DEFAULT_MAX_ATTEMPTS},
+        db::Store,
+        primitives::{BlsPublicKey, ConstraintsMessage, DelegationAction, DelegationMessage},
+        test_util::test_bls_secret_key,
+    };
+
+    /// A relay that fails the first `failures` submissions, recording the bodies it receives.
+    async fn mock_relay(failures: usize) -> (Url, Arc<Mutex<Vec<Bytes>>>) {
+        failing_relay(failures, StatusCode::INTERNAL_SERVER_ERROR).await
+    }
+
+    /// A relay that fails the first `failures` submissions with the status.
+    async fn failing_relay(failures: usize, status: StatusCode) -> (Url, Arc<Mutex<Vec<Bytes>>>) {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let remaining = Arc::new(AtomicUsize::new(failures));
+
+        let bodies = received.clone();
+        let router = Router::new().route(
+            CONSTRAINTS_PATH,
+            post(move |body: Bytes| {
+                bodies.lock().push(body);
+                let failed = remaining
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                    .is_ok();
+                async move {
+                    if failed {
+                        let code = status.as_u16();
+                        let error = json!({ "code": code, "message": "relay unavailable" });
+                        (status, Json(error))
+                    } else {
+                        (StatusCode::OK, Json(json!({})))
+                    }
+                }
+            }),
+        );
+
+        (serve(router).await, received)
+    }
+
+    /// A relay that responds after the scripted delay, and fails every submission while the
+    /// script says so.
+    async fn scripted_relay(delay: Duration) -> (Url, Arc<Mutex<(Duration, bool)>>) {
+        let script = Arc::new(Mutex::new((delay, false)));
+
+        let scripted = script.clone();
+        let router = Router::new().route(
+            CONSTRAINTS_PATH,
+            post(move || {
+                let (delay, fails) = *scripted.lock();
+                async move {
+                    tokio::time::sleep(delay).await;
+                    if fails {
+                        let error = json!({ "code": 500, "message": "relay unavailable" });
+                        (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+                    } else {
+                        (StatusCode::OK, Json(json!({})))
+                    }
+                }
+            }),
+        );
+
+        (serve(router).await, script)
+    }
+
+    async fn serve(router: Router) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap()).parse().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        url
+    }
+
+    fn constraints(validator_index: u64, slot: u64) -> SignedConstraints {
+        let message = ConstraintsMessage { validator_index, slot, ..Default::default() };
+        SignedConstraints { message, ..Default::default() }
+    }
+
+    fn temp_db() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bolt-submissions-{}", rand::random::<u64>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("sidecar.db")
+    }
+
+    /// Opens the store at the path. Without the persistence feature, the store only lives in
+    /// memory, and the tests of restarts are left out.
+    fn open_store(path: &PathBuf) -> ResilientStore {
+        #[cfg(feature = "persistence")]
+        let store = Store::open(path).unwrap();
+        #[cfg(not(feature = "persistence"))]
+        let store = {
+            let _ = path;
+            Store::in_memory().unwrap()
+        };
+        ResilientStore::new(store)
+    }
+
+    /// A client retrying almost right away, whose circuit never opens.
+    fn client(url: &Url) -> MevBoostClient {
+        let retry = RetryPolicy { initial_delay: Duration::from_millis(1), ..Default::default() };
+        MevBoostClient::new(url.clone())
+            .with_retry_policy(retry)
+            .with_circuit_breaker(u32::MAX, Duration::ZERO)
+    }
+
+    fn submissions(path: &PathBuf, relay: &Url) -> ConstraintSubmissions {
+        let store = open_store(path);
+        ConstraintSubmissions::new(store, vec![client(relay)])
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_resume_submission_after_restart() {
+        let path = temp_db();
+        let (relay, received) = mock_relay(0).await;
+
+        // The driver signs constraints for two slots, and is killed before the deadline
+        let before = submissions(&path, &relay);
+        before.enqueue(&constraints(1, 20));
+        before.enqueue(&constraints(2, 20));
+        before.enqueue(&constraints(1, 5));
+        let pending = before.store.pending_submission(20).unwrap().unwrap();
+        drop(before);
+
+        // After the restart, the past slot is skipped and the future one is resumed
+        let after = submissions(&path, &relay);
+        assert_eq!(after.resume(10).unwrap(), vec![20]);
+        assert!(!after.submit(5).await.unwrap());
+        assert!(after.submit(20).await.unwrap());
+
+        // The relay receives the payload persisted before the restart, byte for byte
+        let received = received.lock();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].as_ref(), pending.payload.as_slice());
+        let list: Vec<Value> = serde_json::from_slice(&received[0]).unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[1]["message"]["validator_index"], 2);
+        assert_eq!(after.store.pending_submissions().unwrap(), vec![]);
+    }
+
+    #[tokio::test]
+    async fn test_incremental_submission() {
+        let path = temp_db();
+        let (relay, received) = mock_relay(0).await;
+
+        let updates = submissions(&path, &relay);
+        updates.enqueue(&constraints(1, 20));
+        assert!(updates.submit(20).await.unwrap());
+
+        // The constraints signed after the submission are submitted on their own
+        updates.enqueue(&constraints(2, 20));
+        assert!(updates.submit(20).await.unwrap());
+        let received = received.lock();
+        assert_eq!(received.len(), 2);
+        let list: Vec<Value> = serde_json::from_slice(&received[1]).unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0]["message"]["validator_index"], 2);
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_quarantine_survives_restart() {
+        let path = temp_db();
+        let (relay, received) = mock_relay(usize::MAX).await;
+
+        let before = submissions(&path, &relay);
+        before.enqueue(&constraints(1, 20));
+        let err = before.submit(20).await.unwrap_err();
+        assert!(matches!(err, SubmissionError::Quarantined { slot: 20, .. }), "{err}");
+        assert_eq!(received.lock().len(), DEFAULT_MAX_ATTEMPTS as usize);
+        drop(before);
+
+        // The quarantined target isn't retried after a restart
+        let after = submissions(&path, &relay);
+        assert_eq!(after.resume(10).unwrap(), vec![20]);
+        let submission = after.store.pending_submission(20).unwrap().unwrap();
+        let target = &submission.targets[0];
+        assert_eq!(target.attempts, DEFAULT_MAX_ATTEMPTS);
+        assert!(target.last_error.as_ref().is_some_and(|err| err.contains("relay unavailable")));
+
+        assert!(after.submit(20).await.is_err());
+        assert_eq!(received.lock().len(), DEFAULT_MAX_ATTEMPTS as usize);
+
+        // Until its slot has passed
+        after.prune(21);
+        assert!(!after.submit(20).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_retry_transient_failures() {
+        let path = temp_db();
+        let (relay, received) = failing_relay(2, StatusCode::BAD_GATEWAY).await;
+
+        // The relay responds with 502 twice, then receives the constraints
+        let retried = submissions(&path, &relay);
+        retried.enqueue(&constraints(1, 20));
+        assert!(retried.submit(20).await.unwrap());
+        assert_eq!(received.lock().len(), 3);
+
+        // A permanent failure isn't retried
+        let (relay, received) = failing_relay(usize::MAX, StatusCode::BAD_REQUEST).await;
+        let rejected = submissions(&temp_db(), &relay);
+        rejected.enqueue(&constraints(1, 20));
+        let err = rejected.submit(20).await.unwrap_err();
+        assert!(matches!(err, SubmissionError::Quarantined { slot: 20, .. }), "{err}");
+        assert_eq!(received.lock().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retries_stop_at_deadline() {
+        let (relay, received) = mock_relay(usize::MAX).await;
+        let retry = RetryPolicy { initial_delay: Duration::from_millis(50), ..Default::default() };
+        let store = open_store(&temp_db());
+        let submissions =
+            ConstraintSubmissions::new(store, vec![client(&relay).with_retry_policy(retry)]);
+
+        // The retry would start after the deadline, so that the target is quarantined
+        submissions.enqueue(&constraints(1, 20));
+        let deadline = Instant::now() + Duration::from_millis(30);
+        let err = submissions.submit_before(20, Some(deadline)).await.unwrap_err();
+        assert!(matches!(err, SubmissionError::Quarantined { slot: 20, .. }), "{err}");
+        assert_eq!(received.lock().len(), 1);
+
+        let submission = submissions.store.pending_submission(20).unwrap().unwrap();
+        assert!(submission.targets[0].is_quarantined());
+        assert_eq!(submission.targets[0].attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_open() {
+        let (relay, received) = mock_relay(usize::MAX).await;
+        let client = client(&relay).with_circuit_breaker(2, Duration::from_secs(12));
+        let store = open_store(&temp_db());
+        let submissions = ConstraintSubmissions::new(store, vec![client.clone()]);
+
+        // The circuit opens after two failures, and the following attempts short-circuit
+        submissions.enqueue(&constraints(1, 20));
+        let err = submissions.submit(20).await.unwrap_err();
+        assert!(matches!(err, SubmissionError::CircuitOpen { slot: 20, .. }), "{err}");
+        assert_eq!(received.lock().len(), 2);
+        assert!(client.is_circuit_open());
+
+        submissions.enqueue(&constraints(1, 21));
+        let err = submissions.submit(21).await.unwrap_err();
+        assert!(matches!(err, SubmissionError::CircuitOpen { slot: 21, .. }), "{err}");
+        assert_eq!(received.lock().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_submission_while_store_unavailable() {
+        let path = temp_db();
+        let (relay, received) = mock_relay(1).await;
+
+        let submissions = submissions(&path, &relay);
+        submissions.store.store().set_read_only(true);
+        submissions.enqueue(&constraints(1, 20));
+        assert!(submissions.store.is_degraded());
+
+        // The attempts are kept in memory, and the delivery is replayed once the store recovers
+        assert!(submissions.submit(20).await.unwrap());
+        assert_eq!(received.lock().len(), 2);
+        assert_eq!(submissions.store.store().pending_submissions().unwrap(), vec![]);
+
+        submissions.store.store().set_read_only(false);
+        assert_eq!(submissions.store.recover().unwrap(), 2);
+        assert_eq!(submissions.store.pending_submissions().unwrap(), vec![]);
+    }
+
+    fn relay_submissions(path: &PathBuf, relays: &[&Url]) -> ConstraintSubmissions {
+        let store = open_store(path);
+        let targets = relays.iter().map(|url| client(url)).collect();
+        ConstraintSubmissions::new(store, targets)
+            .with_stagger(Duration::from_millis(5))
+            .with_relay_tracker(RelayTracker::new(0.5))
+    }
+
+    fn submission_order(submissions: &ConstraintSubmissions) -> Vec<String> {
+        submissions.relay_status().relays.into_iter().map(|relay| relay.url).collect()
+    }
+
+    #[tokio::test]
+    async fn test_relay_order_adapts_to_latency() {
+        let path = temp_db();
+        let (slow, _) = scripted_relay(Duration::from_millis(80)).await;
+        let (fast, fast_script) = scripted_relay(Duration::from_millis(5)).await;
+
+        // Without samples, the targets are attempted in the configured order
+        let submissions = relay_submissions(&path, &[&slow, &fast]);
+        assert_eq!(submission_order(&submissions), vec![slow.to_string(), fast.to_string()]);
+
+        submissions.enqueue(&constraints(1, 20));
+        assert!(submissions.submit(20).await.unwrap());
+        assert_eq!(submission_order(&submissions), vec![fast.to_string(), slow.to_string()]);
+
+        // The fast relay degrades, and is attempted after the other one
+        fast_script.lock().0 = Duration::from_millis(300);
+        for slot in [21, 22] {
+            submissions.enqueue(&constraints(1, slot));
+            assert!(submissions.submit(slot).await.unwrap());
+        }
+
+        let status = submissions.relay_status();
+        assert_eq!(submission_order(&submissions), vec![slow.to_string(), fast.to_string()]);
+        assert_eq!((status.quorum, status.stagger_ms), (2, 5));
+        assert!(status.relays.iter().all(|relay| relay.samples == 3 && relay.healthy));
+        assert!(status.relays[1].latency_ms > status.relays[0].latency_ms);
+    }
+
+    #[tokio::test]
+    async fn test_quorum_without_preferred_relay() {
+        let path = temp_db();
+        let (slow, _) = scripted_relay(Duration::from_millis(40)).await;
+        let (fast, fast_script) = scripted_relay(Duration::from_millis(5)).await;
+
+        let submissions = relay_submissions(&path, &[&slow, &fast]).with_quorum(1);
+        submissions.enqueue(&constraints(1, 20));
+        assert!(submissions.submit(20).await.unwrap());
+        assert_eq!(submission_order(&submissions)[0], fast.to_string());
+
+        // The preferred relay fails outright, and the quorum is reached with the other one
+        fast_script.lock().1 = true;
+        submissions.enqueue(&constraints(1, 21));
+        assert!(submissions.submit(21).await.unwrap());
+        assert_eq!(submissions.store.pending_submissions().unwrap(), vec![]);
+
+        let status = submissions.relay_status();
+        assert_eq!(submission_order(&submissions), vec![slow.to_string(), fast.to_string()]);
+        assert!(!status.relays[1].healthy);
+        assert_eq!(status.relays[1].samples, 1 + DEFAULT_MAX_ATTEMPTS as u64);
+
+        // Without the quorum, the constraints aren't submitted
+        let submissions = relay_submissions(&temp_db(), &[&slow, &fast]);
+        submissions.enqueue(&constraints(1, 22));
+        let err = submissions.submit(22).await.unwrap_err();
+        assert!(matches!(err, SubmissionError::Quarantined { slot: 22, .. }), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_failing_relay_backs_off() {
+        let (healthy, _) = mock_relay(0).await;
+        let (failing, received) = mock_relay(usize::MAX).await;
+        let submissions = relay_submissions(&temp_db(), &[&healthy, &failing]).with_quorum(1);
+        let failing_status = || {
+            let status = submissions.relay_status();
+            status.relays.into_iter().find(|relay| relay.url == failing.as_str()).unwrap()
+        };
+
+        // The quorum is reached despite the relay responding with 500, which is recorded
+        submissions.enqueue(&constraints(1, 20));
+        assert!(submissions.submit(20).await.unwrap());
+        let relay = failing_status();
+        let outcome = relay.last_outcome.unwrap();
+        assert_eq!(outcome.status, Some(500));
+        assert!(outcome.error.is_some_and(|err| err.contains("relay unavailable")));
+        assert_eq!((relay.failed_slots, relay.backoff_until_slot), (1, None));
+
+        // After failing successive slots, it's skipped while it backs off
+        for slot in [21, 22] {
+            submissions.enqueue(&constraints(1, slot));
+            assert!(submissions.submit(slot).await.unwrap());
+        }
+        let relay = failing_status();
+        assert_eq!((relay.failed_slots, relay.backoff_until_slot), (3, Some(23)));
+        assert!(!relay.healthy);
+        submissions.enqueue(&constraints(1, 23));
+        assert!(submissions.submit(23).await.unwrap());
+        assert_eq!(received.lock().len(), 3 * DEFAULT_MAX_ATTEMPTS as usize);
+
+        // Then attempted again, backing off twice as long on another failure
+        submissions.enqueue(&constraints(1, 24));
+        assert!(submissions.submit(24).await.unwrap());
+        assert_eq!(received.lock().len(), 4 * DEFAULT_MAX_ATTEMPTS as usize);
+        assert_eq!(failing_status().backoff_until_slot, Some(26));
+
+        // A relay backing off doesn't count towards the quorum
+        let all = relay_submissions(&temp_db(), &[&healthy, &failing])
+            .with_relay_tracker(submissions.relays.clone());
+        all.enqueue(&constraints(1, 25));
+        let err = all.submit(25).await.unwrap_err();
+        assert!(matches!(err, SubmissionError::BackingOff { slot: 25, until: 26, .. }), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_delegations_attached_to_first_submission() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let record = |path: &'static str| {
+            let received = received.clone();
+            post(move |body: Bytes| {
+                received.lock().push((path, body));
+                async { (StatusCode::OK, Json(json!({}))) }
+            })
+        };
+        let router = Router::new()
+            .route(CONSTRAINTS_PATH, record(CONSTRAINTS_PATH))
+            .route(DELEGATE_PATH, record(DELEGATE_PATH))
+            .route(REVOKE_PATH, record(REVOKE_PATH));
+        let relay = serve(router).await;
+
+        let pubkey = test_bls_secret_key().sk_to_pk().to_bytes();
+        let pubkey = BlsPublicKey::try_from(pubkey.as_slice()).unwrap();
+        let delegation = |action| SignedDelegation {
+            message: DelegationMessage {
+                action,
+                validator_pubkey: pubkey.clone(),
+                delegatee_pubkey: pubkey.clone(),
+                slasher: Default::default(),
+            },
+            signature: Default::default(),
+        };
+        let delegations =
+            vec![delegation(DelegationAction::Delegate), delegation(DelegationAction::Revoke)];
+
+        let submissions = submissions(&temp_db(), &relay).with_delegations(delegations.clone());
+        for slot in [20, 21] {
+            submissions.enqueue(&constraints(1, slot));
+            assert!(submissions.submit(slot).await.unwrap());
+        }
+
+        // The delegations and the revocations precede the first constraints only
+        let received = received.lock();
+        let paths = received.iter().map(|(path, _)| *path).collect::<Vec<_>>();
+        assert_eq!(paths, [DELEGATE_PATH, REVOKE_PATH, CONSTRAINTS_PATH, CONSTRAINTS_PATH]);
+        let delegated: Vec<SignedDelegation> = serde_json::from_slice(&received[0].1).unwrap();
+        assert_eq!(delegated, delegations[..1]);
+    }
+}
@@ -0,0 +1,147 @@
+//! Names of the metrics of the sidecar, recorded through the [`metrics`] facade. They are
+//! only exported if the sidecar is built with the `metrics` feature, which installs the
+//! Prometheus recorder.
+
+#[cfg(feature = "metrics")]
+pub use exporter::install_prometheus_exporter;
+
+/// Histogram of the base fee projection error ratios (`actual / projected`).
+pub const BASEFEE_PROJECTION_ERROR: &str = "bolt_sidecar_basefee_projection_error_ratio";
+
+/// Counter of the base fee projections that underestimated the actual base fee.
+pub const BASEFEE_UNDERESTIMATED: &str = "bolt_sidecar_basefee_underestimated_total";
+
+/// Gauge of the margin currently applied to the base fee projections.
+pub const BASEFEE_MARGIN: &str = "bolt_sidecar_basefee_margin";
+
+/// Gauge of the validators whose lease is held by another sidecar instance.
+pub const LEASE_CONFLICTS: &str = "bolt_sidecar_validator_lease_conflicts";
+
+/// Counter of the requests aborted because their deadline passed during processing,
+/// labeled by the processing `stage` at which time ran out.
+pub const DEADLINE_PASSED_DURING_PROCESSING: &str =
+    "bolt_sidecar_deadline_passed_during_processing_total";
+
+/// Gauge of the age of the latest chain head in seconds, labeled by chain `layer`.
+pub const HEAD_AGE: &str = "bolt_sidecar_head_age_seconds";
+
+/// Gauge of the estimated drift of the host clock from the chain in seconds, positive if
+/// it is ahead.
+pub const CLOCK_DRIFT: &str = "bolt_sidecar_clock_drift_seconds";
+
+/// Gauge of the rolling latency percentiles of the commitment pipeline stages in seconds,
+/// labeled by `stage` and `quantile`.
+pub const STAGE_LATENCY: &str = "bolt_sidecar_stage_latency_seconds";
+
+/// Gauge of the recommended minimum margin between the commitment deadline and the start
+/// of the target slot in seconds, derived from the p99 stage latencies.
+pub const RECOMMENDED_DEADLINE_MARGIN: &str = "bolt_sidecar_recommended_deadline_margin_seconds";
+
+/// Gauge of the moving average of the constraint submission latency of each relay in
+/// seconds, labeled by `relay`.
+pub const RELAY_LATENCY: &str = "bolt_sidecar_relay_submission_latency_seconds";
+
+/// Gauge of the moving average of the share of successful constraint submissions of each
+/// relay, labeled by `relay`.
+pub const RELAY_SUCCESS_RATE: &str = "bolt_sidecar_relay_submission_success_rate";
+
+/// Gauge of the position of each relay in the constraint submission order, labeled by
+/// `relay`.
+pub const RELAY_RANK: &str = "bolt_sidecar_relay_submission_rank";
+
+/// Counter of the constraint submission attempts to each relay, labeled by `relay` and by
+/// the HTTP `status` of the response, `none` if the relay didn't respond.
+pub const RELAY_SUBMISSIONS: &str = "bolt_sidecar_relay_submissions_total";
+
+/// Histogram of the durations of the constraint submission attempts to each relay in
+/// seconds, labeled by `relay`.
+pub const RELAY_SUBMISSION_DURATION: &str = "bolt_sidecar_relay_submission_duration_seconds";
+
+/// Gauge of the health of each relay, 1 if it is attempted along with the healthy ones and
+/// 0 otherwise, labeled by `relay`.
+pub const RELAY_HEALTHY: &str = "bolt_sidecar_relay_healthy";
+
+/// Counter of the retries of the constraint submissions to each relay after a transient
+/// failure, labeled by `relay`.
+pub const RELAY_RETRIES: &str = "bolt_sidecar_relay_submission_retries_total";
+
+/// Gauge of the state of the circuit breaker of each relay, 0 if closed, 1 if half-open and
+/// 2 if open, labeled by `relay`.
+pub const RELAY_BREAKER_STATE: &str = "bolt_sidecar_relay_breaker_state";
+
+/// Histogram of the latencies of the signatures of the remote signers in seconds, retries
+/// included, labeled by `signer`.
+pub const REMOTE_SIGNING_LATENCY: &str = "bolt_sidecar_remote_signing_latency_seconds";
+
+/// Gauge of the bytes held by the transactions of accepted commitment requests.
+pub const QUEUED_REQUEST_BYTES: &str = "bolt_sidecar_queued_request_bytes";
+
+/// Histogram of the latencies of the synthetic probe stages in seconds, labeled by `stage`.
+pub const PROBE_STAGE_LATENCY: &str = "bolt_sidecar_probe_stage_latency_seconds";
+
+/// Counter of the failed synthetic probe stages, labeled by `stage`.
+pub const PROBE_STAGE_FAILURES: &str = "bolt_sidecar_probe_stage_failures_total";
+
+/// Gauge of the non-critical writes buffered in memory while the store is unavailable.
+pub const STORE_BUFFERED_WRITES: &str = "bolt_sidecar_store_buffered_writes";
+
+/// Counter of the non-critical writes that were buffered in memory instead of the store.
+pub const STORE_DEGRADED_WRITES: &str = "bolt_sidecar_store_degraded_writes_total";
+
+/// Gauge of the commitment requests dispatched to the driver and awaiting its response.
+pub const PENDING_RESPONSES: &str = "bolt_sidecar_pending_responses";
+
+/// Counter of the commitment requests whose response expired before the driver sent it.
+pub const EXPIRED_RESPONSES: &str = "bolt_sidecar_expired_responses_total";
+
+/// Counter of the commitments signed for clients that disconnected or whose response
+/// expired before the driver sent it.
+pub const ORPHANED_COMMITMENTS: &str = "bolt_sidecar_orphaned_commitments_total";
+
+/// Counter of the commitment requests relayed by a gateway, labeled by the `attestation`
+/// outcome: `honored`, or the reason it was attributed to the gateway.
+pub const RELAYED_REQUESTS: &str = "bolt_sidecar_relayed_requests_total";
+
+#[cfg(feature = "metrics")]
+mod exporter {
+    use std::{net::SocketAddr, time::Duration};
+
+    use axum::Router;
+    use metrics_exporter_prometheus::PrometheusBuilder;
+    use tokio::net::TcpListener;
+    use tracing::{error, info};
+
+    /// Interval at which the histograms of the recorder are drained.
+    const UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+    /// Installs the global metrics recorder and serves the metrics in the Prometheus
+    /// text format on `addr`. Returns the bound address, which tells the actual port
+    /// if `addr` has port 0.
+    ///
+    /// Must be called from within a tokio runtime.
+    pub async fn install_prometheus_exporter(addr: SocketAddr) -> eyre::Result<SocketAddr> {
+        let handle = PrometheusBuilder::new().install_recorder()?;
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+
+        let upkeep = handle.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(UPKEEP_INTERVAL);
+            loop {
+                interval.tick().await;
+                upkeep.run_upkeep();
+            }
+        });
+
+        // Metrics are served on every path, like the exporter's own HTTP listener does
+        let router = Router::new().fallback(move || std::future::ready(handle.render()));
+        tokio::spawn(async move {
+            if let Err(err) = axum::serve(listener, router).await {
+                error!(?err, "Prometheus metrics server error");
+            }
+        });
+
+        info!(%addr, "Serving Prometheus metrics");
+        Ok(addr)
+    }
+}
@@ -1,4 +1,12 @@
+#[cfg(feature = "tls")]
+use std::sync::Arc;
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
 use alloy::{
+    consensus::{BlobTransactionSidecar, SidecarBuilder, SimpleCoder},
     eips::eip2718::Encodable2718,
     network::{EthereumWallet, TransactionBuilder},
     primitives::{Address, U256},
@@ -13,11 +21,28 @@ use alloy_node_bindings::{Anvil, AnvilInstance};
 use blst::min_pk::SecretKey;
 use reth_primitives::PooledTransactionsElement;
 use secp256k1::Message;
+#[cfg(feature = "tls")]
+use tokio_rustls::{
+    rustls::{
+        self,
+        client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        crypto::{ring, verify_tls12_signature, verify_tls13_signature, CryptoProvider},
+        pki_types::{CertificateDer, ServerName, UnixTime},
+        ClientConfig, DigitallySignedStruct, SignatureScheme,
+    },
+    TlsConnector,
+};
 use tracing::warn;
 
+#[cfg(feature = "tls")]
+use crate::api::commitments::tls::CertPair;
 use crate::{
+    api::commitments::server::CommitmentsApiServer,
+    common::rng::Rng,
+    config::ChainConfig,
     crypto::{ecdsa::SignableECDSA, SignableBLS},
     primitives::{CommitmentRequest, FullTransaction, InclusionRequest},
+    state::clock::{Clock, TimeSource, VirtualTimeSource},
     Config,
 };
 
@@ -111,6 +136,29 @@ pub(crate) fn default_test_transaction(sender: Address, nonce: Option<u64>) -> T
         .with_max_fee_per_gas(20_000_000_000)
 }
 
+/// Create a sidecar of `blobs` blobs filled with the given byte, with their KZG commitments
+/// and proofs, to use for tests
+pub(crate) fn test_blob_sidecar(blobs: usize, fill: u8) -> BlobTransactionSidecar {
+    // A blob holds a bit less than 127 KB of data with the simple coder
+    let data = vec![fill; blobs * 120_000];
+    let sidecar = SidecarBuilder::<SimpleCoder>::from_slice(&data).build().expect("valid blobs");
+    assert_eq!(sidecar.blobs.len(), blobs);
+    sidecar
+}
+
+/// Create a default blob transaction template carrying the given sidecar to use for tests
+pub(crate) fn default_blob_transaction(
+    sender: Address,
+    nonce: Option<u64>,
+    sidecar: BlobTransactionSidecar,
+) -> TransactionRequest {
+    let mut tx = default_test_transaction(sender, nonce);
+    tx.max_fee_per_blob_gas = Some(1_000_000_000);
+    tx.blob_versioned_hashes = Some(sidecar.versioned_hashes().collect());
+    tx.sidecar = Some(sidecar);
+    tx
+}
+
 /// Create a default BLS secret key
 pub(crate) fn test_bls_secret_key() -> SecretKey {
     SecretKey::key_gen(&[0u8; 32], &[]).unwrap()
@@ -148,6 +196,37 @@ pub(crate) async fn create_signed_commitment_request(
     txs: &[TransactionRequest],
     sk: &K256SecretKey,
     slot: u64,
+) -> eyre::Result<CommitmentRequest> {
+    create_signed_request(txs, sk, slot, false, None).await
+}
+
+/// Create a valid signed atomic commitment request for testing purposes, whose
+/// transactions are included together in the given order.
+pub(crate) async fn create_signed_atomic_request(
+    txs: &[TransactionRequest],
+    sk: &K256SecretKey,
+    slot: u64,
+) -> eyre::Result<CommitmentRequest> {
+    create_signed_request(txs, sk, slot, true, None).await
+}
+
+/// Create a valid signed commitment request for testing purposes, for the range of slots
+/// from `slot` to `max_slot`.
+pub(crate) async fn create_signed_range_request(
+    txs: &[TransactionRequest],
+    sk: &K256SecretKey,
+    slot: u64,
+    max_slot: u64,
+) -> eyre::Result<CommitmentRequest> {
+    create_signed_request(txs, sk, slot, false, Some(max_slot)).await
+}
+
+async fn create_signed_request(
+    txs: &[TransactionRequest],
+    sk: &K256SecretKey,
+    slot: u64,
+    atomic: bool,
+    max_slot: Option<u64>,
 ) -> eyre::Result<CommitmentRequest> {
     let sk = K256SigningKey::from_slice(sk.to_bytes().as_slice())?;
     let signer = PrivateKeySigner::from_signing_key(sk.clone());
@@ -160,13 +239,184 @@ pub(crate) async fn create_signed_commitment_request(
         let tx_pooled = PooledTransactionsElement::decode_enveloped(&mut raw_encoded.as_slice())?;
         full_txs.push(FullTransaction::from(tx_pooled));
     }
-    let mut request = InclusionRequest { txs: full_txs, slot, signature: None, signer: None };
+    let mut request = InclusionRequest {
+        txs: full_txs,
+        slot,
+        signature: None,
+        signer: None,
+        context: None,
+        atomic,
+        max_slot,
+    };
 
     request.recover_signers()?;
 
-    let signature = signer.sign_hash(&request.digest()).await?;
+    // Signed for the chain of the test servers
+    let digest = request.chain_digest(ChainConfig::default().chain_id());
+    let signature = signer.sign_hash(&digest).await?;
     request.set_signature(signature);
     request.set_signer(signer.address());
 
     Ok(CommitmentRequest::Inclusion(request))
 }
+
+#[cfg(feature = "tls")]
+/// The self-signed test TLS certificates and their keys, by hostname.
+pub(crate) const TEST_CERTS: [(&str, &str, &str); 2] = [
+    (
+        "alpha.test",
+        include_str!("api/commitments/testdata/alpha.crt"),
+        include_str!("api/commitments/testdata/alpha.key"),
+    ),
+    (
+        "beta.test",
+        include_str!("api/commitments/testdata/beta.crt"),
+        include_str!("api/commitments/testdata/beta.key"),
+    ),
+];
+
+#[cfg(feature = "tls")]
+/// Writes the test certificate of the hostname and its key to the directory, as
+/// `<file_name>.crt` and `<file_name>.key`.
+pub(crate) fn write_test_cert(dir: &Path, file_name: &str, hostname: &str) -> CertPair {
+    let (_, cert, key) = TEST_CERTS.iter().find(|(name, ..)| *name == hostname).unwrap();
+    let pair = CertPair {
+        cert: dir.join(format!("{file_name}.crt")),
+        key: dir.join(format!("{file_name}.key")),
+    };
+    std::fs::write(&pair.cert, cert).unwrap();
+    std::fs::write(&pair.key, key).unwrap();
+    pair
+}
+
+#[cfg(feature = "tls")]
+/// Accepts any server certificate, so that the tests can check which one is presented.
+#[derive(Debug)]
+struct AcceptAnyCert(CryptoProvider);
+
+#[cfg(feature = "tls")]
+impl ServerCertVerifier for AcceptAnyCert {
+    fn verify_server_cert(
+        &self,
+        _: &CertificateDer<'_>,
+        _: &[CertificateDer<'_>],
+        _: &ServerName<'_>,
+        _: &[u8],
+        _: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+#[cfg(feature = "tls")]
+/// Returns a TLS connector that accepts any server certificate.
+pub(crate) fn test_tls_connector() -> TlsConnector {
+    let verifier = Arc::new(AcceptAnyCert(ring::default_provider()));
+    let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .dangerous()
+        .with_custom_certificate_verifier(verifier)
+        .with_no_client_auth();
+    TlsConnector::from(Arc::new(config))
+}
+
+/// Writes a config file with the options required to run the sidecar, and the given ones.
+pub(crate) fn write_config_file(options: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("bolt-config-{}.toml", rand::random::<u64>()));
+    rewrite_config_file(&path, options);
+    path
+}
+
+/// Overwrites the config file with the options required to run the sidecar, and the given ones.
+pub(crate) fn rewrite_config_file(path: &Path, options: &str) {
+    let required = r#"
+        beacon_api_url = "http://localhost:5052"
+        mevboost_url = "http://localhost:3030"
+        execution_api_url = "http://localhost:8545"
+        engine_api_url = "http://localhost:8551"
+        mevboost_proxy_port = 18551
+        validator_indexes = "1..4"
+        jwt_hex = "0xd4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3"
+        fee_recipient = "0x0000000000000000000000000000000000000001"
+        private_key = "0x0000000000000000000000000000000000000000000000000000000000000001"
+    "#;
+    std::fs::write(path, format!("{required}{options}")).unwrap();
+}
+
+/// The ephemeral loopback address that the servers of the tests bind to.
+pub(crate) const LOOPBACK_EPHEMERAL: &str = "127.0.0.1:0";
+
+/// The UNIX timestamp at which the clock of a [`DeterministicEnv`] starts.
+pub(crate) const DETERMINISTIC_START: Duration = Duration::from_secs(1_700_000_000);
+
+/// A deterministic test environment: a seeded source of randomness, a clock that only
+/// moves when the test advances it, and servers bound to ephemeral loopback ports only.
+///
+/// Two runs of a test on environments with the same seed generate the same keys and
+/// observe the same timestamps.
+#[derive(Debug, Clone)]
+pub(crate) struct DeterministicEnv {
+    pub rng: Rng,
+    time: VirtualTimeSource,
+}
+
+impl DeterministicEnv {
+    /// Create an environment from the given seed, with its clock at [`DETERMINISTIC_START`].
+    pub(crate) fn new(seed: u64) -> Self {
+        Self { rng: Rng::seeded(seed), time: VirtualTimeSource::new(DETERMINISTIC_START) }
+    }
+
+    /// Returns a handle to the clock of the environment.
+    pub(crate) fn clock(&self) -> Clock {
+        Clock::Virtual(self.time.clone())
+    }
+
+    /// Returns the current UNIX timestamp of the environment, in seconds.
+    pub(crate) fn now(&self) -> u64 {
+        self.time.now().as_secs()
+    }
+
+    /// Moves the clock of the environment forward by the given duration.
+    pub(crate) fn advance(&self, by: Duration) {
+        self.time.set(self.time.now() + by);
+    }
+
+    /// Generate an ECDSA signer from the seeded source of randomness.
+    pub(crate) fn signer(&self) -> PrivateKeySigner {
+        self.rng.ecdsa_signer()
+    }
+
+    /// Generate an ECDSA secret key from the seeded source of randomness.
+    pub(crate) fn secret_key(&self) -> K256SecretKey {
+        self.rng.ecdsa_secret()
+    }
+
+    /// Returns a commitments API server bound to an ephemeral loopback port, reading the
+    /// clock of the environment.
+    pub(crate) fn server(&self) -> CommitmentsApiServer {
+        CommitmentsApiServer::new(LOOPBACK_EPHEMERAL).unwrap().with_clock(self.clock())
+    }
+}